@@ -0,0 +1,298 @@
+//! Agent registry and management
+//!
+//! Handles registration, tracking, and lifecycle of coding agents.
+
+mod registry;
+
+pub use registry::*;
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Supported agent types
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum AgentType {
+    ClaudeCode,
+    OpenCode,
+    AmpCode,
+    Codex,
+    Aider,
+    Custom(String),
+}
+
+impl std::fmt::Display for AgentType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AgentType::ClaudeCode => write!(f, "claude-code"),
+            AgentType::OpenCode => write!(f, "opencode"),
+            AgentType::AmpCode => write!(f, "ampcode"),
+            AgentType::Codex => write!(f, "codex"),
+            AgentType::Aider => write!(f, "aider"),
+            AgentType::Custom(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl AgentType {
+    /// Parse agent type from CLI string
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Self {
+        match s {
+            "claude-code" | "claude" => AgentType::ClaudeCode,
+            "opencode" => AgentType::OpenCode,
+            "ampcode" | "amp" => AgentType::AmpCode,
+            "codex" => AgentType::Codex,
+            "aider" => AgentType::Aider,
+            other => AgentType::Custom(other.to_string()),
+        }
+    }
+
+    /// Get the command to spawn this agent
+    pub fn command(&self) -> &str {
+        match self {
+            AgentType::ClaudeCode => "claude",
+            AgentType::OpenCode => "opencode",
+            AgentType::AmpCode => "amp",
+            AgentType::Codex => "codex",
+            AgentType::Aider => "aider",
+            AgentType::Custom(name) => name.as_str(),
+        }
+    }
+
+    /// Agent type for a spawned command line (`/usr/bin/claude` → Claude Code)
+    pub fn from_command(command: &str) -> Self {
+        let name = std::path::Path::new(command)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or(command);
+        Self::from_str(name)
+    }
+
+    /// How to wake this agent when it stalls, gentlest first
+    ///
+    /// Each further nudge without new output in between moves one step up
+    /// the ladder; the last step repeats.
+    pub fn default_nudge_ladder(&self) -> Vec<NudgeStrategy> {
+        let resume = || NudgeStrategy::SendMessage("Please continue with the task.".to_string());
+        match self {
+            // Claude's TUI submits on Enter (CR); Escape first clears a
+            // half-typed prompt or dismisses a menu
+            AgentType::ClaudeCode => vec![NudgeStrategy::Enter, NudgeStrategy::EscapeEnter, resume()],
+            AgentType::OpenCode | AgentType::AmpCode => {
+                vec![NudgeStrategy::Enter, NudgeStrategy::EscapeEnter, resume()]
+            }
+            AgentType::Codex => vec![NudgeStrategy::Enter, resume()],
+            // An empty line does nothing in aider; it needs a message
+            AgentType::Aider => vec![NudgeStrategy::Text("continue".to_string()), resume()],
+            AgentType::Custom(_) => vec![NudgeStrategy::Newline, NudgeStrategy::Enter],
+        }
+    }
+
+    /// Keystrokes that ask this agent to exit on its own, in order
+    ///
+    /// Ctrl+C first interrupts whatever the agent is in the middle of; the
+    /// rest is its own quit command.
+    pub fn default_quit_sequence(&self) -> Vec<NudgeStrategy> {
+        let command = |text: &str| NudgeStrategy::Text(text.to_string());
+        match self {
+            AgentType::ClaudeCode | AgentType::OpenCode | AgentType::Aider => {
+                vec![NudgeStrategy::Interrupt, command("/exit")]
+            }
+            AgentType::Codex => vec![NudgeStrategy::Interrupt, command("/quit")],
+            // A second Ctrl+C on an idle prompt exits
+            AgentType::AmpCode => vec![NudgeStrategy::Interrupt, NudgeStrategy::Interrupt],
+            AgentType::Custom(_) => vec![NudgeStrategy::Interrupt],
+        }
+    }
+
+    /// Flag that picks this agent's model, `{model}` standing for the name
+    ///
+    /// `None` for agents that can't be given a model on the command line.
+    pub fn default_model_flag(&self) -> Option<&'static str> {
+        match self {
+            AgentType::ClaudeCode | AgentType::OpenCode | AgentType::Codex | AgentType::Aider => {
+                Some("--model {model}")
+            }
+            AgentType::AmpCode | AgentType::Custom(_) => None,
+        }
+    }
+
+    /// Get default arguments for this agent type
+    pub fn default_args(&self) -> Vec<&'static str> {
+        match self {
+            // Most agents work fine with no args, they'll prompt interactively
+            AgentType::ClaudeCode => vec![],
+            AgentType::OpenCode => vec![],
+            AgentType::AmpCode => vec![],
+            AgentType::Codex => vec![],
+            AgentType::Aider => vec![],
+            AgentType::Custom(_) => vec![],
+        }
+    }
+}
+
+/// Arguments for model flag template `flag` (`--model {model}`)
+pub fn model_args(flag: &str, model: &str) -> Vec<String> {
+    flag.split_whitespace()
+        .map(|arg| arg.replace("{model}", model))
+        .collect()
+}
+
+/// How long a new agent may take to draw its first screen before its
+/// prompt is typed anyway
+pub const FIRST_SCREEN_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a starting agent must stay quiet for its first screen to count
+/// as drawn
+pub const FIRST_SCREEN_QUIET: Duration = Duration::from_millis(300);
+
+/// Wait until a freshly spawned agent has printed something and gone quiet,
+/// so a prompt typed next isn't flushed as the agent starts up
+///
+/// `printed` reports how far the session's output stream has got. Gives up
+/// without an error after `FIRST_SCREEN_TIMEOUT`.
+pub fn wait_for_first_screen(mut printed: impl FnMut() -> crate::Result<usize>) -> crate::Result<()> {
+    let poll = Duration::from_millis(50);
+    let started = Instant::now();
+    let (mut seen, mut quiet_since) = (0, started);
+    while started.elapsed() < FIRST_SCREEN_TIMEOUT {
+        let end = printed()?;
+        if end != seen {
+            (seen, quiet_since) = (end, Instant::now());
+        } else if seen > 0 && quiet_since.elapsed() >= FIRST_SCREEN_QUIET {
+            return Ok(());
+        }
+        std::thread::sleep(poll);
+    }
+    Ok(())
+}
+
+/// Names of the variables `context_env` sets
+pub const CONTEXT_ENV_VARS: [&str; 3] = ["REMBRANDT_AGENT_ID", "REMBRANDT_BRANCH", "REMBRANDT_TASK_ID"];
+
+/// Variables that tell an agent, and the hooks and scripts it runs, which
+/// orchestrated session it is: `REMBRANDT_AGENT_ID`, `REMBRANDT_BRANCH`
+/// and, when it has one, `REMBRANDT_TASK_ID`
+pub fn context_env(agent_id: &str, branch: &str, task_id: Option<&str>) -> Vec<(String, String)> {
+    let mut env = vec![
+        ("REMBRANDT_AGENT_ID".to_string(), agent_id.to_string()),
+        ("REMBRANDT_BRANCH".to_string(), branch.to_string()),
+    ];
+    if let Some(task_id) = task_id {
+        env.push(("REMBRANDT_TASK_ID".to_string(), task_id.to_string()));
+    }
+    env
+}
+
+/// One way of waking a stalled agent
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NudgeStrategy {
+    /// A bare `\n`
+    Newline,
+    /// Carriage return, what a terminal sends for the Enter key
+    Enter,
+    /// Escape, then Enter
+    EscapeEnter,
+    /// Ctrl+C
+    Interrupt,
+    /// Type this text and press Enter
+    Text(String),
+    /// A steering message: typed like `Text` into a PTY, delivered with
+    /// the runtime's `send_message` for orchestrated agents
+    SendMessage(String),
+}
+
+impl NudgeStrategy {
+    /// Keystrokes to write to the PTY, in order; the writer pauses between
+    /// chunks so a lone Escape isn't read as Alt+Enter
+    pub fn keystrokes(&self) -> Vec<Vec<u8>> {
+        match self {
+            NudgeStrategy::Newline => vec![b"\n".to_vec()],
+            NudgeStrategy::Enter => vec![b"\r".to_vec()],
+            NudgeStrategy::EscapeEnter => vec![b"\x1b".to_vec(), b"\r".to_vec()],
+            NudgeStrategy::Interrupt => vec![b"\x03".to_vec()],
+            NudgeStrategy::Text(text) | NudgeStrategy::SendMessage(text) => {
+                vec![text.as_bytes().to_vec(), b"\r".to_vec()]
+            }
+        }
+    }
+
+    /// Short label for status lines and logs
+    pub fn describe(&self) -> String {
+        match self {
+            NudgeStrategy::Newline => "newline".to_string(),
+            NudgeStrategy::Enter => "enter".to_string(),
+            NudgeStrategy::EscapeEnter => "escape+enter".to_string(),
+            NudgeStrategy::Interrupt => "ctrl+c".to_string(),
+            NudgeStrategy::Text(text) => format!("text {:?}", text),
+            NudgeStrategy::SendMessage(text) => format!("message {:?}", text),
+        }
+    }
+}
+
+/// Status of an agent session
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AgentStatus {
+    /// Agent is running and working
+    Active,
+    /// Agent is idle, waiting for input
+    Idle,
+    /// Agent completed its task
+    Completed,
+    /// Agent encountered an error
+    Failed(String),
+    /// Agent was stopped by user
+    Stopped,
+}
+
+/// An active agent session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentSession {
+    /// Unique session ID
+    pub id: String,
+    /// Type of agent
+    pub agent_type: AgentType,
+    /// Current status
+    pub status: AgentStatus,
+    /// Path to the worktree this agent is using
+    pub worktree_path: PathBuf,
+    /// Branch name for this agent's work
+    pub branch: String,
+    /// Current task/issue ID (Beads)
+    pub task_id: Option<String>,
+    /// Competition ID if this agent is part of a competition
+    pub competition_id: Option<String>,
+    /// Process ID if running
+    pub pid: Option<u32>,
+    /// Files currently reserved by this agent
+    pub reserved_files: Vec<PathBuf>,
+    /// When the session started
+    pub started_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_model_flags() {
+        let flag = AgentType::ClaudeCode.default_model_flag().unwrap();
+        assert_eq!(model_args(flag, "opus"), vec!["--model", "opus"]);
+        assert_eq!(model_args("--model={model} --fast", "o3"), vec!["--model=o3", "--fast"]);
+        assert_eq!(AgentType::AmpCode.default_model_flag(), None);
+    }
+
+    #[test]
+    fn first_screen_waits_for_output_to_settle() {
+        // Output grows for a few polls, then holds still
+        let mut stream = [0, 0, 40, 120, 300].into_iter();
+        let started = Instant::now();
+        wait_for_first_screen(|| Ok(stream.next().unwrap_or(300))).unwrap();
+        let waited = started.elapsed();
+        assert!(waited >= FIRST_SCREEN_QUIET, "returned before the screen settled");
+        assert!(waited < FIRST_SCREEN_TIMEOUT);
+    }
+}
@@ -1,9 +1,10 @@
 //! Agent registry - tracks available and active agents
 
-use super::{AgentSession, AgentStatus, AgentType};
+use super::{AgentSession, AgentStatus, AgentType, NudgeStrategy};
+use crate::manager::SessionInfo;
+use crate::session::SessionStatus;
 use crate::{RembrandtError, Result};
 use std::collections::HashMap;
-use std::path::Path;
 
 /// Registry of available agent configurations and active sessions
 pub struct AgentRegistry {
@@ -23,6 +24,20 @@ pub struct AgentConfig {
     pub args: Vec<String>,
     /// Whether this agent supports ACP
     pub supports_acp: bool,
+    /// Escalating ways to wake the agent when it stalls
+    pub nudge_ladder: Vec<NudgeStrategy>,
+    /// Flag template that selects a model, e.g. `--model {model}`
+    pub model_flag: Option<String>,
+}
+
+impl AgentConfig {
+    /// Arguments that run this agent with `model`
+    pub fn model_args(&self, model: &str) -> Result<Vec<String>> {
+        let flag = self.model_flag.as_deref().ok_or_else(|| {
+            RembrandtError::Agent(format!("{} has no flag for choosing a model", self.agent_type))
+        })?;
+        Ok(super::model_args(flag, model))
+    }
 }
 
 impl AgentRegistry {
@@ -44,6 +59,8 @@ impl AgentRegistry {
                 command: "claude".to_string(),
                 args: vec![],
                 supports_acp: false, // Not yet, needs adapter
+                nudge_ladder: AgentType::ClaudeCode.default_nudge_ladder(),
+                model_flag: AgentType::ClaudeCode.default_model_flag().map(str::to_string),
             },
         );
 
@@ -55,6 +72,8 @@ impl AgentRegistry {
                 command: "opencode".to_string(),
                 args: vec![],
                 supports_acp: false,
+                nudge_ladder: AgentType::OpenCode.default_nudge_ladder(),
+                model_flag: AgentType::OpenCode.default_model_flag().map(str::to_string),
             },
         );
 
@@ -66,6 +85,8 @@ impl AgentRegistry {
                 command: "codex".to_string(),
                 args: vec![],
                 supports_acp: false,
+                nudge_ladder: AgentType::Codex.default_nudge_ladder(),
+                model_flag: AgentType::Codex.default_model_flag().map(str::to_string),
             },
         );
 
@@ -77,6 +98,8 @@ impl AgentRegistry {
                 command: "aider".to_string(),
                 args: vec![],
                 supports_acp: false,
+                nudge_ladder: AgentType::Aider.default_nudge_ladder(),
+                model_flag: AgentType::Aider.default_model_flag().map(str::to_string),
             },
         );
     }
@@ -121,6 +144,35 @@ impl AgentRegistry {
     pub fn remove_session(&mut self, id: &str) -> Option<AgentSession> {
         self.sessions.remove(id)
     }
+
+    /// Settle running competitors whose PTY sessions have exited: exit code
+    /// 0 completes them, anything else fails them. An agent's newest session
+    /// counts, so one re-run in a retry round is judged by the re-run.
+    pub fn sync_competitors(&mut self, sessions: &[SessionInfo]) {
+        let active: Vec<String> = self
+            .active_sessions()
+            .iter()
+            .filter(|s| s.competition_id.is_some())
+            .map(|s| s.id.clone())
+            .collect();
+
+        for agent_id in active {
+            let Some(info) = sessions
+                .iter()
+                .filter(|s| s.agent_id == agent_id)
+                .max_by_key(|s| s.created_at)
+            else {
+                continue;
+            };
+            let status = match &info.status {
+                SessionStatus::Running => continue,
+                SessionStatus::Exited(0) => AgentStatus::Completed,
+                SessionStatus::Exited(code) => AgentStatus::Failed(format!("exited with code {}", code)),
+                SessionStatus::Failed(reason) => AgentStatus::Failed(reason.clone()),
+            };
+            let _ = self.update_status(&agent_id, status);
+        }
+    }
 }
 
 impl Default for AgentRegistry {
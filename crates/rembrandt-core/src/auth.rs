@@ -0,0 +1,70 @@
+//! Roles of daemon API tokens (`[api_tokens.<name>]`)
+//!
+//! The daemon checks them (`rembrandt::daemon::auth`); they live here so the
+//! shared config can hold them.
+
+use crate::ipc::DaemonCommand;
+use serde::{Deserialize, Serialize};
+
+/// What a token may do; each role includes the ones before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Read,
+    Operator,
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Read => "read",
+            Role::Operator => "operator",
+            Role::Admin => "admin",
+        }
+    }
+
+    /// The least role allowed to run `command`; `None` for commands anyone
+    /// may send, such as `Ping` and `Authenticate`
+    pub fn required_for(command: &DaemonCommand) -> Option<Role> {
+        match command {
+            DaemonCommand::Ping
+            | DaemonCommand::Authenticate { .. }
+            | DaemonCommand::SetFraming { .. } => None,
+            DaemonCommand::List { .. }
+            | DaemonCommand::ListByAgent { .. }
+            | DaemonCommand::GetSession { .. }
+            | DaemonCommand::GetHistory { .. }
+            | DaemonCommand::GetScreen { .. }
+            | DaemonCommand::Attach { read_only: true, .. }
+            | DaemonCommand::Detach { .. } => Some(Role::Read),
+            DaemonCommand::Spawn { .. }
+            | DaemonCommand::Nudge { .. }
+            | DaemonCommand::Write { .. }
+            | DaemonCommand::Resize { .. }
+            | DaemonCommand::Attach { read_only: false, .. }
+            | DaemonCommand::Stop { .. }
+            | DaemonCommand::Kill { .. }
+            | DaemonCommand::Pause { .. }
+            | DaemonCommand::Resume { .. }
+            | DaemonCommand::RecordCompetition { .. } => Some(Role::Operator),
+            DaemonCommand::Merge { .. }
+            | DaemonCommand::GetConfig { .. }
+            | DaemonCommand::SetConfig { .. }
+            | DaemonCommand::Shutdown => Some(Role::Admin),
+        }
+    }
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+/// One `[api_tokens.<name>]` entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub token: String,
+    pub role: Role,
+}
@@ -0,0 +1,380 @@
+//! Beads issue tracker client shared by the CLI, TUI and GUI.
+//!
+//! Beads comes as `br` (beads_rust) or `bd` (the original Go tool). Both take
+//! the same subcommands (`ready`, `list`, `show`, `update`, `close`,
+//! `comments add`, `create`, `sync`) and print JSON with `--json`, though `bd` names the issue
+//! type `issue_type` where `br` says `type`. [`BeadsClient::detect`] uses
+//! whichever is installed, preferring `br`, looking for it the first time
+//! the CLI is needed.
+//!
+//! Both keep issues in a SQLite database (`.beads/beads.db`). With
+//! [`BeadsClient::with_database`], `ready`, `list` and `show` read it
+//! directly instead of starting a process per call, which matters in polling
+//! loops; changes still go through the CLI so its sync and hooks see them.
+
+use crate::{RembrandtError, Result};
+use rusqlite::{Connection, OpenFlags, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::OnceLock;
+
+/// Which Beads CLI is in use
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BeadsBinary {
+    Br,
+    Bd,
+}
+
+impl BeadsBinary {
+    pub fn program(self) -> &'static str {
+        match self {
+            BeadsBinary::Br => "br",
+            BeadsBinary::Bd => "bd",
+        }
+    }
+
+    /// Whether the binary is on `PATH` and runs
+    pub fn is_installed(self) -> bool {
+        Command::new(self.program())
+            .arg("--version")
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+    }
+}
+
+impl std::fmt::Display for BeadsBinary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.program())
+    }
+}
+
+/// A Beads issue
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BeadsTask {
+    pub id: String,
+    pub title: String,
+    pub status: String,
+    #[serde(default)]
+    pub priority: Option<i32>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// `task`, `bug`, `feature`, `epic`, ...
+    #[serde(default, alias = "type")]
+    pub issue_type: Option<String>,
+}
+
+/// Runs the installed Beads CLI. Every call is a no-op (empty result) when
+/// neither `br` nor `bd` is installed.
+#[derive(Debug, Clone)]
+pub struct BeadsClient {
+    /// The CLI in use, once `detect` has looked for it
+    binary: OnceLock<Option<BeadsBinary>>,
+    /// Directory commands run in, so they find that repository's `.beads`
+    dir: Option<PathBuf>,
+    /// Beads database read directly for `ready_tasks`, `tasks` and `show`
+    database: Option<PathBuf>,
+}
+
+impl Default for BeadsClient {
+    /// A client without a CLI
+    fn default() -> Self {
+        Self { binary: OnceLock::from(None), dir: None, database: None }
+    }
+}
+
+impl BeadsClient {
+    /// Use `br` if installed, else `bd`. Nothing runs until the CLI is
+    /// needed, so reads from a database never start a process.
+    pub fn detect() -> Self {
+        Self { binary: OnceLock::new(), ..Self::default() }
+    }
+
+    /// Use `binary` without checking that it is installed
+    pub fn with_binary(binary: BeadsBinary) -> Self {
+        Self { binary: OnceLock::from(Some(binary)), ..Self::default() }
+    }
+
+    /// Run commands in `dir` rather than the current directory
+    pub fn in_dir(mut self, dir: impl AsRef<Path>) -> Self {
+        self.dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Read tasks from the Beads SQLite database at `path` rather than the
+    /// CLI; reads fall back to the CLI if the database can't be queried
+    pub fn with_database(mut self, path: impl AsRef<Path>) -> Self {
+        self.database = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn database(&self) -> Option<&Path> {
+        self.database.as_deref()
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.binary().is_some()
+    }
+
+    pub fn binary(&self) -> Option<BeadsBinary> {
+        *self.binary.get_or_init(|| {
+            [BeadsBinary::Br, BeadsBinary::Bd]
+                .into_iter()
+                .find(|binary| binary.is_installed())
+        })
+    }
+
+    /// Open tasks with no blockers
+    pub fn ready_tasks(&self) -> Result<Vec<BeadsTask>> {
+        if let Some(tasks) = self.read_database(db_ready_tasks) {
+            return Ok(tasks);
+        }
+        match self.run(&["ready", "--json"])? {
+            Some(output) if output.status.success() => {
+                Ok(serde_json::from_slice(&output.stdout).unwrap_or_default())
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// Every task with `status` (`open`, `in_progress`, `blocked`, `closed`),
+    /// blocked or not, by priority
+    pub fn tasks(&self, status: &str) -> Result<Vec<BeadsTask>> {
+        if let Some(tasks) = self.read_database(|conn| db_tasks(conn, status)) {
+            return Ok(tasks);
+        }
+        match self.run(&["list", "--status", status, "--json"])? {
+            Some(output) if output.status.success() => {
+                Ok(serde_json::from_slice(&output.stdout).unwrap_or_default())
+            }
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    /// One task, or `None` if it doesn't exist
+    pub fn show(&self, task_id: &str) -> Result<Option<BeadsTask>> {
+        if let Some(task) = self.read_database(|conn| db_show(conn, task_id)) {
+            return Ok(task);
+        }
+        let Some(output) = self.run(&["show", task_id, "--json"])? else {
+            return Ok(None);
+        };
+        if !output.status.success() {
+            return Ok(None);
+        }
+        // `show` prints a one-element array for a single id
+        let value: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap_or_default();
+        let value = match value {
+            serde_json::Value::Array(mut items) if !items.is_empty() => items.swap_remove(0),
+            other => other,
+        };
+        Ok(serde_json::from_value(value).ok())
+    }
+
+    /// Set a task's status
+    pub fn update_status(&self, task_id: &str, status: &str) -> Result<()> {
+        self.run_checked(&["update", task_id, "--status", status])
+    }
+
+    /// Mark a task taken by an agent
+    pub fn claim(&self, task_id: &str) -> Result<()> {
+        self.update_status(task_id, "in_progress")
+    }
+
+    /// Close a task, recording `reason`
+    pub fn complete(&self, task_id: &str, reason: &str) -> Result<()> {
+        self.run_checked(&["close", task_id, "--reason", reason])
+    }
+
+    /// Add a comment to a task
+    pub fn comment(&self, task_id: &str, text: &str) -> Result<()> {
+        self.run_checked(&["comments", "add", task_id, text])
+    }
+
+    /// Create a task, linked to `parent` as discovered from it; returns its
+    /// id when the CLI reports one
+    pub fn create(&self, title: &str, description: &str, parent: Option<&str>) -> Result<Option<String>> {
+        let deps = parent.map(|parent| format!("discovered-from:{}", parent));
+        let mut args = vec!["create", title, "--type", "task", "--description", description, "--json"];
+        if let Some(deps) = &deps {
+            args.extend(["--deps", deps]);
+        }
+        let Some(output) = self.run(&args)? else {
+            return Ok(None);
+        };
+        self.check("create", &output)?;
+        let created: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap_or_default();
+        Ok(created.get("id").and_then(|id| id.as_str()).map(str::to_string))
+    }
+
+    /// Sync with the remote
+    pub fn sync(&self) -> Result<()> {
+        self.run(&["sync"])?;
+        Ok(())
+    }
+
+    /// Run `query` against the database, `None` without one or on failure
+    fn read_database<T>(&self, query: impl FnOnce(&Connection) -> Result<T>) -> Option<T> {
+        let path = self.database.as_deref()?;
+        let result = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .map_err(RembrandtError::from)
+            .and_then(|conn| {
+                // The CLI may be writing; wait for it rather than failing
+                conn.busy_timeout(std::time::Duration::from_secs(2))?;
+                query(&conn)
+            });
+        match result {
+            Ok(value) => Some(value),
+            Err(e) => {
+                tracing::warn!(database = %path.display(), error = %e, "could not read Beads database, using the CLI");
+                None
+            }
+        }
+    }
+
+    fn run(&self, args: &[&str]) -> Result<Option<Output>> {
+        let Some(binary) = self.binary() else {
+            return Ok(None);
+        };
+        let mut cmd = Command::new(binary.program());
+        cmd.args(args);
+        if let Some(dir) = &self.dir {
+            cmd.current_dir(dir);
+        }
+        Ok(Some(cmd.output()?))
+    }
+
+    fn run_checked(&self, args: &[&str]) -> Result<()> {
+        match self.run(args)? {
+            Some(output) => self.check(args.first().copied().unwrap_or_default(), &output),
+            None => Ok(()),
+        }
+    }
+
+    fn check(&self, subcommand: &str, output: &Output) -> Result<()> {
+        if output.status.success() {
+            return Ok(());
+        }
+        Err(RembrandtError::Runtime(format!(
+            "{} {} failed: {}",
+            self.binary().map(BeadsBinary::program).unwrap_or("beads"),
+            subcommand,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+const TASK_COLUMNS: &str = "i.id, i.title, i.status, i.priority, i.description, i.issue_type";
+
+fn task_from_row(row: &Row<'_>) -> rusqlite::Result<BeadsTask> {
+    Ok(BeadsTask {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        status: row.get(2)?,
+        priority: row.get(3)?,
+        description: row.get::<_, Option<String>>(4)?.filter(|d| !d.is_empty()),
+        issue_type: row.get(5)?,
+    })
+}
+
+/// Open issues with no `blocks` dependency on an issue that is still open,
+/// in the order `ready` prints them
+fn db_ready_tasks(conn: &Connection) -> Result<Vec<BeadsTask>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM issues i
+         WHERE i.status = 'open'
+           AND NOT EXISTS (
+             SELECT 1 FROM dependencies d JOIN issues b ON b.id = d.depends_on_id
+             WHERE d.issue_id = i.id AND d.type = 'blocks' AND b.status != 'closed'
+           )
+         ORDER BY i.priority, i.created_at, i.id",
+        TASK_COLUMNS
+    ))?;
+    let tasks = stmt.query_map([], task_from_row)?.collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(tasks)
+}
+
+fn db_tasks(conn: &Connection, status: &str) -> Result<Vec<BeadsTask>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM issues i WHERE i.status = ?1 ORDER BY i.priority, i.created_at, i.id",
+        TASK_COLUMNS
+    ))?;
+    let tasks = stmt.query_map([status], task_from_row)?.collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(tasks)
+}
+
+fn db_show(conn: &Connection, task_id: &str) -> Result<Option<BeadsTask>> {
+    let task = conn
+        .query_row(&format!("SELECT {} FROM issues i WHERE i.id = ?1", TASK_COLUMNS), [task_id], task_from_row)
+        .optional()?;
+    Ok(task)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_both_clis_task_schemas() {
+        let br = r#"[{"id":"br-1","title":"Fix login","status":"open","priority":1,"type":"bug",
+            "description":"Token expiry","assignee":null}]"#;
+        let bd = r#"[{"id":"bd-a1","title":"Add docs","status":"in_progress","priority":2,
+            "issue_type":"task","created_at":"2026-01-01T00:00:00Z"}]"#;
+        let br: Vec<BeadsTask> = serde_json::from_str(br).unwrap();
+        let bd: Vec<BeadsTask> = serde_json::from_str(bd).unwrap();
+        assert_eq!(br[0].issue_type.as_deref(), Some("bug"));
+        assert_eq!(br[0].description.as_deref(), Some("Token expiry"));
+        assert_eq!(bd[0].issue_type.as_deref(), Some("task"));
+        assert_eq!(bd[0].description, None);
+
+        // Without a CLI every call is a no-op
+        let none = BeadsClient::default();
+        assert!(!none.is_available());
+        assert!(none.ready_tasks().unwrap().is_empty());
+        assert_eq!(none.create("t", "d", Some("bd-a1")).unwrap(), None);
+        none.complete("bd-a1", "done").unwrap();
+        assert_eq!(BeadsBinary::Bd.to_string(), "bd");
+    }
+
+    #[test]
+    fn reads_ready_tasks_from_the_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("beads.db");
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE issues (id TEXT PRIMARY KEY, title TEXT NOT NULL, description TEXT NOT NULL DEFAULT '',
+                status TEXT NOT NULL, priority INTEGER NOT NULL, issue_type TEXT NOT NULL, created_at DATETIME NOT NULL);
+             CREATE TABLE dependencies (issue_id TEXT NOT NULL, depends_on_id TEXT NOT NULL, type TEXT NOT NULL);
+             INSERT INTO issues VALUES
+                ('bd-1', 'Schema', '', 'open', 1, 'task', '2026-01-01'),
+                ('bd-2', 'API', 'Needs the schema', 'open', 0, 'feature', '2026-01-02'),
+                ('bd-3', 'Docs', '', 'open', 2, 'task', '2026-01-03'),
+                ('bd-4', 'Old', '', 'closed', 0, 'bug', '2026-01-04'),
+                ('bd-5', 'Taken', '', 'in_progress', 0, 'task', '2026-01-05');
+             INSERT INTO dependencies VALUES ('bd-2', 'bd-1', 'blocks'), ('bd-3', 'bd-4', 'blocks'),
+                ('bd-3', 'bd-1', 'related');",
+        )
+        .unwrap();
+        drop(conn);
+
+        // Reads don't look for the CLI
+        let client = BeadsClient::detect().with_database(&path);
+        let ready: Vec<String> = client.ready_tasks().unwrap().into_iter().map(|t| t.id).collect();
+        assert_eq!(ready, vec!["bd-1", "bd-3"]);
+        let open: Vec<String> = client.tasks("open").unwrap().into_iter().map(|t| t.id).collect();
+        assert_eq!(open, vec!["bd-2", "bd-1", "bd-3"]);
+        assert_eq!(client.tasks("in_progress").unwrap()[0].id, "bd-5");
+        let api = client.show("bd-2").unwrap().unwrap();
+        assert_eq!(api.description.as_deref(), Some("Needs the schema"));
+        assert_eq!(api.issue_type.as_deref(), Some("feature"));
+        assert_eq!(client.show("bd-1").unwrap().unwrap().description, None);
+        assert_eq!(client.show("bd-9").unwrap(), None);
+        assert_eq!(client.binary.get(), None);
+
+        // An unreadable database falls back to the (missing) CLI
+        let missing = BeadsClient::default().with_database(dir.path().join("nope.db"));
+        assert!(missing.ready_tasks().unwrap().is_empty());
+    }
+}
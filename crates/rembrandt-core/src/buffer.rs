@@ -0,0 +1,345 @@
+//! Ring buffer for PTY output history
+//!
+//! The RingBuffer stores recent output from agent sessions, enabling
+//! "late attach" - connecting to a session and seeing what happened
+//! before you connected.
+
+/// A fixed-capacity ring buffer for storing PTY output
+///
+/// When the buffer is full, old data is overwritten by new data.
+/// This allows us to keep the last N bytes of output without
+/// unbounded memory growth.
+pub struct RingBuffer {
+    /// The underlying storage
+    data: Vec<u8>,
+    /// Maximum capacity in bytes
+    capacity: usize,
+    /// Write position (where next byte goes)
+    write_pos: usize,
+    /// Total bytes written (may exceed capacity due to wraparound)
+    total_written: usize,
+}
+
+impl RingBuffer {
+    /// Create a new ring buffer with the given capacity
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            data: Vec::with_capacity(capacity),
+            capacity,
+            write_pos: 0,
+            total_written: 0,
+        }
+    }
+
+    /// Write data to the buffer
+    ///
+    /// If the data exceeds remaining capacity, older data is overwritten.
+    pub fn write(&mut self, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+        // Offsets count every byte, including ones that never fit
+        self.total_written += data.len();
+
+        // If data is larger than capacity, only keep the last `capacity` bytes
+        let data = if data.len() > self.capacity {
+            &data[data.len() - self.capacity..]
+        } else {
+            data
+        };
+
+        // Ensure buffer is at full capacity for wraparound logic
+        if self.data.len() < self.capacity {
+            // Buffer not full yet - extend it
+            let space_left = self.capacity - self.data.len();
+            if data.len() <= space_left {
+                // Fits entirely in remaining space
+                self.data.extend_from_slice(data);
+                self.write_pos = self.data.len();
+            } else {
+                // Partially fits, need to start wrapping
+                self.data.extend_from_slice(&data[..space_left]);
+                // Now buffer is full, write rest at beginning
+                let remaining = &data[space_left..];
+                self.data[..remaining.len()].copy_from_slice(remaining);
+                self.write_pos = remaining.len();
+            }
+        } else {
+            // Buffer is full, use wraparound
+            let space_to_end = self.capacity - self.write_pos;
+            if data.len() <= space_to_end {
+                // Fits before end of buffer
+                self.data[self.write_pos..self.write_pos + data.len()].copy_from_slice(data);
+                self.write_pos += data.len();
+                if self.write_pos >= self.capacity {
+                    self.write_pos = 0;
+                }
+            } else {
+                // Need to wrap around
+                self.data[self.write_pos..].copy_from_slice(&data[..space_to_end]);
+                let remaining = &data[space_to_end..];
+                self.data[..remaining.len()].copy_from_slice(remaining);
+                self.write_pos = remaining.len();
+            }
+        }
+    }
+
+    /// Read all available data from the buffer
+    ///
+    /// Returns data in chronological order (oldest first).
+    pub fn read_all(&self) -> Vec<u8> {
+        self.copy_from(0)
+    }
+
+    /// Data from stream offset `offset` on, and the offset it actually starts at
+    ///
+    /// Pass the `total_written` seen last time to pick up only new output.
+    /// The start is later than `offset` when those bytes were overwritten.
+    pub fn read_since(&self, offset: usize) -> (usize, Vec<u8>) {
+        let oldest = self.total_written - self.len();
+        let start = offset.clamp(oldest, self.total_written);
+        (start, self.copy_from(start - oldest))
+    }
+
+    /// The last `n` lines, oldest first
+    ///
+    /// A trailing line without a newline counts as a line; line endings
+    /// (`\n` or `\r\n`) are stripped. The first line may be cut short if its
+    /// start was already overwritten.
+    pub fn read_last_lines(&self, n: usize) -> Vec<String> {
+        let len = self.len();
+        if n == 0 || len == 0 {
+            return Vec::new();
+        }
+        // A final newline ends the last line rather than starting an empty one
+        let mut end = len;
+        if self.byte_at(len - 1) == b'\n' {
+            end -= 1;
+        }
+        let mut start = 0;
+        let mut found = 0;
+        for i in (0..end).rev() {
+            if self.byte_at(i) == b'\n' {
+                found += 1;
+                if found == n {
+                    start = i + 1;
+                    break;
+                }
+            }
+        }
+        String::from_utf8_lossy(&self.copy_from(start))
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// The `i`th stored byte, counting from the oldest
+    fn byte_at(&self, i: usize) -> u8 {
+        // Whether or not the buffer has wrapped, the oldest byte sits at
+        // `write_pos` (modulo the stored length)
+        self.data[(self.write_pos + i) % self.data.len()]
+    }
+
+    /// Stored bytes from the `skip`th oldest on, in order
+    fn copy_from(&self, skip: usize) -> Vec<u8> {
+        let len = self.len();
+        if skip >= len {
+            return Vec::new();
+        }
+        let first = (self.write_pos + skip) % len;
+        let count = len - skip;
+        if first + count <= len {
+            self.data[first..first + count].to_vec()
+        } else {
+            let mut result = Vec::with_capacity(count);
+            result.extend_from_slice(&self.data[first..]);
+            result.extend_from_slice(&self.data[..count - (len - first)]);
+            result
+        }
+    }
+
+    /// Get the number of bytes currently stored
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Check if buffer is empty
+    pub fn is_empty(&self) -> bool {
+        self.total_written == 0
+    }
+
+    /// Check if buffer has wrapped around
+    pub fn has_wrapped(&self) -> bool {
+        self.total_written > self.capacity
+    }
+
+    /// Get the total bytes ever written (may exceed capacity)
+    pub fn total_written(&self) -> usize {
+        self.total_written
+    }
+
+    /// Clear the buffer
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.write_pos = 0;
+        self.total_written = 0;
+    }
+
+    /// Get current capacity
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Change the capacity, keeping as much of the most recent output as fits
+    ///
+    /// Stream offsets carry on from where they were.
+    pub fn resize(&mut self, capacity: usize) {
+        let mut data = self.read_all();
+        if data.len() > capacity {
+            data.drain(..data.len() - capacity);
+        }
+        data.reserve_exact(capacity - data.len());
+        self.write_pos = data.len();
+        self.data = data;
+        self.capacity = capacity;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_since() {
+        let mut buf = RingBuffer::new(8);
+        buf.write(b"hello");
+        assert_eq!(buf.read_since(2), (2, b"llo".to_vec()));
+        assert_eq!(buf.read_since(5), (5, Vec::new()));
+        buf.write(b" world");
+        // "hel" was overwritten; the read starts at the oldest kept byte
+        assert_eq!(buf.read_since(0), (3, b"lo world".to_vec()));
+        assert_eq!(buf.read_since(9), (9, b"ld".to_vec()));
+        assert_eq!(buf.read_since(20), (11, Vec::new()));
+    }
+
+    #[test]
+    fn test_read_since_across_wraparound() {
+        let mut buf = RingBuffer::new(6);
+        buf.write(b"abcd");
+        buf.write(b"efgh"); // stored as "ghcdef", oldest at 2
+        assert_eq!(buf.read_since(0), (2, b"cdefgh".to_vec()));
+        assert_eq!(buf.read_since(5), (5, b"fgh".to_vec()));
+        assert_eq!(buf.read_since(6), (6, b"gh".to_vec()));
+
+        // Cursor-style tailing: each read picks up exactly what's new
+        let mut cursor = buf.total_written();
+        for chunk in [&b"ij"[..], b"klmn", b"o"] {
+            buf.write(chunk);
+            let (start, data) = buf.read_since(cursor);
+            assert_eq!((start, data.as_slice()), (cursor, chunk));
+            cursor = buf.total_written();
+        }
+        assert_eq!(buf.read_since(cursor), (cursor, Vec::new()));
+    }
+
+    #[test]
+    fn test_read_last_lines() {
+        let mut buf = RingBuffer::new(64);
+        assert!(buf.read_last_lines(3).is_empty());
+        buf.write(b"one\ntwo\r\nthree\n");
+        assert_eq!(buf.read_last_lines(2), vec!["two", "three"]);
+        assert_eq!(buf.read_last_lines(10), vec!["one", "two", "three"]);
+        assert!(buf.read_last_lines(0).is_empty());
+
+        // An unterminated tail is the last line; blank lines count
+        buf.write(b"\nfour");
+        assert_eq!(buf.read_last_lines(3), vec!["three", "", "four"]);
+    }
+
+    #[test]
+    fn test_read_last_lines_across_wraparound() {
+        let mut buf = RingBuffer::new(10);
+        buf.write(b"alpha\nbeta\n");
+        buf.write(b"gamma\n"); // keeps "eta\ngamma\n", split across the end
+        assert_eq!(buf.read_last_lines(1), vec!["gamma"]);
+        // The oldest line lost its start to the wrap
+        assert_eq!(buf.read_last_lines(5), vec!["eta", "gamma"]);
+
+        // Line boundary exactly at the physical end of the storage
+        let mut buf = RingBuffer::new(4);
+        buf.write(b"ab\nc");
+        buf.write(b"d\n");
+        assert_eq!(buf.read_all(), b"\ncd\n");
+        assert_eq!(buf.read_last_lines(2), vec!["", "cd"]);
+    }
+
+    #[test]
+    fn test_new_buffer() {
+        let buf = RingBuffer::new(100);
+        assert_eq!(buf.capacity(), 100);
+        assert!(buf.is_empty());
+        assert_eq!(buf.len(), 0);
+    }
+
+    #[test]
+    fn test_simple_write_read() {
+        let mut buf = RingBuffer::new(100);
+        buf.write(b"hello");
+        assert_eq!(buf.len(), 5);
+        assert_eq!(buf.read_all(), b"hello");
+    }
+
+    #[test]
+    fn test_multiple_writes() {
+        let mut buf = RingBuffer::new(100);
+        buf.write(b"hello ");
+        buf.write(b"world");
+        assert_eq!(buf.read_all(), b"hello world");
+    }
+
+    #[test]
+    fn test_wraparound() {
+        let mut buf = RingBuffer::new(10);
+        buf.write(b"12345678"); // 8 bytes
+        buf.write(b"abcd");     // 4 more, should wrap
+        // Buffer should contain: "5678abcd" or similar
+        // (oldest data overwritten)
+        let result = buf.read_all();
+        assert_eq!(result.len(), 10);
+        assert!(buf.has_wrapped());
+    }
+
+    #[test]
+    fn test_large_write() {
+        let mut buf = RingBuffer::new(5);
+        buf.write(b"this is way too long");
+        // Should only keep last 5 bytes: " long"
+        let result = buf.read_all();
+        assert_eq!(result.len(), 5);
+        assert_eq!(buf.total_written(), 20);
+    }
+
+    #[test]
+    fn test_resize_keeps_recent_output() {
+        let mut buf = RingBuffer::new(8);
+        buf.write(b"hello world"); // keeps "lo world"
+
+        buf.resize(16);
+        assert_eq!(buf.read_all(), b"lo world");
+        buf.write(b"!!");
+        assert_eq!(buf.read_all(), b"lo world!!");
+        assert_eq!(buf.read_since(0), (3, b"lo world!!".to_vec()));
+
+        buf.resize(4);
+        assert_eq!(buf.read_all(), b"ld!!");
+        assert_eq!(buf.read_since(0), (9, b"ld!!".to_vec()));
+        buf.write(b"abc");
+        assert_eq!(buf.read_all(), b"!abc");
+        assert_eq!(buf.total_written(), 16);
+
+        buf.resize(0);
+        buf.write(b"gone");
+        assert!(buf.read_all().is_empty());
+        assert_eq!(buf.read_since(0), (20, Vec::new()));
+    }
+}
@@ -0,0 +1,211 @@
+//! Benchmarks as an evaluation metric for performance tasks
+//!
+//! A "make it faster" competition is better judged by measurements than by
+//! diff size. A competition with a benchmark runs its command in every valid
+//! solution's worktree and reads one number from the output; the metrics
+//! evaluator then scores each solution against the best measurement, with
+//! the benchmark's weight.
+//!
+//! Criterion output is understood directly (the point estimates of every
+//! benchmark are summed, in seconds). Anything else should print the
+//! measurement last: the first number on the last line containing one is
+//! taken, with an optional `ns`/`us`/`µs`/`ms`/`s` unit.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+
+fn default_lower_is_better() -> bool {
+    true
+}
+
+fn default_weight() -> f64 {
+    0.5
+}
+
+/// A benchmark to run on each solution
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Benchmark {
+    /// Shell command, e.g. `cargo bench --bench parse -- --noplot`
+    pub command: String,
+    /// Smaller measurements are better (times); false for throughput
+    #[serde(default = "default_lower_is_better")]
+    pub lower_is_better: bool,
+    /// Weight of the benchmark next to the other metrics
+    #[serde(default = "default_weight")]
+    pub weight: f64,
+}
+
+/// A solution's measurement
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BenchmarkResult {
+    /// Measured value, in seconds when the output had a time unit
+    pub value: f64,
+    pub lower_is_better: bool,
+}
+
+impl Benchmark {
+    pub fn new(command: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+            lower_is_better: default_lower_is_better(),
+            weight: default_weight(),
+        }
+    }
+
+    /// Run the benchmark in `worktree` and parse its measurement
+    pub fn run(&self, worktree: &Path) -> Result<BenchmarkResult, String> {
+        let output = Command::new("sh")
+            .args(["-c", &self.command])
+            .current_dir(worktree)
+            .output()
+            .map_err(|e| format!("failed to run {}: {}", self.command, e))?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!(
+                "{} failed: {}",
+                self.command,
+                stderr.trim().lines().last().unwrap_or_default()
+            ));
+        }
+        let value = parse_benchmark_output(&stdout)
+            .ok_or_else(|| format!("no measurement in the output of {}", self.command))?;
+        Ok(BenchmarkResult {
+            value,
+            lower_is_better: self.lower_is_better,
+        })
+    }
+}
+
+impl BenchmarkResult {
+    /// Score (0.0-1.0) against the best measurement among the solutions
+    pub fn score(&self, best: f64) -> f64 {
+        let ratio = if self.lower_is_better {
+            best / self.value
+        } else {
+            self.value / best
+        };
+        if ratio.is_finite() {
+            ratio.clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+}
+
+/// The best of `results`: smallest for times, largest otherwise
+pub fn best_benchmark<'a>(results: impl IntoIterator<Item = &'a BenchmarkResult>) -> Option<f64> {
+    results.into_iter().fold(None, |best, result| {
+        Some(match best {
+            None => result.value,
+            Some(best) if result.lower_is_better => result.value.min(best),
+            Some(best) => result.value.max(best),
+        })
+    })
+}
+
+/// The measurement in benchmark output
+pub fn parse_benchmark_output(output: &str) -> Option<f64> {
+    // Criterion: `time:   [1.2034 ms 1.2101 ms 1.2170 ms]`, point estimate in the middle
+    let criterion: Vec<f64> = output
+        .lines()
+        .filter_map(|line| {
+            let estimates = line
+                .split_once("time:")?
+                .1
+                .trim()
+                .strip_prefix('[')?
+                .strip_suffix(']')?;
+            let words: Vec<&str> = estimates.split_whitespace().collect();
+            match words.as_slice() {
+                [_, _, value, unit, _, _] => parse_quantity(value, Some(unit)),
+                _ => None,
+            }
+        })
+        .collect();
+    if !criterion.is_empty() {
+        return Some(criterion.iter().sum());
+    }
+
+    output.lines().rev().find_map(|line| {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        words.iter().enumerate().find_map(|(i, word)| {
+            let word =
+                word.trim_start_matches(|c: char| !c.is_ascii_digit() && c != '.' && c != '-');
+            // `12.5ms` or `12.5 ms`
+            let split =
+                word.find(|c: char| !c.is_ascii_digit() && !matches!(c, '.' | '-' | 'e' | 'E'));
+            match split {
+                Some(at) if at > 0 => parse_quantity(&word[..at], Some(&word[at..])),
+                Some(_) => None,
+                None => parse_quantity(word, words.get(i + 1).copied()),
+            }
+        })
+    })
+}
+
+/// `value` scaled to seconds by a time `unit`; other units leave it as is
+fn parse_quantity(value: &str, unit: Option<&str>) -> Option<f64> {
+    let value: f64 = value.parse().ok()?;
+    let scale = match unit.map(|u| u.trim_end_matches([',', ';', ')'])) {
+        Some("ns") => 1e-9,
+        Some("us" | "µs" | "μs") => 1e-6,
+        Some("ms") => 1e-3,
+        _ => 1.0,
+    };
+    value.is_finite().then_some(value * scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measurements_are_parsed_and_scored_against_the_best() {
+        let criterion = "\
+Benchmarking parse/small: Warming up for 3.0000 s
+parse/small             time:   [1.2034 ms 1.2101 ms 1.2170 ms]
+                        change: [-5.1% -4.2% -3.3%] (p = 0.00 < 0.05)
+parse/large             time:   [850.10 µs 855.00 µs 860.40 µs]
+Found 3 outliers among 100 measurements (3.00%)
+";
+        let total = parse_benchmark_output(criterion).unwrap();
+        assert!((total - 0.0020651).abs() < 1e-9, "{}", total);
+
+        assert_eq!(
+            parse_benchmark_output("warming up\nmean: 42.5ms\n"),
+            Some(0.0425)
+        );
+        assert_eq!(
+            parse_benchmark_output("requests/sec: 1834.2\ndone\n"),
+            Some(1834.2)
+        );
+        assert_eq!(parse_benchmark_output("took 3 s (2 runs)\n"), Some(3.0));
+        assert_eq!(parse_benchmark_output("no numbers here\n"), None);
+
+        let fast = BenchmarkResult {
+            value: 1.0,
+            lower_is_better: true,
+        };
+        let slow = BenchmarkResult {
+            value: 4.0,
+            lower_is_better: true,
+        };
+        let best = best_benchmark([&fast, &slow]).unwrap();
+        assert_eq!((fast.score(best), slow.score(best)), (1.0, 0.25));
+
+        let throughput = [
+            BenchmarkResult {
+                value: 500.0,
+                lower_is_better: false,
+            },
+            BenchmarkResult {
+                value: 2000.0,
+                lower_is_better: false,
+            },
+        ];
+        let best = best_benchmark(&throughput).unwrap();
+        assert_eq!(throughput[0].score(best), 0.25);
+    }
+}
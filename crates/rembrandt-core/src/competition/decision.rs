@@ -0,0 +1,191 @@
+//! Porque decisions for competition winners picked by a person
+//!
+//! A human choosing between competing solutions (the human evaluator stage,
+//! or overriding the metrics' pick) is often making an architectural call.
+//! Competitions with `record_decision` set write that call up with `pq new`:
+//! the options considered, who or what chose, the rationale, the competition
+//! and the winning branch. Until the human stage is interactive it picks on
+//! its own, and the decision names that evaluator rather than a person.
+
+use crate::competition::CompetitionGroup;
+use crate::{RembrandtError, Result};
+use std::path::Path;
+use std::process::Command;
+
+/// One solution that was considered
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecisionOption {
+    pub agent_id: String,
+    pub branch: String,
+    /// Rank and evaluator notes, or validation state when unranked
+    pub summary: String,
+}
+
+/// A decision ready to hand to `pq new`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecisionDraft {
+    pub title: String,
+    pub competition_id: String,
+    pub winner_id: String,
+    pub winning_branch: String,
+    /// Who or what picked the winner, e.g. `a person`
+    pub chosen_by: String,
+    pub options: Vec<DecisionOption>,
+    pub rationale: String,
+}
+
+impl DecisionDraft {
+    /// Draft the decision for `competition`'s winner, `None` before one is picked
+    pub fn from_competition(competition: &CompetitionGroup, chosen_by: &str, rationale: &str) -> Option<Self> {
+        let winner_id = competition.winner.clone()?;
+        let winning_branch = competition
+            .competitors
+            .iter()
+            .find(|c| c.agent_id == winner_id)?
+            .branch
+            .clone();
+        let rankings = competition.evaluation_result.as_ref().map(|r| r.rankings.as_slice()).unwrap_or_default();
+
+        let options = competition
+            .competitors
+            .iter()
+            .map(|competitor| {
+                let summary = match rankings.iter().find(|r| r.agent_id == competitor.agent_id) {
+                    Some(ranking) => format!("rank {}: {}", ranking.rank, ranking.reasoning),
+                    None if competitor.is_valid() => "passed validation, unranked".to_string(),
+                    None => "did not pass validation".to_string(),
+                };
+                DecisionOption {
+                    agent_id: competitor.agent_id.clone(),
+                    branch: competitor.branch.clone(),
+                    summary,
+                }
+            })
+            .collect();
+
+        let task = competition.prompt.lines().next().unwrap_or_default().trim();
+        let task: String = if task.chars().count() > 60 {
+            format!("{}…", task.chars().take(59).collect::<String>())
+        } else {
+            task.to_string()
+        };
+
+        Some(Self {
+            title: format!("{} (competition {})", task, competition.id),
+            competition_id: competition.id.clone(),
+            winner_id,
+            winning_branch,
+            chosen_by: chosen_by.to_string(),
+            options,
+            rationale: rationale.trim().to_string(),
+        })
+    }
+
+    /// Markdown body: the options, the choice and why
+    pub fn body(&self) -> String {
+        let mut body = format!(
+            "Chosen by {} in competition `{}`.\n\n## Options considered\n\n",
+            self.chosen_by, self.competition_id
+        );
+        for option in &self.options {
+            let chosen = if option.agent_id == self.winner_id { " (chosen)" } else { "" };
+            body.push_str(&format!(
+                "- `{}` on `{}`{}: {}\n",
+                option.agent_id, option.branch, chosen, option.summary
+            ));
+        }
+        body.push_str(&format!(
+            "\n## Decision\n\nMerge `{}` from `{}`.\n\n## Rationale\n\n{}\n",
+            self.winner_id,
+            self.winning_branch,
+            if self.rationale.is_empty() { "(none given)" } else { &self.rationale }
+        ));
+        body
+    }
+
+    /// Create the decision with `pq new` in `repo_path`; returns its id, or
+    /// `None` when Porque isn't installed or prints no id
+    pub fn record(&self, repo_path: &Path) -> Result<Option<String>> {
+        let body = self.body();
+        let output = match Command::new("pq")
+            .args(["new", &self.title, "--body", &body, "--json"])
+            .current_dir(repo_path)
+            .output()
+        {
+            Ok(output) => output,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        if !output.status.success() {
+            return Err(RembrandtError::Competition(format!(
+                "pq new failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        let created: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap_or_default();
+        Ok(created.get("id").and_then(|id| id.as_str()).map(str::to_string))
+    }
+}
+
+/// Record `competition`'s winner as a decision if it asked for that, keeping
+/// the decision id on the competition. Failures are logged, not returned:
+/// the choice stands either way.
+pub(crate) fn record_decision(
+    competition: &mut CompetitionGroup,
+    repo_path: &Path,
+    chosen_by: &str,
+    rationale: &str,
+) {
+    if !competition.record_decision {
+        return;
+    }
+    let Some(draft) = DecisionDraft::from_competition(competition, chosen_by, rationale) else {
+        return;
+    };
+    match draft.record(repo_path) {
+        Ok(id) => competition.decision_id = id,
+        Err(e) => tracing::warn!(competition_id = %competition.id, error = %e, "could not record decision"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::AgentType;
+    use crate::competition::{CompetitorSolution, EvaluatorStrategy};
+
+    #[test]
+    fn drafts_list_every_option_and_mark_the_choice() {
+        let mut competition = CompetitionGroup::new(
+            "Replace the cache with an LRU\nKeep the API".to_string(),
+            EvaluatorStrategy::Human,
+            30,
+        );
+        for id in ["a", "b"] {
+            competition.competitors.push(CompetitorSolution {
+                agent_id: id.to_string(),
+                agent_type: AgentType::ClaudeCode,
+                variant: Default::default(),
+                branch: format!("rembrandt/{}", id),
+                worktree_path: std::env::temp_dir(),
+                completed_at: None,
+                validation: None,
+                diff_stats: None,
+            });
+        }
+        assert_eq!(DecisionDraft::from_competition(&competition, "a person", "simpler"), None);
+
+        competition.winner = Some("b".to_string());
+        let draft = DecisionDraft::from_competition(&competition, "a person", " b keeps eviction O(1) ").unwrap();
+        assert_eq!(draft.title, format!("Replace the cache with an LRU (competition {})", competition.id));
+        assert_eq!(draft.winning_branch, "rembrandt/b");
+        let body = draft.body();
+        assert!(body.starts_with("Chosen by a person in competition"), "{}", body);
+        assert!(body.contains("- `a` on `rembrandt/a`: did not pass validation\n"), "{}", body);
+        assert!(body.contains("- `b` on `rembrandt/b` (chosen):"), "{}", body);
+        assert!(body.ends_with("## Rationale\n\nb keeps eviction O(1)\n"), "{}", body);
+
+        assert!(EvaluatorStrategy::from_name("metrics,human", "m").involves_human());
+        assert!(!EvaluatorStrategy::default().involves_human());
+    }
+}
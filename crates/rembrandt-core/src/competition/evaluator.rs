@@ -0,0 +1,576 @@
+//! Evaluator trait and implementations for comparing solutions
+//!
+//! The evaluation pipeline: Metrics → Model → Human
+//! Each layer is optional and builds on the previous; `CompositeEvaluator`
+//! chains them for `EvaluatorStrategy::Pipeline`.
+
+use crate::competition::{
+    best_benchmark, CompetitorSolution, EvaluationResult, EvaluatorStrategy, MetricWeights, SolutionRanking,
+    TournamentEvaluator,
+};
+use crate::Result;
+use async_trait::async_trait;
+use chrono::Utc;
+use std::path::Path;
+
+/// Trait for evaluation strategies
+#[async_trait]
+pub trait Evaluator: Send + Sync {
+    /// Evaluate solutions and return rankings with a winner
+    async fn evaluate(
+        &self,
+        prompt: &str,
+        solutions: &[&CompetitorSolution],
+        repo_path: &Path,
+    ) -> Result<EvaluationResult>;
+
+    /// Get the name of this evaluator for logging
+    fn name(&self) -> &'static str;
+}
+
+/// Metrics-based evaluator using automated scoring
+pub struct MetricsEvaluator {
+    weights: MetricWeights,
+}
+
+impl MetricsEvaluator {
+    pub fn new(weights: MetricWeights) -> Self {
+        Self { weights }
+    }
+
+    /// Score one solution on its own, e.g. for an early stop
+    ///
+    /// With nothing to compare against, speed and the benchmark count as
+    /// neutral (0.5).
+    pub fn score(&self, solution: &CompetitorSolution) -> f64 {
+        self.score_solution(solution, 0, None)
+    }
+
+    /// Calculate score for a single solution
+    ///
+    /// `best_benchmark` is the best measurement among the solutions; `None`
+    /// when there is nothing to compare against.
+    fn score_solution(
+        &self,
+        solution: &CompetitorSolution,
+        max_time_ms: u64,
+        best_benchmark: Option<f64>,
+    ) -> f64 {
+        let validation = match &solution.validation {
+            Some(v) if v.is_valid() => v,
+            _ => return 0.0,
+        };
+
+        // Test score: proportion of tests passed
+        let test_score = if let (Some(total), Some(failures)) =
+            (validation.test_count, validation.test_failures)
+        {
+            if total > 0 {
+                (total - failures) as f64 / total as f64
+            } else {
+                1.0 // No tests = assume pass
+            }
+        } else {
+            1.0
+        };
+
+        // Simplicity score: inverse of lines changed (normalized)
+        let simplicity_score = if let Some(diff) = &solution.diff_stats {
+            let lines = diff.total_lines().max(1) as f64;
+            // Score decreases as lines increase, capped at reasonable range
+            1.0 / (1.0 + (lines / 100.0).ln())
+        } else {
+            0.5
+        };
+
+        // Speed score: inverse of time taken (normalized)
+        let speed_score = if max_time_ms > 0 {
+            1.0 - (validation.validation_time_ms as f64 / max_time_ms as f64)
+        } else {
+            0.5
+        };
+
+        // Benchmark score: measurement relative to the best one; a solution
+        // whose benchmark failed scores nothing
+        let benchmark_score = match (&validation.benchmark, best_benchmark) {
+            (Some(result), Some(best)) => result.score(best),
+            (_, None) => 0.5,
+            (None, Some(_)) => 0.0,
+        };
+
+        // Weighted combination
+        let score = (self.weights.tests * test_score)
+            + (self.weights.simplicity * simplicity_score)
+            + (self.weights.speed * speed_score);
+        if self.weights.benchmark > 0.0 {
+            // The other weights sum to 1; renormalize to keep scores in 0-1
+            let total = self.weights.tests
+                + self.weights.simplicity
+                + self.weights.speed
+                + self.weights.benchmark;
+            (score + self.weights.benchmark * benchmark_score) / total
+        } else {
+            score
+        }
+    }
+}
+
+#[async_trait]
+impl Evaluator for MetricsEvaluator {
+    async fn evaluate(
+        &self,
+        _prompt: &str,
+        solutions: &[&CompetitorSolution],
+        _repo_path: &Path,
+    ) -> Result<EvaluationResult> {
+        if solutions.is_empty() {
+            return Err(crate::RembrandtError::Competition(
+                "No solutions to evaluate".to_string(),
+            ));
+        }
+
+        // Find max validation time for normalization
+        let max_time_ms = solutions
+            .iter()
+            .filter_map(|s| s.validation.as_ref())
+            .map(|v| v.validation_time_ms)
+            .max()
+            .unwrap_or(1);
+
+        // Find the best benchmark measurement for normalization
+        let best_benchmark = best_benchmark(
+            solutions
+                .iter()
+                .filter_map(|s| s.validation.as_ref())
+                .filter_map(|v| v.benchmark.as_ref()),
+        );
+
+        // Score all solutions
+        let mut rankings: Vec<SolutionRanking> = solutions
+            .iter()
+            .map(|s| {
+                let score = self.score_solution(s, max_time_ms, best_benchmark);
+                let mut reasoning = format!(
+                    "Score: {:.2} (tests: {:.0}%, simplicity: {:.0}%, speed: {:.0}%",
+                    score,
+                    self.weights.tests * 100.0,
+                    self.weights.simplicity * 100.0,
+                    self.weights.speed * 100.0
+                );
+                if self.weights.benchmark > 0.0 {
+                    let measured = s
+                        .validation
+                        .as_ref()
+                        .and_then(|v| v.benchmark.as_ref())
+                        .map(|b| format!("{}", b.value))
+                        .unwrap_or_else(|| "none".to_string());
+                    reasoning.push_str(&format!(
+                        ", benchmark: {:.0}%, measured {}",
+                        self.weights.benchmark * 100.0,
+                        measured
+                    ));
+                }
+                reasoning.push(')');
+                SolutionRanking {
+                    agent_id: s.agent_id.clone(),
+                    rank: 0, // Will be set after sorting
+                    score,
+                    reasoning,
+                }
+            })
+            .collect();
+
+        // Sort by score descending
+        rankings.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+        // Assign ranks
+        for (i, ranking) in rankings.iter_mut().enumerate() {
+            ranking.rank = i + 1;
+        }
+
+        let winner = rankings
+            .first()
+            .ok_or_else(|| {
+                crate::RembrandtError::Competition("No valid rankings produced".to_string())
+            })?
+            .clone();
+
+        Ok(EvaluationResult {
+            winner_id: winner.agent_id.clone(),
+            strategy_used: EvaluatorStrategy::Metrics(self.weights.clone()),
+            reasoning: format!(
+                "Winner: {} with score {:.2}. {}",
+                winner.agent_id, winner.score, winner.reasoning
+            ),
+            rankings,
+            evaluated_at: Utc::now(),
+            judgments: Vec::new(),
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "metrics"
+    }
+}
+
+/// Model-based evaluator using an LLM
+pub struct ModelEvaluator {
+    model_name: String,
+}
+
+impl ModelEvaluator {
+    pub fn new(model_name: String) -> Self {
+        Self { model_name }
+    }
+
+    /// Build a comparison prompt for the LLM
+    fn build_prompt(&self, task: &str, solutions: &[&CompetitorSolution]) -> String {
+        let mut prompt = format!(
+            "You are evaluating {} solutions to this coding task:\n\n\
+             Task: {}\n\n\
+             Compare the solutions and rank them from best to worst.\n\
+             Consider: correctness, maintainability, idiomatic style, and elegance.\n\n",
+            solutions.len(),
+            task
+        );
+
+        for (i, solution) in solutions.iter().enumerate() {
+            prompt.push_str(&format!(
+                "=== Solution {} (Agent: {}) ===\n",
+                i + 1,
+                solution.agent_id
+            ));
+            if let Some(diff) = &solution.diff_stats {
+                prompt.push_str(&format!(
+                    "Files changed: {}, +{} -{}\n\n",
+                    diff.files_changed, diff.insertions, diff.deletions
+                ));
+            }
+            if let Some(validation) = &solution.validation
+                && let (Some(count), Some(failures)) = (validation.test_count, validation.test_failures)
+            {
+                prompt.push_str(&format!("Tests: {} of {} passed\n\n", count - failures, count));
+            }
+            // Note: In real implementation, we'd include the actual diff content here
+            prompt.push_str("[Diff content would be included here]\n\n");
+        }
+
+        prompt.push_str(
+            "Respond with JSON: {\"rankings\": [{\"agent_id\": \"...\", \"rank\": 1, \"reasoning\": \"...\"}]}",
+        );
+        prompt
+    }
+}
+
+#[async_trait]
+impl Evaluator for ModelEvaluator {
+    async fn evaluate(
+        &self,
+        prompt: &str,
+        solutions: &[&CompetitorSolution],
+        _repo_path: &Path,
+    ) -> Result<EvaluationResult> {
+        // TODO: Implement actual LLM call via pluggable provider
+        // For now, fall back to metrics-based scoring
+        let _comparison_prompt = self.build_prompt(prompt, solutions);
+
+        // Placeholder: delegate to metrics evaluator
+        let metrics = MetricsEvaluator::new(MetricWeights::default());
+        let mut result = metrics.evaluate(prompt, solutions, _repo_path).await?;
+        result.strategy_used = EvaluatorStrategy::Model {
+            model_name: self.model_name.clone(),
+        };
+        result.reasoning = format!(
+            "[Model evaluation not yet implemented, used metrics fallback] {}",
+            result.reasoning
+        );
+        Ok(result)
+    }
+
+    fn name(&self) -> &'static str {
+        "model"
+    }
+}
+
+/// Human evaluator - leaves the pick to a person
+///
+/// It proposes the first solution (the previous stage's best in a pipeline)
+/// without claiming anyone chose it; the competition then waits at
+/// `Merging` until a person merges the proposal or picks another with
+/// `CompetitionManager::select_winner`.
+pub struct HumanEvaluator;
+
+impl HumanEvaluator {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Default for HumanEvaluator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl Evaluator for HumanEvaluator {
+    async fn evaluate(
+        &self,
+        _prompt: &str,
+        solutions: &[&CompetitorSolution],
+        _repo_path: &Path,
+    ) -> Result<EvaluationResult> {
+        let proposed = solutions.first().ok_or_else(|| {
+            crate::RembrandtError::Competition("No solutions for human review".to_string())
+        })?;
+
+        let rankings: Vec<SolutionRanking> = solutions
+            .iter()
+            .enumerate()
+            .map(|(i, s)| SolutionRanking {
+                agent_id: s.agent_id.clone(),
+                rank: i + 1,
+                score: 0.0,
+                reasoning: if i == 0 {
+                    "Proposed; a person picks the winner".to_string()
+                } else {
+                    "Awaiting a person's pick".to_string()
+                },
+            })
+            .collect();
+
+        Ok(EvaluationResult {
+            winner_id: proposed.agent_id.clone(),
+            strategy_used: EvaluatorStrategy::Human,
+            reasoning: format!("Waiting for a person to pick; proposed {}", proposed.agent_id),
+            rankings,
+            evaluated_at: Utc::now(),
+            judgments: Vec::new(),
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "human"
+    }
+}
+
+/// Share of the best metrics score a solution needs to survive a metrics
+/// stage that isn't last in a pipeline
+const OUTLIER_SCORE_FRACTION: f64 = 0.5;
+
+/// Chains evaluators, each working on the previous stage's picks
+///
+/// Every stage after the first sees the surviving solutions in the order the
+/// stage before ranked them, so a human stage confirms the model's pick
+/// unless overridden. A metrics stage that isn't last also filters: invalid
+/// solutions and outliers scoring under half the best are eliminated.
+pub struct CompositeEvaluator {
+    stages: Vec<(EvaluatorStrategy, Box<dyn Evaluator>)>,
+}
+
+impl CompositeEvaluator {
+    pub fn new(strategies: Vec<EvaluatorStrategy>) -> Self {
+        let stages = strategies
+            .into_iter()
+            .map(|strategy| {
+                let evaluator = create_evaluator(&strategy);
+                (strategy, evaluator)
+            })
+            .collect();
+        Self { stages }
+    }
+
+    /// Chain already-built evaluators, e.g. with a custom judge, each paired
+    /// with the strategy it stands for
+    pub fn with_stages(stages: Vec<(EvaluatorStrategy, Box<dyn Evaluator>)>) -> Self {
+        Self { stages }
+    }
+}
+
+#[async_trait]
+impl Evaluator for CompositeEvaluator {
+    async fn evaluate(
+        &self,
+        prompt: &str,
+        solutions: &[&CompetitorSolution],
+        repo_path: &Path,
+    ) -> Result<EvaluationResult> {
+        if self.stages.is_empty() {
+            return Err(crate::RembrandtError::Competition(
+                "Evaluation pipeline has no stages".to_string(),
+            ));
+        }
+
+        let mut remaining: Vec<&CompetitorSolution> = solutions.to_vec();
+        let mut eliminated: Vec<SolutionRanking> = Vec::new();
+        let mut reasoning = Vec::new();
+        let mut judgments = Vec::new();
+        let mut result = None;
+
+        for (i, (strategy, stage)) in self.stages.iter().enumerate() {
+            let stage_result = stage.evaluate(prompt, &remaining, repo_path).await?;
+            reasoning.push(format!("{}: {}", stage.name(), stage_result.reasoning));
+            judgments.extend(stage_result.judgments.iter().cloned());
+
+            let is_last = i + 1 == self.stages.len();
+            let best = stage_result.rankings.first().map_or(0.0, |r| r.score);
+            let mut ranked = Vec::new();
+            let mut dropped = Vec::new();
+            for ranking in &stage_result.rankings {
+                let Some(solution) = remaining.iter().find(|s| s.agent_id == ranking.agent_id) else {
+                    continue;
+                };
+                let filtered = !is_last
+                    && matches!(strategy, EvaluatorStrategy::Metrics(_))
+                    && ranking.agent_id != stage_result.winner_id
+                    && (ranking.score <= 0.0 || ranking.score < best * OUTLIER_SCORE_FRACTION);
+                if filtered {
+                    dropped.push(SolutionRanking {
+                        reasoning: format!("Eliminated by {}: {}", stage.name(), ranking.reasoning),
+                        ..ranking.clone()
+                    });
+                } else {
+                    ranked.push(*solution);
+                }
+            }
+            // Solutions dropped by earlier stages rank below later ones
+            eliminated.splice(0..0, dropped);
+            remaining = ranked;
+            result = Some(stage_result);
+        }
+
+        let mut result = result.expect("pipeline has at least one stage");
+        let survivors = result.rankings.len();
+        for (i, mut ranking) in eliminated.into_iter().enumerate() {
+            ranking.rank = survivors + i + 1;
+            result.rankings.push(ranking);
+        }
+        result.strategy_used =
+            EvaluatorStrategy::Pipeline(self.stages.iter().map(|(strategy, _)| strategy.clone()).collect());
+        result.reasoning = reasoning.join(" → ");
+        result.judgments = judgments;
+        Ok(result)
+    }
+
+    fn name(&self) -> &'static str {
+        "pipeline"
+    }
+}
+
+/// Create an evaluator based on strategy
+pub fn create_evaluator(strategy: &EvaluatorStrategy) -> Box<dyn Evaluator> {
+    match strategy {
+        EvaluatorStrategy::Metrics(weights) => Box::new(MetricsEvaluator::new(weights.clone())),
+        EvaluatorStrategy::Model { model_name } => {
+            Box::new(ModelEvaluator::new(model_name.clone()))
+        }
+        EvaluatorStrategy::Human => Box::new(HumanEvaluator::new()),
+        EvaluatorStrategy::Tournament { model_name } => {
+            Box::new(TournamentEvaluator::new(model_name.clone()))
+        }
+        EvaluatorStrategy::Pipeline(stages) => Box::new(CompositeEvaluator::new(stages.clone())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::AgentType;
+    use crate::competition::ValidationResult;
+
+    fn solution(agent_id: &str, valid: bool) -> CompetitorSolution {
+        CompetitorSolution {
+            agent_id: agent_id.to_string(),
+            agent_type: AgentType::ClaudeCode,
+            variant: Default::default(),
+            branch: format!("rembrandt/{}", agent_id),
+            worktree_path: std::env::temp_dir(),
+            completed_at: Some(Utc::now()),
+            validation: Some(ValidationResult {
+                agent_id: agent_id.to_string(),
+                type_check_passed: valid,
+                type_check_output: None,
+                tests_passed: true,
+                tests_output: None,
+                test_count: Some(10),
+                test_failures: Some(0),
+                failed_tests: Vec::new(),
+                test_duration_ms: None,
+                lint_results: Vec::new(),
+                security: None,
+                benchmark: None,
+                validation_time_ms: 100,
+                error_message: None,
+            }),
+            diff_stats: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn pipeline_filters_with_metrics_before_later_stages() {
+        let solutions = [solution("broken", false), solution("good", true)];
+        let refs: Vec<&CompetitorSolution> = solutions.iter().collect();
+        let strategy = EvaluatorStrategy::from_name("metrics,human", "unused");
+        assert_eq!(
+            strategy,
+            EvaluatorStrategy::Pipeline(vec![EvaluatorStrategy::default(), EvaluatorStrategy::Human])
+        );
+
+        let result = create_evaluator(&strategy)
+            .evaluate("task", &refs, &std::env::temp_dir())
+            .await
+            .unwrap();
+
+        // The human stage only saw the survivor, so it proposes it
+        assert_eq!(result.winner_id, "good");
+        assert_eq!(result.strategy_used, strategy);
+        let order: Vec<(&str, usize)> =
+            result.rankings.iter().map(|r| (r.agent_id.as_str(), r.rank)).collect();
+        assert_eq!(order, [("good", 1), ("broken", 2)]);
+        assert!(result.rankings[1].reasoning.starts_with("Eliminated by metrics"));
+        assert!(result.reasoning.starts_with("metrics: ") && result.reasoning.contains(" → human: "));
+    }
+
+    #[tokio::test]
+    async fn custom_stages_report_the_strategies_they_were_paired_with() {
+        let solutions = [solution("broken", false), solution("good", true)];
+        let refs: Vec<&CompetitorSolution> = solutions.iter().collect();
+        let evaluator = CompositeEvaluator::with_stages(vec![
+            (EvaluatorStrategy::default(), Box::new(MetricsEvaluator::new(Default::default()))),
+            (EvaluatorStrategy::Human, Box::new(HumanEvaluator::new())),
+        ]);
+
+        let result = evaluator.evaluate("task", &refs, &std::env::temp_dir()).await.unwrap();
+
+        assert_eq!(result.winner_id, "good");
+        assert_eq!(
+            result.strategy_used,
+            EvaluatorStrategy::Pipeline(vec![EvaluatorStrategy::default(), EvaluatorStrategy::Human])
+        );
+        assert!(CompositeEvaluator::with_stages(Vec::new())
+            .evaluate("task", &refs, &std::env::temp_dir())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn benchmark_weight_favours_the_fastest_measurement() {
+        let mut solutions = [solution("slow", true), solution("fast", true)];
+        for (solution, value) in solutions.iter_mut().zip([4.0, 1.0]) {
+            solution.validation.as_mut().unwrap().benchmark =
+                Some(crate::competition::BenchmarkResult { value, lower_is_better: true });
+        }
+        let refs: Vec<&CompetitorSolution> = solutions.iter().collect();
+        let mut strategy = EvaluatorStrategy::default();
+        strategy.set_benchmark_weight(0.5);
+
+        let result = create_evaluator(&strategy)
+            .evaluate("make it faster", &refs, &std::env::temp_dir())
+            .await
+            .unwrap();
+
+        assert_eq!(result.winner_id, "fast");
+        assert!(result.rankings.iter().all(|r| (0.0..=1.0).contains(&r.score)));
+        assert!(result.rankings[0].reasoning.contains("benchmark: 50%, measured 1"));
+    }
+}
@@ -0,0 +1,358 @@
+//! Finished competitions, kept for history and analytics
+//!
+//! A competition reaching a terminal state (completed, failed or cancelled)
+//! is written to `.rembrandt/competitions/<id>.json`. `rembrandt compete
+//! history` reads them back and totals wins per agent type, overall and per
+//! kind of task, to show which agents suit which work.
+
+use crate::competition::{CompetitionGroup, CompetitionStatus, CompetitorSolution};
+use crate::{RembrandtError, Result};
+use chrono::{Duration, Utc};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Directory under `.rembrandt` finished competitions are kept in
+pub const HISTORY_DIR: &str = "competitions";
+
+/// Rough kind of task a competition prompt asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TaskKind {
+    Refactor,
+    Bugfix,
+    Performance,
+    Test,
+    Docs,
+    Feature,
+    Other,
+}
+
+/// Prompt words marking each kind, checked in order
+const TASK_KEYWORDS: &[(TaskKind, &[&str])] = &[
+    (
+        TaskKind::Refactor,
+        &[
+            "refactor",
+            "restructure",
+            "clean up",
+            "cleanup",
+            "rename",
+            "extract",
+            "simplify",
+        ],
+    ),
+    (
+        TaskKind::Bugfix,
+        &["fix", "bug", "crash", "broken", "regression", "error"],
+    ),
+    (
+        TaskKind::Performance,
+        &[
+            "faster",
+            "speed up",
+            "performance",
+            "optimize",
+            "optimise",
+            "latency",
+        ],
+    ),
+    (TaskKind::Test, &["test", "coverage"]),
+    (TaskKind::Docs, &["document", "docs", "readme", "comment"]),
+    (
+        TaskKind::Feature,
+        &["add", "implement", "support", "create", "build", "new"],
+    ),
+];
+
+impl TaskKind {
+    /// Classify `prompt` by its first matching keyword group
+    pub fn classify(prompt: &str) -> Self {
+        let prompt = prompt.to_lowercase();
+        let words: Vec<&str> = prompt.split(|c: char| !c.is_alphanumeric()).collect();
+        TASK_KEYWORDS
+            .iter()
+            .find(|(_, keywords)| {
+                keywords.iter().any(|keyword| {
+                    if keyword.contains(' ') {
+                        prompt.contains(keyword)
+                    } else {
+                        words.iter().any(|word| word.starts_with(keyword))
+                    }
+                })
+            })
+            .map_or(TaskKind::Other, |(kind, _)| *kind)
+    }
+}
+
+impl fmt::Display for TaskKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            TaskKind::Refactor => "refactor",
+            TaskKind::Bugfix => "bugfix",
+            TaskKind::Performance => "performance",
+            TaskKind::Test => "test",
+            TaskKind::Docs => "docs",
+            TaskKind::Feature => "feature",
+            TaskKind::Other => "other",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl CompetitionGroup {
+    /// The winning competitor, once one is picked
+    pub fn winning_solution(&self) -> Option<&CompetitorSolution> {
+        let winner = self.winner.as_deref()?;
+        self.competitors.iter().find(|c| c.agent_id == winner)
+    }
+
+    /// The winner's evaluation score
+    pub fn winner_score(&self) -> Option<f64> {
+        let winner = self.winner.as_deref()?;
+        self.evaluation_result
+            .as_ref()?
+            .rankings
+            .iter()
+            .find(|r| r.agent_id == winner)
+            .map(|r| r.score)
+    }
+
+    /// Time from start to a terminal state
+    pub fn duration(&self) -> Option<Duration> {
+        self.completed_at.map(|at| at - self.started_at)
+    }
+}
+
+/// Write a finished competition to `rembrandt_dir`'s history
+pub fn archive_competition(
+    rembrandt_dir: &Path,
+    competition: &CompetitionGroup,
+) -> Result<PathBuf> {
+    let dir = rembrandt_dir.join(HISTORY_DIR);
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}.json", competition.id));
+    let mut competition = competition.clone();
+    competition.completed_at.get_or_insert_with(Utc::now);
+    let json = serde_json::to_string_pretty(&competition).map_err(|e| {
+        RembrandtError::Competition(format!("Failed to serialize competition: {}", e))
+    })?;
+    std::fs::write(&path, json)?;
+    Ok(path)
+}
+
+/// Finished competitions in `rembrandt_dir`'s history, newest first
+///
+/// Files that can't be read are logged and skipped.
+pub fn load_history(rembrandt_dir: &Path) -> Result<Vec<CompetitionGroup>> {
+    let dir = rembrandt_dir.join(HISTORY_DIR);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut history = Vec::new();
+    for entry in std::fs::read_dir(&dir)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != "json") {
+            continue;
+        }
+        let parsed = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|json| {
+                serde_json::from_str::<CompetitionGroup>(&json).map_err(|e| e.to_string())
+            });
+        match parsed {
+            Ok(competition) => history.push(competition),
+            Err(e) => tracing::warn!(path = %path.display(), "skipping competition record: {}", e),
+        }
+    }
+    history.sort_by_key(|c| std::cmp::Reverse(c.started_at));
+    Ok(history)
+}
+
+/// How one agent type did in decided competitions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgentRecord {
+    pub agent: String,
+    /// Decided competitions the agent type took part in
+    pub entered: usize,
+    pub wins: usize,
+}
+
+impl AgentRecord {
+    /// Wins as a share of competitions entered, 0.0-1.0
+    pub fn win_rate(&self) -> f64 {
+        if self.entered == 0 {
+            0.0
+        } else {
+            self.wins as f64 / self.entered as f64
+        }
+    }
+}
+
+/// Win totals across a competition history
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HistoryStats {
+    /// Competitions in the history
+    pub total: usize,
+    /// Competitions that picked a winner
+    pub decided: usize,
+    /// Per agent type, most wins first
+    pub agents: Vec<AgentRecord>,
+    /// Per task kind, each with its agent records
+    pub by_kind: Vec<(TaskKind, Vec<AgentRecord>)>,
+}
+
+/// Total wins per agent type in `history`, overall and per task kind
+///
+/// Only competitions with a winner count; an agent type entered several
+/// times in one competition counts once.
+pub fn history_stats(history: &[CompetitionGroup]) -> HistoryStats {
+    let decided: Vec<(&CompetitionGroup, &CompetitorSolution)> = history
+        .iter()
+        .filter(|c| {
+            !matches!(
+                c.status,
+                CompetitionStatus::Failed(_) | CompetitionStatus::Cancelled
+            )
+        })
+        .filter_map(|c| Some((c, c.winning_solution()?)))
+        .collect();
+
+    let mut kinds: Vec<TaskKind> = decided
+        .iter()
+        .map(|(c, _)| TaskKind::classify(&c.prompt))
+        .collect();
+    kinds.sort();
+    kinds.dedup();
+
+    HistoryStats {
+        total: history.len(),
+        decided: decided.len(),
+        agents: agent_records(decided.iter().copied()),
+        by_kind: kinds
+            .into_iter()
+            .map(|kind| {
+                let records = agent_records(
+                    decided
+                        .iter()
+                        .copied()
+                        .filter(|(c, _)| TaskKind::classify(&c.prompt) == kind),
+                );
+                (kind, records)
+            })
+            .collect(),
+    }
+}
+
+fn agent_records<'a>(
+    decided: impl Iterator<Item = (&'a CompetitionGroup, &'a CompetitorSolution)>,
+) -> Vec<AgentRecord> {
+    let mut records: Vec<AgentRecord> = Vec::new();
+    for (competition, winner) in decided {
+        let mut entered: Vec<String> = competition
+            .competitors
+            .iter()
+            .map(|c| c.agent_type.to_string())
+            .collect();
+        entered.sort();
+        entered.dedup();
+        for agent in entered {
+            let won = agent == winner.agent_type.to_string();
+            match records.iter_mut().find(|r| r.agent == agent) {
+                Some(record) => {
+                    record.entered += 1;
+                    record.wins += won as usize;
+                }
+                None => records.push(AgentRecord {
+                    agent,
+                    entered: 1,
+                    wins: won as usize,
+                }),
+            }
+        }
+    }
+    records.sort_by(|a, b| b.wins.cmp(&a.wins).then_with(|| a.agent.cmp(&b.agent)));
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::AgentType;
+    use crate::competition::EvaluatorStrategy;
+
+    fn competition(prompt: &str, agents: &[AgentType], winner: Option<usize>) -> CompetitionGroup {
+        let mut competition =
+            CompetitionGroup::new(prompt.to_string(), EvaluatorStrategy::default(), 30);
+        for (i, agent_type) in agents.iter().enumerate() {
+            competition.competitors.push(CompetitorSolution {
+                agent_id: format!("{}-{}", competition.id, i),
+                agent_type: agent_type.clone(),
+                variant: Default::default(),
+                branch: String::new(),
+                worktree_path: PathBuf::new(),
+                completed_at: None,
+                validation: None,
+                diff_stats: None,
+            });
+        }
+        competition.winner = winner.map(|i| competition.competitors[i].agent_id.clone());
+        competition.status = match &competition.winner {
+            Some(winner_id) => CompetitionStatus::Completed {
+                winner_id: winner_id.clone(),
+            },
+            None => CompetitionStatus::Failed("no valid solutions".to_string()),
+        };
+        competition
+    }
+
+    #[test]
+    fn history_round_trips_and_totals_wins_per_task_kind() {
+        let dir = tempfile::tempdir().unwrap();
+        let both = [AgentType::ClaudeCode, AgentType::Codex];
+        let mut history = [
+            competition("Refactor the parser into modules", &both, Some(0)),
+            competition("Extract a trait for runtimes", &both, Some(0)),
+            competition("Refactor config loading", &both, Some(1)),
+            competition("Fix the crash on empty input", &both, Some(1)),
+            competition("Fix flaky test", &both, None),
+        ];
+        for (i, competition) in history.iter_mut().enumerate() {
+            competition.id = format!("comp-{}", i);
+            competition.started_at += Duration::seconds(i as i64);
+            archive_competition(dir.path(), competition).unwrap();
+        }
+
+        let loaded = load_history(dir.path()).unwrap();
+        assert_eq!(loaded.len(), 5);
+        assert_eq!(loaded[0].id, "comp-4", "newest first");
+        assert!(loaded.iter().all(|c| c.duration().is_some()));
+        assert_eq!(
+            loaded[4].winning_solution().unwrap().agent_type,
+            AgentType::ClaudeCode
+        );
+
+        let stats = history_stats(&loaded);
+        assert_eq!((stats.total, stats.decided), (5, 4));
+        assert_eq!(stats.agents[0].agent, "claude-code");
+        assert_eq!((stats.agents[0].wins, stats.agents[0].entered), (2, 4));
+
+        let (kind, refactors) = &stats.by_kind[0];
+        assert_eq!(*kind, TaskKind::Refactor);
+        assert_eq!(
+            refactors[0],
+            AgentRecord {
+                agent: "claude-code".to_string(),
+                entered: 3,
+                wins: 2
+            }
+        );
+        assert!((refactors[0].win_rate() - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(stats.by_kind[1].0, TaskKind::Bugfix);
+
+        assert_eq!(
+            TaskKind::classify("Make startup faster"),
+            TaskKind::Performance
+        );
+        assert_eq!(TaskKind::classify("Add a --json flag"), TaskKind::Feature);
+        assert_eq!(TaskKind::classify("Tidy"), TaskKind::Other);
+    }
+}
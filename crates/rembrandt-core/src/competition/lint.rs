@@ -0,0 +1,167 @@
+//! Lint and formatting checks run alongside type checks and tests
+//!
+//! Each check is a shell command run in the solution's worktree. A few
+//! common ones are built in by name; anything else needs a `command`.
+//! Failing checks with `block` severity fail validation (and so the merge
+//! gate); `warn` checks are reported but don't.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+
+/// Built-in checks: name, command, and the file marking projects they apply to
+const BUILTIN_CHECKS: &[(&str, &str, Option<&str>)] = &[
+    ("clippy", "cargo clippy --all-targets -- -D warnings", Some("Cargo.toml")),
+    ("rustfmt", "cargo fmt --check", Some("Cargo.toml")),
+    ("eslint", "npx eslint .", Some("package.json")),
+    ("prettier", "npx prettier --check .", Some("package.json")),
+    ("ruff", "ruff check .", None),
+    ("ruff-format", "ruff format --check .", None),
+];
+
+/// Lines of output kept per check
+const LINT_OUTPUT_LINES: usize = 40;
+
+/// What a failing check does to validation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintSeverity {
+    /// Fail validation
+    #[default]
+    Block,
+    /// Report only
+    Warn,
+}
+
+/// A configured lint or formatting check
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LintCheck {
+    /// A built-in check (`clippy`, `rustfmt`, `eslint`, `prettier`, `ruff`,
+    /// `ruff-format`) or a label for `command`
+    pub name: String,
+    /// Shell command to run instead of the built-in one
+    #[serde(default)]
+    pub command: Option<String>,
+    #[serde(default)]
+    pub severity: LintSeverity,
+}
+
+/// Outcome of one lint check
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LintResult {
+    pub name: String,
+    pub severity: LintSeverity,
+    pub passed: bool,
+    /// Last lines of the check's output
+    pub output: Option<String>,
+}
+
+impl LintResult {
+    /// Whether this result fails validation
+    pub fn blocks(&self) -> bool {
+        !self.passed && self.severity == LintSeverity::Block
+    }
+}
+
+impl LintCheck {
+    pub fn new(name: impl Into<String>, severity: LintSeverity) -> Self {
+        Self {
+            name: name.into(),
+            command: None,
+            severity,
+        }
+    }
+
+    pub fn with_command(mut self, command: impl Into<String>) -> Self {
+        self.command = Some(command.into());
+        self
+    }
+
+    fn builtin(&self) -> Option<&'static (&'static str, &'static str, Option<&'static str>)> {
+        BUILTIN_CHECKS.iter().find(|(name, _, _)| *name == self.name)
+    }
+
+    /// The command to run, `None` for an unknown name without a command
+    pub fn command(&self) -> Option<&str> {
+        self.command
+            .as_deref()
+            .or_else(|| self.builtin().map(|(_, command, _)| *command))
+    }
+
+    /// Whether the check applies to the project in `worktree`; built-ins
+    /// for another ecosystem (clippy in a Node.js project) are skipped
+    pub fn applies_to(&self, worktree: &Path) -> bool {
+        match (self.command.is_some(), self.builtin()) {
+            (false, Some((_, _, Some(marker)))) => worktree.join(marker).exists(),
+            _ => true,
+        }
+    }
+
+    /// Run the check in `worktree`
+    pub fn run(&self, worktree: &Path) -> LintResult {
+        let result = |passed: bool, output: String| LintResult {
+            name: self.name.clone(),
+            severity: self.severity,
+            passed,
+            output: Some(output),
+        };
+        let Some(command) = self.command() else {
+            return result(
+                false,
+                format!("Unknown lint check '{}'; give it a command", self.name),
+            );
+        };
+        match Command::new("sh").args(["-c", command]).current_dir(worktree).output() {
+            Ok(output) => {
+                let combined = format!(
+                    "{}{}",
+                    String::from_utf8_lossy(&output.stdout),
+                    String::from_utf8_lossy(&output.stderr)
+                );
+                let lines: Vec<&str> = combined.trim_end().lines().collect();
+                let tail = lines[lines.len().saturating_sub(LINT_OUTPUT_LINES)..].join("\n");
+                result(output.status.success(), tail)
+            }
+            Err(e) => result(false, format!("Failed to run {}: {}", command, e)),
+        }
+    }
+}
+
+/// Run the `checks` that apply to `worktree`, in order
+pub fn run_lint_checks(checks: &[LintCheck], worktree: &Path) -> Vec<LintResult> {
+    checks
+        .iter()
+        .filter(|check| check.applies_to(worktree))
+        .map(|check| check.run(worktree))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_apply_by_project_and_severity_decides_blocking() {
+        let dir = tempfile::tempdir().unwrap();
+        let checks = vec![
+            LintCheck::new("clippy", LintSeverity::Block),
+            LintCheck::new("style", LintSeverity::Warn).with_command("echo trailing space; exit 1"),
+            LintCheck::new("docs", LintSeverity::Block).with_command("true"),
+            LintCheck::new("mypy", LintSeverity::Block),
+        ];
+        assert_eq!(checks[0].command(), Some("cargo clippy --all-targets -- -D warnings"));
+
+        // No Cargo.toml, so clippy is skipped
+        let results = run_lint_checks(&checks, dir.path());
+        let names: Vec<&str> = results.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["style", "docs", "mypy"]);
+
+        assert!(!results[0].passed && !results[0].blocks());
+        assert_eq!(results[0].output.as_deref(), Some("trailing space"));
+        assert!(results[1].passed && !results[1].blocks());
+        assert!(results[2].blocks(), "unknown checks without a command fail");
+
+        std::fs::write(dir.path().join("Cargo.toml"), "").unwrap();
+        assert!(checks[0].applies_to(dir.path()));
+    }
+}
@@ -0,0 +1,847 @@
+//! CompetitionManager - orchestrates competition lifecycle
+
+use crate::agent::{AgentRegistry, AgentSession, AgentStatus};
+use crate::competition::decision::record_decision;
+use crate::competition::{
+    archive_competition, competitor_ids, create_evaluator, Benchmark, CompetitionGroup,
+    CompetitionId, CompetitionRound, CompetitionStatus, CompetitorSolution, CompetitorSpec,
+    EarlyStopPolicy, EvaluatorStrategy, FollowUp, LintCheck, MetricWeights, MetricsEvaluator,
+    SecurityConfig, SolutionValidator, ValidationResult,
+};
+use crate::worktree::{CopyRules, WorktreeManager};
+use crate::Result;
+use chrono::Utc;
+use tracing::Instrument;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Manages competition lifecycle and state
+pub struct CompetitionManager {
+    /// Path to the repository
+    repo_path: PathBuf,
+    /// Worktree manager for creating agent worktrees
+    worktree_manager: WorktreeManager,
+    /// In-memory storage of active competitions
+    competitions: HashMap<CompetitionId, CompetitionGroup>,
+    /// Base branch for worktrees
+    base_branch: String,
+    /// Lint and formatting checks solutions are validated with
+    lint_checks: Vec<LintCheck>,
+    /// Security scanners solutions are validated with
+    security: SecurityConfig,
+}
+
+impl CompetitionManager {
+    /// Create a new competition manager
+    pub fn new(repo_path: PathBuf, base_branch: String) -> Result<Self> {
+        let worktree_manager = WorktreeManager::new(&repo_path)?;
+        Ok(Self {
+            repo_path,
+            worktree_manager,
+            competitions: HashMap::new(),
+            base_branch,
+            lint_checks: Vec::new(),
+            security: SecurityConfig::default(),
+        })
+    }
+
+    /// Propagate untracked files matching `rules` into competitors' worktrees
+    pub fn with_copy_rules(mut self, rules: CopyRules) -> Self {
+        self.worktree_manager = self.worktree_manager.with_copy_rules(rules);
+        self
+    }
+
+    /// Create competitors' worktrees in `dir` instead of `.rembrandt/agents`
+    pub fn with_worktrees_dir(mut self, dir: impl AsRef<std::path::Path>) -> Self {
+        self.worktree_manager = self.worktree_manager.with_worktrees_dir(dir);
+        self
+    }
+
+    /// Validate solutions with these lint and formatting checks too
+    pub fn with_lint_checks(mut self, checks: Vec<LintCheck>) -> Self {
+        self.lint_checks = checks;
+        self
+    }
+
+    /// Scan solutions' changes with these security scanners too
+    pub fn with_security(mut self, security: SecurityConfig) -> Self {
+        self.security = security;
+        self
+    }
+
+    fn validator(&self) -> SolutionValidator {
+        SolutionValidator::new(self.base_branch.clone())
+            .with_lint_checks(self.lint_checks.clone())
+            .with_security(self.security.clone())
+    }
+
+    /// Start a new competition
+    #[tracing::instrument(skip_all, fields(competition_id = tracing::field::Empty))]
+    pub async fn start_competition(
+        &mut self,
+        prompt: String,
+        competitors: impl IntoIterator<Item = impl Into<CompetitorSpec>>,
+        evaluator_strategy: EvaluatorStrategy,
+        timeout_minutes: u64,
+        registry: &mut AgentRegistry,
+    ) -> Result<CompetitionId> {
+        // Create competition group
+        let mut competition = CompetitionGroup::new(prompt.clone(), evaluator_strategy, timeout_minutes);
+        let competition_id = competition.id.clone();
+        tracing::Span::current().record("competition_id", competition_id.as_str());
+
+        // Spawn each agent
+        let specs: Vec<CompetitorSpec> = competitors.into_iter().map(Into::into).collect();
+        let agent_ids = competitor_ids(&competition_id, &specs);
+        for (CompetitorSpec { agent_type, variant }, agent_id) in specs.into_iter().zip(agent_ids) {
+
+            // Create worktree for this agent
+            let worktree_info = self
+                .worktree_manager
+                .create_worktree(&agent_id, &self.base_branch)?;
+
+            // Create agent session
+            let session = AgentSession {
+                id: agent_id.clone(),
+                agent_type: agent_type.clone(),
+                status: AgentStatus::Active,
+                worktree_path: worktree_info.path.clone(),
+                branch: worktree_info.branch.clone(),
+                task_id: None,
+                pid: None,
+                reserved_files: Vec::new(),
+                started_at: Utc::now(),
+                competition_id: Some(competition_id.clone()),
+            };
+            registry.register_session(session);
+
+            // Add competitor to competition
+            competition.competitors.push(CompetitorSolution {
+                agent_id,
+                agent_type,
+                variant,
+                branch: worktree_info.branch,
+                worktree_path: worktree_info.path,
+                completed_at: None,
+                validation: None,
+                diff_stats: None,
+            });
+
+            // TODO: Actually spawn the agent process with the prompt
+            // self.spawn_agent_with_prompt(&agent_id, &prompt)?;
+        }
+
+        // Update status to running
+        competition.status = CompetitionStatus::Running {
+            completed: 0,
+            total: competition.competitors.len(),
+        };
+
+        // Store competition
+        self.competitions.insert(competition_id.clone(), competition);
+
+        Ok(competition_id)
+    }
+
+    /// Enable (or with `None`, disable) early stop for a competition
+    pub fn set_early_stop(
+        &mut self,
+        competition_id: &str,
+        policy: Option<EarlyStopPolicy>,
+    ) -> Result<()> {
+        let competition = self.competitions.get_mut(competition_id).ok_or_else(|| {
+            crate::RembrandtError::Competition(format!(
+                "Competition not found: {}",
+                competition_id
+            ))
+        })?;
+        competition.early_stop = policy;
+        Ok(())
+    }
+
+    /// Record a Porque decision when a person picks the competition's winner,
+    /// through a human evaluator stage or `select_winner`
+    pub fn set_record_decision(&mut self, competition_id: &str, record: bool) -> Result<()> {
+        let competition = self.competitions.get_mut(competition_id).ok_or_else(|| {
+            crate::RembrandtError::Competition(format!(
+                "Competition not found: {}",
+                competition_id
+            ))
+        })?;
+        competition.record_decision = record;
+        Ok(())
+    }
+
+    /// Measure `benchmark` on each valid solution and weigh it in metrics
+    /// evaluation (with the benchmark's weight), for performance tasks
+    pub fn set_benchmark(&mut self, competition_id: &str, benchmark: Option<Benchmark>) -> Result<()> {
+        let competition = self.competitions.get_mut(competition_id).ok_or_else(|| {
+            crate::RembrandtError::Competition(format!(
+                "Competition not found: {}",
+                competition_id
+            ))
+        })?;
+        let weight = benchmark.as_ref().map_or(0.0, |b| b.weight);
+        competition.evaluator_strategy.set_benchmark_weight(weight);
+        competition.benchmark = benchmark;
+        Ok(())
+    }
+
+    /// Allow up to `rounds` re-prompting rounds if every solution fails validation
+    pub fn set_retry_rounds(&mut self, competition_id: &str, rounds: usize) -> Result<()> {
+        let competition = self.competitions.get_mut(competition_id).ok_or_else(|| {
+            crate::RembrandtError::Competition(format!(
+                "Competition not found: {}",
+                competition_id
+            ))
+        })?;
+        competition.max_retry_rounds = rounds;
+        Ok(())
+    }
+
+    /// Follow-up prompts of the current retry round not yet handed out
+    ///
+    /// The caller delivers each one to its agent (in the competitor's
+    /// worktree); they are marked sent and not returned again.
+    pub fn take_follow_ups(&mut self, competition_id: &str) -> Vec<FollowUp> {
+        let Some(round) = self
+            .competitions
+            .get_mut(competition_id)
+            .and_then(|c| c.retry_rounds.last_mut())
+        else {
+            return Vec::new();
+        };
+        let now = Utc::now();
+        round
+            .follow_ups
+            .iter_mut()
+            .filter(|f| f.sent_at.is_none())
+            .map(|f| {
+                f.sent_at = Some(now);
+                f.clone()
+            })
+            .collect()
+    }
+
+    /// Update competition status based on agent states
+    ///
+    /// Takes the registry mutably because starting a retry round puts the
+    /// re-prompted agents back to active.
+    pub async fn update_competition(
+        &mut self,
+        competition_id: &str,
+        registry: &mut AgentRegistry,
+    ) -> Result<CompetitionStatus> {
+        // First, check what state we're in
+        let current_status = {
+            let competition = self
+                .competitions
+                .get(competition_id)
+                .ok_or_else(|| {
+                    crate::RembrandtError::Competition(format!(
+                        "Competition not found: {}",
+                        competition_id
+                    ))
+                })?;
+            competition.status.clone()
+        };
+
+        let status = match current_status {
+            CompetitionStatus::Running { .. } => {
+                self.update_running_competition(competition_id, registry).await?
+            }
+            CompetitionStatus::Evaluating => {
+                self.run_evaluation(competition_id, registry).await?
+            }
+            CompetitionStatus::Merging => {
+                // Merge is handled separately
+                current_status
+            }
+            _ => return Ok(current_status),
+        };
+        if status.is_terminal() {
+            self.archive(competition_id);
+        }
+        Ok(status)
+    }
+
+    /// Update a running competition - check for completions and timeout
+    async fn update_running_competition(
+        &mut self,
+        competition_id: &str,
+        registry: &AgentRegistry,
+    ) -> Result<CompetitionStatus> {
+        let validator = self.validator();
+        let competition = self
+            .competitions
+            .get_mut(competition_id)
+            .ok_or_else(|| {
+                crate::RembrandtError::Competition(format!(
+                    "Competition not found: {}",
+                    competition_id
+                ))
+            })?;
+        let validator = validator.with_benchmark(competition.benchmark.clone());
+
+        let mut completed = 0;
+        let total = competition.competitors.len();
+
+        // Check each competitor's status
+        for competitor in &mut competition.competitors {
+            if let Some(session) = registry.get_session(&competitor.agent_id) {
+                match &session.status {
+                    AgentStatus::Completed => {
+                        if competitor.completed_at.is_none() {
+                            competitor.completed_at = Some(Utc::now());
+                        }
+                        completed += 1;
+                    }
+                    AgentStatus::Failed(_) | AgentStatus::Stopped => {
+                        // Mark as completed but with no valid solution
+                        if competitor.completed_at.is_none() {
+                            competitor.completed_at = Some(Utc::now());
+                        }
+                        completed += 1;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if let Some(policy) = competition.early_stop.clone()
+            && competition.early_stop_at.is_none()
+        {
+            let weights = match &competition.evaluator_strategy {
+                EvaluatorStrategy::Metrics(weights) => weights.clone(),
+                _ => MetricWeights::default(),
+            };
+            let scorer = MetricsEvaluator::new(weights);
+            for competitor in &mut competition.competitors {
+                let finished = registry
+                    .get_session(&competitor.agent_id)
+                    .is_some_and(|s| s.status == AgentStatus::Completed);
+                if !finished || competitor.validation.is_some() {
+                    continue;
+                }
+                validate_competitor(&validator, competitor).await;
+                let score = scorer.score(competitor);
+                if competitor.is_valid() && score >= policy.min_score {
+                    tracing::info!(
+                        competition_id,
+                        leader = %competitor.agent_id,
+                        score,
+                        "Early stop triggered"
+                    );
+                    competition.early_stop_at =
+                        Some(Utc::now() + chrono::Duration::minutes(policy.grace_minutes as i64));
+                    break;
+                }
+            }
+        }
+
+        // Early stop: once the grace period is over, cut off whoever is still running
+        let stop_early = competition.early_stop_at.is_some_and(|at| Utc::now() >= at);
+        if stop_early && completed < total {
+            competition.stopped_early = competition
+                .competitors
+                .iter()
+                .filter(|c| c.completed_at.is_none())
+                .map(|c| c.agent_id.clone())
+                .collect();
+        }
+
+        // Check for timeout, early stop or all complete
+        let timed_out = competition.is_timed_out();
+        let all_complete = completed == total;
+
+        if all_complete || timed_out || stop_early {
+            if completed == 0 {
+                competition.status = CompetitionStatus::Failed(
+                    "No agents completed before timeout".to_string(),
+                );
+            } else {
+                competition.status = CompetitionStatus::Evaluating;
+            }
+        } else {
+            competition.status = CompetitionStatus::Running { completed, total };
+        }
+
+        Ok(competition.status.clone())
+    }
+
+    /// Run evaluation on completed solutions
+    #[tracing::instrument(skip(self, registry))]
+    async fn run_evaluation(
+        &mut self,
+        competition_id: &str,
+        registry: &mut AgentRegistry,
+    ) -> Result<CompetitionStatus> {
+        // Validate each completed solution
+        let validator = self.validator();
+
+        let competition = self
+            .competitions
+            .get_mut(competition_id)
+            .ok_or_else(|| {
+                crate::RembrandtError::Competition(format!(
+                    "Competition not found: {}",
+                    competition_id
+                ))
+            })?;
+        let validator = validator.with_benchmark(competition.benchmark.clone());
+
+        for competitor in &mut competition.competitors {
+            if competitor.completed_at.is_some() && competitor.validation.is_none() {
+                validate_competitor(&validator, competitor).await;
+            }
+        }
+
+        // Get valid solutions
+        let valid_solutions = competition.valid_solutions();
+
+        if valid_solutions.is_empty() && competition.retry_rounds.len() < competition.max_retry_rounds {
+            start_retry_round(competition, registry);
+            return Ok(competition.status.clone());
+        }
+
+        if valid_solutions.is_empty() {
+            competition.status = CompetitionStatus::Failed(
+                "No solutions passed validation".to_string(),
+            );
+            return Ok(competition.status.clone());
+        }
+
+        // Run evaluator
+        let evaluator = create_evaluator(&competition.evaluator_strategy);
+        let prompt = competition.prompt.clone();
+        let repo_path = self.repo_path.clone();
+
+        match evaluator
+            .evaluate(&prompt, &valid_solutions, &repo_path)
+            .instrument(tracing::info_span!(
+                "evaluate",
+                competition_id,
+                candidates = valid_solutions.len()
+            ))
+            .await
+        {
+            Ok(result) => {
+                // A human stage only proposes; the decision is recorded once
+                // a person picks or merges
+                competition.winner = Some(result.winner_id.clone());
+                competition.evaluation_result = Some(result);
+                competition.status = CompetitionStatus::Merging;
+            }
+            Err(e) => {
+                competition.status = CompetitionStatus::Failed(format!(
+                    "Evaluation failed: {}",
+                    e
+                ));
+            }
+        }
+
+        Ok(competition.status.clone())
+    }
+
+    /// Get a competition by ID
+    pub fn get_competition(&self, id: &str) -> Option<&CompetitionGroup> {
+        self.competitions.get(id)
+    }
+
+    /// Get mutable reference to a competition
+    pub fn get_competition_mut(&mut self, id: &str) -> Option<&mut CompetitionGroup> {
+        self.competitions.get_mut(id)
+    }
+
+    /// List all competitions
+    pub fn list_competitions(&self) -> Vec<&CompetitionGroup> {
+        self.competitions.values().collect()
+    }
+
+    /// Get active (non-terminal) competitions
+    pub fn active_competitions(&self) -> Vec<&CompetitionGroup> {
+        self.competitions
+            .values()
+            .filter(|c| !c.status.is_terminal())
+            .collect()
+    }
+
+    /// Cancel a competition
+    pub fn cancel_competition(
+        &mut self,
+        competition_id: &str,
+        registry: &mut AgentRegistry,
+    ) -> Result<()> {
+        let competition = self
+            .competitions
+            .get_mut(competition_id)
+            .ok_or_else(|| {
+                crate::RembrandtError::Competition(format!(
+                    "Competition not found: {}",
+                    competition_id
+                ))
+            })?;
+
+        if competition.status.is_terminal() {
+            return Err(crate::RembrandtError::Competition(
+                "Cannot cancel a completed competition".to_string(),
+            ));
+        }
+
+        // Stop all agents
+        for competitor in &competition.competitors {
+            if let Some(_session) = registry.get_session(&competitor.agent_id) {
+                let _ = registry.update_status(&competitor.agent_id, AgentStatus::Stopped);
+                // TODO: Actually kill the agent process
+            }
+        }
+
+        competition.status = CompetitionStatus::Cancelled;
+        competition.completed_at = Some(Utc::now());
+        self.archive(competition_id);
+
+        Ok(())
+    }
+
+    /// Cleanup after a competition (remove losing worktrees)
+    pub fn cleanup_competition(&mut self, competition_id: &str) -> Result<()> {
+        let competition = self.competitions.get(competition_id).ok_or_else(|| {
+            crate::RembrandtError::Competition(format!(
+                "Competition not found: {}",
+                competition_id
+            ))
+        })?;
+
+        let winner_id = competition.winner.as_ref();
+
+        for competitor in &competition.competitors {
+            // Skip the winner
+            if winner_id == Some(&competitor.agent_id) {
+                continue;
+            }
+
+            // Remove the worktree
+            if let Err(e) = self.worktree_manager.remove_worktree(&competitor.agent_id) {
+                eprintln!(
+                    "Warning: Failed to remove worktree for {}: {}",
+                    competitor.agent_id, e
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Override the evaluator's pick with `agent_id`, a solution that passed
+    /// validation, giving `rationale` for the choice
+    ///
+    /// Only valid once evaluation has picked a winner and before the merge.
+    pub fn select_winner(&mut self, competition_id: &str, agent_id: &str, rationale: &str) -> Result<()> {
+        let repo_path = self.repo_path.clone();
+        let competition = self.competitions.get_mut(competition_id).ok_or_else(|| {
+            crate::RembrandtError::Competition(format!(
+                "Competition not found: {}",
+                competition_id
+            ))
+        })?;
+
+        if competition.status != CompetitionStatus::Merging {
+            return Err(crate::RembrandtError::Competition(format!(
+                "Competition {} has no winner to override yet",
+                competition_id
+            )));
+        }
+        if !competition.competitors.iter().any(|c| c.agent_id == agent_id && c.is_valid()) {
+            return Err(crate::RembrandtError::Competition(format!(
+                "{} has no valid solution in competition {}",
+                agent_id, competition_id
+            )));
+        }
+
+        let previous = competition.winner.replace(agent_id.to_string());
+        if let Some(result) = competition.evaluation_result.as_mut() {
+            result.winner_id = agent_id.to_string();
+            result.reasoning = format!(
+                "Overridden by a person (evaluator picked {}): {}",
+                previous.as_deref().unwrap_or("none"),
+                rationale
+            );
+        }
+        tracing::info!(competition_id, winner = agent_id, previous = ?previous, "Winner overridden");
+        record_decision(competition, &repo_path, "a person", rationale);
+        Ok(())
+    }
+
+    /// Merge the winning branch into the base branch and finish the competition
+    ///
+    /// Only valid once evaluation has picked a winner. Losing worktrees are
+    /// cleaned up after the merge lands. Returns the new base branch tip.
+    #[tracing::instrument(skip(self))]
+    pub fn merge_winner(&mut self, competition_id: &str) -> Result<String> {
+        let competition = self.competitions.get(competition_id).ok_or_else(|| {
+            crate::RembrandtError::Competition(format!(
+                "Competition not found: {}",
+                competition_id
+            ))
+        })?;
+
+        if competition.status != CompetitionStatus::Merging {
+            return Err(crate::RembrandtError::Competition(format!(
+                "Competition {} is not ready to merge",
+                competition_id
+            )));
+        }
+
+        let winner = competition
+            .winner
+            .as_ref()
+            .and_then(|id| competition.competitors.iter().find(|c| &c.agent_id == id))
+            .ok_or_else(|| {
+                crate::RembrandtError::Competition("No winner selected".to_string())
+            })?;
+
+        let tip = self
+            .worktree_manager
+            .merge_branch(&winner.branch, &self.base_branch)?;
+
+        // Merging a human stage's proposal is the person's pick
+        if let Some(competition) = self.competitions.get_mut(competition_id)
+            && competition.evaluator_strategy.involves_human()
+            && competition.decision_id.is_none()
+        {
+            record_decision(competition, &self.repo_path, "a person", "merged the proposed winner");
+        }
+        self.cleanup_competition(competition_id)?;
+        self.complete_competition(competition_id)?;
+
+        Ok(tip.to_string())
+    }
+
+    /// Mark competition as completed after successful merge
+    pub fn complete_competition(&mut self, competition_id: &str) -> Result<()> {
+        let competition = self
+            .competitions
+            .get_mut(competition_id)
+            .ok_or_else(|| {
+                crate::RembrandtError::Competition(format!(
+                    "Competition not found: {}",
+                    competition_id
+                ))
+            })?;
+
+        if let Some(winner_id) = competition.winner.clone() {
+            competition.status = CompetitionStatus::Completed { winner_id };
+            competition.completed_at = Some(Utc::now());
+            self.archive(competition_id);
+        }
+
+        Ok(())
+    }
+
+    /// Keep a finished competition in the history; failures are logged
+    fn archive(&self, competition_id: &str) {
+        let Some(competition) = self.competitions.get(competition_id) else {
+            return;
+        };
+        if let Err(e) = archive_competition(self.worktree_manager.rembrandt_dir(), competition) {
+            tracing::warn!(competition_id, "failed to archive competition: {}", e);
+        }
+    }
+}
+
+/// Re-prompt every competitor whose solution failed validation with its own
+/// failures, and run the competition again with a fresh timeout
+fn start_retry_round(competition: &mut CompetitionGroup, registry: &mut AgentRegistry) {
+    let timeout = competition.timeout_at - competition.started_at;
+    let now = Utc::now();
+    let mut follow_ups = Vec::new();
+
+    for competitor in &mut competition.competitors {
+        let Some(validation) = competitor.validation.take() else {
+            continue;
+        };
+        follow_ups.push(FollowUp {
+            agent_id: competitor.agent_id.clone(),
+            prompt: follow_up_prompt(&competition.prompt, &validation),
+            validation,
+            sent_at: None,
+        });
+        competitor.completed_at = None;
+        competitor.diff_stats = None;
+        let _ = registry.update_status(&competitor.agent_id, AgentStatus::Active);
+    }
+
+    let round = CompetitionRound {
+        number: competition.round() + 1,
+        started_at: now,
+        follow_ups,
+    };
+    tracing::info!(
+        competition_id = %competition.id,
+        round = round.number,
+        agents = round.follow_ups.len(),
+        "No solution passed validation; starting a retry round"
+    );
+    competition.retry_rounds.push(round);
+    competition.timeout_at = now + timeout;
+    competition.status = CompetitionStatus::Running {
+        completed: 0,
+        total: competition.competitors.len(),
+    };
+}
+
+/// Follow-up prompt telling an agent why its solution was rejected
+fn follow_up_prompt(task: &str, validation: &ValidationResult) -> String {
+    format!(
+        "Your solution to this task did not pass validation.\n\nTask: {}\n\n{}\
+         Fix these problems in the same worktree, make sure the checks pass, and commit.",
+        task,
+        validation.failure_summary()
+    )
+}
+
+/// Validate a finished solution and record its diff stats
+async fn validate_competitor(validator: &SolutionValidator, competitor: &mut CompetitorSolution) {
+    match validator.validate(competitor).await {
+        Ok(result) => {
+            competitor.validation = Some(result);
+        }
+        Err(e) => {
+            competitor.validation = Some(crate::competition::ValidationResult {
+                agent_id: competitor.agent_id.clone(),
+                type_check_passed: false,
+                type_check_output: None,
+                tests_passed: false,
+                tests_output: None,
+                test_count: None,
+                test_failures: None,
+                failed_tests: Vec::new(),
+                test_duration_ms: None,
+                lint_results: Vec::new(),
+                security: None,
+                benchmark: None,
+                validation_time_ms: 0,
+                error_message: Some(e.to_string()),
+            });
+        }
+    }
+
+    if let Ok(stats) = validator.calculate_diff_stats(competitor) {
+        competitor.diff_stats = Some(stats);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::AgentType;
+    use git2::{Repository, Signature};
+    use std::path::Path;
+
+    #[tokio::test]
+    async fn early_stop_cuts_off_competitors_still_running() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let sig = Signature::now("test", "test@example.com").unwrap();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+        repo.commit(Some("refs/heads/main"), &sig, &sig, "init", &tree, &[]).unwrap();
+
+        let mut manager = CompetitionManager::new(dir.path().to_path_buf(), "main".to_string()).unwrap();
+        let mut registry = AgentRegistry::new();
+        let id = manager
+            .start_competition(
+                "task".to_string(),
+                vec![AgentType::Custom("fast".to_string()), AgentType::Custom("slow".to_string())],
+                EvaluatorStrategy::default(),
+                60,
+                &mut registry,
+            )
+            .await
+            .unwrap();
+        manager
+            .set_early_stop(&id, Some(EarlyStopPolicy { min_score: 0.5, grace_minutes: 0 }))
+            .unwrap();
+
+        let fast = format!("{}-fast", id);
+        let slow = format!("{}-slow", id);
+        let status = manager.update_competition(&id, &mut registry).await.unwrap();
+        assert_eq!(status, CompetitionStatus::Running { completed: 0, total: 2 });
+
+        // The fast agent's (empty) solution validates and scores above the bar
+        registry.update_status(&fast, AgentStatus::Completed).unwrap();
+        let status = manager.update_competition(&id, &mut registry).await.unwrap();
+        assert_eq!(status, CompetitionStatus::Evaluating);
+        let competition = manager.get_competition(&id).unwrap();
+        assert_eq!(competition.stopped_early, vec![slow]);
+        assert!(competition.competitors[0].is_valid());
+
+        let status = manager.update_competition(&id, &mut registry).await.unwrap();
+        assert_eq!(status, CompetitionStatus::Merging);
+        assert_eq!(manager.get_competition(&id).unwrap().winner, Some(fast.clone()));
+
+        // The cut-off agent never validated, so it can't be picked instead
+        let slow = format!("{}-slow", id);
+        assert!(manager.select_winner(&id, &slow, "prefer its approach").is_err());
+        manager.select_winner(&id, &fast, "smallest diff").unwrap();
+        let result = manager.get_competition(&id).unwrap().evaluation_result.clone().unwrap();
+        assert!(result.reasoning.ends_with(": smallest diff"), "{}", result.reasoning);
+    }
+
+    #[tokio::test]
+    async fn failed_solutions_get_a_retry_round_with_their_failures() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nname = \"broken\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("src/lib.rs"), "pub fn answer() -> u32 { \"no\" }\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("Cargo.toml")).unwrap();
+        index.add_path(Path::new("src/lib.rs")).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = Signature::now("test", "test@example.com").unwrap();
+        repo.commit(Some("refs/heads/main"), &sig, &sig, "init", &tree, &[]).unwrap();
+
+        let mut manager = CompetitionManager::new(dir.path().to_path_buf(), "main".to_string()).unwrap();
+        let mut registry = AgentRegistry::new();
+        let id = manager
+            .start_competition(
+                "make it compile".to_string(),
+                vec![AgentType::Custom("solo".to_string())],
+                EvaluatorStrategy::default(),
+                60,
+                &mut registry,
+            )
+            .await
+            .unwrap();
+        manager.set_retry_rounds(&id, 1).unwrap();
+        let agent = format!("{}-solo", id);
+
+        registry.update_status(&agent, AgentStatus::Completed).unwrap();
+        assert_eq!(
+            manager.update_competition(&id, &mut registry).await.unwrap(),
+            CompetitionStatus::Evaluating
+        );
+        assert_eq!(
+            manager.update_competition(&id, &mut registry).await.unwrap(),
+            CompetitionStatus::Running { completed: 0, total: 1 }
+        );
+        assert_eq!(manager.get_competition(&id).unwrap().round(), 2);
+        assert_eq!(registry.get_session(&agent).unwrap().status, AgentStatus::Active);
+
+        let follow_ups = manager.take_follow_ups(&id);
+        assert_eq!(follow_ups.len(), 1);
+        assert!(follow_ups[0].prompt.contains("Task: make it compile"));
+        assert!(follow_ups[0].prompt.contains("Type check failed"));
+        assert!(manager.take_follow_ups(&id).is_empty());
+
+        // Still broken after the retry, and no rounds left
+        registry.update_status(&agent, AgentStatus::Completed).unwrap();
+        manager.update_competition(&id, &mut registry).await.unwrap();
+        let status = manager.update_competition(&id, &mut registry).await.unwrap();
+        assert!(matches!(status, CompetitionStatus::Failed(_)));
+    }
+}
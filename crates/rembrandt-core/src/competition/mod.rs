@@ -0,0 +1,506 @@
+//! Competition mode for parallel agent evaluation
+//!
+//! Multiple agents work on the same task independently, with an evaluation
+//! pipeline selecting the best solution for merging.
+
+mod benchmark;
+mod decision;
+mod evaluator;
+mod history;
+mod lint;
+mod manager;
+mod security;
+mod test_report;
+mod tournament;
+mod validator;
+mod variant;
+
+pub use benchmark::*;
+pub use decision::*;
+pub use evaluator::*;
+pub use history::*;
+pub use lint::*;
+pub use manager::*;
+pub use security::*;
+pub use test_report::*;
+pub use tournament::*;
+pub use validator::*;
+pub use variant::*;
+
+use crate::agent::AgentType;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Unique identifier for a competition
+pub type CompetitionId = String;
+
+/// Generate a unique competition ID
+pub fn generate_competition_id() -> CompetitionId {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis();
+    format!("comp-{:x}", timestamp)
+}
+
+/// Status of a competition (state machine)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CompetitionStatus {
+    /// Agents are being spawned
+    Spawning,
+    /// All agents running, waiting for completion
+    Running {
+        completed: usize,
+        total: usize,
+    },
+    /// All agents done (or timeout), evaluation in progress
+    Evaluating,
+    /// Winner selected, merge in progress
+    Merging,
+    /// Competition finished successfully
+    Completed {
+        winner_id: String,
+    },
+    /// Competition failed (no valid solutions, timeout with none complete, etc.)
+    Failed(String),
+    /// Competition was cancelled by user
+    Cancelled,
+}
+
+impl CompetitionStatus {
+    /// Check if the competition is in a terminal state
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            CompetitionStatus::Completed { .. }
+                | CompetitionStatus::Failed(_)
+                | CompetitionStatus::Cancelled
+        )
+    }
+
+    /// How a finished competition ended (`completed`, `failed`,
+    /// `cancelled`); None while it's still going
+    pub fn outcome(&self) -> Option<&'static str> {
+        match self {
+            CompetitionStatus::Completed { .. } => Some("completed"),
+            CompetitionStatus::Failed(_) => Some("failed"),
+            CompetitionStatus::Cancelled => Some("cancelled"),
+            _ => None,
+        }
+    }
+}
+
+/// Git diff statistics for a solution
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct DiffStats {
+    pub files_changed: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+    pub files_added: Vec<PathBuf>,
+    pub files_modified: Vec<PathBuf>,
+    pub files_deleted: Vec<PathBuf>,
+    /// Renamed files as (old path, new path)
+    #[serde(default)]
+    pub files_renamed: Vec<(PathBuf, PathBuf)>,
+    /// Changed files git treats as binary; they add no line counts
+    #[serde(default)]
+    pub binary_files: usize,
+}
+
+impl DiffStats {
+    /// Total lines changed (insertions + deletions)
+    pub fn total_lines(&self) -> usize {
+        self.insertions + self.deletions
+    }
+}
+
+/// Result of validating a single solution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationResult {
+    pub agent_id: String,
+    pub type_check_passed: bool,
+    pub type_check_output: Option<String>,
+    pub tests_passed: bool,
+    pub tests_output: Option<String>,
+    pub test_count: Option<usize>,
+    pub test_failures: Option<usize>,
+    /// Names of failing tests, when the runner reports them
+    #[serde(default)]
+    pub failed_tests: Vec<String>,
+    /// Time the runner spent in tests, excluding builds
+    #[serde(default)]
+    pub test_duration_ms: Option<u64>,
+    /// Configured lint and formatting checks that applied
+    #[serde(default)]
+    pub lint_results: Vec<LintResult>,
+    /// Security scan of the changes, when scanners are configured
+    #[serde(default)]
+    pub security: Option<SecurityReport>,
+    /// The competition's benchmark, measured when the solution passed its
+    /// checks
+    #[serde(default)]
+    pub benchmark: Option<BenchmarkResult>,
+    pub validation_time_ms: u64,
+    pub error_message: Option<String>,
+}
+
+/// Lines of check output kept per failure in a follow-up prompt
+const FAILURE_OUTPUT_LINES: usize = 60;
+
+impl ValidationResult {
+    /// Check if the solution passed all validation checks
+    pub fn is_valid(&self) -> bool {
+        self.type_check_passed
+            && self.tests_passed
+            && !self.lint_results.iter().any(LintResult::blocks)
+            && !self.security.as_ref().is_some_and(SecurityReport::blocks)
+    }
+
+    /// Failed lint checks that only warn
+    pub fn lint_warnings(&self) -> impl Iterator<Item = &LintResult> {
+        self.lint_results.iter().filter(|r| !r.passed && !r.blocks())
+    }
+
+    /// What failed, with the tail of each failing check's output
+    pub fn failure_summary(&self) -> String {
+        let mut summary = String::new();
+        let mut section = |title: &str, output: Option<&String>| {
+            summary.push_str(title);
+            summary.push('\n');
+            if let Some(output) = output {
+                let lines: Vec<&str> = output.trim_end().lines().collect();
+                let tail = &lines[lines.len().saturating_sub(FAILURE_OUTPUT_LINES)..];
+                summary.push_str(&tail.join("\n"));
+                summary.push('\n');
+            }
+            summary.push('\n');
+        };
+        if let Some(error) = &self.error_message {
+            section("Validation could not run:", Some(error));
+        }
+        if !self.type_check_passed {
+            section("Type check failed:", self.type_check_output.as_ref());
+        }
+        if !self.tests_passed {
+            let mut title = match (self.test_failures, self.test_count) {
+                (Some(failures), Some(count)) => format!("Tests failed ({} of {}):", failures, count),
+                _ => "Tests failed:".to_string(),
+            };
+            if !self.failed_tests.is_empty() {
+                title.push_str(&format!("\n{}", self.failed_tests.join("\n")));
+            }
+            section(&title, self.tests_output.as_ref());
+        }
+        for lint in self.lint_results.iter().filter(|r| r.blocks()) {
+            section(&format!("Lint check {} failed:", lint.name), lint.output.as_ref());
+        }
+        if let Some(security) = self.security.as_ref().filter(|s| s.blocking().next().is_some()) {
+            let findings = security
+                .blocking()
+                .map(|f| format!("[{}] {} {}: {} ({})", f.severity, f.scanner, f.location, f.message, f.rule))
+                .collect::<Vec<_>>()
+                .join("\n");
+            section(
+                &format!("Security scan found issues at or above {}:", security.threshold),
+                Some(&findings),
+            );
+        }
+        if let Some(security) = self.security.as_ref().filter(|s| !s.errors.is_empty()) {
+            section("Security scanners could not run:", Some(&security.errors.join("\n")));
+        }
+        summary
+    }
+}
+
+/// A solution submitted by a competing agent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompetitorSolution {
+    pub agent_id: String,
+    pub agent_type: AgentType,
+    /// Model and temperature this competitor ran with
+    #[serde(default)]
+    pub variant: CompetitorVariant,
+    pub branch: String,
+    pub worktree_path: PathBuf,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub validation: Option<ValidationResult>,
+    pub diff_stats: Option<DiffStats>,
+}
+
+impl CompetitorSolution {
+    /// Check if the solution is complete and validated
+    pub fn is_validated(&self) -> bool {
+        self.validation.is_some()
+    }
+
+    /// Check if the solution passed validation
+    pub fn is_valid(&self) -> bool {
+        self.validation.as_ref().is_some_and(|v| v.is_valid())
+    }
+}
+
+/// Strategy for selecting the winning solution
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum EvaluatorStrategy {
+    /// Automated metrics comparison (test coverage, complexity, lines changed)
+    Metrics(MetricWeights),
+    /// Use an LLM to compare solutions and pick best
+    Model {
+        /// Model identifier (provider-specific)
+        model_name: String,
+    },
+    /// Present solutions for human selection via TUI
+    Human,
+    /// Have an LLM judge every pair of solutions and rank them by wins
+    Tournament {
+        /// Model identifier (provider-specific)
+        model_name: String,
+    },
+    /// Run strategies in order, each narrowing or re-ranking the last one's
+    /// picks (see `CompositeEvaluator`)
+    Pipeline(Vec<EvaluatorStrategy>),
+}
+
+impl Default for EvaluatorStrategy {
+    fn default() -> Self {
+        EvaluatorStrategy::Metrics(MetricWeights::default())
+    }
+}
+
+impl EvaluatorStrategy {
+    /// Parse a strategy name as given on the command line
+    ///
+    /// Comma-separated names (`metrics,model,human`) build a pipeline.
+    /// Unknown names fall back to metrics.
+    pub fn from_name(name: &str, model_name: &str) -> Self {
+        if name.contains(',') {
+            return EvaluatorStrategy::Pipeline(
+                name.split(',')
+                    .map(|stage| Self::from_name(stage.trim(), model_name))
+                    .collect(),
+            );
+        }
+        match name {
+            "model" => EvaluatorStrategy::Model {
+                model_name: model_name.to_string(),
+            },
+            "tournament" => EvaluatorStrategy::Tournament {
+                model_name: model_name.to_string(),
+            },
+            "human" => EvaluatorStrategy::Human,
+            _ => EvaluatorStrategy::default(),
+        }
+    }
+
+    /// Set the benchmark weight of metrics evaluation, including metrics
+    /// stages of a pipeline
+    pub fn set_benchmark_weight(&mut self, weight: f64) {
+        match self {
+            EvaluatorStrategy::Metrics(weights) => weights.benchmark = weight,
+            EvaluatorStrategy::Pipeline(stages) => {
+                stages.iter_mut().for_each(|stage| stage.set_benchmark_weight(weight))
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether a person picks or confirms the winner
+    pub fn involves_human(&self) -> bool {
+        match self {
+            EvaluatorStrategy::Human => true,
+            EvaluatorStrategy::Pipeline(stages) => stages.iter().any(Self::involves_human),
+            _ => false,
+        }
+    }
+}
+
+/// Weights for metrics-based evaluation
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MetricWeights {
+    /// Weight for test pass rate (0.0-1.0)
+    pub tests: f64,
+    /// Weight for fewer lines changed (simpler solution)
+    pub simplicity: f64,
+    /// Weight for faster completion time
+    pub speed: f64,
+    /// Weight for the competition's benchmark measurement
+    #[serde(default)]
+    pub benchmark: f64,
+}
+
+impl Default for MetricWeights {
+    fn default() -> Self {
+        Self {
+            tests: 0.5,
+            simplicity: 0.3,
+            speed: 0.2,
+            benchmark: 0.0,
+        }
+    }
+}
+
+/// Stop a competition before its timeout once one solution is clearly good
+///
+/// When a competitor finishes, it is validated right away and scored with
+/// the metrics weights (see `MetricsEvaluator::score`). A valid solution
+/// scoring at least `min_score` starts a grace period of `grace_minutes`;
+/// competitors still running when it ends are cut off and evaluation starts
+/// with the solutions that made it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EarlyStopPolicy {
+    /// Metrics score (0.0-1.0) that counts as clearly winning
+    pub min_score: f64,
+    /// How long the other competitors get to finish; 0 stops them at once
+    #[serde(default)]
+    pub grace_minutes: u64,
+}
+
+/// A follow-up prompt for one competitor in a retry round
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FollowUp {
+    pub agent_id: String,
+    pub prompt: String,
+    /// The failed validation the prompt was built from
+    pub validation: ValidationResult,
+    /// When the caller took it for delivery (`CompetitionManager::take_follow_ups`)
+    pub sent_at: Option<DateTime<Utc>>,
+}
+
+/// A re-prompting round started because no solution passed validation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompetitionRound {
+    /// 2 for the first retry; round 1 is the original run
+    pub number: usize,
+    pub started_at: DateTime<Utc>,
+    pub follow_ups: Vec<FollowUp>,
+}
+
+/// Ranking of a single solution after evaluation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SolutionRanking {
+    pub agent_id: String,
+    pub rank: usize,
+    pub score: f64,
+    pub reasoning: String,
+}
+
+/// Result of evaluating all solutions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvaluationResult {
+    pub winner_id: String,
+    pub strategy_used: EvaluatorStrategy,
+    pub reasoning: String,
+    pub rankings: Vec<SolutionRanking>,
+    pub evaluated_at: DateTime<Utc>,
+    /// Head-to-head judgments behind a tournament ranking
+    #[serde(default)]
+    pub judgments: Vec<PairwiseJudgment>,
+}
+
+/// One head-to-head comparison in a tournament
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PairwiseJudgment {
+    /// Solution shown to the judge as A
+    pub agent_a: String,
+    /// Solution shown to the judge as B
+    pub agent_b: String,
+    pub winner: String,
+    pub reasoning: String,
+}
+
+/// A competition group tracking multiple agents on the same task
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompetitionGroup {
+    pub id: CompetitionId,
+    pub prompt: String,
+    pub status: CompetitionStatus,
+    pub evaluator_strategy: EvaluatorStrategy,
+    pub competitors: Vec<CompetitorSolution>,
+    pub winner: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub timeout_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub evaluation_result: Option<EvaluationResult>,
+    /// Optional early termination once a solution validates with a high score
+    #[serde(default)]
+    pub early_stop: Option<EarlyStopPolicy>,
+    /// When still-running competitors get cut off, once early stop triggered
+    #[serde(default)]
+    pub early_stop_at: Option<DateTime<Utc>>,
+    /// Competitors cut off by early stop; their processes should be killed
+    #[serde(default)]
+    pub stopped_early: Vec<String>,
+    /// Extra rounds to run when every solution fails validation
+    #[serde(default)]
+    pub max_retry_rounds: usize,
+    /// Retry rounds started so far, oldest first
+    #[serde(default)]
+    pub retry_rounds: Vec<CompetitionRound>,
+    /// Record a Porque decision when a person picks the winner
+    #[serde(default)]
+    pub record_decision: bool,
+    /// The Porque decision recorded for the winner
+    #[serde(default)]
+    pub decision_id: Option<String>,
+    /// Benchmark measured on each valid solution
+    #[serde(default)]
+    pub benchmark: Option<Benchmark>,
+}
+
+impl CompetitionGroup {
+    /// Create a new competition group
+    pub fn new(
+        prompt: String,
+        evaluator_strategy: EvaluatorStrategy,
+        timeout_minutes: u64,
+    ) -> Self {
+        let now = Utc::now();
+        Self {
+            id: generate_competition_id(),
+            prompt,
+            status: CompetitionStatus::Spawning,
+            evaluator_strategy,
+            competitors: Vec::new(),
+            winner: None,
+            started_at: now,
+            timeout_at: now + Duration::minutes(timeout_minutes as i64),
+            completed_at: None,
+            evaluation_result: None,
+            early_stop: None,
+            early_stop_at: None,
+            stopped_early: Vec::new(),
+            max_retry_rounds: 0,
+            retry_rounds: Vec::new(),
+            record_decision: false,
+            decision_id: None,
+            benchmark: None,
+        }
+    }
+
+    /// Current round, starting at 1
+    pub fn round(&self) -> usize {
+        self.retry_rounds.len() + 1
+    }
+
+    /// Check if the competition has timed out
+    pub fn is_timed_out(&self) -> bool {
+        Utc::now() >= self.timeout_at
+    }
+
+    /// Get count of completed competitors
+    pub fn completed_count(&self) -> usize {
+        self.competitors
+            .iter()
+            .filter(|c| c.completed_at.is_some())
+            .count()
+    }
+
+    /// Get valid (passed validation) solutions
+    pub fn valid_solutions(&self) -> Vec<&CompetitorSolution> {
+        self.competitors.iter().filter(|c| c.is_valid()).collect()
+    }
+}
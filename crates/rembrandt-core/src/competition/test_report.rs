@@ -0,0 +1,422 @@
+//! Structured test results from test runner output
+//!
+//! Counting tests by scraping human-readable summaries breaks whenever a
+//! runner tweaks its wording. Runners that can emit machine-readable events
+//! are asked to, and those events are parsed here into a `TestReport`.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// What a test run reported
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TestReport {
+    /// Tests that ran (ignored ones excluded)
+    pub total: usize,
+    pub failed: usize,
+    pub ignored: usize,
+    /// Names of the failing tests, in the order they finished
+    pub failed_tests: Vec<String>,
+    /// Time spent running tests, as reported by the runner
+    pub duration_ms: Option<u64>,
+}
+
+impl TestReport {
+    pub fn passed(&self) -> usize {
+        self.total - self.failed
+    }
+}
+
+/// One line of libtest's `--format json` output
+#[derive(Debug, Deserialize)]
+struct LibtestEvent {
+    #[serde(rename = "type")]
+    kind: String,
+    event: String,
+    name: Option<String>,
+    passed: Option<usize>,
+    failed: Option<usize>,
+    ignored: Option<usize>,
+    /// Seconds
+    exec_time: Option<f64>,
+}
+
+/// Parse libtest JSON events, from `cargo nextest run --message-format
+/// libtest-json`.
+///
+/// Each test binary (and doctests) reports its own suite; their totals are
+/// summed. Non-JSON lines are skipped. `None` when no events were found,
+/// e.g. because the toolchain rejected the format flags.
+pub fn parse_libtest_json(output: &str) -> Option<TestReport> {
+    let mut report = TestReport::default();
+    let mut duration = 0.0;
+    let mut seen_event = false;
+    let mut suite_totals = false;
+    // Per-test tallies for runs cut short before a suite summary
+    let (mut tests_run, mut tests_ignored) = (0, 0);
+
+    for line in output.lines() {
+        let Ok(event) = serde_json::from_str::<LibtestEvent>(line.trim()) else {
+            continue;
+        };
+        seen_event = true;
+        match (event.kind.as_str(), event.event.as_str()) {
+            ("suite", "ok" | "failed") => {
+                suite_totals = true;
+                let failed = event.failed.unwrap_or(0);
+                report.total += event.passed.unwrap_or(0) + failed;
+                report.failed += failed;
+                report.ignored += event.ignored.unwrap_or(0);
+                duration += event.exec_time.unwrap_or(0.0);
+            }
+            ("test", "ok") => tests_run += 1,
+            ("test", "ignored") => tests_ignored += 1,
+            ("test", "failed" | "timeout") => {
+                tests_run += 1;
+                if let Some(name) = event.name {
+                    report.failed_tests.push(name);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if !seen_event {
+        return None;
+    }
+    if suite_totals {
+        report.duration_ms = Some((duration * 1000.0).round() as u64);
+    } else {
+        report.total = tests_run;
+        report.failed = report.failed_tests.len();
+        report.ignored = tests_ignored;
+    }
+    Some(report)
+}
+
+/// Parse libtest's plain output, from `cargo test`
+///
+/// libtest only prints JSON on nightly toolchains, but its plain output is
+/// stable: every test binary (and doctests) ends with a `test result:`
+/// summary, which are summed, and failing tests are listed as
+/// `test <name> ... FAILED`. `None` when no summary was printed, e.g.
+/// because the build failed.
+pub fn parse_libtest_text(output: &str) -> Option<TestReport> {
+    let mut report = TestReport::default();
+    let mut duration = 0.0;
+    let mut seen_summary = false;
+
+    for line in output.lines().map(str::trim_end) {
+        if let Some(summary) = line.strip_prefix("test result: ") {
+            seen_summary = true;
+            // `ok. 5 passed; 1 failed; 2 ignored; 0 measured; 0 filtered out; finished in 0.25s`
+            for part in summary.split(';') {
+                let part = part.trim();
+                let part = part.split_once(". ").map_or(part, |(_, counts)| counts);
+                if let Some(secs) = part.strip_prefix("finished in ").and_then(|s| s.strip_suffix('s')) {
+                    duration += secs.parse::<f64>().unwrap_or(0.0);
+                    continue;
+                }
+                let Some((count, what)) = part.split_once(' ') else {
+                    continue;
+                };
+                let Ok(count) = count.parse::<usize>() else {
+                    continue;
+                };
+                match what {
+                    "passed" => report.total += count,
+                    "failed" => {
+                        report.total += count;
+                        report.failed += count;
+                    }
+                    "ignored" => report.ignored += count,
+                    _ => {}
+                }
+            }
+        } else if let Some(test) = line.strip_prefix("test ")
+            && let Some((name, "FAILED")) = test.rsplit_once(" ... ")
+        {
+            report.failed_tests.push(name.to_string());
+        }
+    }
+
+    if !seen_summary {
+        return None;
+    }
+    report.duration_ms = Some((duration * 1000.0).round() as u64);
+    Some(report)
+}
+
+/// Parse a pytest-json-report file (`pytest --json-report`).
+///
+/// Errors during setup or teardown count as failures; skipped and xfailed
+/// tests count as ignored.
+pub fn parse_pytest_json(report: &str) -> Option<TestReport> {
+    #[derive(Deserialize)]
+    struct PytestReport {
+        /// Seconds
+        duration: Option<f64>,
+        #[serde(default)]
+        tests: Vec<PytestTest>,
+    }
+    #[derive(Deserialize)]
+    struct PytestTest {
+        nodeid: String,
+        outcome: String,
+    }
+
+    let parsed: PytestReport = serde_json::from_str(report).ok()?;
+    let mut report = TestReport {
+        duration_ms: parsed.duration.map(|secs| (secs * 1000.0).round() as u64),
+        ..Default::default()
+    };
+    for test in parsed.tests {
+        match test.outcome.as_str() {
+            "skipped" | "xfailed" => report.ignored += 1,
+            "failed" | "error" => {
+                report.total += 1;
+                report.failed += 1;
+                report.failed_tests.push(test.nodeid);
+            }
+            _ => report.total += 1,
+        }
+    }
+    Some(report)
+}
+
+/// Parse `go test -json` events.
+///
+/// Only top-level tests are counted; a failing subtest already fails its
+/// parent. Failed tests are named `<package>.<test>`. `None` when no events
+/// were found.
+pub fn parse_go_test_json(output: &str) -> Option<TestReport> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct GoEvent {
+        action: String,
+        package: Option<String>,
+        test: Option<String>,
+        /// Seconds
+        elapsed: Option<f64>,
+    }
+
+    let mut report = TestReport::default();
+    let mut duration = 0.0;
+    let mut seen_event = false;
+    for line in output.lines() {
+        let Ok(event) = serde_json::from_str::<GoEvent>(line.trim()) else {
+            continue;
+        };
+        seen_event = true;
+        match (event.test, event.action.as_str()) {
+            // Package results carry the package's run time
+            (None, "pass" | "fail") => duration += event.elapsed.unwrap_or(0.0),
+            (Some(test), action) if !test.contains('/') => match action {
+                "pass" => report.total += 1,
+                "skip" => report.ignored += 1,
+                "fail" => {
+                    report.total += 1;
+                    report.failed += 1;
+                    let package = event.package.unwrap_or_default();
+                    report.failed_tests.push(format!("{}.{}", package, test));
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+    seen_event.then(|| TestReport {
+        duration_ms: Some((duration * 1000.0).round() as u64),
+        ..report
+    })
+}
+
+/// Parse jest's `--json` results.
+///
+/// Pending and todo tests count as ignored. Output before the JSON object
+/// (e.g. from an npm script) is skipped.
+pub fn parse_jest_json(output: &str) -> Option<TestReport> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct JestResults {
+        num_passed_tests: usize,
+        num_failed_tests: usize,
+        #[serde(default)]
+        num_pending_tests: usize,
+        #[serde(default)]
+        num_todo_tests: usize,
+        #[serde(default)]
+        test_results: Vec<JestFile>,
+    }
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct JestFile {
+        #[serde(default)]
+        assertion_results: Vec<JestAssertion>,
+        /// Milliseconds since the epoch; newer jest nests these in `perfStats`
+        start_time: Option<u64>,
+        end_time: Option<u64>,
+        #[serde(default)]
+        perf_stats: HashMap<String, serde_json::Value>,
+    }
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct JestAssertion {
+        full_name: String,
+        status: String,
+    }
+
+    let start = output.find('{')?;
+    let results: JestResults = serde_json::Deserializer::from_str(&output[start..])
+        .into_iter()
+        .next()?
+        .ok()?;
+    let mut report = TestReport {
+        total: results.num_passed_tests + results.num_failed_tests,
+        failed: results.num_failed_tests,
+        ignored: results.num_pending_tests + results.num_todo_tests,
+        ..Default::default()
+    };
+    let mut duration = None;
+    for file in results.test_results {
+        let stat = |key: &str| file.perf_stats.get(key).and_then(|v| v.as_u64());
+        let start = file.start_time.or_else(|| stat("start"));
+        let end = file.end_time.or_else(|| stat("end"));
+        if let (Some(start), Some(end)) = (start, end) {
+            *duration.get_or_insert(0) += end.saturating_sub(start);
+        }
+        report.failed_tests.extend(
+            file.assertion_results
+                .into_iter()
+                .filter(|assertion| assertion.status == "failed")
+                .map(|assertion| assertion.full_name),
+        );
+    }
+    report.duration_ms = duration;
+    Some(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn libtest_suites_are_summed_with_failed_names() {
+        let output = r#"
+   Compiling demo v0.1.0 (/tmp/demo)
+{ "type": "suite", "event": "started", "test_count": 3 }
+{ "type": "test", "event": "started", "name": "cache::evicts" }
+{ "type": "test", "name": "cache::evicts", "event": "ok" }
+{ "type": "test", "name": "cache::expires", "event": "failed", "stdout": "thread panicked\n" }
+{ "type": "test", "name": "cache::slow", "event": "ignored" }
+{ "type": "suite", "event": "failed", "passed": 1, "failed": 1, "ignored": 1, "measured": 0, "filtered_out": 0, "exec_time": 0.25 }
+{ "type": "suite", "event": "started", "test_count": 2 }
+{ "type": "test", "name": "src/lib.rs - Cache (line 3)", "event": "ok" }
+{ "type": "test", "name": "src/lib.rs - Cache::get (line 9)", "event": "ok" }
+{ "type": "suite", "event": "ok", "passed": 2, "failed": 0, "ignored": 0, "measured": 0, "filtered_out": 0, "exec_time": 1.5 }
+"#;
+        let report = parse_libtest_json(output).unwrap();
+        assert_eq!(report.total, 4);
+        assert_eq!(report.failed, 1);
+        assert_eq!(report.passed(), 3);
+        assert_eq!(report.ignored, 1);
+        assert_eq!(report.failed_tests, vec!["cache::expires"]);
+        assert_eq!(report.duration_ms, Some(1750));
+
+        // nextest names tests by binary; a run killed mid-suite still counts
+        let cut_short = r#"{"type":"suite","event":"started","test_count":2,"nextest":{"crate":"demo","test_binary":"demo","kind":"lib"}}
+{"type":"test","event":"ok","name":"demo::demo$cache::evicts","exec_time":0.01}
+{"type":"test","event":"timeout","name":"demo::demo$cache::hangs"}"#;
+        let report = parse_libtest_json(cut_short).unwrap();
+        assert_eq!((report.total, report.failed), (2, 1));
+        assert_eq!(report.failed_tests, vec!["demo::demo$cache::hangs"]);
+        assert_eq!(report.duration_ms, None);
+
+        assert_eq!(parse_libtest_json("error: Unrecognized option: 'format'\n"), None);
+    }
+
+    #[test]
+    fn libtest_text_summaries_are_summed_with_failed_names() {
+        let output = "
+running 3 tests
+test cache::evicts ... ok
+test cache::expires ... FAILED
+test cache::slow ... ignored, needs a network
+
+failures:
+
+---- cache::expires stdout ----
+thread 'cache::expires' panicked at src/cache.rs:40:9
+
+failures:
+    cache::expires
+
+test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out; finished in 0.25s
+
+running 2 tests
+test src/lib.rs - Cache (line 3) ... ok
+test src/lib.rs - Cache::get (line 9) ... ok
+
+test result: ok. 2 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; finished in 1.50s
+";
+        let report = parse_libtest_text(output).unwrap();
+        assert_eq!(report.total, 4);
+        assert_eq!(report.failed, 1);
+        assert_eq!(report.ignored, 1);
+        assert_eq!(report.failed_tests, vec!["cache::expires"]);
+        assert_eq!(report.duration_ms, Some(1750));
+
+        assert_eq!(parse_libtest_text("error[E0425]: cannot find value `x`\n"), None);
+    }
+
+    #[test]
+    fn pytest_go_and_jest_reports() {
+        let pytest = r#"{"created": 1.7e9, "duration": 0.42, "exitcode": 1,
+            "summary": {"passed": 2, "failed": 1, "error": 1, "skipped": 1, "total": 5},
+            "tests": [
+                {"nodeid": "tests/test_cache.py::test_evicts", "outcome": "passed"},
+                {"nodeid": "tests/test_cache.py::test_expires", "outcome": "failed"},
+                {"nodeid": "tests/test_cache.py::test_db", "outcome": "error"},
+                {"nodeid": "tests/test_cache.py::test_slow", "outcome": "skipped"},
+                {"nodeid": "tests/test_cache.py::test_get[1]", "outcome": "passed"}
+            ]}"#;
+        let report = parse_pytest_json(pytest).unwrap();
+        assert_eq!((report.total, report.failed, report.ignored), (4, 2, 1));
+        assert_eq!(report.failed_tests, vec!["tests/test_cache.py::test_expires", "tests/test_cache.py::test_db"]);
+        assert_eq!(report.duration_ms, Some(420));
+        assert_eq!(parse_pytest_json("not json"), None);
+
+        let go = r#"{"Action":"start","Package":"example.com/cache"}
+{"Action":"run","Package":"example.com/cache","Test":"TestEvict"}
+{"Action":"output","Package":"example.com/cache","Test":"TestEvict","Output":"=== RUN   TestEvict
+"}
+{"Action":"pass","Package":"example.com/cache","Test":"TestEvict","Elapsed":0.01}
+{"Action":"fail","Package":"example.com/cache","Test":"TestExpire/zero_ttl","Elapsed":0}
+{"Action":"fail","Package":"example.com/cache","Test":"TestExpire","Elapsed":0.02}
+{"Action":"skip","Package":"example.com/cache","Test":"TestSlow","Elapsed":0}
+{"Action":"fail","Package":"example.com/cache","Elapsed":0.35}
+{"Action":"skip","Package":"example.com/cache/internal","Elapsed":0}"#;
+        let report = parse_go_test_json(go).unwrap();
+        assert_eq!((report.total, report.failed, report.ignored), (2, 1, 1));
+        assert_eq!(report.failed_tests, vec!["example.com/cache.TestExpire"]);
+        assert_eq!(report.duration_ms, Some(350));
+
+        let jest = r#"
+> demo@1.0.0 test
+> jest --json
+
+{"numFailedTests":1,"numPassedTests":2,"numPendingTests":1,"numTodoTests":0,"numTotalTests":4,"success":false,
+ "testResults":[
+   {"name":"/w/cache.test.js","perfStats":{"start":1000,"end":1250},"assertionResults":[
+     {"fullName":"cache evicts","status":"passed","title":"evicts"},
+     {"fullName":"cache expires","status":"failed","title":"expires"},
+     {"fullName":"cache is slow","status":"pending","title":"is slow"}]},
+   {"name":"/w/db.test.js","startTime":2000,"endTime":2100,"assertionResults":[
+     {"fullName":"db connects","status":"passed","title":"connects"}]}
+ ]}"#;
+        let report = parse_jest_json(jest).unwrap();
+        assert_eq!((report.total, report.failed, report.ignored), (3, 1, 1));
+        assert_eq!(report.failed_tests, vec!["cache expires"]);
+        assert_eq!(report.duration_ms, Some(350));
+        assert_eq!(parse_jest_json("npm ERR! missing script: test"), None);
+    }
+}
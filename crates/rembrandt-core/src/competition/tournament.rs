@@ -0,0 +1,365 @@
+//! Pairwise tournament evaluation
+//!
+//! Instead of asking a model to rank every diff at once, each pair of
+//! solutions is shown to a judge as "A vs B" and the wins are tallied into a
+//! ranking. Smaller, focused comparisons hold up better than single-shot
+//! ranking once there are more than a handful of competitors.
+
+use crate::competition::{
+    CompetitorSolution, EvaluationResult, Evaluator, EvaluatorStrategy, MetricWeights,
+    MetricsEvaluator, PairwiseJudgment, SolutionRanking,
+};
+use crate::ignore::IgnoreRules;
+use crate::{RembrandtError, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Longest diff (in bytes) shown to the judge per solution
+const MAX_DIFF_BYTES: usize = 40_000;
+
+/// One side of a head-to-head comparison
+#[derive(Debug, Clone)]
+pub struct Contender {
+    pub agent_id: String,
+    /// Changes relative to the base branch, possibly truncated
+    pub diff: String,
+}
+
+/// Which side the judge preferred
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum Side {
+    A,
+    B,
+}
+
+/// A judge's decision for one pair
+#[derive(Debug, Clone)]
+pub struct Verdict {
+    pub winner: Side,
+    pub reasoning: String,
+}
+
+/// Decides which of two solutions better solves the task
+#[async_trait]
+pub trait PairwiseJudge: Send + Sync {
+    async fn judge(&self, task: &str, a: &Contender, b: &Contender) -> Result<Verdict>;
+}
+
+/// Judge that asks the `claude` CLI in print mode
+pub struct CliJudge {
+    model_name: String,
+}
+
+impl CliJudge {
+    pub fn new(model_name: String) -> Self {
+        Self { model_name }
+    }
+
+    /// Build the comparison prompt for one pair
+    fn build_prompt(task: &str, a: &Contender, b: &Contender) -> String {
+        format!(
+            "You are judging two solutions to this coding task:\n\n\
+             Task: {}\n\n\
+             Decide which solution is better.\n\
+             Consider: correctness, maintainability, idiomatic style, and elegance.\n\n\
+             === Solution A ===\n{}\n\n\
+             === Solution B ===\n{}\n\n\
+             Respond with JSON only: {{\"winner\": \"A\" or \"B\", \"reasoning\": \"...\"}}",
+            task, a.diff, b.diff
+        )
+    }
+}
+
+#[derive(Deserialize)]
+struct RawVerdict {
+    winner: Side,
+    reasoning: String,
+}
+
+/// Pull the JSON verdict out of a model response that may wrap it in prose
+fn parse_verdict(response: &str) -> Result<Verdict> {
+    let json = match (response.find('{'), response.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &response[start..=end],
+        _ => {
+            return Err(RembrandtError::Competition(format!(
+                "Judge response has no JSON verdict: {}",
+                response.trim()
+            )));
+        }
+    };
+    let raw: RawVerdict = serde_json::from_str(json)
+        .map_err(|e| RembrandtError::Competition(format!("Invalid judge verdict: {}", e)))?;
+    Ok(Verdict {
+        winner: raw.winner,
+        reasoning: raw.reasoning,
+    })
+}
+
+#[async_trait]
+impl PairwiseJudge for CliJudge {
+    async fn judge(&self, task: &str, a: &Contender, b: &Contender) -> Result<Verdict> {
+        let mut child = Command::new("claude")
+            .args(["-p", "--model", &self.model_name])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| RembrandtError::Competition(format!("Failed to run judge: {}", e)))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(Self::build_prompt(task, a, b).as_bytes())?;
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(RembrandtError::Competition(format!(
+                "Judge exited with {}: {}",
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        parse_verdict(&String::from_utf8_lossy(&output.stdout))
+    }
+}
+
+/// Round-robin evaluator: every pair is judged once, most wins takes it
+///
+/// Pairs the judge can't decide (the CLI is missing, the response doesn't
+/// parse) fall back to comparing metrics scores, noted in the reasoning.
+pub struct TournamentEvaluator {
+    model_name: String,
+    judge: Box<dyn PairwiseJudge>,
+}
+
+impl TournamentEvaluator {
+    pub fn new(model_name: String) -> Self {
+        let judge = Box::new(CliJudge::new(model_name.clone()));
+        Self { model_name, judge }
+    }
+
+    /// Use a different judge, e.g. another provider or a test double
+    pub fn with_judge(model_name: String, judge: Box<dyn PairwiseJudge>) -> Self {
+        Self { model_name, judge }
+    }
+}
+
+/// Diff of a solution branch against where it forked from the checked-out base
+fn solution_diff(repo_path: &Path, solution: &CompetitorSolution) -> String {
+    let range = format!("HEAD...{}", solution.branch);
+    // Generated files matched by .rembrandtignore would only crowd the judge
+    let ignore = IgnoreRules::load(repo_path);
+    let excluded: Vec<String> = if ignore.is_empty() {
+        Vec::new()
+    } else {
+        Command::new("git")
+            .args(["diff", "--name-only", &range])
+            .current_dir(repo_path)
+            .output()
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+            .unwrap_or_default()
+            .lines()
+            .filter(|path| ignore.is_ignored(path))
+            .map(|path| format!(":(exclude,literal){}", path))
+            .collect()
+    };
+    let output = Command::new("git")
+        .args(["diff", &range, "--"])
+        .args(&excluded)
+        .current_dir(repo_path)
+        .output();
+    let mut diff = match output {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout).into_owned(),
+        _ => return format!("[Diff unavailable for branch {}]", solution.branch),
+    };
+    if diff.len() > MAX_DIFF_BYTES {
+        let mut cut = MAX_DIFF_BYTES;
+        while !diff.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        diff.truncate(cut);
+        diff.push_str("\n[... diff truncated]");
+    }
+    diff
+}
+
+#[async_trait]
+impl Evaluator for TournamentEvaluator {
+    async fn evaluate(
+        &self,
+        prompt: &str,
+        solutions: &[&CompetitorSolution],
+        repo_path: &Path,
+    ) -> Result<EvaluationResult> {
+        if solutions.is_empty() {
+            return Err(RembrandtError::Competition(
+                "No solutions to evaluate".to_string(),
+            ));
+        }
+
+        let metrics = MetricsEvaluator::new(MetricWeights::default());
+        let contenders: Vec<Contender> = solutions
+            .iter()
+            .map(|s| Contender {
+                agent_id: s.agent_id.clone(),
+                diff: solution_diff(repo_path, s),
+            })
+            .collect();
+
+        let mut wins: HashMap<&str, usize> = HashMap::new();
+        let mut judgments = Vec::new();
+        for i in 0..solutions.len() {
+            for j in (i + 1)..solutions.len() {
+                // Alternate who goes first to spread out any position bias
+                let (a, b) = if (i + j) % 2 == 0 { (i, j) } else { (j, i) };
+                let verdict = match self.judge.judge(prompt, &contenders[a], &contenders[b]).await {
+                    Ok(verdict) => verdict,
+                    Err(e) => {
+                        let prefers_a = metrics.score(solutions[a]) >= metrics.score(solutions[b]);
+                        Verdict {
+                            winner: if prefers_a { Side::A } else { Side::B },
+                            reasoning: format!("[Judge failed: {}; used metrics fallback]", e),
+                        }
+                    }
+                };
+                let winner = match verdict.winner {
+                    Side::A => &contenders[a].agent_id,
+                    Side::B => &contenders[b].agent_id,
+                };
+                *wins.entry(winner.as_str()).or_default() += 1;
+                judgments.push(PairwiseJudgment {
+                    agent_a: contenders[a].agent_id.clone(),
+                    agent_b: contenders[b].agent_id.clone(),
+                    winner: winner.clone(),
+                    reasoning: verdict.reasoning,
+                });
+            }
+        }
+
+        let rounds = solutions.len().saturating_sub(1).max(1);
+        let mut rankings: Vec<(SolutionRanking, f64)> = solutions
+            .iter()
+            .map(|s| {
+                let won = wins.get(s.agent_id.as_str()).copied().unwrap_or(0);
+                let ranking = SolutionRanking {
+                    agent_id: s.agent_id.clone(),
+                    rank: 0, // Will be set after sorting
+                    score: won as f64 / rounds as f64,
+                    reasoning: format!("Won {} of {} head-to-head comparisons", won, rounds),
+                };
+                (ranking, metrics.score(s))
+            })
+            .collect();
+
+        // Most wins first; metrics break ties
+        rankings.sort_by(|(a, a_metrics), (b, b_metrics)| {
+            b.score.total_cmp(&a.score).then(b_metrics.total_cmp(a_metrics))
+        });
+        let mut rankings: Vec<SolutionRanking> = rankings.into_iter().map(|(r, _)| r).collect();
+        for (i, ranking) in rankings.iter_mut().enumerate() {
+            ranking.rank = i + 1;
+        }
+
+        let winner = rankings[0].clone();
+        Ok(EvaluationResult {
+            winner_id: winner.agent_id.clone(),
+            strategy_used: EvaluatorStrategy::Tournament {
+                model_name: self.model_name.clone(),
+            },
+            reasoning: format!(
+                "Winner: {} after {} pairwise judgments. {}",
+                winner.agent_id,
+                judgments.len(),
+                winner.reasoning
+            ),
+            rankings,
+            evaluated_at: Utc::now(),
+            judgments,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "tournament"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::AgentType;
+    use crate::competition::ValidationResult;
+
+    /// Prefers whichever contender has the alphabetically smaller ID
+    struct AlphabeticalJudge;
+
+    #[async_trait]
+    impl PairwiseJudge for AlphabeticalJudge {
+        async fn judge(&self, _task: &str, a: &Contender, b: &Contender) -> Result<Verdict> {
+            let winner = if a.agent_id < b.agent_id { Side::A } else { Side::B };
+            Ok(Verdict {
+                winner,
+                reasoning: format!("{} vs {}", a.agent_id, b.agent_id),
+            })
+        }
+    }
+
+    fn solution(agent_id: &str) -> CompetitorSolution {
+        CompetitorSolution {
+            agent_id: agent_id.to_string(),
+            agent_type: AgentType::ClaudeCode,
+            variant: Default::default(),
+            branch: format!("rembrandt/{}", agent_id),
+            worktree_path: std::env::temp_dir(),
+            completed_at: Some(Utc::now()),
+            validation: Some(ValidationResult {
+                agent_id: agent_id.to_string(),
+                type_check_passed: true,
+                type_check_output: None,
+                tests_passed: true,
+                tests_output: None,
+                test_count: None,
+                test_failures: None,
+                failed_tests: Vec::new(),
+                test_duration_ms: None,
+                lint_results: Vec::new(),
+                security: None,
+                benchmark: None,
+                validation_time_ms: 0,
+                error_message: None,
+            }),
+            diff_stats: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn ranks_solutions_by_head_to_head_wins() {
+        let solutions = [solution("carol"), solution("alice"), solution("bob")];
+        let refs: Vec<&CompetitorSolution> = solutions.iter().collect();
+        let evaluator =
+            TournamentEvaluator::with_judge("test-model".to_string(), Box::new(AlphabeticalJudge));
+
+        let result = evaluator
+            .evaluate("task", &refs, &std::env::temp_dir())
+            .await
+            .unwrap();
+
+        assert_eq!(result.winner_id, "alice");
+        let order: Vec<&str> = result.rankings.iter().map(|r| r.agent_id.as_str()).collect();
+        assert_eq!(order, ["alice", "bob", "carol"]);
+        assert_eq!(result.rankings[0].score, 1.0);
+        assert_eq!(result.rankings[2].score, 0.0);
+        assert_eq!(result.judgments.len(), 3);
+        assert!(result.judgments.iter().all(|j| j.reasoning.contains(" vs ")));
+    }
+
+    #[test]
+    fn parses_a_verdict_wrapped_in_prose() {
+        let verdict =
+            parse_verdict("Sure.\n{\"winner\": \"B\", \"reasoning\": \"fewer changes\"}\n").unwrap();
+        assert_eq!(verdict.winner, Side::B);
+        assert_eq!(verdict.reasoning, "fewer changes");
+        assert!(parse_verdict("no idea").is_err());
+    }
+}
@@ -0,0 +1,620 @@
+//! Solution validation - run type checks and tests on each solution
+
+use crate::competition::{
+    parse_go_test_json, parse_jest_json, parse_libtest_json, parse_libtest_text, parse_pytest_json, run_lint_checks,
+    CompetitorSolution, security, Benchmark, DiffStats, LintCheck, SecurityConfig, TestReport, ValidationResult,
+};
+use crate::ignore::IgnoreRules;
+use crate::Result;
+use git2::{Delta, DiffFindOptions, Patch, Repository};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Instant;
+
+/// Validator for running type checks and tests on solutions
+#[derive(Debug, Clone)]
+pub struct SolutionValidator {
+    /// Base branch to compare against for diff stats
+    base_branch: String,
+    /// Lint and formatting checks run after the tests
+    lint_checks: Vec<LintCheck>,
+    /// Security scanners run over the changes since the base
+    security: SecurityConfig,
+    /// Benchmark measured on solutions that pass their checks
+    benchmark: Option<Benchmark>,
+}
+
+impl SolutionValidator {
+    pub fn new(base_branch: String) -> Self {
+        Self {
+            base_branch,
+            lint_checks: Vec::new(),
+            security: SecurityConfig::default(),
+            benchmark: None,
+        }
+    }
+
+    /// Also run `checks`; failing `block` checks fail validation
+    pub fn with_lint_checks(mut self, checks: Vec<LintCheck>) -> Self {
+        self.lint_checks = checks;
+        self
+    }
+
+    /// Also scan the changes; findings at the threshold fail validation
+    pub fn with_security(mut self, security: SecurityConfig) -> Self {
+        self.security = security;
+        self
+    }
+
+    /// Measure `benchmark` on solutions whose type check and tests pass
+    pub fn with_benchmark(mut self, benchmark: Option<Benchmark>) -> Self {
+        self.benchmark = benchmark;
+        self
+    }
+
+    /// Validate a solution by running type check and tests
+    #[tracing::instrument(name = "validate", skip_all, fields(agent_id = %solution.agent_id))]
+    pub async fn validate(&self, solution: &CompetitorSolution) -> Result<ValidationResult> {
+        let start = Instant::now();
+        let worktree = &solution.worktree_path;
+
+        // Detect project type and run appropriate checks
+        let (type_check_passed, type_check_output) = self.run_type_check(worktree).await;
+        let (tests_passed, tests_output, test_report) = self.run_tests(worktree).await;
+        let lint_results = run_lint_checks(&self.lint_checks, worktree);
+        let security =
+            (!self.security.scanners.is_empty()).then(|| security::scan(worktree, &self.base_branch, &self.security));
+
+        let elapsed = start.elapsed();
+        let validation_time_ms = elapsed.as_millis() as u64;
+
+        // Not part of validation time, which the speed metric compares
+        let benchmark = match &self.benchmark {
+            Some(benchmark) if type_check_passed && tests_passed => match benchmark.run(worktree) {
+                Ok(result) => Some(result),
+                Err(e) => {
+                    tracing::warn!(agent_id = %solution.agent_id, error = %e, "benchmark failed");
+                    None
+                }
+            },
+            _ => None,
+        };
+
+        Ok(ValidationResult {
+            agent_id: solution.agent_id.clone(),
+            type_check_passed,
+            type_check_output,
+            tests_passed,
+            tests_output,
+            test_count: test_report.as_ref().map(|r| r.total),
+            test_failures: test_report.as_ref().map(|r| r.failed),
+            failed_tests: test_report.as_ref().map(|r| r.failed_tests.clone()).unwrap_or_default(),
+            test_duration_ms: test_report.and_then(|r| r.duration_ms),
+            lint_results,
+            security,
+            benchmark,
+            validation_time_ms,
+            error_message: None,
+        })
+    }
+
+    /// Run type check based on detected project type
+    async fn run_type_check(&self, worktree: &Path) -> (bool, Option<String>) {
+        // Check for Rust project
+        if worktree.join("Cargo.toml").exists() {
+            return self.run_cargo_check(worktree).await;
+        }
+
+        // Check for TypeScript project
+        if worktree.join("tsconfig.json").exists() {
+            return self.run_tsc_check(worktree).await;
+        }
+
+        // Check for JavaScript with package.json
+        if worktree.join("package.json").exists() {
+            // Could run eslint or similar, for now just pass
+            return (true, Some("No type check configured for JS project".to_string()));
+        }
+
+        // Unknown project type - pass by default
+        (true, Some("No type check configured".to_string()))
+    }
+
+    /// Run cargo check for Rust projects
+    async fn run_cargo_check(&self, worktree: &Path) -> (bool, Option<String>) {
+        match Command::new("cargo")
+            .arg("check")
+            .arg("--message-format=short")
+            .current_dir(worktree)
+            .output()
+        {
+            Ok(output) => {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                let combined = format!("{}\n{}", stdout, stderr);
+                (output.status.success(), Some(combined))
+            }
+            Err(e) => (false, Some(format!("Failed to run cargo check: {}", e))),
+        }
+    }
+
+    /// Run tsc for TypeScript projects
+    async fn run_tsc_check(&self, worktree: &Path) -> (bool, Option<String>) {
+        match Command::new("npx")
+            .args(["tsc", "--noEmit"])
+            .current_dir(worktree)
+            .output()
+        {
+            Ok(output) => {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                let combined = format!("{}\n{}", stdout, stderr);
+                (output.status.success(), Some(combined))
+            }
+            Err(e) => (false, Some(format!("Failed to run tsc: {}", e))),
+        }
+    }
+
+    /// Run tests based on detected project type
+    async fn run_tests(&self, worktree: &Path) -> (bool, Option<String>, Option<TestReport>) {
+        match detect_test_runner(worktree) {
+            Some(TestRunner::Cargo) => self.run_cargo_test(worktree).await,
+            Some(TestRunner::Go) => self.run_go_test(worktree).await,
+            Some(TestRunner::Pytest) => self.run_pytest(worktree).await,
+            Some(TestRunner::Jest) => self.run_jest(worktree).await,
+            Some(TestRunner::Npm) => self.run_npm_test(worktree).await,
+            // No tests configured - pass by default
+            None => (true, Some("No test runner configured".to_string()), None),
+        }
+    }
+
+    /// Run tests for Rust projects: through cargo-nextest with libtest JSON
+    /// events when it is installed, else `cargo test`, whose plain output is
+    /// parsed (libtest's own JSON format needs a nightly toolchain)
+    async fn run_cargo_test(&self, worktree: &Path) -> (bool, Option<String>, Option<TestReport>) {
+        let nextest = Command::new("cargo")
+            .args(["nextest", "--version"])
+            .current_dir(worktree)
+            .output()
+            .is_ok_and(|output| output.status.success());
+        let mut command = Command::new("cargo");
+        if nextest {
+            command
+                .args(["nextest", "run", "--no-fail-fast", "--message-format", "libtest-json"])
+                .env("NEXTEST_EXPERIMENTAL_LIBTEST_JSON", "1");
+        } else {
+            command.args(["test", "--no-fail-fast"]);
+        }
+
+        match command.current_dir(worktree).output() {
+            Ok(output) => {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                let report = if nextest {
+                    parse_libtest_json(&stdout)
+                } else {
+                    parse_libtest_text(&stdout)
+                };
+                let combined = format!("{}\n{}", stdout, stderr);
+                (output.status.success(), Some(combined), report)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => runner_missing("cargo"),
+            Err(e) => (false, Some(format!("Failed to run cargo test: {}", e)), None),
+        }
+    }
+
+    /// Run `go test -json` over every package
+    async fn run_go_test(&self, worktree: &Path) -> (bool, Option<String>, Option<TestReport>) {
+        match Command::new("go")
+            .args(["test", "-json", "./..."])
+            .current_dir(worktree)
+            .output()
+        {
+            Ok(output) => {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                let report = parse_go_test_json(&stdout);
+                let combined = format!("{}\n{}", stdout, stderr);
+                (output.status.success(), Some(combined), report)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => runner_missing("go"),
+            Err(e) => (false, Some(format!("Failed to run go test: {}", e)), None),
+        }
+    }
+
+    /// Run pytest, with a JSON report when the pytest-json-report plugin is
+    /// installed and pass/fail only otherwise
+    async fn run_pytest(&self, worktree: &Path) -> (bool, Option<String>, Option<TestReport>) {
+        let dir_name = worktree.file_name().unwrap_or_default().to_string_lossy();
+        let report_path = std::env::temp_dir().join(format!("rembrandt-pytest-{}-{}.json", std::process::id(), dir_name));
+        let report_arg = format!("--json-report-file={}", report_path.display());
+
+        let output = match Command::new("pytest")
+            .args(["-q", "--json-report", &report_arg])
+            .current_dir(worktree)
+            .output()
+        {
+            Ok(output) => output,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return runner_missing("pytest"),
+            Err(e) => return (false, Some(format!("Failed to run pytest: {}", e)), None),
+        };
+        let report = std::fs::read_to_string(&report_path).ok().and_then(|json| parse_pytest_json(&json));
+        let _ = std::fs::remove_file(&report_path);
+
+        let output = match report {
+            Some(_) => output,
+            // Without the plugin pytest rejects --json-report; run it plainly
+            None => match Command::new("pytest").arg("-q").current_dir(worktree).output() {
+                Ok(output) => output,
+                Err(e) => return (false, Some(format!("Failed to run pytest: {}", e)), None),
+            },
+        };
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let combined = format!("{}\n{}", stdout, stderr);
+        (output.status.success(), Some(combined), report)
+    }
+
+    /// Run jest directly with `--json` results
+    async fn run_jest(&self, worktree: &Path) -> (bool, Option<String>, Option<TestReport>) {
+        match Command::new("npx")
+            .args(["jest", "--json", "--passWithNoTests"])
+            .current_dir(worktree)
+            .output()
+        {
+            Ok(output) => {
+                // Results go to stdout, jest's own progress to stderr
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                let report = parse_jest_json(&stdout);
+                let combined = format!("{}\n{}", stdout, stderr);
+                (output.status.success(), Some(combined), report)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => runner_missing("npx"),
+            Err(e) => (false, Some(format!("Failed to run jest: {}", e)), None),
+        }
+    }
+
+    /// Run npm test for Node.js projects
+    async fn run_npm_test(&self, worktree: &Path) -> (bool, Option<String>, Option<TestReport>) {
+        match Command::new("npm")
+            .args(["test", "--", "--passWithNoTests"])
+            .current_dir(worktree)
+            .output()
+        {
+            Ok(output) => {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                let combined = format!("{}\n{}", stdout, stderr);
+
+                // For npm test, we'd need to parse test framework output
+                // Simplified: just check exit status
+                (output.status.success(), Some(combined), None)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => runner_missing("npm"),
+            Err(e) => (false, Some(format!("Failed to run npm test: {}", e)), None),
+        }
+    }
+
+    /// Calculate diff stats for a solution branch vs base
+    ///
+    /// Compares HEAD with its merge base with the base branch, so commits the
+    /// base gained since don't count against the solution. Uses git2 with
+    /// rename detection, falling back to `git diff --numstat` when libgit2
+    /// can't read the repository.
+    pub fn calculate_diff_stats(&self, solution: &CompetitorSolution) -> Result<DiffStats> {
+        let worktree = &solution.worktree_path;
+        let ignore = IgnoreRules::load(worktree);
+        match diff_stats(worktree, &self.base_branch, &ignore) {
+            Ok(stats) => Ok(stats),
+            Err(e) => {
+                tracing::debug!(error = %e, "git2 diff failed, falling back to git diff --numstat");
+                let output = Command::new("git")
+                    .args([
+                        "diff",
+                        "-M",
+                        "--numstat",
+                        "--summary",
+                        &format!("{}...HEAD", self.base_branch),
+                    ])
+                    .current_dir(worktree)
+                    .output()
+                    .map_err(|e| crate::RembrandtError::Git(git2::Error::from_str(&e.to_string())))?;
+                if !output.status.success() {
+                    return Err(crate::RembrandtError::Git(git2::Error::from_str(
+                        String::from_utf8_lossy(&output.stderr).trim(),
+                    )));
+                }
+                parse_git_diff_stat(&String::from_utf8_lossy(&output.stdout), &ignore)
+            }
+        }
+    }
+}
+
+/// Test runners the validator knows how to drive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TestRunner {
+    Cargo,
+    Go,
+    Pytest,
+    Jest,
+    Npm,
+}
+
+/// The test runner `worktree`'s project files call for
+///
+/// pytest's own config files win over `package.json`, but a `package.json`
+/// wins over `pyproject.toml`, `setup.py`, `setup.cfg` and `tox.ini`: those
+/// often only configure Python tooling in a JavaScript project.
+fn detect_test_runner(worktree: &Path) -> Option<TestRunner> {
+    let has = |files: &[&str]| files.iter().any(|file| worktree.join(file).exists());
+    if has(&["Cargo.toml"]) {
+        Some(TestRunner::Cargo)
+    } else if has(&["go.mod"]) {
+        Some(TestRunner::Go)
+    } else if has(&["pytest.ini", "conftest.py"]) {
+        Some(TestRunner::Pytest)
+    } else if has(&["package.json"]) {
+        Some(if uses_jest(worktree) { TestRunner::Jest } else { TestRunner::Npm })
+    } else if has(&["pyproject.toml", "setup.py", "setup.cfg", "tox.ini"]) {
+        Some(TestRunner::Pytest)
+    } else {
+        None
+    }
+}
+
+/// Outcome when the project's test runner isn't installed: the tests are
+/// reported as skipped rather than failed
+fn runner_missing(program: &str) -> (bool, Option<String>, Option<TestReport>) {
+    (true, Some(format!("Tests skipped: `{}` is not installed", program)), None)
+}
+
+/// Whether the Node.js project in `worktree` tests with jest
+fn uses_jest(worktree: &Path) -> bool {
+    let Ok(manifest) = std::fs::read_to_string(worktree.join("package.json")) else {
+        return false;
+    };
+    let Ok(manifest) = serde_json::from_str::<serde_json::Value>(&manifest) else {
+        return false;
+    };
+    let test_script = manifest.pointer("/scripts/test").and_then(|s| s.as_str()).unwrap_or_default();
+    test_script.split_whitespace().any(|word| word == "jest")
+        || ["dependencies", "devDependencies"]
+            .iter()
+            .any(|section| manifest.get(section).and_then(|deps| deps.get("jest")).is_some())
+}
+
+/// Diff stats of `worktree`'s HEAD against its merge base with `base`
+fn diff_stats(worktree: &Path, base: &str, ignore: &IgnoreRules) -> Result<DiffStats> {
+    let repo = Repository::open(worktree)?;
+    let head = repo.head()?.peel_to_commit()?;
+    let base = repo.revparse_single(base)?.peel_to_commit()?;
+    let ancestor = repo.find_commit(repo.merge_base(base.id(), head.id())?)?;
+
+    let mut diff = repo.diff_tree_to_tree(Some(&ancestor.tree()?), Some(&head.tree()?), None)?;
+    diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+
+    let mut stats = DiffStats::default();
+    for idx in 0..diff.deltas().len() {
+        let Some(patch) = Patch::from_diff(&diff, idx)? else {
+            continue;
+        };
+        let delta = patch.delta();
+        let new_path = delta.new_file().path().map(Path::to_path_buf);
+        let old_path = delta.old_file().path().map(Path::to_path_buf);
+        let Some(path) = new_path.clone().or_else(|| old_path.clone()) else {
+            continue;
+        };
+        if ignore.is_ignored(&path) {
+            continue;
+        }
+
+        stats.files_changed += 1;
+        if delta.flags().is_binary() {
+            stats.binary_files += 1;
+        } else {
+            let (_, insertions, deletions) = patch.line_stats()?;
+            stats.insertions += insertions;
+            stats.deletions += deletions;
+        }
+        match delta.status() {
+            Delta::Added | Delta::Copied => stats.files_added.push(path),
+            Delta::Deleted => stats.files_deleted.push(path),
+            Delta::Renamed => stats.files_renamed.push((old_path.unwrap_or_default(), path)),
+            _ => stats.files_modified.push(path),
+        }
+    }
+    Ok(stats)
+}
+
+/// Parse `git diff -M --numstat --summary` output, skipping files matched by `ignore`
+///
+/// Binary files show `-` for both counts. Renames appear as `old => new` or
+/// `dir/{old => new}.rs`; `--summary` lines mark created and deleted files.
+fn parse_git_diff_stat(output: &str, ignore: &IgnoreRules) -> Result<DiffStats> {
+    let mut stats = DiffStats::default();
+    let mut created = Vec::new();
+    let mut deleted = Vec::new();
+
+    for line in output.lines() {
+        // Summary: " create mode 100644 path", " delete mode 100644 path"
+        let summary = line.trim_start();
+        for (prefix, paths) in [("create mode ", &mut created), ("delete mode ", &mut deleted)] {
+            if let Some((_, path)) = summary.strip_prefix(prefix).and_then(|rest| rest.split_once(' ')) {
+                paths.push(PathBuf::from(path));
+            }
+        }
+
+        // Format: insertions<TAB>deletions<TAB>path
+        let mut parts = line.splitn(3, '\t');
+        let (Some(ins), Some(del), Some(path)) = (parts.next(), parts.next(), parts.next()) else {
+            continue;
+        };
+        let (old_path, path) = match split_rename(path) {
+            Some((old, new)) => (Some(old), new),
+            None => (None, PathBuf::from(path)),
+        };
+        if ignore.is_ignored(&path) {
+            continue;
+        }
+        match (ins.parse::<usize>(), del.parse::<usize>()) {
+            (Ok(ins), Ok(del)) => {
+                stats.insertions += ins;
+                stats.deletions += del;
+            }
+            _ if ins == "-" && del == "-" => stats.binary_files += 1,
+            _ => continue,
+        }
+        stats.files_changed += 1;
+        match old_path {
+            Some(old) => stats.files_renamed.push((old, path)),
+            None => stats.files_modified.push(path),
+        }
+    }
+
+    // Numstat lines don't say whether a file is new or gone
+    stats.files_modified.retain(|path| {
+        if created.contains(path) {
+            stats.files_added.push(path.clone());
+            false
+        } else if deleted.contains(path) {
+            stats.files_deleted.push(path.clone());
+            false
+        } else {
+            true
+        }
+    });
+
+    Ok(stats)
+}
+
+/// Old and new path of a numstat rename: `a.rs => b.rs` or `src/{a => b}.rs`
+fn split_rename(path: &str) -> Option<(PathBuf, PathBuf)> {
+    let (before, after) = path.split_once(" => ")?;
+    let (prefix, old, new, suffix) = match (before.rsplit_once('{'), after.split_once('}')) {
+        (Some((prefix, old)), Some((new, suffix))) => (prefix, old, new, suffix),
+        _ => ("", before, after, ""),
+    };
+    // `src/{ => util}/a.rs` leaves an empty side, hence the doubled slash
+    let join = |middle: &str| PathBuf::from(format!("{}{}{}", prefix, middle, suffix).replace("//", "/"));
+    Some((join(old), join(new)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runner_follows_the_strongest_project_file() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(detect_test_runner(dir.path()), None);
+        std::fs::write(dir.path().join("pyproject.toml"), "[tool.black]\n").unwrap();
+        assert_eq!(detect_test_runner(dir.path()), Some(TestRunner::Pytest));
+        std::fs::write(dir.path().join("package.json"), r#"{"scripts": {"test": "jest"}}"#).unwrap();
+        assert_eq!(detect_test_runner(dir.path()), Some(TestRunner::Jest));
+        std::fs::write(dir.path().join("conftest.py"), "").unwrap();
+        assert_eq!(detect_test_runner(dir.path()), Some(TestRunner::Pytest));
+        std::fs::write(dir.path().join("Cargo.toml"), "").unwrap();
+        assert_eq!(detect_test_runner(dir.path()), Some(TestRunner::Cargo));
+
+        let (passed, output, report) = runner_missing("pytest");
+        assert!(passed && report.is_none());
+        assert_eq!(output.as_deref(), Some("Tests skipped: `pytest` is not installed"));
+    }
+
+    #[test]
+    fn test_parse_git_diff_stat() {
+        let output = "10\t5\tsrc/main.rs\n20\t3\tsrc/lib.rs";
+        let stats = parse_git_diff_stat(output, &IgnoreRules::default()).unwrap();
+        assert_eq!(stats.insertions, 30);
+        assert_eq!(stats.deletions, 8);
+        assert_eq!(stats.files_changed, 2);
+
+        let output = "10\t5\tsrc/main.rs\n900\t400\tCargo.lock";
+        let stats = parse_git_diff_stat(output, &IgnoreRules::parse("Cargo.lock\n")).unwrap();
+        assert_eq!(stats.total_lines(), 15);
+        assert_eq!(stats.files_changed, 1);
+    }
+
+    #[test]
+    fn numstat_classifies_renames_binaries_and_new_files() {
+        let output = "\
+4\t0\tsrc/new.rs
+0\t12\tsrc/old.rs
+2\t1\tsrc/{cache.rs => lru.rs}
+0\t0\tdocs/{ => guide}/intro.md
+1\t1\tREADME.md => README
+-\t-\tassets/logo.png
+3\t3\tsrc/main.rs
+ create mode 100644 src/new.rs
+ delete mode 100644 src/old.rs
+ rename src/{cache.rs => lru.rs} (90%)
+ create mode 100644 assets/logo.png
+";
+        let stats = parse_git_diff_stat(output, &IgnoreRules::default()).unwrap();
+        assert_eq!(stats.files_changed, 7);
+        assert_eq!((stats.insertions, stats.deletions), (10, 17));
+        assert_eq!(stats.binary_files, 1);
+        let paths = |list: &[PathBuf]| list.iter().map(|p| p.to_string_lossy().into_owned()).collect::<Vec<_>>();
+        assert_eq!(paths(&stats.files_added), vec!["src/new.rs", "assets/logo.png"]);
+        assert_eq!(paths(&stats.files_deleted), vec!["src/old.rs"]);
+        assert_eq!(paths(&stats.files_modified), vec!["src/main.rs"]);
+        assert_eq!(
+            stats.files_renamed,
+            vec![
+                (PathBuf::from("src/cache.rs"), PathBuf::from("src/lru.rs")),
+                (PathBuf::from("docs/intro.md"), PathBuf::from("docs/guide/intro.md")),
+                (PathBuf::from("README.md"), PathBuf::from("README")),
+            ]
+        );
+    }
+
+    #[test]
+    fn git2_stats_match_numstat() {
+        let dir = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            let output = Command::new("git")
+                .args(["-c", "user.name=t", "-c", "user.email=t@example.com"])
+                .args(args)
+                .current_dir(dir.path())
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+            String::from_utf8_lossy(&output.stdout).into_owned()
+        };
+        let body: String = (0..20).map(|i| format!("line {}\n", i)).collect();
+        git(&["init", "-q", "-b", "main"]);
+        std::fs::write(dir.path().join("cache.rs"), &body).unwrap();
+        std::fs::write(dir.path().join("old.rs"), "a\nb\n").unwrap();
+        std::fs::write(dir.path().join("main.rs"), "fn main() {}\n").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "-qm", "base"]);
+
+        git(&["switch", "-qc", "solution"]);
+        git(&["mv", "cache.rs", "lru.rs"]);
+        std::fs::write(dir.path().join("lru.rs"), format!("{}line 20\n", body)).unwrap();
+        git(&["rm", "-q", "old.rs"]);
+        std::fs::write(dir.path().join("main.rs"), "fn main() { run() }\n").unwrap();
+        std::fs::write(dir.path().join("logo.png"), [0u8, 159, 146, 150, 0, 1]).unwrap();
+        std::fs::write(dir.path().join("new.rs"), "pub fn run() {}\n").unwrap();
+        git(&["add", "-A"]);
+        git(&["commit", "-qm", "solution"]);
+
+        let stats = diff_stats(dir.path(), "main", &IgnoreRules::default()).unwrap();
+        let numstat = git(&["diff", "-M", "--numstat", "--summary", "main...HEAD"]);
+        let expected = parse_git_diff_stat(&numstat, &IgnoreRules::default()).unwrap();
+        assert_eq!(stats.files_changed, 5);
+        assert_eq!(stats.binary_files, 1);
+        assert_eq!(stats.files_renamed, vec![(PathBuf::from("cache.rs"), PathBuf::from("lru.rs"))]);
+        assert_eq!(stats.files_deleted, vec![PathBuf::from("old.rs")]);
+        assert_eq!(stats.files_modified, vec![PathBuf::from("main.rs")]);
+        for stats in [&stats, &expected] {
+            assert_eq!((stats.insertions, stats.deletions), (3, 3));
+        }
+        let mut added = stats.files_added.clone();
+        added.sort();
+        assert_eq!(added, vec![PathBuf::from("logo.png"), PathBuf::from("new.rs")]);
+        assert_eq!(expected.files_renamed, stats.files_renamed);
+        assert_eq!(expected.files_changed, stats.files_changed);
+    }
+}
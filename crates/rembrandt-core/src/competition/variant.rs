@@ -0,0 +1,193 @@
+//! Competitor specs: the same agent several times, with different settings
+//!
+//! `--agents` entries read `AGENT[@MODEL][~TEMPERATURE][*COUNT]`:
+//! `claude-code*3` runs three Claude Code competitors, `codex@o3~0.2`
+//! runs Codex with model `o3` at temperature 0.2, and `aider@gpt-4o*2`
+//! runs two Aider competitors on `gpt-4o`. The model is passed with the
+//! agent's model flag and the temperature with its `[temperature_flags]`
+//! flag; both are recorded on each `CompetitorSolution`, so a winning
+//! configuration can be told apart from its siblings.
+
+use crate::agent::AgentType;
+use crate::{RembrandtError, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Settings a competitor runs with beyond its agent type
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct CompetitorVariant {
+    /// Model, passed with the agent's model flag
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Sampling temperature, passed with the agent's `[temperature_flags]` flag
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+}
+
+impl CompetitorVariant {
+    /// Whether the competitor runs with the agent's defaults
+    pub fn is_default(&self) -> bool {
+        self.model.is_none() && self.temperature.is_none()
+    }
+}
+
+impl fmt::Display for CompetitorVariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.model, self.temperature) {
+            (Some(model), Some(temperature)) => {
+                write!(f, "model {}, temperature {}", model, temperature)
+            }
+            (Some(model), None) => write!(f, "model {}", model),
+            (None, Some(temperature)) => write!(f, "temperature {}", temperature),
+            (None, None) => write!(f, "defaults"),
+        }
+    }
+}
+
+/// One competitor to start
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompetitorSpec {
+    pub agent_type: AgentType,
+    pub variant: CompetitorVariant,
+}
+
+impl From<AgentType> for CompetitorSpec {
+    fn from(agent_type: AgentType) -> Self {
+        Self {
+            agent_type,
+            variant: CompetitorVariant::default(),
+        }
+    }
+}
+
+impl CompetitorSpec {
+    /// Parse one `--agents` entry, repeated `*COUNT` times
+    pub fn parse(entry: &str) -> Result<Vec<Self>> {
+        let invalid = |reason: &str| {
+            RembrandtError::Competition(format!("Invalid competitor '{}': {}", entry, reason))
+        };
+
+        let (rest, count) = match entry.rsplit_once('*') {
+            Some((rest, count)) => {
+                let count: usize = count.trim().parse().map_err(|_| invalid("bad count"))?;
+                if count == 0 {
+                    return Err(invalid("count must be at least 1"));
+                }
+                (rest, count)
+            }
+            None => (entry, 1),
+        };
+        let (rest, temperature) = match rest.rsplit_once('~') {
+            Some((rest, temperature)) => {
+                let temperature: f64 = temperature
+                    .trim()
+                    .parse()
+                    .map_err(|_| invalid("bad temperature"))?;
+                (rest, Some(temperature))
+            }
+            None => (rest, None),
+        };
+        let (agent, model) = match rest.split_once('@') {
+            Some((agent, model)) if !model.trim().is_empty() => {
+                (agent, Some(model.trim().to_string()))
+            }
+            Some(_) => return Err(invalid("empty model")),
+            None => (rest, None),
+        };
+        let agent = agent.trim();
+        if agent.is_empty() {
+            return Err(invalid("missing agent type"));
+        }
+
+        let spec = Self {
+            agent_type: AgentType::from_str(agent),
+            variant: CompetitorVariant { model, temperature },
+        };
+        Ok(vec![spec; count])
+    }
+
+    /// Parse every `--agents` entry, in order
+    pub fn parse_all<S: AsRef<str>>(entries: &[S]) -> Result<Vec<Self>> {
+        let mut specs = Vec::new();
+        for entry in entries {
+            specs.extend(Self::parse(entry.as_ref())?);
+        }
+        Ok(specs)
+    }
+}
+
+/// Agent IDs for `specs` in competition `competition_id`
+///
+/// An agent type that appears once keeps the plain `<competition>-<agent>`
+/// ID; repeated ones are numbered from 1.
+pub fn competitor_ids(competition_id: &str, specs: &[CompetitorSpec]) -> Vec<String> {
+    let mut seen: Vec<(String, usize)> = Vec::new();
+    specs
+        .iter()
+        .map(|spec| {
+            let name = spec.agent_type.to_string();
+            let repeated = specs
+                .iter()
+                .filter(|other| other.agent_type == spec.agent_type)
+                .count()
+                > 1;
+            if !repeated {
+                return format!("{}-{}", competition_id, name);
+            }
+            let n = match seen.iter_mut().find(|(seen, _)| *seen == name) {
+                Some((_, n)) => {
+                    *n += 1;
+                    *n
+                }
+                None => {
+                    seen.push((name.clone(), 1));
+                    1
+                }
+            };
+            format!("{}-{}-{}", competition_id, name, n)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_expand_into_distinct_competitors() {
+        let specs =
+            CompetitorSpec::parse_all(&["claude-code*3", "codex@o3~0.2", "aider@gpt-4o"]).unwrap();
+        assert_eq!(specs.len(), 5);
+        assert!(
+            specs[..3]
+                .iter()
+                .all(|s| s.agent_type == AgentType::ClaudeCode)
+        );
+        assert!(specs[0].variant.is_default());
+        assert_eq!(
+            specs[3].variant,
+            CompetitorVariant {
+                model: Some("o3".to_string()),
+                temperature: Some(0.2)
+            }
+        );
+        assert_eq!(specs[3].variant.to_string(), "model o3, temperature 0.2");
+        assert_eq!(specs[4].variant.model.as_deref(), Some("gpt-4o"));
+
+        let ids = competitor_ids("comp-1", &specs);
+        assert_eq!(
+            ids,
+            [
+                "comp-1-claude-code-1",
+                "comp-1-claude-code-2",
+                "comp-1-claude-code-3",
+                "comp-1-codex",
+                "comp-1-aider",
+            ]
+        );
+
+        for bad in ["claude-code*0", "codex~hot", "@o3", "aider@"] {
+            assert!(CompetitorSpec::parse(bad).is_err(), "{}", bad);
+        }
+    }
+}
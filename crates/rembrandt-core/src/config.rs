@@ -0,0 +1,1041 @@
+//! Rembrandt configuration for v2 orchestration paths.
+//!
+//! Settings are read from `.rembrandt/config.toml` in the repository. Every
+//! key is optional; anything missing falls back to `AppConfig::default()`.
+//!
+//! ```toml
+//! csi_poll_interval_secs = 30
+//! csi_remediations = ["nudge", "mark_blocked"]
+//! budget_session_usd = 5.0
+//! budget_daily_usd = 40.0
+//! otlp_endpoint = "http://localhost:4318"
+//! log_format = "json"
+//! buffer_capacity = 262144
+//! restart = "on-failure(3)"
+//!
+//! [log_levels]
+//! "rembrandt::daemon" = "debug"
+//!
+//! [nudge_ladders]
+//! aider = [{ text = "go on" }, { send_message = "Please finish the task." }]
+//! claude = ["enter", "escape_enter"]
+//!
+//! [quit_sequences]
+//! aider = ["interrupt", { text = "/quit" }]
+//!
+//! [model_flags]
+//! amp = "--model {model}"
+//!
+//! [temperature_flags]
+//! my-agent = "--temperature {temperature}"
+//!
+//! [env]
+//! CARGO_TARGET_DIR = "/tmp/agent-target"
+//!
+//! [hooks]
+//! post_worktree = ["npm ci"]
+//!
+//! [worktree]
+//! copy_patterns = [".env", "certs/*.pem"]
+//! copy_mode = "symlink"
+//! lock = true
+//! dir = "~/.rembrandt/worktrees/{repo_hash}"
+//!
+//! [beads]
+//! direct_read = true
+//!
+//! [pool]
+//! base_branch = "main"
+//! agents = { claude = 2 }
+//!
+//! [profiles.quickfix]
+//! agent = "claude-code"
+//! isolation = "worktree"
+//! model = "sonnet"
+//! template = "bugfix"
+//! env = { RUST_LOG = "debug" }
+//!
+//! [api_tokens.ci]
+//! token = "op-71be..."
+//! role = "operator"
+//!
+//! [listen_tls]
+//! cert = "/etc/rembrandt/daemon.crt"
+//! key = "/etc/rembrandt/daemon.key"
+//!
+//! [remotes.buildbox]
+//! host = "build.example.com"
+//! user = "dev"
+//! repo_path = "/srv/src/myproject"
+//!
+//! [[lint]]
+//! name = "clippy"
+//!
+//! [[lint]]
+//! name = "rustfmt"
+//! severity = "warn"
+//!
+//! [security]
+//! threshold = "high"
+//! scanners = [{ name = "gitleaks" }, { name = "semgrep", rules = "p/rust" }]
+//! ```
+
+use crate::agent::{AgentType, NudgeStrategy};
+use crate::auth::ApiToken;
+use crate::beads::BeadsClient;
+use crate::competition::{CompetitionManager, LintCheck, SecurityConfig, SolutionValidator};
+use crate::hooks::HookPoint;
+use crate::isolation::IsolationMode;
+use crate::restart::RestartPolicy;
+use crate::worktree::{CopyMode, CopyRules, WorktreeManager};
+use crate::{RembrandtError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Workspace isolation mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DefaultIsolationMode {
+    Branch,
+    Worktree,
+}
+
+impl From<DefaultIsolationMode> for IsolationMode {
+    fn from(mode: DefaultIsolationMode) -> Self {
+        match mode {
+            DefaultIsolationMode::Branch => IsolationMode::Branch,
+            DefaultIsolationMode::Worktree => IsolationMode::Worktree,
+        }
+    }
+}
+
+/// Preferred terminal backend for attach/observe flows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TerminalBackendKind {
+    None,
+    Tmux,
+    Zellij,
+    Cmux,
+}
+
+/// What the daemon does with running agents when it exits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShutdownPolicy {
+    /// SIGTERM every agent, then SIGKILL whatever is left after
+    /// `shutdown_grace_secs`
+    Terminate,
+    /// Leave agents running and record their PIDs for re-adoption
+    Detach,
+}
+
+/// How the daemon learns that agents printed something.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PtyIo {
+    /// Sleep until a session's reader reports output; idle sessions cost
+    /// nothing
+    #[default]
+    Events,
+    /// Check every session every 10ms, as older versions did
+    Poll,
+}
+
+/// How log events are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per event, with the fields of its enclosing spans
+    /// (`agent_id`, `session_id`, ...) alongside its own
+    Json,
+}
+
+impl std::str::FromStr for LogFormat {
+    type Err = RembrandtError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(RembrandtError::Config(format!(
+                "unknown log format '{}': expected text or json",
+                s
+            ))),
+        }
+    }
+}
+
+/// What a CSI pass does about a stale or silent agent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CsiRemediation {
+    /// Send the agent a nudge through the daemon once stale past
+    /// `csi_stale_after_secs`
+    Nudge,
+    /// Set the session to `blocked` once stale past `csi_block_after_secs`
+    MarkBlocked,
+    /// Log a warning and record a `notify` event
+    Notify,
+}
+
+/// A named spawn configuration (`[profiles.<name>]`), used with
+/// `rembrandt spawn --profile <name>` and in the dashboard's spawn picker.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpawnProfile {
+    /// Agent type, as given to `rembrandt spawn`
+    pub agent: String,
+    /// Workspace isolation (default: worktree)
+    #[serde(default)]
+    pub isolation: Option<DefaultIsolationMode>,
+    /// Model the agent should use
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Prompt template from `.rembrandt/templates/<name>.md`, sent as the
+    /// agent's first prompt
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Environment variables for the agent, on top of `[env]`
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+}
+
+impl SpawnProfile {
+    /// Isolation for spawns with this profile
+    pub fn isolation_mode(&self) -> IsolationMode {
+        self.isolation.map_or(IsolationMode::Worktree, IsolationMode::from)
+    }
+}
+
+/// Shell commands run at points of an agent's life (`[hooks]`; see
+/// `rembrandt::hooks`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HooksConfig {
+    pub pre_spawn: Vec<String>,
+    pub post_worktree: Vec<String>,
+    pub pre_merge: Vec<String>,
+    pub post_merge: Vec<String>,
+    pub on_failure: Vec<String>,
+    /// How long each hook may run before it is killed
+    pub timeout_secs: u64,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            pre_spawn: Vec::new(),
+            post_worktree: Vec::new(),
+            pre_merge: Vec::new(),
+            post_merge: Vec::new(),
+            on_failure: Vec::new(),
+            timeout_secs: 60,
+        }
+    }
+}
+
+impl HooksConfig {
+    /// Commands configured for `point`
+    pub fn commands(&self, point: HookPoint) -> &[String] {
+        match point {
+            HookPoint::PreSpawn => &self.pre_spawn,
+            HookPoint::PostWorktree => &self.post_worktree,
+            HookPoint::PreMerge => &self.pre_merge,
+            HookPoint::PostMerge => &self.post_merge,
+            HookPoint::OnFailure => &self.on_failure,
+        }
+    }
+}
+
+/// How new worktrees are set up (`[worktree]`)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorktreeConfig {
+    /// Untracked files copied from the main checkout into each new worktree,
+    /// e.g. `.env` or `certs/*.pem`
+    pub copy_patterns: Vec<String>,
+    /// `copy` (default) or `symlink`
+    pub copy_mode: CopyMode,
+    /// Lock agent worktrees (`git worktree lock`) and put a guard file in
+    /// each naming the agent that owns it
+    pub lock: bool,
+    /// Directory agent worktrees are created in instead of
+    /// `.rembrandt/agents`, e.g. `~/.rembrandt/worktrees/{repo_hash}`;
+    /// `{repo}` is the repository's directory name
+    pub dir: Option<String>,
+}
+
+impl WorktreeConfig {
+    /// The worktree manager for `repo_path` with these settings applied
+    pub fn manager(&self, repo_path: impl AsRef<Path>) -> Result<WorktreeManager> {
+        let repo_path = repo_path.as_ref();
+        let mut manager = WorktreeManager::new(repo_path)?
+            .with_copy_rules(self.copy_rules())
+            .with_locking(self.lock);
+        if let Some(dir) = self.worktrees_dir(repo_path) {
+            manager = manager.with_worktrees_dir(dir);
+        }
+        Ok(manager)
+    }
+
+    /// The configured worktree directory for `repo_path`, placeholders and
+    /// a leading `~` expanded; `None` for the default
+    pub fn worktrees_dir(&self, repo_path: &Path) -> Option<PathBuf> {
+        let template = self.dir.as_deref()?;
+        let repo = repo_path.canonicalize().unwrap_or_else(|_| repo_path.to_path_buf());
+        let name = repo.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        let expanded = template
+            .replace("{repo_hash}", &repo_hash(&repo))
+            .replace("{repo}", &name);
+        let path = match (expanded.strip_prefix("~/"), std::env::var_os("HOME")) {
+            (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+            _ => PathBuf::from(expanded),
+        };
+        Some(if path.is_relative() { repo.join(path) } else { path })
+    }
+
+    /// Rules for `WorktreeManager::with_copy_rules`
+    pub fn copy_rules(&self) -> CopyRules {
+        CopyRules {
+            patterns: self.copy_patterns.clone(),
+            mode: self.copy_mode,
+        }
+    }
+}
+
+/// How Beads is reached (`[beads]`)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BeadsConfig {
+    /// Read ready tasks and task details straight from the Beads SQLite
+    /// database instead of running `br`/`bd`; changes still use the CLI
+    pub direct_read: bool,
+    /// Database to read, relative to the repository; defaults to
+    /// `.beads/beads.db`
+    pub db_path: Option<PathBuf>,
+}
+
+impl BeadsConfig {
+    /// The Beads client for `repo_path`, reading its database directly when
+    /// `direct_read` is set
+    pub fn client(&self, repo_path: impl AsRef<Path>) -> BeadsClient {
+        let repo_path = repo_path.as_ref();
+        let client = BeadsClient::detect().in_dir(repo_path);
+        if !self.direct_read {
+            return client;
+        }
+        let db_path = self.db_path.as_deref().unwrap_or(Path::new(".beads/beads.db"));
+        client.with_database(repo_path.join(db_path))
+    }
+}
+
+/// Idle agents kept running for `spawn` to take (`[pool]`)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PoolConfig {
+    /// Branch the pooled agents' worktrees are created from
+    pub base_branch: String,
+    /// How many idle agents to keep, by agent type
+    pub agents: BTreeMap<String, usize>,
+}
+
+impl Default for PoolConfig {
+    fn default() -> Self {
+        Self {
+            base_branch: "main".to_string(),
+            agents: BTreeMap::new(),
+        }
+    }
+}
+
+/// Drop entries of `table` that are the same in `defaults`, and tables left
+/// empty by that
+fn strip_defaults(table: &mut toml::Table, defaults: &toml::Table) {
+    table.retain(|key, value| match (value, defaults.get(key)) {
+        (toml::Value::Table(nested), Some(toml::Value::Table(default))) => {
+            strip_defaults(nested, default);
+            !nested.is_empty()
+        }
+        (value, default) => default != Some(value),
+    });
+}
+
+/// Short stable id of a repository path: FNV-1a, as 12 hex digits
+fn repo_hash(path: &Path) -> String {
+    let hash = path.to_string_lossy().bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)[..12].to_string()
+}
+
+/// Certificate and key `rembrandt daemon --listen` serves TLS with
+/// (`[listen_tls]`); PEM files, relative paths from the repository root.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ListenTlsConfig {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+/// A machine agents can be run on over SSH (`[remotes.<name>]`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoteHostConfig {
+    /// Hostname or `~/.ssh/config` alias
+    pub host: String,
+    #[serde(default)]
+    pub user: Option<String>,
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub identity_file: Option<PathBuf>,
+    /// Non-bare clone of the repository on the remote machine
+    pub repo_path: String,
+    /// Extra `-o` options passed to ssh (e.g. `ServerAliveInterval=30`)
+    #[serde(default)]
+    pub ssh_options: Vec<String>,
+}
+
+/// Runtime config for v2 services.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    pub default_spawn_isolation: DefaultIsolationMode,
+    pub default_compete_isolation: DefaultIsolationMode,
+    pub csi_poll_interval_secs: u64,
+    /// Heartbeat age after which CSI reports an agent as stale
+    pub csi_stale_after_secs: u64,
+    /// Heartbeat age after which `mark_blocked` applies
+    pub csi_block_after_secs: u64,
+    pub csi_remediations: Vec<CsiRemediation>,
+    pub terminal_backend: TerminalBackendKind,
+    /// Flag sessions whose cumulative cost passes this many USD
+    pub usage_alert_usd: Option<f64>,
+    /// Stop a session once it has cost this many USD
+    pub budget_session_usd: Option<f64>,
+    /// Stop all sessions once today's total cost passes this many USD
+    pub budget_daily_usd: Option<f64>,
+    /// Stop a session once it has run this many minutes
+    pub budget_session_minutes: Option<u64>,
+    /// Steer sent before stopping, giving the agent `budget_grace_secs` to
+    /// commit its work; unset to stop immediately
+    pub budget_wrap_up_message: Option<String>,
+    pub budget_grace_secs: u64,
+    /// `text` or `json` log output; `--log-format` overrides it
+    pub log_format: LogFormat,
+    /// Log levels by module (`[log_levels]`), e.g. `"rembrandt::daemon" =
+    /// "debug"`, on top of `RUST_LOG` and the default `rembrandt=info`
+    pub log_levels: BTreeMap<String, String>,
+    /// OTLP/HTTP collector to export trace spans to (needs the `otel` feature)
+    pub otlp_endpoint: Option<String>,
+    /// `service.name` reported with exported spans
+    pub otlp_service_name: String,
+    pub shutdown_policy: ShutdownPolicy,
+    pub shutdown_grace_secs: u64,
+    /// `events` (default) or `poll`: how the daemon picks up agent output
+    pub pty_io: PtyIo,
+    /// Days session logs (`.rembrandt/logs`) are kept; 0 keeps them forever
+    pub log_retention_days: u64,
+    /// Megabytes of session logs kept, oldest removed first; 0 for no cap
+    pub log_retention_mb: u64,
+    /// Seconds between WAL checkpoints of state.db by `rembrandt csi run`;
+    /// 0 leaves checkpointing to SQLite
+    pub wal_checkpoint_interval_secs: u64,
+    pub remotes: BTreeMap<String, RemoteHostConfig>,
+    /// Tokens daemon clients can present, by name (`[api_tokens.<name>]`)
+    pub api_tokens: BTreeMap<String, ApiToken>,
+    /// TLS for the daemon's TCP listener; required to listen on anything
+    /// but loopback
+    pub listen_tls: Option<ListenTlsConfig>,
+    /// Nudge ladders by agent type (`claude`, `aider`, ...), replacing the
+    /// built-in ones
+    pub nudge_ladders: BTreeMap<String, Vec<NudgeStrategy>>,
+    /// Shell commands `rembrandt sync` runs in an agent's worktree after
+    /// updating it; empty to detect cargo/npm checks like competitions do
+    pub validation_commands: Vec<String>,
+    /// Lint and formatting checks (`[[lint]]`) run by competition
+    /// validation, the merge queue, `rembrandt sync` and reports
+    pub lint: Vec<LintCheck>,
+    /// Security scanners run over agents' changes by competition validation
+    /// and the merge queue
+    pub security: SecurityConfig,
+    /// Smoke check run in each agent's checkout by `rembrandt csi run`,
+    /// e.g. `cargo check -q`; unset to disable health probes
+    pub health_command: Option<String>,
+    /// Minutes between health checks of the same agent
+    pub health_interval_mins: u64,
+    /// Minutes of failing health checks before the build counts as broken
+    pub health_broken_after_mins: u64,
+    /// Seconds a health check may run before it is killed and counts as failing
+    pub health_timeout_secs: u64,
+    /// Steer an agent with the failure output when its health check starts failing
+    pub health_steer: bool,
+    /// Write `.rembrandt/reports/<agent>.md` when an agent stops or finishes
+    pub generate_reports: bool,
+    /// Command that reads a prompt on stdin and prints a change summary for
+    /// reports, e.g. `claude -p --model claude-haiku-4-5`
+    pub report_summary_command: Option<String>,
+    /// Command that reads a prompt on stdin and prints JSON for `rembrandt
+    /// plan`; defaults to `report_summary_command`
+    pub plan_command: Option<String>,
+    /// Bytes of output history kept per session for late attach (default 10KB)
+    pub buffer_capacity: Option<usize>,
+    /// Keystrokes that ask an agent to exit, by agent type, replacing the
+    /// built-in ones (Ctrl+C then `/exit` for Claude)
+    pub quit_sequences: BTreeMap<String, Vec<NudgeStrategy>>,
+    /// How long `rembrandt stop` waits after each step (quit sequence,
+    /// SIGTERM) before escalating to the next
+    pub stop_grace_secs: u64,
+    /// Whether the daemon respawns detached agents that crash: `never`,
+    /// `on-failure` or `on-failure(N)` (N retries, default 3)
+    pub restart: RestartPolicy,
+    /// Named spawn configurations
+    pub profiles: BTreeMap<String, SpawnProfile>,
+    /// Flag templates that pick a model, by agent type, replacing the
+    /// built-in ones (`--model {model}` for Claude, Codex, OpenCode, Aider)
+    pub model_flags: BTreeMap<String, String>,
+    /// Flag templates that set the sampling temperature, by agent type; no
+    /// agent has one built in
+    pub temperature_flags: BTreeMap<String, String>,
+    /// Environment variables set for every spawned agent
+    pub env: BTreeMap<String, String>,
+    pub hooks: HooksConfig,
+    pub worktree: WorktreeConfig,
+    pub beads: BeadsConfig,
+    pub pool: PoolConfig,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            default_spawn_isolation: DefaultIsolationMode::Branch,
+            default_compete_isolation: DefaultIsolationMode::Worktree,
+            csi_poll_interval_secs: 15,
+            csi_stale_after_secs: 300,
+            csi_block_after_secs: 1800,
+            csi_remediations: vec![CsiRemediation::Nudge, CsiRemediation::Notify],
+            terminal_backend: TerminalBackendKind::None,
+            usage_alert_usd: None,
+            budget_session_usd: None,
+            budget_daily_usd: None,
+            budget_session_minutes: None,
+            budget_wrap_up_message: Some(
+                "You have hit your budget. Wrap up now: commit what you have with a summary of what's left, then stop."
+                    .to_string(),
+            ),
+            budget_grace_secs: 120,
+            log_format: LogFormat::Text,
+            log_levels: BTreeMap::new(),
+            otlp_endpoint: None,
+            otlp_service_name: "rembrandt".to_string(),
+            shutdown_policy: ShutdownPolicy::Terminate,
+            shutdown_grace_secs: 10,
+            pty_io: PtyIo::Events,
+            log_retention_days: 30,
+            log_retention_mb: 500,
+            wal_checkpoint_interval_secs: 300,
+            remotes: BTreeMap::new(),
+            api_tokens: BTreeMap::new(),
+            listen_tls: None,
+            nudge_ladders: BTreeMap::new(),
+            validation_commands: Vec::new(),
+            lint: Vec::new(),
+            security: SecurityConfig::default(),
+            health_command: None,
+            health_interval_mins: 10,
+            health_broken_after_mins: 30,
+            health_timeout_secs: 300,
+            health_steer: false,
+            generate_reports: false,
+            report_summary_command: None,
+            plan_command: None,
+            buffer_capacity: None,
+            quit_sequences: BTreeMap::new(),
+            stop_grace_secs: 10,
+            restart: RestartPolicy::Never,
+            profiles: BTreeMap::new(),
+            model_flags: BTreeMap::new(),
+            temperature_flags: BTreeMap::new(),
+            env: BTreeMap::new(),
+            hooks: HooksConfig::default(),
+            worktree: WorktreeConfig::default(),
+            beads: BeadsConfig::default(),
+            pool: PoolConfig::default(),
+        }
+    }
+}
+
+impl AppConfig {
+    /// Path of the config file for a repository
+    pub fn path(repo_path: impl AsRef<Path>) -> PathBuf {
+        repo_path.as_ref().join(".rembrandt").join("config.toml")
+    }
+
+    /// Load `.rembrandt/config.toml`, or the defaults if there is none
+    pub fn load(repo_path: impl AsRef<Path>) -> Result<Self> {
+        let path = Self::path(repo_path);
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => Self::from_toml(&contents)
+                .map_err(|e| RembrandtError::Config(format!("{}: {}", path.display(), e))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Parse config file contents
+    pub fn from_toml(contents: &str) -> Result<Self> {
+        toml::from_str(contents).map_err(|e| RembrandtError::Config(e.to_string()))
+    }
+
+    /// Parse settings given as JSON, e.g. by the GUI's settings screen;
+    /// missing keys take their defaults
+    pub fn from_json(value: serde_json::Value) -> Result<Self> {
+        serde_json::from_value(value).map_err(|e| RembrandtError::Config(e.to_string()))
+    }
+
+    /// The settings that differ from the defaults, as config file contents
+    pub fn to_toml(&self) -> Result<String> {
+        let to_table = |config: &Self| {
+            toml::Table::try_from(config).map_err(|e| RembrandtError::Config(e.to_string()))
+        };
+        let mut table = to_table(self)?;
+        strip_defaults(&mut table, &to_table(&Self::default())?);
+        toml::to_string_pretty(&table).map_err(|e| RembrandtError::Config(e.to_string()))
+    }
+
+    /// Write the settings that differ from the defaults to
+    /// `.rembrandt/config.toml`, returning its path
+    ///
+    /// The file is rewritten, so comments in it are lost; the previous one is
+    /// kept as `config.toml.bak`.
+    pub fn save(&self, repo_path: impl AsRef<Path>) -> Result<PathBuf> {
+        let path = Self::path(repo_path);
+        let contents = self.to_toml()?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        if path.exists() {
+            std::fs::copy(&path, path.with_extension("toml.bak"))?;
+        }
+        std::fs::write(&path, contents)?;
+        Ok(path)
+    }
+
+    /// Validator for solutions against `base_branch`, with the configured
+    /// lint checks and security scanners
+    pub fn solution_validator(&self, base_branch: &str) -> SolutionValidator {
+        SolutionValidator::new(base_branch.to_string())
+            .with_lint_checks(self.lint.clone())
+            .with_security(self.security.clone())
+    }
+
+    /// Competition manager for `base_branch`, validating solutions like
+    /// `solution_validator` and placing competitors' worktrees like
+    /// `[worktree]` says
+    pub fn competition_manager(&self, repo_path: impl AsRef<Path>, base_branch: &str) -> Result<CompetitionManager> {
+        let repo_path = repo_path.as_ref();
+        let mut manager = CompetitionManager::new(repo_path.to_path_buf(), base_branch.to_string())?
+            .with_copy_rules(self.worktree.copy_rules())
+            .with_lint_checks(self.lint.clone())
+            .with_security(self.security.clone());
+        if let Some(dir) = self.worktree.worktrees_dir(repo_path) {
+            manager = manager.with_worktrees_dir(dir);
+        }
+        Ok(manager)
+    }
+
+    /// Configured nudge ladders keyed by agent type, for
+    /// `SessionManager::set_nudge_ladders`
+    pub fn agent_nudge_ladders(&self) -> HashMap<AgentType, Vec<NudgeStrategy>> {
+        self.nudge_ladders
+            .iter()
+            .filter(|(_, ladder)| !ladder.is_empty())
+            .map(|(agent, ladder)| (AgentType::from_str(agent), ladder.clone()))
+            .collect()
+    }
+
+    /// Configured quit sequences keyed by agent type, for
+    /// `SessionManager::set_quit_sequences`
+    pub fn agent_quit_sequences(&self) -> HashMap<AgentType, Vec<NudgeStrategy>> {
+        self.quit_sequences
+            .iter()
+            .filter(|(_, sequence)| !sequence.is_empty())
+            .map(|(agent, sequence)| (AgentType::from_str(agent), sequence.clone()))
+            .collect()
+    }
+
+    /// Arguments that run `agent` with `model`, from `[model_flags]` or the
+    /// agent's built-in flag
+    pub fn model_args(&self, agent: &AgentType, model: &str) -> Result<Vec<String>> {
+        let flag = self
+            .model_flags
+            .iter()
+            .find(|(name, _)| AgentType::from_str(name) == *agent)
+            .map(|(_, flag)| flag.as_str())
+            .or_else(|| agent.default_model_flag())
+            .ok_or_else(|| {
+                RembrandtError::Config(format!(
+                    "{} has no flag for choosing a model (set one under [model_flags])",
+                    agent
+                ))
+            })?;
+        Ok(crate::agent::model_args(flag, model))
+    }
+
+    /// Arguments that run `agent` at `temperature`, from `[temperature_flags]`
+    pub fn temperature_args(&self, agent: &AgentType, temperature: f64) -> Result<Vec<String>> {
+        let flag = self
+            .temperature_flags
+            .iter()
+            .find(|(name, _)| AgentType::from_str(name) == *agent)
+            .map(|(_, flag)| flag)
+            .ok_or_else(|| {
+                RembrandtError::Config(format!(
+                    "{} has no flag for setting a temperature (set one under [temperature_flags])",
+                    agent
+                ))
+            })?;
+        Ok(flag
+            .split_whitespace()
+            .map(|arg| arg.replace("{temperature}", &temperature.to_string()))
+            .collect())
+    }
+
+    /// Environment for a spawned agent, later entries winning: `[env]`, the
+    /// profile's `env`, `extra` (e.g. `--env`), then the
+    /// `REMBRANDT_AGENT_ID`/`REMBRANDT_BRANCH`/`REMBRANDT_TASK_ID` context
+    pub fn spawn_env(
+        &self,
+        profile: Option<&SpawnProfile>,
+        extra: &[(String, String)],
+        agent_id: &str,
+        branch: &str,
+        task_id: Option<&str>,
+    ) -> Vec<(String, String)> {
+        let mut env: Vec<(String, String)> = self
+            .env
+            .iter()
+            .chain(profile.into_iter().flat_map(|p| p.env.iter()))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        env.extend(extra.iter().cloned());
+        env.extend(crate::agent::context_env(agent_id, branch, task_id));
+        env
+    }
+
+    /// Look up a configured SSH remote by name
+    pub fn remote(&self, name: &str) -> Result<&RemoteHostConfig> {
+        self.remotes.get(name).ok_or_else(|| {
+            let known: Vec<&str> = self.remotes.keys().map(String::as_str).collect();
+            RembrandtError::Config(format!(
+                "Unknown remote '{}' (configured: {})",
+                name,
+                if known.is_empty() { "none".to_string() } else { known.join(", ") }
+            ))
+        })
+    }
+
+    /// Look up a spawn profile by name
+    pub fn profile(&self, name: &str) -> Result<&SpawnProfile> {
+        self.profiles.get(name).ok_or_else(|| {
+            let known: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            RembrandtError::Config(format!(
+                "Unknown profile '{}' (configured: {})",
+                name,
+                if known.is_empty() { "none".to_string() } else { known.join(", ") }
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_config_keeps_defaults() {
+        let config = AppConfig::from_toml(
+            r#"
+            default_spawn_isolation = "worktree"
+
+            [remotes.buildbox]
+            host = "build.example.com"
+            port = 2222
+            repo_path = "/srv/src/app"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.default_spawn_isolation, DefaultIsolationMode::Worktree);
+        assert_eq!(config.csi_poll_interval_secs, 15);
+        assert_eq!(config.csi_remediations, AppConfig::default().csi_remediations);
+        assert_eq!(config.buffer_capacity, None);
+        let remote = config.remote("buildbox").unwrap();
+        assert_eq!(remote.port, Some(2222));
+        assert_eq!(remote.user, None);
+        assert!(config.remote("laptop").is_err());
+    }
+
+    #[test]
+    fn saved_config_keeps_only_changed_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(AppConfig::default().to_toml().unwrap(), "");
+
+        let config = AppConfig::from_toml(
+            r#"
+            restart = "on-failure(2)"
+            budget_daily_usd = 40.0
+            csi_remediations = ["nudge", "notify"]
+
+            [nudge_ladders]
+            aider = [{ text = "go on" }, "enter"]
+
+            [hooks]
+            post_worktree = ["npm ci"]
+
+            [profiles.quickfix]
+            agent = "claude-code"
+            model = "sonnet"
+
+            [api_tokens.ci]
+            token = "op-71be"
+            role = "operator"
+
+            [[lint]]
+            name = "clippy"
+            "#,
+        )
+        .unwrap();
+        let path = config.save(dir.path()).unwrap();
+        let saved = std::fs::read_to_string(&path).unwrap();
+        // Unchanged settings, like the default remediations and hook timeout, stay out
+        assert!(!saved.contains("csi_remediations") && !saved.contains("timeout_secs"), "{}", saved);
+        assert_eq!(AppConfig::load(dir.path()).unwrap(), config);
+
+        // Settings from the GUI arrive as JSON; the old file is kept
+        let edited = AppConfig::from_json(serde_json::json!({ "stop_grace_secs": 5 })).unwrap();
+        edited.save(dir.path()).unwrap();
+        assert_eq!(AppConfig::load(dir.path()).unwrap().stop_grace_secs, 5);
+        assert_eq!(std::fs::read_to_string(path.with_extension("toml.bak")).unwrap(), saved);
+        assert!(AppConfig::from_json(serde_json::json!({ "stop_grace_secs": "soon" })).is_err());
+    }
+
+    #[test]
+    fn worktree_dir_expands_placeholders() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("app");
+        std::fs::create_dir(&repo).unwrap();
+        let config = AppConfig::from_toml("[worktree]\ndir = \"/srv/wt/{repo}-{repo_hash}\"\n").unwrap();
+        let resolved = config.worktree.worktrees_dir(&repo).unwrap();
+        let name = resolved.file_name().unwrap().to_string_lossy().into_owned();
+        assert!(name.starts_with("app-") && name.len() == "app-".len() + 12, "{}", name);
+        // Stable across calls, distinct per repository
+        assert_eq!(config.worktree.worktrees_dir(&repo), Some(resolved.clone()));
+        assert_ne!(config.worktree.worktrees_dir(dir.path()), Some(resolved));
+        let relative = AppConfig::from_toml("[worktree]\ndir = \"../wt\"\n").unwrap();
+        assert!(relative.worktree.worktrees_dir(&repo).unwrap().ends_with("app/../wt"));
+        assert_eq!(AppConfig::default().worktree.worktrees_dir(&repo), None);
+    }
+
+    #[tokio::test]
+    async fn competitors_get_the_worktree_settings() {
+        use crate::agent::{AgentRegistry, AgentType};
+        use crate::competition::EvaluatorStrategy;
+        use git2::{Repository, Signature};
+
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let sig = Signature::now("test", "test@example.com").unwrap();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+        repo.commit(Some("refs/heads/main"), &sig, &sig, "init", &tree, &[]).unwrap();
+        std::fs::write(dir.path().join(".env"), "TOKEN=1\n").unwrap();
+        let outside = tempfile::tempdir().unwrap();
+
+        let config = AppConfig::from_toml(&format!(
+            "[worktree]\ncopy_patterns = [\".env\"]\ndir = {:?}\n",
+            outside.path().display().to_string()
+        ))
+        .unwrap();
+        let mut manager = config.competition_manager(dir.path(), "main").unwrap();
+        let mut registry = AgentRegistry::new();
+        let id = manager
+            .start_competition(
+                "task".to_string(),
+                vec![AgentType::Custom("solo".to_string())],
+                EvaluatorStrategy::default(),
+                60,
+                &mut registry,
+            )
+            .await
+            .unwrap();
+        let worktree = &manager.get_competition(&id).unwrap().competitors[0].worktree_path;
+        assert!(worktree.starts_with(outside.path()), "{}", worktree.display());
+        assert_eq!(std::fs::read_to_string(worktree.join(".env")).unwrap(), "TOKEN=1\n");
+    }
+
+    #[test]
+    fn beads_direct_read_points_at_the_repo_database() {
+        let config = AppConfig::from_toml("[beads]\ndirect_read = true\n").unwrap();
+        let client = config.beads.client("/src/app");
+        assert_eq!(client.database(), Some(Path::new("/src/app/.beads/beads.db")));
+        let config = AppConfig::from_toml("[beads]\ndirect_read = true\ndb_path = \"/var/beads.db\"\n").unwrap();
+        assert_eq!(config.beads.client("/src/app").database(), Some(Path::new("/var/beads.db")));
+        assert_eq!(AppConfig::default().beads.client("/src/app").database(), None);
+    }
+
+    #[test]
+    fn lint_checks_and_security_scanners_parse() {
+        use crate::competition::{FindingSeverity, LintSeverity};
+
+        let config = AppConfig::from_toml(
+            r#"
+            [[lint]]
+            name = "clippy"
+
+            [[lint]]
+            name = "mypy"
+            command = "mypy src"
+            severity = "warn"
+
+            [security]
+            threshold = "critical"
+            scanners = [{ name = "gitleaks" }, { name = "trivy", command = "trivy fs .", severity = "medium" }]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.lint[0].severity, LintSeverity::Block);
+        assert_eq!(config.lint[1].command(), Some("mypy src"));
+        assert_eq!(config.security.threshold, FindingSeverity::Critical);
+        assert_eq!(config.security.scanners[1].severity, FindingSeverity::Medium);
+        assert_eq!(AppConfig::default().security.threshold, FindingSeverity::High);
+    }
+
+    #[test]
+    fn profiles_resolve_by_name() {
+        let config = AppConfig::from_toml(
+            r#"
+            [profiles.quickfix]
+            agent = "claude-code"
+            isolation = "branch"
+            model = "sonnet"
+            template = "bugfix"
+
+            [profiles.bare]
+            agent = "aider"
+            "#,
+        )
+        .unwrap();
+
+        let quickfix = config.profile("quickfix").unwrap();
+        assert_eq!(quickfix.agent, "claude-code");
+        assert_eq!(quickfix.isolation_mode(), IsolationMode::Branch);
+        assert_eq!(quickfix.model.as_deref(), Some("sonnet"));
+        assert_eq!(quickfix.template.as_deref(), Some("bugfix"));
+        assert_eq!(config.profile("bare").unwrap().isolation_mode(), IsolationMode::Worktree);
+        let err = config.profile("nightly").unwrap_err().to_string();
+        assert!(err.contains("bare, quickfix"), "{}", err);
+    }
+
+    #[test]
+    fn model_flags_override_the_built_in_ones() {
+        let config = AppConfig::from_toml(
+            r#"
+            [model_flags]
+            amp = "--model={model}"
+            aider = "--model {model} --no-auto-commits"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.model_args(&AgentType::AmpCode, "x").unwrap(), vec!["--model=x"]);
+        assert_eq!(
+            config.model_args(&AgentType::Aider, "gpt-4o").unwrap(),
+            vec!["--model", "gpt-4o", "--no-auto-commits"]
+        );
+        assert_eq!(config.model_args(&AgentType::Codex, "o3").unwrap(), vec!["--model", "o3"]);
+        assert!(config.model_args(&AgentType::Custom("foo".into()), "x").is_err());
+
+        let config = AppConfig::from_toml("[temperature_flags]\nmy-agent = \"-t {temperature}\"").unwrap();
+        let agent = AgentType::from_str("my-agent");
+        assert_eq!(config.temperature_args(&agent, 0.2).unwrap(), vec!["-t", "0.2"]);
+        assert!(config.temperature_args(&AgentType::ClaudeCode, 0.2).is_err());
+    }
+
+    #[test]
+    fn spawn_env_layers_config_profile_flags_and_context() {
+        let config = AppConfig::from_toml(
+            r#"
+            [env]
+            RUST_LOG = "info"
+            CARGO_TARGET_DIR = "/tmp/t"
+
+            [profiles.debug]
+            agent = "claude"
+            env = { RUST_LOG = "debug" }
+            "#,
+        )
+        .unwrap();
+        let extra = vec![("REMBRANDT_AGENT_ID".to_string(), "spoofed".to_string())];
+        let env = config.spawn_env(config.profiles.get("debug"), &extra, "claude-1", "rembrandt/claude-1", None);
+
+        // Later entries override earlier ones when applied, as with `env`
+        let applied: HashMap<_, _> = env.into_iter().collect();
+        assert_eq!(applied["RUST_LOG"], "debug");
+        assert_eq!(applied["CARGO_TARGET_DIR"], "/tmp/t");
+        assert_eq!(applied["REMBRANDT_AGENT_ID"], "claude-1");
+        assert_eq!(applied["REMBRANDT_BRANCH"], "rembrandt/claude-1");
+        assert!(!applied.contains_key("REMBRANDT_TASK_ID"));
+    }
+
+    #[test]
+    fn nudge_ladders_mix_keys_and_messages() {
+        let config = AppConfig::from_toml(
+            r#"
+            [nudge_ladders]
+            aider = [{ text = "go on" }, { send_message = "Please finish." }]
+            claude = ["enter", "escape_enter"]
+            "#,
+        )
+        .unwrap();
+
+        let ladders = config.agent_nudge_ladders();
+        assert_eq!(
+            ladders[&AgentType::Aider],
+            vec![
+                NudgeStrategy::Text("go on".to_string()),
+                NudgeStrategy::SendMessage("Please finish.".to_string()),
+            ]
+        );
+        assert_eq!(
+            ladders[&AgentType::ClaudeCode],
+            vec![NudgeStrategy::Enter, NudgeStrategy::EscapeEnter]
+        );
+    }
+
+    #[test]
+    fn quit_sequences_override_by_agent_type() {
+        let config = AppConfig::from_toml(
+            r#"
+            stop_grace_secs = 3
+            restart = "on-failure(2)"
+
+            [quit_sequences]
+            aider = ["interrupt", { text = "/quit" }]
+            codex = []
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.stop_grace_secs, 3);
+        assert_eq!(config.restart, RestartPolicy::OnFailure { max_retries: 2 });
+        let sequences = config.agent_quit_sequences();
+        assert_eq!(
+            sequences[&AgentType::Aider],
+            vec![NudgeStrategy::Interrupt, NudgeStrategy::Text("/quit".to_string())]
+        );
+        // An empty sequence keeps the default rather than sending nothing
+        assert!(!sequences.contains_key(&AgentType::Codex));
+    }
+}
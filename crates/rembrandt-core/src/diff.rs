@@ -0,0 +1,281 @@
+//! Structured branch diffs for review
+//!
+//! Turns a git2 diff into per-file patches (hunks and lines) that the GUI
+//! can render without parsing unified diff text.
+
+use crate::Result;
+use git2::{Delta, DiffOptions, Patch, Repository};
+use serde::{Deserialize, Serialize};
+
+/// How a file changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileChange {
+    Added,
+    Modified,
+    Deleted,
+    Renamed,
+    Copied,
+    TypeChange,
+}
+
+/// One changed file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileDiff {
+    /// Path on the branch side (the old path for deletions)
+    pub path: String,
+    /// Previous path, for renames and copies
+    pub old_path: Option<String>,
+    pub change: FileChange,
+    pub binary: bool,
+    pub additions: usize,
+    pub deletions: usize,
+    pub hunks: Vec<DiffHunk>,
+}
+
+/// A contiguous block of changes within a file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffHunk {
+    /// The `@@ -a,b +c,d @@ ...` header line
+    pub header: String,
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    pub lines: Vec<DiffLine>,
+}
+
+/// A single line in a hunk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffLine {
+    /// `+` added, `-` removed, ` ` context
+    pub origin: char,
+    pub content: String,
+    pub old_lineno: Option<u32>,
+    pub new_lineno: Option<u32>,
+}
+
+/// Diff `branch` against the point where it forked from `base`
+///
+/// Uses the merge base rather than `base`'s tip, so commits landed on `base`
+/// since the fork don't show up as reverted changes on the branch.
+pub fn diff_branch(repo: &Repository, branch: &str, base: &str) -> Result<Vec<FileDiff>> {
+    let branch_commit = repo
+        .find_branch(branch, git2::BranchType::Local)?
+        .get()
+        .peel_to_commit()?;
+    let base_commit = repo
+        .find_branch(base, git2::BranchType::Local)?
+        .get()
+        .peel_to_commit()?;
+    let fork_point = repo.find_commit(repo.merge_base(base_commit.id(), branch_commit.id())?)?;
+
+    let mut opts = DiffOptions::new();
+    let mut diff = repo.diff_tree_to_tree(
+        Some(&fork_point.tree()?),
+        Some(&branch_commit.tree()?),
+        Some(&mut opts),
+    )?;
+    diff.find_similar(None)?;
+
+    let mut files = Vec::new();
+    for idx in 0..diff.deltas().len() {
+        let Some(patch) = Patch::from_diff(&diff, idx)? else {
+            continue;
+        };
+        files.push(file_diff(&patch)?);
+    }
+    Ok(files)
+}
+
+fn file_diff(patch: &Patch) -> Result<FileDiff> {
+    let delta = patch.delta();
+    let path_of = |file: git2::DiffFile| file.path().map(|p| p.to_string_lossy().into_owned());
+    let old_path = path_of(delta.old_file());
+    let new_path = path_of(delta.new_file());
+
+    let change = match delta.status() {
+        Delta::Added => FileChange::Added,
+        Delta::Deleted => FileChange::Deleted,
+        Delta::Renamed => FileChange::Renamed,
+        Delta::Copied => FileChange::Copied,
+        Delta::Typechange => FileChange::TypeChange,
+        _ => FileChange::Modified,
+    };
+
+    let mut hunks = Vec::with_capacity(patch.num_hunks());
+    for hunk_idx in 0..patch.num_hunks() {
+        let (hunk, line_count) = patch.hunk(hunk_idx)?;
+        let mut lines = Vec::with_capacity(line_count);
+        for line_idx in 0..line_count {
+            let line = patch.line_in_hunk(hunk_idx, line_idx)?;
+            lines.push(DiffLine {
+                origin: line.origin(),
+                content: String::from_utf8_lossy(line.content()).into_owned(),
+                old_lineno: line.old_lineno(),
+                new_lineno: line.new_lineno(),
+            });
+        }
+        hunks.push(DiffHunk {
+            header: String::from_utf8_lossy(hunk.header()).trim_end().to_string(),
+            old_start: hunk.old_start(),
+            old_lines: hunk.old_lines(),
+            new_start: hunk.new_start(),
+            new_lines: hunk.new_lines(),
+            lines,
+        });
+    }
+
+    let (_, additions, deletions) = patch.line_stats()?;
+    let old_path_if_moved = match change {
+        FileChange::Renamed | FileChange::Copied => old_path.clone(),
+        _ => None,
+    };
+
+    Ok(FileDiff {
+        path: new_path.or(old_path).unwrap_or_default(),
+        old_path: old_path_if_moved,
+        change,
+        binary: delta.flags().is_binary(),
+        additions,
+        deletions,
+        hunks,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Signature;
+
+    /// Commit on `branch` (starting it from `from` if new), writing each
+    /// `(name, Some(contents))` and removing each `(name, None)`
+    fn commit(repo: &Repository, branch: &str, from: &str, files: &[(&str, Option<&[u8]>)]) {
+        let sig = Signature::now("test", "test@example.com").unwrap();
+        let parent = repo
+            .find_branch(branch, git2::BranchType::Local)
+            .or_else(|_| repo.find_branch(from, git2::BranchType::Local))
+            .ok()
+            .map(|b| b.get().peel_to_commit().unwrap());
+        let mut builder = repo
+            .treebuilder(parent.as_ref().map(|p| p.tree().unwrap()).as_ref())
+            .unwrap();
+        for (name, contents) in files {
+            match contents {
+                Some(contents) => {
+                    builder
+                        .insert(name, repo.blob(contents).unwrap(), 0o100644)
+                        .unwrap();
+                }
+                None => builder.remove(name).unwrap(),
+            }
+        }
+        let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+        let parents: Vec<_> = parent.iter().collect();
+        repo.commit(
+            Some(&format!("refs/heads/{}", branch)),
+            &sig,
+            &sig,
+            "change",
+            &tree,
+            &parents,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn diffs_each_kind_of_change_against_the_fork_point() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init_bare(dir.path()).unwrap();
+        let moved = "one\ntwo\nthree\nfour\nfive\nsix\nseven\neight\n";
+        commit(
+            &repo,
+            "main",
+            "main",
+            &[
+                ("edited.txt", Some(b"a\nb\nc\n")),
+                ("removed.txt", Some(b"bye\n")),
+                ("old-name.txt", Some(moved.as_bytes())),
+            ],
+        );
+        commit(
+            &repo,
+            "agent",
+            "main",
+            &[
+                ("edited.txt", Some(b"a\nB\nc\n")),
+                ("removed.txt", None),
+                ("old-name.txt", None),
+                ("new-name.txt", Some(moved.as_bytes())),
+                ("added.txt", Some(b"hello\n")),
+                ("logo.png", Some(b"\x89PNG\r\n\x1a\n\0\0\0")),
+            ],
+        );
+        // Landed on main after the fork: not the agent's change to undo
+        commit(&repo, "main", "main", &[("later.txt", Some(b"later\n"))]);
+
+        let files = diff_branch(&repo, "agent", "main").unwrap();
+        let by_path = |path: &str| {
+            files
+                .iter()
+                .find(|f| f.path == path)
+                .unwrap_or_else(|| panic!("{}", path))
+        };
+        assert_eq!(
+            files.len(),
+            5,
+            "{:?}",
+            files.iter().map(|f| &f.path).collect::<Vec<_>>()
+        );
+        assert!(files.iter().all(|f| f.path != "later.txt"));
+
+        let edited = by_path("edited.txt");
+        assert_eq!(edited.change, FileChange::Modified);
+        assert_eq!((edited.additions, edited.deletions), (1, 1));
+        let hunk = &edited.hunks[0];
+        assert!(
+            hunk.header.starts_with("@@ -1,3 +1,3 @@"),
+            "{}",
+            hunk.header
+        );
+        let changed: Vec<(char, &str, Option<u32>, Option<u32>)> = hunk
+            .lines
+            .iter()
+            .map(|l| (l.origin, l.content.as_str(), l.old_lineno, l.new_lineno))
+            .collect();
+        assert_eq!(
+            changed,
+            [
+                (' ', "a\n", Some(1), Some(1)),
+                ('-', "b\n", Some(2), None),
+                ('+', "B\n", None, Some(2)),
+                (' ', "c\n", Some(3), Some(3)),
+            ]
+        );
+
+        let removed = by_path("removed.txt");
+        assert_eq!(
+            (removed.change, removed.deletions),
+            (FileChange::Deleted, 1)
+        );
+        let renamed = by_path("new-name.txt");
+        assert_eq!(renamed.change, FileChange::Renamed);
+        assert_eq!(renamed.old_path.as_deref(), Some("old-name.txt"));
+        assert!(renamed.hunks.is_empty());
+        assert_eq!(by_path("added.txt").change, FileChange::Added);
+        assert_eq!(by_path("added.txt").old_path, None);
+        let logo = by_path("logo.png");
+        assert!(logo.binary && logo.hunks.is_empty());
+    }
+
+    #[test]
+    fn unknown_branches_are_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init_bare(dir.path()).unwrap();
+        commit(&repo, "main", "main", &[("a.txt", Some(b"a\n"))]);
+
+        assert!(diff_branch(&repo, "main", "main").unwrap().is_empty());
+        assert!(diff_branch(&repo, "gone", "main").is_err());
+        assert!(diff_branch(&repo, "main", "trunk").is_err());
+    }
+}
@@ -0,0 +1,42 @@
+//! Points of an agent's life where `[hooks]` commands run
+//!
+//! The CLI runs the hooks (`rembrandt::hooks`); the points live here so the
+//! shared config can name them.
+
+/// Where in an agent's life a hook runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookPoint {
+    /// Before the agent's workspace is created
+    PreSpawn,
+    /// After the workspace exists, before the agent starts; runs in it
+    PostWorktree,
+    /// Before an agent's branch is merged; runs in its checkout
+    PreMerge,
+    /// After an agent's branch is merged
+    PostMerge,
+    /// After an agent exits non-zero (not when it was stopped)
+    OnFailure,
+}
+
+impl HookPoint {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HookPoint::PreSpawn => "pre_spawn",
+            HookPoint::PostWorktree => "post_worktree",
+            HookPoint::PreMerge => "pre_merge",
+            HookPoint::PostMerge => "post_merge",
+            HookPoint::OnFailure => "on_failure",
+        }
+    }
+
+    /// Whether a failing hook stops what it runs before
+    pub fn is_blocking(self) -> bool {
+        matches!(self, HookPoint::PreSpawn | HookPoint::PostWorktree | HookPoint::PreMerge)
+    }
+}
+
+impl std::fmt::Display for HookPoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
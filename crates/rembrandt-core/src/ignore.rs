@@ -0,0 +1,171 @@
+//! `.rembrandtignore`: changed paths that don't count as an agent's work.
+//!
+//! Generated artifacts (lockfiles, snapshots, `dist/`) inflate diff stats,
+//! crowd evaluator prompts and show up as conflicts no one has to resolve.
+//! Paths matching the repository's `.rembrandtignore` are left out of
+//! competition diff stats, the diffs shown to judges and conflict pre-checks.
+//!
+//! The syntax is a subset of `.gitignore`: one pattern per line, `#`
+//! comments, `*` and `?` within a segment, `**` across directories, a
+//! trailing `/` for directories only, a leading `/` (or any inner `/`) to
+//! anchor at the repository root, and `!` to re-include. Later lines win.
+//!
+//! ```text
+//! Cargo.lock
+//! *.snap
+//! dist/
+//! /docs/api/**
+//! ```
+
+use crate::worktree::{main_repo_root, wildcard_match};
+use std::path::Path;
+
+/// Name of the ignore file, at the repository root
+pub const IGNORE_FILE: &str = ".rembrandtignore";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    segments: Vec<String>,
+    /// Only matches directories (pattern ended in `/`)
+    dir_only: bool,
+    /// Matches at any depth (pattern had no inner `/`)
+    floating: bool,
+    negated: bool,
+}
+
+/// Parsed `.rembrandtignore` patterns
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IgnoreRules {
+    rules: Vec<Rule>,
+}
+
+impl IgnoreRules {
+    /// Rules of the repository containing `path` (a worktree resolves to its
+    /// main repository, so an untracked ignore file still applies); empty
+    /// without an ignore file
+    pub fn load(path: &Path) -> Self {
+        let root = main_repo_root(path).unwrap_or_else(|| path.to_path_buf());
+        std::fs::read_to_string(root.join(IGNORE_FILE))
+            .map(|contents| Self::parse(&contents))
+            .unwrap_or_default()
+    }
+
+    pub fn parse(contents: &str) -> Self {
+        let rules = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let (negated, line) = match line.strip_prefix('!') {
+                    Some(rest) => (true, rest),
+                    None => (false, line),
+                };
+                let dir_only = line.ends_with('/');
+                let line = line.trim_end_matches('/');
+                let floating = !line.contains('/');
+                let segments: Vec<String> = line
+                    .trim_start_matches('/')
+                    .split('/')
+                    .filter(|s| !s.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                (!segments.is_empty()).then_some(Rule { segments, dir_only, floating, negated })
+            })
+            .collect();
+        Self { rules }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Whether the file at `path`, relative to the repository root, is ignored
+    pub fn is_ignored(&self, path: impl AsRef<Path>) -> bool {
+        let components: Vec<String> = path
+            .as_ref()
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        let mut ignored = false;
+        for rule in &self.rules {
+            if rule.negated == ignored && rule.matches(&components) {
+                ignored = !rule.negated;
+            }
+        }
+        ignored
+    }
+
+    /// Keep the paths of `paths` that aren't ignored
+    pub fn retain<P: AsRef<Path>>(&self, paths: &mut Vec<P>) {
+        if !self.is_empty() {
+            paths.retain(|path| !self.is_ignored(path));
+        }
+    }
+}
+
+impl Rule {
+    /// Whether the rule matches the file or any directory containing it
+    fn matches(&self, components: &[String]) -> bool {
+        // A directory-only rule can't match the file itself
+        let longest = if self.dir_only { components.len().saturating_sub(1) } else { components.len() };
+        (1..=longest).any(|len| {
+            let prefix = &components[..len];
+            if self.floating {
+                wildcard_match(&self.segments[0], &prefix[len - 1])
+            } else {
+                segments_match(&self.segments, prefix)
+            }
+        })
+    }
+}
+
+/// Match path segments against pattern segments, `**` spanning any number
+fn segments_match(pattern: &[String], path: &[String]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((first, rest)) if first == "**" => {
+            (0..=path.len()).any(|skip| segments_match(rest, &path[skip..]))
+        }
+        Some((first, rest)) => {
+            path.split_first().is_some_and(|(name, path)| wildcard_match(first, name) && segments_match(rest, path))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gitignore_style_patterns() {
+        let rules = IgnoreRules::parse(
+            "# generated\n\
+             Cargo.lock\n\
+             *.snap\n\
+             dist/\n\
+             /docs/api/**\n\
+             src/**/generated.rs\n\
+             !keep.snap\n",
+        );
+        for ignored in [
+            "Cargo.lock",
+            "crates/core/Cargo.lock",
+            "tests/__snapshots__/app.snap",
+            "dist/bundle.js",
+            "web/dist/index.html",
+            "docs/api/index.md",
+            "src/generated.rs",
+            "src/a/b/generated.rs",
+        ] {
+            assert!(rules.is_ignored(ignored), "{} should be ignored", ignored);
+        }
+        for kept in ["src/main.rs", "dist", "keep.snap", "web/docs/api/x.md", "Cargo.toml", "distro/x"] {
+            assert!(!rules.is_ignored(kept), "{} should be kept", kept);
+        }
+
+        let mut files = vec!["src/lib.rs", "Cargo.lock", "app.snap"];
+        rules.retain(&mut files);
+        assert_eq!(files, vec!["src/lib.rs"]);
+        assert!(IgnoreRules::parse("\n# nothing\n").is_empty());
+    }
+}
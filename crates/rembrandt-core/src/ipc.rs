@@ -0,0 +1,1051 @@
+//! IPC Protocol for daemon communication
+//!
+//! The Rembrandt daemon listens on a Unix socket. Clients (TUI, CLI, GUI)
+//! send commands and receive responses using this protocol.
+//!
+//! Messages are newline-delimited JSON. A connection that has attached to a
+//! session also receives `DaemonResponse::Event` messages, interleaved with
+//! responses to any further commands.
+//!
+//! JSON encodes PTY output as an array of numbers, several bytes per byte of
+//! output. Clients that stream output can send `SetFraming { Binary }` first:
+//! from then on the daemon sends length-prefixed frames (see `encode_frame`)
+//! that carry output as raw bytes and everything else as JSON. Commands stay
+//! newline-delimited JSON either way.
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::manager::SessionInfo;
+use crate::restart::RestartPolicy;
+use crate::screen::ScreenSnapshot;
+use crate::session::{SessionId, SessionStatus};
+use crate::{ErrorCode, ErrorPayload, RembrandtError, Result};
+
+/// Commands that can be sent to the daemon
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DaemonCommand {
+    /// Spawn a new agent session
+    Spawn {
+        agent_id: String,
+        command: String,
+        args: Vec<String>,
+        /// Extra environment variables for the agent
+        #[serde(default)]
+        env: Vec<(String, String)>,
+        workdir: PathBuf,
+        /// Initial terminal size (defaults to 24x80)
+        #[serde(default)]
+        rows: Option<u16>,
+        #[serde(default)]
+        cols: Option<u16>,
+        /// Bytes of output history to keep (defaults to the daemon's setting)
+        #[serde(default)]
+        buffer_capacity: Option<usize>,
+        /// Whether the daemon respawns the agent when it crashes
+        #[serde(default)]
+        restart: RestartPolicy,
+    },
+
+    /// Send a nudge to wake a stalled agent
+    Nudge { session_id: SessionId },
+
+    /// Write data to a session's PTY
+    Write { session_id: SessionId, data: Vec<u8> },
+
+    /// Kill a session
+    Kill { session_id: SessionId },
+
+    /// Stop a session's agent with SIGSTOP until `Resume`
+    Pause { session_id: SessionId },
+
+    /// Continue a paused session's agent
+    Resume { session_id: SessionId },
+
+    /// Stop a session, escalating until it exits: the agent's quit
+    /// sequence, then SIGTERM after `grace_secs`, then SIGKILL after
+    /// another `grace_secs`
+    ///
+    /// Answered with `Ok` once the session has exited, naming the step
+    /// that did it.
+    Stop {
+        session_id: SessionId,
+        /// Defaults to the daemon's stop grace period
+        #[serde(default)]
+        grace_secs: Option<u64>,
+    },
+
+    /// List all sessions, optionally only those in one repository
+    List {
+        #[serde(default)]
+        repo: Option<PathBuf>,
+    },
+
+    /// List sessions for a specific agent
+    ///
+    /// Agent IDs are only unique per repository; pass `repo` to avoid
+    /// matching a same-named agent in another project.
+    ListByAgent {
+        agent_id: String,
+        #[serde(default)]
+        repo: Option<PathBuf>,
+    },
+
+    /// Get session info
+    GetSession { session_id: SessionId },
+
+    /// Attach to a session (start streaming output)
+    ///
+    /// Answered with the buffered history as `Output`, followed by `Event`
+    /// messages for that session until it exits or the client detaches.
+    /// A `read_only` attachment refuses `Write` and `Resize` for the session
+    /// on the same connection, so an observer can't type into it.
+    Attach {
+        session_id: SessionId,
+        #[serde(default)]
+        read_only: bool,
+    },
+
+    /// Detach from a session (stop streaming)
+    Detach { session_id: SessionId },
+
+    /// Get buffered output history
+    GetHistory { session_id: SessionId },
+
+    /// Get what a session's terminal currently shows
+    GetScreen { session_id: SessionId },
+
+    /// Resize a session's PTY
+    Resize {
+        session_id: SessionId,
+        rows: u16,
+        cols: u16,
+    },
+
+    /// Switch how the daemon frames its messages on this connection
+    ///
+    /// Answered with `Ok` in the current framing; later messages use the new
+    /// one. Daemons that predate framing answer with an `Error`.
+    SetFraming { framing: Framing },
+
+    /// Present an API token, limiting the connection to its role
+    ///
+    /// Required before anything but `Ping` on the daemon's TCP listener;
+    /// optional on the Unix socket. Answered with `Ok` naming the role.
+    Authenticate { token: String },
+
+    /// Count a competition that finished in the client in the daemon's
+    /// metrics
+    ///
+    /// `outcome` is `completed`, `failed` or `cancelled`; `validation_ms`
+    /// is how long validating each of its solutions took.
+    RecordCompetition {
+        outcome: String,
+        #[serde(default)]
+        validation_ms: Vec<u64>,
+    },
+
+    /// Merge an agent's branch into `base` in `repo` right away, as
+    /// `rembrandt merge` does
+    ///
+    /// Answered with `Ok` naming the merge queue status it ended in.
+    Merge {
+        repo: PathBuf,
+        agent_id: String,
+        base: String,
+        /// Rerun validation after rebasing
+        #[serde(default = "default_true")]
+        validate: bool,
+    },
+
+    /// Read `repo`'s `.rembrandt/config.toml`, answered with `Config`
+    GetConfig { repo: PathBuf },
+
+    /// Replace `repo`'s `.rembrandt/config.toml` with `config`, which must
+    /// parse; the previous file is kept as `config.toml.bak`
+    SetConfig { repo: PathBuf, config: String },
+
+    /// Ping the daemon (health check)
+    Ping,
+
+    /// Request daemon shutdown
+    Shutdown,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Responses from the daemon
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DaemonResponse {
+    /// Success with optional message
+    Ok { message: Option<String> },
+
+    /// Session was spawned
+    Spawned { session_id: SessionId },
+
+    /// List of sessions
+    Sessions { sessions: Vec<SessionInfo> },
+
+    /// Single session info
+    Session { info: SessionInfo },
+
+    /// Output data (for attach/history)
+    Output {
+        data: Vec<u8>,
+        /// Stream offset of the first byte in `data`
+        offset: usize,
+    },
+
+    /// Rendered virtual screen of a session
+    Screen { screen: ScreenSnapshot },
+
+    /// A repository's config file contents
+    Config { config: String },
+
+    /// Streamed event for an attached session
+    Event { event: DaemonEvent },
+
+    /// Pong response to ping
+    ///
+    /// `schedule_repo` is the repository whose scheduled steers the daemon
+    /// sends; it is only told to local clients, and absent from daemons that
+    /// predate it.
+    Pong {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        schedule_repo: Option<PathBuf>,
+    },
+
+    /// Error occurred
+    ///
+    /// `code` and `hint` are absent from daemons that predate them.
+    Error {
+        message: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        code: Option<ErrorCode>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        hint: Option<String>,
+    },
+}
+
+impl DaemonResponse {
+    /// Error response carrying `error`'s code and hint
+    pub fn error(error: &RembrandtError) -> Self {
+        let payload = ErrorPayload::from(error);
+        DaemonResponse::Error {
+            message: payload.message,
+            code: Some(payload.code),
+            hint: payload.hint,
+        }
+    }
+}
+
+/// Events streamed from daemon to attached clients
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum DaemonEvent {
+    /// New output from a session
+    Output {
+        session_id: SessionId,
+        /// Stream offset of the first byte in `data`
+        offset: usize,
+        data: Vec<u8>,
+    },
+
+    /// Session status changed
+    StatusChanged {
+        session_id: SessionId,
+        status: SessionStatus,
+    },
+
+    /// Session exited
+    Exited { session_id: SessionId, code: i32 },
+}
+
+impl DaemonEvent {
+    /// The session this event is about
+    pub fn session_id(&self) -> &str {
+        match self {
+            DaemonEvent::Output { session_id, .. }
+            | DaemonEvent::StatusChanged { session_id, .. }
+            | DaemonEvent::Exited { session_id, .. } => session_id,
+        }
+    }
+}
+
+/// How the daemon frames the messages it sends on a connection
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Framing {
+    /// Newline-delimited JSON
+    #[default]
+    Json,
+    /// Length-prefixed frames with output as raw bytes
+    Binary,
+}
+
+impl Framing {
+    /// Encode a daemon message for a connection using this framing
+    pub fn encode(self, response: &DaemonResponse) -> Result<Vec<u8>> {
+        match self {
+            Framing::Json => encode_message(response),
+            Framing::Binary => encode_frame(response),
+        }
+    }
+}
+
+/// Largest binary frame body a client accepts
+pub const MAX_FRAME_LEN: usize = 64 * 1024 * 1024;
+
+/// Binary frame kinds (first byte of the body)
+const FRAME_JSON: u8 = 0;
+const FRAME_OUTPUT: u8 = 1;
+const FRAME_EVENT_OUTPUT: u8 = 2;
+
+/// Encode a daemon message as a binary frame
+///
+/// A frame is a big-endian `u32` body length followed by the body: a kind
+/// byte, then for output responses the `u64` stream offset and raw data;
+/// for output events the offset, a `u16`-length session ID and raw data; and
+/// for anything else the message as JSON.
+pub fn encode_frame(response: &DaemonResponse) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    match response {
+        DaemonResponse::Output { data, offset } => {
+            body.reserve(9 + data.len());
+            body.push(FRAME_OUTPUT);
+            body.extend_from_slice(&(*offset as u64).to_be_bytes());
+            body.extend_from_slice(data);
+        }
+        DaemonResponse::Event {
+            event: DaemonEvent::Output { session_id, offset, data },
+        } => {
+            let id_len = u16::try_from(session_id.len())
+                .map_err(|_| RembrandtError::Daemon("Session ID too long to frame".to_string()))?;
+            body.reserve(11 + session_id.len() + data.len());
+            body.push(FRAME_EVENT_OUTPUT);
+            body.extend_from_slice(&(*offset as u64).to_be_bytes());
+            body.extend_from_slice(&id_len.to_be_bytes());
+            body.extend_from_slice(session_id.as_bytes());
+            body.extend_from_slice(data);
+        }
+        other => {
+            body.push(FRAME_JSON);
+            serde_json::to_writer(&mut body, other)
+                .map_err(|e| RembrandtError::Daemon(e.to_string()))?;
+        }
+    }
+
+    let len = u32::try_from(body.len())
+        .ok()
+        .filter(|&len| len as usize <= MAX_FRAME_LEN)
+        .ok_or_else(|| RembrandtError::Daemon(format!("Frame of {} bytes is too large", body.len())))?;
+    let mut frame = Vec::with_capacity(4 + body.len());
+    frame.extend_from_slice(&len.to_be_bytes());
+    frame.extend_from_slice(&body);
+    Ok(frame)
+}
+
+/// Decode the body of a binary frame (without its length prefix)
+pub fn decode_frame(body: &[u8]) -> Result<DaemonResponse> {
+    let truncated = || RembrandtError::Daemon("Truncated binary frame".to_string());
+    let (&kind, rest) = body.split_first().ok_or_else(truncated)?;
+    let offset = || -> Result<usize> {
+        let bytes = rest.get(..8).ok_or_else(truncated)?;
+        let mut offset = [0u8; 8];
+        offset.copy_from_slice(bytes);
+        Ok(u64::from_be_bytes(offset) as usize)
+    };
+    match kind {
+        FRAME_JSON => decode_message(rest),
+        FRAME_OUTPUT => Ok(DaemonResponse::Output {
+            offset: offset()?,
+            data: rest[8..].to_vec(),
+        }),
+        FRAME_EVENT_OUTPUT => {
+            let offset = offset()?;
+            let id_len = rest.get(8..10).ok_or_else(truncated)?;
+            let id_end = 10 + u16::from_be_bytes([id_len[0], id_len[1]]) as usize;
+            let session_id = rest.get(10..id_end).ok_or_else(truncated)?;
+            Ok(DaemonResponse::Event {
+                event: DaemonEvent::Output {
+                    session_id: String::from_utf8_lossy(session_id).into_owned(),
+                    offset,
+                    data: rest[id_end..].to_vec(),
+                },
+            })
+        }
+        other => Err(RembrandtError::Daemon(format!("Unknown frame kind {}", other))),
+    }
+}
+
+/// Serialize a message as one protocol frame (JSON plus trailing newline)
+pub fn encode_message<T: Serialize>(message: &T) -> Result<Vec<u8>> {
+    let mut bytes =
+        serde_json::to_vec(message).map_err(|e| RembrandtError::Daemon(e.to_string()))?;
+    bytes.push(b'\n');
+    Ok(bytes)
+}
+
+/// Parse one protocol frame (with or without its trailing newline)
+pub fn decode_message<T: DeserializeOwned>(frame: &[u8]) -> Result<T> {
+    serde_json::from_slice(frame).map_err(|e| RembrandtError::Daemon(e.to_string()))
+}
+
+/// Environment variable whose token clients present when they connect
+pub const TOKEN_ENV: &str = "REMBRANDT_TOKEN";
+
+/// Get the default socket path for the daemon
+pub fn default_socket_path() -> PathBuf {
+    // Use XDG_RUNTIME_DIR if available, otherwise /tmp
+    if let Ok(runtime_dir) = std::env::var("XDG_RUNTIME_DIR") {
+        PathBuf::from(runtime_dir).join("rembrandt.sock")
+    } else {
+        PathBuf::from("/tmp").join(format!("rembrandt-{}.sock", whoami()))
+    }
+}
+
+/// Get current username for socket path
+fn whoami() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+#[cfg(unix)]
+pub use blocking::DaemonConnection;
+
+#[cfg(unix)]
+mod blocking {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpStream;
+    use rustls::pki_types::ServerName;
+    use std::os::unix::net::UnixStream;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    /// A TLS stream read and written through separate handles; the
+    /// connection only ever uses one at a time
+    #[derive(Clone)]
+    struct SharedStream(Arc<std::sync::Mutex<rustls::StreamOwned<rustls::ClientConnection, TcpStream>>>);
+
+    impl SharedStream {
+        fn with<T>(
+            &self,
+            f: impl FnOnce(&mut rustls::StreamOwned<rustls::ClientConnection, TcpStream>) -> std::io::Result<T>,
+        ) -> std::io::Result<T> {
+            let mut stream = self.0.lock().map_err(|_| std::io::Error::other("TLS stream poisoned"))?;
+            f(&mut stream)
+        }
+    }
+
+    impl Read for SharedStream {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.with(|stream| stream.read(buf))
+        }
+    }
+
+    impl Write for SharedStream {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.with(|stream| stream.write(buf))
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.with(|stream| stream.flush())
+        }
+    }
+
+    /// Blocking connection to the daemon, for callers without an async
+    /// runtime (the GUI's command handlers and reader threads)
+    pub struct DaemonConnection {
+        reader: BufReader<Box<dyn Read + Send>>,
+        writer: Box<dyn Write + Send>,
+        /// How the daemon frames messages on this connection
+        framing: Framing,
+    }
+
+    impl DaemonConnection {
+        /// Connect to the daemon socket
+        ///
+        /// A token in `$REMBRANDT_TOKEN` is presented straight away.
+        pub fn connect(socket_path: &Path) -> Result<Self> {
+            let writer = UnixStream::connect(socket_path)
+                .map_err(|e| RembrandtError::Daemon(e.to_string()))?;
+            let reader = writer.try_clone()?;
+            Self::over(Box::new(reader), Box::new(writer))
+        }
+
+        /// Connect to a daemon's TCP listener (`rembrandt daemon --listen`)
+        ///
+        /// The daemon refuses commands until a token is presented, either
+        /// from `$REMBRANDT_TOKEN` or with `authenticate`.
+        pub fn connect_tcp(addr: &str) -> Result<Self> {
+            let writer = TcpStream::connect(addr)
+                .map_err(|e| RembrandtError::Daemon(format!("{}: {}", addr, e)))?;
+            let reader = writer.try_clone()?;
+            Self::over(Box::new(reader), Box::new(writer))
+        }
+
+        /// Connect to a daemon's TLS listener (`[listen_tls]`), trusting the
+        /// certificates in the PEM file `ca`; for a self-signed daemon
+        /// that is its own certificate
+        ///
+        /// `addr` names the host the certificate must be for, e.g.
+        /// `build-box:7878` or `10.0.0.5:7878`.
+        pub fn connect_tls(addr: &str, ca: &Path) -> Result<Self> {
+            let tls_error = |e: &dyn std::fmt::Display| RembrandtError::Daemon(format!("{}: {}", addr, e));
+            let mut roots = rustls::RootCertStore::empty();
+            let pem = std::fs::read(ca)
+                .map_err(|e| RembrandtError::Config(format!("{}: {}", ca.display(), e)))?;
+            for cert in rustls_pemfile::certs(&mut pem.as_slice()) {
+                roots.add(cert?).map_err(|e| tls_error(&e))?;
+            }
+            if roots.is_empty() {
+                return Err(RembrandtError::Config(format!("{} holds no certificate", ca.display())));
+            }
+            let config = rustls::ClientConfig::builder_with_provider(Arc::new(
+                rustls::crypto::ring::default_provider(),
+            ))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| tls_error(&e))?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+
+            let host = addr.rsplit_once(':').map_or(addr, |(host, _)| host);
+            let name = ServerName::try_from(host.trim_start_matches('[').trim_end_matches(']').to_string())
+                .map_err(|e| tls_error(&e))?;
+            let tls = rustls::ClientConnection::new(Arc::new(config), name).map_err(|e| tls_error(&e))?;
+            let tcp = TcpStream::connect(addr).map_err(|e| tls_error(&e))?;
+            let mut stream = rustls::StreamOwned::new(tls, tcp);
+            // Handshake now, so a certificate mismatch fails here
+            while stream.conn.is_handshaking() {
+                stream.conn.complete_io(&mut stream.sock).map_err(|e| tls_error(&e))?;
+            }
+            let shared = SharedStream(Arc::new(std::sync::Mutex::new(stream)));
+            Self::over(Box::new(shared.clone()), Box::new(shared))
+        }
+
+        fn over(reader: Box<dyn Read + Send>, writer: Box<dyn Write + Send>) -> Result<Self> {
+            let mut conn = Self {
+                reader: BufReader::new(reader),
+                writer,
+                framing: Framing::Json,
+            };
+            if let Ok(token) = std::env::var(TOKEN_ENV)
+                && !token.is_empty()
+            {
+                conn.authenticate(&token)?;
+            }
+            Ok(conn)
+        }
+
+        /// Present an API token, returning the role it grants
+        pub fn authenticate(&mut self, token: &str) -> Result<String> {
+            match self.request(&DaemonCommand::Authenticate {
+                token: token.to_string(),
+            })? {
+                DaemonResponse::Ok { message } => Ok(message.unwrap_or_default()),
+                other => Err(unexpected(&other)),
+            }
+        }
+
+        /// Ask the daemon to switch this connection's framing
+        ///
+        /// Fails, leaving the framing unchanged, if the daemon predates
+        /// framing negotiation.
+        pub fn set_framing(&mut self, framing: Framing) -> Result<()> {
+            self.expect_ok(&DaemonCommand::SetFraming { framing })?;
+            self.framing = framing;
+            Ok(())
+        }
+
+        pub fn framing(&self) -> Framing {
+            self.framing
+        }
+
+        /// Send a command and wait for its response
+        ///
+        /// Events for attached sessions that arrive first are dropped; use a
+        /// dedicated connection per attachment. `Error` responses become `Err`.
+        pub fn request(&mut self, command: &DaemonCommand) -> Result<DaemonResponse> {
+            self.writer.write_all(&encode_message(command)?)?;
+            loop {
+                match self.read_message()? {
+                    Some(DaemonResponse::Event { .. }) => continue,
+                    Some(DaemonResponse::Error { message, code: Some(code), hint }) => {
+                        return Err(RembrandtError::Remote(ErrorPayload { code, message, hint }));
+                    }
+                    Some(DaemonResponse::Error { message, code: None, .. }) => {
+                        return Err(RembrandtError::Daemon(message));
+                    }
+                    Some(response) => return Ok(response),
+                    None => {
+                        return Err(RembrandtError::Daemon(
+                            "Daemon closed the connection".to_string(),
+                        ));
+                    }
+                }
+            }
+        }
+
+        /// Wait for the next event on an attached connection
+        ///
+        /// Returns `None` once the daemon closes the connection.
+        pub fn next_event(&mut self) -> Result<Option<DaemonEvent>> {
+            loop {
+                match self.read_message()? {
+                    Some(DaemonResponse::Event { event }) => return Ok(Some(event)),
+                    Some(_) => continue,
+                    None => return Ok(None),
+                }
+            }
+        }
+
+        fn read_message(&mut self) -> Result<Option<DaemonResponse>> {
+            if self.framing == Framing::Binary {
+                return self.read_frame();
+            }
+            let mut line = Vec::new();
+            if self.reader.read_until(b'\n', &mut line)? == 0 {
+                return Ok(None);
+            }
+            decode_message(&line).map(Some)
+        }
+
+        fn read_frame(&mut self) -> Result<Option<DaemonResponse>> {
+            let mut len = [0u8; 4];
+            match self.reader.read_exact(&mut len) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+                Err(e) => return Err(e.into()),
+            }
+            let len = u32::from_be_bytes(len) as usize;
+            if len > MAX_FRAME_LEN {
+                return Err(RembrandtError::Daemon(format!(
+                    "Frame of {} bytes is too large",
+                    len
+                )));
+            }
+            let mut body = vec![0u8; len];
+            self.reader.read_exact(&mut body)?;
+            decode_frame(&body).map(Some)
+        }
+
+        /// Health check
+        pub fn ping(&mut self) -> Result<()> {
+            match self.request(&DaemonCommand::Ping)? {
+                DaemonResponse::Pong { .. } => Ok(()),
+                other => Err(unexpected(&other)),
+            }
+        }
+
+        /// The repository whose scheduled steers the daemon sends, if any
+        pub fn schedule_repo(&mut self) -> Result<Option<PathBuf>> {
+            match self.request(&DaemonCommand::Ping)? {
+                DaemonResponse::Pong { schedule_repo } => Ok(schedule_repo),
+                other => Err(unexpected(&other)),
+            }
+        }
+
+        /// Spawn a session, returning its ID
+        #[allow(clippy::too_many_arguments)]
+        pub fn spawn(
+            &mut self,
+            agent_id: String,
+            command: String,
+            args: Vec<String>,
+            env: Vec<(String, String)>,
+            workdir: PathBuf,
+            rows: Option<u16>,
+            cols: Option<u16>,
+            buffer_capacity: Option<usize>,
+            restart: RestartPolicy,
+        ) -> Result<SessionId> {
+            let command = DaemonCommand::Spawn {
+                agent_id,
+                command,
+                args,
+                env,
+                workdir,
+                rows,
+                cols,
+                buffer_capacity,
+                restart,
+            };
+            match self.request(&command)? {
+                DaemonResponse::Spawned { session_id } => Ok(session_id),
+                other => Err(unexpected(&other)),
+            }
+        }
+
+        /// List sessions, across all repositories if `repo` is `None`
+        pub fn list(&mut self, repo: Option<PathBuf>) -> Result<Vec<SessionInfo>> {
+            match self.request(&DaemonCommand::List { repo })? {
+                DaemonResponse::Sessions { sessions } => Ok(sessions),
+                other => Err(unexpected(&other)),
+            }
+        }
+
+        /// List an agent's sessions, optionally only within one repository
+        pub fn list_by_agent(
+            &mut self,
+            agent_id: &str,
+            repo: Option<PathBuf>,
+        ) -> Result<Vec<SessionInfo>> {
+            let command = DaemonCommand::ListByAgent {
+                agent_id: agent_id.to_string(),
+                repo,
+            };
+            match self.request(&command)? {
+                DaemonResponse::Sessions { sessions } => Ok(sessions),
+                other => Err(unexpected(&other)),
+            }
+        }
+
+        /// Write input to a session's PTY
+        pub fn write(&mut self, session_id: &str, data: Vec<u8>) -> Result<()> {
+            self.expect_ok(&DaemonCommand::Write {
+                session_id: session_id.to_string(),
+                data,
+            })
+        }
+
+        /// Resize a session's PTY
+        pub fn resize(&mut self, session_id: &str, rows: u16, cols: u16) -> Result<()> {
+            self.expect_ok(&DaemonCommand::Resize {
+                session_id: session_id.to_string(),
+                rows,
+                cols,
+            })
+        }
+
+        /// Count a finished competition in the daemon's metrics
+        pub fn record_competition(&mut self, outcome: &str, validation_ms: Vec<u64>) -> Result<()> {
+            self.expect_ok(&DaemonCommand::RecordCompetition {
+                outcome: outcome.to_string(),
+                validation_ms,
+            })
+        }
+
+        /// Kill a session
+        pub fn kill(&mut self, session_id: &str) -> Result<()> {
+            self.expect_ok(&DaemonCommand::Kill {
+                session_id: session_id.to_string(),
+            })
+        }
+
+        /// Pause a session's agent
+        pub fn pause(&mut self, session_id: &str) -> Result<()> {
+            self.expect_ok(&DaemonCommand::Pause {
+                session_id: session_id.to_string(),
+            })
+        }
+
+        /// Continue a paused session's agent
+        pub fn resume(&mut self, session_id: &str) -> Result<()> {
+            self.expect_ok(&DaemonCommand::Resume {
+                session_id: session_id.to_string(),
+            })
+        }
+
+        /// Stop a session gracefully, escalating to signals; returns how it
+        /// ended. Blocks until the session has exited.
+        pub fn stop(&mut self, session_id: &str, grace_secs: Option<u64>) -> Result<String> {
+            match self.request(&DaemonCommand::Stop {
+                session_id: session_id.to_string(),
+                grace_secs,
+            })? {
+                DaemonResponse::Ok { message } => Ok(message.unwrap_or_default()),
+                other => Err(unexpected(&other)),
+            }
+        }
+
+        /// Nudge a session; returns a description of the strategy used
+        pub fn nudge(&mut self, session_id: &str) -> Result<String> {
+            match self.request(&DaemonCommand::Nudge {
+                session_id: session_id.to_string(),
+            })? {
+                DaemonResponse::Ok { message } => Ok(message.unwrap_or_default()),
+                other => Err(unexpected(&other)),
+            }
+        }
+
+        /// Merge an agent's branch into `base`; returns the status it
+        /// ended in and why
+        pub fn merge(&mut self, repo: PathBuf, agent_id: &str, base: &str, validate: bool) -> Result<String> {
+            match self.request(&DaemonCommand::Merge {
+                repo,
+                agent_id: agent_id.to_string(),
+                base: base.to_string(),
+                validate,
+            })? {
+                DaemonResponse::Ok { message } => Ok(message.unwrap_or_default()),
+                other => Err(unexpected(&other)),
+            }
+        }
+
+        /// A repository's config file contents
+        pub fn config(&mut self, repo: PathBuf) -> Result<String> {
+            match self.request(&DaemonCommand::GetConfig { repo })? {
+                DaemonResponse::Config { config } => Ok(config),
+                other => Err(unexpected(&other)),
+            }
+        }
+
+        /// Replace a repository's config file
+        pub fn set_config(&mut self, repo: PathBuf, config: String) -> Result<()> {
+            self.expect_ok(&DaemonCommand::SetConfig { repo, config })
+        }
+
+        /// Buffered history and the stream offset of its first byte
+        pub fn history(&mut self, session_id: &str) -> Result<(Vec<u8>, usize)> {
+            self.output(&DaemonCommand::GetHistory {
+                session_id: session_id.to_string(),
+            })
+        }
+
+        /// How far a session's output stream has got, in bytes
+        pub fn output_end(&mut self, session_id: &str) -> Result<usize> {
+            let (data, offset) = self.history(session_id)?;
+            Ok(offset + data.len())
+        }
+
+        /// What a session's terminal currently shows
+        pub fn screen(&mut self, session_id: &str) -> Result<ScreenSnapshot> {
+            match self.request(&DaemonCommand::GetScreen {
+                session_id: session_id.to_string(),
+            })? {
+                DaemonResponse::Screen { screen } => Ok(screen),
+                other => Err(unexpected(&other)),
+            }
+        }
+
+        /// Attach to a session; follow with `next_event`
+        ///
+        /// Returns the buffered history and the stream offset of its first byte.
+        pub fn attach(&mut self, session_id: &str) -> Result<(Vec<u8>, usize)> {
+            self.output(&DaemonCommand::Attach {
+                session_id: session_id.to_string(),
+                read_only: false,
+            })
+        }
+
+        /// Attach as an observer: like `attach`, but the daemon refuses input
+        /// and resizes for the session on this connection
+        pub fn attach_read_only(&mut self, session_id: &str) -> Result<(Vec<u8>, usize)> {
+            self.output(&DaemonCommand::Attach {
+                session_id: session_id.to_string(),
+                read_only: true,
+            })
+        }
+
+        fn output(&mut self, command: &DaemonCommand) -> Result<(Vec<u8>, usize)> {
+            match self.request(command)? {
+                DaemonResponse::Output { data, offset } => Ok((data, offset)),
+                other => Err(unexpected(&other)),
+            }
+        }
+
+        fn expect_ok(&mut self, command: &DaemonCommand) -> Result<()> {
+            match self.request(command)? {
+                DaemonResponse::Ok { .. } => Ok(()),
+                other => Err(unexpected(&other)),
+            }
+        }
+    }
+
+    fn unexpected(response: &DaemonResponse) -> RembrandtError {
+        RembrandtError::Daemon(format!("Unexpected daemon response: {:?}", response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode then decode, comparing through the JSON form
+    fn round_trip<T: Serialize + DeserializeOwned>(message: &T) {
+        let frame = encode_message(message).unwrap();
+        assert_eq!(frame.last(), Some(&b'\n'));
+        let decoded: T = decode_message(&frame).unwrap();
+        assert_eq!(
+            serde_json::to_value(&decoded).unwrap(),
+            serde_json::to_value(message).unwrap()
+        );
+    }
+
+    fn info(status: SessionStatus) -> SessionInfo {
+        SessionInfo {
+            id: "ses-1".to_string(),
+            agent_id: "bd-7".to_string(),
+            command: "claude".to_string(),
+            workdir: "/repo/.rembrandt/agents/bd-7".to_string(),
+            repo: Some(PathBuf::from("/repo")),
+            status,
+            created_at: chrono::Utc::now(),
+            output_bytes: 512,
+            paused: false,
+            resources: None,
+            peak_rss_bytes: 0,
+        }
+    }
+
+    fn screen() -> ScreenSnapshot {
+        let mut parser = vt100::Parser::new(2, 10, 0);
+        parser.process(b"\x1b[31mred\x1b[0m plain");
+        ScreenSnapshot::from_screen(parser.screen())
+    }
+
+    #[test]
+    fn commands_round_trip() {
+        let session_id = || "ses-1".to_string();
+        for command in [
+            DaemonCommand::Spawn {
+                agent_id: "bd-7".to_string(),
+                command: "claude".to_string(),
+                args: vec!["--resume".to_string()],
+                env: vec![("REMBRANDT_AGENT_ID".to_string(), "bd-7".to_string())],
+                workdir: PathBuf::from("/repo"),
+                rows: Some(40),
+                cols: None,
+                buffer_capacity: Some(256 * 1024),
+                restart: RestartPolicy::OnFailure { max_retries: 2 },
+            },
+            DaemonCommand::Nudge { session_id: session_id() },
+            DaemonCommand::Write { session_id: session_id(), data: b"hi\r".to_vec() },
+            DaemonCommand::Kill { session_id: session_id() },
+            DaemonCommand::Pause { session_id: session_id() },
+            DaemonCommand::Resume { session_id: session_id() },
+            DaemonCommand::Stop { session_id: session_id(), grace_secs: Some(5) },
+            DaemonCommand::List { repo: None },
+            DaemonCommand::List { repo: Some(PathBuf::from("/repo")) },
+            DaemonCommand::ListByAgent { agent_id: "bd-7".to_string(), repo: None },
+            DaemonCommand::GetSession { session_id: session_id() },
+            DaemonCommand::Attach { session_id: session_id(), read_only: false },
+            DaemonCommand::Attach { session_id: session_id(), read_only: true },
+            DaemonCommand::Detach { session_id: session_id() },
+            DaemonCommand::GetHistory { session_id: session_id() },
+            DaemonCommand::GetScreen { session_id: session_id() },
+            DaemonCommand::Resize { session_id: session_id(), rows: 24, cols: 80 },
+            DaemonCommand::SetFraming { framing: Framing::Binary },
+            DaemonCommand::Ping,
+            DaemonCommand::Shutdown,
+        ] {
+            round_trip(&command);
+        }
+
+        // Fields added later default when older clients leave them out
+        let spawn: DaemonCommand = decode_message(
+            br#"{"type":"spawn","agent_id":"a","command":"sh","args":[],"workdir":"/"}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            spawn,
+            DaemonCommand::Spawn {
+                rows: None,
+                cols: None,
+                env,
+                buffer_capacity: None,
+                restart: RestartPolicy::Never,
+                ..
+            } if env.is_empty()
+        ));
+    }
+
+    #[test]
+    fn responses_and_events_round_trip() {
+        let events = [
+            DaemonEvent::Output { session_id: "ses-1".to_string(), offset: 10, data: vec![0, 159, 255] },
+            DaemonEvent::StatusChanged {
+                session_id: "ses-1".to_string(),
+                status: SessionStatus::Failed("pty closed".to_string()),
+            },
+            DaemonEvent::Exited { session_id: "ses-1".to_string(), code: 1 },
+        ];
+        for event in &events {
+            round_trip(event);
+        }
+
+        let mut responses = vec![
+            DaemonResponse::Ok { message: None },
+            DaemonResponse::Ok { message: Some("nudged".to_string()) },
+            DaemonResponse::Spawned { session_id: "ses-1".to_string() },
+            DaemonResponse::Sessions {
+                sessions: vec![
+                    info(SessionStatus::Running),
+                    info(SessionStatus::Exited(0)),
+                    info(SessionStatus::Failed("crashed".to_string())),
+                ],
+            },
+            DaemonResponse::Session { info: info(SessionStatus::Running) },
+            DaemonResponse::Output { data: b"hello".to_vec(), offset: 0 },
+            DaemonResponse::Screen { screen: screen() },
+            DaemonResponse::Pong { schedule_repo: None },
+            DaemonResponse::Pong { schedule_repo: Some(PathBuf::from("/repo")) },
+            DaemonResponse::Error { message: "no such session".to_string(), code: None, hint: None },
+            DaemonResponse::error(&RembrandtError::SessionNotFound("ses-2".to_string())),
+        ];
+        responses.extend(events.into_iter().map(|event| DaemonResponse::Event { event }));
+        for response in &responses {
+            round_trip(response);
+        }
+        let old_pong: DaemonResponse = serde_json::from_str(r#"{"type":"pong"}"#).unwrap();
+        assert!(matches!(old_pong, DaemonResponse::Pong { schedule_repo: None }));
+    }
+
+    #[test]
+    fn binary_frames_round_trip_and_carry_output_raw() {
+        let responses = [
+            DaemonResponse::Output { data: vec![0, 1, 255], offset: 1 << 40 },
+            DaemonResponse::Event {
+                event: DaemonEvent::Output { session_id: "ses-1".to_string(), offset: 7, data: b"\x1b[2J".to_vec() },
+            },
+            DaemonResponse::Event {
+                event: DaemonEvent::Exited { session_id: "ses-1".to_string(), code: 0 },
+            },
+            DaemonResponse::Pong { schedule_repo: None },
+            DaemonResponse::Output { data: Vec::new(), offset: 0 },
+        ];
+        for response in &responses {
+            let frame = encode_frame(response).unwrap();
+            let len = u32::from_be_bytes(frame[..4].try_into().unwrap()) as usize;
+            assert_eq!(len, frame.len() - 4);
+            let decoded = decode_frame(&frame[4..]).unwrap();
+            assert_eq!(
+                serde_json::to_value(&decoded).unwrap(),
+                serde_json::to_value(response).unwrap()
+            );
+        }
+
+        // Output costs its length plus a fixed header, not JSON's 3-4x
+        let data = vec![200u8; 4096];
+        let event = DaemonResponse::Event {
+            event: DaemonEvent::Output { session_id: "ses-1".to_string(), offset: 0, data },
+        };
+        assert_eq!(encode_frame(&event).unwrap().len(), 4 + 11 + 5 + 4096);
+        assert!(Framing::Json.encode(&event).unwrap().len() > 3 * 4096);
+
+        assert!(decode_frame(&[FRAME_EVENT_OUTPUT, 0, 0]).is_err());
+        assert!(decode_frame(&[9]).is_err());
+        assert!(decode_frame(&[]).is_err());
+    }
+
+    #[test]
+    fn session_status_is_a_tagged_enum() {
+        let frame = encode_message(&DaemonResponse::Session { info: info(SessionStatus::Exited(3)) }).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&frame).unwrap();
+        assert_eq!(json["info"]["status"], serde_json::json!({"type": "Exited", "value": 3}));
+
+        let decoded: DaemonResponse = decode_message(&frame).unwrap();
+        let DaemonResponse::Session { info } = decoded else {
+            panic!("expected a session, got {:?}", decoded);
+        };
+        assert_eq!(info.status, SessionStatus::Exited(3));
+        assert_eq!(info.repo.as_deref(), Some(std::path::Path::new("/repo")));
+    }
+}
@@ -0,0 +1,20 @@
+//! How an agent's workspace is kept apart from the main checkout
+//!
+//! The strategies themselves are the CLI's (`rembrandt::isolation`); the
+//! mode lives here so the shared config can name it.
+
+/// Supported workspace isolation modes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IsolationMode {
+    Branch,
+    Worktree,
+}
+
+impl std::fmt::Display for IsolationMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IsolationMode::Branch => write!(f, "branch"),
+            IsolationMode::Worktree => write!(f, "worktree"),
+        }
+    }
+}
@@ -0,0 +1,256 @@
+//! Rembrandt core: PTY sessions, worktrees and competitions shared by the CLI
+//! and the GUI
+//!
+//! Both the `rembrandt` binary (daemon/TUI) and the Tauri desktop backend
+//! drive agents through the same session and worktree code, so fixes to
+//! non-blocking reads or exit detection only have to land once. The
+//! `.rembrandt/config.toml` schema (`config`) is shared the same way, so the
+//! GUI's settings screen reads and writes exactly what the CLI does.
+
+pub mod agent;
+pub mod auth;
+pub mod beads;
+pub mod buffer;
+pub mod competition;
+pub mod config;
+pub mod diff;
+pub mod hooks;
+pub mod ignore;
+pub mod ipc;
+pub mod isolation;
+pub mod manager;
+pub mod metrics;
+pub mod process;
+pub mod restart;
+pub mod screen;
+pub mod session;
+pub mod worktree;
+
+pub use buffer::RingBuffer;
+pub use diff::{DiffHunk, DiffLine, FileChange, FileDiff};
+pub use manager::{SessionInfo, SessionManager};
+pub use restart::RestartPolicy;
+pub use screen::ScreenSnapshot;
+pub use session::{PtySession, SessionId, SessionStatus};
+pub use worktree::{CopyMode, CopyRules, WorktreeInfo, WorktreeManager};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum RembrandtError {
+    #[error("Git operation failed: {0}")]
+    Git(#[from] git2::Error),
+
+    #[error("Database error: {0}")]
+    Database(#[from] rusqlite::Error),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Agent error: {0}")]
+    Agent(String),
+
+    #[error("Worktree error: {0}")]
+    Worktree(String),
+
+    #[error("Competition error: {0}")]
+    Competition(String),
+
+    #[error("Evaluation error: {0}")]
+    Evaluation(String),
+
+    #[error("Validation error: {0}")]
+    Validation(String),
+
+    #[error("Daemon error: {0}")]
+    Daemon(String),
+
+    #[error("Runtime error: {0}")]
+    Runtime(String),
+
+    #[error("Orchestrator error: {0}")]
+    Orchestrator(String),
+
+    #[error("State error: {0}")]
+    State(String),
+
+    /// A row changed between reading and writing it; re-read and retry
+    #[error("Concurrent update conflict: {0}")]
+    Conflict(String),
+
+    #[error("Isolation error: {0}")]
+    Isolation(String),
+
+    #[error("Configuration error: {0}")]
+    Config(String),
+
+    #[error("Session not found: {0}")]
+    SessionNotFound(String),
+
+    #[error("PTY error: {0}")]
+    Pty(String),
+
+    /// The caller's token doesn't grant the operation
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    /// An error reported by the daemon, with its original code kept
+    #[error("{}", .0.message)]
+    Remote(ErrorPayload),
+}
+
+pub type Result<T> = std::result::Result<T, RembrandtError>;
+
+/// Stable category of a [`RembrandtError`], for clients that branch on the
+/// kind of failure rather than parse its message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    Git,
+    Database,
+    Io,
+    Agent,
+    Worktree,
+    Competition,
+    Evaluation,
+    Validation,
+    Daemon,
+    Runtime,
+    Orchestrator,
+    State,
+    Conflict,
+    Isolation,
+    Config,
+    SessionNotFound,
+    Pty,
+    Forbidden,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::Git => "git",
+            ErrorCode::Database => "database",
+            ErrorCode::Io => "io",
+            ErrorCode::Agent => "agent",
+            ErrorCode::Worktree => "worktree",
+            ErrorCode::Competition => "competition",
+            ErrorCode::Evaluation => "evaluation",
+            ErrorCode::Validation => "validation",
+            ErrorCode::Daemon => "daemon",
+            ErrorCode::Runtime => "runtime",
+            ErrorCode::Orchestrator => "orchestrator",
+            ErrorCode::State => "state",
+            ErrorCode::Conflict => "conflict",
+            ErrorCode::Isolation => "isolation",
+            ErrorCode::Config => "config",
+            ErrorCode::SessionNotFound => "session_not_found",
+            ErrorCode::Pty => "pty",
+            ErrorCode::Forbidden => "forbidden",
+        }
+    }
+
+    /// What to try next, for codes where there's a usual fix
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            ErrorCode::SessionNotFound => Some("run `rembrandt list` to see current sessions"),
+            ErrorCode::Conflict => Some("another process updated the session first; retry the command"),
+            ErrorCode::Config => Some("check .rembrandt/config.toml"),
+            ErrorCode::Database => Some("run `rembrandt doctor` to check state.db"),
+            ErrorCode::Daemon => Some("run `rembrandt doctor` to check the daemon socket"),
+            ErrorCode::Forbidden => Some("use a token whose role allows this (`[api_tokens]` in config.toml)"),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl RembrandtError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            RembrandtError::Git(_) => ErrorCode::Git,
+            RembrandtError::Database(_) => ErrorCode::Database,
+            RembrandtError::Io(_) => ErrorCode::Io,
+            RembrandtError::Agent(_) => ErrorCode::Agent,
+            RembrandtError::Worktree(_) => ErrorCode::Worktree,
+            RembrandtError::Competition(_) => ErrorCode::Competition,
+            RembrandtError::Evaluation(_) => ErrorCode::Evaluation,
+            RembrandtError::Validation(_) => ErrorCode::Validation,
+            RembrandtError::Daemon(_) => ErrorCode::Daemon,
+            RembrandtError::Runtime(_) => ErrorCode::Runtime,
+            RembrandtError::Orchestrator(_) => ErrorCode::Orchestrator,
+            RembrandtError::State(_) => ErrorCode::State,
+            RembrandtError::Conflict(_) => ErrorCode::Conflict,
+            RembrandtError::Isolation(_) => ErrorCode::Isolation,
+            RembrandtError::Config(_) => ErrorCode::Config,
+            RembrandtError::SessionNotFound(_) => ErrorCode::SessionNotFound,
+            RembrandtError::Pty(_) => ErrorCode::Pty,
+            RembrandtError::Forbidden(_) => ErrorCode::Forbidden,
+            RembrandtError::Remote(payload) => payload.code,
+        }
+    }
+
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            RembrandtError::Remote(payload) => payload.hint.clone(),
+            RembrandtError::Git(e) if e.code() == git2::ErrorCode::NotFound => {
+                Some("run `git branch -a` to see available branches".to_string())
+            }
+            other => other.code().hint().map(str::to_string),
+        }
+    }
+}
+
+/// A [`RembrandtError`] as sent over IPC or returned to the GUI
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorPayload {
+    pub code: ErrorCode,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+}
+
+impl From<&RembrandtError> for ErrorPayload {
+    fn from(e: &RembrandtError) -> Self {
+        match e {
+            RembrandtError::Remote(payload) => payload.clone(),
+            e => ErrorPayload {
+                code: e.code(),
+                message: e.to_string(),
+                hint: e.hint(),
+            },
+        }
+    }
+}
+
+impl From<RembrandtError> for ErrorPayload {
+    fn from(e: RembrandtError) -> Self {
+        ErrorPayload::from(&e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payloads_keep_code_and_hint_across_the_wire() {
+        let error = RembrandtError::SessionNotFound("ses-9".to_string());
+        let payload = ErrorPayload::from(&error);
+        assert_eq!(payload.code, ErrorCode::SessionNotFound);
+        assert_eq!(payload.message, "Session not found: ses-9");
+
+        let json = serde_json::to_string(&payload).unwrap();
+        assert!(json.contains("\"code\":\"session_not_found\""));
+        let remote = RembrandtError::Remote(serde_json::from_str(&json).unwrap());
+        assert_eq!(remote.code(), ErrorCode::SessionNotFound);
+        assert_eq!(remote.to_string(), error.to_string());
+        assert_eq!(remote.hint(), error.hint());
+    }
+}
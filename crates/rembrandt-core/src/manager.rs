@@ -0,0 +1,887 @@
+//! Session Manager
+//!
+//! Manages the lifecycle of all PTY sessions. The daemon uses this
+//! to spawn, track, nudge, and cleanup agent sessions.
+
+use crate::agent::{AgentType, NudgeStrategy};
+use crate::process::{ProcessTable, ResourceSample};
+use crate::restart::RestartPolicy;
+use crate::screen::ScreenSnapshot;
+use crate::session::{OutputWaker, PtySession, SessionId, SessionStatus};
+use crate::{RembrandtError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Default output buffer size (10KB per session)
+pub const DEFAULT_BUFFER_CAPACITY: usize = 10 * 1024;
+
+/// Summary of a session for listing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub id: SessionId,
+    pub agent_id: String,
+    pub command: String,
+    pub workdir: String,
+    /// Main repository root, if the workdir is inside one
+    #[serde(default)]
+    pub repo: Option<PathBuf>,
+    pub status: SessionStatus,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    /// Total bytes of output captured so far, to spot stalled agents
+    #[serde(default)]
+    pub output_bytes: usize,
+    /// Whether the agent is paused (SIGSTOP) while running
+    #[serde(default)]
+    pub paused: bool,
+    /// Latest CPU and memory sample of the agent's process tree, while it runs
+    #[serde(default)]
+    pub resources: Option<ResourceSample>,
+    /// Highest resident memory the process tree was sampled at
+    #[serde(default)]
+    pub peak_rss_bytes: u64,
+}
+
+impl From<&PtySession> for SessionInfo {
+    fn from(session: &PtySession) -> Self {
+        Self {
+            id: session.id.clone(),
+            agent_id: session.agent_id.clone(),
+            command: session.command.clone(),
+            workdir: session.workdir.clone(),
+            repo: session.repo.clone(),
+            status: session.status.clone(),
+            created_at: session.created_at,
+            output_bytes: session.output_total_written(),
+            paused: session.paused,
+            resources: session.latest_resources(),
+            peak_rss_bytes: session.peak_rss_bytes(),
+        }
+    }
+}
+
+/// Manages all active PTY sessions
+pub struct SessionManager {
+    /// Active sessions indexed by session ID
+    sessions: HashMap<SessionId, PtySession>,
+    /// Output buffer capacity for new sessions
+    buffer_capacity: usize,
+    /// Nudge ladders that replace an agent type's defaults for new sessions
+    nudge_ladders: HashMap<AgentType, Vec<NudgeStrategy>>,
+    /// Quit sequences that replace an agent type's defaults for new sessions
+    quit_sequences: HashMap<AgentType, Vec<NudgeStrategy>>,
+    /// Restart policies of sessions that have one, and how many restarts
+    /// led up to each
+    restarts: HashMap<SessionId, (RestartPolicy, u32)>,
+    /// Given to every session, so a consumer can wait for output
+    output_waker: Option<OutputWaker>,
+}
+
+impl SessionManager {
+    /// Create a new session manager
+    pub fn new() -> Self {
+        Self {
+            sessions: HashMap::new(),
+            buffer_capacity: DEFAULT_BUFFER_CAPACITY,
+            nudge_ladders: HashMap::new(),
+            quit_sequences: HashMap::new(),
+            restarts: HashMap::new(),
+            output_waker: None,
+        }
+    }
+
+    /// Create with custom buffer capacity
+    pub fn with_buffer_capacity(capacity: usize) -> Self {
+        Self {
+            sessions: HashMap::new(),
+            buffer_capacity: capacity,
+            nudge_ladders: HashMap::new(),
+            quit_sequences: HashMap::new(),
+            restarts: HashMap::new(),
+            output_waker: None,
+        }
+    }
+
+    /// Output buffer capacity for sessions spawned from now on
+    pub fn set_buffer_capacity(&mut self, capacity: usize) {
+        self.buffer_capacity = capacity;
+    }
+
+    /// Override the default nudge ladder of some agent types for sessions
+    /// spawned from now on
+    pub fn set_nudge_ladders(&mut self, ladders: HashMap<AgentType, Vec<NudgeStrategy>>) {
+        self.nudge_ladders = ladders;
+    }
+
+    /// Override the default quit sequence of some agent types for sessions
+    /// spawned from now on
+    pub fn set_quit_sequences(&mut self, sequences: HashMap<AgentType, Vec<NudgeStrategy>>) {
+        self.quit_sequences = sequences;
+    }
+
+    /// Call `waker` whenever any session has new output or its PTY closes,
+    /// for sessions running now and spawned from now on
+    pub fn set_output_waker(&mut self, waker: OutputWaker) {
+        for session in self.sessions.values() {
+            session.set_output_waker(waker.clone());
+        }
+        self.output_waker = Some(waker);
+    }
+
+    /// Spawn a new agent session
+    ///
+    /// Returns the session ID on success.
+    pub fn spawn(
+        &mut self,
+        agent_id: String,
+        command: &str,
+        args: &[&str],
+        workdir: &Path,
+    ) -> Result<SessionId> {
+        self.spawn_with_size(agent_id, command, args, workdir, None, None)
+    }
+
+    /// Spawn a new agent session with specific terminal size
+    ///
+    /// Returns the session ID on success.
+    pub fn spawn_with_size(
+        &mut self,
+        agent_id: String,
+        command: &str,
+        args: &[&str],
+        workdir: &Path,
+        rows: Option<u16>,
+        cols: Option<u16>,
+    ) -> Result<SessionId> {
+        self.spawn_with_env(agent_id, command, args, &[], workdir, rows, cols)
+    }
+
+    /// Spawn a new agent session with extra environment variables
+    ///
+    /// Returns the session ID on success.
+    #[allow(clippy::too_many_arguments)]
+    pub fn spawn_with_env(
+        &mut self,
+        agent_id: String,
+        command: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        workdir: &Path,
+        rows: Option<u16>,
+        cols: Option<u16>,
+    ) -> Result<SessionId> {
+        let mut session = PtySession::spawn(
+            agent_id,
+            command,
+            args,
+            env,
+            workdir,
+            self.buffer_capacity,
+            rows,
+            cols,
+        )?;
+        let agent_type = AgentType::from_command(command);
+        if let Some(ladder) = self.nudge_ladders.get(&agent_type) {
+            session.set_nudge_ladder(ladder.clone());
+        }
+        if let Some(sequence) = self.quit_sequences.get(&agent_type) {
+            session.set_quit_sequence(sequence.clone());
+        }
+        if let Some(waker) = &self.output_waker {
+            session.set_output_waker(waker.clone());
+        }
+        let id = session.id.clone();
+        self.sessions.insert(id.clone(), session);
+        crate::metrics::record_spawn();
+        Ok(id)
+    }
+
+    /// Have a session's agent respawned when it crashes, as `policy` allows
+    pub fn set_restart_policy(&mut self, id: &str, policy: RestartPolicy) -> Result<()> {
+        if !self.sessions.contains_key(id) {
+            return Err(RembrandtError::SessionNotFound(id.to_string()));
+        }
+        if policy == RestartPolicy::Never {
+            self.restarts.remove(id);
+        } else {
+            self.restarts.insert(id.to_string(), (policy, 0));
+        }
+        Ok(())
+    }
+
+    /// The number the next restart of an exited session would have, if its
+    /// restart policy calls for one
+    ///
+    /// Sessions stopped on purpose (`kill`, `terminate`, `send_quit`) have
+    /// lost their policy by then and are never due.
+    pub fn restart_due(&self, id: &str) -> Option<u32> {
+        let session = self.sessions.get(id)?;
+        let (policy, attempts) = self.restarts.get(id)?;
+        policy
+            .should_restart(&session.status, *attempts)
+            .then_some(attempts + 1)
+    }
+
+    /// Start a session's agent again, in place of the exited session
+    ///
+    /// The new session runs the same command in the same workdir, with the
+    /// same environment, terminal size and buffer capacity, and inherits the restart
+    /// policy with one more attempt counted. The old session is removed.
+    pub fn respawn(&mut self, id: &str) -> Result<SessionId> {
+        let old = self
+            .sessions
+            .get(id)
+            .ok_or_else(|| RembrandtError::SessionNotFound(id.to_string()))?;
+        if old.is_running() {
+            return Err(RembrandtError::Runtime(format!("session {} is still running", id)));
+        }
+        let agent_id = old.agent_id.clone();
+        let command = old.command.clone();
+        let args = old.args.clone();
+        let env = old.env.clone();
+        let workdir = PathBuf::from(&old.workdir);
+        let (rows, cols) = old.size();
+        let capacity = old.buffer_capacity();
+
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let new_id = self.spawn_with_env(
+            agent_id,
+            &command,
+            &args,
+            &env,
+            &workdir,
+            Some(rows),
+            Some(cols),
+        )?;
+        self.resize_buffer(&new_id, capacity)?;
+        if let Some((policy, attempts)) = self.restarts.remove(id) {
+            self.restarts.insert(new_id.clone(), (policy, attempts + 1));
+        }
+        self.sessions.remove(id);
+        Ok(new_id)
+    }
+
+    /// Get a session by ID
+    pub fn get(&self, id: &str) -> Option<&PtySession> {
+        self.sessions.get(id)
+    }
+
+    /// Get a mutable session by ID
+    pub fn get_mut(&mut self, id: &str) -> Option<&mut PtySession> {
+        self.sessions.get_mut(id)
+    }
+
+    /// Read buffered output from a session
+    pub fn read_output(&self, id: &str) -> Option<String> {
+        self.sessions.get(id).map(|s| s.read_output())
+    }
+
+    /// What a session's terminal currently shows
+    pub fn get_screen(&self, id: &str) -> Result<ScreenSnapshot> {
+        self.sessions
+            .get(id)
+            .map(PtySession::screen)
+            .ok_or_else(|| RembrandtError::SessionNotFound(id.to_string()))
+    }
+
+    /// Change how much output history a session keeps
+    ///
+    /// Shrinking drops the oldest output; growing keeps everything there is.
+    pub fn resize_buffer(&self, id: &str, capacity: usize) -> Result<()> {
+        self.sessions
+            .get(id)
+            .ok_or_else(|| RembrandtError::SessionNotFound(id.to_string()))?
+            .resize_buffer(capacity)
+    }
+
+    /// Get raw buffered output history for a session (ANSI codes intact)
+    pub fn get_history(&self, id: &str) -> Result<Vec<u8>> {
+        self.sessions
+            .get(id)
+            .ok_or_else(|| RembrandtError::SessionNotFound(id.to_string()))
+            .map(|s| s.read_output_raw())
+    }
+
+    /// Buffered output of a session from stream offset `offset` on, and the
+    /// offset it actually starts at
+    pub fn output_since(&self, id: &str, offset: usize) -> Result<(usize, Vec<u8>)> {
+        self.sessions
+            .get(id)
+            .ok_or_else(|| RembrandtError::SessionNotFound(id.to_string()))
+            .map(|s| s.read_since(offset))
+    }
+
+    /// The last `n` lines of a session's buffered output
+    pub fn last_lines(&self, id: &str, n: usize) -> Result<Vec<String>> {
+        self.sessions
+            .get(id)
+            .ok_or_else(|| RembrandtError::SessionNotFound(id.to_string()))
+            .map(|s| s.read_last_lines(n))
+    }
+
+    /// Send a nudge to a session; returns the strategy used
+    pub fn nudge(&mut self, id: &str) -> Result<NudgeStrategy> {
+        self.sessions
+            .get_mut(id)
+            .ok_or_else(|| RembrandtError::SessionNotFound(id.to_string()))?
+            .nudge()
+    }
+
+    /// Take a session's next nudge step without typing it (see
+    /// `PtySession::next_nudge`)
+    pub fn next_nudge(&mut self, id: &str) -> Result<NudgeStrategy> {
+        self.sessions
+            .get_mut(id)
+            .ok_or_else(|| RembrandtError::SessionNotFound(id.to_string()))?
+            .next_nudge()
+    }
+
+    /// Use a custom nudge ladder for a session
+    pub fn set_nudge_ladder(&mut self, id: &str, ladder: Vec<NudgeStrategy>) -> Result<()> {
+        self.sessions
+            .get_mut(id)
+            .ok_or_else(|| RembrandtError::SessionNotFound(id.to_string()))?
+            .set_nudge_ladder(ladder);
+        Ok(())
+    }
+
+    /// Type a session's quit sequence (see `PtySession::send_quit`)
+    pub fn send_quit(&mut self, id: &str) -> Result<()> {
+        let session = self
+            .sessions
+            .get_mut(id)
+            .ok_or_else(|| RembrandtError::SessionNotFound(id.to_string()))?;
+        session.stop_requested = true;
+        // A stopped process can't read its quit keys or handle SIGTERM
+        if session.paused {
+            session.resume()?;
+        }
+        session.send_quit()?;
+        self.restarts.remove(id);
+        Ok(())
+    }
+
+    /// SIGTERM a session's process tree
+    pub fn terminate(&mut self, id: &str) -> Result<()> {
+        let session = self
+            .sessions
+            .get_mut(id)
+            .ok_or_else(|| RembrandtError::SessionNotFound(id.to_string()))?;
+        session.stop_requested = true;
+        if session.paused {
+            session.resume()?;
+        }
+        session.terminate();
+        self.restarts.remove(id);
+        Ok(())
+    }
+
+    /// Pause a session's agent with SIGSTOP
+    pub fn pause(&mut self, id: &str) -> Result<()> {
+        self.sessions
+            .get_mut(id)
+            .ok_or_else(|| RembrandtError::SessionNotFound(id.to_string()))?
+            .pause()
+    }
+
+    /// Continue a paused session's agent
+    pub fn resume(&mut self, id: &str) -> Result<()> {
+        self.sessions
+            .get_mut(id)
+            .ok_or_else(|| RembrandtError::SessionNotFound(id.to_string()))?
+            .resume()
+    }
+
+    /// Write data to a session's PTY
+    pub fn write(&mut self, id: &str, data: &[u8]) -> Result<()> {
+        self.sessions
+            .get_mut(id)
+            .ok_or_else(|| RembrandtError::SessionNotFound(id.to_string()))?
+            .write(data)
+    }
+
+    /// Resize a session's PTY
+    pub fn resize(&self, id: &str, rows: u16, cols: u16) -> Result<()> {
+        self.sessions
+            .get(id)
+            .ok_or_else(|| RembrandtError::SessionNotFound(id.to_string()))?
+            .resize(rows, cols)
+    }
+
+    /// Kill a session
+    pub fn kill(&mut self, id: &str) -> Result<()> {
+        let session = self
+            .sessions
+            .get_mut(id)
+            .ok_or_else(|| RembrandtError::SessionNotFound(id.to_string()))?;
+        session.stop_requested = true;
+        session.kill()?;
+        self.restarts.remove(id);
+        crate::metrics::record_kill();
+        Ok(())
+    }
+
+    /// Remove a session from management
+    ///
+    /// Returns the session if it existed.
+    pub fn remove(&mut self, id: &str) -> Option<PtySession> {
+        self.restarts.remove(id);
+        self.sessions.remove(id)
+    }
+
+    /// List all sessions
+    pub fn list(&self) -> Vec<SessionInfo> {
+        self.sessions.values().map(SessionInfo::from).collect()
+    }
+
+    /// List sessions for a specific agent
+    pub fn list_by_agent(&self, agent_id: &str) -> Vec<SessionInfo> {
+        self.sessions
+            .values()
+            .filter(|s| s.agent_id == agent_id)
+            .map(SessionInfo::from)
+            .collect()
+    }
+
+    /// List sessions whose workdir belongs to the repository at `repo`
+    pub fn list_by_repo(&self, repo: &Path) -> Vec<SessionInfo> {
+        let repo = normalize_repo(repo);
+        self.sessions
+            .values()
+            .filter(|s| s.repo.as_deref() == Some(repo.as_path()))
+            .map(SessionInfo::from)
+            .collect()
+    }
+
+    /// Most recent session for an agent in a given repository
+    ///
+    /// Agent IDs are only unique per repository, so lookups that may span
+    /// repositories (e.g. through a shared daemon) should use this rather
+    /// than `list_by_agent`.
+    pub fn find_agent(&self, repo: &Path, agent_id: &str) -> Option<&PtySession> {
+        let repo = normalize_repo(repo);
+        self.sessions
+            .values()
+            .filter(|s| s.agent_id == agent_id && s.repo.as_deref() == Some(repo.as_path()))
+            .max_by_key(|s| s.created_at)
+    }
+
+    /// Poll all sessions and update their status
+    pub fn poll_all(&mut self) {
+        for session in self.sessions.values_mut() {
+            session.poll();
+        }
+    }
+
+    /// Take a resource sample of every running session from one scan of
+    /// the process table
+    pub fn sample_resources(&mut self, processes: &ProcessTable) {
+        for session in self.sessions.values_mut() {
+            session.sample_resources(processes);
+        }
+    }
+
+    /// Mark every session's captured output as drained
+    ///
+    /// Output is captured in the background; this only moves each session's
+    /// drain cursor, so a later attach streams just what's new.
+    pub fn read_all_available(&mut self) {
+        for session in self.sessions.values_mut() {
+            session.read_available();
+        }
+    }
+
+    /// Get IDs of all exited sessions
+    pub fn exited_sessions(&self) -> Vec<SessionId> {
+        self.sessions
+            .values()
+            .filter(|s| !s.is_running())
+            .map(|s| s.id.clone())
+            .collect()
+    }
+
+    /// Cleanup exited sessions based on policy
+    ///
+    /// Policy: Remove successful sessions (exit code 0) immediately.
+    /// Keep failed sessions (non-zero exit or Failed status) for inspection
+    /// until explicitly removed via `cleanup_failed()` or `remove()`.
+    ///
+    /// Rationale: Failures are the signal - you want to inspect them.
+    /// Successes produced their artifacts (git commits) and can be cleaned.
+    /// Session metadata will be persisted to SQLite (rembrandt-xz6) before
+    /// cleanup for audit trail.
+    ///
+    /// Returns IDs of sessions that were removed.
+    pub fn cleanup(&mut self) -> Vec<SessionId> {
+        let successful: Vec<SessionId> = self
+            .sessions
+            .iter()
+            .filter(|(_, s)| s.status == SessionStatus::Exited(0))
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        for id in &successful {
+            self.sessions.remove(id);
+        }
+
+        successful
+    }
+
+    /// Cleanup all exited sessions, including failures
+    ///
+    /// Use this for explicit "I've seen the failures, clean them up" action.
+    /// Returns IDs of sessions that were removed.
+    pub fn cleanup_all(&mut self) -> Vec<SessionId> {
+        let exited = self.exited_sessions();
+        for id in &exited {
+            self.remove(id);
+        }
+        exited
+    }
+
+    /// Get IDs of failed sessions (non-zero exit or Failed status)
+    pub fn failed_sessions(&self) -> Vec<SessionId> {
+        self.sessions
+            .values()
+            .filter(|s| matches!(&s.status,
+                SessionStatus::Exited(code) if *code != 0)
+                || matches!(&s.status, SessionStatus::Failed(_)))
+            .map(|s| s.id.clone())
+            .collect()
+    }
+
+    /// Number of active sessions
+    pub fn active_count(&self) -> usize {
+        self.sessions.values().filter(|s| s.is_running()).count()
+    }
+
+    /// Total number of sessions (including exited)
+    pub fn total_count(&self) -> usize {
+        self.sessions.len()
+    }
+}
+
+/// Canonical form of a repository path, matching `PtySession::repo`
+fn normalize_repo(repo: &Path) -> PathBuf {
+    crate::worktree::main_repo_root(repo).unwrap_or_else(|| repo.to_path_buf())
+}
+
+impl Default for SessionManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    fn wait_for_exit(manager: &mut SessionManager, ids: &[&SessionId]) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while Instant::now() < deadline {
+            manager.read_all_available();
+            manager.poll_all();
+            if ids.iter().all(|id| !manager.get(id).unwrap().is_running()) {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        panic!("sessions did not exit in time");
+    }
+
+    #[test]
+    fn cleanup_preserves_failures_and_removes_successes() {
+        // Policy:
+        // 1. Successful exits (code 0) → auto-cleaned (artifacts are in git)
+        // 2. Failed exits (code != 0) → preserved for inspection
+        // 3. Running → never cleaned
+        let dir = std::env::temp_dir();
+        let mut manager = SessionManager::new();
+        let ok = manager
+            .spawn("ok".to_string(), "sh", &["-c", "exit 0"], &dir)
+            .unwrap();
+        let failed = manager
+            .spawn("failed".to_string(), "sh", &["-c", "exit 3"], &dir)
+            .unwrap();
+        wait_for_exit(&mut manager, &[&ok, &failed]);
+
+        let removed = manager.cleanup();
+        assert_eq!(removed, vec![ok]);
+        assert_eq!(manager.failed_sessions(), vec![failed.clone()]);
+
+        let removed = manager.cleanup_all();
+        assert_eq!(removed, vec![failed]);
+        assert_eq!(manager.total_count(), 0);
+    }
+
+    #[test]
+    fn output_wakes_the_consumer() {
+        use std::sync::mpsc;
+
+        let dir = std::env::temp_dir();
+        let mut manager = SessionManager::new();
+        let (tx, rx) = mpsc::channel();
+        let tx = std::sync::Mutex::new(tx);
+        manager.set_output_waker(std::sync::Arc::new(move || {
+            let _ = tx.lock().unwrap().send(());
+        }));
+        let id = manager
+            .spawn("waker".to_string(), "sh", &["-c", "sleep 0.2; echo hello"], &dir)
+            .unwrap();
+
+        // Drained only when woken, never on a timer
+        let mut output = Vec::new();
+        while !String::from_utf8_lossy(&output).contains("hello") {
+            rx.recv_timeout(Duration::from_secs(10)).expect("no wakeup for output");
+            output.extend(manager.get_mut(&id).unwrap().drain_available());
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn samples_the_process_tree() {
+        let dir = std::env::temp_dir();
+        let mut manager = SessionManager::new();
+        let id = manager
+            .spawn("busy".to_string(), "sh", &["-c", "sleep 5 & sleep 5"], &dir)
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+        manager.sample_resources(&ProcessTable::scan());
+        manager.sample_resources(&ProcessTable::scan());
+
+        let info = manager.list().into_iter().find(|s| s.id == id).unwrap();
+        let sample = info.resources.expect("no sample of a running session");
+        assert!(sample.processes >= 2);
+        assert!(sample.rss_bytes > 0 && info.peak_rss_bytes >= sample.rss_bytes);
+        assert_eq!(manager.get(&id).unwrap().resource_samples().len(), 2);
+
+        manager.kill(&id).unwrap();
+        assert!(manager.list()[0].resources.is_none());
+    }
+
+    #[test]
+    fn output_is_captured_without_polling() {
+        let dir = std::env::temp_dir();
+        let mut manager = SessionManager::new();
+        let id = manager
+            .spawn("quiet".to_string(), "sh", &["-c", "echo first; sleep 0.3; echo second"], &dir)
+            .unwrap();
+
+        // Nobody drains while it runs; both lines still land in history
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while manager.get_mut(&id).unwrap().poll() == SessionStatus::Running {
+            assert!(Instant::now() < deadline, "session did not exit in time");
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        let session = manager.get_mut(&id).unwrap();
+        let history = String::from_utf8_lossy(&session.read_output_raw()).into_owned();
+        assert!(history.contains("first") && history.contains("second"), "{:?}", history);
+
+        // A drain hands over everything once, from the start of the stream
+        let (offset, data) = session.drain_output();
+        assert_eq!((offset, data.len()), (0, session.output_total_written()));
+        assert!(session.drain_output().1.is_empty());
+    }
+
+    #[test]
+    fn screen_shows_redrawn_output() {
+        let dir = std::env::temp_dir();
+        let mut manager = SessionManager::new();
+        let id = manager
+            .spawn(
+                "painter".to_string(),
+                "sh",
+                &["-c", r"printf 'loading 10%%\rloading 99%%\r\033[Kready\n'"],
+                &dir,
+            )
+            .unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while manager.get_mut(&id).unwrap().poll() == SessionStatus::Running {
+            assert!(Instant::now() < deadline, "session did not exit in time");
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        let screen = manager.get_screen(&id).unwrap();
+        assert_eq!(screen.text(), "ready");
+        assert!(manager.get_screen("missing").is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn kill_takes_processes_that_left_the_group() {
+        fn alive(pid: u32) -> bool {
+            std::fs::read_to_string(format!("/proc/{pid}/stat"))
+                .is_ok_and(|stat| !matches!(stat.rsplit_once(") ").map(|(_, r)| &r[..1]), Some("Z")))
+        }
+
+        let dir = std::env::temp_dir();
+        let mut manager = SessionManager::new();
+        // One sleep in a job-control group of its own, one in a new session
+        let id = manager
+            .spawn(
+                "spawner".to_string(),
+                "sh",
+                &[
+                    "-c",
+                    "setsid sleep 60 & echo daemon=$!; set -m; sleep 60 & echo job=$!; wait",
+                ],
+                &dir,
+            )
+            .unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let pids = loop {
+            let lines = manager.last_lines(&id, 10).unwrap();
+            let pids: Vec<u32> = lines
+                .iter()
+                .filter_map(|l| l.trim().split_once('=').and_then(|(_, p)| p.parse().ok()))
+                .collect();
+            if pids.len() == 2 {
+                break pids;
+            }
+            assert!(Instant::now() < deadline, "helpers did not start: {lines:?}");
+            std::thread::sleep(Duration::from_millis(20));
+        };
+        assert!(pids.iter().all(|pid| alive(*pid)));
+
+        manager.kill(&id).unwrap();
+        assert_eq!(manager.get_mut(&id).unwrap().poll(), SessionStatus::Exited(-1));
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while pids.iter().any(|pid| alive(*pid)) {
+            assert!(Instant::now() < deadline, "helpers outlived the kill: {pids:?}");
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    #[test]
+    fn crashed_sessions_respawn_until_retries_run_out() {
+        let dir = std::env::temp_dir();
+        let mut manager = SessionManager::new();
+        let id = manager
+            .spawn("crasher".to_string(), "sh", &["-c", "echo crashed; exit 3"], &dir)
+            .unwrap();
+        manager
+            .set_restart_policy(&id, RestartPolicy::OnFailure { max_retries: 1 })
+            .unwrap();
+        assert_eq!(manager.restart_due(&id), None);
+
+        wait_for_exit(&mut manager, &[&id]);
+        assert!(manager.get(&id).unwrap().crashed());
+        assert_eq!(manager.restart_due(&id), Some(1));
+        let again = manager.respawn(&id).unwrap();
+        assert!(manager.get(&id).is_none());
+        let session = manager.get(&again).unwrap();
+        assert_eq!(session.agent_id, "crasher");
+        assert_eq!(session.args, vec!["-c", "echo crashed; exit 3"]);
+
+        wait_for_exit(&mut manager, &[&again]);
+        assert_eq!(manager.get(&again).unwrap().status, SessionStatus::Exited(3));
+        assert_eq!(manager.restart_due(&again), None);
+
+        // Stopping a session on purpose drops its policy
+        let id = manager
+            .spawn("stopped".to_string(), "sh", &["-c", "sleep 5"], &dir)
+            .unwrap();
+        manager
+            .set_restart_policy(&id, RestartPolicy::OnFailure { max_retries: 3 })
+            .unwrap();
+        manager.kill(&id).unwrap();
+        assert_eq!(manager.restart_due(&id), None);
+        assert!(!manager.get(&id).unwrap().crashed());
+    }
+
+    #[test]
+    fn env_reaches_the_agent_and_survives_respawn() {
+        let dir = std::env::temp_dir();
+        let mut manager = SessionManager::new();
+        let env = crate::agent::context_env("envy", "rembrandt/envy", Some("br-7"));
+        let id = manager
+            .spawn_with_env(
+                "envy".to_string(),
+                "sh",
+                &["-c", "echo \"id=$REMBRANDT_AGENT_ID task=$REMBRANDT_TASK_ID\"; exit 1"],
+                &env,
+                &dir,
+                None,
+                None,
+            )
+            .unwrap();
+        wait_for_exit(&mut manager, &[&id]);
+        assert!(manager.read_output(&id).unwrap().contains("id=envy task=br-7"));
+
+        let again = manager.respawn(&id).unwrap();
+        wait_for_exit(&mut manager, &[&again]);
+        assert_eq!(manager.get(&again).unwrap().env, env);
+        assert!(manager.read_output(&again).unwrap().contains("id=envy task=br-7"));
+    }
+
+    #[test]
+    fn same_agent_id_in_two_repos_stays_separate() {
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        git2::Repository::init(first.path()).unwrap();
+        git2::Repository::init(second.path()).unwrap();
+
+        let mut manager = SessionManager::new();
+        let a = manager
+            .spawn("claude-1".to_string(), "sh", &["-c", "sleep 5"], first.path())
+            .unwrap();
+        let b = manager
+            .spawn("claude-1".to_string(), "sh", &["-c", "sleep 5"], second.path())
+            .unwrap();
+
+        let listed = manager.list_by_repo(first.path());
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, a);
+        assert_eq!(manager.find_agent(second.path(), "claude-1").unwrap().id, b);
+
+        manager.kill(&a).unwrap();
+        manager.kill(&b).unwrap();
+    }
+
+    #[test]
+    fn nudges_escalate_until_the_agent_responds() {
+        let dir = std::env::temp_dir();
+        let mut manager = SessionManager::new();
+        // The first nudge unblocks `read`, and the agent answers with output
+        let id = manager
+            .spawn(
+                "stalled".to_string(),
+                "sh",
+                &["-c", "read x; head -c 2000 /dev/zero | tr '\\0' a; sleep 10"],
+                &dir,
+            )
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+
+        assert_eq!(manager.nudge(&id).unwrap(), NudgeStrategy::Newline);
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while manager.get(&id).unwrap().output_total_written() < 2000 {
+            assert!(Instant::now() < deadline, "no output after the nudge");
+            manager.read_all_available();
+            std::thread::sleep(Duration::from_millis(20));
+        }
+
+        // Output since the last nudge starts the ladder over; silence climbs it,
+        // and the last step repeats
+        assert_eq!(manager.nudge(&id).unwrap(), NudgeStrategy::Newline);
+        assert_eq!(manager.nudge(&id).unwrap(), NudgeStrategy::Enter);
+        assert_eq!(manager.nudge(&id).unwrap(), NudgeStrategy::Enter);
+        manager.kill(&id).unwrap();
+    }
+
+    #[test]
+    fn configured_ladders_apply_to_new_sessions() {
+        let dir = std::env::temp_dir();
+        let mut manager = SessionManager::new();
+        let ladder = vec![NudgeStrategy::Text("keep going".to_string())];
+        manager.set_nudge_ladders(HashMap::from([(AgentType::from_str("sh"), ladder.clone())]));
+
+        let id = manager
+            .spawn("sh".to_string(), "/bin/sh", &["-c", "sleep 10"], &dir)
+            .unwrap();
+        assert_eq!(manager.nudge(&id).unwrap(), ladder[0]);
+        manager.kill(&id).unwrap();
+    }
+}
@@ -0,0 +1,270 @@
+//! Process-wide counters exposed in the Prometheus text format
+//!
+//! Sessions, competitions and validation record into a single global
+//! registry; `render` turns it, plus a snapshot of live sessions, into the
+//! body served at `/metrics`. There is no client library dependency: the
+//! exposition format is plain text and the daemon serves it directly.
+//!
+//! Competitions run in the process that started them (`rembrandt compete`),
+//! not the daemon, so that process reports each finished one with
+//! `DaemonCommand::RecordCompetition` and the daemon records it here.
+
+use crate::manager::SessionInfo;
+use crate::session::SessionStatus;
+use crate::{RembrandtError, Result};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Upper bounds (seconds) of the validation duration histogram buckets
+const VALIDATION_BUCKETS: &[f64] = &[1.0, 5.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0];
+
+static SPAWNS: AtomicU64 = AtomicU64::new(0);
+static KILLS: AtomicU64 = AtomicU64::new(0);
+static DROPPED_OUTPUT: AtomicU64 = AtomicU64::new(0);
+static COMPETITIONS: Mutex<BTreeMap<&'static str, u64>> = Mutex::new(BTreeMap::new());
+static VALIDATIONS: Mutex<Histogram> = Mutex::new(Histogram::new());
+
+struct Histogram {
+    /// Per-bucket (non-cumulative) counts, plus one for +Inf
+    counts: [u64; VALIDATION_BUCKETS.len() + 1],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    const fn new() -> Self {
+        Self {
+            counts: [0; VALIDATION_BUCKETS.len() + 1],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        let bucket = VALIDATION_BUCKETS
+            .iter()
+            .position(|&le| value <= le)
+            .unwrap_or(VALIDATION_BUCKETS.len());
+        self.counts[bucket] += 1;
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+/// Count a spawned PTY session
+pub fn record_spawn() {
+    SPAWNS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Count a killed PTY session
+pub fn record_kill() {
+    KILLS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Count live output bytes an attached client never received
+pub fn record_dropped_output(bytes: usize) {
+    DROPPED_OUTPUT.fetch_add(bytes as u64, Ordering::Relaxed);
+}
+
+/// Count a finished competition by its outcome (`completed`, `failed`,
+/// `cancelled`), with how long validating each of its solutions took
+pub fn record_competition(outcome: &str, validations: &[Duration]) -> Result<()> {
+    let outcome = ["completed", "failed", "cancelled"]
+        .into_iter()
+        .find(|known| *known == outcome)
+        .ok_or_else(|| RembrandtError::Validation(format!("unknown competition outcome '{}'", outcome)))?;
+    *COMPETITIONS.lock().unwrap_or_else(|e| e.into_inner()).entry(outcome).or_default() += 1;
+    let mut histogram = VALIDATIONS.lock().unwrap_or_else(|e| e.into_inner());
+    for duration in validations {
+        histogram.observe(duration.as_secs_f64());
+    }
+    Ok(())
+}
+
+/// Render all metrics in the Prometheus text exposition format
+pub fn render(sessions: &[SessionInfo]) -> String {
+    let mut out = String::new();
+
+    let mut by_status: BTreeMap<&str, u64> =
+        [("running", 0), ("exited", 0), ("failed", 0)].into_iter().collect();
+    for session in sessions {
+        let status = match session.status {
+            SessionStatus::Running => "running",
+            SessionStatus::Exited(_) => "exited",
+            SessionStatus::Failed(_) => "failed",
+        };
+        *by_status.entry(status).or_default() += 1;
+    }
+    header(&mut out, "rembrandt_sessions", "gauge", "PTY sessions by status");
+    for (status, count) in &by_status {
+        let _ = writeln!(out, "rembrandt_sessions{{status=\"{}\"}} {}", status, count);
+    }
+
+    header(&mut out, "rembrandt_session_spawns_total", "counter", "PTY sessions spawned");
+    let _ = writeln!(out, "rembrandt_session_spawns_total {}", SPAWNS.load(Ordering::Relaxed));
+    header(&mut out, "rembrandt_session_kills_total", "counter", "PTY sessions killed");
+    let _ = writeln!(out, "rembrandt_session_kills_total {}", KILLS.load(Ordering::Relaxed));
+
+    header(
+        &mut out,
+        "rembrandt_pty_bytes_read_total",
+        "counter",
+        "Bytes of output read from each session's PTY",
+    );
+    for session in sessions {
+        let _ = writeln!(
+            out,
+            "rembrandt_pty_bytes_read_total{{session_id=\"{}\",agent_id=\"{}\"}} {}",
+            escape(&session.id),
+            escape(&session.agent_id),
+            session.output_bytes
+        );
+    }
+
+    header(
+        &mut out,
+        "rembrandt_session_cpu_percent",
+        "gauge",
+        "CPU use of each running session's process tree, in percent of one core",
+    );
+    for session in sessions {
+        if let Some(sample) = &session.resources {
+            let _ = writeln!(
+                out,
+                "rembrandt_session_cpu_percent{{session_id=\"{}\",agent_id=\"{}\"}} {:.1}",
+                escape(&session.id),
+                escape(&session.agent_id),
+                sample.cpu_percent
+            );
+        }
+    }
+    header(
+        &mut out,
+        "rembrandt_session_rss_bytes",
+        "gauge",
+        "Resident memory of each running session's process tree",
+    );
+    for session in sessions {
+        if let Some(sample) = &session.resources {
+            let _ = writeln!(
+                out,
+                "rembrandt_session_rss_bytes{{session_id=\"{}\",agent_id=\"{}\"}} {}",
+                escape(&session.id),
+                escape(&session.agent_id),
+                sample.rss_bytes
+            );
+        }
+    }
+
+    header(
+        &mut out,
+        "rembrandt_output_dropped_bytes_total",
+        "counter",
+        "Live output bytes dropped for clients that fell behind (history keeps them)",
+    );
+    let _ = writeln!(
+        out,
+        "rembrandt_output_dropped_bytes_total {}",
+        DROPPED_OUTPUT.load(Ordering::Relaxed)
+    );
+
+    header(
+        &mut out,
+        "rembrandt_competitions_total",
+        "counter",
+        "Competitions by outcome",
+    );
+    for (outcome, count) in COMPETITIONS.lock().unwrap_or_else(|e| e.into_inner()).iter() {
+        let _ = writeln!(out, "rembrandt_competitions_total{{outcome=\"{}\"}} {}", outcome, count);
+    }
+
+    header(
+        &mut out,
+        "rembrandt_validation_duration_seconds",
+        "histogram",
+        "Time spent type-checking and testing a solution",
+    );
+    let validations = VALIDATIONS.lock().unwrap_or_else(|e| e.into_inner());
+    let mut cumulative = 0;
+    for (le, count) in VALIDATION_BUCKETS.iter().zip(validations.counts.iter()) {
+        cumulative += count;
+        let _ = writeln!(
+            out,
+            "rembrandt_validation_duration_seconds_bucket{{le=\"{}\"}} {}",
+            le, cumulative
+        );
+    }
+    let _ = writeln!(
+        out,
+        "rembrandt_validation_duration_seconds_bucket{{le=\"+Inf\"}} {}",
+        validations.count
+    );
+    let _ = writeln!(out, "rembrandt_validation_duration_seconds_sum {}", validations.sum);
+    let _ = writeln!(out, "rembrandt_validation_duration_seconds_count {}", validations.count);
+
+    out
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+/// Escape a label value (backslash, quote, newline)
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::ResourceSample;
+
+    #[test]
+    fn renders_sessions_and_histogram() {
+        record_competition("completed", &[Duration::from_secs(3)]).unwrap();
+        assert!(record_competition("won", &[]).is_err());
+        let info = SessionInfo {
+            id: "s1".to_string(),
+            agent_id: "claude-\"x\"".to_string(),
+            command: "claude".to_string(),
+            workdir: "/tmp".to_string(),
+            repo: None,
+            status: SessionStatus::Running,
+            created_at: chrono::Utc::now(),
+            output_bytes: 42,
+            paused: false,
+            resources: Some(ResourceSample {
+                at: chrono::Utc::now(),
+                cpu_percent: 187.5,
+                rss_bytes: 3 << 30,
+                processes: 14,
+            }),
+            peak_rss_bytes: 3 << 30,
+        };
+
+        let text = render(&[info]);
+        assert!(text.contains("rembrandt_sessions{status=\"running\"} 1"));
+        assert!(text.contains("rembrandt_sessions{status=\"failed\"} 0"));
+        assert!(text.contains("rembrandt_session_cpu_percent{session_id=\"s1\",agent_id=\"claude-\\\"x\\\"\"} 187.5"));
+        assert!(text.contains("rembrandt_session_rss_bytes{session_id=\"s1\",agent_id=\"claude-\\\"x\\\"\"} 3221225472"));
+        assert!(text.contains(
+            "rembrandt_pty_bytes_read_total{session_id=\"s1\",agent_id=\"claude-\\\"x\\\"\"} 42"
+        ));
+        // Buckets are cumulative: the 3s sample is in every bucket from le=5.
+        // Other tests may validate concurrently, so compare bucket counts
+        let bucket = |le: &str| -> u64 {
+            let prefix = format!("rembrandt_validation_duration_seconds_bucket{{le=\"{}\"}} ", le);
+            text.lines()
+                .find_map(|line| line.strip_prefix(prefix.as_str()))
+                .unwrap()
+                .parse()
+                .unwrap()
+        };
+        assert!(bucket("5") > bucket("1"));
+        assert!(bucket("+Inf") >= bucket("5"));
+    }
+}
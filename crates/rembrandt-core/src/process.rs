@@ -0,0 +1,427 @@
+//! Process trees of agent sessions
+//!
+//! portable-pty starts every agent with `setsid()`, so the agent leads its
+//! own session and process group. A group signal reaches everything the
+//! agent started in its terminal, but not what left the group: shells with
+//! job control put each pipeline in a group of its own, and daemonizing
+//! tools (dev servers, watchers) call `setsid()` themselves. On Linux the
+//! tree is therefore walked through `/proc` as well: every process in the
+//! agent's session, and every descendant of one, is signalled directly.
+//! A process that double-forks out of the session is orphaned to init and
+//! cannot be traced back to the agent this way. Each pid found by the walk
+//! is signalled only if it still has the start time it was found with, so
+//! a pid that exited and was reused in between is left alone.
+//!
+//! Elsewhere only the agent's process group is signalled. Windows has no
+//! process groups in this sense; there each agent is put in a Job Object
+//! ([`JobObject`]) as it starts, and `PtySession::kill` terminates the job,
+//! taking every process the agent started with it.
+//!
+//! The same walk measures what the tree uses: `tree_usage` adds up the CPU
+//! time and resident memory of the agent and everything it started, so a
+//! runaway build shows up against the agent that spawned it. Sampling many
+//! agents at once scans `/proc` a single time through [`ProcessTable`].
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// Signal the agent's process group and, on Linux, the rest of its tree
+///
+/// `leader` is the agent's pid, which is also its session and process group
+/// id. Processes are signalled even after the agent itself has exited: the
+/// group and session ids stay reserved while any member is alive.
+#[cfg(unix)]
+pub fn signal_tree(leader: u32, signal: i32) {
+    // Walk the tree before signalling: children of a killed process are
+    // reparented to init right away and no longer trace back to the agent.
+    let procs = list_processes();
+    let members: Vec<(u32, u64)> = live_members(&procs, leader).map(|p| (p.pid, p.start_ticks)).collect();
+    unsafe {
+        libc::kill(-(leader as i32), signal);
+    }
+    for (pid, start_ticks) in members {
+        if start_ticks_of(pid) != Some(start_ticks) {
+            continue;
+        }
+        unsafe {
+            libc::kill(pid as i32, signal);
+        }
+    }
+}
+
+/// SIGKILL the agent's whole tree
+///
+/// Killed processes cannot fork, but a process can fork between the scan
+/// and the signal; a few passes catch children born in that window.
+#[cfg(unix)]
+pub fn kill_tree(leader: u32) {
+    const PASSES: usize = 3;
+    for _ in 0..PASSES {
+        signal_tree(leader, libc::SIGKILL);
+        if tree_members(leader).is_empty() {
+            break;
+        }
+    }
+}
+
+/// Live processes in the agent's session or descended from it, leader excluded
+///
+/// Zombies are left out: they are already dead and only wait to be reaped.
+/// Always empty where `/proc` is not available.
+pub fn tree_members(leader: u32) -> Vec<u32> {
+    let procs = list_processes();
+    let mut members: Vec<u32> = live_members(&procs, leader).map(|p| p.pid).collect();
+    members.sort_unstable();
+    members
+}
+
+fn live_members(procs: &[ProcStat], leader: u32) -> impl Iterator<Item = &ProcStat> {
+    let tree = tree_of(procs, leader);
+    procs.iter().filter(move |p| p.pid != leader && !p.zombie && tree.contains(&p.pid))
+}
+
+/// Pids in the session `leader` leads, and their descendants, leader included
+fn tree_of(procs: &[ProcStat], leader: u32) -> HashSet<u32> {
+    let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+    for p in procs {
+        children.entry(p.ppid).or_default().push(p.pid);
+    }
+
+    // The walk starts from the session, not from `leader` alone: once the
+    // agent is reaped its pid may belong to an unrelated process, which is
+    // only a match if it also leads a session of that id.
+    let mut members: HashSet<u32> = procs
+        .iter()
+        .filter(|p| p.session == leader)
+        .map(|p| p.pid)
+        .collect();
+    let mut stack: Vec<u32> = members.iter().copied().collect();
+    while let Some(pid) = stack.pop() {
+        for &child in children.get(&pid).into_iter().flatten() {
+            if members.insert(child) {
+                stack.push(child);
+            }
+        }
+    }
+    members
+}
+
+/// CPU time and memory of an agent's process tree at one moment
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TreeUsage {
+    /// User and system time of the live processes, in seconds
+    pub cpu_seconds: f64,
+    /// Resident memory of the live processes, in bytes
+    pub rss_bytes: u64,
+    /// Live processes, the agent included
+    pub processes: usize,
+}
+
+/// What the agent and everything it started use right now
+///
+/// None when nothing in the tree is alive, or `/proc` is not available.
+/// CPU time of processes that already exited is not counted, so the total
+/// can drop between two calls.
+pub fn tree_usage(leader: u32) -> Option<TreeUsage> {
+    ProcessTable::scan().tree_usage(leader)
+}
+
+/// Every process at one moment, so several trees can be measured from a
+/// single scan of `/proc`
+#[derive(Debug, Clone, Default)]
+pub struct ProcessTable {
+    procs: Vec<ProcStat>,
+}
+
+impl ProcessTable {
+    /// Read the current processes; empty where `/proc` is not available
+    pub fn scan() -> Self {
+        Self { procs: list_processes() }
+    }
+
+    /// What `leader`'s tree used when the table was scanned; see [`tree_usage`]
+    pub fn tree_usage(&self, leader: u32) -> Option<TreeUsage> {
+        let members = tree_of(&self.procs, leader);
+        let mut ticks = 0;
+        let mut pages = 0;
+        let mut processes = 0;
+        for p in self.procs.iter().filter(|p| !p.zombie && members.contains(&p.pid)) {
+            ticks += p.cpu_ticks;
+            pages += p.rss_pages;
+            processes += 1;
+        }
+        if processes == 0 {
+            return None;
+        }
+        let (ticks_per_second, page_size) = system_units();
+        Some(TreeUsage {
+            cpu_seconds: ticks as f64 / ticks_per_second as f64,
+            rss_bytes: pages * page_size,
+            processes,
+        })
+    }
+}
+
+/// One periodic measurement of a session's process tree
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ResourceSample {
+    pub at: DateTime<Utc>,
+    /// Share of one core used since the previous sample; above 100 when
+    /// the tree keeps several cores busy
+    pub cpu_percent: f64,
+    pub rss_bytes: u64,
+    pub processes: usize,
+}
+
+/// `cpu 187% · 3.0G rss · 14 procs`
+impl fmt::Display for ResourceSample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "cpu {:.0}% · {} rss · {} proc{}",
+            self.cpu_percent,
+            format_bytes(self.rss_bytes),
+            self.processes,
+            if self.processes == 1 { "" } else { "s" }
+        )
+    }
+}
+
+/// Memory size in binary units, e.g. `512K`, `1.4G`
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["K", "M", "G", "T"];
+    if bytes < 1024 {
+        return format!("{}B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if value < 10.0 && unit > 0 {
+        format!("{:.1}{}", value, UNITS[unit])
+    } else {
+        format!("{:.0}{}", value, UNITS[unit])
+    }
+}
+
+/// Clock ticks per second and bytes per page, as `/proc` counts them
+#[cfg(target_os = "linux")]
+fn system_units() -> (u64, u64) {
+    let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    (
+        if ticks > 0 { ticks as u64 } else { 100 },
+        if page > 0 { page as u64 } else { 4096 },
+    )
+}
+
+#[cfg(not(target_os = "linux"))]
+fn system_units() -> (u64, u64) {
+    (100, 4096)
+}
+
+/// Fields of `/proc/<pid>/stat` the tree walk and usage totals need
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ProcStat {
+    pid: u32,
+    ppid: u32,
+    session: u32,
+    zombie: bool,
+    /// utime + stime, in clock ticks
+    cpu_ticks: u64,
+    rss_pages: u64,
+    /// When the process started, in clock ticks after boot; tells a pid
+    /// apart from a later process that reused it
+    start_ticks: u64,
+}
+
+impl ProcStat {
+    /// Parse `pid (comm) state ppid pgrp session ... utime stime ... rss ...`
+    ///
+    /// `comm` may itself contain spaces and parentheses, so fields are read
+    /// after the last `)`. Usage fields missing from a short line count as 0.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    fn parse(stat: &str) -> Option<Self> {
+        let (head, rest) = stat.rsplit_once(')')?;
+        let pid = head.split_once('(')?.0.trim().parse().ok()?;
+        let mut fields = rest.split_whitespace();
+        let state = fields.next()?;
+        let ppid = fields.next()?.parse().ok()?;
+        let _pgrp = fields.next()?;
+        let session = fields.next()?.parse().ok()?;
+        let mut number = |skip: usize| -> u64 { fields.nth(skip).and_then(|f| f.parse().ok()).unwrap_or(0) };
+        // Fields 14 and 15, then 22 and 24 (man 5 proc)
+        let utime = number(7);
+        let stime = number(0);
+        let start_ticks = number(6);
+        let rss_pages = number(1);
+        Some(Self {
+            pid,
+            ppid,
+            session,
+            zombie: matches!(state, "Z" | "X"),
+            cpu_ticks: utime + stime,
+            rss_pages,
+            start_ticks,
+        })
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn list_processes() -> Vec<ProcStat> {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter(|e| e.file_name().to_str().is_some_and(|n| n.bytes().all(|b| b.is_ascii_digit())))
+        .filter_map(|e| std::fs::read_to_string(e.path().join("stat")).ok())
+        .filter_map(|stat| ProcStat::parse(&stat))
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn list_processes() -> Vec<ProcStat> {
+    Vec::new()
+}
+
+/// Start time of `pid` right now, None once it is gone
+#[cfg(target_os = "linux")]
+fn start_ticks_of(pid: u32) -> Option<u64> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    ProcStat::parse(&stat).map(|p| p.start_ticks)
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn start_ticks_of(_pid: u32) -> Option<u64> {
+    None
+}
+
+/// A Windows Job Object holding an agent and every process it starts
+///
+/// Windows has no process groups to signal, but children join their
+/// parent's job, so terminating the job ends the agent's whole tree.
+/// Processes the agent started before it was assigned are not in it.
+#[cfg(windows)]
+pub struct JobObject(windows_sys::Win32::Foundation::HANDLE);
+
+// The handle is only passed to thread-safe Win32 calls
+#[cfg(windows)]
+unsafe impl Send for JobObject {}
+#[cfg(windows)]
+unsafe impl Sync for JobObject {}
+
+#[cfg(windows)]
+impl JobObject {
+    /// Put process `pid` in a job of its own
+    pub fn assign(pid: u32) -> std::io::Result<Self> {
+        use windows_sys::Win32::Foundation::CloseHandle;
+        use windows_sys::Win32::System::JobObjects::{AssignProcessToJobObject, CreateJobObjectW};
+        use windows_sys::Win32::System::Threading::{OpenProcess, PROCESS_SET_QUOTA, PROCESS_TERMINATE};
+
+        unsafe {
+            let job = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+            if job.is_null() {
+                return Err(std::io::Error::last_os_error());
+            }
+            let job = Self(job);
+            let process = OpenProcess(PROCESS_SET_QUOTA | PROCESS_TERMINATE, 0, pid);
+            if process.is_null() {
+                return Err(std::io::Error::last_os_error());
+            }
+            let assigned = AssignProcessToJobObject(job.0, process);
+            let error = std::io::Error::last_os_error();
+            CloseHandle(process);
+            if assigned == 0 {
+                return Err(error);
+            }
+            Ok(job)
+        }
+    }
+
+    /// Terminate every process in the job
+    pub fn kill(&self) -> std::io::Result<()> {
+        use windows_sys::Win32::System::JobObjects::TerminateJobObject;
+
+        if unsafe { TerminateJobObject(self.0, 1) } == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+impl Drop for JobObject {
+    fn drop(&mut self) {
+        unsafe {
+            windows_sys::Win32::Foundation::CloseHandle(self.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_stat_with_awkward_command_names() {
+        let stat = "4242 (my (odd) cmd) S 4200 4242 4100 34816 4242 4194560 0 0";
+        assert_eq!(
+            ProcStat::parse(stat),
+            Some(ProcStat {
+                pid: 4242,
+                ppid: 4200,
+                session: 4100,
+                zombie: false,
+                cpu_ticks: 0,
+                rss_pages: 0,
+                start_ticks: 0,
+            })
+        );
+
+        let zombie = ProcStat::parse("17 (sleep) Z 1 17 17 0 -1").unwrap();
+        assert!(zombie.zombie);
+        assert_eq!(ProcStat::parse("garbage"), None);
+
+        let full = "812 (cargo) R 800 812 800 34816 812 4194304 9120 0 3 0 1250 310 0 0 20 0 \
+                    14 0 51234 912384000 20480 18446744073709551615";
+        let cargo = ProcStat::parse(full).unwrap();
+        assert_eq!((cargo.cpu_ticks, cargo.rss_pages, cargo.start_ticks), (1560, 20480, 51234));
+
+        assert_eq!(format_bytes(512), "512B");
+        assert_eq!(format_bytes(80 << 20), "80M");
+        assert_eq!(format_bytes(3 << 29), "1.5G");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn measures_the_whole_tree() {
+        use std::os::unix::process::CommandExt;
+
+        // A session leader with two children, like an agent under a PTY
+        let mut child = unsafe {
+            std::process::Command::new("sh")
+                .args(["-c", "sleep 5 & sleep 5 & wait"])
+                .pre_exec(|| {
+                    libc::setsid();
+                    Ok(())
+                })
+                .spawn()
+                .unwrap()
+        };
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        let usage = tree_usage(child.id()).unwrap();
+        assert!(start_ticks_of(child.id()).is_some());
+        kill_tree(child.id());
+        let _ = child.wait();
+        assert!(tree_members(child.id()).is_empty());
+
+        assert_eq!(usage.processes, 3);
+        assert!(usage.rss_bytes > 0);
+        assert!(tree_usage(u32::MAX).is_none());
+    }
+}
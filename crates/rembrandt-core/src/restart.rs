@@ -0,0 +1,192 @@
+//! Restart policies for crashed agents
+//!
+//! A session spawned with `on-failure(N)` is respawned by the daemon, in the
+//! same worktree, each time its agent exits non-zero, up to N times. Each
+//! attempt waits a little longer than the last, and the new agent is told
+//! that it is picking up after a crash, with the tail of the previous
+//! session's output.
+
+use crate::session::SessionStatus;
+use crate::RembrandtError;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Retries `on-failure` allows when no count is given
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+/// Wait before the first restart; doubles with every further attempt
+const BACKOFF_BASE: Duration = Duration::from_secs(1);
+
+/// Longest wait between restarts
+const BACKOFF_MAX: Duration = Duration::from_secs(60);
+
+/// Whether a session is respawned when its agent exits
+///
+/// Written as `never`, `on-failure` or `on-failure(N)` in config and on the
+/// command line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum RestartPolicy {
+    #[default]
+    Never,
+    /// Respawn after a non-zero exit, at most `max_retries` times
+    OnFailure { max_retries: u32 },
+}
+
+impl RestartPolicy {
+    /// Whether a session that ended with `status`, after `attempts` restarts
+    /// already, gets another one
+    pub fn should_restart(&self, status: &SessionStatus, attempts: u32) -> bool {
+        let failed = match status {
+            SessionStatus::Running | SessionStatus::Exited(0) => false,
+            SessionStatus::Exited(_) | SessionStatus::Failed(_) => true,
+        };
+        match self {
+            RestartPolicy::Never => false,
+            RestartPolicy::OnFailure { max_retries } => failed && attempts < *max_retries,
+        }
+    }
+
+    /// How long to wait before restart number `attempt` (counting from 1)
+    pub fn backoff(attempt: u32) -> Duration {
+        let doublings = attempt.saturating_sub(1).min(16);
+        (BACKOFF_BASE * 2u32.pow(doublings)).min(BACKOFF_MAX)
+    }
+}
+
+impl std::fmt::Display for RestartPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RestartPolicy::Never => write!(f, "never"),
+            RestartPolicy::OnFailure { max_retries } => write!(f, "on-failure({})", max_retries),
+        }
+    }
+}
+
+impl std::str::FromStr for RestartPolicy {
+    type Err = RembrandtError;
+
+    fn from_str(s: &str) -> crate::Result<Self> {
+        let invalid = || {
+            RembrandtError::Config(format!(
+                "invalid restart policy '{}' (expected never, on-failure or on-failure(N))",
+                s
+            ))
+        };
+        match s.trim() {
+            "never" | "no" => Ok(RestartPolicy::Never),
+            "on-failure" => Ok(RestartPolicy::OnFailure {
+                max_retries: DEFAULT_MAX_RETRIES,
+            }),
+            other => {
+                let count = other
+                    .strip_prefix("on-failure(")
+                    .and_then(|rest| rest.strip_suffix(')'))
+                    .or_else(|| other.strip_prefix("on-failure:"))
+                    .ok_or_else(invalid)?;
+                let max_retries = count.trim().parse().map_err(|_| invalid())?;
+                Ok(RestartPolicy::OnFailure { max_retries })
+            }
+        }
+    }
+}
+
+impl TryFrom<String> for RestartPolicy {
+    type Error = RembrandtError;
+
+    fn try_from(value: String) -> crate::Result<Self> {
+        value.parse()
+    }
+}
+
+impl From<RestartPolicy> for String {
+    fn from(policy: RestartPolicy) -> Self {
+        policy.to_string()
+    }
+}
+
+/// How a session ended, e.g. `exited with code 1`
+pub fn exit_reason(status: &SessionStatus) -> String {
+    match status {
+        SessionStatus::Exited(code) => format!("exited with code {}", code),
+        SessionStatus::Failed(reason) => format!("failed ({})", reason),
+        SessionStatus::Running => "is still running".to_string(),
+    }
+}
+
+/// What a restarted agent is told first
+///
+/// `tail` is the end of the crashed session's output, as buffered; `task`
+/// the prompt the agent was originally given, if known.
+pub fn restart_prompt(attempt: u32, status: &SessionStatus, task: Option<&str>, tail: &[String]) -> String {
+    let mut prompt = format!(
+        "Your previous session in this worktree {} and was restarted (attempt {}). \
+         Check the state of the worktree and carry on where it left off.",
+        exit_reason(status),
+        attempt
+    );
+    if let Some(task) = task {
+        prompt.push_str(&format!(" The task was: {}", task));
+    }
+    let tail: Vec<String> = tail
+        .iter()
+        .map(|line| strip_ansi_escapes::strip_str(line).trim_end().to_string())
+        .filter(|line| !line.is_empty())
+        .collect();
+    if !tail.is_empty() {
+        // Agents read one line of input as the whole prompt
+        prompt.push_str(" Its last output was: ");
+        prompt.push_str(&tail.join(" | "));
+    }
+    prompt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_decides_restarts() {
+        assert_eq!("never".parse::<RestartPolicy>().unwrap(), RestartPolicy::Never);
+        let policy: RestartPolicy = "on-failure(2)".parse().unwrap();
+        assert_eq!(policy, RestartPolicy::OnFailure { max_retries: 2 });
+        assert_eq!(policy.to_string(), "on-failure(2)");
+        assert_eq!(
+            "on-failure".parse::<RestartPolicy>().unwrap(),
+            RestartPolicy::OnFailure {
+                max_retries: DEFAULT_MAX_RETRIES
+            }
+        );
+        assert!("always".parse::<RestartPolicy>().is_err());
+        assert!("on-failure(x)".parse::<RestartPolicy>().is_err());
+
+        assert!(policy.should_restart(&SessionStatus::Exited(1), 0));
+        assert!(policy.should_restart(&SessionStatus::Failed("crash".into()), 1));
+        assert!(!policy.should_restart(&SessionStatus::Exited(1), 2));
+        assert!(!policy.should_restart(&SessionStatus::Exited(0), 0));
+        assert!(!RestartPolicy::Never.should_restart(&SessionStatus::Exited(1), 0));
+
+        assert_eq!(RestartPolicy::backoff(1), Duration::from_secs(1));
+        assert_eq!(RestartPolicy::backoff(3), Duration::from_secs(4));
+        assert_eq!(RestartPolicy::backoff(40), BACKOFF_MAX);
+
+        let json = serde_json::to_string(&policy).unwrap();
+        assert_eq!(json, "\"on-failure(2)\"");
+        assert_eq!(serde_json::from_str::<RestartPolicy>(&json).unwrap(), policy);
+    }
+
+    #[test]
+    fn prompt_carries_task_and_log_tail() {
+        let tail = vec![
+            "running tests".to_string(),
+            "".to_string(),
+            "\x1b[31mpanicked at foo\x1b[0m  ".to_string(),
+        ];
+        let prompt = restart_prompt(2, &SessionStatus::Exited(101), Some("fix the parser"), &tail);
+        assert!(prompt.contains("exited with code 101"));
+        assert!(prompt.contains("attempt 2"));
+        assert!(prompt.contains("The task was: fix the parser"));
+        assert!(prompt.ends_with("running tests | panicked at foo"));
+        assert!(!prompt.contains('\n'));
+    }
+}
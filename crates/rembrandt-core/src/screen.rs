@@ -0,0 +1,196 @@
+//! Virtual screens for session previews
+//!
+//! Full-screen agents (Claude Code, OpenCode) redraw in place with cursor
+//! movement, so an ANSI-stripped tail of their output is a jumble of partial
+//! frames. Each session also feeds its output through a VT100 parser; a
+//! `ScreenSnapshot` is what that virtual terminal currently shows, as styled
+//! runs of text the TUI and GUI can draw as a preview.
+
+use serde::{Deserialize, Serialize};
+
+/// A cell color
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value")]
+pub enum ScreenColor {
+    /// The terminal's default foreground or background
+    #[default]
+    Default,
+    /// One of the 256 indexed colors
+    Idx(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl From<vt100::Color> for ScreenColor {
+    fn from(color: vt100::Color) -> Self {
+        match color {
+            vt100::Color::Default => ScreenColor::Default,
+            vt100::Color::Idx(i) => ScreenColor::Idx(i),
+            vt100::Color::Rgb(r, g, b) => ScreenColor::Rgb(r, g, b),
+        }
+    }
+}
+
+/// How a run of cells is drawn
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CellStyle {
+    pub fg: ScreenColor,
+    pub bg: ScreenColor,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub inverse: bool,
+}
+
+impl CellStyle {
+    fn of(cell: &vt100::Cell) -> Self {
+        Self {
+            fg: cell.fgcolor().into(),
+            bg: cell.bgcolor().into(),
+            bold: cell.bold(),
+            italic: cell.italic(),
+            underline: cell.underline(),
+            inverse: cell.inverse(),
+        }
+    }
+}
+
+/// Consecutive cells sharing a style
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScreenSpan {
+    pub text: String,
+    #[serde(flatten)]
+    pub style: CellStyle,
+}
+
+/// One screen row; trailing blank cells are left out
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScreenLine {
+    pub spans: Vec<ScreenSpan>,
+}
+
+impl ScreenLine {
+    pub fn text(&self) -> String {
+        self.spans.iter().map(|s| s.text.as_str()).collect()
+    }
+
+    pub fn is_blank(&self) -> bool {
+        self.spans.is_empty()
+    }
+}
+
+/// What a session's virtual terminal shows right now
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScreenSnapshot {
+    pub rows: u16,
+    pub cols: u16,
+    /// Cursor position as (row, column)
+    pub cursor: (u16, u16),
+    /// One entry per row, top to bottom
+    pub lines: Vec<ScreenLine>,
+}
+
+impl ScreenSnapshot {
+    pub fn from_screen(screen: &vt100::Screen) -> Self {
+        let (rows, cols) = screen.size();
+        let lines = (0..rows)
+            .map(|row| {
+                let mut spans: Vec<ScreenSpan> = Vec::new();
+                // Trailing default-styled blanks are dropped as the row is built
+                let mut blank_tail = 0;
+                for col in 0..cols {
+                    let Some(cell) = screen.cell(row, col) else {
+                        continue;
+                    };
+                    if cell.is_wide_continuation() {
+                        continue;
+                    }
+                    let style = CellStyle::of(cell);
+                    let text = if cell.has_contents() {
+                        cell.contents()
+                    } else {
+                        " ".to_string()
+                    };
+                    let blank = text == " " && style.bg == ScreenColor::Default && !style.inverse;
+                    match spans.last_mut() {
+                        Some(span) if span.style == style => span.text.push_str(&text),
+                        _ => spans.push(ScreenSpan { text, style }),
+                    }
+                    blank_tail = if blank { blank_tail + 1 } else { 0 };
+                }
+                trim_blank_tail(&mut spans, blank_tail);
+                ScreenLine { spans }
+            })
+            .collect();
+
+        Self {
+            rows,
+            cols,
+            cursor: screen.cursor_position(),
+            lines,
+        }
+    }
+
+    /// Rows down to the last one with anything on it
+    pub fn content_lines(&self) -> &[ScreenLine] {
+        let end = self
+            .lines
+            .iter()
+            .rposition(|line| !line.is_blank())
+            .map_or(0, |i| i + 1);
+        &self.lines[..end]
+    }
+
+    /// Plain text of the screen, one line per row
+    pub fn text(&self) -> String {
+        self.content_lines()
+            .iter()
+            .map(ScreenLine::text)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Remove the last `blank` single-width blank cells from the end of a row
+fn trim_blank_tail(spans: &mut Vec<ScreenSpan>, mut blank: usize) {
+    while blank > 0 {
+        let Some(span) = spans.last_mut() else {
+            return;
+        };
+        let keep = span.text.len().saturating_sub(blank);
+        blank -= span.text.len() - keep;
+        span.text.truncate(keep);
+        if span.text.is_empty() {
+            spans.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redraws_in_place_and_keeps_styles() {
+        let mut parser = vt100::Parser::new(4, 20, 0);
+        parser.process(b"Working...\r\n\x1b[1;32mok\x1b[0m done   ");
+        // A full-screen app repaints the first row over the old text
+        parser.process(b"\x1b[1;1H\x1b[2KFinished");
+
+        let screen = ScreenSnapshot::from_screen(parser.screen());
+        assert_eq!((screen.rows, screen.cols), (4, 20));
+        assert_eq!(screen.lines.len(), 4);
+        assert_eq!(screen.text(), "Finished\nok done");
+        assert_eq!(screen.content_lines().len(), 2);
+
+        let spans = &screen.lines[1].spans;
+        assert_eq!(spans[0].text, "ok");
+        assert!(spans[0].style.bold);
+        assert_eq!(spans[0].style.fg, ScreenColor::Idx(2));
+        assert_eq!(spans[1].text, " done");
+        assert_eq!(spans[1].style, CellStyle::default());
+
+        let json = serde_json::to_value(&spans[0]).unwrap();
+        assert_eq!(json["text"], "ok");
+        assert_eq!(json["fg"], serde_json::json!({"type": "Idx", "value": 2}));
+    }
+}
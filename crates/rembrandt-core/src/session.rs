@@ -0,0 +1,788 @@
+//! PTY Session management
+//!
+//! Each PtySession wraps a single agent process running in a pseudo-terminal.
+//! Sessions survive TUI disconnects - the daemon keeps them alive.
+//!
+//! A reader thread per session drains the PTY into the session's ring buffer
+//! as output arrives, so history has no gaps while nobody is watching.
+//! Consumers pick up what's new with `drain_output`, either on a timer or
+//! when the reader calls the session's output waker. The same output drives a
+//! VT100 parser, so `screen` shows what a terminal would (see `crate::screen`).
+//!
+//! `sample_resources` measures the agent's process tree (see
+//! `crate::process::ProcessTable`); the last few samples are kept per session.
+
+use crate::agent::{AgentType, NudgeStrategy};
+use crate::buffer::RingBuffer;
+use crate::process::{ProcessTable, ResourceSample};
+use crate::screen::ScreenSnapshot;
+use crate::{RembrandtError, Result};
+use chrono::{DateTime, Utc};
+use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Pause between the keystrokes of a multi-key nudge
+pub const NUDGE_KEY_DELAY: Duration = Duration::from_millis(50);
+
+/// Output after a nudge that still counts as its echo rather than progress
+const NUDGE_ECHO_ALLOWANCE: usize = 512;
+
+/// How long `poll` waits, once the agent exits, for its last output to be read
+const READER_DRAIN_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// How long `kill` waits for the agent to be reaped
+const KILL_REAP_TIMEOUT: Duration = Duration::from_secs(2);
+const KILL_REAP_POLL: Duration = Duration::from_millis(10);
+
+/// Resource samples kept per session
+const RESOURCE_SAMPLES: usize = 60;
+
+/// Unique session identifier
+pub type SessionId = String;
+
+/// Called from a session's reader thread whenever output arrives and once
+/// the PTY closes, so a consumer can sleep until there's something to drain
+pub type OutputWaker = Arc<dyn Fn() + Send + Sync>;
+
+/// Generate a unique session ID
+///
+/// A process-wide sequence number is appended so sessions spawned within the
+/// same millisecond (e.g. competition fan-out) don't collide.
+pub fn generate_session_id() -> SessionId {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::{SystemTime, UNIX_EPOCH};
+    static SEQ: AtomicU64 = AtomicU64::new(0);
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis();
+    let seq = SEQ.fetch_add(1, Ordering::Relaxed);
+    format!("ses-{:x}-{}", timestamp, seq)
+}
+
+/// Status of a PTY session
+///
+/// Serialized as `{"type": "Exited", "value": 0}` so the GUI frontend and
+/// IPC clients can match on the variant without string parsing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value")]
+pub enum SessionStatus {
+    /// Process is running
+    Running,
+    /// Process exited with code
+    Exited(i32),
+    /// Process failed to start or crashed
+    Failed(String),
+}
+
+/// A single PTY session wrapping an agent process
+///
+/// The session owns:
+/// - The PTY master (for writing input and resizing)
+/// - The child process handle
+/// - An output buffer for late-attach scenarios, fed by a reader thread
+pub struct PtySession {
+    /// Unique session ID
+    pub id: SessionId,
+    /// Agent identity this session belongs to (Bead ID)
+    pub agent_id: String,
+    /// PTY master for I/O
+    master: Box<dyn MasterPty + Send>,
+    /// Writer for PTY input (cloned from master)
+    writer: Box<dyn Write + Send>,
+    /// Child process handle
+    child: Box<dyn Child + Send + Sync>,
+    /// Job holding the agent and what it starts, so `kill` ends them all
+    #[cfg(windows)]
+    job: Option<crate::process::JobObject>,
+    /// Ring buffer for output history (allows late-attach)
+    output_buffer: Arc<Mutex<RingBuffer>>,
+    /// Virtual terminal fed the same output, for previews
+    screen: Arc<Mutex<vt100::Parser>>,
+    /// Current session status
+    pub status: SessionStatus,
+    /// When this session was created
+    pub created_at: DateTime<Utc>,
+    /// Command that was spawned
+    pub command: String,
+    /// Arguments it was spawned with
+    pub args: Vec<String>,
+    /// Environment variables set for the agent on top of the inherited ones
+    pub env: Vec<(String, String)>,
+    /// Working directory
+    pub workdir: String,
+    /// Main repository the workdir belongs to (None outside a git repo)
+    ///
+    /// Sessions from different repositories can share a daemon, so an
+    /// agent is identified by `(repo, agent_id)` rather than `agent_id` alone.
+    pub repo: Option<PathBuf>,
+    /// Escalating nudges for this agent (see `AgentType::default_nudge_ladder`)
+    nudge_ladder: Vec<NudgeStrategy>,
+    /// Ladder step the next nudge uses
+    nudge_level: usize,
+    /// Output stream offset at the last nudge; real output since resets the ladder
+    output_at_last_nudge: usize,
+    /// Keystrokes asking the agent to exit (see `AgentType::default_quit_sequence`)
+    quit_sequence: Vec<NudgeStrategy>,
+    /// Set by the reader thread once the PTY is closed
+    reader_done: Arc<AtomicBool>,
+    /// Called by the reader thread on output (see `set_output_waker`)
+    waker: Arc<Mutex<Option<OutputWaker>>>,
+    /// Set once the session is asked to stop (see `SessionManager`), so
+    /// its exit isn't taken for a crash
+    pub(crate) stop_requested: bool,
+    /// Whether the agent's processes are stopped with SIGSTOP
+    pub paused: bool,
+    /// Stream offset up to which output has been drained
+    drained: usize,
+    /// Recent measurements of the process tree, oldest first
+    resources: VecDeque<ResourceSample>,
+    /// Highest resident memory seen in any sample
+    peak_rss_bytes: u64,
+    /// When the tree's CPU time was last read, and what it was
+    last_cpu: Option<(Instant, f64)>,
+}
+
+impl PtySession {
+    /// Spawn a new agent process in a PTY
+    ///
+    /// # Arguments
+    /// * `agent_id` - The Bead ID of the agent identity
+    /// * `command` - The command to run (e.g., "claude")
+    /// * `args` - Command arguments
+    /// * `env` - Extra environment variables, on top of the inherited ones
+    /// * `workdir` - Working directory for the process
+    /// * `buffer_capacity` - How many bytes of output to buffer for late-attach
+    /// * `rows` - Terminal rows (None for default 24)
+    /// * `cols` - Terminal columns (None for default 80)
+    #[allow(clippy::too_many_arguments)]
+    pub fn spawn(
+        agent_id: String,
+        command: &str,
+        args: &[&str],
+        env: &[(String, String)],
+        workdir: &Path,
+        buffer_capacity: usize,
+        rows: Option<u16>,
+        cols: Option<u16>,
+    ) -> Result<Self> {
+        let pty_system = native_pty_system();
+
+        // Use provided size or defaults
+        let size = PtySize {
+            rows: rows.unwrap_or(24),
+            cols: cols.unwrap_or(80),
+            pixel_width: 0,
+            pixel_height: 0,
+        };
+
+        let pair = pty_system
+            .openpty(size)
+            .map_err(|e| RembrandtError::Pty(e.to_string()))?;
+
+        let mut cmd = CommandBuilder::new(command);
+        cmd.args(args);
+        for (key, value) in env {
+            cmd.env(key, value);
+        }
+        cmd.cwd(workdir);
+
+        // Spawn the process in the PTY
+        let child = pair
+            .slave
+            .spawn_command(cmd)
+            .map_err(|e| RembrandtError::Pty(e.to_string()))?;
+
+        #[cfg(windows)]
+        let job = child.process_id().and_then(|pid| {
+            crate::process::JobObject::assign(pid)
+                .map_err(|e| tracing::warn!(pid, "Agent not put in a job object; kill ends it alone: {}", e))
+                .ok()
+        });
+
+        // Get a writer for sending input to the PTY
+        let writer = pair
+            .master
+            .take_writer()
+            .map_err(|e| RembrandtError::Pty(e.to_string()))?;
+
+        // Create output buffer
+        let output_buffer = Arc::new(Mutex::new(RingBuffer::new(buffer_capacity)));
+
+        let reader = pair
+            .master
+            .try_clone_reader()
+            .map_err(|e| RembrandtError::Pty(e.to_string()))?;
+        let screen = Arc::new(Mutex::new(vt100::Parser::new(size.rows, size.cols, 0)));
+        let reader_done = Arc::new(AtomicBool::new(false));
+        let waker = Arc::new(Mutex::new(None));
+        spawn_reader(
+            reader,
+            output_buffer.clone(),
+            screen.clone(),
+            reader_done.clone(),
+            waker.clone(),
+        )?;
+
+        Ok(Self {
+            id: generate_session_id(),
+            agent_id,
+            master: pair.master,
+            writer,
+            child,
+            #[cfg(windows)]
+            job,
+            output_buffer,
+            screen,
+            status: SessionStatus::Running,
+            created_at: Utc::now(),
+            command: command.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+            env: env.to_vec(),
+            workdir: workdir.display().to_string(),
+            repo: crate::worktree::main_repo_root(workdir),
+            nudge_ladder: AgentType::from_command(command).default_nudge_ladder(),
+            nudge_level: 0,
+            output_at_last_nudge: 0,
+            quit_sequence: AgentType::from_command(command).default_quit_sequence(),
+            reader_done,
+            waker,
+            stop_requested: false,
+            paused: false,
+            drained: 0,
+            resources: VecDeque::new(),
+            peak_rss_bytes: 0,
+            last_cpu: None,
+        })
+    }
+
+    /// Mark captured output as drained, returning how many bytes were new
+    ///
+    /// Output is captured in the background; this is for callers that only
+    /// care whether there was any.
+    pub fn read_available(&mut self) -> usize {
+        self.drain_output().1.len()
+    }
+
+    /// Output captured since the last drain
+    ///
+    /// Same as `drain_output` without the offset.
+    pub fn drain_available(&mut self) -> Vec<u8> {
+        self.drain_output().1
+    }
+
+    /// Output captured since the last drain, and its stream offset
+    ///
+    /// Hands freshly captured output to a single consumer (the daemon's
+    /// event pump, the GUI, attach) so it can be streamed without re-reading
+    /// the whole buffer. If the consumer fell more than a buffer behind, the
+    /// offset skips ahead past what was overwritten.
+    pub fn drain_output(&mut self) -> (usize, Vec<u8>) {
+        let Ok(guard) = self.output_buffer.lock() else {
+            return (self.drained, Vec::new());
+        };
+        let (offset, data) = guard.read_since(self.drained);
+        self.drained = guard.total_written();
+        (offset, data)
+    }
+
+    /// Have the reader thread call `waker` whenever output arrives and when
+    /// the PTY closes
+    ///
+    /// It's called once right away, so output captured before it was set
+    /// isn't missed.
+    pub fn set_output_waker(&self, waker: OutputWaker) {
+        if let Ok(mut guard) = self.waker.lock() {
+            *guard = Some(waker.clone());
+        }
+        waker();
+    }
+
+    /// Write data to the PTY (agent's stdin)
+    ///
+    /// Use this for sending input or nudging stalled agents.
+    pub fn write(&mut self, data: &[u8]) -> Result<()> {
+        self.writer
+            .write_all(data)
+            .map_err(|e| RembrandtError::Pty(e.to_string()))?;
+        self.writer
+            .flush()
+            .map_err(|e| RembrandtError::Pty(e.to_string()))?;
+        Ok(())
+    }
+
+    /// Send a nudge to wake a stalled agent
+    ///
+    /// Uses the next step of the nudge ladder: repeated nudges with no
+    /// output in between escalate, and any output resets the ladder.
+    /// Returns the strategy that was sent.
+    pub fn nudge(&mut self) -> Result<NudgeStrategy> {
+        let strategy = self.next_nudge()?;
+        for (i, keys) in strategy.keystrokes().iter().enumerate() {
+            if i > 0 {
+                std::thread::sleep(NUDGE_KEY_DELAY);
+            }
+            self.write(keys)?;
+        }
+        Ok(strategy)
+    }
+
+    /// Take the next step of the nudge ladder without typing it
+    ///
+    /// For callers that type the keystrokes themselves, [`NUDGE_KEY_DELAY`]
+    /// apart, without holding the session meanwhile.
+    pub fn next_nudge(&mut self) -> Result<NudgeStrategy> {
+        let output = self.output_total_written();
+        if output > self.output_at_last_nudge + NUDGE_ECHO_ALLOWANCE {
+            self.nudge_level = 0;
+        }
+        let Some(strategy) = self
+            .nudge_ladder
+            .get(self.nudge_level)
+            .or_else(|| self.nudge_ladder.last())
+            .cloned()
+        else {
+            return Err(RembrandtError::Agent("no nudge strategies configured".to_string()));
+        };
+        self.nudge_level += 1;
+        self.output_at_last_nudge = output;
+        Ok(strategy)
+    }
+
+    /// Replace the nudge ladder, e.g. with one from config
+    pub fn set_nudge_ladder(&mut self, ladder: Vec<NudgeStrategy>) {
+        self.nudge_ladder = ladder;
+        self.nudge_level = 0;
+    }
+
+    /// Replace the quit sequence, e.g. with one from config
+    pub fn set_quit_sequence(&mut self, sequence: Vec<NudgeStrategy>) {
+        self.quit_sequence = sequence;
+    }
+
+    /// Type the agent's quit sequence, the first and gentlest step of a stop
+    ///
+    /// Poll to see whether the agent has exited; escalate with `terminate`
+    /// and then `kill` if it hasn't.
+    pub fn send_quit(&mut self) -> Result<()> {
+        let sequence = self.quit_sequence.clone();
+        for (i, keys) in sequence.iter().flat_map(NudgeStrategy::keystrokes).enumerate() {
+            if i > 0 {
+                std::thread::sleep(NUDGE_KEY_DELAY);
+            }
+            self.write(&keys)?;
+        }
+        Ok(())
+    }
+
+    /// Resize the PTY
+    pub fn resize(&self, rows: u16, cols: u16) -> Result<()> {
+        self.master
+            .resize(PtySize {
+                rows,
+                cols,
+                pixel_width: 0,
+                pixel_height: 0,
+            })
+            .map_err(|e| RembrandtError::Pty(e.to_string()))?;
+        if let Ok(mut parser) = self.screen.lock() {
+            parser.set_size(rows, cols);
+        }
+        Ok(())
+    }
+
+    /// Current terminal size as (rows, cols)
+    pub fn size(&self) -> (u16, u16) {
+        self.master
+            .get_size()
+            .map_or((24, 80), |size| (size.rows, size.cols))
+    }
+
+    /// Send SIGWINCH to the child process group to force a redraw
+    #[cfg(unix)]
+    pub fn send_sigwinch(&self) {
+        if let Some(pid) = self.child.process_id() {
+            unsafe {
+                // Send to process group (negative pid) to reach all child processes
+                libc::kill(-(pid as i32), libc::SIGWINCH);
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn send_sigwinch(&self) {
+        // No-op on non-Unix
+    }
+
+    /// OS process ID of the agent, while it is known
+    pub fn process_id(&self) -> Option<u32> {
+        self.child.process_id()
+    }
+
+    /// File descriptor of the PTY master; the agent's terminal hangs up
+    /// once every copy of it is closed
+    #[cfg(unix)]
+    pub fn master_fd(&self) -> Option<std::os::unix::io::RawFd> {
+        self.master.as_raw_fd()
+    }
+
+    /// Measure the agent's process tree in `processes` and keep the sample
+    ///
+    /// CPU use is averaged since the previous call, so the first sample of
+    /// a session reads 0%. None once the agent has exited, or where the tree
+    /// can't be measured.
+    pub fn sample_resources(&mut self, processes: &ProcessTable) -> Option<ResourceSample> {
+        if !self.is_running() {
+            return None;
+        }
+        let usage = processes.tree_usage(self.process_id()?)?;
+        let now = Instant::now();
+        let cpu_percent = match self.last_cpu {
+            Some((at, cpu_seconds)) if now > at => {
+                // Exited children take their CPU time with them
+                (usage.cpu_seconds - cpu_seconds).max(0.0) / now.duration_since(at).as_secs_f64() * 100.0
+            }
+            _ => 0.0,
+        };
+        self.last_cpu = Some((now, usage.cpu_seconds));
+
+        let sample = ResourceSample {
+            at: Utc::now(),
+            cpu_percent,
+            rss_bytes: usage.rss_bytes,
+            processes: usage.processes,
+        };
+        if self.resources.len() == RESOURCE_SAMPLES {
+            self.resources.pop_front();
+        }
+        self.resources.push_back(sample);
+        self.peak_rss_bytes = self.peak_rss_bytes.max(usage.rss_bytes);
+        Some(sample)
+    }
+
+    /// Kept resource samples, oldest first
+    pub fn resource_samples(&self) -> Vec<ResourceSample> {
+        self.resources.iter().copied().collect()
+    }
+
+    /// The most recent resource sample, while the agent runs
+    pub fn latest_resources(&self) -> Option<ResourceSample> {
+        self.resources.back().copied().filter(|_| self.is_running())
+    }
+
+    /// Highest resident memory the process tree was sampled at
+    pub fn peak_rss_bytes(&self) -> u64 {
+        self.peak_rss_bytes
+    }
+
+    /// Ask the agent to exit: SIGTERM to its whole process tree
+    ///
+    /// Unlike `kill`, this gives the agent a chance to clean up; poll to see
+    /// whether it has exited.
+    #[cfg(unix)]
+    pub fn terminate(&self) {
+        if let Some(pid) = self.child.process_id() {
+            crate::process::signal_tree(pid, libc::SIGTERM);
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn terminate(&self) {
+        // No graceful signal on non-Unix; callers fall back to `kill`
+    }
+
+    /// Stop the agent's process tree with SIGSTOP, keeping its state
+    ///
+    /// A paused agent uses no CPU and makes no API calls; its PTY keeps
+    /// whatever is typed until `resume`.
+    #[cfg(unix)]
+    pub fn pause(&mut self) -> Result<()> {
+        if self.status != SessionStatus::Running {
+            return Err(RembrandtError::Pty(format!("session {} is not running", self.id)));
+        }
+        if let Some(pid) = self.child.process_id() {
+            crate::process::signal_tree(pid, libc::SIGSTOP);
+        }
+        self.paused = true;
+        Ok(())
+    }
+
+    /// Continue a paused agent's process tree with SIGCONT
+    #[cfg(unix)]
+    pub fn resume(&mut self) -> Result<()> {
+        if let Some(pid) = self.child.process_id() {
+            crate::process::signal_tree(pid, libc::SIGCONT);
+        }
+        self.paused = false;
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    pub fn pause(&mut self) -> Result<()> {
+        Err(RembrandtError::Pty("pausing needs Unix signals".to_string()))
+    }
+
+    #[cfg(not(unix))]
+    pub fn resume(&mut self) -> Result<()> {
+        Err(RembrandtError::Pty("pausing needs Unix signals".to_string()))
+    }
+
+    /// What the agent's terminal currently shows
+    pub fn screen(&self) -> ScreenSnapshot {
+        match self.screen.lock() {
+            Ok(parser) => ScreenSnapshot::from_screen(parser.screen()),
+            Err(_) => ScreenSnapshot::default(),
+        }
+    }
+
+    /// Get the output buffer for reading historical output
+    pub fn output_buffer(&self) -> Arc<Mutex<RingBuffer>> {
+        self.output_buffer.clone()
+    }
+
+    /// Read all buffered output as a string (lossy UTF-8 conversion)
+    /// Strips ANSI escape codes for clean display
+    pub fn read_output(&self) -> String {
+        if let Ok(guard) = self.output_buffer.lock() {
+            let raw = guard.read_all();
+            // Strip ANSI escape sequences for clean text display
+            let stripped = strip_ansi_escapes::strip(&raw);
+            String::from_utf8_lossy(&stripped).to_string()
+        } else {
+            String::new()
+        }
+    }
+
+    /// Buffered output and the stream offset of its first byte, read together
+    pub fn output_snapshot(&self) -> (Vec<u8>, usize) {
+        if let Ok(guard) = self.output_buffer.lock() {
+            let data = guard.read_all();
+            let offset = guard.total_written() - data.len();
+            (data, offset)
+        } else {
+            (Vec::new(), 0)
+        }
+    }
+
+    /// Buffered output from stream offset `offset` on, and where it starts
+    pub fn read_since(&self, offset: usize) -> (usize, Vec<u8>) {
+        if let Ok(guard) = self.output_buffer.lock() {
+            guard.read_since(offset)
+        } else {
+            (offset, Vec::new())
+        }
+    }
+
+    /// The last `n` lines of buffered output (ANSI codes intact)
+    pub fn read_last_lines(&self, n: usize) -> Vec<String> {
+        if let Ok(guard) = self.output_buffer.lock() {
+            guard.read_last_lines(n)
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Read raw buffered output (with ANSI codes intact)
+    pub fn read_output_raw(&self) -> Vec<u8> {
+        if let Ok(guard) = self.output_buffer.lock() {
+            guard.read_all()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Total bytes of output ever captured (a stream offset for consumers)
+    pub fn output_total_written(&self) -> usize {
+        if let Ok(guard) = self.output_buffer.lock() {
+            guard.total_written()
+        } else {
+            0
+        }
+    }
+
+    /// Get the number of bytes in the output buffer
+    pub fn output_len(&self) -> usize {
+        if let Ok(guard) = self.output_buffer.lock() {
+            guard.len()
+        } else {
+            0
+        }
+    }
+
+    /// How many bytes of output history are kept
+    pub fn buffer_capacity(&self) -> usize {
+        if let Ok(guard) = self.output_buffer.lock() {
+            guard.capacity()
+        } else {
+            0
+        }
+    }
+
+    /// Keep `capacity` bytes of output history from now on, preserving as
+    /// much of the current history as fits
+    pub fn resize_buffer(&self, capacity: usize) -> Result<()> {
+        let mut guard = self
+            .output_buffer
+            .lock()
+            .map_err(|_| RembrandtError::Pty("output buffer lock poisoned".to_string()))?;
+        guard.resize(capacity);
+        Ok(())
+    }
+
+    /// Poll the child process status
+    ///
+    /// Updates internal status and returns current state. When the agent
+    /// has just exited, waits briefly for the reader thread to capture its
+    /// last output, so a drain after an exited poll sees all of it.
+    pub fn poll(&mut self) -> SessionStatus {
+        if self.status != SessionStatus::Running {
+            return self.status.clone();
+        }
+
+        match self.child.try_wait() {
+            Ok(Some(status)) => {
+                let code = status.exit_code() as i32;
+                self.status = SessionStatus::Exited(code);
+                self.wait_for_reader();
+            }
+            Ok(None) => {
+                // Still running
+            }
+            Err(e) => {
+                self.status = SessionStatus::Failed(e.to_string());
+            }
+        }
+
+        self.status.clone()
+    }
+
+    /// Kill the agent and everything it started
+    ///
+    /// On Unix the agent's whole process tree gets SIGKILL, and on Windows
+    /// its Job Object is terminated (see [`crate::process`]), so test
+    /// runners and dev servers it launched go with it, even after the agent
+    /// itself has exited. The agent is then
+    /// reaped before the session reports it stopped; a session whose agent
+    /// outlives the kill stays running and the error says so.
+    pub fn kill(&mut self) -> Result<()> {
+        #[cfg(unix)]
+        if let Some(pid) = self.child.process_id() {
+            crate::process::kill_tree(pid);
+        }
+        #[cfg(windows)]
+        match &self.job {
+            Some(job) => job.kill().map_err(|e| RembrandtError::Pty(e.to_string()))?,
+            None if self.status == SessionStatus::Running => self
+                .child
+                .kill()
+                .map_err(|e| RembrandtError::Pty(e.to_string()))?,
+            None => {}
+        }
+
+        let deadline = Instant::now() + KILL_REAP_TIMEOUT;
+        loop {
+            match self.child.try_wait() {
+                Ok(Some(_)) => break,
+                Ok(None) if Instant::now() < deadline => std::thread::sleep(KILL_REAP_POLL),
+                Ok(None) => {
+                    return Err(RembrandtError::Pty(format!(
+                        "agent process {} still running after kill",
+                        self.child.process_id().unwrap_or_default()
+                    )));
+                }
+                Err(e) => return Err(RembrandtError::Pty(e.to_string())),
+            }
+        }
+        if self.status == SessionStatus::Running {
+            self.status = SessionStatus::Exited(-1);
+        }
+        Ok(())
+    }
+
+    /// Check if the session is still running
+    pub fn is_running(&self) -> bool {
+        self.status == SessionStatus::Running
+    }
+
+    /// Whether the agent ended with a failure it wasn't asked to stop with
+    pub fn crashed(&self) -> bool {
+        let failed = match self.status {
+            SessionStatus::Running | SessionStatus::Exited(0) => false,
+            SessionStatus::Exited(_) | SessionStatus::Failed(_) => true,
+        };
+        failed && !self.stop_requested
+    }
+
+    /// Wait (bounded) for the reader thread to hit the end of the PTY
+    ///
+    /// Processes the agent left behind can hold the PTY open indefinitely.
+    fn wait_for_reader(&self) {
+        let deadline = Instant::now() + READER_DRAIN_TIMEOUT;
+        while !self.reader_done.load(Ordering::Acquire) && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+}
+
+/// Copy PTY output into `buffer` and `screen` until the PTY closes
+fn spawn_reader(
+    mut reader: Box<dyn Read + Send>,
+    buffer: Arc<Mutex<RingBuffer>>,
+    screen: Arc<Mutex<vt100::Parser>>,
+    done: Arc<AtomicBool>,
+    waker: Arc<Mutex<Option<OutputWaker>>>,
+) -> Result<()> {
+    let wake = move || {
+        let waker = waker.lock().ok().and_then(|guard| guard.clone());
+        if let Some(waker) = waker {
+            waker();
+        }
+    };
+    std::thread::Builder::new()
+        .name("pty-reader".to_string())
+        .spawn(move || {
+            let mut buf = [0u8; 8192];
+            loop {
+                match reader.read(&mut buf) {
+                    Ok(0) => break,
+                    Ok(n) => {
+                        match buffer.lock() {
+                            Ok(mut guard) => guard.write(&buf[..n]),
+                            Err(_) => break,
+                        }
+                        if let Ok(mut parser) = screen.lock() {
+                            parser.process(&buf[..n]);
+                        }
+                        wake();
+                    }
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                    // EIO once the agent's side of the PTY is closed
+                    Err(_) => break,
+                }
+            }
+            done.store(true, Ordering::Release);
+            wake();
+        })
+        .map_err(|e| RembrandtError::Pty(format!("Could not start PTY reader: {}", e)))?;
+    Ok(())
+}
+
+impl std::fmt::Debug for PtySession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PtySession")
+            .field("id", &self.id)
+            .field("agent_id", &self.agent_id)
+            .field("status", &self.status)
+            .field("command", &self.command)
+            .field("workdir", &self.workdir)
+            .field("created_at", &self.created_at)
+            .finish()
+    }
+}
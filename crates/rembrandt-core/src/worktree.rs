@@ -0,0 +1,673 @@
+//! Git worktree management
+//!
+//! Creates and manages isolated worktrees for each agent session.
+//!
+//! A fresh worktree only has tracked files, so gitignored ones an agent needs
+//! to run the project (`.env`, local certificates) can be propagated into it
+//! from the main checkout with `CopyRules`.
+//!
+//! Worktrees go in `.rembrandt/agents/<id>` unless `with_worktrees_dir`
+//! moves them out of the repository, where tools scanning the whole tree
+//! (rust-analyzer, file watchers) won't trip over them. Removal goes by the
+//! path git recorded, so it stays correct if the directory setting changes.
+//!
+//! With `with_locking`, new worktrees are locked (`git worktree lock`) and
+//! get a guard file saying which agent owns them, so they aren't mistaken for
+//! a person's own checkout or pruned by hand. `remove_worktree` lifts locks
+//! Rembrandt placed; a lock with any other reason is left for its owner.
+
+use crate::diff::FileDiff;
+use crate::{RembrandtError, Result};
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+/// Directories never searched by copy patterns' wildcards
+const COPY_SKIP_DIRS: &[&str] = &[".git", ".rembrandt"];
+
+/// Guard file written at the root of locked worktrees
+pub const GUARD_FILE: &str = "REMBRANDT-AGENT-WORKTREE.md";
+
+/// Start of the lock reason on worktrees Rembrandt locked
+const LOCK_REASON_PREFIX: &str = "rembrandt:";
+
+/// How files matched by copy patterns are put into a new worktree
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CopyMode {
+    /// Independent copies the agent can change freely
+    #[default]
+    Copy,
+    /// Symlinks to the main checkout's files, so edits there show up
+    Symlink,
+}
+
+/// Untracked files to propagate from the main checkout into new worktrees
+///
+/// Patterns are paths relative to the repository root; a segment may use
+/// `*` and `?`, and `**` matches any number of directories (e.g. `.env`,
+/// `certs/*.pem`, `**/.env.local`). A matched directory is propagated whole.
+/// Paths the worktree already has (tracked files) are left alone.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CopyRules {
+    pub patterns: Vec<String>,
+    pub mode: CopyMode,
+}
+
+/// Manages git worktrees for agent isolation
+pub struct WorktreeManager {
+    /// Path to the main repository
+    repo_path: PathBuf,
+    /// Path to the .rembrandt directory
+    rembrandt_dir: PathBuf,
+    /// Directory new worktrees are created in, one per agent
+    worktrees_dir: PathBuf,
+    /// Untracked files propagated into new worktrees
+    copy_rules: CopyRules,
+    /// Lock new worktrees and write a guard file into them
+    lock: bool,
+}
+
+impl WorktreeManager {
+    /// Initialize worktree manager for a repository
+    pub fn new(repo_path: impl AsRef<Path>) -> Result<Self> {
+        let repo_path = repo_path.as_ref().to_path_buf();
+        let rembrandt_dir = repo_path.join(".rembrandt");
+
+        // Ensure .rembrandt/agents directory exists
+        std::fs::create_dir_all(rembrandt_dir.join("agents"))?;
+
+        Ok(Self {
+            repo_path,
+            worktrees_dir: rembrandt_dir.join("agents"),
+            rembrandt_dir,
+            copy_rules: CopyRules::default(),
+            lock: false,
+        })
+    }
+
+    /// Propagate files matching `rules` into every worktree created
+    pub fn with_copy_rules(mut self, rules: CopyRules) -> Self {
+        self.copy_rules = rules;
+        self
+    }
+
+    /// Create worktrees in `dir` instead of `.rembrandt/agents`
+    pub fn with_worktrees_dir(mut self, dir: impl AsRef<Path>) -> Self {
+        self.worktrees_dir = dir.as_ref().to_path_buf();
+        self
+    }
+
+    /// Lock every worktree created and mark it with a guard file
+    pub fn with_locking(mut self, lock: bool) -> Self {
+        self.lock = lock;
+        self
+    }
+
+    /// Create a new worktree for an agent
+    #[tracing::instrument(skip(self))]
+    pub fn create_worktree(&self, agent_id: &str, base_branch: &str) -> Result<WorktreeInfo> {
+        let repo = Repository::open(&self.repo_path)?;
+
+        let worktree_path = self.worktree_path(agent_id);
+        let branch_name = format!("rembrandt/{}", agent_id);
+        std::fs::create_dir_all(&self.worktrees_dir)?;
+
+        // Create branch from base
+        let base_ref = repo.find_branch(base_branch, git2::BranchType::Local)?;
+        let base_commit = base_ref.get().peel_to_commit()?;
+
+        // Create the new branch
+        let new_branch = repo.branch(&branch_name, &base_commit, false)?;
+        let branch_ref = new_branch.into_reference();
+
+        // Create the worktree with the new branch
+        let worktree = repo.worktree(
+            agent_id,
+            &worktree_path,
+            Some(git2::WorktreeAddOptions::new().reference(Some(&branch_ref))),
+        )?;
+        if self.lock {
+            worktree.lock(Some(&format!("{} owned by agent {}", LOCK_REASON_PREFIX, agent_id)))?;
+            write_guard_file(&repo, &worktree_path, agent_id)?;
+        }
+        let propagated = self.propagate_files(&worktree_path)?;
+
+        Ok(WorktreeInfo {
+            path: worktree_path,
+            branch: branch_name,
+            agent_id: agent_id.to_string(),
+            propagated,
+        })
+    }
+
+    /// Copy or link the files matching the copy rules into `worktree_path`
+    ///
+    /// Returns the propagated paths, relative to the repository root.
+    pub fn propagate_files(&self, worktree_path: &Path) -> Result<Vec<PathBuf>> {
+        let mut propagated: Vec<PathBuf> = Vec::new();
+        for pattern in &self.copy_rules.patterns {
+            for relative in expand_pattern(&self.repo_path, pattern)? {
+                let target = worktree_path.join(&relative);
+                // Tracked, or inside something already propagated
+                if target.symlink_metadata().is_ok() {
+                    continue;
+                }
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                let source = self.repo_path.join(&relative);
+                match self.copy_rules.mode {
+                    CopyMode::Copy => copy_recursive(&source, &target)?,
+                    CopyMode::Symlink => link(&source.canonicalize()?, &target)?,
+                }
+                tracing::debug!(path = %relative.display(), mode = ?self.copy_rules.mode, "propagated into worktree");
+                propagated.push(relative);
+            }
+        }
+        Ok(propagated)
+    }
+
+    /// Remove a worktree
+    pub fn remove_worktree(&self, agent_id: &str) -> Result<()> {
+        let repo = Repository::open(&self.repo_path)?;
+
+        // Prune the worktree
+        let mut worktree_path = self.worktree_path(agent_id);
+        if let Ok(worktree) = repo.find_worktree(agent_id) {
+            worktree_path = worktree.path().to_path_buf();
+            if locked_by_rembrandt(&worktree) {
+                worktree.unlock()?;
+            }
+            worktree.prune(Some(
+                git2::WorktreePruneOptions::new()
+                    .working_tree(true)
+                    .valid(true),
+            ))?;
+        }
+
+        // Remove the directory
+        if worktree_path.exists() {
+            std::fs::remove_dir_all(worktree_path)?;
+        }
+
+        Ok(())
+    }
+
+    /// List all active worktrees
+    pub fn list_worktrees(&self) -> Result<Vec<WorktreeInfo>> {
+        let repo = Repository::open(&self.repo_path)?;
+        let mut worktrees = Vec::new();
+
+        for name in repo.worktrees()?.iter().flatten() {
+            if let Ok(worktree) = repo.find_worktree(name)
+                && let Some(path) = worktree.path().to_str()
+            {
+                worktrees.push(WorktreeInfo {
+                    path: PathBuf::from(path),
+                    branch: format!("rembrandt/{}", name),
+                    agent_id: name.to_string(),
+                    propagated: Vec::new(),
+                });
+            }
+        }
+
+        Ok(worktrees)
+    }
+
+    /// Merge `branch` into the local branch `into`, returning the new tip
+    ///
+    /// Fast-forwards when possible, otherwise writes a merge commit. Fails
+    /// without touching any refs if the merge has conflicts. If `into` is
+    /// checked out in the main repository, its working tree is updated too.
+    #[tracing::instrument(skip(self))]
+    pub fn merge_branch(&self, branch: &str, into: &str) -> Result<git2::Oid> {
+        let repo = Repository::open(&self.repo_path)?;
+
+        let source = repo
+            .find_branch(branch, git2::BranchType::Local)?
+            .get()
+            .peel_to_commit()?;
+        let mut target_ref = repo
+            .find_branch(into, git2::BranchType::Local)?
+            .into_reference();
+        let target = target_ref.peel_to_commit()?;
+
+        let annotated = repo.find_annotated_commit(source.id())?;
+        let (analysis, _) = repo.merge_analysis_for_ref(&target_ref, &[&annotated])?;
+
+        let new_tip = if analysis.is_up_to_date() {
+            return Ok(target.id());
+        } else if analysis.is_fast_forward() {
+            source.id()
+        } else {
+            let mut index = repo.merge_commits(&target, &source, None)?;
+            if index.has_conflicts() {
+                return Err(crate::RembrandtError::Worktree(format!(
+                    "Merging {} into {} has conflicts",
+                    branch, into
+                )));
+            }
+            let tree = repo.find_tree(index.write_tree_to(&repo)?)?;
+            let signature = repo.signature()?;
+            repo.commit(
+                None,
+                &signature,
+                &signature,
+                &format!("Merge branch '{}' into {}", branch, into),
+                &tree,
+                &[&target, &source],
+            )?
+        };
+
+        target_ref.set_target(new_tip, &format!("rembrandt: merge {}", branch))?;
+
+        // Keep the main checkout in sync if it has `into` checked out
+        let head_is_target = repo
+            .head()
+            .ok()
+            .and_then(|head| head.shorthand().map(|name| name == into))
+            .unwrap_or(false);
+        if head_is_target {
+            repo.checkout_head(Some(git2::build::CheckoutBuilder::new().safe()))?;
+        }
+
+        Ok(new_tip)
+    }
+
+    /// Per-file diff of `branch` against where it forked from `base`
+    pub fn branch_diff(&self, branch: &str, base: &str) -> Result<Vec<FileDiff>> {
+        let repo = Repository::open(&self.repo_path)?;
+        crate::diff::diff_branch(&repo, branch, base)
+    }
+
+    /// Delete a local branch
+    ///
+    /// Remove any worktree that has it checked out first.
+    pub fn delete_branch(&self, branch: &str) -> Result<()> {
+        let repo = Repository::open(&self.repo_path)?;
+        repo.find_branch(branch, git2::BranchType::Local)?.delete()?;
+        Ok(())
+    }
+
+    /// Get the rembrandt directory path
+    pub fn rembrandt_dir(&self) -> &Path {
+        &self.rembrandt_dir
+    }
+
+    /// Directory new worktrees are created in
+    pub fn worktrees_dir(&self) -> &Path {
+        &self.worktrees_dir
+    }
+
+    /// Where `agent_id`'s worktree is created
+    pub fn worktree_path(&self, agent_id: &str) -> PathBuf {
+        self.worktrees_dir.join(agent_id)
+    }
+
+    /// Get the repository path
+    pub fn repo_path(&self) -> &Path {
+        &self.repo_path
+    }
+}
+
+/// Whether `worktree` carries a lock Rembrandt placed
+pub fn locked_by_rembrandt(worktree: &git2::Worktree) -> bool {
+    matches!(
+        worktree.is_locked(),
+        Ok(git2::WorktreeLockStatus::Locked(Some(reason))) if reason.starts_with(LOCK_REASON_PREFIX)
+    )
+}
+
+/// Explain who owns the worktree at its root, and keep the file out of commits
+fn write_guard_file(repo: &Repository, worktree_path: &Path, agent_id: &str) -> Result<()> {
+    std::fs::write(
+        worktree_path.join(GUARD_FILE),
+        format!(
+            "# Agent worktree\n\n\
+             This checkout belongs to Rembrandt agent `{agent_id}` (branch `rembrandt/{agent_id}`).\n\
+             Edits made here land in the agent's work; use the main checkout for your own\n\
+             changes. `rembrandt cleanup` unlocks and removes it.\n"
+        ),
+    )?;
+    // `repo` is the main repository, whose info/exclude worktrees share
+    let exclude = repo.path().join("info").join("exclude");
+    let pattern = format!("/{}", GUARD_FILE);
+    let current = std::fs::read_to_string(&exclude).unwrap_or_default();
+    if current.lines().any(|line| line.trim() == pattern) {
+        return Ok(());
+    }
+    if let Some(dir) = exclude.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let separator = if current.is_empty() || current.ends_with('\n') { "" } else { "\n" };
+    std::fs::write(&exclude, format!("{}{}{}\n", current, separator, pattern))?;
+    Ok(())
+}
+
+/// Root of the main repository containing `path`
+///
+/// For a linked worktree (e.g. an agent's `.rembrandt/agents/<id>`) this is
+/// the repository it was created from, not the worktree itself. The result is
+/// canonicalized so it can be compared across callers. Returns `None` outside
+/// a git repository or for bare repositories.
+pub fn main_repo_root(path: &Path) -> Option<PathBuf> {
+    let repo = Repository::discover(path).ok()?;
+    let root = if repo.is_worktree() {
+        // A linked worktree's git dir is `<main>/.git/worktrees/<name>`
+        let common_dir = repo.path().parent()?.parent()?;
+        Repository::open(common_dir).ok()?.workdir()?.to_path_buf()
+    } else {
+        repo.workdir()?.to_path_buf()
+    };
+    Some(root.canonicalize().unwrap_or(root))
+}
+
+/// Information about a worktree
+#[derive(Debug, Clone)]
+pub struct WorktreeInfo {
+    pub path: PathBuf,
+    pub branch: String,
+    pub agent_id: String,
+    /// Untracked files propagated into it when it was created, relative to
+    /// the repository root (empty for listed worktrees)
+    pub propagated: Vec<PathBuf>,
+}
+
+/// Existing paths under `root` matching a copy pattern, relative to `root`
+fn expand_pattern(root: &Path, pattern: &str) -> Result<Vec<PathBuf>> {
+    let escapes = Path::new(pattern)
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_) | Component::CurDir));
+    if escapes {
+        return Err(RembrandtError::Worktree(format!(
+            "copy pattern `{}` must be a path inside the repository",
+            pattern
+        )));
+    }
+
+    let segments: Vec<&str> = pattern
+        .split('/')
+        .filter(|s| !s.is_empty() && *s != ".")
+        .collect();
+    let mut matches = Vec::new();
+    expand_segments(root, PathBuf::new(), &segments, &mut matches);
+    matches.sort();
+    matches.dedup();
+    Ok(matches)
+}
+
+fn expand_segments(root: &Path, relative: PathBuf, segments: &[&str], matches: &mut Vec<PathBuf>) {
+    let Some((segment, rest)) = segments.split_first() else {
+        if !relative.as_os_str().is_empty() {
+            matches.push(relative);
+        }
+        return;
+    };
+
+    if !segment.contains(['*', '?']) {
+        let next = relative.join(segment);
+        if root.join(&next).symlink_metadata().is_ok() {
+            expand_segments(root, next, rest, matches);
+        }
+        return;
+    }
+
+    let Ok(entries) = std::fs::read_dir(root.join(&relative)) else {
+        return;
+    };
+    let recursive = *segment == "**";
+    if recursive {
+        expand_segments(root, relative.clone(), rest, matches);
+    }
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+        let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+        if is_dir && COPY_SKIP_DIRS.contains(&name) {
+            continue;
+        }
+        if recursive {
+            if is_dir {
+                expand_segments(root, relative.join(name), segments, matches);
+            }
+        } else if wildcard_match(segment, name) && (rest.is_empty() || is_dir) {
+            expand_segments(root, relative.join(name), rest, matches);
+        }
+    }
+}
+
+/// Whether `name` matches `pattern`, where `*` is any run of characters and
+/// `?` any one character
+pub(crate) fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` and the name position it was tried at
+    let mut backtrack = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((after_star, tried)) => {
+                    p = after_star;
+                    n = tried + 1;
+                    backtrack = Some((after_star, tried + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+fn copy_recursive(source: &Path, target: &Path) -> Result<()> {
+    if source.is_dir() {
+        std::fs::create_dir_all(target)?;
+        for entry in std::fs::read_dir(source)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &target.join(entry.file_name()))?;
+        }
+    } else {
+        std::fs::copy(source, target)?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn link(source: &Path, target: &Path) -> Result<()> {
+    std::os::unix::fs::symlink(source, target)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn link(source: &Path, target: &Path) -> Result<()> {
+    copy_recursive(source, target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::Signature;
+
+    fn commit_file(repo: &Repository, branch: &str, name: &str, contents: &str) -> git2::Oid {
+        let sig = Signature::now("test", "test@example.com").unwrap();
+        let blob = repo.blob(contents.as_bytes()).unwrap();
+        let parent = repo
+            .find_branch(branch, git2::BranchType::Local)
+            .ok()
+            .map(|b| b.get().peel_to_commit().unwrap());
+        let mut builder = repo
+            .treebuilder(parent.as_ref().map(|p| p.tree().unwrap()).as_ref())
+            .unwrap();
+        builder.insert(name, blob, 0o100644).unwrap();
+        let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+        let parents: Vec<_> = parent.iter().collect();
+        repo.commit(
+            Some(&format!("refs/heads/{}", branch)),
+            &sig,
+            &sig,
+            name,
+            &tree,
+            &parents,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn merge_and_diff_branches() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init_bare(dir.path()).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "test").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+
+        commit_file(&repo, "main", "base.txt", "base");
+        let base = repo.find_branch("main", git2::BranchType::Local).unwrap();
+        repo.branch("feature", &base.get().peel_to_commit().unwrap(), false)
+            .unwrap();
+        let feature_tip = commit_file(&repo, "feature", "a.txt", "a");
+
+        let manager = WorktreeManager::new(dir.path()).unwrap();
+
+        // main hasn't moved: fast-forward to the feature tip
+        assert_eq!(manager.merge_branch("feature", "main").unwrap(), feature_tip);
+
+        // Both sides diverge: a merge commit with two parents
+        commit_file(&repo, "main", "b.txt", "b");
+        commit_file(&repo, "feature", "c.txt", "c");
+        let merged = repo
+            .find_commit(manager.merge_branch("feature", "main").unwrap())
+            .unwrap();
+        assert_eq!(merged.parent_count(), 2);
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            assert!(merged.tree().unwrap().get_name(name).is_some());
+        }
+
+        // Diff is taken from the fork point, so main's own commits don't show
+        let diff = manager.branch_diff("feature", "main").unwrap();
+        assert!(diff.is_empty());
+        commit_file(&repo, "main", "e.txt", "e");
+        commit_file(&repo, "feature", "c.txt", "c\nmore\n");
+        let diff = manager.branch_diff("feature", "main").unwrap();
+        assert_eq!(diff.len(), 1);
+        assert_eq!(diff[0].path, "c.txt");
+        assert_eq!(diff[0].change, crate::diff::FileChange::Modified);
+        assert_eq!((diff[0].additions, diff[0].deletions), (2, 1));
+        assert_eq!(diff[0].hunks.len(), 1);
+
+        // Conflicting edits are rejected without moving main
+        commit_file(&repo, "main", "a.txt", "main side");
+        let main_tip = commit_file(&repo, "main", "d.txt", "d");
+        commit_file(&repo, "feature", "a.txt", "feature side");
+        assert!(manager.merge_branch("feature", "main").is_err());
+        let main = repo.find_branch("main", git2::BranchType::Local).unwrap();
+        assert_eq!(main.get().target(), Some(main_tip));
+    }
+
+    #[test]
+    fn propagates_untracked_files_into_new_worktrees() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit_file(&repo, "main", "config.toml", "tracked");
+        std::fs::write(dir.path().join(".env"), "SECRET=1").unwrap();
+        std::fs::write(dir.path().join("config.toml"), "local edit").unwrap();
+        std::fs::create_dir_all(dir.path().join("certs")).unwrap();
+        std::fs::write(dir.path().join("certs/dev.pem"), "cert").unwrap();
+        std::fs::write(dir.path().join("certs/notes.txt"), "notes").unwrap();
+        std::fs::create_dir_all(dir.path().join("app/config")).unwrap();
+        std::fs::write(dir.path().join("app/config/local.toml"), "local").unwrap();
+
+        let patterns = [".env", "config.toml", "certs/*.pem", "**/local.toml", "missing"];
+        let rules = |mode| CopyRules {
+            patterns: patterns.iter().map(|p| p.to_string()).collect(),
+            mode,
+        };
+        let manager = WorktreeManager::new(dir.path())
+            .unwrap()
+            .with_copy_rules(rules(CopyMode::Copy));
+        let copied = manager.create_worktree("copied", "main").unwrap();
+        let expected: Vec<PathBuf> = [".env", "certs/dev.pem", "app/config/local.toml"]
+            .iter()
+            .map(PathBuf::from)
+            .collect();
+        assert_eq!(copied.propagated, expected);
+        assert_eq!(std::fs::read_to_string(copied.path.join(".env")).unwrap(), "SECRET=1");
+        assert!(!copied.path.join("certs/notes.txt").exists());
+        // Tracked files keep the committed version
+        assert_eq!(std::fs::read_to_string(copied.path.join("config.toml")).unwrap(), "tracked");
+
+        let manager = manager.with_copy_rules(rules(CopyMode::Symlink));
+        let linked = manager.create_worktree("linked", "main").unwrap();
+        assert_eq!(linked.propagated, expected);
+        assert!(linked.path.join(".env").symlink_metadata().unwrap().is_symlink());
+        std::fs::write(dir.path().join(".env"), "SECRET=2").unwrap();
+        assert_eq!(std::fs::read_to_string(linked.path.join(".env")).unwrap(), "SECRET=2");
+
+        let escaping = manager.with_copy_rules(CopyRules {
+            patterns: vec!["../outside".to_string()],
+            mode: CopyMode::Copy,
+        });
+        assert!(escaping.create_worktree("escaping", "main").is_err());
+        assert!(wildcard_match("*.env.*", "app.env.local"));
+        assert!(!wildcard_match("cert?.pem", "certs.key"));
+    }
+
+    #[test]
+    fn locked_worktrees_get_a_guard_and_unlock_on_removal() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit_file(&repo, "main", "README", "hello");
+        let manager = WorktreeManager::new(dir.path()).unwrap().with_locking(true);
+
+        let info = manager.create_worktree("a1", "main").unwrap();
+        let worktree = repo.find_worktree("a1").unwrap();
+        assert!(locked_by_rembrandt(&worktree));
+        let guard = std::fs::read_to_string(info.path.join(GUARD_FILE)).unwrap();
+        assert!(guard.contains("agent `a1`"), "{}", guard);
+        // The guard never shows up as a change to commit
+        let checkout = Repository::open(&info.path).unwrap();
+        assert!(checkout.status_should_ignore(Path::new(GUARD_FILE)).unwrap());
+
+        // A lock someone else placed is theirs to lift
+        manager.create_worktree("a2", "main").unwrap();
+        let other = repo.find_worktree("a2").unwrap();
+        other.unlock().unwrap();
+        other.lock(Some("on a USB disk")).unwrap();
+        assert!(!locked_by_rembrandt(&other));
+        assert!(manager.remove_worktree("a2").is_err());
+
+        manager.remove_worktree("a1").unwrap();
+        assert!(!info.path.exists());
+        assert!(repo.find_worktree("a1").is_err());
+    }
+
+    #[test]
+    fn worktrees_can_live_outside_the_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        let external = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        commit_file(&repo, "main", "README", "hello");
+        let outside = external.path().join("worktrees");
+        let manager = WorktreeManager::new(dir.path()).unwrap().with_worktrees_dir(&outside);
+
+        let info = manager.create_worktree("a1", "main").unwrap();
+        assert_eq!(info.path, outside.join("a1"));
+        assert!(info.path.join("README").exists());
+        assert_eq!(main_repo_root(&info.path), dir.path().canonicalize().ok());
+
+        // A manager with the default layout still removes it where it is
+        WorktreeManager::new(dir.path()).unwrap().remove_worktree("a1").unwrap();
+        assert!(!info.path.exists());
+        assert!(repo.find_worktree("a1").is_err());
+    }
+}
@@ -0,0 +1,234 @@
+//! Where the GUI's agent sessions live
+//!
+//! If a Rembrandt daemon is listening on the standard socket, sessions are
+//! spawned and driven through it, so agents keep running after the window
+//! closes. Otherwise the GUI owns them in-process, as before.
+
+use crate::ipc::{default_socket_path, DaemonConnection, DaemonEvent, Framing};
+use crate::manager::{SessionInfo, SessionManager};
+use crate::screen::ScreenSnapshot;
+use crate::session::{SessionId, SessionStatus};
+use rembrandt_core::{RembrandtError, RestartPolicy, Result};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// How often an in-process session's reader drains its PTY
+const OUTPUT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Something that happened to a followed session
+#[derive(Debug, Clone)]
+pub enum SessionEvent {
+    /// New output; `offset` is the stream offset of the first byte
+    Output { offset: usize, data: Vec<u8> },
+    /// The process is gone; no further events follow
+    Exited(SessionStatus),
+}
+
+/// Session storage: the shared daemon, or this process
+pub enum SessionBackend {
+    Local(Arc<Mutex<SessionManager>>),
+    Daemon {
+        socket_path: PathBuf,
+        /// Request/response connection; attachments get their own
+        conn: Mutex<DaemonConnection>,
+    },
+}
+
+impl SessionBackend {
+    /// Use the daemon if one answers on the standard socket
+    pub fn detect() -> Self {
+        Self::connect(&default_socket_path())
+            .unwrap_or_else(|_| Self::Local(Arc::new(Mutex::new(SessionManager::new()))))
+    }
+
+    /// Connect to the daemon at `socket_path`
+    pub fn connect(socket_path: &Path) -> Result<Self> {
+        let mut conn = DaemonConnection::connect(socket_path)?;
+        conn.ping()?;
+        Ok(Self::Daemon {
+            socket_path: socket_path.to_path_buf(),
+            conn: Mutex::new(conn),
+        })
+    }
+
+    pub fn is_daemon(&self) -> bool {
+        matches!(self, Self::Daemon { .. })
+    }
+
+    pub fn spawn(
+        &self,
+        agent_id: String,
+        command: &str,
+        args: &[&str],
+        workdir: &Path,
+        rows: Option<u16>,
+        cols: Option<u16>,
+    ) -> Result<SessionId> {
+        match self {
+            Self::Local(sessions) => {
+                lock(sessions)?.spawn_with_size(agent_id, command, args, workdir, rows, cols)
+            }
+            Self::Daemon { conn, .. } => lock(conn)?.spawn(
+                agent_id,
+                command.to_string(),
+                args.iter().map(|a| a.to_string()).collect(),
+                Vec::new(),
+                workdir.to_path_buf(),
+                rows,
+                cols,
+                None,
+                RestartPolicy::Never,
+            ),
+        }
+    }
+
+    pub fn list(&self) -> Result<Vec<SessionInfo>> {
+        match self {
+            Self::Local(sessions) => Ok(lock(sessions)?.list()),
+            Self::Daemon { conn, .. } => lock(conn)?.list(None),
+        }
+    }
+
+    pub fn write(&self, session_id: &str, data: &[u8]) -> Result<()> {
+        match self {
+            Self::Local(sessions) => lock(sessions)?.write(session_id, data),
+            Self::Daemon { conn, .. } => lock(conn)?.write(session_id, data.to_vec()),
+        }
+    }
+
+    pub fn resize(&self, session_id: &str, rows: u16, cols: u16) -> Result<()> {
+        match self {
+            Self::Local(sessions) => lock(sessions)?.resize(session_id, rows, cols),
+            Self::Daemon { conn, .. } => lock(conn)?.resize(session_id, rows, cols),
+        }
+    }
+
+    pub fn kill(&self, session_id: &str) -> Result<()> {
+        match self {
+            Self::Local(sessions) => lock(sessions)?.kill(session_id),
+            Self::Daemon { conn, .. } => lock(conn)?.kill(session_id),
+        }
+    }
+
+    pub fn nudge(&self, session_id: &str) -> Result<()> {
+        match self {
+            Self::Local(sessions) => lock(sessions)?.nudge(session_id).map(|_| ()),
+            Self::Daemon { conn, .. } => lock(conn)?.nudge(session_id).map(|_| ()),
+        }
+    }
+
+    /// Buffered output and the stream offset it ends at
+    pub fn history(&self, session_id: &str) -> Result<(Vec<u8>, usize)> {
+        match self {
+            Self::Local(sessions) => {
+                let sessions = lock(sessions)?;
+                let session = sessions
+                    .get(session_id)
+                    .ok_or_else(|| RembrandtError::SessionNotFound(session_id.to_string()))?;
+                let (data, start) = session.output_snapshot();
+                let end = start + data.len();
+                Ok((data, end))
+            }
+            Self::Daemon { conn, .. } => {
+                let (data, start) = lock(conn)?.history(session_id)?;
+                let end = start + data.len();
+                Ok((data, end))
+            }
+        }
+    }
+
+    /// What a session's terminal currently shows
+    pub fn screen(&self, session_id: &str) -> Result<ScreenSnapshot> {
+        match self {
+            Self::Local(sessions) => lock(sessions)?.get_screen(session_id),
+            Self::Daemon { conn, .. } => lock(conn)?.screen(session_id),
+        }
+    }
+
+    /// Call `on_event` from a background thread for each new chunk of
+    /// output, then once more when the session exits
+    pub fn follow<F>(&self, session_id: SessionId, on_event: F) -> Result<()>
+    where
+        F: FnMut(SessionEvent) + Send + 'static,
+    {
+        match self {
+            Self::Local(sessions) => {
+                follow_local(sessions.clone(), session_id, on_event);
+                Ok(())
+            }
+            Self::Daemon { socket_path, .. } => {
+                let mut conn = DaemonConnection::connect(socket_path)?;
+                // Raw output frames; an older daemon keeps speaking JSON
+                let _ = conn.set_framing(Framing::Binary);
+                conn.attach(&session_id)?;
+                follow_daemon(conn, on_event);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Stream an in-process session's output until it exits or is removed
+fn follow_local<F>(sessions: Arc<Mutex<SessionManager>>, session_id: SessionId, mut on_event: F)
+where
+    F: FnMut(SessionEvent) + Send + 'static,
+{
+    std::thread::spawn(move || loop {
+        let (offset, data, status) = {
+            let Ok(mut sessions) = sessions.lock() else {
+                break;
+            };
+            let Some(session) = sessions.get_mut(&session_id) else {
+                break;
+            };
+            // Poll first so an exited session's final output is in this drain
+            let status = session.poll();
+            let (offset, data) = session.drain_output();
+            (offset, data, status)
+        };
+
+        if !data.is_empty() {
+            on_event(SessionEvent::Output { offset, data });
+        }
+        if status != SessionStatus::Running {
+            on_event(SessionEvent::Exited(status));
+            break;
+        }
+
+        std::thread::sleep(OUTPUT_POLL_INTERVAL);
+    });
+}
+
+/// Relay an attached daemon connection's events until the session exits
+fn follow_daemon<F>(mut conn: DaemonConnection, mut on_event: F)
+where
+    F: FnMut(SessionEvent) + Send + 'static,
+{
+    std::thread::spawn(move || {
+        while let Ok(Some(event)) = conn.next_event() {
+            match event {
+                DaemonEvent::Output { offset, data, .. } => {
+                    on_event(SessionEvent::Output { offset, data });
+                }
+                DaemonEvent::Exited { code, .. } => {
+                    on_event(SessionEvent::Exited(SessionStatus::Exited(code)));
+                    break;
+                }
+                DaemonEvent::StatusChanged { status, .. } => {
+                    if status == SessionStatus::Running {
+                        continue;
+                    }
+                    on_event(SessionEvent::Exited(status));
+                    break;
+                }
+            }
+        }
+    });
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> Result<MutexGuard<'_, T>> {
+    mutex
+        .lock()
+        .map_err(|_| RembrandtError::Pty("Session lock poisoned".to_string()))
+}
@@ -0,0 +1,150 @@
+//! Competition state for the desktop app
+//!
+//! Wraps `rembrandt-core`'s `CompetitionManager` together with the agent
+//! registry it reads completion from. Managers are keyed by repository and
+//! base branch, so one window can run competitions against several checkouts,
+//! and built from each repository's config, so solutions are checked with its
+//! lint checks and security scanners.
+//! Competitor processes run as ordinary PTY sessions; their exit codes are
+//! folded back into the registry before each status refresh.
+
+use crate::agent::{AgentRegistry, AgentStatus};
+use crate::competition::{
+    Benchmark, CompetitionGroup, CompetitionId, CompetitionManager, CompetitionStatus,
+    CompetitorSpec, EarlyStopPolicy, EvaluatorStrategy, FollowUp,
+};
+use crate::manager::SessionInfo;
+use rembrandt_core::config::AppConfig;
+use rembrandt_core::{RembrandtError, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// All competitions started from the GUI
+#[derive(Default)]
+pub struct Competitions {
+    managers: HashMap<(PathBuf, String), CompetitionManager>,
+    registry: AgentRegistry,
+}
+
+impl Competitions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a competition, creating one worktree per agent
+    ///
+    /// The caller is responsible for spawning the competitor processes.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn start(
+        &mut self,
+        repo_path: &Path,
+        base_branch: &str,
+        prompt: String,
+        competitors: Vec<CompetitorSpec>,
+        evaluator_strategy: EvaluatorStrategy,
+        timeout_minutes: u64,
+        early_stop: Option<EarlyStopPolicy>,
+        benchmark: Option<Benchmark>,
+        retry_rounds: usize,
+        record_decision: bool,
+    ) -> Result<CompetitionId> {
+        let key = (repo_path.to_path_buf(), base_branch.to_string());
+        if !self.managers.contains_key(&key) {
+            let manager = AppConfig::load(repo_path)?.competition_manager(repo_path, base_branch)?;
+            self.managers.insert(key.clone(), manager);
+        }
+
+        let Self { managers, registry } = self;
+        let manager = managers.get_mut(&key).expect("manager inserted above");
+        let competition_id = manager
+            .start_competition(
+                prompt,
+                competitors,
+                evaluator_strategy,
+                timeout_minutes,
+                registry,
+            )
+            .await?;
+        manager.set_early_stop(&competition_id, early_stop)?;
+        manager.set_benchmark(&competition_id, benchmark)?;
+        manager.set_retry_rounds(&competition_id, retry_rounds)?;
+        manager.set_record_decision(&competition_id, record_decision)?;
+        Ok(competition_id)
+    }
+
+    /// Look up a competition by ID
+    pub fn get(&self, competition_id: &str) -> Option<&CompetitionGroup> {
+        self.managers
+            .values()
+            .find_map(|m| m.get_competition(competition_id))
+    }
+
+    /// All competitions, newest first
+    pub fn list(&self) -> Vec<&CompetitionGroup> {
+        let mut competitions: Vec<_> = self
+            .managers
+            .values()
+            .flat_map(|m| m.list_competitions())
+            .collect();
+        competitions.sort_by_key(|c| std::cmp::Reverse(c.started_at));
+        competitions
+    }
+
+    /// Advance a competition's state machine
+    ///
+    /// May run validation and evaluation, so call `sync_agent_statuses`
+    /// first and don't hold the session lock across this. Competitors cut
+    /// off by early stop are marked stopped; the caller kills their sessions.
+    pub async fn refresh(&mut self, competition_id: &str) -> Result<CompetitionStatus> {
+        let Self { managers, registry } = self;
+        let manager = find_manager(managers, competition_id)?;
+        let status = manager.update_competition(competition_id, registry).await?;
+        if let Some(competition) = manager.get_competition(competition_id) {
+            for agent_id in &competition.stopped_early {
+                if registry.get_session(agent_id).is_some_and(|s| s.status == AgentStatus::Active) {
+                    let _ = registry.update_status(agent_id, AgentStatus::Stopped);
+                }
+            }
+        }
+        Ok(status)
+    }
+
+    /// Follow-up prompts of a new retry round, for the caller to deliver
+    pub fn take_follow_ups(&mut self, competition_id: &str) -> Result<Vec<FollowUp>> {
+        Ok(find_manager(&mut self.managers, competition_id)?.take_follow_ups(competition_id))
+    }
+
+    /// Cancel a competition and mark its agents stopped
+    pub fn cancel(&mut self, competition_id: &str) -> Result<()> {
+        let Self { managers, registry } = self;
+        let manager = find_manager(managers, competition_id)?;
+        manager.cancel_competition(competition_id, registry)
+    }
+
+    /// Pick a different winner than the evaluator did
+    pub fn select_winner(&mut self, competition_id: &str, agent_id: &str, rationale: &str) -> Result<()> {
+        find_manager(&mut self.managers, competition_id)?.select_winner(competition_id, agent_id, rationale)
+    }
+
+    /// Merge the winning branch and clean up the losing worktrees
+    pub fn merge_winner(&mut self, competition_id: &str) -> Result<String> {
+        find_manager(&mut self.managers, competition_id)?.merge_winner(competition_id)
+    }
+
+    /// Map exited PTY sessions onto the registry's agent statuses
+    pub fn sync_agent_statuses(&mut self, sessions: &[SessionInfo]) {
+        self.registry.sync_competitors(sessions);
+    }
+}
+
+fn find_manager<'a>(
+    managers: &'a mut HashMap<(PathBuf, String), CompetitionManager>,
+    competition_id: &str,
+) -> Result<&'a mut CompetitionManager> {
+    managers
+        .values_mut()
+        .find(|m| m.get_competition(competition_id).is_some())
+        .ok_or_else(|| {
+            RembrandtError::Competition(format!("Competition not found: {}", competition_id))
+        })
+}
@@ -0,0 +1,136 @@
+//! Branch graph of a repository's agents
+//!
+//! How far each agent's `rembrandt/<agent_id>` branch has moved from the base
+//! branch: commits ahead and behind, and whether it is merged, would merge
+//! cleanly or conflicts. Computed from refs alone with git2, so agents whose
+//! worktrees are already gone still show up.
+
+use chrono::{DateTime, TimeZone, Utc};
+use git2::{BranchType, Commit, Repository};
+use rembrandt_core::{RembrandtError, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Prefix of the branches agents work on
+const AGENT_BRANCH_PREFIX: &str = "rembrandt/";
+
+/// The tip commit of a branch
+#[derive(Debug, Clone, Serialize)]
+pub struct BranchTip {
+    pub branch: String,
+    pub commit: String,
+    /// First line of the commit message
+    pub summary: String,
+    pub time: DateTime<Utc>,
+}
+
+/// Whether an agent's branch is in the base yet, and if not how it would merge
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStatus {
+    /// Every commit is already in the base
+    Merged,
+    /// The base can fast-forward to it
+    FastForward,
+    /// Both moved on, but they merge without conflicts
+    Clean,
+    Conflicts,
+}
+
+/// One agent's branch relative to the base
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentNode {
+    pub agent_id: String,
+    pub tip: BranchTip,
+    /// Where the branch forked from the base (None without common history)
+    pub merge_base: Option<String>,
+    /// Commits on the branch that the base lacks
+    pub ahead: usize,
+    /// Commits on the base since the branch forked
+    pub behind: usize,
+    pub merge_status: MergeStatus,
+    /// The agent's worktree, if it still has one
+    pub worktree: Option<PathBuf>,
+}
+
+/// The base branch and every agent branch, most recently moved first
+#[derive(Debug, Clone, Serialize)]
+pub struct RepoGraph {
+    pub base: BranchTip,
+    pub agents: Vec<AgentNode>,
+}
+
+/// Graph of the agent branches in `repo_path` against `base`, or the branch
+/// the main checkout has checked out
+pub fn repo_graph(repo_path: &Path, base: Option<&str>) -> Result<RepoGraph> {
+    let repo = Repository::open(repo_path)?;
+    let base = match base {
+        Some(base) => base.to_string(),
+        None => repo
+            .head()?
+            .shorthand()
+            .map(str::to_string)
+            .ok_or_else(|| RembrandtError::Worktree("Main checkout has a detached HEAD".into()))?,
+    };
+    let base_commit = repo.find_branch(&base, BranchType::Local)?.get().peel_to_commit()?;
+
+    let worktrees: HashMap<String, PathBuf> = repo
+        .worktrees()?
+        .iter()
+        .flatten()
+        .filter_map(|name| {
+            let worktree = repo.find_worktree(name).ok()?;
+            Some((name.to_string(), worktree.path().to_path_buf()))
+        })
+        .collect();
+
+    let mut agents = Vec::new();
+    for branch in repo.branches(Some(BranchType::Local))? {
+        let (branch, _) = branch?;
+        let Some(name) = branch.name()?.map(str::to_string) else {
+            continue;
+        };
+        let Some(agent_id) = name.strip_prefix(AGENT_BRANCH_PREFIX) else {
+            continue;
+        };
+        let commit = branch.get().peel_to_commit()?;
+        let (ahead, behind) = repo.graph_ahead_behind(commit.id(), base_commit.id())?;
+        let merge_status = if ahead == 0 {
+            MergeStatus::Merged
+        } else if behind == 0 {
+            MergeStatus::FastForward
+        } else if repo.merge_commits(&base_commit, &commit, None)?.has_conflicts() {
+            MergeStatus::Conflicts
+        } else {
+            MergeStatus::Clean
+        };
+        agents.push(AgentNode {
+            agent_id: agent_id.to_string(),
+            merge_base: repo.merge_base(commit.id(), base_commit.id()).ok().map(|oid| oid.to_string()),
+            tip: tip(&name, &commit),
+            ahead,
+            behind,
+            merge_status,
+            worktree: worktrees.get(agent_id).cloned(),
+        });
+    }
+    agents.sort_by_key(|agent| std::cmp::Reverse(agent.tip.time));
+
+    Ok(RepoGraph {
+        base: tip(&base, &base_commit),
+        agents,
+    })
+}
+
+fn tip(branch: &str, commit: &Commit<'_>) -> BranchTip {
+    BranchTip {
+        branch: branch.to_string(),
+        commit: commit.id().to_string(),
+        summary: commit.summary().unwrap_or_default().to_string(),
+        time: Utc
+            .timestamp_opt(commit.time().seconds(), 0)
+            .single()
+            .unwrap_or_default(),
+    }
+}
@@ -1,24 +1,36 @@
 //! Rembrandt GUI - Tauri backend
 //!
-//! Agent orchestration desktop app powered by Tauri + Svelte + xterm.js
+//! Agent orchestration desktop app powered by Tauri + Svelte + xterm.js.
+//! PTY sessions, ring buffers, worktrees and competitions come from
+//! `rembrandt-core`, the same implementation the CLI daemon and TUI use.
+//! When `rembrandt daemon` is running, sessions live there instead of in
+//! this process (see `backend`). Settings are read and written with the
+//! config schema the CLI uses, from `rembrandt-core` (see `settings`), and
+//! the agents it starts are recorded in the CLI's state.db so they can be
+//! restored (see `restore`).
 
-pub mod buffer;
-pub mod session;
-pub mod manager;
+pub mod backend;
+pub mod competitions;
+pub mod graph;
+pub mod review;
+pub mod settings;
+pub mod tasks;
+
+pub use rembrandt::restore;
+pub use rembrandt_core::{
+    agent, beads, buffer, competition, diff, ipc, manager, screen, session, worktree,
+};
 
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum AppError {
+    #[error(transparent)]
+    Core(#[from] rembrandt_core::RembrandtError),
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
-    #[error("Session not found: {0}")]
-    SessionNotFound(String),
-
-    #[error("PTY error: {0}")]
-    Pty(String),
-
     #[error("Git error: {0}")]
     Git(#[from] git2::Error),
 }
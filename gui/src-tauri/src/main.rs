@@ -1,54 +1,216 @@
 // Prevents additional console window on Windows in release
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use rembrandt_gui::manager::{SessionInfo, SessionManager};
-use std::path::PathBuf;
-use std::sync::Mutex;
-use tauri::State;
+use rembrandt_core::config::AppConfig;
+use rembrandt_core::{ErrorPayload, RembrandtError};
+use rembrandt_gui::agent::{wait_for_first_screen, AgentType};
+use rembrandt_gui::backend::{SessionBackend, SessionEvent};
+use rembrandt_gui::beads::{BeadsClient, BeadsTask};
+use rembrandt_gui::competition::{
+    Benchmark, CompetitionGroup, CompetitionId, CompetitorSolution, CompetitorSpec,
+    EarlyStopPolicy, EvaluatorStrategy,
+};
+use rembrandt_gui::competitions::Competitions;
+use rembrandt_gui::diff::FileDiff;
+use rembrandt_gui::graph::RepoGraph;
+use rembrandt_gui::manager::SessionInfo;
+use rembrandt_gui::restore::{Launch, RestorableSession};
+use rembrandt_gui::screen::ScreenSnapshot;
+use rembrandt_gui::review::{resolve_agent_branch, AgentBranch};
+use rembrandt_gui::session::{SessionId, SessionStatus};
+use rembrandt_gui::settings::Settings;
+use rembrandt_gui::tasks::TaskColumn;
+use rembrandt_gui::worktree::{main_repo_root, WorktreeManager};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// Defaults for `start_competition`, matching `rembrandt compete`
+const DEFAULT_COMPETITION_BRANCH: &str = "main";
+const DEFAULT_COMPETITION_TIMEOUT_MINUTES: u64 = 30;
+const DEFAULT_EVALUATOR_MODEL: &str = "claude-3-5-sonnet";
 
 /// Application state managed by Tauri
 pub struct AppState {
-    pub sessions: Mutex<SessionManager>,
+    /// In-process sessions, or a proxy to `rembrandt daemon` if it's running
+    pub sessions: SessionBackend,
+    /// Async lock: refreshing a competition can run validation and evaluation
+    pub competitions: tokio::sync::Mutex<Competitions>,
+}
+
+/// Incremental PTY output, emitted as `pty-output`
+#[derive(Debug, Clone, Serialize)]
+struct PtyOutputEvent {
+    session_id: SessionId,
+    /// Stream offset of the first byte in `data`
+    offset: usize,
+    data: Vec<u8>,
+}
+
+/// Emitted as `session-exited` once a session's process is gone
+#[derive(Debug, Clone, Serialize)]
+struct SessionExitedEvent {
+    session_id: SessionId,
+    status: SessionStatus,
+}
+
+/// Buffered history plus the stream offset it ends at
+///
+/// The frontend drops `pty-output` chunks whose offset is below `offset`,
+/// since they are already contained in `data`.
+#[derive(Debug, Clone, Serialize)]
+struct HistorySnapshot {
+    data: Vec<u8>,
+    offset: usize,
+}
+
+/// Push a session's output and exit to the frontend as Tauri events
+///
+/// Emits `pty-output` for each new chunk, then `session-exited` once the
+/// process is gone. The exit is also recorded in state.db, so the agent
+/// isn't offered for restore on the next launch.
+fn follow_session(
+    app: AppHandle,
+    backend: &SessionBackend,
+    session_id: SessionId,
+) -> rembrandt_core::Result<()> {
+    let id = session_id.clone();
+    let agent = session_agent(backend, &session_id)?;
+    backend
+        .follow(session_id, move |event| {
+            let _ = match event {
+                SessionEvent::Output { offset, data } => app.emit(
+                    "pty-output",
+                    PtyOutputEvent {
+                        session_id: id.clone(),
+                        offset,
+                        data,
+                    },
+                ),
+                SessionEvent::Exited(status) => {
+                    if let Some((repo, agent_id)) = &agent
+                        && let Err(e) = rembrandt_gui::restore::record_exit(repo, agent_id, &status)
+                    {
+                        eprintln!("Warning: could not record exit of {}: {}", agent_id, e);
+                    }
+                    app.emit(
+                        "session-exited",
+                        SessionExitedEvent {
+                            session_id: id.clone(),
+                            status,
+                        },
+                    )
+                }
+            };
+        })
+}
+
+/// Repository and agent id of a session, if it runs inside a repository
+fn session_agent(
+    backend: &SessionBackend,
+    session_id: &str,
+) -> rembrandt_core::Result<Option<(PathBuf, String)>> {
+    Ok(backend
+        .list()?
+        .into_iter()
+        .find(|info| info.id == session_id)
+        .and_then(|info| Some((info.repo?, info.agent_id))))
+}
+
+/// Record a started agent so it can be restored; failing to only costs
+/// the restore, so it doesn't fail the spawn
+fn record_launch(state: &AppState, launch: Launch<'_>) {
+    let launch = Launch {
+        daemon: state.sessions.is_daemon(),
+        ..launch
+    };
+    if let Err(e) = rembrandt_gui::restore::record_launch(&launch) {
+        eprintln!("Warning: could not record {}: {}", launch.agent_id, e);
+    }
+}
+
+/// Config of the repository `path` is in, for agent flags like
+/// `[model_flags]`; outside a repository, the defaults
+fn repo_config(path: &Path) -> rembrandt_core::Result<AppConfig> {
+    match main_repo_root(path) {
+        Some(repo) => AppConfig::load(&repo),
+        None => Ok(AppConfig::default()),
+    }
+}
+
+/// Error for a competition id that isn't known here
+fn competition_not_found(competition_id: &str) -> RembrandtError {
+    RembrandtError::Competition(format!("Competition not found: {}", competition_id))
 }
 
 /// Spawn a new agent
 #[tauri::command]
 fn spawn_agent(
+    app: AppHandle,
     state: State<AppState>,
     agent_id: String,
     command: String,
     workdir: String,
     rows: Option<u16>,
     cols: Option<u16>,
-) -> Result<String, String> {
-    let mut sessions = state.sessions.lock().map_err(|e| e.to_string())?;
-    let args: Vec<&str> = vec![];
+    model: Option<String>,
+) -> Result<String, ErrorPayload> {
     let path = PathBuf::from(&workdir);
-
-    sessions
-        .spawn(agent_id, &command, &args, &path, rows, cols)
-        .map_err(|e| e.to_string())
+    let model = model.as_deref().map(str::trim).filter(|m| !m.is_empty());
+    let args = match model {
+        Some(model) => repo_config(&path)?
+            .model_args(&AgentType::from_command(&command), model)?,
+        None => Vec::new(),
+    };
+    let arg_refs: Vec<&str> = args.iter().map(String::as_str).collect();
+    let session_id = state
+        .sessions
+        .spawn(agent_id.clone(), &command, &arg_refs, &path, rows, cols)?;
+    record_launch(
+        &state,
+        Launch {
+            agent_id: &agent_id,
+            command: &command,
+            args: &args,
+            workdir: &path,
+            session_id: &session_id,
+            daemon: false,
+            task_id: None,
+            model,
+            prompt: None,
+        },
+    );
+    follow_session(app, &state.sessions, session_id.clone())?;
+    Ok(session_id)
 }
 
 /// List all agents
 #[tauri::command]
-fn list_agents(state: State<AppState>) -> Result<Vec<SessionInfo>, String> {
-    let sessions = state.sessions.lock().map_err(|e| e.to_string())?;
-    Ok(sessions.list())
+fn list_agents(state: State<AppState>) -> Result<Vec<SessionInfo>, ErrorPayload> {
+    state.sessions.list().map_err(ErrorPayload::from)
 }
 
 /// Kill an agent
+///
+/// Like recording its launch, recording the stop only affects restore, so
+/// a failure to is a warning rather than a failed kill.
 #[tauri::command]
-fn kill_agent(state: State<AppState>, session_id: String) -> Result<(), String> {
-    let mut sessions = state.sessions.lock().map_err(|e| e.to_string())?;
-    sessions.kill(&session_id).map_err(|e| e.to_string())
+fn kill_agent(state: State<AppState>, session_id: String) -> Result<(), ErrorPayload> {
+    let agent = session_agent(&state.sessions, &session_id)?;
+    state.sessions.kill(&session_id)?;
+    if let Some((repo, agent_id)) = agent
+        && let Err(e) = rembrandt_gui::restore::record_stopped(&repo, &agent_id)
+    {
+        eprintln!("Warning: could not record {} as stopped: {}", agent_id, e);
+    }
+    Ok(())
 }
 
 /// Nudge an agent
 #[tauri::command]
-fn nudge_agent(state: State<AppState>, session_id: String) -> Result<(), String> {
-    let mut sessions = state.sessions.lock().map_err(|e| e.to_string())?;
-    sessions.nudge(&session_id).map_err(|e| e.to_string())
+fn nudge_agent(state: State<AppState>, session_id: String) -> Result<(), ErrorPayload> {
+    state.sessions.nudge(&session_id).map_err(ErrorPayload::from)
 }
 
 /// Write to an agent's PTY
@@ -57,9 +219,11 @@ fn write_to_agent(
     state: State<AppState>,
     session_id: String,
     data: Vec<u8>,
-) -> Result<(), String> {
-    let mut sessions = state.sessions.lock().map_err(|e| e.to_string())?;
-    sessions.write(&session_id, &data).map_err(|e| e.to_string())
+) -> Result<(), ErrorPayload> {
+    state
+        .sessions
+        .write(&session_id, &data)
+        .map_err(ErrorPayload::from)
 }
 
 /// Resize an agent's PTY
@@ -69,25 +233,539 @@ fn resize_agent(
     session_id: String,
     cols: u16,
     rows: u16,
-) -> Result<(), String> {
-    let sessions = state.sessions.lock().map_err(|e| e.to_string())?;
-    sessions
+) -> Result<(), ErrorPayload> {
+    state
+        .sessions
         .resize(&session_id, rows, cols)
-        .map_err(|e| e.to_string())
+        .map_err(ErrorPayload::from)
+}
+
+/// Get buffered output history for an agent (one-time backfill)
+///
+/// Live output after this arrives via `pty-output` events.
+#[tauri::command]
+fn get_history(
+    state: State<AppState>,
+    session_id: String,
+) -> Result<HistorySnapshot, ErrorPayload> {
+    let (data, offset) = state.sessions.history(&session_id)?;
+    Ok(HistorySnapshot { data, offset })
 }
 
-/// Get output history for an agent
+/// Rendered terminal screen of an agent, for thumbnails
 #[tauri::command]
-fn get_history(state: State<AppState>, session_id: String) -> Result<Vec<u8>, String> {
-    let sessions = state.sessions.lock().map_err(|e| e.to_string())?;
-    sessions.get_history(&session_id).map_err(|e| e.to_string())
+fn get_screen(
+    state: State<AppState>,
+    session_id: String,
+) -> Result<ScreenSnapshot, ErrorPayload> {
+    state.sessions.screen(&session_id).map_err(ErrorPayload::from)
+}
+
+/// Find the branch an agent works on, via its most recent session's workdir
+fn agent_branch(state: &AppState, agent_id: &str) -> rembrandt_core::Result<(AgentBranch, bool)> {
+    let sessions = state.sessions.list()?;
+    let agent_sessions: Vec<_> = sessions.iter().filter(|s| s.agent_id == agent_id).collect();
+    let latest = agent_sessions
+        .iter()
+        .max_by_key(|s| s.created_at)
+        .ok_or_else(|| RembrandtError::SessionNotFound(agent_id.to_string()))?;
+    let running = agent_sessions
+        .iter()
+        .any(|s| s.status == SessionStatus::Running);
+
+    let branch = resolve_agent_branch(&PathBuf::from(&latest.workdir), agent_id)?;
+    Ok((branch, running))
+}
+
+/// Per-file diff of an agent's branch against the point it forked from
+/// the main checkout's branch
+#[tauri::command]
+fn get_agent_diff(
+    state: State<AppState>,
+    agent_id: String,
+) -> Result<Vec<FileDiff>, ErrorPayload> {
+    let (target, _) = agent_branch(state.inner(), &agent_id)?;
+    WorktreeManager::new(&target.repo_path)
+        .and_then(|m| m.branch_diff(&target.branch, &target.base))
+        .map_err(ErrorPayload::from)
+}
+
+/// Base branch tip and every agent branch's tip, ahead/behind counts and
+/// merge status, for the branch graph
+///
+/// `base_branch` defaults to the branch the main checkout has checked out.
+#[tauri::command]
+fn get_repo_graph(
+    repo_path: String,
+    base_branch: Option<String>,
+) -> Result<RepoGraph, ErrorPayload> {
+    rembrandt_gui::graph::repo_graph(&PathBuf::from(&repo_path), base_branch.as_deref())
+        .map_err(ErrorPayload::from)
+}
+
+/// Beads tasks ready to work on in `repo_path` (empty without `br`/`bd`)
+#[tauri::command]
+fn list_ready_tasks(repo_path: String) -> Result<Vec<BeadsTask>, ErrorPayload> {
+    BeadsClient::detect()
+        .in_dir(&repo_path)
+        .ready_tasks()
+        .map_err(ErrorPayload::from)
+}
+
+/// Mark a Beads task in progress
+#[tauri::command]
+fn claim_task(repo_path: String, task_id: String) -> Result<(), ErrorPayload> {
+    BeadsClient::detect()
+        .in_dir(&repo_path)
+        .claim(&task_id)
+        .map_err(ErrorPayload::from)
+}
+
+/// Close a Beads task with a reason
+#[tauri::command]
+fn complete_task(
+    repo_path: String,
+    task_id: String,
+    reason: String,
+) -> Result<(), ErrorPayload> {
+    BeadsClient::detect()
+        .in_dir(&repo_path)
+        .complete(&task_id, &reason)
+        .map_err(ErrorPayload::from)
+}
+
+/// Whether `br` or `bd` is installed
+#[tauri::command]
+fn beads_available() -> bool {
+    BeadsClient::detect().is_available()
+}
+
+/// Every Beads task in `repo_path`, a column per status, for the board view
+#[tauri::command]
+fn get_task_board(repo_path: String) -> Result<Vec<TaskColumn>, ErrorPayload> {
+    let client = BeadsClient::detect().in_dir(&repo_path);
+    rembrandt_gui::tasks::board(&client).map_err(ErrorPayload::from)
+}
+
+/// Start an agent on a Beads task: claim it, create a worktree off
+/// `base_branch` and spawn `command` there with the task as its first input
+///
+/// Dropping a task into the board's "In Progress" column calls this.
+#[tauri::command]
+fn start_task(
+    app: AppHandle,
+    state: State<AppState>,
+    repo_path: String,
+    task_id: String,
+    command: String,
+    base_branch: Option<String>,
+    model: Option<String>,
+) -> Result<String, ErrorPayload> {
+    let agent = AgentType::from_command(&command);
+    let mut args: Vec<String> = agent.default_args().iter().map(|a| a.to_string()).collect();
+    if let Some(model) = model.as_deref().map(str::trim).filter(|m| !m.is_empty()) {
+        let config = repo_config(Path::new(&repo_path))?;
+        args.extend(config.model_args(&agent, model)?);
+    }
+    let client = BeadsClient::detect().in_dir(&repo_path);
+    let task_agent = rembrandt_gui::tasks::prepare(
+        &client,
+        &PathBuf::from(&repo_path),
+        &task_id,
+        &command,
+        base_branch.as_deref().unwrap_or(DEFAULT_COMPETITION_BRANCH),
+    )?;
+    let arg_refs: Vec<&str> = args.iter().map(String::as_str).collect();
+    let started = state
+        .sessions
+        .spawn(task_agent.agent_id.clone(), &command, &arg_refs, &task_agent.worktree_path, None, None)
+        .and_then(|session_id| {
+            let typed = wait_for_first_screen(|| state.sessions.history(&session_id).map(|(_, end)| end))
+                .and_then(|()| state.sessions.write(&session_id, format!("{}\n", task_agent.prompt).as_bytes()));
+            match typed {
+                Ok(()) => Ok(session_id),
+                Err(e) => {
+                    let _ = state.sessions.kill(&session_id);
+                    Err(e)
+                }
+            }
+        });
+    let session_id = match started {
+        Ok(session_id) => session_id,
+        Err(e) => {
+            // Leave the task startable again rather than claimed by nobody
+            if let Err(undo) = rembrandt_gui::tasks::abandon(&client, Path::new(&repo_path), &task_agent) {
+                eprintln!("Warning: could not release task {}: {}", task_agent.task_id, undo);
+            }
+            return Err(e.into());
+        }
+    };
+    record_launch(
+        &state,
+        Launch {
+            agent_id: &task_agent.agent_id,
+            command: &command,
+            args: &args,
+            workdir: &task_agent.worktree_path,
+            session_id: &session_id,
+            daemon: false,
+            task_id: Some(&task_agent.task_id),
+            model: model.as_deref().map(str::trim).filter(|m| !m.is_empty()),
+            prompt: Some(&task_agent.prompt),
+        },
+    );
+    follow_session(app, &state.sessions, session_id.clone())?;
+    Ok(session_id)
+}
+
+/// Agents recorded as active in the repository around `repo_path` that no
+/// longer run, offered for restore on launch
+#[tauri::command]
+fn get_restorable_sessions(
+    state: State<AppState>,
+    repo_path: String,
+) -> Result<Vec<RestorableSession>, ErrorPayload> {
+    let running: HashSet<String> = state
+        .sessions
+        .list()?
+        .into_iter()
+        .filter(|info| info.status == SessionStatus::Running)
+        .map(|info| info.agent_id)
+        .collect();
+    rembrandt_gui::restore::restorable(Path::new(&repo_path), &running).map_err(ErrorPayload::from)
+}
+
+/// Start a previously active agent again in its existing worktree
+#[tauri::command]
+fn restore_session(
+    app: AppHandle,
+    state: State<AppState>,
+    repo_path: String,
+    agent_id: String,
+) -> Result<String, ErrorPayload> {
+    let relaunch = rembrandt_gui::restore::relaunch(Path::new(&repo_path), &agent_id)?;
+    let arg_refs: Vec<&str> = relaunch.args.iter().map(String::as_str).collect();
+    let session_id = state
+        .sessions
+        .spawn(relaunch.agent_id.clone(), &relaunch.command, &arg_refs, &relaunch.workdir, None, None)?;
+    if let Some(prompt) = &relaunch.prompt {
+        state.sessions.write(&session_id, format!("{}\r", prompt).as_bytes())?;
+    }
+    record_launch(
+        &state,
+        Launch {
+            agent_id: &relaunch.agent_id,
+            command: &relaunch.command,
+            args: &relaunch.args,
+            workdir: &relaunch.workdir,
+            session_id: &session_id,
+            daemon: false,
+            task_id: relaunch.task_id.as_deref(),
+            model: relaunch.model.as_deref(),
+            prompt: relaunch.prompt.as_deref(),
+        },
+    );
+    follow_session(app, &state.sessions, session_id.clone())?;
+    Ok(session_id)
+}
+
+/// Stop offering an agent for restore
+#[tauri::command]
+fn dismiss_session(repo_path: String, agent_id: String) -> Result<(), ErrorPayload> {
+    rembrandt_gui::restore::dismiss(Path::new(&repo_path), &agent_id).map_err(ErrorPayload::from)
+}
+
+/// Comment on a Beads task
+#[tauri::command]
+fn comment_task(repo_path: String, task_id: String, text: String) -> Result<(), ErrorPayload> {
+    BeadsClient::detect()
+        .in_dir(&repo_path)
+        .comment(&task_id, &text)
+        .map_err(ErrorPayload::from)
+}
+
+/// Merge an agent's branch into the main checkout's branch
+///
+/// With `delete_branch`, also removes the agent's worktree and branch; the
+/// agent must have exited first. Returns the new base branch commit.
+#[tauri::command]
+fn merge_agent_branch(
+    state: State<AppState>,
+    agent_id: String,
+    delete_branch: bool,
+) -> Result<String, ErrorPayload> {
+    let (target, running) = agent_branch(state.inner(), &agent_id)?;
+    if delete_branch && running {
+        return Err(RembrandtError::Agent(format!(
+            "Agent {} is still running; stop it before deleting its branch",
+            agent_id
+        ))
+        .into());
+    }
+
+    let manager = WorktreeManager::new(&target.repo_path)?;
+    let tip = manager.merge_branch(&target.branch, &target.base)?;
+
+    if delete_branch {
+        if let Some(worktree) = &target.worktree {
+            manager.remove_worktree(worktree)?;
+        }
+        manager.delete_branch(&target.branch)?;
+    }
+
+    Ok(tip.to_string())
+}
+
+/// The repository's `.rembrandt/config.toml`, defaults filled in
+#[tauri::command]
+fn get_settings(repo_path: String) -> Result<Settings, ErrorPayload> {
+    rembrandt_gui::settings::load(&PathBuf::from(&repo_path)).map_err(ErrorPayload::from)
+}
+
+/// Check edited settings against the config schema without saving them
+#[tauri::command]
+fn validate_settings(values: serde_json::Value) -> Result<(), ErrorPayload> {
+    rembrandt_gui::settings::validate(values).map_err(ErrorPayload::from)
+}
+
+/// Merge edited settings onto `.rembrandt/config.toml` and save them;
+/// invalid ones are rejected and nothing is written
+#[tauri::command]
+fn save_settings(repo_path: String, values: serde_json::Value) -> Result<Settings, ErrorPayload> {
+    rembrandt_gui::settings::save(&PathBuf::from(&repo_path), values).map_err(ErrorPayload::from)
+}
+
+/// Start a competition: one worktree and PTY session per agent, all given
+/// the same prompt
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn start_competition(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    repo_path: String,
+    prompt: String,
+    agents: Vec<String>,
+    evaluator: Option<String>,
+    model: Option<String>,
+    timeout_minutes: Option<u64>,
+    base_branch: Option<String>,
+    early_stop_score: Option<f64>,
+    early_stop_grace_minutes: Option<u64>,
+    benchmark_command: Option<String>,
+    benchmark_weight: Option<f64>,
+    benchmark_higher_is_better: Option<bool>,
+    retry_rounds: Option<usize>,
+    record_decision: Option<bool>,
+) -> Result<CompetitionId, ErrorPayload> {
+    if agents.is_empty() {
+        return Err(
+            RembrandtError::Competition("A competition needs at least one agent".to_string())
+                .into(),
+        );
+    }
+
+    let competitors = CompetitorSpec::parse_all(&agents)?;
+    let evaluator_strategy = EvaluatorStrategy::from_name(
+        evaluator.as_deref().unwrap_or("metrics"),
+        model.as_deref().unwrap_or(DEFAULT_EVALUATOR_MODEL),
+    );
+    let base_branch = base_branch.unwrap_or_else(|| DEFAULT_COMPETITION_BRANCH.to_string());
+    let early_stop = early_stop_score.map(|min_score| EarlyStopPolicy {
+        min_score,
+        grace_minutes: early_stop_grace_minutes.unwrap_or(0),
+    });
+    let benchmark = benchmark_command.map(|command| {
+        let mut benchmark = Benchmark::new(command);
+        benchmark.lower_is_better = !benchmark_higher_is_better.unwrap_or(false);
+        if let Some(weight) = benchmark_weight {
+            benchmark.weight = weight;
+        }
+        benchmark
+    });
+
+    let mut competitions = state.competitions.lock().await;
+    let competition_id = competitions
+        .start(
+            &PathBuf::from(&repo_path),
+            &base_branch,
+            prompt.clone(),
+            competitors,
+            evaluator_strategy,
+            timeout_minutes.unwrap_or(DEFAULT_COMPETITION_TIMEOUT_MINUTES),
+            early_stop,
+            benchmark,
+            retry_rounds.unwrap_or(0),
+            record_decision.unwrap_or(false),
+        )
+        .await?;
+
+    let competition = competitions
+        .get(&competition_id)
+        .ok_or_else(|| competition_not_found(&competition_id))?;
+
+    for competitor in &competition.competitors {
+        spawn_competitor(&app, &state, competitor, &prompt)?;
+    }
+
+    Ok(competition_id)
+}
+
+/// Run a competitor's agent in its worktree with `prompt` as the first input
+fn spawn_competitor(
+    app: &AppHandle,
+    state: &AppState,
+    competitor: &CompetitorSolution,
+    prompt: &str,
+) -> rembrandt_core::Result<()> {
+    let mut args: Vec<String> =
+        competitor.agent_type.default_args().iter().map(|a| a.to_string()).collect();
+    let config = repo_config(&competitor.worktree_path)?;
+    if let Some(model) = &competitor.variant.model {
+        args.extend(config.model_args(&competitor.agent_type, model)?);
+    }
+    if let Some(temperature) = competitor.variant.temperature {
+        args.extend(config.temperature_args(&competitor.agent_type, temperature)?);
+    }
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let session_id = state
+        .sessions
+        .spawn(
+            competitor.agent_id.clone(),
+            competitor.agent_type.command(),
+            &args,
+            &competitor.worktree_path,
+            None,
+            None,
+        )?;
+    wait_for_first_screen(|| state.sessions.history(&session_id).map(|(_, end)| end))?;
+    state.sessions.write(&session_id, format!("{}\n", prompt).as_bytes())?;
+    follow_session(app.clone(), &state.sessions, session_id)
+}
+
+/// Get a competition, advancing its status from the competitors' sessions
+#[tauri::command]
+async fn get_competition(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    competition_id: String,
+) -> Result<CompetitionGroup, ErrorPayload> {
+    let mut competitions = state.competitions.lock().await;
+    let sessions = state.sessions.list()?;
+    competitions.sync_agent_statuses(&sessions);
+    competitions.refresh(&competition_id).await?;
+
+    let competition = competitions
+        .get(&competition_id)
+        .cloned()
+        .ok_or_else(|| competition_not_found(&competition_id))?;
+
+    // Competitors cut off by early stop
+    for info in &sessions {
+        if competition.stopped_early.contains(&info.agent_id) && info.status == SessionStatus::Running {
+            let _ = state.sessions.kill(&info.id);
+        }
+    }
+
+    // A retry round started: re-run each failed agent with its failures
+    let follow_ups = competitions.take_follow_ups(&competition_id)?;
+    for follow_up in follow_ups {
+        if let Some(competitor) = competition
+            .competitors
+            .iter()
+            .find(|c| c.agent_id == follow_up.agent_id)
+        {
+            spawn_competitor(&app, &state, competitor, &follow_up.prompt)?;
+        }
+    }
+
+    Ok(competition)
+}
+
+/// List all competitions, newest first
+///
+/// Statuses are as of the last `get_competition` call for each.
+#[tauri::command]
+async fn list_competitions(
+    state: State<'_, AppState>,
+) -> Result<Vec<CompetitionGroup>, ErrorPayload> {
+    let competitions = state.competitions.lock().await;
+    Ok(competitions.list().into_iter().cloned().collect())
+}
+
+/// Cancel a competition and kill its agents
+#[tauri::command]
+async fn cancel_competition(
+    state: State<'_, AppState>,
+    competition_id: String,
+) -> Result<(), ErrorPayload> {
+    let mut competitions = state.competitions.lock().await;
+    competitions.cancel(&competition_id)?;
+
+    let agent_ids: Vec<String> = competitions
+        .get(&competition_id)
+        .map(|c| c.competitors.iter().map(|s| s.agent_id.clone()).collect())
+        .unwrap_or_default();
+
+    let sessions = state.sessions.list()?;
+    for info in sessions {
+        if agent_ids.contains(&info.agent_id) && info.status == SessionStatus::Running {
+            let _ = state.sessions.kill(&info.id);
+        }
+    }
+
+    Ok(())
+}
+
+/// Override the evaluator's pick; records a Porque decision with `rationale`
+/// when the competition was started with `record_decision`
+#[tauri::command]
+async fn select_winner(
+    state: State<'_, AppState>,
+    competition_id: String,
+    agent_id: String,
+    rationale: String,
+) -> Result<CompetitionGroup, ErrorPayload> {
+    let mut competitions = state.competitions.lock().await;
+    competitions.select_winner(&competition_id, &agent_id, &rationale)?;
+    competitions
+        .get(&competition_id)
+        .cloned()
+        .ok_or_else(|| competition_not_found(&competition_id).into())
+}
+
+/// Merge the winning solution into the base branch
+///
+/// Returns the new base branch commit.
+#[tauri::command]
+async fn merge_winner(
+    state: State<'_, AppState>,
+    competition_id: String,
+) -> Result<String, ErrorPayload> {
+    let mut competitions = state.competitions.lock().await;
+    competitions
+        .merge_winner(&competition_id)
+        .map_err(ErrorPayload::from)
 }
 
 fn main() {
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .manage(AppState {
-            sessions: Mutex::new(SessionManager::new()),
+            sessions: SessionBackend::detect(),
+            competitions: tokio::sync::Mutex::new(Competitions::new()),
+        })
+        .setup(|app| {
+            // Sessions already running in the daemon (e.g. from before the
+            // window was last closed) need their output streamed too
+            let state = app.state::<AppState>();
+            if state.sessions.is_daemon() {
+                for info in state.sessions.list()? {
+                    if info.status == SessionStatus::Running {
+                        follow_session(app.handle().clone(), &state.sessions, info.id)?;
+                    }
+                }
+            }
+            Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             spawn_agent,
@@ -97,6 +775,29 @@ fn main() {
             write_to_agent,
             resize_agent,
             get_history,
+            get_screen,
+            start_competition,
+            get_competition,
+            list_competitions,
+            cancel_competition,
+            select_winner,
+            merge_winner,
+            get_agent_diff,
+            merge_agent_branch,
+            get_repo_graph,
+            get_settings,
+            validate_settings,
+            save_settings,
+            list_ready_tasks,
+            claim_task,
+            complete_task,
+            comment_task,
+            beads_available,
+            get_task_board,
+            start_task,
+            get_restorable_sessions,
+            restore_session,
+            dismiss_session,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
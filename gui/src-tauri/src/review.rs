@@ -0,0 +1,71 @@
+//! Reviewing and merging an agent's branch
+//!
+//! An agent's work lives on its own branch: the branch checked out in its
+//! worktree, or `rembrandt/<agent_id>` if it runs in the main checkout. The
+//! base is whatever branch the main checkout has checked out.
+
+use git2::Repository;
+use rembrandt_core::{RembrandtError, Result};
+use std::path::{Path, PathBuf};
+
+/// Where an agent's work lives and what it will be merged into
+#[derive(Debug, Clone)]
+pub struct AgentBranch {
+    /// Main repository (not the agent's worktree)
+    pub repo_path: PathBuf,
+    pub branch: String,
+    pub base: String,
+    /// Name of the linked worktree that has `branch` checked out, if any
+    pub worktree: Option<String>,
+}
+
+/// Resolve the branch and base for an agent running in `workdir`
+pub fn resolve_agent_branch(workdir: &Path, agent_id: &str) -> Result<AgentBranch> {
+    let repo = Repository::discover(workdir)?;
+
+    let (main_repo, branch, worktree) = if repo.is_worktree() {
+        let branch = repo
+            .head()?
+            .shorthand()
+            .map(str::to_string)
+            .ok_or_else(|| {
+                RembrandtError::Worktree(format!("Worktree for {} has a detached HEAD", agent_id))
+            })?;
+        // A linked worktree's git dir is `<main>/.git/worktrees/<name>`
+        let git_dir = repo.path();
+        let name = git_dir
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned());
+        let common_dir = git_dir
+            .parent()
+            .and_then(Path::parent)
+            .ok_or_else(|| RembrandtError::Worktree("Unexpected worktree layout".into()))?;
+        (Repository::open(common_dir)?, branch, name)
+    } else {
+        (repo, format!("rembrandt/{}", agent_id), None)
+    };
+
+    let base = main_repo
+        .head()?
+        .shorthand()
+        .map(str::to_string)
+        .ok_or_else(|| RembrandtError::Worktree("Main checkout has a detached HEAD".into()))?;
+    if base == branch {
+        return Err(RembrandtError::Worktree(format!(
+            "Agent {} is working directly on {}; there is no separate branch to review",
+            agent_id, base
+        )));
+    }
+
+    let repo_path = main_repo
+        .workdir()
+        .ok_or_else(|| RembrandtError::Worktree("Main repository is bare".into()))?
+        .to_path_buf();
+
+    Ok(AgentBranch {
+        repo_path,
+        branch,
+        base,
+        worktree,
+    })
+}
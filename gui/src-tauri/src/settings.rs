@@ -0,0 +1,105 @@
+//! Settings screen backend
+//!
+//! Reads and writes the repository's `.rembrandt/config.toml` through the
+//! shared `AppConfig` in `rembrandt-core`, so settings from the GUI are
+//! checked against the same schema and fall back to the same defaults as
+//! everywhere else.
+//!
+//! API token secrets never reach the screen: each is shown as
+//! [`REDACTED_TOKEN`], and saving that placeholder back keeps the stored
+//! secret. Saved values are merged onto the stored config as a JSON merge
+//! patch (RFC 7396), so settings the screen leaves out keep their values and
+//! `null` removes one.
+
+use rembrandt_core::config::AppConfig;
+use rembrandt_core::{RembrandtError, Result};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Shown in place of every API token's secret
+pub const REDACTED_TOKEN: &str = "********";
+
+/// Settings as the settings screen shows them
+#[derive(Debug, Clone, Serialize)]
+pub struct Settings {
+    /// `.rembrandt/config.toml` of the repository
+    pub path: PathBuf,
+    /// Whether the file exists; without it every value is a default
+    pub exists: bool,
+    /// Every setting, defaults filled in (unset optional ones are `null`)
+    /// and token secrets redacted
+    pub values: serde_json::Value,
+    /// What each setting is when the file doesn't set it
+    pub defaults: serde_json::Value,
+}
+
+/// The repository's settings
+pub fn load(repo_path: &Path) -> Result<Settings> {
+    snapshot(repo_path, &AppConfig::load(repo_path)?)
+}
+
+/// Check `values` against the config schema; missing keys are fine
+pub fn validate(values: serde_json::Value) -> Result<()> {
+    AppConfig::from_json(values).map(drop)
+}
+
+/// Merge `values` onto the stored settings and save them to
+/// `.rembrandt/config.toml`, keeping only those that differ from the
+/// defaults; nothing is written if the result doesn't fit the schema
+pub fn save(repo_path: &Path, values: serde_json::Value) -> Result<Settings> {
+    let stored = AppConfig::load(repo_path)?;
+    let mut merged = to_json(&stored)?;
+    merge_patch(&mut merged, values);
+    let mut config = AppConfig::from_json(merged)?;
+    for (name, token) in config.api_tokens.iter_mut() {
+        if token.token == REDACTED_TOKEN {
+            token.token = stored
+                .api_tokens
+                .get(name)
+                .map(|stored| stored.token.clone())
+                .ok_or_else(|| RembrandtError::Config(format!("API token '{}' needs a secret", name)))?;
+        }
+    }
+    config.save(repo_path)?;
+    snapshot(repo_path, &config)
+}
+
+/// Apply `patch` to `target`: objects merge key by key, `null` removes a
+/// key, anything else replaces what was there
+fn merge_patch(target: &mut serde_json::Value, patch: serde_json::Value) {
+    let serde_json::Value::Object(patch) = patch else {
+        *target = patch;
+        return;
+    };
+    if !target.is_object() {
+        *target = serde_json::Value::Object(Default::default());
+    }
+    let Some(target) = target.as_object_mut() else {
+        return;
+    };
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(&key);
+        } else {
+            merge_patch(target.entry(key).or_insert(serde_json::Value::Null), value);
+        }
+    }
+}
+
+fn to_json(config: &AppConfig) -> Result<serde_json::Value> {
+    serde_json::to_value(config).map_err(|e| RembrandtError::Config(e.to_string()))
+}
+
+fn snapshot(repo_path: &Path, config: &AppConfig) -> Result<Settings> {
+    let mut shown = config.clone();
+    for token in shown.api_tokens.values_mut() {
+        token.token = REDACTED_TOKEN.to_string();
+    }
+    let path = AppConfig::path(repo_path);
+    Ok(Settings {
+        exists: path.exists(),
+        path,
+        values: to_json(&shown)?,
+        defaults: to_json(&AppConfig::default())?,
+    })
+}
@@ -0,0 +1,112 @@
+//! Beads task board
+//!
+//! The board shows every Beads task in a column per status. Dragging a task
+//! into "In Progress" starts an agent on it in a worktree of its own, set up
+//! by the repository's `[worktree]` config like the CLI's; dragging it into
+//! "Done" closes it.
+
+use crate::beads::{BeadsClient, BeadsTask};
+use crate::worktree::WorktreeManager;
+use rembrandt_core::config::AppConfig;
+use rembrandt_core::{RembrandtError, Result};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+/// Board columns, left to right, by Beads status
+pub const BOARD_STATUSES: &[&str] = &["open", "in_progress", "blocked", "closed"];
+
+/// Tasks with one status
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskColumn {
+    pub status: String,
+    pub tasks: Vec<BeadsTask>,
+}
+
+/// An agent about to start on a task; the caller spawns its session in
+/// `worktree_path` and types `prompt` into it
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskAgent {
+    pub agent_id: String,
+    pub task_id: String,
+    pub worktree_path: PathBuf,
+    pub branch: String,
+    pub prompt: String,
+}
+
+/// Every task in the repository, a column per status in `BOARD_STATUSES`
+pub fn board(client: &BeadsClient) -> Result<Vec<TaskColumn>> {
+    BOARD_STATUSES
+        .iter()
+        .map(|status| {
+            Ok(TaskColumn {
+                status: status.to_string(),
+                tasks: client.tasks(status)?,
+            })
+        })
+        .collect()
+}
+
+/// Create a worktree off `base_branch` for an `agent` working on `task_id`
+/// and mark the task in progress
+///
+/// The agent is named after the task (`claude-bd-a1`), so a task can't be
+/// started twice while its worktree is still around. If the agent then
+/// fails to start, [`abandon`] undoes this.
+pub fn prepare(
+    client: &BeadsClient,
+    repo_path: &Path,
+    task_id: &str,
+    agent: &str,
+    base_branch: &str,
+) -> Result<TaskAgent> {
+    let task = client
+        .show(task_id)?
+        .ok_or_else(|| RembrandtError::Runtime(format!("No such task: {}", task_id)))?;
+    let agent_id = format!("{}-{}", agent, task.id);
+    let manager = worktrees(repo_path)?;
+    let worktree = manager.create_worktree(&agent_id, base_branch)?;
+    if let Err(e) = client.claim(&task.id) {
+        if let Err(undo) = remove_checkout(&manager, repo_path, &agent_id, &worktree.branch) {
+            eprintln!("Warning: could not remove worktree of {}: {}", agent_id, undo);
+        }
+        return Err(e);
+    }
+    Ok(TaskAgent {
+        agent_id,
+        task_id: task.id.clone(),
+        worktree_path: worktree.path,
+        branch: worktree.branch,
+        prompt: task_prompt(&task),
+    })
+}
+
+/// Undo [`prepare`] for an agent that failed to start: hand the task back
+/// and remove the worktree and its branch, so the task can be started again
+pub fn abandon(client: &BeadsClient, repo_path: &Path, agent: &TaskAgent) -> Result<()> {
+    let reopened = client.update_status(&agent.task_id, "open");
+    remove_checkout(&worktrees(repo_path)?, repo_path, &agent.agent_id, &agent.branch)?;
+    reopened
+}
+
+/// The repository's worktree manager, with its `[worktree]` config applied
+fn worktrees(repo_path: &Path) -> Result<WorktreeManager> {
+    AppConfig::load(repo_path)?.worktree.manager(repo_path)
+}
+
+/// Remove a worktree `prepare` just created, and its branch, which nothing
+/// was committed to yet
+fn remove_checkout(manager: &WorktreeManager, repo_path: &Path, agent_id: &str, branch: &str) -> Result<()> {
+    manager.remove_worktree(agent_id)?;
+    git2::Repository::open(repo_path)?
+        .find_branch(branch, git2::BranchType::Local)?
+        .delete()?;
+    Ok(())
+}
+
+/// First message for an agent taking `task`
+fn task_prompt(task: &BeadsTask) -> String {
+    match &task.description {
+        Some(description) => format!("Work on task {}: {}\n\nDescription: {}", task.id, task.title, description),
+        None => format!("Work on task {}: {}", task.id, task.title),
+    }
+}
@@ -0,0 +1,17 @@
+// Errors from Tauri commands arrive as `ErrorPayload`s (see rembrandt-core)
+
+export interface ErrorPayload {
+  code: string
+  message: string
+  hint?: string
+}
+
+function isErrorPayload(e: unknown): e is ErrorPayload {
+  return typeof e === 'object' && e !== null && 'code' in e && 'message' in e
+}
+
+// A command's error as one line for the user, with its hint when it has one
+export function errorText(e: unknown): string {
+  if (!isErrorPayload(e)) return String(e)
+  return e.hint ? `${e.message} (${e.hint})` : e.message
+}
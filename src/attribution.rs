@@ -0,0 +1,298 @@
+//! Which agents the commits on a branch came from.
+//!
+//! `rembrandt blame-report` walks the base branch's history over a time
+//! range and credits each commit (merge commits aside) with the lines it
+//! added and removed. A commit's agent comes from its `Rembrandt-Agent`
+//! trailer (see [`crate::trailers`]), or failing that from the branch it
+//! came in on: the commits a `Merge branch 'rembrandt/<id>'` merge brought
+//! in, and the commits on a still-existing `rembrandt/<id>` branch since it
+//! was created. Agent types come from the agents' sessions in state.db, or
+//! the `<type>-<hex>` shape of their ids. Commits with neither are counted
+//! as unattributed.
+
+use crate::state::StateStore;
+use crate::trailers::Provenance;
+use crate::{RembrandtError, Result};
+use chrono::{DateTime, TimeZone, Utc};
+use git2::{BranchType, Commit, Oid, Repository, Sort};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+
+/// Prefix of the branches agents work on
+const AGENT_BRANCH_PREFIX: &str = "rembrandt/";
+
+/// Commits and lines credited to someone
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Totals {
+    pub commits: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+impl Totals {
+    fn add(&mut self, commit: &Totals) {
+        self.commits += commit.commits;
+        self.insertions += commit.insertions;
+        self.deletions += commit.deletions;
+    }
+}
+
+/// One agent's share
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AgentShare {
+    pub agent_id: String,
+    pub agent_type: String,
+    /// Tasks named in its commits' trailers or its session
+    pub tasks: BTreeSet<String>,
+    /// Runtime sessions named in its commits' trailers
+    pub sessions: BTreeSet<String>,
+    #[serde(flatten)]
+    pub totals: Totals,
+}
+
+/// One agent type's share
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TypeShare {
+    pub agent_type: String,
+    pub agents: usize,
+    #[serde(flatten)]
+    pub totals: Totals,
+}
+
+/// Attribution of a branch's commits over a time range
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AttributionReport {
+    pub base: String,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// Every commit in the range, merge commits aside
+    pub total: Totals,
+    /// Most lines added first
+    pub by_type: Vec<TypeShare>,
+    /// Most lines added first
+    pub by_agent: Vec<AgentShare>,
+    pub unattributed: Totals,
+}
+
+/// Attribute the commits on `base` (or the main checkout's branch) made
+/// between `since` and `until`
+pub fn report(
+    repo_path: &Path,
+    store: Option<&StateStore>,
+    base: Option<&str>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+) -> Result<AttributionReport> {
+    let repo = Repository::open(repo_path)?;
+    let base = match base {
+        Some(base) => base.to_string(),
+        None => repo
+            .head()?
+            .shorthand()
+            .map(str::to_string)
+            .ok_or_else(|| RembrandtError::Worktree("Main checkout has a detached HEAD".into()))?,
+    };
+    let tip = repo.find_branch(&base, BranchType::Local)?.get().peel_to_commit()?.id();
+
+    let mut walk = repo.revwalk()?;
+    walk.push(tip)?;
+    walk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+    let history: Vec<Oid> = walk.collect::<std::result::Result<_, _>>()?;
+    let from_branches = branch_provenance(&repo, &history)?;
+
+    let mut report = AttributionReport {
+        base,
+        since,
+        until,
+        total: Totals::default(),
+        by_type: Vec::new(),
+        by_agent: Vec::new(),
+        unattributed: Totals::default(),
+    };
+    let mut agents: BTreeMap<String, AgentShare> = BTreeMap::new();
+    for oid in history {
+        let commit = repo.find_commit(oid)?;
+        if commit.parent_count() > 1 {
+            continue;
+        }
+        let time = commit_time(&commit);
+        if since.is_some_and(|since| time < since) || until.is_some_and(|until| time > until) {
+            continue;
+        }
+        let totals = commit_totals(&repo, &commit)?;
+        report.total.add(&totals);
+
+        let provenance = commit
+            .message()
+            .and_then(Provenance::from_message)
+            .or_else(|| from_branches.get(&oid).map(|agent_id| Provenance {
+                agent_id: agent_id.clone(),
+                ..Default::default()
+            }));
+        let Some(provenance) = provenance else {
+            report.unattributed.add(&totals);
+            continue;
+        };
+        let share = agents.entry(provenance.agent_id.clone()).or_insert_with(|| {
+            let record = store.and_then(|store| store.get_session(&provenance.agent_id).ok().flatten());
+            AgentShare {
+                agent_type: agent_type(&provenance.agent_id, record.as_ref()),
+                tasks: record.and_then(|r| r.task_id).into_iter().collect(),
+                agent_id: provenance.agent_id.clone(),
+                sessions: BTreeSet::new(),
+                totals: Totals::default(),
+            }
+        });
+        share.tasks.extend(provenance.task_id);
+        share.sessions.extend(provenance.session_id);
+        share.totals.add(&totals);
+    }
+
+    let mut by_type: BTreeMap<String, TypeShare> = BTreeMap::new();
+    for share in agents.values() {
+        let row = by_type.entry(share.agent_type.clone()).or_insert_with(|| TypeShare {
+            agent_type: share.agent_type.clone(),
+            agents: 0,
+            totals: Totals::default(),
+        });
+        row.agents += 1;
+        row.totals.add(&share.totals);
+    }
+    report.by_type = by_type.into_values().collect();
+    report.by_type.sort_by_key(|row| std::cmp::Reverse(row.totals.insertions));
+    report.by_agent = agents.into_values().collect();
+    report.by_agent.sort_by_key(|row| std::cmp::Reverse(row.totals.insertions));
+    Ok(report)
+}
+
+/// Agents of commits that came in on an agent branch, by commit
+///
+/// Covers the commits each `Merge branch 'rembrandt/<id>'` merge in
+/// `history` brought in, and those on each remaining agent branch since it
+/// was created (per its reflog).
+fn branch_provenance(repo: &Repository, history: &[Oid]) -> Result<HashMap<Oid, String>> {
+    let mut agents = HashMap::new();
+    let mut credit = |agent_id: &str, tip: Oid, hide: Oid| -> Result<()> {
+        let mut walk = repo.revwalk()?;
+        walk.push(tip)?;
+        walk.hide(hide)?;
+        for oid in walk {
+            agents.entry(oid?).or_insert_with(|| agent_id.to_string());
+        }
+        Ok(())
+    };
+
+    for &oid in history {
+        let commit = repo.find_commit(oid)?;
+        if commit.parent_count() != 2 {
+            continue;
+        }
+        let merged = commit
+            .summary()
+            .and_then(|summary| summary.strip_prefix("Merge branch '"))
+            .and_then(|rest| rest.split_once('\'').map(|(branch, _)| branch))
+            .and_then(|branch| branch.strip_prefix(AGENT_BRANCH_PREFIX));
+        if let Some(agent_id) = merged {
+            credit(agent_id, commit.parent_id(1)?, commit.parent_id(0)?)?;
+        }
+    }
+
+    for branch in repo.branches(Some(BranchType::Local))? {
+        let (branch, _) = branch?;
+        let Some(name) = branch.get().name().map(str::to_string) else {
+            continue;
+        };
+        let Some(agent_id) = name.strip_prefix("refs/heads/").and_then(|n| n.strip_prefix(AGENT_BRANCH_PREFIX)) else {
+            continue;
+        };
+        let Some(tip) = branch.get().target() else {
+            continue;
+        };
+        // The oldest reflog entry is the branch's creation
+        let reflog = repo.reflog(&name)?;
+        let Some(created_at) = reflog.iter().next_back().map(|entry| entry.id_new()) else {
+            continue;
+        };
+        credit(agent_id, tip, created_at)?;
+    }
+    Ok(agents)
+}
+
+/// An agent's type: from its session, or the `<type>-<hex>` shape of its id
+fn agent_type(agent_id: &str, record: Option<&crate::state::SessionRecord>) -> String {
+    record
+        .and_then(|record| record.agent_type.clone())
+        .or_else(|| crate::fork::agent_type_of(agent_id).map(crate::warm_pool::canonical_type))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Lines `commit` added and removed relative to its parent
+fn commit_totals(repo: &Repository, commit: &Commit<'_>) -> Result<Totals> {
+    let parent_tree = match commit.parent_count() {
+        0 => None,
+        _ => Some(commit.parent(0)?.tree()?),
+    };
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+    let stats = diff.stats()?;
+    Ok(Totals {
+        commits: 1,
+        insertions: stats.insertions(),
+        deletions: stats.deletions(),
+    })
+}
+
+fn commit_time(commit: &Commit<'_>) -> DateTime<Utc> {
+    Utc.timestamp_opt(commit.time().seconds(), 0)
+        .single()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::git;
+    use crate::worktree::WorktreeManager;
+
+    fn commit_file(dir: &Path, file: &str, lines: usize, message: &str) {
+        std::fs::write(dir.join(file), "line\n".repeat(lines)).unwrap();
+        git(dir, &["add", file]);
+        git(dir, &["commit", "-q", "-m", message]);
+    }
+
+    #[test]
+    fn credits_commits_by_trailer_and_merged_branch() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo_path = dir.path();
+        git(repo_path, &["init", "-q", "-b", "main"]);
+        git(repo_path, &["config", "user.name", "t"]);
+        git(repo_path, &["config", "user.email", "t@example.com"]);
+        commit_file(repo_path, "README", 1, "Initial");
+
+        // An agent's branch, merged with a merge commit after main moved on
+        git(repo_path, &["checkout", "-q", "-b", "rembrandt/aider-0b1c"]);
+        commit_file(repo_path, "a.rs", 3, "Add a");
+        commit_file(repo_path, "b.rs", 2, "Add b");
+        git(repo_path, &["checkout", "-q", "main"]);
+        commit_file(repo_path, "human.txt", 4, "Human change");
+        commit_file(repo_path, "c.rs", 6, "Add c\n\nRembrandt-Agent: claude-bd-7\nRembrandt-Task: bd-7\nRembrandt-Session: s-1");
+        WorktreeManager::new(repo_path).unwrap().merge_branch("rembrandt/aider-0b1c", "main").unwrap();
+        git(repo_path, &["branch", "-q", "-D", "rembrandt/aider-0b1c"]);
+
+        let attribution = report(repo_path, None, None, None, None).unwrap();
+        assert_eq!(attribution.base, "main");
+        assert_eq!(attribution.total, Totals { commits: 5, insertions: 16, deletions: 0 });
+        assert_eq!(attribution.unattributed, Totals { commits: 2, insertions: 5, deletions: 0 });
+        let aider = attribution.by_agent.iter().find(|a| a.agent_id == "aider-0b1c").unwrap();
+        assert_eq!(aider.agent_type, "aider");
+        assert_eq!(aider.totals, Totals { commits: 2, insertions: 5, deletions: 0 });
+        let claude = &attribution.by_agent[0];
+        assert_eq!(claude.agent_id, "claude-bd-7");
+        assert_eq!(claude.tasks, BTreeSet::from(["bd-7".to_string()]));
+        assert_eq!(claude.sessions, BTreeSet::from(["s-1".to_string()]));
+        assert_eq!(attribution.by_type.len(), 2);
+
+        let later = report(repo_path, None, None, Some(Utc::now() + chrono::Duration::hours(1)), None).unwrap();
+        assert_eq!(later.total, Totals::default());
+    }
+}
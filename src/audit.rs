@@ -0,0 +1,238 @@
+//! Append-only audit log of state-changing operations.
+//!
+//! Every spawn, kill, steer, merge, task transition and similar mutation is
+//! recorded in the `audit_log` table of state.db with who triggered it
+//! (`$REMBRANDT_ACTOR`, else `$USER`; the token's name for API clients),
+//! from where (CLI, TUI, CSI, the daemon or a token-holding API client),
+//! when, and with what parameters. Triggers reject updates and
+//! deletes, so entries can only be added. `rembrandt audit` queries it.
+
+use crate::state::StateStore;
+use crate::{RembrandtError, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::path::Path;
+
+/// Environment variable naming who is acting, e.g. a CI job or service account
+pub const ACTOR_ENV: &str = "REMBRANDT_ACTOR";
+
+/// Where a mutation was triggered from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditSource {
+    Cli,
+    Tui,
+    Csi,
+    Daemon,
+    /// A daemon client that presented an API token
+    Api,
+}
+
+impl AuditSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AuditSource::Cli => "cli",
+            AuditSource::Tui => "tui",
+            AuditSource::Csi => "csi",
+            AuditSource::Daemon => "daemon",
+            AuditSource::Api => "api",
+        }
+    }
+}
+
+impl std::str::FromStr for AuditSource {
+    type Err = RembrandtError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "cli" => Ok(AuditSource::Cli),
+            "tui" => Ok(AuditSource::Tui),
+            "csi" => Ok(AuditSource::Csi),
+            "daemon" => Ok(AuditSource::Daemon),
+            "api" => Ok(AuditSource::Api),
+            other => Err(RembrandtError::State(format!(
+                "unknown audit source '{}'",
+                other
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for AuditSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+/// One recorded mutation
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub source: AuditSource,
+    pub actor: String,
+    /// What was done, e.g. `spawn`, `kill`, `steer`, `merge`
+    pub action: String,
+    /// What it was done to, usually an agent ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// The operation's parameters, as JSON
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub params: Option<serde_json::Value>,
+}
+
+/// Filters for reading the audit log
+#[derive(Debug, Clone, Default)]
+pub struct AuditQuery {
+    pub action: Option<String>,
+    pub target: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    /// Most recent entries to return; all when `None`
+    pub limit: Option<usize>,
+}
+
+/// Who is acting: `$REMBRANDT_ACTOR`, else `$USER`, else `unknown`
+pub fn actor() -> String {
+    [ACTOR_ENV, "USER", "USERNAME"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Start of a `--since` window: an age like `30m`, `24h` or `7d`, or an
+/// RFC 3339 time
+pub fn parse_since(value: &str) -> Result<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    let invalid = || {
+        RembrandtError::Config(format!(
+            "invalid --since '{}': expected e.g. 30m, 24h, 7d or an RFC 3339 time",
+            value
+        ))
+    };
+    let age = parse_duration(value).ok_or_else(invalid)?;
+    Ok(Utc::now() - age)
+}
+
+/// A span like `90s`, `30m`, `24h`, `7d` or `2w`
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let split = value.find(|c: char| !c.is_ascii_digit())?;
+    let (amount, unit) = value.split_at(split);
+    let amount: i64 = amount.parse().ok()?;
+    match unit {
+        "s" => Some(Duration::seconds(amount)),
+        "m" => Some(Duration::minutes(amount)),
+        "h" => Some(Duration::hours(amount)),
+        "d" => Some(Duration::days(amount)),
+        "w" => Some(Duration::weeks(amount)),
+        _ => None,
+    }
+}
+
+/// Record a mutation in `store` on behalf of the current actor
+pub fn record_in(
+    store: &StateStore,
+    source: AuditSource,
+    action: &str,
+    target: Option<&str>,
+    params: serde_json::Value,
+) -> Result<()> {
+    let params = (!params.is_null()).then_some(params);
+    store.record_audit(source, &actor(), action, target, params.as_ref())
+}
+
+/// Record a mutation in `repo_path`'s state.db
+///
+/// The audited operation has already happened, so a failure to record it
+/// is logged rather than returned.
+pub fn record(
+    repo_path: &Path,
+    source: AuditSource,
+    action: &str,
+    target: Option<&str>,
+    params: serde_json::Value,
+) {
+    record_by(repo_path, source, &actor(), action, target, params);
+}
+
+/// Record a mutation in `repo_path`'s state.db on behalf of `actor`
+pub fn record_by(
+    repo_path: &Path,
+    source: AuditSource,
+    actor: &str,
+    action: &str,
+    target: Option<&str>,
+    params: serde_json::Value,
+) {
+    let params = (!params.is_null()).then_some(params);
+    let recorded = StateStore::open(repo_path)
+        .and_then(|store| store.record_audit(source, actor, action, target, params.as_ref()));
+    if let Err(e) = recorded {
+        tracing::warn!(action, "failed to record audit entry: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn entries_are_append_only_and_filterable() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = StateStore::open(dir.path()).unwrap();
+        record_in(
+            &store,
+            AuditSource::Cli,
+            "spawn",
+            Some("claude-1"),
+            json!({"agent": "claude-code", "task": "br-7"}),
+        )
+        .unwrap();
+        record_in(&store, AuditSource::Tui, "kill", Some("claude-1"), json!(null)).unwrap();
+        record_in(&store, AuditSource::Cli, "merge", Some("codex-2"), json!({"base": "main"}))
+            .unwrap();
+
+        let all = store.audit_log(&AuditQuery::default()).unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].action, "spawn");
+        assert_eq!(all[0].params.as_ref().unwrap()["task"], "br-7");
+        assert_eq!(all[1].source, AuditSource::Tui);
+        assert!(all[1].params.is_none());
+
+        let claude = store
+            .audit_log(&AuditQuery {
+                target: Some("claude-1".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(claude.len(), 2);
+        let latest = store
+            .audit_log(&AuditQuery {
+                limit: Some(1),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(latest[0].action, "merge", "limit keeps the newest");
+
+        let conn = rusqlite::Connection::open(dir.path().join(".rembrandt/state.db")).unwrap();
+        assert!(conn.execute("DELETE FROM audit_log", []).is_err());
+        assert!(conn.execute("UPDATE audit_log SET actor = 'someone'", []).is_err());
+        assert_eq!(store.audit_log(&AuditQuery::default()).unwrap().len(), 3);
+
+        let future = store
+            .audit_log(&AuditQuery {
+                since: Some(Utc::now() + Duration::hours(1)),
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(future.is_empty());
+        let day_ago = parse_since("24h").unwrap();
+        assert!((Utc::now() - day_ago - Duration::hours(24)).num_seconds().abs() < 5);
+        assert!(parse_since("2026-01-02T03:04:05Z").is_ok());
+        assert!(parse_since("soon").is_err());
+    }
+}
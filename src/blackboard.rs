@@ -0,0 +1,175 @@
+//! Shared blackboard: decisions every agent can read without merging.
+//!
+//! The blackboard lives at `.rembrandt/blackboard.md` in the main checkout.
+//! A read-only copy is put at the same path in every agent worktree when it
+//! is created, after each `rembrandt blackboard add`, and on every
+//! `rembrandt csi run` pass, so edits made by hand spread too. Worktrees
+//! share `.git/info/exclude`, where `/.rembrandt/` is added so the copy is
+//! never committed.
+
+use crate::state::StateStore;
+use crate::worktree::WorktreeManager;
+use crate::Result;
+use chrono::Utc;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+const HEADER: &str = "# Blackboard\n\n\
+    Decisions shared between agents. This copy is read-only; add an entry with\n\
+    `rembrandt blackboard add \"...\"`.\n";
+
+/// The blackboard in the main checkout at `repo_root`
+pub fn path(repo_root: &Path) -> PathBuf {
+    repo_root.join(".rembrandt").join("blackboard.md")
+}
+
+/// The blackboard's contents, or `None` if nobody has written to it
+pub fn read(repo_root: &Path) -> Result<Option<String>> {
+    match std::fs::read_to_string(path(repo_root)) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Append an entry signed by `author`, creating the blackboard if needed
+pub fn append(repo_root: &Path, author: &str, text: &str) -> Result<()> {
+    let path = path(repo_root);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&path)?;
+    if file.metadata()?.len() == 0 {
+        file.write_all(HEADER.as_bytes())?;
+    }
+    write!(
+        file,
+        "\n## {} — {}\n\n{}\n",
+        Utc::now().format("%Y-%m-%d %H:%M UTC"),
+        author,
+        text.trim()
+    )?;
+    Ok(())
+}
+
+/// Put a read-only copy of the blackboard into `checkout`
+///
+/// Returns whether the copy changed. Does nothing without a blackboard, or
+/// when `checkout` is the main checkout itself (branch isolation).
+pub fn sync_into(repo_root: &Path, checkout: &Path) -> Result<bool> {
+    let Some(contents) = read(repo_root)? else {
+        return Ok(false);
+    };
+    if same_dir(repo_root, checkout) || !checkout.is_dir() {
+        return Ok(false);
+    }
+    let target = path(checkout);
+    if std::fs::read_to_string(&target).is_ok_and(|current| current == contents) {
+        return Ok(false);
+    }
+
+    exclude_rembrandt_dir(repo_root)?;
+    if let Some(dir) = target.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    // The copy is read-only, so replace it rather than writing through it
+    if target.exists() {
+        std::fs::remove_file(&target)?;
+    }
+    std::fs::write(&target, contents)?;
+    let mut permissions = std::fs::metadata(&target)?.permissions();
+    permissions.set_readonly(true);
+    std::fs::set_permissions(&target, permissions)?;
+    Ok(true)
+}
+
+/// Sync the blackboard into every agent worktree and live session checkout;
+/// returns how many copies changed.
+pub fn sync_all(repo_root: &Path) -> Result<usize> {
+    if read(repo_root)?.is_none() {
+        return Ok(0);
+    }
+    let mut checkouts: Vec<PathBuf> = WorktreeManager::new(repo_root)?
+        .list_worktrees()?
+        .into_iter()
+        .map(|wt| wt.path)
+        .collect();
+    if repo_root.join(".rembrandt/state.db").exists() {
+        checkouts.extend(
+            StateStore::open(repo_root)?
+                .list_active()?
+                .into_iter()
+                .map(|session| session.checkout_path),
+        );
+    }
+    checkouts.sort();
+    checkouts.dedup();
+
+    let mut changed = 0;
+    for checkout in checkouts {
+        match sync_into(repo_root, &checkout) {
+            Ok(true) => changed += 1,
+            Ok(false) => {}
+            Err(e) => tracing::warn!(checkout = %checkout.display(), error = %e, "could not sync blackboard"),
+        }
+    }
+    Ok(changed)
+}
+
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// Keep `.rembrandt/` out of commits in every worktree of the repository
+fn exclude_rembrandt_dir(repo_root: &Path) -> Result<()> {
+    const PATTERN: &str = "/.rembrandt/";
+    let repo = git2::Repository::open(repo_root)?;
+    // `repo_root` is the main checkout, whose git dir worktrees share
+    let exclude = repo.path().join("info").join("exclude");
+    let current = std::fs::read_to_string(&exclude).unwrap_or_default();
+    if current.lines().any(|line| line.trim() == PATTERN || line.trim() == ".rembrandt/") {
+        return Ok(());
+    }
+    if let Some(dir) = exclude.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let separator = if current.is_empty() || current.ends_with('\n') { "" } else { "\n" };
+    std::fs::write(&exclude, format!("{}{}{}\n", current, separator, PATTERN))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::init_repo;
+    use git2::Repository;
+
+    #[test]
+    fn entries_reach_worktrees_as_read_only_ignored_copies() {
+        let dir = tempfile::tempdir().unwrap();
+        init_repo(dir.path());
+        let manager = WorktreeManager::new(dir.path()).unwrap();
+        let worktree = manager.create_worktree("a1", "main").unwrap();
+
+        assert_eq!(sync_all(dir.path()).unwrap(), 0, "no blackboard yet");
+        append(dir.path(), "a2", "Config::load takes a &Path").unwrap();
+        assert_eq!(sync_all(dir.path()).unwrap(), 1);
+        assert_eq!(sync_all(dir.path()).unwrap(), 0, "already current");
+
+        let copy = std::fs::read_to_string(path(&worktree.path)).unwrap();
+        assert!(copy.starts_with("# Blackboard"));
+        assert!(copy.contains(" — a2\n\nConfig::load takes a &Path\n"), "{}", copy);
+        assert!(std::fs::metadata(path(&worktree.path)).unwrap().permissions().readonly());
+
+        append(dir.path(), "a1", "Use anyhow in the CLI only").unwrap();
+        assert!(sync_into(dir.path(), &worktree.path).unwrap());
+        assert!(std::fs::read_to_string(path(&worktree.path)).unwrap().contains("anyhow"));
+        assert!(!sync_into(dir.path(), dir.path()).unwrap());
+
+        let checkout = Repository::open(&worktree.path).unwrap();
+        assert!(checkout.status_should_ignore(Path::new(".rembrandt/blackboard.md")).unwrap());
+    }
+}
@@ -0,0 +1,228 @@
+//! Cost and time budgets for agent sessions.
+//!
+//! `BudgetPolicy` decides whether a session is over budget; the orchestrator
+//! acts on it (`Orchestrator::enforce_budgets`): first a "wrap up now" steer,
+//! then, after the grace period, a stop that marks the session
+//! `stopped_for_budget`. Both steps land in the attention queue.
+
+use crate::config::AppConfig;
+use crate::state::SessionRecord;
+use crate::usage::UsageTotals;
+use chrono::{DateTime, Duration, Utc};
+
+/// Attention kind raised when an agent is asked to wrap up
+pub const BUDGET_WARNING: &str = "budget-warning";
+/// Attention kind raised when an agent is stopped
+pub const BUDGET_STOPPED: &str = "budget-stopped";
+
+#[derive(Debug, Clone)]
+pub struct BudgetPolicy {
+    pub session_usd: Option<f64>,
+    pub daily_usd: Option<f64>,
+    pub session_time: Option<Duration>,
+    pub wrap_up_message: Option<String>,
+    pub grace: Duration,
+}
+
+impl BudgetPolicy {
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            session_usd: config.budget_session_usd,
+            daily_usd: config.budget_daily_usd,
+            session_time: config
+                .budget_session_minutes
+                .map(|m| Duration::minutes(m as i64)),
+            wrap_up_message: config.budget_wrap_up_message.clone(),
+            grace: Duration::seconds(config.budget_grace_secs as i64),
+        }
+    }
+
+    /// Whether any budget is configured at all.
+    pub fn is_enabled(&self) -> bool {
+        self.session_usd.is_some() || self.daily_usd.is_some() || self.session_time.is_some()
+    }
+
+    /// The budget `session` is over, as a human-readable reason.
+    pub fn exceeded(
+        &self,
+        session: &SessionRecord,
+        session_usage: &UsageTotals,
+        today: &UsageTotals,
+        now: DateTime<Utc>,
+    ) -> Option<String> {
+        if let Some(limit) = self.session_usd
+            && session_usage.cost_usd >= limit
+        {
+            return Some(format!(
+                "session cost ${:.2} reached its ${:.2} budget",
+                session_usage.cost_usd, limit
+            ));
+        }
+        if let Some(limit) = self.daily_usd
+            && today.cost_usd >= limit
+        {
+            return Some(format!(
+                "today's cost ${:.2} reached the ${:.2} daily budget",
+                today.cost_usd, limit
+            ));
+        }
+        if let Some(limit) = self.session_time {
+            let elapsed = now.signed_duration_since(session.created_at);
+            if elapsed >= limit {
+                return Some(format!(
+                    "session ran {}m, over its {}m budget",
+                    elapsed.num_minutes(),
+                    limit.num_minutes()
+                ));
+            }
+        }
+        None
+    }
+}
+
+/// What `enforce_budgets` did to a session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BudgetAction {
+    /// Sent the wrap-up steer; the stop follows after the grace period
+    WrapUp { agent_id: String, reason: String },
+    Stopped { agent_id: String, reason: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::isolation::{IsolationMode, TempdirIsolation};
+    use crate::orchestrator::{Orchestrator, SpawnRequest};
+    use crate::runtime::{InMemoryRuntime, RuntimeAgentStatus};
+    use crate::state::SessionStatus;
+    use crate::test_support::session_record;
+    use crate::usage::UsageSample;
+    use std::sync::Arc;
+
+    fn spend(cost: f64) -> UsageSample {
+        UsageSample {
+            cost_usd: Some(cost),
+            source: "claude",
+            ..UsageSample::default()
+        }
+    }
+
+    #[test]
+    fn time_budget_counts_from_session_start() {
+        let policy = BudgetPolicy {
+            session_time: Some(Duration::minutes(30)),
+            ..BudgetPolicy::from_config(&AppConfig::default())
+        };
+        let session = session_record("a");
+        let none = UsageTotals::default();
+
+        assert!(policy.exceeded(&session, &none, &none, session.created_at).is_none());
+        let later = session.created_at + Duration::minutes(31);
+        assert!(policy.exceeded(&session, &none, &none, later).unwrap().contains("31m"));
+    }
+
+    #[tokio::test]
+    async fn over_budget_agent_is_warned_then_stopped() {
+        let dir = tempfile::tempdir().unwrap();
+        let isolation = TempdirIsolation::new().unwrap();
+        let orch = Arc::new(Orchestrator::new(dir.path(), InMemoryRuntime::new()).unwrap().with_isolation(isolation));
+        for agent_id in ["spender", "frugal"] {
+            orch.spawn_agent(SpawnRequest {
+                agent_id: agent_id.to_string(),
+                base_branch: "main".to_string(),
+                isolation_mode: IsolationMode::Branch,
+                prompt: None,
+                model: None,
+                task_id: None,
+            })
+            .await
+            .unwrap();
+            orch.refresh_runtime_status(agent_id).await.unwrap();
+        }
+        let store = orch.state().get().unwrap();
+        store.record_usage("spender", &spend(6.0)).unwrap();
+        store.record_usage("frugal", &spend(0.5)).unwrap();
+
+        let mut policy = BudgetPolicy {
+            session_usd: Some(5.0),
+            ..BudgetPolicy::from_config(&AppConfig::default())
+        };
+
+        // Enforcement can run from its own task alongside other orchestrator work
+        let actions = tokio::spawn({
+            let (orch, policy) = (orch.clone(), policy.clone());
+            async move { orch.enforce_budgets(&policy).await }
+        })
+        .await
+        .unwrap()
+        .unwrap();
+        assert!(matches!(&actions[..], [BudgetAction::WrapUp { agent_id, .. }] if agent_id == "spender"));
+        assert_eq!(store.open_attention().unwrap()[0].kind, BUDGET_WARNING);
+        assert_eq!(orch.runtime().session("spender").unwrap().messages.len(), 1);
+
+        // Still within the grace period: nothing more happens
+        assert!(orch.enforce_budgets(&policy).await.unwrap().is_empty());
+
+        policy.grace = Duration::zero();
+        let actions = orch.enforce_budgets(&policy).await.unwrap();
+        assert!(matches!(&actions[..], [BudgetAction::Stopped { agent_id, .. }] if agent_id == "spender"));
+
+        let session = orch.get_status("spender").await.unwrap().unwrap();
+        assert_eq!(session.status, SessionStatus::StoppedForBudget);
+        assert_eq!(orch.runtime().session("spender").unwrap().status, RuntimeAgentStatus::Stopped);
+        let open = store.open_attention().unwrap();
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].kind, BUDGET_STOPPED);
+        assert_eq!(orch.get_status("frugal").await.unwrap().unwrap().status, SessionStatus::Active);
+    }
+
+    #[tokio::test]
+    async fn a_failed_stop_is_reported_without_skipping_other_agents() {
+        let dir = tempfile::tempdir().unwrap();
+        let isolation = TempdirIsolation::new().unwrap();
+        let orch = Orchestrator::new(dir.path(), InMemoryRuntime::new()).unwrap().with_isolation(isolation);
+        for agent_id in ["stuck", "spender"] {
+            orch.spawn_agent(SpawnRequest {
+                agent_id: agent_id.to_string(),
+                base_branch: "main".to_string(),
+                isolation_mode: IsolationMode::Branch,
+                prompt: None,
+                model: None,
+                task_id: None,
+            })
+            .await
+            .unwrap();
+            orch.refresh_runtime_status(agent_id).await.unwrap();
+        }
+        let store = orch.state().get().unwrap();
+        store.record_usage("stuck", &spend(6.0)).unwrap();
+        store.record_usage("spender", &spend(6.0)).unwrap();
+        // Stopping "stuck" fails when its status is recorded, leaving it active
+        let conn = rusqlite::Connection::open(dir.path().join(".rembrandt/state.db")).unwrap();
+        conn.execute_batch(
+            "CREATE TRIGGER stuck_session BEFORE UPDATE ON sessions WHEN NEW.agent_id = 'stuck'
+             BEGIN SELECT RAISE(ABORT, 'disk I/O error'); END;",
+        )
+        .unwrap();
+
+        let policy = BudgetPolicy {
+            session_usd: Some(5.0),
+            wrap_up_message: None,
+            ..BudgetPolicy::from_config(&AppConfig::default())
+        };
+        let actions = orch.enforce_budgets(&policy).await.unwrap();
+
+        assert!(matches!(&actions[..], [BudgetAction::Stopped { agent_id, .. }] if agent_id == "spender"));
+        let open = store.open_attention().unwrap();
+        assert_eq!(open.len(), 2);
+        assert!(open.iter().all(|a| a.kind == BUDGET_STOPPED));
+        let stuck = open.iter().find(|a| a.agent_id.as_deref() == Some("stuck")).unwrap();
+        assert!(stuck.message.contains("could not stop it"), "{}", stuck.message);
+
+        assert_eq!(orch.get_status("stuck").await.unwrap().unwrap().status, SessionStatus::Active);
+
+        // The next pass retries the stop without piling up more items
+        assert!(orch.enforce_budgets(&policy).await.unwrap().is_empty());
+        assert_eq!(store.open_attention().unwrap().len(), 2);
+    }
+}
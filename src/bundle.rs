@@ -0,0 +1,639 @@
+//! Support bundles: orchestration state packed up for another machine.
+//!
+//! `rembrandt export` writes a zstd-compressed tarball of `.rembrandt`:
+//! state.db (sessions, timelines, messages, usage, merge queue...), config,
+//! review reports, plans, competition history, the blackboard, prompt
+//! templates and session logs, plus a `manifest.json`. Agent worktrees are
+//! not included.
+//!
+//! Bundles are sanitized unless asked otherwise: values of `env` tables in
+//! the config are replaced, and text in state.db and the session logs is
+//! scrubbed of those values and of anything that looks like a credential
+//! (API keys, tokens, `PASSWORD=...`). `rembrandt import` unpacks a bundle into a repository,
+//! setting aside files it would overwrite.
+
+use crate::daemon::ipc::DaemonConnection;
+use crate::daemon::session::SessionStatus as PtyStatus;
+use crate::state::{StateStore, migrations};
+use crate::worktree::main_repo_root;
+use crate::{RembrandtError, Result};
+use chrono::Utc;
+use rusqlite::{Connection, params};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// Bundle layout version written to the manifest
+pub const BUNDLE_FORMAT: u32 = 1;
+
+/// What sanitized values are replaced with
+pub const REDACTED: &str = "<redacted>";
+
+const MANIFEST: &str = "manifest.json";
+
+/// Files and directories under `.rembrandt` that go into a bundle
+/// (state.db is added separately, as a sanitized copy)
+const BUNDLED_PATHS: &[&str] = &[
+    "config.toml",
+    "blackboard.md",
+    "reports",
+    "plans",
+    "competitions",
+    "templates",
+    "logs",
+];
+
+/// Prefixes of well-known credential formats
+const SECRET_PREFIXES: &[&str] = &[
+    "sk-",
+    "sk_live_",
+    "ghp_",
+    "gho_",
+    "ghs_",
+    "ghu_",
+    "github_pat_",
+    "glpat-",
+    "xoxb-",
+    "xoxp-",
+    "AKIA",
+    "AIza",
+];
+
+/// Variable names whose values are secret
+const SECRET_NAMES: &[&str] = &["TOKEN", "SECRET", "PASSWORD", "PASSWD", "API_KEY", "APIKEY"];
+
+/// Describes a bundle; stored as `manifest.json` inside it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub format: u32,
+    pub created_at: String,
+    pub rembrandt_version: String,
+    /// state.db schema version, 0 without a database
+    pub schema_version: i64,
+    pub sanitized: bool,
+    /// Bundled paths, relative to `.rembrandt`
+    pub files: Vec<String>,
+}
+
+/// Result of an export
+#[derive(Debug, Clone)]
+pub struct ExportSummary {
+    pub manifest: BundleManifest,
+    /// Values replaced while sanitizing
+    pub redactions: usize,
+}
+
+/// Result of an import
+#[derive(Debug, Clone)]
+pub struct ImportSummary {
+    pub manifest: BundleManifest,
+    /// Where files the import replaced were moved, if any were
+    pub backup_dir: Option<PathBuf>,
+}
+
+/// Write a bundle of `repo_path`'s `.rembrandt` state to `output`
+pub fn export(repo_path: &Path, output: &Path, sanitize: bool) -> Result<ExportSummary> {
+    let rembrandt_dir = repo_path.join(".rembrandt");
+    let config_path = rembrandt_dir.join("config.toml");
+    let secrets = if sanitize && config_path.exists() {
+        config_secrets(&std::fs::read_to_string(&config_path)?)
+    } else {
+        Vec::new()
+    };
+
+    let encoder = zstd::Encoder::new(File::create(output)?, 0)?;
+    let mut tar = tar::Builder::new(encoder);
+    let mut files = Vec::new();
+    let mut redactions = 0;
+    let mut schema_version = 0;
+
+    let db_path = rembrandt_dir.join("state.db");
+    if db_path.exists() {
+        schema_version = StateStore::open(repo_path)?.schema_version()?;
+        let copy = std::env::temp_dir().join(format!("rembrandt-export-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&copy);
+        let result = (|| -> Result<()> {
+            let conn = Connection::open(&db_path)?;
+            conn.execute("VACUUM INTO ?1", params![copy.to_string_lossy()])?;
+            if sanitize {
+                redactions += sanitize_database(&Connection::open(&copy)?, &secrets)?;
+            }
+            tar.append_path_with_name(&copy, "state.db")?;
+            Ok(())
+        })();
+        let _ = std::fs::remove_file(&copy);
+        result?;
+        files.push("state.db".to_string());
+    }
+
+    for name in BUNDLED_PATHS {
+        let path = rembrandt_dir.join(name);
+        if *name == "logs" && sanitize && path.is_dir() {
+            redactions += append_sanitized_dir(&mut tar, Path::new(name), &path, &secrets)?;
+        } else if path.is_dir() {
+            tar.append_dir_all(name, &path)?;
+        } else if *name == "config.toml" && sanitize && path.exists() {
+            let (config, n) = sanitize_config(&std::fs::read_to_string(&path)?)?;
+            redactions += n;
+            append_bytes(&mut tar, name, config.as_bytes())?;
+        } else if path.is_file() {
+            tar.append_path_with_name(&path, name)?;
+        } else {
+            continue;
+        }
+        files.push(name.to_string());
+    }
+
+    let manifest = BundleManifest {
+        format: BUNDLE_FORMAT,
+        created_at: Utc::now().to_rfc3339(),
+        rembrandt_version: env!("CARGO_PKG_VERSION").to_string(),
+        schema_version,
+        sanitized: sanitize,
+        files,
+    };
+    let json = serde_json::to_vec_pretty(&manifest)
+        .map_err(|e| RembrandtError::State(format!("failed to write bundle manifest: {}", e)))?;
+    append_bytes(&mut tar, MANIFEST, &json)?;
+    tar.into_inner()?.finish()?;
+
+    Ok(ExportSummary {
+        manifest,
+        redactions,
+    })
+}
+
+/// Refuse to import into `repo_path` while `daemon` serves it: running
+/// agents and scheduled steers keep writing the state.db and logs an import
+/// replaces
+pub fn check_not_served(daemon: &mut DaemonConnection, repo_path: &Path) -> Result<()> {
+    let root = main_repo_root(repo_path).unwrap_or_else(|| repo_path.to_path_buf());
+    let running = daemon
+        .list(Some(root.clone()))?
+        .iter()
+        .filter(|session| session.status == PtyStatus::Running)
+        .count();
+    if running > 0 || daemon.schedule_repo()?.as_ref() == Some(&root) {
+        return Err(RembrandtError::Conflict(format!(
+            "the daemon is serving this repository ({} running agent(s)); stop it before importing",
+            running
+        )));
+    }
+    Ok(())
+}
+
+/// Unpack the bundle at `bundle` into `repo_path`'s `.rembrandt`
+///
+/// Without `force`, refuses to replace an existing state.db. Files that are
+/// replaced are moved to `.rembrandt/import-backup-<timestamp>/` first.
+pub fn import(repo_path: &Path, bundle: &Path, force: bool) -> Result<ImportSummary> {
+    let rembrandt_dir = repo_path.join(".rembrandt");
+    let staging = rembrandt_dir.join(format!("import-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&staging);
+    std::fs::create_dir_all(&staging)?;
+    let result = import_staged(&rembrandt_dir, &staging, bundle, force);
+    let _ = std::fs::remove_dir_all(&staging);
+    let summary = result?;
+
+    // Bring an older database up to this version's schema
+    StateStore::open(repo_path)?;
+    Ok(summary)
+}
+
+fn import_staged(
+    rembrandt_dir: &Path,
+    staging: &Path,
+    bundle: &Path,
+    force: bool,
+) -> Result<ImportSummary> {
+    tar::Archive::new(zstd::Decoder::new(File::open(bundle)?)?).unpack(staging)?;
+
+    let manifest: BundleManifest = std::fs::read(staging.join(MANIFEST))
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .ok_or_else(|| {
+            RembrandtError::State(format!("{} is not a rembrandt bundle", bundle.display()))
+        })?;
+    if manifest.format > BUNDLE_FORMAT {
+        return Err(RembrandtError::State(format!(
+            "bundle format {} is newer than this rembrandt supports ({}); upgrade rembrandt",
+            manifest.format, BUNDLE_FORMAT
+        )));
+    }
+    if manifest.schema_version > migrations::latest_version() {
+        return Err(RembrandtError::State(format!(
+            "bundle's state.db is at schema version {}, but this rembrandt only knows up to {}; upgrade rembrandt",
+            manifest.schema_version,
+            migrations::latest_version()
+        )));
+    }
+    if !force
+        && manifest.files.iter().any(|f| f == "state.db")
+        && rembrandt_dir.join("state.db").exists()
+    {
+        return Err(RembrandtError::State(
+            "this repository already has a state.db; pass --force to replace it (the current one is backed up)"
+                .to_string(),
+        ));
+    }
+
+    let backup_dir = rembrandt_dir.join(format!(
+        "import-backup-{}",
+        Utc::now().format("%Y%m%d-%H%M%S")
+    ));
+    let mut backed_up = false;
+    for name in &manifest.files {
+        let (from, to) = (staging.join(name), rembrandt_dir.join(name));
+        // Only ever write the paths a bundle is made of
+        let known = name == "state.db" || BUNDLED_PATHS.contains(&name.as_str());
+        if !known || !from.exists() {
+            continue;
+        }
+        if to.exists() {
+            std::fs::create_dir_all(&backup_dir)?;
+            std::fs::rename(&to, backup_dir.join(name))?;
+            backed_up = true;
+            // A replaced database's WAL belongs to the old one
+            if name == "state.db" {
+                for suffix in ["-wal", "-shm"] {
+                    let _ = std::fs::rename(
+                        rembrandt_dir.join(format!("state.db{}", suffix)),
+                        backup_dir.join(format!("state.db{}", suffix)),
+                    );
+                }
+            }
+        }
+        std::fs::rename(&from, &to)?;
+    }
+
+    Ok(ImportSummary {
+        manifest,
+        backup_dir: backed_up.then_some(backup_dir),
+    })
+}
+
+fn append_bytes<W: std::io::Write>(
+    tar: &mut tar::Builder<W>,
+    name: &str,
+    bytes: &[u8],
+) -> Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(bytes.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(Utc::now().timestamp().max(0) as u64);
+    header.set_cksum();
+    tar.append_data(&mut header, name, bytes)?;
+    Ok(())
+}
+
+/// Add the files under `dir` to `tar` at `name`, scrubbed like state.db;
+/// returns the number of values replaced
+fn append_sanitized_dir<W: std::io::Write>(
+    tar: &mut tar::Builder<W>,
+    name: &Path,
+    dir: &Path,
+    secrets: &[String],
+) -> Result<usize> {
+    let mut total = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let name = name.join(entry.file_name());
+        if path.is_dir() {
+            total += append_sanitized_dir(tar, &name, &path, secrets)?;
+        } else if path.is_file() {
+            let (text, n) = redact(&String::from_utf8_lossy(&std::fs::read(&path)?), secrets);
+            total += n;
+            append_bytes(tar, &name.to_string_lossy(), text.as_bytes())?;
+        }
+    }
+    Ok(total)
+}
+
+/// Values of every `env` table in a config (`[env]`, `[profiles.*.env]`)
+/// and every `token` (`[api_tokens.*]`)
+fn config_secrets(config: &str) -> Vec<String> {
+    fn collect(value: &toml::Value, in_env: bool, out: &mut Vec<String>) {
+        match value {
+            toml::Value::Table(table) => {
+                for (key, value) in table {
+                    match value {
+                        toml::Value::String(s) if key == "token" && !s.is_empty() => {
+                            out.push(s.clone())
+                        }
+                        _ => collect(value, in_env || key == "env", out),
+                    }
+                }
+            }
+            toml::Value::String(s) if in_env && s.len() >= 4 => out.push(s.clone()),
+            _ => {}
+        }
+    }
+    let mut secrets = Vec::new();
+    if let Ok(value) = config.parse::<toml::Value>() {
+        collect(&value, false, &mut secrets);
+    }
+    secrets
+}
+
+/// `config` with the values of its `env` tables and its tokens replaced
+fn sanitize_config(config: &str) -> Result<(String, usize)> {
+    fn redact_env(value: &mut toml::Value, count: &mut usize) {
+        if let toml::Value::Table(table) = value {
+            for (key, value) in table.iter_mut() {
+                match value {
+                    toml::Value::Table(env) if key == "env" => {
+                        for (_, value) in env.iter_mut() {
+                            *value = toml::Value::String(REDACTED.to_string());
+                            *count += 1;
+                        }
+                    }
+                    toml::Value::String(_) if key == "token" => {
+                        *value = toml::Value::String(REDACTED.to_string());
+                        *count += 1;
+                    }
+                    _ => redact_env(value, count),
+                }
+            }
+        }
+    }
+    let mut value: toml::Value = config
+        .parse()
+        .map_err(|e| RembrandtError::Config(format!("config.toml: {}", e)))?;
+    let mut count = 0;
+    redact_env(&mut value, &mut count);
+    let config = toml::to_string_pretty(&value)
+        .map_err(|e| RembrandtError::Config(format!("config.toml: {}", e)))?;
+    Ok((config, count))
+}
+
+/// Scrub every text column of every table in `conn`; returns the number of
+/// values replaced
+fn sanitize_database(conn: &Connection, secrets: &[String]) -> Result<usize> {
+    // The audit log's triggers refuse updates; lift them on the copy while scrubbing
+    let triggers: Vec<(String, String)> = conn
+        .prepare("SELECT name, sql FROM sqlite_master WHERE type = 'trigger'")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    for (name, _) in &triggers {
+        conn.execute_batch(&format!("DROP TRIGGER \"{}\"", name))?;
+    }
+    let total = scrub_tables(conn, secrets)?;
+    for (_, sql) in &triggers {
+        conn.execute_batch(sql)?;
+    }
+    Ok(total)
+}
+
+fn scrub_tables(conn: &Connection, secrets: &[String]) -> Result<usize> {
+    let tables: Vec<String> = conn
+        .prepare(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'",
+        )?
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut total = 0;
+    for table in tables {
+        let columns: Vec<String> = conn
+            .prepare(&format!(
+                "SELECT name, type FROM pragma_table_info('{}')",
+                table
+            ))?
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?
+            .filter_map(|column| column.ok())
+            .filter(|(_, kind)| kind.eq_ignore_ascii_case("TEXT"))
+            .map(|(name, _)| name)
+            .collect();
+        for column in columns {
+            let rows: Vec<(i64, String)> = conn
+                .prepare(&format!(
+                    "SELECT rowid, \"{column}\" FROM \"{table}\" WHERE \"{column}\" IS NOT NULL"
+                ))?
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<rusqlite::Result<_>>()?;
+            for (rowid, text) in rows {
+                let (clean, n) = redact(&text, secrets);
+                if n > 0 {
+                    conn.execute(
+                        &format!("UPDATE \"{table}\" SET \"{column}\" = ?1 WHERE rowid = ?2"),
+                        params![clean, rowid],
+                    )?;
+                    total += n;
+                }
+            }
+        }
+    }
+    Ok(total)
+}
+
+/// `text` with known secret values and credential-looking words replaced
+fn redact(text: &str, secrets: &[String]) -> (String, usize) {
+    let mut count = 0;
+    let mut text = text.to_string();
+    for secret in secrets {
+        let n = text.matches(secret.as_str()).count();
+        if n > 0 {
+            text = text.replace(secret.as_str(), REDACTED);
+            count += n;
+        }
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text.as_str();
+    while !rest.is_empty() {
+        let start = rest
+            .find(|c: char| !c.is_whitespace())
+            .unwrap_or(rest.len());
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let word = &rest[..end];
+        rest = &rest[end..];
+
+        let token = word.trim_matches(|c: char| "\"'`,;()[]{}".contains(c));
+        if SECRET_PREFIXES
+            .iter()
+            .any(|p| token.starts_with(p) && token.len() >= p.len() + 12)
+        {
+            out.push_str(&word.replace(token, REDACTED));
+            count += 1;
+        } else if let Some((name, value)) = token.split_once(['=', ':'])
+            && !value.is_empty()
+            && value != REDACTED
+            && SECRET_NAMES
+                .iter()
+                .any(|s| name.to_ascii_uppercase().contains(s))
+        {
+            out.push_str(&word.replace(token, &format!("{}={}", name, REDACTED)));
+            count += 1;
+        } else {
+            out.push_str(word);
+        }
+    }
+    (out, count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon::Daemon;
+    use crate::test_support::init_repo;
+    use std::sync::Arc;
+
+    /// A bundle holding `entries`, as `(name, contents)`
+    fn pack(path: &Path, entries: &[(&str, &[u8])]) {
+        let mut tar = tar::Builder::new(zstd::Encoder::new(File::create(path).unwrap(), 0).unwrap());
+        for (name, bytes) in entries {
+            append_bytes(&mut tar, name, bytes).unwrap();
+        }
+        tar.into_inner().unwrap().finish().unwrap();
+    }
+
+    #[test]
+    fn export_sanitizes_and_import_restores() {
+        let source = tempfile::tempdir().unwrap();
+        let rembrandt_dir = source.path().join(".rembrandt");
+        std::fs::create_dir_all(rembrandt_dir.join("reports")).unwrap();
+        std::fs::write(
+            rembrandt_dir.join("config.toml"),
+            "stop_grace_secs = 5\n\n[env]\nDEPLOY_KEY = \"hunter2-hunter2\"\n\n[api_tokens.ci]\ntoken = \"op-7f3a\"\nrole = \"operator\"\n",
+        )
+        .unwrap();
+        std::fs::write(rembrandt_dir.join("reports/agent-1.md"), "# agent-1\n").unwrap();
+        std::fs::create_dir_all(rembrandt_dir.join("logs/agent-1")).unwrap();
+        std::fs::write(rembrandt_dir.join("logs/agent-1/s1.log"), "$ deploy --key hunter2-hunter2\r\n").unwrap();
+        let store = StateStore::open(source.path()).unwrap();
+        store
+            .send_agent_message("agent-1", "agent-2", "use hunter2-hunter2 to deploy")
+            .unwrap();
+        store
+            .send_agent_message("agent-1", "agent-2", "export GITHUB_TOKEN=abc123 and retry")
+            .unwrap();
+        store
+            .record_audit(
+                crate::audit::AuditSource::Cli,
+                "ops",
+                "message",
+                Some("agent-2"),
+                Some(&serde_json::json!({ "text": "use hunter2-hunter2 to deploy" })),
+            )
+            .unwrap();
+        drop(store);
+
+        let bundle = source.path().join("bundle.tar.zst");
+        let summary = export(source.path(), &bundle, true).unwrap();
+        assert_eq!(
+            summary.manifest.files,
+            ["state.db", "config.toml", "reports", "logs"]
+        );
+        assert_eq!(summary.redactions, 6);
+
+        let target = tempfile::tempdir().unwrap();
+        let imported = import(target.path(), &bundle, false).unwrap();
+        assert!(imported.backup_dir.is_none());
+        let config = std::fs::read_to_string(target.path().join(".rembrandt/config.toml")).unwrap();
+        assert!(config.contains("stop_grace_secs = 5") && config.contains(REDACTED));
+        assert!(!config.contains("hunter2") && !config.contains("op-7f3a"));
+        assert!(config.contains("role = \"operator\""));
+        assert!(target.path().join(".rembrandt/reports/agent-1.md").exists());
+        let log = std::fs::read_to_string(target.path().join(".rembrandt/logs/agent-1/s1.log")).unwrap();
+        assert_eq!(log, "$ deploy --key <redacted>\r\n");
+
+        let store = StateStore::open(target.path()).unwrap();
+        let bodies: Vec<String> = store
+            .inbox("agent-2", false)
+            .unwrap()
+            .into_iter()
+            .map(|m| m.body)
+            .collect();
+        assert_eq!(
+            bodies,
+            [
+                "use <redacted> to deploy",
+                "export GITHUB_TOKEN=<redacted> and retry"
+            ]
+        );
+        let audit = store.audit_log(&Default::default()).unwrap();
+        assert_eq!(audit[0].params.as_ref().unwrap()["text"], "use <redacted> to deploy");
+        let conn = Connection::open(target.path().join(".rembrandt/state.db")).unwrap();
+        assert!(conn.execute("DELETE FROM audit_log", []).is_err(), "still append-only");
+        drop(store);
+
+        // A second import needs --force and keeps the replaced files
+        assert!(import(target.path(), &bundle, false).is_err());
+        let imported = import(target.path(), &bundle, true).unwrap();
+        let backup = imported.backup_dir.unwrap();
+        assert!(backup.join("state.db").exists() && backup.join("reports/agent-1.md").exists());
+    }
+
+    #[test]
+    fn corrupt_and_foreign_bundles_are_refused_without_touching_the_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = tempfile::tempdir().unwrap();
+
+        let corrupt = dir.path().join("corrupt.tar.zst");
+        std::fs::write(&corrupt, b"not a tarball at all").unwrap();
+        assert!(import(target.path(), &corrupt, true).is_err());
+
+        let foreign = dir.path().join("foreign.tar.zst");
+        pack(&foreign, &[("state.db", b"someone else's database")]);
+        let err = import(target.path(), &foreign, true).unwrap_err().to_string();
+        assert!(err.contains("is not a rembrandt bundle"), "{}", err);
+
+        let newer = dir.path().join("newer.tar.zst");
+        let manifest = serde_json::json!({
+            "format": BUNDLE_FORMAT + 1,
+            "created_at": "2026-01-01T00:00:00Z",
+            "rembrandt_version": "9.0.0",
+            "schema_version": 0,
+            "sanitized": true,
+            "files": ["config.toml"],
+        });
+        pack(&newer, &[(MANIFEST, manifest.to_string().as_bytes()), ("config.toml", b"stop_grace_secs = 1\n")]);
+        let err = import(target.path(), &newer, true).unwrap_err().to_string();
+        assert!(err.contains("upgrade rembrandt"), "{}", err);
+
+        // Nothing was unpacked or left staged
+        let rembrandt_dir = target.path().join(".rembrandt");
+        let left: Vec<_> = std::fs::read_dir(&rembrandt_dir).unwrap().collect();
+        assert!(left.is_empty(), "{:?}", left);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn import_is_refused_while_a_daemon_serves_the_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        let (repo, elsewhere) = (dir.path().join("repo"), dir.path().join("elsewhere"));
+        for path in [&repo, &elsewhere] {
+            std::fs::create_dir(path).unwrap();
+            init_repo(path);
+        }
+        let socket = dir.path().join("daemon.sock");
+        let daemon = Arc::new(Daemon::new(socket.clone()).with_schedule(repo.clone()));
+        let server = {
+            let daemon = daemon.clone();
+            tokio::spawn(async move { daemon.run().await })
+        };
+
+        tokio::task::spawn_blocking(move || {
+            let mut conn = (0..100)
+                .find_map(|_| {
+                    DaemonConnection::connect(&socket)
+                        .inspect_err(|_| std::thread::sleep(std::time::Duration::from_millis(10)))
+                        .ok()
+                })
+                .expect("daemon never came up");
+            let err = check_not_served(&mut conn, &repo).unwrap_err();
+            assert!(matches!(err, RembrandtError::Conflict(_)), "{}", err);
+            assert!(err.to_string().contains("stop it before importing"));
+            check_not_served(&mut conn, &elsewhere).unwrap();
+            conn.request(&crate::daemon::ipc::DaemonCommand::Shutdown).unwrap();
+        })
+        .await
+        .unwrap();
+
+        server.await.unwrap().unwrap();
+    }
+}
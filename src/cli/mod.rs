@@ -1,6 +1,11 @@
 //! CLI command definitions
 
+use crate::completion::{agent_ids, ready_task_ids};
+use crate::config::LogFormat;
+use crate::restart::RestartPolicy;
+use crate::state::SessionStatus;
 use clap::{Parser, Subcommand};
+use clap_complete::ArgValueCompleter;
 use std::path::PathBuf;
 
 #[derive(Parser)]
@@ -17,20 +22,31 @@ pub struct Cli {
     /// Use v2 orchestration paths for commands that support it
     #[arg(long, global = true)]
     pub v2: bool,
+
+    /// Log output: `text` or `json` (default: `log_format` in config.toml, else text)
+    #[arg(long, global = true, value_name = "FORMAT")]
+    pub log_format: Option<LogFormat>,
 }
 
 #[derive(Subcommand)]
 pub enum Commands {
     /// Initialize Rembrandt in the current repository
-    Init,
+    Init {
+        /// Also install a prepare-commit-msg hook that adds Rembrandt-Agent,
+        /// Rembrandt-Task and Rembrandt-Session trailers to agents' commits
+        #[arg(long)]
+        hooks: bool,
+    },
 
     /// Spawn a new agent in an isolated worktree
     Spawn {
-        /// Agent type (claude-code, opencode, codex, aider)
-        agent: String,
+        /// Agent type (claude-code, opencode, codex, aider); defaults to
+        /// the profile's agent with --profile
+        #[arg(required_unless_present = "profile")]
+        agent: Option<String>,
 
         /// Optional task ID from Beads to assign
-        #[arg(short, long)]
+        #[arg(short, long, add = ArgValueCompleter::new(ready_task_ids))]
         task: Option<String>,
 
         /// Base branch to create worktree from
@@ -38,7 +54,7 @@ pub enum Commands {
         branch: String,
 
         /// Continue in existing worktree (agent-id from previous session)
-        #[arg(short = 'C', long)]
+        #[arg(short = 'C', long, add = ArgValueCompleter::new(agent_ids))]
         r#continue: Option<String>,
 
         /// Initial prompt/task to send to the agent
@@ -48,22 +64,110 @@ pub enum Commands {
         /// Skip the interactive prompt for starting task
         #[arg(long)]
         no_prompt: bool,
+
+        /// Run the agent in the shared daemon and return immediately
+        #[arg(long)]
+        detach: bool,
+
+        /// Run the agent on an SSH host from `[remotes]` in .rembrandt/config.toml
+        #[arg(long)]
+        remote: Option<String>,
+
+        /// Bytes of output history to keep for late attach (overrides
+        /// `buffer_capacity` in .rembrandt/config.toml)
+        #[arg(long, value_name = "BYTES")]
+        buffer_capacity: Option<usize>,
+
+        /// Respawn the agent in its worktree when it crashes: `never`,
+        /// `on-failure` or `on-failure(N)` (needs --detach; overrides
+        /// `restart` in .rembrandt/config.toml)
+        #[arg(long, value_name = "POLICY")]
+        restart: Option<RestartPolicy>,
+
+        /// Spawn with a named profile from `[profiles]` in
+        /// .rembrandt/config.toml (agent, isolation, model, prompt template)
+        #[arg(long)]
+        profile: Option<String>,
+
+        /// Model for the agent, passed with its model flag (`[model_flags]`
+        /// in .rembrandt/config.toml); overrides the profile's
+        #[arg(long)]
+        model: Option<String>,
+
+        /// Set an environment variable for the agent (repeatable; overrides
+        /// `[env]` in .rembrandt/config.toml)
+        #[arg(long = "env", value_name = "KEY=VALUE", value_parser = parse_env_var)]
+        env: Vec<(String, String)>,
+
+        /// Agent ID to use instead of a generated one (set by `fork`)
+        #[arg(skip)]
+        id: Option<String>,
+
+        /// Timeline note on where the agent came from (set by `fork`)
+        #[arg(skip)]
+        forked_from: Option<String>,
+    },
+
+    /// Spawn a new agent from another agent's work
+    ///
+    /// The new agent gets its own worktree on a branch started at the tip
+    /// of the source agent's branch, so one half-done approach can be
+    /// taken in two directions. Uncommitted changes in the source aren't
+    /// carried over.
+    Fork {
+        /// Agent to fork
+        #[arg(add = ArgValueCompleter::new(agent_ids))]
+        source: String,
+
+        /// ID for the new agent
+        #[arg(long = "as", value_name = "NEW_AGENT")]
+        new_agent: String,
+
+        /// Agent type for the new agent (default: the source's)
+        #[arg(long)]
+        agent: Option<String>,
+
+        /// Prompt sending the new agent in its own direction
+        #[arg(short, long)]
+        prompt: Option<String>,
+
+        /// Skip the interactive prompt for starting task
+        #[arg(long)]
+        no_prompt: bool,
+
+        /// Run the agent in the shared daemon and return immediately
+        #[arg(long)]
+        detach: bool,
+
+        /// Model for the new agent (default: the source's)
+        #[arg(long)]
+        model: Option<String>,
     },
 
     /// Run agents in competition mode on the same task
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
     Compete {
+        #[command(subcommand)]
+        action: Option<CompeteAction>,
+
         /// The prompt/task for all agents to work on
-        prompt: String,
+        #[arg(required = true)]
+        prompt: Option<String>,
 
         /// Comma-separated list of agent types (e.g., claude-code,opencode,codex)
+        ///
+        /// Each entry is `AGENT[@MODEL][~TEMPERATURE][*COUNT]`, so
+        /// `claude-code*3,codex@o3~0.2` runs three Claude Code competitors
+        /// and Codex on o3 at temperature 0.2.
         #[arg(short, long, value_delimiter = ',')]
         agents: Vec<String>,
 
-        /// Evaluator strategy: metrics, model, human
+        /// Evaluator strategy: metrics, model, tournament, human, or a
+        /// comma-separated pipeline such as metrics,model,human
         #[arg(short, long, default_value = "metrics")]
         evaluator: String,
 
-        /// Model name for model evaluator
+        /// Model name for the model and tournament evaluators
         #[arg(long, default_value = "claude-3-5-sonnet")]
         model: String,
 
@@ -74,6 +178,62 @@ pub enum Commands {
         /// Base branch to create worktrees from
         #[arg(short, long, default_value = "main")]
         branch: String,
+
+        /// Stop early once a solution validates with at least this metrics score (0.0-1.0)
+        #[arg(long)]
+        early_stop_score: Option<f64>,
+
+        /// Minutes the other agents get to finish after an early stop triggers
+        #[arg(long, default_value = "0", requires = "early_stop_score")]
+        early_stop_grace: u64,
+
+        /// Benchmark command run in each valid solution; its measurement
+        /// (e.g. criterion's mean time) is scored by the metrics evaluator
+        #[arg(long)]
+        benchmark: Option<String>,
+
+        /// Weight of the benchmark next to tests, simplicity and speed
+        #[arg(long, default_value = "0.5", requires = "benchmark")]
+        benchmark_weight: f64,
+
+        /// Larger benchmark measurements are better (throughput, not time)
+        #[arg(long, requires = "benchmark")]
+        benchmark_higher_is_better: bool,
+
+        /// If every solution fails validation, re-prompt each agent with its
+        /// failures for up to this many extra rounds
+        #[arg(long, default_value = "0")]
+        retries: usize,
+    },
+
+    /// Have the planner split a feature into parallel tasks with file scopes
+    ///
+    /// Tasks become Beads issues (when `br` is available) and a manifest in
+    /// .rembrandt/plans/; with --spawn, one detached agent per task.
+    Plan {
+        /// The feature to decompose
+        description: String,
+
+        /// Most tasks to propose
+        #[arg(short = 'n', long, default_value_t = 4)]
+        tasks: usize,
+
+        /// Only write the manifest; don't create Beads issues
+        #[arg(long)]
+        manifest_only: bool,
+
+        /// Spawn this agent type (e.g. claude-code) for each task, after
+        /// confirmation
+        #[arg(long, value_name = "AGENT")]
+        spawn: Option<String>,
+
+        /// Base branch for spawned agents
+        #[arg(short, long, default_value = "main")]
+        branch: String,
+
+        /// Don't ask before spawning
+        #[arg(short, long)]
+        yes: bool,
     },
 
     /// Show status of a competition
@@ -94,12 +254,66 @@ pub enum Commands {
         /// Show detailed output
         #[arg(short, long)]
         verbose: bool,
+
+        /// Only v2 sessions with these statuses (comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        status: Vec<SessionStatus>,
+
+        /// Only v2 sessions assigned to this task
+        #[arg(long)]
+        task: Option<String>,
+
+        /// Only v2 sessions running this agent CLI, e.g. `claude` or `aider`
+        #[arg(long, value_name = "AGENT")]
+        agent_type: Option<String>,
+
+        /// Only v2 sessions started since this long ago (`30m`, `24h`, `7d`)
+        /// or an RFC 3339 time
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Only v2 sessions that are starting, active, or idle
+        #[arg(long, conflicts_with = "status")]
+        active: bool,
+
+        /// Show at most this many v2 sessions
+        #[arg(long)]
+        limit: Option<usize>,
+
+        /// Skip this many v2 sessions
+        #[arg(long, default_value = "0")]
+        offset: usize,
+
+        /// Print the matching v2 sessions as JSON lines, and nothing else
+        #[arg(long)]
+        json: bool,
     },
 
     /// Attach to an agent's terminal (zoom in)
     Attach {
         /// Agent session ID or index
+        #[arg(add = ArgValueCompleter::new(agent_ids))]
         agent: String,
+
+        /// Watch without being able to type into the session
+        #[arg(long)]
+        read_only: bool,
+    },
+
+    /// Tile every running agent in one terminal multiplexer session
+    /// (`terminal_backend`, default tmux), a pane attached to each
+    Layout {
+        /// Session to create
+        #[arg(long, default_value = "rembrandt-agents")]
+        session: String,
+
+        /// Attach panes read-only, so typing in them reaches no agent
+        #[arg(long)]
+        read_only: bool,
+
+        /// Close and rebuild the session if it already exists
+        #[arg(long)]
+        replace: bool,
     },
 
     /// Send a message to agents
@@ -108,31 +322,210 @@ pub enum Commands {
         message: String,
 
         /// Send only to specific agent
-        #[arg(short, long)]
+        #[arg(short, long, add = ArgValueCompleter::new(agent_ids))]
         to: Option<String>,
     },
 
     /// Merge an agent's work back to main
     Merge {
         /// Agent session ID
+        #[arg(add = ArgValueCompleter::new(agent_ids))]
         agent: String,
 
+        /// Branch to merge into
+        #[arg(short, long, default_value = "main")]
+        base: String,
+
         /// Skip decision check (pq check)
         #[arg(long)]
         no_check: bool,
+
+        /// Merge after a clean rebase without rerunning validation
+        #[arg(long)]
+        no_validate: bool,
+
+        /// If the rebase conflicts, start an agent of the same type in the
+        /// resolution worktree to resolve it
+        #[arg(long)]
+        spawn_resolver: bool,
+    },
+
+    /// Find active agent branches that would conflict with each other or the base
+    Conflicts {
+        /// Base branch agents will merge into
+        #[arg(short, long, default_value = "main")]
+        base: String,
+    },
+
+    /// Rebase an agent's branch onto the latest base and rerun validation
+    Sync {
+        /// Agent session ID
+        #[arg(add = ArgValueCompleter::new(agent_ids))]
+        agent: String,
+
+        /// Base branch to sync with (its upstream is fetched first)
+        #[arg(short, long, default_value = "main")]
+        base: String,
+
+        /// Merge the base into the branch instead of rebasing
+        #[arg(long)]
+        merge: bool,
+
+        /// Only update the branch, without rerunning validation
+        #[arg(long)]
+        no_validate: bool,
+    },
+
+    /// Capture an agent's worktree (commit and uncommitted changes) for rollback
+    Snapshot {
+        /// Agent session ID
+        #[arg(add = ArgValueCompleter::new(agent_ids))]
+        agent: String,
+
+        /// Note to recognize the snapshot by
+        #[arg(short, long)]
+        label: Option<String>,
+
+        /// List the agent's snapshots instead of taking one
+        #[arg(long)]
+        list: bool,
+    },
+
+    /// Roll an agent's worktree back to a snapshot
+    Restore {
+        /// Agent session ID
+        #[arg(add = ArgValueCompleter::new(agent_ids))]
+        agent: String,
+
+        /// Snapshot ID (see `rembrandt snapshot <agent> --list`)
+        snapshot: i64,
+    },
+
+    /// Write a review report for an agent to .rembrandt/reports/<agent>.md
+    Report {
+        /// Agent session ID
+        #[arg(add = ArgValueCompleter::new(agent_ids))]
+        agent: String,
+
+        /// Branch to diff against
+        #[arg(short, long, default_value = "main")]
+        base: String,
+
+        /// Skip rerunning validation in the agent's worktree
+        #[arg(long)]
+        no_validate: bool,
+    },
+
+    /// Inspect the merge queue, or queue and merge agent branches one at a time
+    Queue {
+        #[command(subcommand)]
+        action: Option<QueueAction>,
     },
 
     /// Stop an agent session
+    ///
+    /// Sends the agent's quit sequence, then SIGTERM, then SIGKILL, waiting
+    /// the grace period after each step that doesn't end it.
     Stop {
         /// Agent session ID
+        #[arg(add = ArgValueCompleter::new(agent_ids), required_unless_present = "all", conflicts_with = "all")]
+        agent: Option<String>,
+
+        /// Stop every active v2 session in this repository, or every one
+        /// with a `--status`
+        #[arg(long)]
+        all: bool,
+
+        /// With `--all`, only sessions with these statuses (comma-separated)
+        #[arg(long, value_delimiter = ',', conflicts_with = "agent")]
+        status: Vec<SessionStatus>,
+
+        /// Seconds to wait after each step (default: `stop_grace_secs`)
+        #[arg(long, value_name = "SECS")]
+        grace: Option<u64>,
+
+        /// Skip straight to SIGKILL
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Nudge a stalled agent with the next step of its nudge ladder
+    Nudge {
+        /// Agent session ID
+        #[arg(add = ArgValueCompleter::new(agent_ids), required_unless_present = "all", conflicts_with = "all")]
+        agent: Option<String>,
+
+        /// Nudge every running daemon agent in this repository, or every one
+        /// whose v2 session has a `--status`
+        #[arg(long)]
+        all: bool,
+
+        /// With `--all`, only sessions with these statuses (comma-separated)
+        #[arg(long, value_delimiter = ',', conflicts_with = "agent")]
+        status: Vec<SessionStatus>,
+    },
+
+    /// Type a message into an agent's session now, later or on a cycle
+    ///
+    /// Scheduled steers are sent by `rembrandt daemon` started in this
+    /// repository and recorded on the agent's timeline.
+    Steer {
+        /// Agent session ID
+        #[arg(add = ArgValueCompleter::new(agent_ids), required_unless_present_any = ["list", "cancel"])]
+        agent: Option<String>,
+
+        /// Message to send
+        #[arg(required_unless_present_any = ["list", "cancel"])]
+        message: Option<String>,
+
+        /// Send it at this time instead of now: `+30m`, `+2h` or an RFC 3339 time
+        #[arg(long)]
+        at: Option<String>,
+
+        /// Send it again at this interval (e.g. `30m`) while the agent lives;
+        /// the first goes out after one interval unless `--at` says otherwise
+        #[arg(long)]
+        every: Option<String>,
+
+        /// List scheduled steers (only the agent's, if given)
+        #[arg(long, conflicts_with_all = ["message", "at", "every", "cancel"])]
+        list: bool,
+
+        /// Cancel the scheduled steer with this number
+        #[arg(long, conflicts_with_all = ["agent", "message", "at", "every"])]
+        cancel: Option<i64>,
+    },
+
+    /// Pause an agent, stopping its processes until `rembrandt resume`
+    ///
+    /// The agent keeps its context and PTY; it uses no CPU and makes no
+    /// API calls while paused.
+    Pause {
+        /// Agent session ID
+        #[arg(add = ArgValueCompleter::new(agent_ids))]
+        agent: String,
+    },
+
+    /// Resume a paused agent
+    Resume {
+        /// Agent session ID
+        #[arg(add = ArgValueCompleter::new(agent_ids))]
         agent: String,
     },
 
     /// Clean up completed agent worktrees
+    ///
+    /// Worktrees with uncommitted or unpushed work are skipped unless
+    /// `--force` is given; their uncommitted changes are then saved as a
+    /// snapshot first.
     Cleanup {
         /// Remove all worktrees (including active)
         #[arg(long)]
         all: bool,
+
+        /// Remove worktrees holding uncommitted or unpushed work too
+        #[arg(long)]
+        force: bool,
     },
 
     /// Garbage collect orphaned worktrees (no active session)
@@ -140,11 +533,446 @@ pub enum Commands {
         /// Dry run - show what would be cleaned without deleting
         #[arg(long)]
         dry_run: bool,
+
+        /// Remove worktrees holding uncommitted or unpushed work too
+        #[arg(long)]
+        force: bool,
     },
 
     /// Launch the TUI dashboard
     Dashboard,
 
+    /// Run the session daemon so agents outlive the TUI and GUI
+    Daemon {
+        #[command(subcommand)]
+        action: Option<DaemonAction>,
+
+        /// Socket path (defaults to $XDG_RUNTIME_DIR/rembrandt.sock)
+        #[arg(long, global = true)]
+        socket: Option<PathBuf>,
+
+        /// Serve Prometheus metrics at http://<addr>/metrics (e.g. 127.0.0.1:9464)
+        #[arg(long, global = true)]
+        metrics_addr: Option<std::net::SocketAddr>,
+
+        /// Also accept clients over TCP at PORT on loopback or at ADDR:PORT;
+        /// they must present a token from `[api_tokens]`, and addresses other
+        /// than loopback need `[listen_tls]`
+        #[arg(long, global = true, value_name = "[ADDR:]PORT", value_parser = parse_listen_addr)]
+        listen: Option<std::net::SocketAddr>,
+    },
+
     /// Show status of all integrations
+    Status {
+        /// Print only agent counts, e.g. `3▶ 1⚠ 2✔` (running, needing
+        /// attention, completed), for shell prompts and tmux status lines
+        #[arg(long)]
+        oneline: bool,
+    },
+
+    /// Check the environment: repo, agent CLIs, tools, daemon, state.db, leftovers
+    Doctor {
+        /// Apply safe repairs (migrate state.db, drop a stale socket, prune
+        /// missing worktrees, delete merged orphan branches)
+        #[arg(long)]
+        fix: bool,
+
+        /// Base branch agents fork from
+        #[arg(short, long, default_value = "main")]
+        base: String,
+
+        /// Daemon socket path (defaults to $XDG_RUNTIME_DIR/rembrandt.sock)
+        #[arg(long)]
+        socket: Option<PathBuf>,
+    },
+
+    /// Pack state.db, config, reports, plans and competition history into a
+    /// support bundle, with secrets redacted
+    Export {
+        /// Bundle to write
+        #[arg(short, long, default_value = "rembrandt-bundle.tar.zst")]
+        output: PathBuf,
+
+        /// Keep env values and credential-looking text (e.g. to move a
+        /// workshop to another host you control)
+        #[arg(long)]
+        no_sanitize: bool,
+    },
+
+    /// Restore a bundle written by `rembrandt export` into this repository
+    Import {
+        /// Bundle to read
+        bundle: PathBuf,
+
+        /// Replace an existing state.db; replaced files are backed up
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Print a refreshing status table (or `--stream` events) without the TUI
+    ///
+    /// Exits 3 if an agent failed, 2 if one needs attention, 0 otherwise.
+    Watch {
+        /// Print one line per event instead of redrawing the table
+        #[arg(long)]
+        stream: bool,
+
+        /// Seconds between polls
+        #[arg(long, default_value_t = 2)]
+        interval: u64,
+
+        /// Print the table once and exit
+        #[arg(long, conflicts_with = "stream")]
+        once: bool,
+
+        /// Exit once no agent is active
+        #[arg(long)]
+        until_idle: bool,
+    },
+
+    /// Show items that need a human (e.g. agents stopped for budget)
+    Attention {
+        /// Mark this item as handled
+        #[arg(long)]
+        resolve: Option<i64>,
+    },
+
+    /// Show an agent's activity timeline (spawn, output, tools, commits, nudges)
+    Timeline {
+        /// Agent ID
+        #[arg(add = ArgValueCompleter::new(agent_ids))]
+        agent: String,
+    },
+
+    /// Show a Claude agent's conversation: prompts, replies, tool calls and
+    /// results, read from its session files or stream-json output
+    Transcript {
+        /// Agent ID
+        #[arg(add = ArgValueCompleter::new(agent_ids))]
+        agent: String,
+
+        /// Only the most recent turns
+        #[arg(short = 'n', long, value_name = "N")]
+        tail: Option<usize>,
+
+        /// Print entries as JSON lines
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Search session output logs and transcripts with a regular expression,
+    /// reporting hits per session with excerpts
+    Search {
+        /// Regular expression, e.g. `error\[E\d+\]`
+        pattern: String,
+
+        /// Match case-insensitively
+        #[arg(short = 'i', long)]
+        ignore_case: bool,
+
+        /// Only this agent's sessions
+        #[arg(long, add = ArgValueCompleter::new(agent_ids))]
+        agent: Option<String>,
+
+        /// Only sessions active since this long ago (`30m`, `24h`, `7d`) or an RFC 3339 time
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Only sessions active before this long ago or an RFC 3339 time
+        #[arg(long)]
+        until: Option<String>,
+
+        /// Excerpts to show per session
+        #[arg(short = 'm', long, default_value_t = 5)]
+        max_per_session: usize,
+
+        /// Print results as JSON lines
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Leave messages in agents' inboxes (state.db); each is typed into the
+    /// agent's session the next time `rembrandt csi run` finds it idle
+    Msg {
+        #[command(subcommand)]
+        action: MsgAction,
+    },
+
+    /// List follow-up work agents marked with `TODO(rembrandt): ...` or a
+    /// fenced `follow-up` block; `rembrandt csi run` files them in Beads
+    Followups {
+        /// Only this agent's
+        #[arg(add = ArgValueCompleter::new(agent_ids))]
+        agent: Option<String>,
+
+        /// Only those not filed as issues yet
+        #[arg(long)]
+        pending: bool,
+    },
+
+    /// Show the audit log of state-changing operations (spawn, kill, steer,
+    /// merge, task transitions) with who triggered them and from where
+    Audit {
+        /// Only this action, e.g. `spawn` or `merge`
+        #[arg(long)]
+        action: Option<String>,
+
+        /// Only entries about this agent
+        #[arg(long, add = ArgValueCompleter::new(agent_ids))]
+        agent: Option<String>,
+
+        /// Only entries since this long ago (`30m`, `24h`, `7d`) or an RFC 3339 time
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Show at most this many of the most recent entries
+        #[arg(long, default_value_t = 50)]
+        limit: usize,
+
+        /// Print entries as JSON lines
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show how many commits and lines on a branch came from which agent
+    /// types and agents, by commit trailer or the agent branch they came in on
+    BlameReport {
+        /// Branch whose history to attribute (default: the main checkout's)
+        #[arg(long)]
+        base: Option<String>,
+
+        /// Only commits since this long ago (`30m`, `24h`, `7d`) or an RFC 3339 time
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Only commits until this long ago or an RFC 3339 time
+        #[arg(long)]
+        until: Option<String>,
+
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show the shared blackboard, append to it, or sync it into worktrees
+    Blackboard {
+        #[command(subcommand)]
+        action: Option<BlackboardAction>,
+    },
+
+    /// Let one agent oversee others: send it digests of its apprentices and
+    /// forward its `STEER <agent>: <message>` replies to them
+    Master {
+        /// Agent ID of the master
+        #[arg(add = ArgValueCompleter::new(agent_ids))]
+        master: String,
+
+        /// Apprentice agent IDs (default: every other active session)
+        #[arg(add = ArgValueCompleter::new(agent_ids))]
+        apprentices: Vec<String>,
+
+        /// Branch apprentices' diffs are taken against
+        #[arg(short, long, default_value = "main")]
+        base: String,
+
+        /// Seconds between passes (default: `csi_poll_interval_secs`)
+        #[arg(long)]
+        interval: Option<u64>,
+
+        /// Run a single pass and exit
+        #[arg(long)]
+        once: bool,
+    },
+
+    /// Manage the warm pool of idle agents that detached spawns take
+    Pool {
+        #[command(subcommand)]
+        action: PoolAction,
+    },
+
+    /// Maintain `.rembrandt/state.db`
+    State {
+        #[command(subcommand)]
+        action: StateAction,
+    },
+
+    /// Continuous Studio Inspection: supervise v2 sessions
+    Csi {
+        #[command(subcommand)]
+        action: CsiAction,
+    },
+
+    /// Print a shell completion script (bash, zsh, fish, elvish, powershell)
+    ///
+    /// The script asks `rembrandt` for candidates on each TAB, so agent ids
+    /// and ready Beads tasks complete live, e.g. `source <(rembrandt
+    /// completions bash)` in ~/.bashrc.
+    Completions {
+        /// Shell to generate the script for
+        shell: String,
+    },
+
+    /// Add Rembrandt trailers to an agent's commit message (run by the hook
+    /// `rembrandt init --hooks` installs)
+    #[command(hide = true)]
+    CommitTrailers {
+        /// The commit message file git is about to use
+        message_file: PathBuf,
+    },
+
+    /// Play a scripted agent (say:TEXT, sleep:MS, read, commit:PATH=CONTENT,
+    /// exit:CODE) for tests; steps default to $REMBRANDT_FAKE_SCRIPT
+    #[command(hide = true)]
+    FakeAgent {
+        /// Steps to play, in order
+        steps: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum BlackboardAction {
+    /// Append an entry and sync it into every agent worktree
+    Add {
+        /// Entry text (markdown)
+        text: String,
+
+        /// Who the entry is from (default: $REMBRANDT_AGENT_ID, else `human`)
+        #[arg(long = "as", value_name = "NAME")]
+        author: Option<String>,
+    },
+
+    /// Copy the blackboard into every agent worktree
+    Sync,
+}
+
+#[derive(Subcommand)]
+pub enum MsgAction {
+    /// Put a message in an agent's inbox
+    Send {
+        /// Sender: an agent ID, or any name such as `human`
+        from: String,
+
+        /// Recipient agent ID
+        #[arg(add = ArgValueCompleter::new(agent_ids))]
+        to: String,
+
+        /// Message text
+        text: String,
+    },
+
+    /// Show an agent's messages
+    Inbox {
+        /// Agent ID
+        #[arg(add = ArgValueCompleter::new(agent_ids))]
+        agent: String,
+
+        /// Only messages not yet delivered
+        #[arg(long)]
+        pending: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum CompeteAction {
+    /// Past competitions with winners, scores, durations and costs, and
+    /// win rates per agent type and kind of task
+    History {
+        /// Number of competitions to list
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: usize,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum QueueAction {
+    /// Queue an agent's branch for merging
+    Add {
+        /// Agent session ID
+        #[arg(add = ArgValueCompleter::new(agent_ids))]
+        agent: String,
+    },
+
+    /// Take an agent's branch off the queue
+    Remove {
+        /// Agent session ID
+        #[arg(add = ArgValueCompleter::new(agent_ids))]
+        agent: String,
+    },
+
+    /// Rebase, revalidate and merge queued branches in order
+    Run {
+        /// Branch to merge into
+        #[arg(short, long, default_value = "main")]
+        base: String,
+
+        /// Merge right after rebasing, without type checks and tests
+        #[arg(long)]
+        no_validate: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum CsiAction {
+    /// Inspect sessions every `csi_poll_interval_secs` and remediate
+    Run {
+        /// Run a single pass and exit
+        #[arg(long)]
+        once: bool,
+    },
+
+    /// Show recent CSI runs and their findings
+    Report {
+        /// Number of runs to show
+        #[arg(short, long, default_value = "5")]
+        limit: usize,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum DaemonAction {
+    /// Run the daemon and restart it whenever it crashes, rerunning
+    /// reconciliation, until it shuts down cleanly
+    Supervise {
+        /// Give up after this many crashes in a row (default: keep restarting)
+        #[arg(long)]
+        max_restarts: Option<u32>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum PoolAction {
+    /// Start idle agents until the pool has the sizes set under `[pool]`
+    Fill,
+    /// List the idle agents waiting in the pool
     Status,
+    /// Stop every pooled agent and remove its worktree
+    Drain,
+}
+
+#[derive(Subcommand)]
+pub enum StateAction {
+    /// Run SQLite's integrity check over state.db and report what's damaged
+    Verify {
+        /// Fix damage: rebuild indexes, copy out what's readable, or restore
+        /// `state.db.bak`; stop other rembrandt processes first
+        #[arg(long)]
+        repair: bool,
+    },
+}
+
+/// `--listen` value: a bare port binds loopback
+fn parse_listen_addr(s: &str) -> Result<std::net::SocketAddr, String> {
+    if let Ok(port) = s.parse::<u16>() {
+        return Ok(std::net::SocketAddr::from(([127, 0, 0, 1], port)));
+    }
+    s.parse()
+        .map_err(|_| format!("expected PORT or ADDR:PORT, e.g. 7878 or 127.0.0.1:7878, got '{}'", s))
+}
+
+/// Parse a `KEY=VALUE` pair for `--env`
+fn parse_env_var(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected KEY=VALUE, got '{}'", s)),
+    }
 }
@@ -0,0 +1,138 @@
+//! Shell completions.
+//!
+//! `rembrandt completions <shell>` prints a registration script that calls
+//! back into `rembrandt` (with `COMPLETE=<shell>` set) on every TAB, so agent
+//! ids and Beads task ids come from the live state: state.db, the daemon, the
+//! agent worktrees and `br ready`. Generating the script on shell startup
+//! keeps it in step with the installed binary.
+
+use crate::cli::Cli;
+use crate::daemon::ipc::{default_socket_path, DaemonConnection};
+use crate::config::AppConfig;
+use crate::state::StateStore;
+use crate::worktree::main_repo_root;
+use crate::{RembrandtError, Result};
+use clap::CommandFactory;
+use clap_complete::engine::CompletionCandidate;
+use clap_complete::env::Shells;
+use clap_complete::CompleteEnv;
+use std::ffi::OsStr;
+use std::path::Path;
+
+/// Environment variable the registration scripts set when asking for
+/// completions
+pub const COMPLETE_VAR: &str = "COMPLETE";
+
+/// Answer a completion request and exit, if this run is one
+///
+/// Must run before anything is printed.
+pub fn handle_request() {
+    CompleteEnv::with_factory(Cli::command).var(COMPLETE_VAR).complete();
+}
+
+/// Write the registration script for `shell` (bash, zsh, fish, elvish,
+/// powershell)
+pub fn write_registration(shell: &str, buf: &mut dyn std::io::Write) -> Result<()> {
+    let shells = Shells::builtins();
+    let completer = shells.completer(shell).ok_or_else(|| {
+        RembrandtError::Validation(format!(
+            "unsupported shell '{}' (expected one of: {})",
+            shell,
+            shells.names().collect::<Vec<_>>().join(", ")
+        ))
+    })?;
+    let exe = std::env::current_exe()?;
+    completer.write_registration(COMPLETE_VAR, "rembrandt", "rembrandt", &exe.to_string_lossy(), buf)?;
+    Ok(())
+}
+
+/// Agents known for the repository containing `path`, each with a status
+///
+/// Reads state.db only if it already exists, so completing never creates it.
+pub fn known_agents(path: &Path) -> Vec<(String, String)> {
+    let Some(root) = main_repo_root(path) else {
+        return Vec::new();
+    };
+    let mut agents: Vec<(String, String)> = Vec::new();
+    let mut add = |agent_id: String, status: String| {
+        if !agents.iter().any(|(id, _)| *id == agent_id) {
+            agents.push((agent_id, status));
+        }
+    };
+
+    if root.join(".rembrandt/state.db").exists()
+        && let Ok(sessions) = StateStore::open(&root).and_then(|store| store.list_sessions())
+    {
+        for session in sessions {
+            add(session.agent_id, session.status.to_string());
+        }
+    }
+    if let Ok(sessions) =
+        DaemonConnection::connect(&default_socket_path()).and_then(|mut daemon| daemon.list(Some(root.clone())))
+    {
+        for session in sessions {
+            add(session.agent_id, "pty".to_string());
+        }
+    }
+    let mut agents_dirs = vec![root.join(".rembrandt/agents")];
+    if let Ok(config) = AppConfig::load(&root) {
+        agents_dirs.extend(config.worktree.worktrees_dir(&root));
+    }
+    for entries in agents_dirs.iter().filter_map(|dir| std::fs::read_dir(dir).ok()) {
+        for entry in entries.flatten() {
+            if entry.path().is_dir() {
+                add(entry.file_name().to_string_lossy().into_owned(), "worktree".to_string());
+            }
+        }
+    }
+    agents
+}
+
+/// Completer for agent id arguments
+pub fn agent_ids(current: &OsStr) -> Vec<CompletionCandidate> {
+    let current = current.to_string_lossy();
+    let cwd = std::env::current_dir().unwrap_or_default();
+    known_agents(&cwd)
+        .into_iter()
+        .filter(|(id, _)| id.starts_with(current.as_ref()))
+        .map(|(id, status)| CompletionCandidate::new(id).help(Some(status.into())))
+        .collect()
+}
+
+/// Completer for `--task`: Beads tasks that are ready to work on
+pub fn ready_task_ids(current: &OsStr) -> Vec<CompletionCandidate> {
+    let current = current.to_string_lossy();
+    let cwd = std::env::current_dir().unwrap_or_default();
+    let repo = main_repo_root(&cwd).unwrap_or(cwd);
+    AppConfig::load(&repo)
+        .unwrap_or_default()
+        .beads
+        .client(&repo)
+        .ready_tasks()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|task| task.id.starts_with(current.as_ref()))
+        .map(|task| CompletionCandidate::new(task.id).help(Some(task.title.into())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn agents_come_from_state_and_worktrees_without_creating_state() {
+        let dir = tempfile::tempdir().unwrap();
+        git2::Repository::init(dir.path()).unwrap();
+        std::fs::create_dir_all(dir.path().join(".rembrandt/agents/claude-1")).unwrap();
+
+        let agents = known_agents(dir.path());
+        assert!(agents.contains(&("claude-1".to_string(), "worktree".to_string())), "{:?}", agents);
+        assert!(!dir.path().join(".rembrandt/state.db").exists());
+
+        let mut script = Vec::new();
+        write_registration("bash", &mut script).unwrap();
+        assert!(String::from_utf8(script).unwrap().contains("COMPLETE"));
+        assert!(write_registration("tcsh", &mut Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,251 @@
+//! Conflict pre-check across active agent branches.
+//!
+//! Every pair of active agent branches, and each branch against the base, is
+//! merged in memory (`merge_trees` from their merge base) without touching
+//! any ref or checkout. Pairs whose merge would conflict are reported with
+//! the conflicting files, along with files both sides changed that still
+//! merge cleanly today, so the operator can step in before merge time.
+//! Paths matching `.rembrandtignore` (lockfiles and other generated files)
+//! are left out.
+
+use crate::ignore::IgnoreRules;
+use crate::state::StateStore;
+use crate::worktree::WorktreeManager;
+use crate::Result;
+use git2::{Commit, Oid, Repository};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// A pair of branches whose changes touch the same files
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Collision {
+    /// Agent ID, or the base branch name
+    pub left: String,
+    pub right: String,
+    /// Files an actual merge would conflict on
+    pub conflicts: Vec<String>,
+    /// Files both sides changed that still merge cleanly
+    pub overlapping: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ConflictReport {
+    /// Agents whose branches were checked
+    pub agents: Vec<String>,
+    /// Agents against the base branch
+    pub with_base: Vec<Collision>,
+    /// Agents against each other
+    pub between_agents: Vec<Collision>,
+}
+
+impl ConflictReport {
+    pub fn has_conflicts(&self) -> bool {
+        self.with_base
+            .iter()
+            .chain(&self.between_agents)
+            .any(|c| !c.conflicts.is_empty())
+    }
+}
+
+/// Agent branches to check: active sessions plus untracked agent worktrees
+///
+/// Worktrees whose recorded session has finished are left out.
+pub fn active_agent_branches(repo_path: &Path, store: &StateStore) -> Result<BTreeMap<String, String>> {
+    let sessions = store.list_sessions()?;
+    let mut branches: BTreeMap<String, String> = sessions
+        .iter()
+        .filter(|s| s.status.is_active())
+        .map(|s| (s.agent_id.clone(), s.branch_name.clone()))
+        .collect();
+    for worktree in WorktreeManager::new(repo_path)?.list_worktrees()? {
+        if !sessions.iter().any(|s| s.agent_id == worktree.agent_id) {
+            branches.entry(worktree.agent_id).or_insert(worktree.branch);
+        }
+    }
+    Ok(branches)
+}
+
+/// Merge every pair of `branches` (agent ID to branch) and each against `base` in memory
+#[tracing::instrument(skip(branches))]
+pub fn check(repo_path: &Path, base: &str, branches: &BTreeMap<String, String>) -> Result<ConflictReport> {
+    let repo = Repository::open(repo_path)?;
+    let ignore = IgnoreRules::load(repo_path);
+    let base_tip = repo
+        .find_branch(base, git2::BranchType::Local)?
+        .get()
+        .peel_to_commit()?;
+
+    // Branches that don't resolve (e.g. deleted by hand) are skipped
+    let tips: Vec<(String, Commit<'_>)> = branches
+        .iter()
+        .filter_map(|(agent_id, branch)| {
+            let tip = repo
+                .find_branch(branch, git2::BranchType::Local)
+                .ok()?
+                .get()
+                .peel_to_commit()
+                .ok()?;
+            Some((agent_id.clone(), tip))
+        })
+        .collect();
+
+    let mut report = ConflictReport {
+        agents: tips.iter().map(|(agent_id, _)| agent_id.clone()).collect(),
+        ..Default::default()
+    };
+
+    for (agent_id, tip) in &tips {
+        if let Some(collision) = collide(&repo, &ignore, (base, &base_tip), (agent_id, tip))? {
+            report.with_base.push(collision);
+        }
+    }
+    for (i, (left_id, left)) in tips.iter().enumerate() {
+        for (right_id, right) in &tips[i + 1..] {
+            if let Some(collision) = collide(&repo, &ignore, (left_id, left), (right_id, right))? {
+                report.between_agents.push(collision);
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Merge two commits in memory; `None` if their changes don't overlap
+/// outside ignored paths
+fn collide(
+    repo: &Repository,
+    ignore: &IgnoreRules,
+    (left_name, left): (&str, &Commit<'_>),
+    (right_name, right): (&str, &Commit<'_>),
+) -> Result<Option<Collision>> {
+    let Ok(base_id) = repo.merge_base(left.id(), right.id()) else {
+        // Unrelated histories have nothing in common to compare
+        return Ok(None);
+    };
+    let ancestor = repo.find_commit(base_id)?.tree()?;
+
+    let left_files = changed_files(repo, base_id, left.id())?;
+    let right_files = changed_files(repo, base_id, right.id())?;
+    let touched: BTreeSet<&String> = left_files
+        .intersection(&right_files)
+        .filter(|path| !ignore.is_ignored(path))
+        .collect();
+    if touched.is_empty() {
+        return Ok(None);
+    }
+
+    let index = repo.merge_trees(&ancestor, &left.tree()?, &right.tree()?, None)?;
+    let mut conflicts = BTreeSet::new();
+    if index.has_conflicts() {
+        for conflict in index.conflicts()? {
+            let conflict = conflict?;
+            if let Some(entry) = conflict.our.or(conflict.their).or(conflict.ancestor) {
+                let path = String::from_utf8_lossy(&entry.path).into_owned();
+                if !ignore.is_ignored(&path) {
+                    conflicts.insert(path);
+                }
+            }
+        }
+    }
+    let overlapping = touched
+        .into_iter()
+        .filter(|path| !conflicts.contains(*path))
+        .cloned()
+        .collect();
+
+    Ok(Some(Collision {
+        left: left_name.to_string(),
+        right: right_name.to_string(),
+        conflicts: conflicts.into_iter().collect(),
+        overlapping,
+    }))
+}
+
+/// Paths changed between two commits
+fn changed_files(repo: &Repository, from: Oid, to: Oid) -> Result<BTreeSet<String>> {
+    let diff = repo.diff_tree_to_tree(
+        Some(&repo.find_commit(from)?.tree()?),
+        Some(&repo.find_commit(to)?.tree()?),
+        None,
+    )?;
+    Ok(diff
+        .deltas()
+        .filter_map(|delta| delta.new_file().path().or_else(|| delta.old_file().path()))
+        .map(|path| path.to_string_lossy().into_owned())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::init_repo;
+    use git2::Signature;
+
+    fn commit_file(repo: &Repository, branch: &str, name: &str, contents: &str) {
+        let sig = Signature::now("test", "test@example.com").unwrap();
+        let parent = repo
+            .find_branch(branch, git2::BranchType::Local)
+            .ok()
+            .map(|b| b.get().peel_to_commit().unwrap());
+        let mut builder = repo
+            .treebuilder(parent.as_ref().map(|p| p.tree().unwrap()).as_ref())
+            .unwrap();
+        builder.insert(name, repo.blob(contents.as_bytes()).unwrap(), 0o100644).unwrap();
+        let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+        let parents: Vec<_> = parent.iter().collect();
+        repo.commit(Some(&format!("refs/heads/{}", branch)), &sig, &sig, name, &tree, &parents)
+            .unwrap();
+    }
+
+    #[test]
+    fn reports_conflicting_and_overlapping_branches() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = init_repo(dir.path());
+        commit_file(&repo, "main", "shared.txt", "one\ntwo\nthree\n");
+        commit_file(&repo, "main", "config.txt", "a = 1\n");
+
+        let worktrees = WorktreeManager::new(dir.path()).unwrap();
+        for agent in ["a", "b", "c"] {
+            worktrees.create_worktree(agent, "main").unwrap();
+        }
+        // a and b rewrite the same line; c only appends to shared.txt
+        commit_file(&repo, "rembrandt/a", "shared.txt", "ONE\ntwo\nthree\n");
+        commit_file(&repo, "rembrandt/b", "shared.txt", "uno\ntwo\nthree\n");
+        commit_file(&repo, "rembrandt/c", "shared.txt", "one\ntwo\nthree\nfour\n");
+        // main moved on config.txt, which c also edits
+        commit_file(&repo, "main", "config.txt", "a = 2\n");
+        commit_file(&repo, "rembrandt/c", "config.txt", "a = 3\n");
+
+        let store = StateStore::open(dir.path()).unwrap();
+        let branches = active_agent_branches(dir.path(), &store).unwrap();
+        assert_eq!(branches.len(), 3);
+        let report = check(dir.path(), "main", &branches).unwrap();
+        assert!(report.has_conflicts());
+
+        assert_eq!(
+            report.with_base,
+            vec![Collision {
+                left: "main".to_string(),
+                right: "c".to_string(),
+                conflicts: vec!["config.txt".to_string()],
+                overlapping: vec![],
+            }]
+        );
+
+        let pair = |left: &str, right: &str| {
+            report
+                .between_agents
+                .iter()
+                .find(|c| c.left == left && c.right == right)
+                .cloned()
+        };
+        assert_eq!(pair("a", "b").unwrap().conflicts, vec!["shared.txt".to_string()]);
+        let a_c = pair("a", "c").unwrap();
+        assert!(a_c.conflicts.is_empty());
+        assert_eq!(a_c.overlapping, vec!["shared.txt".to_string()]);
+
+        // Ignored files don't count as collisions
+        std::fs::write(dir.path().join(crate::ignore::IGNORE_FILE), "config.txt\n").unwrap();
+        assert!(check(dir.path(), "main", &branches).unwrap().with_base.is_empty());
+    }
+}
@@ -0,0 +1,489 @@
+//! Continuous Studio Inspection (CSI): periodic supervision of v2 sessions.
+//!
+//! Each pass inspects every live session in `state.db` (status, heartbeat
+//! age, output growth), records a `csi_runs` row with one `csi_events` row
+//! per finding, and applies the remediations configured in
+//! `csi_remediations`. Output growth counts as activity, so a pass refreshes
+//! the heartbeat of any agent that has written since the previous pass.
+//! Passes also feed the activity timeline: first output, tool runs seen in
+//! the output buffer, new commits on the agent's branch, and nudges.
+
+use crate::agent::NudgeStrategy;
+use crate::audit::AuditSource;
+use crate::config::{AppConfig, CsiRemediation};
+use crate::daemon::ipc::DaemonConnection;
+use crate::daemon::SessionStatus as PtyStatus;
+use crate::state::{SessionStatus, StateStore};
+use crate::timeline::{TimelineKind, TimelineObserver};
+use crate::{RembrandtError, Result};
+use chrono::Utc;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// What a pass noticed about one agent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FindingKind {
+    /// No heartbeat or output for longer than `csi_stale_after_secs`
+    Stale,
+    /// No new output since the previous pass
+    Silent,
+    /// state.db says the agent is live, but no session is running
+    Missing,
+}
+
+impl FindingKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FindingKind::Stale => "stale",
+            FindingKind::Silent => "silent",
+            FindingKind::Missing => "missing",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub agent_id: String,
+    pub kind: FindingKind,
+    pub message: String,
+}
+
+/// Outcome of a single pass.
+#[derive(Debug, Clone)]
+pub struct CsiPassReport {
+    pub run_id: i64,
+    pub inspected: usize,
+    pub findings: Vec<Finding>,
+    pub remediations: usize,
+    /// Inbox messages typed into idle agents' sessions
+    pub delivered: usize,
+}
+
+impl CsiPassReport {
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "inspected {} session(s), {} finding(s), {} remediation(s)",
+            self.inspected,
+            self.findings.len(),
+            self.remediations
+        );
+        if self.delivered > 0 {
+            summary.push_str(&format!(", {} message(s) delivered", self.delivered));
+        }
+        summary
+    }
+}
+
+/// Live view of running agents, used for output growth and nudges.
+pub trait SessionProbe {
+    /// Output bytes captured so far, or `None` if the agent has no running session
+    fn output_bytes(&mut self, agent_id: &str) -> Result<Option<usize>>;
+
+    /// Buffered output and the stream offset of its first byte
+    fn recent_output(&mut self, _agent_id: &str) -> Result<Option<(Vec<u8>, usize)>> {
+        Ok(None)
+    }
+
+    /// Nudge the agent, returning a description of the strategy used
+    fn nudge(&mut self, agent_id: &str) -> Result<String>;
+
+    /// Type a message into the agent's session and submit it
+    fn send_message(&mut self, agent_id: &str, _message: &str) -> Result<()> {
+        Err(RembrandtError::Runtime(format!("cannot send messages to {}", agent_id)))
+    }
+}
+
+/// Probe backed by the shared daemon, scoped to one repository.
+pub struct DaemonProbe {
+    conn: DaemonConnection,
+    repo: PathBuf,
+}
+
+impl DaemonProbe {
+    pub fn new(conn: DaemonConnection, repo: PathBuf) -> Self {
+        Self { conn, repo }
+    }
+
+    fn running_session(&mut self, agent_id: &str) -> Result<Option<crate::daemon::SessionInfo>> {
+        let sessions = self.conn.list_by_agent(agent_id, Some(self.repo.clone()))?;
+        Ok(sessions
+            .into_iter()
+            .filter(|s| s.status == PtyStatus::Running)
+            .max_by_key(|s| s.created_at))
+    }
+}
+
+impl SessionProbe for DaemonProbe {
+    fn output_bytes(&mut self, agent_id: &str) -> Result<Option<usize>> {
+        Ok(self.running_session(agent_id)?.map(|s| s.output_bytes))
+    }
+
+    fn recent_output(&mut self, agent_id: &str) -> Result<Option<(Vec<u8>, usize)>> {
+        match self.running_session(agent_id)? {
+            Some(session) => Ok(Some(self.conn.history(&session.id)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn nudge(&mut self, agent_id: &str) -> Result<String> {
+        let session = self
+            .running_session(agent_id)?
+            .ok_or_else(|| RembrandtError::SessionNotFound(agent_id.to_string()))?;
+        self.conn.nudge(&session.id)
+    }
+
+    fn send_message(&mut self, agent_id: &str, message: &str) -> Result<()> {
+        let session = self
+            .running_session(agent_id)?
+            .ok_or_else(|| RembrandtError::SessionNotFound(agent_id.to_string()))?;
+        for chunk in NudgeStrategy::SendMessage(message.to_string()).keystrokes() {
+            self.conn.write(&session.id, chunk)?;
+            // Let the agent read the text before the Enter arrives
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        Ok(())
+    }
+}
+
+/// Type `agent_id`'s pending messages into its session, oldest first;
+/// returns how many were delivered.
+pub fn deliver_inbox<P: SessionProbe + ?Sized>(store: &StateStore, agent_id: &str, probe: &mut P) -> Result<usize> {
+    let mut delivered = 0;
+    for message in store.inbox(agent_id, true)? {
+        if let Err(e) = probe.send_message(agent_id, &message.delivery_text()) {
+            tracing::warn!(agent_id, error = %e, "could not deliver message");
+            break;
+        }
+        store.mark_message_delivered(&message)?;
+        delivered += 1;
+    }
+    Ok(delivered)
+}
+
+/// Runs CSI passes, remembering output sizes between them.
+pub struct Supervisor {
+    stale_after: chrono::Duration,
+    block_after: chrono::Duration,
+    remediations: Vec<CsiRemediation>,
+    last_output: HashMap<String, usize>,
+    timeline: TimelineObserver,
+}
+
+impl Supervisor {
+    pub fn new(config: &AppConfig) -> Self {
+        Self {
+            stale_after: chrono::Duration::seconds(config.csi_stale_after_secs as i64),
+            block_after: chrono::Duration::seconds(config.csi_block_after_secs as i64),
+            remediations: config.csi_remediations.clone(),
+            last_output: HashMap::new(),
+            timeline: TimelineObserver::new(),
+        }
+    }
+
+    /// Inspect all live sessions once and record the run.
+    ///
+    /// Without a probe only heartbeat age is checked.
+    pub fn run_pass<P: SessionProbe + ?Sized>(
+        &mut self,
+        store: &StateStore,
+        mut probe: Option<&mut P>,
+    ) -> Result<CsiPassReport> {
+        let run_id = store.begin_csi_run()?;
+        let mut report = CsiPassReport {
+            run_id,
+            inspected: 0,
+            findings: Vec::new(),
+            remediations: 0,
+            delivered: 0,
+        };
+
+        for session in store.list_active()? {
+            report.inspected += 1;
+            let agent_id = session.agent_id.as_str();
+            let mut findings = Vec::new();
+
+            if let Some(probe) = probe.as_deref_mut() {
+                match probe.output_bytes(agent_id) {
+                    Ok(Some(bytes)) => {
+                        self.timeline.observe_output_size(store, agent_id, bytes)?;
+                        let previous = self.last_output.insert(agent_id.to_string(), bytes);
+                        match previous {
+                            // Idle: hand over its mail instead of nudging
+                            Some(prev) if prev == bytes => match deliver_inbox(store, agent_id, probe)? {
+                                0 => findings.push(Finding {
+                                    agent_id: agent_id.to_string(),
+                                    kind: FindingKind::Silent,
+                                    message: format!("no new output since last pass ({} bytes)", bytes),
+                                }),
+                                delivered => report.delivered += delivered,
+                            },
+                            _ => {
+                                store.touch_heartbeat(agent_id, Some("output"))?;
+                                if let Ok(Some((output, start))) = probe.recent_output(agent_id) {
+                                    self.timeline.observe_output(store, agent_id, &output, start)?;
+                                }
+                            }
+                        }
+                    }
+                    Ok(None) => {
+                        self.last_output.remove(agent_id);
+                        findings.push(Finding {
+                            agent_id: agent_id.to_string(),
+                            kind: FindingKind::Missing,
+                            message: format!("status is {} but no session is running", session.status),
+                        });
+                    }
+                    Err(e) => {
+                        store.record_csi_event(run_id, Some(agent_id), "error", &e.to_string())?;
+                    }
+                }
+            }
+
+            // A worktree checkout may be gone already; commits are best-effort
+            if let Err(e) = self.timeline.observe_commits(
+                store,
+                agent_id,
+                &session.checkout_path,
+                &session.branch_name,
+                session.created_at,
+            ) {
+                tracing::debug!(agent_id, error = %e, "could not scan commits for timeline");
+            }
+
+            let last_seen = store
+                .get_heartbeat(agent_id)?
+                .map(|h| h.last_seen_at)
+                .unwrap_or(session.updated_at);
+            let idle_for = Utc::now().signed_duration_since(last_seen);
+            if idle_for >= self.stale_after {
+                findings.push(Finding {
+                    agent_id: agent_id.to_string(),
+                    kind: FindingKind::Stale,
+                    message: format!("no activity for {}s", idle_for.num_seconds()),
+                });
+            }
+
+            for finding in &findings {
+                store.record_csi_event(run_id, Some(agent_id), finding.kind.as_str(), &finding.message)?;
+            }
+            if !findings.is_empty() {
+                report.remediations +=
+                    self.remediate(store, run_id, agent_id, &findings, idle_for, probe.as_deref_mut())?;
+            }
+            report.findings.extend(findings);
+        }
+
+        let status = if report.findings.is_empty() { "ok" } else { "findings" };
+        store.finish_csi_run(run_id, status, &report.summary())?;
+        Ok(report)
+    }
+
+    /// Apply configured remediations; returns how many were performed.
+    fn remediate<P: SessionProbe + ?Sized>(
+        &self,
+        store: &StateStore,
+        run_id: i64,
+        agent_id: &str,
+        findings: &[Finding],
+        idle_for: chrono::Duration,
+        mut probe: Option<&mut P>,
+    ) -> Result<usize> {
+        let missing = findings.iter().any(|f| f.kind == FindingKind::Missing);
+        // A quiet pass alone may be an agent thinking or waiting on a person
+        let stale = findings.iter().any(|f| f.kind == FindingKind::Stale);
+        let mut performed = 0;
+
+        for remediation in &self.remediations {
+            match remediation {
+                CsiRemediation::Nudge if stale && !missing => {
+                    let Some(probe) = probe.as_deref_mut() else {
+                        continue;
+                    };
+                    match probe.nudge(agent_id) {
+                        Ok(strategy) => {
+                            let detail = format!("csi: {}", strategy);
+                            store.record_csi_event(run_id, Some(agent_id), "nudge", &detail)?;
+                            store.record_timeline(agent_id, TimelineKind::Nudge, Some(&detail))?;
+                            crate::audit::record_in(
+                                store,
+                                AuditSource::Csi,
+                                "nudge",
+                                Some(agent_id),
+                                serde_json::json!({ "strategy": strategy, "csi_run": run_id }),
+                            )?;
+                            performed += 1;
+                        }
+                        Err(e) => {
+                            store.record_csi_event(run_id, Some(agent_id), "error", &e.to_string())?;
+                        }
+                    }
+                }
+                CsiRemediation::MarkBlocked if idle_for >= self.block_after => {
+                    // Unless it finished or was stopped since the pass read it
+                    let blocked = store
+                        .transition_status(agent_id, |record| {
+                            record.status.is_active().then_some(SessionStatus::Blocked)
+                        })?
+                        .is_some();
+                    if blocked {
+                        store.record_csi_event(run_id, Some(agent_id), "blocked", "marked blocked")?;
+                        crate::audit::record_in(
+                            store,
+                            AuditSource::Csi,
+                            "status",
+                            Some(agent_id),
+                            serde_json::json!({ "to": SessionStatus::Blocked.to_string(), "csi_run": run_id }),
+                        )?;
+                        performed += 1;
+                    }
+                }
+                CsiRemediation::Notify => {
+                    let kinds: Vec<&str> = findings.iter().map(|f| f.kind.as_str()).collect();
+                    tracing::warn!(agent_id, findings = %kinds.join(","), "CSI needs attention");
+                    store.record_csi_event(run_id, Some(agent_id), "notify", &kinds.join(", "))?;
+                    performed += 1;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(performed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::session_record;
+
+    #[derive(Default)]
+    struct FakeProbe {
+        bytes: HashMap<String, usize>,
+        nudged: Vec<String>,
+        sent: Vec<(String, String)>,
+    }
+
+    impl SessionProbe for FakeProbe {
+        fn output_bytes(&mut self, agent_id: &str) -> Result<Option<usize>> {
+            Ok(self.bytes.get(agent_id).copied())
+        }
+
+        fn nudge(&mut self, agent_id: &str) -> Result<String> {
+            self.nudged.push(agent_id.to_string());
+            Ok("enter".to_string())
+        }
+
+        fn send_message(&mut self, agent_id: &str, message: &str) -> Result<()> {
+            self.sent.push((agent_id.to_string(), message.to_string()));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn silent_agents_are_reported_but_not_nudged() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = StateStore::open(dir.path()).unwrap();
+        store.upsert_session(&session_record("busy")).unwrap();
+        store.upsert_session(&session_record("gone")).unwrap();
+
+        let mut probe = FakeProbe::default();
+        probe.bytes.insert("busy".to_string(), 100);
+        let mut supervisor = Supervisor::new(&AppConfig::default());
+
+        // First sighting of output is activity, not silence
+        let first = supervisor.run_pass(&store, Some(&mut probe)).unwrap();
+        assert_eq!(first.inspected, 2);
+        assert_eq!(first.findings.len(), 1);
+        assert_eq!(first.findings[0].kind, FindingKind::Missing);
+
+        // Quiet since the last pass, but well short of stale
+        let second = supervisor.run_pass(&store, Some(&mut probe)).unwrap();
+        let busy: Vec<FindingKind> =
+            second.findings.iter().filter(|f| f.agent_id == "busy").map(|f| f.kind).collect();
+        assert_eq!(busy, vec![FindingKind::Silent]);
+        assert!(probe.nudged.is_empty());
+
+        let runs = store.list_csi_runs(10).unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].id, second.run_id);
+        assert_eq!(runs[0].status, "findings");
+        let kinds: Vec<String> = store
+            .list_csi_events(second.run_id)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.kind)
+            .collect();
+        assert!(kinds.contains(&"silent".to_string()));
+        assert!(kinds.contains(&"missing".to_string()));
+        assert!(!kinds.contains(&"nudge".to_string()));
+    }
+
+    #[test]
+    fn stale_agents_are_nudged() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = StateStore::open(dir.path()).unwrap();
+        store.upsert_session(&session_record("stuck")).unwrap();
+        store.upsert_session(&session_record("gone")).unwrap();
+
+        let mut probe = FakeProbe::default();
+        probe.bytes.insert("stuck".to_string(), 100);
+        let config = AppConfig {
+            csi_stale_after_secs: 0,
+            csi_remediations: vec![CsiRemediation::Nudge],
+            ..AppConfig::default()
+        };
+        let report = Supervisor::new(&config).run_pass(&store, Some(&mut probe)).unwrap();
+
+        assert!(report.findings.iter().any(|f| f.agent_id == "stuck" && f.kind == FindingKind::Stale));
+        // Missing agents have no session to nudge
+        assert_eq!(probe.nudged, vec!["stuck".to_string()]);
+        let events = store.list_csi_events(report.run_id).unwrap();
+        assert!(events.iter().any(|e| e.kind == "nudge"));
+    }
+
+    #[test]
+    fn idle_agents_get_their_mail_instead_of_a_nudge() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = StateStore::open(dir.path()).unwrap();
+        store.upsert_session(&session_record("a1")).unwrap();
+        store.send_agent_message("a2", "a1", "I renamed Config::load").unwrap();
+
+        let mut probe = FakeProbe::default();
+        probe.bytes.insert("a1".to_string(), 100);
+        let mut supervisor = Supervisor::new(&AppConfig::default());
+        supervisor.run_pass(&store, Some(&mut probe)).unwrap();
+        assert!(probe.sent.is_empty(), "busy agents keep their mail");
+
+        let idle = supervisor.run_pass(&store, Some(&mut probe)).unwrap();
+        assert_eq!(idle.delivered, 1);
+        assert!(idle.findings.is_empty());
+        assert!(probe.nudged.is_empty());
+        assert_eq!(
+            probe.sent,
+            vec![("a1".to_string(), "[message from a2] I renamed Config::load".to_string())]
+        );
+        assert!(store.inbox("a1", true).unwrap().is_empty());
+        assert_eq!(store.timeline("a1").unwrap().last().unwrap().kind, TimelineKind::Message);
+    }
+
+    #[test]
+    fn stale_agents_are_marked_blocked() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = StateStore::open(dir.path()).unwrap();
+        store.upsert_session(&session_record("stuck")).unwrap();
+
+        let config = AppConfig {
+            csi_stale_after_secs: 0,
+            csi_block_after_secs: 0,
+            csi_remediations: vec![CsiRemediation::MarkBlocked],
+            ..AppConfig::default()
+        };
+        let report = Supervisor::new(&config)
+            .run_pass(&store, None::<&mut FakeProbe>).unwrap();
+
+        assert_eq!(report.findings[0].kind, FindingKind::Stale);
+        assert_eq!(report.remediations, 1);
+        let session = store.get_session("stuck").unwrap().unwrap();
+        assert_eq!(session.status, SessionStatus::Blocked);
+    }
+}
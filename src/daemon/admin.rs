@@ -0,0 +1,72 @@
+//! Repository administration over the daemon protocol
+//!
+//! `Merge`, `GetConfig` and `SetConfig` need the admin role, so a token
+//! holder can merge an agent's work or change a repository's settings from
+//! another machine. Neither touches the session manager.
+
+use crate::config::AppConfig;
+use crate::daemon::DaemonResponse;
+use crate::state::StateStore;
+use crate::{RembrandtError, Result};
+use std::path::{Path, PathBuf};
+
+/// Merge `agent_id`'s branch into `base` right away, as `rembrandt merge`
+/// does without its decision check
+pub async fn merge(repo: PathBuf, agent_id: String, base: String, validate: bool) -> DaemonResponse {
+    // The store isn't shared between threads, so the merge runs on one
+    let merged = tokio::task::spawn_blocking(move || {
+        let config = AppConfig::load(&repo)?;
+        let store = StateStore::open(&repo)?;
+        tokio::runtime::Handle::current().block_on(crate::merge_queue::merge_now(
+            &repo,
+            &base,
+            &store,
+            &agent_id,
+            validate,
+            &config.solution_validator(&base),
+            &config.hooks,
+        ))
+    })
+    .await
+    .map_err(|e| RembrandtError::Daemon(e.to_string()))
+    .and_then(|merged| merged);
+    match merged {
+        Ok(entry) => DaemonResponse::Ok {
+            message: Some(match entry.detail {
+                Some(detail) => format!("{}: {}", entry.status, detail),
+                None => entry.status.to_string(),
+            }),
+        },
+        Err(e) => DaemonResponse::error(&e),
+    }
+}
+
+/// `repo`'s config file, or nothing if it has none
+pub fn config(repo: &Path) -> DaemonResponse {
+    match std::fs::read_to_string(AppConfig::path(repo)) {
+        Ok(config) => DaemonResponse::Config { config },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => DaemonResponse::Config { config: String::new() },
+        Err(e) => DaemonResponse::error(&RembrandtError::from(e)),
+    }
+}
+
+/// Replace `repo`'s config file with `config` once it parses
+pub fn set_config(repo: &Path, config: &str) -> DaemonResponse {
+    let saved = AppConfig::from_toml(config).and_then(|_| write_config(repo, config));
+    match saved {
+        Ok(()) => DaemonResponse::Ok { message: None },
+        Err(e) => DaemonResponse::error(&e),
+    }
+}
+
+fn write_config(repo: &Path, config: &str) -> Result<()> {
+    let path = AppConfig::path(repo);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    if path.exists() {
+        std::fs::copy(&path, path.with_extension("toml.bak"))?;
+    }
+    std::fs::write(&path, config)?;
+    Ok(())
+}
@@ -0,0 +1,271 @@
+//! Role-based tokens for daemon clients
+//!
+//! Tokens are named in `.rembrandt/config.toml`, each with a role:
+//!
+//! ```toml
+//! [api_tokens.grafana]
+//! token = "read-9f2c..."
+//! role = "read"        # list, status, history, screens, read-only attach
+//!
+//! [api_tokens.ci]
+//! token = "op-71be..."
+//! role = "operator"    # also spawn, steer (write), nudge, pause, stop, kill
+//!
+//! [api_tokens.ops]
+//! token = "admin-c04d..."
+//! role = "admin"       # also merge, config, shutdown
+//! ```
+//!
+//! A client presents one with `DaemonCommand::Authenticate`. Connections on
+//! the TCP listener (`rembrandt daemon --listen`) must authenticate before
+//! anything but `Ping`; the Unix socket, which only its owner can open, allows everything
+//! until a token narrows it to that token's role. Mutations made with a
+//! token are recorded in the audit log with the token's name as the actor.
+//! TCP clients may only spawn the known agents, with their default arguments,
+//! in repositories the daemon already serves, whatever their role.
+
+use crate::agent::{AgentType, CONTEXT_ENV_VARS};
+use crate::audit::AuditSource;
+use crate::daemon::DaemonCommand;
+use crate::{RembrandtError, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+pub use rembrandt_core::auth::{ApiToken, Role};
+
+/// What a connection is allowed to do
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Access {
+    /// The Unix socket's owner, before presenting a token
+    Local,
+    /// A TCP client that hasn't presented a token
+    Anonymous,
+    /// A client that presented the token called `name`
+    Token { name: String, role: Role },
+}
+
+impl Access {
+    /// Refuse `command` unless this access allows it
+    pub fn check(&self, command: &DaemonCommand) -> Result<()> {
+        let Some(required) = Role::required_for(command) else {
+            return Ok(());
+        };
+        match self {
+            Access::Local => Ok(()),
+            Access::Anonymous => Err(RembrandtError::Forbidden(
+                "present an API token first (Authenticate)".to_string(),
+            )),
+            Access::Token { name, role } if *role < required => {
+                Err(RembrandtError::Forbidden(format!(
+                    "token '{}' has role {}; this needs {}",
+                    name, role, required
+                )))
+            }
+            Access::Token { .. } => Ok(()),
+        }
+    }
+}
+
+/// Narrow a command from a TCP client to what remote clients may do
+///
+/// Merges and config edits must name a repository this daemon serves
+/// (one of `repos`). A `Spawn` must name one of the known agents by its plain command name
+/// (`claude`, `codex`, ...) and run in a checkout of one of `repos`; its
+/// arguments are replaced with the agent's defaults and its environment may
+/// only carry Rembrandt's context variables. Anything more (another
+/// program, agent flags, `PATH` or `LD_PRELOAD`) would let a token holder
+/// run arbitrary code on the daemon's machine.
+pub fn restrict_remote(command: &mut DaemonCommand, repos: &[PathBuf]) -> Result<()> {
+    let (command, args, env, workdir) = match command {
+        DaemonCommand::Spawn { command, args, env, workdir, .. } => (command, args, env, workdir),
+        DaemonCommand::Merge { repo, .. }
+        | DaemonCommand::GetConfig { repo }
+        | DaemonCommand::SetConfig { repo, .. } => return check_served(repo, repos),
+        _ => return Ok(()),
+    };
+    let agent = AgentType::from_str(command);
+    if matches!(agent, AgentType::Custom(_)) || agent.command() != command {
+        return Err(RembrandtError::Forbidden(format!(
+            "TCP clients may only spawn known agents (claude, opencode, amp, codex, aider), not '{}'",
+            command
+        )));
+    }
+    if let Some((name, _)) = env.iter().find(|(name, _)| !CONTEXT_ENV_VARS.contains(&name.as_str())) {
+        return Err(RembrandtError::Forbidden(format!(
+            "TCP clients may not set {} for an agent",
+            name
+        )));
+    }
+    check_served(workdir, repos)?;
+    *args = agent.default_args().into_iter().map(str::to_string).collect();
+    Ok(())
+}
+
+/// Refuse `path` unless it is in one of `repos`, or a worktree of one
+fn check_served(path: &Path, repos: &[PathBuf]) -> Result<()> {
+    match crate::worktree::main_repo_root(path) {
+        Some(root) if repos.contains(&root) => Ok(()),
+        _ => Err(RembrandtError::Forbidden(format!(
+            "{} is not in a repository this daemon serves",
+            path.display()
+        ))),
+    }
+}
+
+/// Configured tokens, and where to audit commands that name no repository
+#[derive(Debug, Clone, Default)]
+pub struct AccessPolicy {
+    pub tokens: BTreeMap<String, ApiToken>,
+    pub audit_repo: Option<PathBuf>,
+}
+
+impl AccessPolicy {
+    /// The access `presented` grants
+    pub fn authenticate(&self, presented: &str) -> Result<Access> {
+        self.tokens
+            .iter()
+            .find(|(_, token)| constant_time_eq(token.token.as_bytes(), presented.as_bytes()))
+            .map(|(name, token)| Access::Token {
+                name: name.clone(),
+                role: token.role,
+            })
+            .ok_or_else(|| RembrandtError::Forbidden("unknown API token".to_string()))
+    }
+
+    /// Record `command`, sent with a token, in `repo`'s audit log (or the
+    /// daemon's own repository's); reads and connection housekeeping aren't
+    /// recorded
+    pub fn audit(&self, access: &Access, command: &DaemonCommand, repo: Option<&Path>, agent_id: Option<&str>) {
+        let Access::Token { name, role } = access else {
+            return;
+        };
+        let (action, params) = match command {
+            DaemonCommand::Spawn { command, args, workdir, restart, .. } => (
+                "spawn",
+                serde_json::json!({
+                    "command": command,
+                    "args": args,
+                    "workdir": workdir,
+                    "restart": restart.to_string(),
+                }),
+            ),
+            DaemonCommand::Write { data, .. } => (
+                "steer",
+                serde_json::json!({ "text": String::from_utf8_lossy(data) }),
+            ),
+            DaemonCommand::Nudge { .. } => ("nudge", serde_json::Value::Null),
+            DaemonCommand::Stop { grace_secs, .. } => ("stop", serde_json::json!({ "grace_secs": grace_secs })),
+            DaemonCommand::Kill { .. } => ("kill", serde_json::Value::Null),
+            DaemonCommand::Pause { .. } => ("pause", serde_json::Value::Null),
+            DaemonCommand::Resume { .. } => ("resume", serde_json::Value::Null),
+            DaemonCommand::Merge { base, validate, .. } => {
+                ("merge", serde_json::json!({ "base": base, "validate": validate }))
+            }
+            DaemonCommand::SetConfig { .. } => ("config", serde_json::Value::Null),
+            DaemonCommand::Shutdown => ("shutdown", serde_json::Value::Null),
+            _ => return,
+        };
+        let Some(repo) = repo.or(self.audit_repo.as_deref()) else {
+            return;
+        };
+        let mut params = params;
+        if let serde_json::Value::Object(map) = &mut params {
+            map.insert("role".to_string(), role.as_str().into());
+        } else {
+            params = serde_json::json!({ "role": role.as_str() });
+        }
+        crate::audit::record_by(repo, AuditSource::Api, name, action, agent_id, params);
+    }
+}
+
+/// Compare secrets without returning early on the first differing byte
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roles_gate_commands_by_rank() {
+        let policy = AccessPolicy {
+            tokens: [
+                ("grafana", "read-token", Role::Read),
+                ("ci", "operator-token", Role::Operator),
+            ]
+            .into_iter()
+            .map(|(name, token, role)| {
+                (name.to_string(), ApiToken { token: token.to_string(), role })
+            })
+            .collect(),
+            audit_repo: None,
+        };
+        let list = DaemonCommand::List { repo: None };
+        let kill = DaemonCommand::Kill { session_id: "s1".to_string() };
+        let watch = DaemonCommand::Attach { session_id: "s1".to_string(), read_only: true };
+        let drive = DaemonCommand::Attach { session_id: "s1".to_string(), read_only: false };
+
+        let read = policy.authenticate("read-token").unwrap();
+        assert!(read.check(&list).is_ok() && read.check(&watch).is_ok());
+        let err = read.check(&kill).unwrap_err();
+        assert_eq!(err.code(), crate::ErrorCode::Forbidden);
+        assert!(err.to_string().contains("token 'grafana' has role read"));
+        assert!(read.check(&drive).is_err());
+
+        let operator = policy.authenticate("operator-token").unwrap();
+        assert!(operator.check(&kill).is_ok());
+        assert!(operator.check(&DaemonCommand::Shutdown).is_err());
+        assert!(operator.check(&DaemonCommand::GetConfig { repo: PathBuf::from(".") }).is_err());
+
+        assert!(policy.authenticate("operator-tokem").is_err());
+        assert!(Access::Anonymous.check(&list).is_err());
+        assert!(Access::Anonymous.check(&DaemonCommand::Ping).is_ok());
+        assert!(Access::Local.check(&DaemonCommand::Shutdown).is_ok());
+    }
+
+    #[test]
+    fn remote_spawns_run_known_agents_in_served_repos() {
+        let dir = tempfile::tempdir().unwrap();
+        git2::Repository::init(dir.path()).unwrap();
+        let served = crate::worktree::main_repo_root(dir.path()).unwrap();
+        let elsewhere = tempfile::tempdir().unwrap();
+        let spawn = |command: &str, args: &[&str], env: &[(&str, &str)], workdir: &Path| DaemonCommand::Spawn {
+            agent_id: "a1".to_string(),
+            command: command.to_string(),
+            args: args.iter().map(|a| a.to_string()).collect(),
+            env: env.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            workdir: workdir.to_path_buf(),
+            rows: None,
+            cols: None,
+            buffer_capacity: None,
+            restart: Default::default(),
+        };
+        let refused = |mut command: DaemonCommand| {
+            let err = restrict_remote(&mut command, std::slice::from_ref(&served)).unwrap_err();
+            assert_eq!(err.code(), crate::ErrorCode::Forbidden);
+            err.to_string()
+        };
+
+        assert!(refused(spawn("sh", &[], &[], dir.path())).contains("'sh'"));
+        assert!(refused(spawn("/tmp/claude", &[], &[], dir.path())).contains("known agents"));
+        assert!(refused(spawn("claude", &[], &[("LD_PRELOAD", "/tmp/x.so")], dir.path())).contains("LD_PRELOAD"));
+        assert!(refused(spawn("claude", &[], &[], elsewhere.path())).contains("not in a repository"));
+
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        let mut allowed = spawn(
+            "claude",
+            &["--dangerously-skip-permissions"],
+            &[("REMBRANDT_AGENT_ID", "a1")],
+            &dir.path().join("src"),
+        );
+        restrict_remote(&mut allowed, &[served]).unwrap();
+        let DaemonCommand::Spawn { args, env, .. } = allowed else { unreachable!() };
+        assert!(args.is_empty(), "client arguments are replaced by the agent's defaults");
+        assert_eq!(env, [("REMBRANDT_AGENT_ID".to_string(), "a1".to_string())]);
+
+        // Everything else passes through untouched
+        let mut list = DaemonCommand::List { repo: None };
+        assert!(restrict_remote(&mut list, &[]).is_ok());
+    }
+}
@@ -0,0 +1,139 @@
+//! Keeping detached agents' terminals open after the daemon exits
+//!
+//! An agent's PTY master belongs to the daemon process. When the daemon
+//! exits the master is closed, the kernel hangs up the agent's terminal and
+//! the agent gets SIGHUP, so `shutdown_policy = "detach"` alone would not
+//! leave anything running. Before exiting, a detaching daemon forks a
+//! holder process that keeps every running agent's master open and appends
+//! what the agent prints to its session log (see [`super::logs`]), so
+//! output isn't lost and a full terminal never blocks the agent. The holder
+//! exits once every agent has closed its terminal.
+
+use crate::{RembrandtError, Result};
+use std::fs::File;
+use std::os::unix::io::{AsRawFd, RawFd};
+
+/// A running agent's terminal to keep open
+pub struct Terminal {
+    /// The PTY master
+    pub master: RawFd,
+    /// Where the agent's further output goes, if anywhere
+    pub log: Option<File>,
+}
+
+/// Fork a process holding `terminals` open until their agents exit;
+/// returns its pid, or None when there is nothing to hold
+///
+/// The holder is detached from the daemon (its own session, reparented to
+/// init), so it neither stops with the daemon nor lingers as its zombie.
+pub fn hold(terminals: &[Terminal]) -> Result<Option<u32>> {
+    if terminals.is_empty() {
+        return Ok(None);
+    }
+    // Everything the holder needs is allocated here: after fork() in a
+    // multi-threaded process, the child may only make plain system calls
+    let mut polled: Vec<libc::pollfd> = terminals
+        .iter()
+        .map(|t| libc::pollfd { fd: t.master, events: libc::POLLIN, revents: 0 })
+        .collect();
+    let logs: Vec<RawFd> = terminals
+        .iter()
+        .map(|t| t.log.as_ref().map_or(-1, AsRawFd::as_raw_fd))
+        .collect();
+    let max_fd = match unsafe { libc::sysconf(libc::_SC_OPEN_MAX) } {
+        n if n > 0 => n.min(65_536) as RawFd,
+        _ => 1024,
+    };
+    let (mut pipe_fds, mut pid) = ([0 as RawFd; 2], 0 as libc::pid_t);
+    if unsafe { libc::pipe(pipe_fds.as_mut_ptr()) } != 0 {
+        return Err(RembrandtError::Daemon(format!(
+            "can't start terminal holder: {}",
+            std::io::Error::last_os_error()
+        )));
+    }
+
+    match unsafe { libc::fork() } {
+        -1 => {
+            unsafe {
+                libc::close(pipe_fds[0]);
+                libc::close(pipe_fds[1]);
+            }
+            Err(RembrandtError::Daemon(format!(
+                "can't start terminal holder: {}",
+                std::io::Error::last_os_error()
+            )))
+        }
+        0 => unsafe {
+            // Intermediate child: new session, fork the holder, report its
+            // pid and exit so the holder is reparented
+            libc::setsid();
+            let holder = libc::fork();
+            if holder == 0 {
+                run_holder(&mut polled, &logs, max_fd);
+            }
+            libc::write(pipe_fds[1], (&holder as *const libc::pid_t).cast(), size_of::<libc::pid_t>());
+            libc::_exit(0);
+        },
+        child => unsafe {
+            libc::close(pipe_fds[1]);
+            let read = libc::read(pipe_fds[0], (&mut pid as *mut libc::pid_t).cast(), size_of::<libc::pid_t>());
+            libc::close(pipe_fds[0]);
+            libc::waitpid(child, std::ptr::null_mut(), 0);
+            if read != size_of::<libc::pid_t>() as isize || pid <= 0 {
+                return Err(RembrandtError::Daemon("can't start terminal holder".to_string()));
+            }
+            Ok(Some(pid as u32))
+        },
+    }
+}
+
+/// The holder's loop: drain every master into its log until all hang up
+///
+/// # Safety
+/// Runs in a forked child; only async-signal-safe calls are made.
+unsafe fn run_holder(polled: &mut [libc::pollfd], logs: &[RawFd], max_fd: RawFd) -> ! {
+    unsafe {
+        for fd in 0..max_fd {
+            if !polled.iter().any(|p| p.fd == fd) && !logs.contains(&fd) {
+                libc::close(fd);
+            }
+        }
+        libc::signal(libc::SIGHUP, libc::SIG_IGN);
+        libc::signal(libc::SIGINT, libc::SIG_IGN);
+
+        let mut open = polled.len();
+        let mut buf = [0u8; 4096];
+        while open > 0 {
+            if libc::poll(polled.as_mut_ptr(), polled.len() as libc::nfds_t, -1) < 0 {
+                if errno() == libc::EINTR {
+                    continue;
+                }
+                break;
+            }
+            for (entry, &log) in polled.iter_mut().zip(logs) {
+                if entry.fd < 0 || entry.revents == 0 {
+                    continue;
+                }
+                let n = libc::read(entry.fd, buf.as_mut_ptr().cast(), buf.len());
+                if n > 0 {
+                    if log >= 0 {
+                        libc::write(log, buf.as_ptr().cast(), n as usize);
+                    }
+                    continue;
+                }
+                if n < 0 && matches!(errno(), libc::EINTR | libc::EAGAIN) {
+                    continue;
+                }
+                // EIO: the agent and everything it started closed the terminal
+                libc::close(entry.fd);
+                entry.fd = -1;
+                open -= 1;
+            }
+        }
+        libc::_exit(0);
+    }
+}
+
+fn errno() -> i32 {
+    std::io::Error::last_os_error().raw_os_error().unwrap_or(0)
+}
@@ -0,0 +1,182 @@
+//! Session output logs kept on disk
+//!
+//! The daemon's ring buffers only hold recent output, and only while the
+//! daemon runs. Every session whose workdir is inside a repository also has
+//! its raw output appended to `.rembrandt/logs/<agent>/<session>.log` there,
+//! so `rembrandt search` can look through sessions long after they ended.
+//! Writes happen on a thread of their own, off the output pump, which
+//! closes a session's file once it exits.
+//!
+//! Logs older than `log_retention_days` (default 30), then the oldest past
+//! `log_retention_mb` (default 500) in all, are removed by [`prune`] when
+//! the daemon starts and on `rembrandt gc`.
+
+use super::SessionId;
+use crate::config::AppConfig;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, SystemTime};
+
+/// Where session logs of `repo` are kept
+pub fn logs_dir(repo: &Path) -> PathBuf {
+    repo.join(".rembrandt").join("logs")
+}
+
+/// Log file of one session
+pub fn log_path(repo: &Path, agent_id: &str, session_id: &str) -> PathBuf {
+    logs_dir(repo).join(agent_id).join(format!("{}.log", session_id))
+}
+
+/// Output to append to a session's log
+struct Chunk {
+    path: PathBuf,
+    session_id: SessionId,
+    data: Vec<u8>,
+}
+
+enum Message {
+    Append(Chunk),
+    /// The session ended; its file can be closed
+    Close(SessionId),
+}
+
+/// Hands output to the log-writing thread
+#[derive(Clone)]
+pub struct LogWriter {
+    sender: mpsc::Sender<Message>,
+}
+
+impl LogWriter {
+    /// Start the thread that writes logs
+    pub fn spawn() -> Self {
+        let (sender, receiver) = mpsc::channel::<Message>();
+        std::thread::spawn(move || {
+            let mut files: HashMap<SessionId, File> = HashMap::new();
+            for message in receiver {
+                let chunk = match message {
+                    Message::Append(chunk) => chunk,
+                    Message::Close(session_id) => {
+                        files.remove(&session_id);
+                        continue;
+                    }
+                };
+                if !files.contains_key(&chunk.session_id) {
+                    let opened = chunk
+                        .path
+                        .parent()
+                        .map_or(Ok(()), std::fs::create_dir_all)
+                        .and_then(|_| File::options().create(true).append(true).open(&chunk.path));
+                    match opened {
+                        Ok(file) => {
+                            files.insert(chunk.session_id.clone(), file);
+                        }
+                        Err(e) => {
+                            tracing::warn!("Can't write session log {}: {}", chunk.path.display(), e);
+                            continue;
+                        }
+                    }
+                }
+                if let Some(file) = files.get_mut(&chunk.session_id)
+                    && let Err(e) = file.write_all(&chunk.data)
+                {
+                    tracing::warn!("Can't write session log {}: {}", chunk.path.display(), e);
+                    files.remove(&chunk.session_id);
+                }
+            }
+        });
+        Self { sender }
+    }
+
+    /// Queue `data` for `session_id`'s log in `repo`
+    pub fn append(&self, repo: &Path, agent_id: &str, session_id: &str, data: &[u8]) {
+        let _ = self.sender.send(Message::Append(Chunk {
+            path: log_path(repo, agent_id, session_id),
+            session_id: session_id.to_string(),
+            data: data.to_vec(),
+        }));
+    }
+
+    /// Close `session_id`'s log once everything queued for it is written
+    pub fn close(&self, session_id: &str) {
+        let _ = self.sender.send(Message::Close(session_id.to_string()));
+    }
+}
+
+/// Remove `repo`'s session logs older than `log_retention_days`, then the
+/// oldest until the rest fit in `log_retention_mb`; returns those removed
+pub fn prune(repo: &Path, config: &AppConfig) -> std::io::Result<Vec<PathBuf>> {
+    let max_age = (config.log_retention_days > 0).then(|| Duration::from_secs(config.log_retention_days * 24 * 60 * 60));
+    let max_bytes = (config.log_retention_mb > 0).then(|| config.log_retention_mb * 1024 * 1024);
+
+    let mut logs = Vec::new();
+    let dir = logs_dir(repo);
+    let agents = match std::fs::read_dir(&dir) {
+        Ok(agents) => agents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    for agent in agents.flatten().filter(|entry| entry.path().is_dir()) {
+        for entry in std::fs::read_dir(agent.path())?.flatten() {
+            let metadata = entry.metadata()?;
+            if metadata.is_file() {
+                logs.push((entry.path(), metadata.modified()?, metadata.len()));
+            }
+        }
+    }
+    // Newest first, so the size cap keeps the most recent sessions
+    logs.sort_by_key(|(_, modified, _)| std::cmp::Reverse(*modified));
+
+    let now = SystemTime::now();
+    let mut kept = 0u64;
+    let mut removed = Vec::new();
+    for (path, modified, len) in logs {
+        let expired = max_age.is_some_and(|max| now.duration_since(modified).unwrap_or_default() > max);
+        if expired || max_bytes.is_some_and(|max| kept + len > max) {
+            std::fs::remove_file(&path)?;
+            // Drops the agent's directory once its last log is gone
+            if let Some(agent_dir) = path.parent() {
+                let _ = std::fs::remove_dir(agent_dir);
+            }
+            removed.push(path);
+        } else {
+            kept += len;
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn old_and_excess_logs_are_pruned_newest_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |agent: &str, session: &str, bytes: usize, age_days: u64| {
+            let path = log_path(dir.path(), agent, session);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, vec![b'x'; bytes]).unwrap();
+            let modified = SystemTime::now() - Duration::from_secs(age_days * 24 * 60 * 60);
+            File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+            path
+        };
+        let expired = write("claude-1", "s-1", 10, 40);
+        let oldest = write("claude-2", "s-2", 700 * 1024, 3);
+        let newer = write("claude-2", "s-3", 700 * 1024, 2);
+        let newest = write("claude-3", "s-4", 10, 0);
+
+        let config = AppConfig { log_retention_days: 30, log_retention_mb: 1, ..AppConfig::default() };
+        let mut removed = prune(dir.path(), &config).unwrap();
+        removed.sort();
+        assert_eq!(removed, vec![expired.clone(), oldest.clone()]);
+        assert!(newer.exists() && newest.exists());
+        // An agent left with no logs loses its directory too
+        assert!(!expired.parent().unwrap().exists());
+
+        let keep_all = AppConfig { log_retention_days: 0, log_retention_mb: 0, ..AppConfig::default() };
+        assert!(prune(dir.path(), &keep_all).unwrap().is_empty());
+    }
+}
@@ -26,22 +26,182 @@
 //!
 //! When an agent session starts, if there's work on its easel (assignment),
 //! it should begin immediately. The daemon supports nudging stalled agents.
+//!
+//! # Shutdown
+//!
+//! On `Shutdown`, Ctrl-C or SIGTERM the daemon applies `shutdown_policy`:
+//! `terminate` sends every agent SIGTERM and SIGKILLs stragglers after
+//! `shutdown_grace_secs`; `detach` leaves them running, their terminals
+//! held open by a process of their own (see [`detach`]) that appends their
+//! output to the session logs. Either way the final session list, with
+//! PIDs, is written next to the socket (`rembrandt.shutdown.json`). The next
+//! daemon reports detached agents that are still alive; their PTYs can't be
+//! re-attached, since they belonged to the old daemon, so they have to be
+//! watched through their logs or stopped by PID.
+//!
+//! # Remote access
+//!
+//! With `--listen`, the daemon also accepts the same protocol over TCP.
+//! Those clients must present a token from `[api_tokens]` (see [`auth`]),
+//! whose role decides which commands they may send, and may only spawn the
+//! known agents. Plain TCP is served on loopback only; other addresses need
+//! `[listen_tls]` (see [`tls`]).
+//!
+//! # Scheduled steers
+//!
+//! With a repository (`with_schedule`), the daemon types the steers
+//! scheduled there with `rembrandt steer --at/--every` into their agents'
+//! sessions as they fall due (see [`crate::schedule`]).
+//!
+//! # Supervision
+//!
+//! `rembrandt daemon supervise` runs the daemon under a [`supervise::Supervisor`]
+//! that starts it again, reconciliation and all, when it crashes.
+//!
+//! # Metrics
+//!
+//! With `--metrics-addr`, the daemon also serves Prometheus metrics
+//! (`rembrandt_core::metrics`) at `GET /metrics` on that TCP address.
+//!
+//! # Usage
+//!
+//! Token counts and cost an agent prints (see [`crate::usage`]) are picked
+//! out of its output as it is read and recorded in the `usage` table of the
+//! session's repository.
+
+// PTY sessions, buffers, the session manager and the IPC protocol live in
+// `rembrandt-core` so the Tauri GUI shares the exact same implementation.
+pub use rembrandt_core::{buffer, ipc, manager, session};
 
-pub mod buffer;
-pub mod ipc;
-pub mod manager;
-pub mod session;
+pub mod admin;
+pub mod auth;
+pub mod detach;
+pub mod logs;
+pub mod supervise;
+pub mod tls;
 
 pub use buffer::RingBuffer;
-pub use ipc::{DaemonCommand, DaemonEvent, DaemonResponse};
+pub use ipc::{DaemonCommand, DaemonEvent, DaemonResponse, Framing};
 pub use manager::{SessionInfo, SessionManager};
 pub use session::{PtySession, SessionId, SessionStatus};
 
-use crate::Result;
-use std::path::PathBuf;
+use crate::agent::{AgentType, NudgeStrategy};
+use auth::{Access, AccessPolicy, ApiToken};
+use crate::config::{PtyIo, ShutdownPolicy};
+use crate::process::ProcessTable;
+use crate::restart::{self, RestartPolicy};
+use crate::hooks::{self, HookContext, HookPoint};
+use crate::state::StateStore;
+use crate::usage::{UsageSample, UsageScanner};
+use crate::{RembrandtError, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use tokio::net::{UnixListener, UnixStream};
-use tokio::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream, UnixListener, UnixStream};
+use tokio::sync::{broadcast, mpsc, Mutex, Notify};
+use tokio::task::JoinHandle;
+use tracing::Instrument;
+
+/// Lines of a crashed session's output a restarted agent is shown
+const RESTART_LOG_LINES: usize = 20;
+
+/// Time a restarted agent gets to start up before its prompt is typed
+const RESTART_PROMPT_DELAY: Duration = Duration::from_millis(500);
+
+/// How often the daemon drains PTY output and checks for exits when
+/// polling, and the least time between passes when woken by output
+const OUTPUT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How often the daemon checks for exits when woken by output: an agent
+/// whose PTY stays open after it exits (a leftover background process
+/// holds it) wakes nobody
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How often the daemon measures each agent's CPU and memory use
+const RESOURCE_SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Buffered events per attached client before it starts lagging
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// Messages queued for a client's socket before senders wait
+const OUTGOING_CAPACITY: usize = 64;
+
+/// Live output held per attachment while its client catches up; beyond
+/// this the oldest bytes are dropped (history keeps them)
+const MAX_PENDING_OUTPUT: usize = 1024 * 1024;
+
+/// How often shutdown checks whether terminated agents have exited
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long each step of a `Stop` gets before the next, by default
+const DEFAULT_STOP_GRACE: Duration = Duration::from_secs(10);
+
+/// How often the daemon checks for scheduled steers that are due
+const SCHEDULE_POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Time an agent gets to read a steer's text before the Enter arrives
+const STEER_SUBMIT_DELAY: Duration = Duration::from_millis(50);
+
+/// What a daemon left behind when it exited
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShutdownManifest {
+    pub stopped_at: chrono::DateTime<chrono::Utc>,
+    pub policy: ShutdownPolicy,
+    pub sessions: Vec<ShutdownSession>,
+}
+
+/// A session's final state at daemon exit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShutdownSession {
+    #[serde(flatten)]
+    pub info: SessionInfo,
+    pub pid: Option<u32>,
+}
+
+impl ShutdownManifest {
+    /// Where the daemon listening on `socket_path` writes its manifest
+    pub fn path_for(socket_path: &Path) -> PathBuf {
+        socket_path.with_extension("shutdown.json")
+    }
+
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        match std::fs::read(path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map(Some)
+                .map_err(|e| RembrandtError::Daemon(format!("{}: {}", path.display(), e))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_vec_pretty(self)
+            .map_err(|e| RembrandtError::Daemon(e.to_string()))?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+
+    /// Detached agents whose process still exists
+    pub fn surviving(&self) -> Vec<&ShutdownSession> {
+        if self.policy != ShutdownPolicy::Detach {
+            return Vec::new();
+        }
+        self.sessions
+            .iter()
+            .filter(|s| s.info.status == SessionStatus::Running)
+            .filter(|s| s.pid.is_some_and(process_alive))
+            .collect()
+    }
+}
+
+fn process_alive(pid: u32) -> bool {
+    // Signal 0 only checks that the process exists
+    unsafe { libc::kill(pid as i32, 0) == 0 }
+}
 
 /// The Rembrandt daemon server
 pub struct Daemon {
@@ -49,44 +209,295 @@ pub struct Daemon {
     manager: Arc<Mutex<SessionManager>>,
     /// Path to the Unix socket
     socket_path: PathBuf,
+    /// Output and exit events, fanned out to attached clients
+    events: broadcast::Sender<DaemonEvent>,
+    /// Signalled by a client's `Shutdown` command
+    shutdown: Arc<Notify>,
+    /// Where to serve Prometheus metrics, if anywhere
+    metrics_addr: Option<SocketAddr>,
+    /// Where to accept token-authenticated clients over TCP, if anywhere
+    listen_addr: Option<SocketAddr>,
+    /// TLS for the TCP listener
+    tls: Option<tokio_rustls::TlsAcceptor>,
+    /// API tokens and where their commands are audited
+    access: Arc<AccessPolicy>,
+    shutdown_policy: ShutdownPolicy,
+    /// How long terminated agents get before SIGKILL
+    shutdown_grace: Duration,
+    /// Per-agent-type nudge ladders from config
+    nudge_ladders: HashMap<AgentType, Vec<NudgeStrategy>>,
+    /// Per-agent-type quit sequences from config
+    quit_sequences: HashMap<AgentType, Vec<NudgeStrategy>>,
+    /// How long each step of a `Stop` gets when the client doesn't say
+    stop_grace: Duration,
+    /// Output history kept per session unless a spawn asks otherwise
+    buffer_capacity: Option<usize>,
+    /// Whether output is picked up when readers report it or on a timer
+    pty_io: PtyIo,
+    /// Repository whose scheduled steers the daemon sends, if any
+    schedule_repo: Option<PathBuf>,
 }
 
 impl Daemon {
     /// Create a new daemon instance
     pub fn new(socket_path: PathBuf) -> Self {
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
         Self {
             manager: Arc::new(Mutex::new(SessionManager::new())),
             socket_path,
+            events,
+            shutdown: Arc::new(Notify::new()),
+            metrics_addr: None,
+            listen_addr: None,
+            tls: None,
+            access: Arc::new(AccessPolicy::default()),
+            shutdown_policy: ShutdownPolicy::Terminate,
+            shutdown_grace: Duration::from_secs(10),
+            nudge_ladders: HashMap::new(),
+            quit_sequences: HashMap::new(),
+            stop_grace: DEFAULT_STOP_GRACE,
+            buffer_capacity: None,
+            pty_io: PtyIo::Events,
+            schedule_repo: None,
         }
     }
 
+    /// What to do with running agents on exit (default: terminate, 10s grace)
+    pub fn with_shutdown_policy(mut self, policy: ShutdownPolicy, grace: Duration) -> Self {
+        self.shutdown_policy = policy;
+        self.shutdown_grace = grace;
+        self
+    }
+
+    /// Nudge ladders replacing the built-in ones for some agent types
+    pub fn with_nudge_ladders(mut self, ladders: HashMap<AgentType, Vec<NudgeStrategy>>) -> Self {
+        self.nudge_ladders = ladders;
+        self
+    }
+
+    /// Quit sequences replacing the built-in ones for some agent types
+    pub fn with_quit_sequences(mut self, sequences: HashMap<AgentType, Vec<NudgeStrategy>>) -> Self {
+        self.quit_sequences = sequences;
+        self
+    }
+
+    /// How long each step of a `Stop` waits before escalating (default 10s)
+    pub fn with_stop_grace(mut self, grace: Duration) -> Self {
+        self.stop_grace = grace;
+        self
+    }
+
+    /// Bytes of output history kept per session (default 10KB)
+    pub fn with_buffer_capacity(mut self, capacity: usize) -> Self {
+        self.buffer_capacity = Some(capacity);
+        self
+    }
+
+    /// How to pick up agent output (default: when readers report it)
+    pub fn with_pty_io(mut self, pty_io: PtyIo) -> Self {
+        self.pty_io = pty_io;
+        self
+    }
+
+    /// Send the steers scheduled in `repo`'s state.db as they fall due
+    pub fn with_schedule(mut self, repo: PathBuf) -> Self {
+        self.schedule_repo = Some(repo);
+        self
+    }
+
+    /// Also serve Prometheus metrics at `http://<addr>/metrics`
+    pub fn with_metrics_addr(mut self, addr: SocketAddr) -> Self {
+        self.metrics_addr = Some(addr);
+        self
+    }
+
+    /// Also accept clients over TCP at `addr`; they must present a token
+    pub fn with_listen_addr(mut self, addr: SocketAddr) -> Self {
+        self.listen_addr = Some(addr);
+        self
+    }
+
+    /// Serve the TCP listener over TLS
+    pub fn with_tls(mut self, acceptor: tokio_rustls::TlsAcceptor) -> Self {
+        self.tls = Some(acceptor);
+        self
+    }
+
+    /// API tokens clients may present, by name; token holders' mutations
+    /// are audited in the repository of the session they touch, or in
+    /// `audit_repo`
+    pub fn with_api_tokens(mut self, tokens: BTreeMap<String, ApiToken>, audit_repo: PathBuf) -> Self {
+        self.access = Arc::new(AccessPolicy {
+            tokens,
+            audit_repo: Some(audit_repo),
+        });
+        self
+    }
+
     /// Run the daemon, listening for client connections
+    ///
+    /// Returns after a `Shutdown` command, Ctrl-C or SIGTERM, once the
+    /// shutdown policy has been applied and the manifest written.
     pub async fn run(&self) -> Result<()> {
+        let manifest_path = ShutdownManifest::path_for(&self.socket_path);
+        match ShutdownManifest::load(&manifest_path) {
+            Ok(Some(previous)) => {
+                for session in previous.surviving() {
+                    tracing::warn!(
+                        agent_id = %session.info.agent_id,
+                        pid = session.pid,
+                        workdir = %session.info.workdir,
+                        "Agent detached by the previous daemon is still running"
+                    );
+                }
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Ignoring unreadable shutdown manifest: {}", e),
+        }
+        {
+            let mut manager = self.manager.lock().await;
+            manager.set_nudge_ladders(self.nudge_ladders.clone());
+            manager.set_quit_sequences(self.quit_sequences.clone());
+            if let Some(capacity) = self.buffer_capacity {
+                manager.set_buffer_capacity(capacity);
+            }
+        }
+
+        let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .map_err(|e| RembrandtError::Daemon(e.to_string()))?;
+
+        // Bind metrics first so a taken port fails before the socket appears
+        let metrics = match self.metrics_addr {
+            Some(addr) => {
+                let listener = TcpListener::bind(addr)
+                    .await
+                    .map_err(|e| RembrandtError::Daemon(format!("metrics on {}: {}", addr, e)))?;
+                tracing::info!("Serving metrics on http://{}/metrics", addr);
+                Some(tokio::spawn(serve_metrics(listener, self.manager.clone())))
+            }
+            None => None,
+        };
+
+        let tcp = match self.listen_addr {
+            Some(_) if self.access.tokens.is_empty() => {
+                return Err(RembrandtError::Config(
+                    "--listen needs at least one [api_tokens] entry in config.toml".to_string(),
+                ));
+            }
+            Some(addr) => {
+                tls::check_listen_addr(addr, self.tls.is_some())?;
+                let listener = TcpListener::bind(addr)
+                    .await
+                    .map_err(|e| RembrandtError::Daemon(format!("listen on {}: {}", addr, e)))?;
+                tracing::info!(tls = self.tls.is_some(), "Accepting token-authenticated clients on {}", addr);
+                Some(listener)
+            }
+            None => None,
+        };
+
         // Remove stale socket if it exists
         if self.socket_path.exists() {
             std::fs::remove_file(&self.socket_path)
-                .map_err(|e| crate::RembrandtError::Daemon(e.to_string()))?;
+                .map_err(|e| RembrandtError::Daemon(e.to_string()))?;
         }
 
         let listener = UnixListener::bind(&self.socket_path)
-            .map_err(|e| crate::RembrandtError::Daemon(e.to_string()))?;
+            .map_err(|e| RembrandtError::Daemon(e.to_string()))?;
 
         tracing::info!("Daemon listening on {:?}", self.socket_path);
 
+        let pump = tokio::spawn(pump_output(self.manager.clone(), self.events.clone(), self.pty_io));
+        let scheduler = self
+            .schedule_repo
+            .clone()
+            .map(|repo| tokio::spawn(run_schedule(self.manager.clone(), repo)));
+
         loop {
-            match listener.accept().await {
-                Ok((stream, _addr)) => {
-                    let manager = self.manager.clone();
-                    tokio::spawn(async move {
-                        if let Err(e) = handle_client(stream, manager).await {
-                            tracing::error!("Client handler error: {}", e);
+            tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _addr)) => self.serve(stream, Access::Local, false),
+                    Err(e) => {
+                        tracing::error!("Accept error: {}", e);
+                    }
+                },
+                accepted = accept_tcp(tcp.as_ref()) => match accepted {
+                    Ok((stream, addr)) => {
+                        tracing::debug!(%addr, "TCP client connected");
+                        match &self.tls {
+                            Some(acceptor) => self.serve_tls(acceptor.clone(), stream, addr),
+                            None => self.serve(stream, Access::Anonymous, true),
                         }
-                    });
-                }
+                    }
+                    Err(e) => {
+                        tracing::error!("TCP accept error: {}", e);
+                    }
+                },
+                _ = self.shutdown.notified() => break,
+                _ = tokio::signal::ctrl_c() => break,
+                _ = sigterm.recv() => break,
+            }
+        }
+
+        tracing::info!(policy = ?self.shutdown_policy, "Daemon shutting down");
+        pump.abort();
+        if let Some(scheduler) = scheduler {
+            scheduler.abort();
+        }
+        let sessions = self.stop_sessions().await;
+        let manifest = ShutdownManifest {
+            stopped_at: chrono::Utc::now(),
+            policy: self.shutdown_policy,
+            sessions,
+        };
+        if let Err(e) = manifest.save(&manifest_path) {
+            tracing::error!("Failed to write shutdown manifest: {}", e);
+        }
+        if let Some(metrics) = metrics {
+            metrics.abort();
+        }
+        let _ = std::fs::remove_file(&self.socket_path);
+        Ok(())
+    }
+
+    /// Handle a client connection on its own task; `remote` for TCP clients
+    fn serve<S>(&self, stream: S, access: Access, remote: bool)
+    where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let client = self.client(remote);
+        tokio::spawn(async move {
+            if let Err(e) = handle_client(stream, client, access).await {
+                tracing::error!("Client handler error: {}", e);
+            }
+        });
+    }
+
+    /// Handle a TCP client after its TLS handshake, on its own task
+    fn serve_tls(&self, acceptor: tokio_rustls::TlsAcceptor, stream: TcpStream, addr: SocketAddr) {
+        let client = self.client(true);
+        tokio::spawn(async move {
+            let stream = match acceptor.accept(stream).await {
+                Ok(stream) => stream,
                 Err(e) => {
-                    tracing::error!("Accept error: {}", e);
+                    tracing::debug!(%addr, "TLS handshake failed: {}", e);
+                    return;
                 }
+            };
+            if let Err(e) = handle_client(stream, client, Access::Anonymous).await {
+                tracing::error!("Client handler error: {}", e);
             }
+        });
+    }
+
+    fn client(&self, remote: bool) -> Client {
+        Client {
+            manager: self.manager.clone(),
+            events: self.events.clone(),
+            shutdown: self.shutdown.clone(),
+            stop_grace: self.stop_grace,
+            policy: self.access.clone(),
+            remote,
+            schedule_repo: self.schedule_repo.clone(),
         }
     }
 
@@ -94,79 +505,1027 @@ impl Daemon {
     pub fn manager(&self) -> Arc<Mutex<SessionManager>> {
         self.manager.clone()
     }
+
+    /// Apply the shutdown policy and return every session's final state
+    async fn stop_sessions(&self) -> Vec<ShutdownSession> {
+        if self.shutdown_policy == ShutdownPolicy::Terminate {
+            let running: Vec<SessionId> = {
+                let mut mgr = self.manager.lock().await;
+                let ids: Vec<SessionId> = mgr.list().into_iter().map(|s| s.id).collect();
+                ids.into_iter()
+                    .filter(|id| {
+                        mgr.get_mut(id).is_some_and(|s| {
+                            let running = s.poll() == SessionStatus::Running;
+                            if running {
+                                s.terminate();
+                            }
+                            running
+                        })
+                    })
+                    .collect()
+            };
+
+            let deadline = Instant::now() + self.shutdown_grace;
+            loop {
+                let remaining: Vec<SessionId> = {
+                    let mut mgr = self.manager.lock().await;
+                    running
+                        .iter()
+                        .filter(|id| {
+                            mgr.get_mut(id)
+                                .is_some_and(|s| s.poll() == SessionStatus::Running)
+                        })
+                        .cloned()
+                        .collect()
+                };
+                if remaining.is_empty() {
+                    break;
+                }
+                if Instant::now() >= deadline {
+                    let mut mgr = self.manager.lock().await;
+                    for id in &remaining {
+                        tracing::warn!(session_id = %id, "Session ignored SIGTERM; killing it");
+                        let _ = mgr.kill(id);
+                    }
+                    break;
+                }
+                tokio::time::sleep(SHUTDOWN_POLL_INTERVAL).await;
+            }
+        }
+
+        let mut mgr = self.manager.lock().await;
+        if self.shutdown_policy == ShutdownPolicy::Detach {
+            hold_terminals(&mut mgr);
+        }
+        mgr.list()
+            .into_iter()
+            .map(|info| {
+                let pid = mgr.get(&info.id).and_then(PtySession::process_id);
+                ShutdownSession { info, pid }
+            })
+            .collect()
+    }
 }
 
-/// Handle a single client connection
+/// Hand running agents' terminals to a holder process, so they don't hang
+/// up when the daemon exits
+fn hold_terminals(mgr: &mut SessionManager) {
+    let ids: Vec<SessionId> = mgr.list().into_iter().map(|s| s.id).collect();
+    let mut terminals = Vec::new();
+    for id in &ids {
+        let Some(session) = mgr.get_mut(id) else { continue };
+        if session.poll() != SessionStatus::Running {
+            continue;
+        }
+        let Some(master) = session.master_fd() else { continue };
+        let log = session.repo.as_ref().and_then(|repo| {
+            let path = logs::log_path(repo, &session.agent_id, &session.id);
+            path.parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|_| std::fs::File::options().create(true).append(true).open(&path))
+                .inspect_err(|e| tracing::warn!("Can't write session log {}: {}", path.display(), e))
+                .ok()
+        });
+        terminals.push(detach::Terminal { master, log });
+    }
+    match detach::hold(&terminals) {
+        Ok(Some(pid)) => tracing::info!(pid, agents = terminals.len(), "Detached agents' terminals held open"),
+        Ok(None) => {}
+        Err(e) => tracing::error!("Detached agents will hang up with the daemon: {}", e),
+    }
+}
+
+/// Publish every session's newly captured output, plus one exit event per
+/// session
+async fn pump_output(manager: Arc<Mutex<SessionManager>>, events: broadcast::Sender<DaemonEvent>, pty_io: PtyIo) {
+    let mut exited: HashSet<SessionId> = HashSet::new();
+    let mut usage: HashMap<SessionId, UsageScanner> = HashMap::new();
+    let logs = logs::LogWriter::spawn();
+    let woken = Arc::new(Notify::new());
+    let mut interval = match pty_io {
+        PtyIo::Poll => tokio::time::interval(OUTPUT_POLL_INTERVAL),
+        PtyIo::Events => {
+            let notify = woken.clone();
+            manager
+                .lock()
+                .await
+                .set_output_waker(Arc::new(move || notify.notify_one()));
+            tokio::time::interval(EXIT_POLL_INTERVAL)
+        }
+    };
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut last_pass = Instant::now();
+    let mut last_sample: Option<Instant> = None;
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = woken.notified() => {
+                // A chatty agent wakes the pump on every read; batching them
+                // keeps passes no more frequent than polling
+                let since = last_pass.elapsed();
+                if since < OUTPUT_POLL_INTERVAL {
+                    tokio::time::sleep(OUTPUT_POLL_INTERVAL - since).await;
+                }
+            }
+        }
+        last_pass = Instant::now();
+        // One /proc scan serves every session, read before taking the lock
+        let processes = if last_sample.is_none_or(|at| at.elapsed() >= RESOURCE_SAMPLE_INTERVAL) {
+            last_sample = Some(last_pass);
+            tokio::task::spawn_blocking(ProcessTable::scan).await.ok()
+        } else {
+            None
+        };
+        let mut mgr = manager.lock().await;
+        if let Some(processes) = &processes {
+            mgr.sample_resources(processes);
+        }
+
+        for info in mgr.list() {
+            if exited.contains(&info.id) {
+                continue;
+            }
+            let Some(session) = mgr.get_mut(&info.id) else {
+                continue;
+            };
+
+            // Polling first means an exited session's final output is in
+            // this drain, ahead of the exit event
+            let status = session.poll();
+            let (offset, data) = session.drain_output();
+            let crash = match &session.repo {
+                Some(repo) if session.crashed() => Some(FailedAgent {
+                    agent_id: session.agent_id.clone(),
+                    repo: repo.clone(),
+                    workdir: PathBuf::from(&session.workdir),
+                    env: session.env.clone(),
+                    reason: restart::exit_reason(&status),
+                }),
+                _ => None,
+            };
+
+            // Send errors just mean nobody is attached
+            if !data.is_empty() {
+                if let Some(repo) = &session.repo {
+                    logs.append(repo, &session.agent_id, &info.id, &data);
+                    let samples = usage.entry(info.id.clone()).or_default().feed(&data);
+                    if !samples.is_empty() {
+                        let (repo, agent_id) = (repo.clone(), session.agent_id.clone());
+                        tokio::task::spawn_blocking(move || record_usage(&repo, &agent_id, &samples));
+                    }
+                }
+                let _ = events.send(DaemonEvent::Output {
+                    session_id: info.id.clone(),
+                    offset,
+                    data,
+                });
+            }
+            if let Some(event) = exit_event(&info.id, &status) {
+                tracing::info!(
+                    agent_id = %info.agent_id,
+                    session_id = %info.id,
+                    "Session {}",
+                    restart::exit_reason(&status)
+                );
+                let _ = events.send(event);
+                logs.close(&info.id);
+                if let Some(crash) = crash {
+                    tokio::task::spawn_blocking(move || run_failure_hooks(crash));
+                }
+                if let Some(attempt) = mgr.restart_due(&info.id) {
+                    tokio::spawn(
+                        restart_session(manager.clone(), info.id.clone(), attempt).instrument(tracing::info_span!(
+                            "restart",
+                            agent_id = %info.agent_id,
+                            session_id = %info.id
+                        )),
+                    );
+                }
+                exited.insert(info.id);
+            }
+        }
+
+        exited.retain(|id| mgr.get(id).is_some());
+        usage.retain(|id, _| mgr.get(id).is_some());
+    }
+}
+
+/// Record usage an agent reported in its repository's state.db
+fn record_usage(repo: &Path, agent_id: &str, samples: &[UsageSample]) {
+    let recorded = StateStore::open(repo)
+        .and_then(|store| samples.iter().try_for_each(|sample| store.record_usage(agent_id, sample)));
+    if let Err(e) = recorded {
+        tracing::warn!(agent_id, "Failed to record usage in {}: {}", repo.display(), e);
+    }
+}
+
+/// A session whose agent crashed, for its `on_failure` hooks
+struct FailedAgent {
+    agent_id: String,
+    repo: PathBuf,
+    workdir: PathBuf,
+    env: Vec<(String, String)>,
+    reason: String,
+}
+
+/// Run the `on_failure` hooks of the crashed agent's repository
 ///
-/// # TODO: Implement client handling logic
+/// They run in the agent's workdir with its environment, plus
+/// `REMBRANDT_EXIT_REASON`.
+fn run_failure_hooks(crash: FailedAgent) {
+    let _span = tracing::info_span!("on_failure", agent_id = %crash.agent_id).entered();
+    let hooks = match crate::config::AppConfig::load(&crash.repo) {
+        Ok(config) if !config.hooks.on_failure.is_empty() => config.hooks,
+        Ok(_) => return,
+        Err(e) => {
+            tracing::warn!("Skipping on_failure hooks: {}", e);
+            return;
+        }
+    };
+    let store = StateStore::open(&crash.repo)
+        .inspect_err(|e| tracing::warn!("Not recording hooks: {}", e))
+        .ok();
+    let mut env = crash.env;
+    env.push(("REMBRANDT_EXIT_REASON".to_string(), crash.reason));
+    let ctx = HookContext {
+        repo_path: &crash.repo,
+        cwd: &crash.workdir,
+        agent_id: &crash.agent_id,
+        env: &env,
+        store: store.as_ref(),
+    };
+    if let Err(e) = hooks::run(&hooks, HookPoint::OnFailure, &ctx) {
+        tracing::warn!("on_failure hooks failed: {}", e);
+    }
+}
+
+/// Respawn a crashed session once its backoff is up
+///
+/// The new agent is told it is picking up after a crash, with the tail of
+/// the old session's output, and the attempt is recorded in the state.db
+/// of the session's repository. A session stopped or removed during the
+/// backoff stays down.
+async fn restart_session(manager: Arc<Mutex<SessionManager>>, session_id: SessionId, attempt: u32) {
+    tokio::time::sleep(RestartPolicy::backoff(attempt)).await;
+
+    let (respawned, agent_id, repo, status, tail) = {
+        let mut mgr = manager.lock().await;
+        if mgr.restart_due(&session_id) != Some(attempt) {
+            return;
+        }
+        let Some(session) = mgr.get(&session_id) else {
+            return;
+        };
+        let agent_id = session.agent_id.clone();
+        let repo = session.repo.clone();
+        let status = session.status.clone();
+        let tail = session.read_last_lines(RESTART_LOG_LINES);
+        let respawned = mgr.respawn(&session_id).map_err(|e| e.to_string());
+        (respawned, agent_id, repo, status, tail)
+    };
+    let reason = restart::exit_reason(&status);
+
+    let store = repo.and_then(|repo| match StateStore::open(&repo) {
+        Ok(store) => Some(store),
+        Err(e) => {
+            tracing::warn!("Not recording restart in {}: {}", repo.display(), e);
+            None
+        }
+    });
+    let outcome = respawned.as_deref().map_err(String::as_str);
+    if let Some(store) = &store
+        && let Err(e) = store.record_restart(&agent_id, attempt, &reason, outcome)
+    {
+        tracing::warn!("Failed to record restart: {}", e);
+    }
+
+    let new_id = match respawned {
+        Ok(new_id) => new_id,
+        Err(e) => {
+            tracing::error!(attempt, "Restart failed: {}", e);
+            return;
+        }
+    };
+    tracing::info!(attempt, new_session_id = %new_id, "Restarted agent that {}", reason);
+
+    let task = store
+        .as_ref()
+        .and_then(|store| store.get_session(&agent_id).ok().flatten())
+        .and_then(|record| record.prompt);
+    let prompt = restart::restart_prompt(attempt, &status, task.as_deref(), &tail);
+    tokio::time::sleep(RESTART_PROMPT_DELAY).await;
+    if let Err(e) = manager
+        .lock()
+        .await
+        .write(&new_id, format!("{}\n", prompt).as_bytes())
+    {
+        tracing::warn!("Failed to prompt restarted agent: {}", e);
+    }
+}
+
+/// Send due scheduled steers every `SCHEDULE_POLL_INTERVAL` until aborted
+async fn run_schedule(manager: Arc<Mutex<SessionManager>>, repo: PathBuf) {
+    let mut interval = tokio::time::interval(SCHEDULE_POLL_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        if let Err(e) = send_due_steers(&manager, &repo).await {
+            tracing::warn!(repo = %repo.display(), "Scheduled steers not sent: {}", e);
+        }
+    }
+}
+
+/// Type each due steer into its agent's running session
 ///
-/// This is the core IPC handler. When a client connects:
-/// 1. Read commands from the stream
-/// 2. Execute them against the SessionManager
-/// 3. Send responses back
+/// A steer whose agent isn't running stays due until it is, unless the
+/// agent has ended; then it is dropped with a warning and a note on the
+/// agent's timeline. One steer failing doesn't hold up the others.
+async fn send_due_steers(manager: &Mutex<SessionManager>, repo: &Path) -> Result<()> {
+    let store = StateStore::open(repo)?;
+    let now = chrono::Utc::now();
+    let due: Vec<_> = store.scheduled_steers(None)?.into_iter().filter(|s| s.is_due(now)).collect();
+    for steer in due {
+        if let Err(e) = send_steer(manager, repo, &steer, now).await {
+            tracing::warn!(agent_id = %steer.agent_id, id = steer.id, "Scheduled steer not sent: {}", e);
+        }
+    }
+    Ok(())
+}
+
+async fn send_steer(
+    manager: &Mutex<SessionManager>,
+    repo: &Path,
+    steer: &crate::schedule::ScheduledSteer,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Result<()> {
+    let store = StateStore::open(repo)?;
+    let session_id = manager
+        .lock()
+        .await
+        .find_agent(repo, &steer.agent_id)
+        .filter(|session| session.is_running())
+        .map(|session| session.id.clone());
+    let Some(session_id) = session_id else {
+        if crate::schedule::agent_ended(&store, &steer.agent_id)? {
+            tracing::warn!(agent_id = %steer.agent_id, id = steer.id, "Dropping steer for an agent that has ended");
+            crate::schedule::drop_steer(&store, steer)?;
+        }
+        return Ok(());
+    };
+    for chunk in NudgeStrategy::SendMessage(steer.message.clone()).keystrokes() {
+        manager.lock().await.write(&session_id, &chunk)?;
+        tokio::time::sleep(STEER_SUBMIT_DELAY).await;
+    }
+    tracing::info!(agent_id = %steer.agent_id, id = steer.id, "Sent scheduled steer");
+    crate::schedule::mark_sent(&store, steer, now)
+}
+
+/// Answer `GET /metrics` scrapes until aborted
+async fn serve_metrics(listener: TcpListener, manager: Arc<Mutex<SessionManager>>) {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _addr)) => stream,
+            Err(e) => {
+                tracing::error!("Metrics accept error: {}", e);
+                continue;
+            }
+        };
+        let manager = manager.clone();
+        tokio::spawn(async move {
+            if let Err(e) = answer_scrape(stream, &manager).await {
+                tracing::debug!("Metrics request failed: {}", e);
+            }
+        });
+    }
+}
+
+/// Minimal HTTP/1.1 responder: only the request line matters
+async fn answer_scrape(stream: TcpStream, manager: &Mutex<SessionManager>) -> Result<()> {
+    let (read_half, mut write_half) = stream.into_split();
+    let mut reader = BufReader::new(read_half);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    // Skip the headers
+    let mut header = String::new();
+    while reader.read_line(&mut header).await? > 0 && !header.trim_end().is_empty() {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (status, content_type, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => {
+            let sessions = manager.lock().await.list();
+            (
+                "200 OK",
+                "text/plain; version=0.0.4; charset=utf-8",
+                rembrandt_core::metrics::render(&sessions),
+            )
+        }
+        _ => ("404 Not Found", "text/plain", "not found\n".to_string()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    write_half.write_all(response.as_bytes()).await?;
+    write_half.shutdown().await?;
+    Ok(())
+}
+
+/// The event announcing that a session is no longer running, if it isn't
+fn exit_event(session_id: &str, status: &SessionStatus) -> Option<DaemonEvent> {
+    match status {
+        SessionStatus::Running => None,
+        SessionStatus::Exited(code) => Some(DaemonEvent::Exited {
+            session_id: session_id.to_string(),
+            code: *code,
+        }),
+        SessionStatus::Failed(_) => Some(DaemonEvent::StatusChanged {
+            session_id: session_id.to_string(),
+            status: status.clone(),
+        }),
+    }
+}
+
+/// What a connection's writer task sends next
+enum Outgoing {
+    Message(Box<DaemonResponse>),
+    /// Frame later messages this way
+    SetFraming(Framing),
+}
+
+/// Handle a single client connection
 ///
-/// For `Attach` commands, you'll need to:
-/// - Send buffered history first
-/// - Then stream new output as it arrives
+/// Reads newline-delimited `DaemonCommand`s and answers each with a
+/// `DaemonResponse`. Attached sessions stream `Event`s over the same
+/// connection; a single writer task keeps messages whole and in order, and
+/// switches framing between messages so a `SetFraming` reply still goes out
+/// in the old one.
 ///
-/// Consider:
-/// - How to frame messages (length-prefix? newline-delimited JSON?)
-/// - How to handle multiple attached clients to same session
-/// - Error handling and recovery
-async fn handle_client(
-    stream: UnixStream,
+/// `access` starts as the listener grants it and changes when the client
+/// presents a token; commands its role doesn't allow are refused.
+async fn handle_client<S>(stream: S, client: Client, mut access: Access) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let Client { manager, events, shutdown, stop_grace, policy, remote, schedule_repo } = client;
+    let (read_half, mut write_half) = tokio::io::split(stream);
+    let (tx, mut rx) = mpsc::channel::<Outgoing>(OUTGOING_CAPACITY);
+
+    let writer = tokio::spawn(async move {
+        let mut framing = Framing::Json;
+        while let Some(outgoing) = rx.recv().await {
+            match outgoing {
+                Outgoing::Message(message) => {
+                    write_half.write_all(&framing.encode(&message)?).await?;
+                }
+                Outgoing::SetFraming(next) => framing = next,
+            }
+        }
+        Ok::<_, RembrandtError>(())
+    });
+
+    let mut lines = BufReader::new(read_half).lines();
+    let mut attachments: HashMap<SessionId, JoinHandle<()>> = HashMap::new();
+    // Sessions this connection observes without being allowed to type
+    let mut read_only: HashSet<SessionId> = HashSet::new();
+    let mut shutdown_requested = false;
+
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let command = match ipc::decode_message::<DaemonCommand>(line.as_bytes()) {
+            Ok(command) => command,
+            Err(e) => {
+                let _ = send(&tx, DaemonResponse::error(&e)).await;
+                continue;
+            }
+        };
+
+        if let DaemonCommand::Authenticate { token } = &command {
+            let response = match policy.authenticate(token) {
+                Ok(granted) => {
+                    let message = match &granted {
+                        Access::Token { name, role } => format!("{} ({})", role, name),
+                        _ => String::new(),
+                    };
+                    access = granted;
+                    DaemonResponse::Ok { message: Some(message) }
+                }
+                Err(e) => DaemonResponse::error(&e),
+            };
+            if !send(&tx, response).await {
+                break;
+            }
+            continue;
+        }
+        let mut command = command;
+        let allowed = match access.check(&command) {
+            Ok(()) if remote => {
+                let repos = served_repos(&policy, schedule_repo.as_deref(), &manager).await;
+                auth::restrict_remote(&mut command, &repos)
+            }
+            checked => checked,
+        };
+        if let Err(e) = allowed {
+            if !send(&tx, DaemonResponse::error(&e)).await {
+                break;
+            }
+            continue;
+        }
+        if matches!(access, Access::Token { .. }) {
+            audit_command(&policy, &access, &command, &manager).await;
+        }
+
+        let response = match command {
+            DaemonCommand::Write { session_id, .. } | DaemonCommand::Resize { session_id, .. }
+                if read_only.contains(&session_id) =>
+            {
+                DaemonResponse::error(&RembrandtError::Validation(format!(
+                    "attached to {} read-only; input is not forwarded",
+                    session_id
+                )))
+            }
+            DaemonCommand::Attach { session_id, read_only: observe } => {
+                if observe {
+                    read_only.insert(session_id.clone());
+                } else {
+                    read_only.remove(&session_id);
+                }
+                // Subscribe before snapshotting so nothing falls in between;
+                // the client drops events already covered by the history
+                let receiver = events.subscribe();
+                let snapshot = {
+                    let mgr = manager.lock().await;
+                    mgr.get(&session_id).map(|s| {
+                        let (data, offset) = s.output_snapshot();
+                        (data, offset, s.status.clone())
+                    })
+                };
+                match snapshot {
+                    Some((data, offset, status)) => {
+                        let _ = send(&tx, DaemonResponse::Output { data, offset }).await;
+                        if let Some(event) = exit_event(&session_id, &status) {
+                            let _ = send(&tx, DaemonResponse::Event { event }).await;
+                        } else {
+                            let forwarder =
+                                forward_events(session_id.clone(), receiver, tx.clone());
+                            if let Some(previous) = attachments.insert(session_id, forwarder) {
+                                previous.abort();
+                            }
+                        }
+                        continue;
+                    }
+                    None => DaemonResponse::error(&RembrandtError::SessionNotFound(session_id)),
+                }
+            }
+            DaemonCommand::Detach { session_id } => {
+                read_only.remove(&session_id);
+                if let Some(forwarder) = attachments.remove(&session_id) {
+                    forwarder.abort();
+                }
+                DaemonResponse::Ok { message: None }
+            }
+            DaemonCommand::SetFraming { framing } => {
+                let _ = send(&tx, DaemonResponse::Ok { message: None }).await;
+                let _ = tx.send(Outgoing::SetFraming(framing)).await;
+                continue;
+            }
+            // These run outside `execute` so the manager isn't locked while they wait
+            DaemonCommand::Nudge { session_id } => nudge_session(&manager, &session_id).await,
+            DaemonCommand::Stop { session_id, grace_secs } => {
+                let grace = grace_secs.map_or(stop_grace, Duration::from_secs);
+                stop_session(&manager, &session_id, grace)
+                    .instrument(tracing::info_span!("stop", session_id = %session_id))
+                    .await
+            }
+            DaemonCommand::Merge { repo, agent_id, base, validate } => {
+                admin::merge(repo, agent_id, base, validate).await
+            }
+            DaemonCommand::GetConfig { repo } => admin::config(&repo),
+            DaemonCommand::SetConfig { repo, config } => admin::set_config(&repo, &config),
+            DaemonCommand::Ping => DaemonResponse::Pong {
+                schedule_repo: schedule_repo.clone().filter(|_| !remote),
+            },
+            DaemonCommand::Shutdown => {
+                shutdown_requested = true;
+                let _ = send(&tx, DaemonResponse::Ok {
+                    message: Some("Shutting down".to_string()),
+                })
+                .await;
+                break;
+            }
+            command => execute(command, &manager).await,
+        };
+
+        if !send(&tx, response).await {
+            break;
+        }
+    }
+
+    for forwarder in attachments.values() {
+        forwarder.abort();
+    }
+    drop(tx);
+    let written = writer
+        .await
+        .map_err(|e| RembrandtError::Daemon(e.to_string()))?;
+
+    if shutdown_requested {
+        shutdown.notify_one();
+    }
+    written
+}
+
+/// What a connection handler shares with the daemon
+struct Client {
     manager: Arc<Mutex<SessionManager>>,
-) -> Result<()> {
-    // YOUR IMPLEMENTATION HERE
-    //
-    // Suggested approach:
-    //
-    // 1. Choose a framing protocol. Options:
-    //    a) Length-prefixed: [4-byte len][JSON payload]
-    //    b) Newline-delimited JSON (simpler, slightly less efficient)
-    //
-    // 2. Read loop:
-    //    - Read a command from the stream
-    //    - Deserialize to DaemonCommand
-    //    - Match on command type and execute
-    //    - Serialize response to DaemonResponse
-    //    - Write response to stream
-    //
-    // 3. For Attach:
-    //    - Get session's output buffer
-    //    - Send history as DaemonResponse::Output
-    //    - Switch to streaming mode: spawn a task that reads from
-    //      the PTY and sends DaemonEvent::Output
-    //    - Keep reading commands (Detach, Write, etc.)
-    //
-    // Example skeleton:
-    //
-    // let (reader, writer) = stream.into_split();
-    // let mut reader = BufReader::new(reader);
-    // let mut writer = BufWriter::new(writer);
-    //
-    // loop {
-    //     let mut line = String::new();
-    //     reader.read_line(&mut line).await?;
-    //     if line.is_empty() { break; }
-    //
-    //     let cmd: DaemonCommand = serde_json::from_str(&line)?;
-    //     let response = match cmd {
-    //         DaemonCommand::Ping => DaemonResponse::Pong,
-    //         DaemonCommand::List => {
-    //             let mgr = manager.lock().await;
-    //             DaemonResponse::Sessions { sessions: mgr.list() }
-    //         }
-    //         // ... handle other commands
-    //     };
-    //
-    //     let json = serde_json::to_string(&response)?;
-    //     writer.write_all(json.as_bytes()).await?;
-    //     writer.write_all(b"\n").await?;
-    //     writer.flush().await?;
-    // }
-
-    todo!("Implement client handling")
+    events: broadcast::Sender<DaemonEvent>,
+    shutdown: Arc<Notify>,
+    stop_grace: Duration,
+    policy: Arc<AccessPolicy>,
+    /// Connected over TCP rather than the Unix socket
+    remote: bool,
+    /// Repository whose scheduled steers this daemon sends
+    schedule_repo: Option<PathBuf>,
+}
+
+/// Accept on the TCP listener, or wait forever without one
+async fn accept_tcp(listener: Option<&TcpListener>) -> std::io::Result<(TcpStream, SocketAddr)> {
+    match listener {
+        Some(listener) => listener.accept().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Audit a token holder's command against the session's repository
+async fn audit_command(
+    policy: &AccessPolicy,
+    access: &Access,
+    command: &DaemonCommand,
+    manager: &Mutex<SessionManager>,
+) {
+    let (repo, agent_id) = match command {
+        DaemonCommand::Spawn { agent_id, workdir, .. } => {
+            (crate::worktree::main_repo_root(workdir), Some(agent_id.clone()))
+        }
+        DaemonCommand::Merge { repo, agent_id, .. } => (Some(repo.clone()), Some(agent_id.clone())),
+        DaemonCommand::SetConfig { repo, .. } => (Some(repo.clone()), None),
+        DaemonCommand::Write { session_id, .. }
+        | DaemonCommand::Nudge { session_id }
+        | DaemonCommand::Stop { session_id, .. }
+        | DaemonCommand::Kill { session_id }
+        | DaemonCommand::Pause { session_id }
+        | DaemonCommand::Resume { session_id } => {
+            let mgr = manager.lock().await;
+            match mgr.get(session_id).map(SessionInfo::from) {
+                Some(info) => (info.repo, Some(info.agent_id)),
+                None => (None, None),
+            }
+        }
+        _ => (None, None),
+    };
+    policy.audit(access, command, repo.as_deref(), agent_id.as_deref());
+}
+
+/// Repositories this daemon serves: its own, and those its sessions run in
+async fn served_repos(policy: &AccessPolicy, schedule_repo: Option<&Path>, manager: &Mutex<SessionManager>) -> Vec<PathBuf> {
+    let mut repos: Vec<PathBuf> = policy
+        .audit_repo
+        .as_deref()
+        .into_iter()
+        .chain(schedule_repo)
+        .filter_map(crate::worktree::main_repo_root)
+        .collect();
+    for session in manager.lock().await.list() {
+        if let Some(repo) = session.repo
+            && !repos.contains(&repo)
+        {
+            repos.push(repo);
+        }
+    }
+    repos
+}
+
+/// Nudge a session with its ladder's next step, locking the manager only
+/// for each keystroke rather than across the pauses between them
+async fn nudge_session(manager: &Mutex<SessionManager>, session_id: &str) -> DaemonResponse {
+    let strategy = match manager.lock().await.next_nudge(session_id) {
+        Ok(strategy) => strategy,
+        Err(e) => return DaemonResponse::error(&e),
+    };
+    for (i, keys) in strategy.keystrokes().into_iter().enumerate() {
+        if i > 0 {
+            tokio::time::sleep(session::NUDGE_KEY_DELAY).await;
+        }
+        if let Err(e) = manager.lock().await.write(session_id, &keys) {
+            return DaemonResponse::error(&e);
+        }
+    }
+    DaemonResponse::Ok {
+        message: Some(strategy.describe()),
+    }
+}
+
+/// Stop a session, escalating until it exits: its quit sequence, SIGTERM
+/// to its process group, then SIGKILL, with `grace` between steps
+async fn stop_session(manager: &Mutex<SessionManager>, session_id: &str, grace: Duration) -> DaemonResponse {
+    type Step = fn(&mut SessionManager, &str) -> Result<()>;
+    let steps: [(&str, Step); 3] = [
+        ("quit sequence", |mgr, id| mgr.send_quit(id)),
+        ("SIGTERM", |mgr, id| mgr.terminate(id)),
+        ("SIGKILL", |mgr, id| mgr.kill(id)),
+    ];
+
+    let mut ended_by = None;
+    for (name, step) in steps {
+        {
+            let mut mgr = manager.lock().await;
+            let Some(session) = mgr.get_mut(session_id) else {
+                return DaemonResponse::error(&RembrandtError::SessionNotFound(session_id.to_string()));
+            };
+            if session.poll() != SessionStatus::Running {
+                // A crashed agent waiting to be restarted stays down
+                let _ = mgr.set_restart_policy(session_id, RestartPolicy::Never);
+                break;
+            }
+            ended_by = Some(name);
+            if let Err(e) = step(&mut mgr, session_id) {
+                tracing::warn!(step = name, "Stop step failed: {}", e);
+            }
+        }
+
+        let deadline = Instant::now() + grace;
+        loop {
+            let running = manager
+                .lock()
+                .await
+                .get_mut(session_id)
+                .is_some_and(|s| s.poll() == SessionStatus::Running);
+            if !running {
+                return DaemonResponse::Ok {
+                    message: Some(format!("stopped by {}", name)),
+                };
+            }
+            if Instant::now() >= deadline {
+                break;
+            }
+            tokio::time::sleep(SHUTDOWN_POLL_INTERVAL).await;
+        }
+    }
+
+    DaemonResponse::Ok {
+        message: Some(match ended_by {
+            Some(name) => format!("stopped by {}", name),
+            None => "already exited".to_string(),
+        }),
+    }
+}
+
+/// Queue a message for a client's writer; false once the writer is gone
+async fn send(tx: &mpsc::Sender<Outgoing>, message: DaemonResponse) -> bool {
+    tx.send(Outgoing::Message(Box::new(message))).await.is_ok()
+}
+
+/// Forward one session's events to an attached client until it exits
+///
+/// Output is coalesced while the client's writer is busy, so a chatty agent
+/// costs one message per write the client can keep up with rather than one
+/// per read. A client that falls more than `MAX_PENDING_OUTPUT` behind loses
+/// the oldest live bytes; it sees the gap in the next offset and can refetch
+/// history.
+fn forward_events(
+    session_id: SessionId,
+    mut receiver: broadcast::Receiver<DaemonEvent>,
+    tx: mpsc::Sender<Outgoing>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut pending = PendingOutput::new(MAX_PENDING_OUTPUT);
+        loop {
+            tokio::select! {
+                biased;
+                permit = tx.reserve(), if !pending.is_empty() => {
+                    let Ok(permit) = permit else { break };
+                    if let Some((offset, data)) = pending.pop() {
+                        permit.send(Outgoing::Message(Box::new(DaemonResponse::Event {
+                            event: DaemonEvent::Output { session_id: session_id.clone(), offset, data },
+                        })));
+                    }
+                }
+                received = receiver.recv() => {
+                    let event = match received {
+                        Ok(event) => event,
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            // The lost output shows up as a gap in the next offset
+                            tracing::warn!(session_id = %session_id, skipped, "Client lagged behind live output");
+                            continue;
+                        }
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    };
+                    if event.session_id() != session_id {
+                        continue;
+                    }
+                    match event {
+                        DaemonEvent::Output { offset, data, .. } => pending.push(offset, data),
+                        event => {
+                            // Deliver what's left before the exit
+                            while let Some((offset, data)) = pending.pop() {
+                                let output = DaemonEvent::Output { session_id: session_id.clone(), offset, data };
+                                if !send(&tx, DaemonResponse::Event { event: output }).await {
+                                    return;
+                                }
+                            }
+                            let _ = send(&tx, DaemonResponse::Event { event }).await;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// Live output waiting for a slow client
+///
+/// Contiguous chunks are merged; a gap (output the broadcast channel dropped)
+/// starts a new chunk. Past `limit` bytes the oldest are dropped and counted.
+struct PendingOutput {
+    chunks: VecDeque<(usize, Vec<u8>)>,
+    len: usize,
+    limit: usize,
+    /// Offset the next chunk should start at
+    expected: Option<usize>,
+    /// Bytes this client never received
+    dropped: usize,
+}
+
+impl PendingOutput {
+    fn new(limit: usize) -> Self {
+        Self {
+            chunks: VecDeque::new(),
+            len: 0,
+            limit,
+            expected: None,
+            dropped: 0,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    fn push(&mut self, offset: usize, data: Vec<u8>) {
+        if let Some(expected) = self.expected
+            && offset > expected
+        {
+            self.drop_bytes(offset - expected);
+        }
+        self.expected = Some(offset + data.len());
+        self.len += data.len();
+
+        match self.chunks.back_mut() {
+            Some((start, chunk)) if *start + chunk.len() == offset => chunk.extend(data),
+            _ => self.chunks.push_back((offset, data)),
+        }
+
+        while self.len > self.limit {
+            let excess = self.len - self.limit;
+            let Some((start, chunk)) = self.chunks.front_mut() else {
+                break;
+            };
+            if chunk.len() <= excess {
+                let len = chunk.len();
+                self.chunks.pop_front();
+                self.len -= len;
+                self.drop_bytes(len);
+            } else {
+                chunk.drain(..excess);
+                *start += excess;
+                self.len -= excess;
+                self.drop_bytes(excess);
+            }
+        }
+    }
+
+    /// The oldest chunk, as one output event's offset and data
+    fn pop(&mut self) -> Option<(usize, Vec<u8>)> {
+        let (offset, data) = self.chunks.pop_front()?;
+        self.len -= data.len();
+        Some((offset, data))
+    }
+
+    fn drop_bytes(&mut self, bytes: usize) {
+        self.dropped += bytes;
+        rembrandt_core::metrics::record_dropped_output(bytes);
+    }
+}
+
+/// Execute a request/response command against the session manager
+async fn execute(command: DaemonCommand, manager: &Mutex<SessionManager>) -> DaemonResponse {
+    let ok = |result: Result<()>| match result {
+        Ok(()) => DaemonResponse::Ok { message: None },
+        Err(e) => DaemonResponse::error(&e),
+    };
+    let mut mgr = manager.lock().await;
+
+    match command {
+        DaemonCommand::Spawn {
+            agent_id,
+            command,
+            args,
+            env,
+            workdir,
+            rows,
+            cols,
+            buffer_capacity,
+            restart,
+        } => {
+            let args: Vec<&str> = args.iter().map(String::as_str).collect();
+            let agent = agent_id.clone();
+            let spawned = mgr
+                .spawn_with_env(agent_id, &command, &args, &env, &workdir, rows, cols)
+                .and_then(|session_id| match buffer_capacity {
+                    Some(capacity) => mgr.resize_buffer(&session_id, capacity).map(|_| session_id),
+                    None => Ok(session_id),
+                })
+                .and_then(|session_id| {
+                    mgr.set_restart_policy(&session_id, restart).map(|_| session_id)
+                });
+            match spawned {
+                Ok(session_id) => {
+                    tracing::info!(agent_id = %agent, session_id = %session_id, %command, "Spawned session");
+                    DaemonResponse::Spawned { session_id }
+                }
+                Err(e) => DaemonResponse::error(&e),
+            }
+        }
+        DaemonCommand::Write { session_id, data } => ok(mgr.write(&session_id, &data)),
+        DaemonCommand::Kill { session_id } => ok(mgr.kill(&session_id)),
+        DaemonCommand::Pause { session_id } => ok(mgr.pause(&session_id)),
+        DaemonCommand::Resume { session_id } => ok(mgr.resume(&session_id)),
+        DaemonCommand::Resize {
+            session_id,
+            rows,
+            cols,
+        } => ok(mgr.resize(&session_id, rows, cols)),
+        DaemonCommand::List { repo } => DaemonResponse::Sessions {
+            sessions: match repo {
+                Some(repo) => mgr.list_by_repo(&repo),
+                None => mgr.list(),
+            },
+        },
+        DaemonCommand::ListByAgent { agent_id, repo } => {
+            let mut sessions = mgr.list_by_agent(&agent_id);
+            if let Some(repo) = repo {
+                let in_repo = mgr.list_by_repo(&repo);
+                sessions.retain(|s| in_repo.iter().any(|r| r.id == s.id));
+            }
+            DaemonResponse::Sessions { sessions }
+        }
+        DaemonCommand::GetSession { session_id } => match mgr.get(&session_id) {
+            Some(session) => DaemonResponse::Session {
+                info: SessionInfo::from(session),
+            },
+            None => DaemonResponse::error(&RembrandtError::SessionNotFound(session_id)),
+        },
+        DaemonCommand::GetHistory { session_id } => match mgr.get(&session_id) {
+            Some(session) => {
+                let (data, offset) = session.output_snapshot();
+                DaemonResponse::Output { data, offset }
+            }
+            None => DaemonResponse::error(&RembrandtError::SessionNotFound(session_id)),
+        },
+        DaemonCommand::GetScreen { session_id } => match mgr.get_screen(&session_id) {
+            Ok(screen) => DaemonResponse::Screen { screen },
+            Err(e) => DaemonResponse::error(&e),
+        },
+        DaemonCommand::RecordCompetition { outcome, validation_ms } => {
+            let validations: Vec<Duration> = validation_ms.into_iter().map(Duration::from_millis).collect();
+            ok(rembrandt_core::metrics::record_competition(&outcome, &validations))
+        }
+        DaemonCommand::Ping
+        | DaemonCommand::Attach { .. }
+        | DaemonCommand::Detach { .. }
+        | DaemonCommand::SetFraming { .. }
+        | DaemonCommand::Authenticate { .. }
+        | DaemonCommand::Nudge { .. }
+        | DaemonCommand::Stop { .. }
+        | DaemonCommand::Merge { .. }
+        | DaemonCommand::GetConfig { .. }
+        | DaemonCommand::SetConfig { .. }
+        | DaemonCommand::Shutdown => {
+            DaemonResponse::error(&RembrandtError::Validation(
+                "Connection-level command".to_string(),
+            ))
+        }
+    }
 }
 
 /// Daemon client for TUI/CLI to communicate with daemon
@@ -184,12 +1543,729 @@ impl DaemonClient {
     pub async fn connect(&self) -> Result<UnixStream> {
         UnixStream::connect(&self.socket_path)
             .await
-            .map_err(|e| crate::RembrandtError::Daemon(e.to_string()))
+            .map_err(|e| RembrandtError::Daemon(e.to_string()))
+    }
+
+    /// Send one command on a fresh connection and return the response
+    pub async fn request(&self, command: &DaemonCommand) -> Result<DaemonResponse> {
+        let mut stream = self.connect().await?;
+        stream.write_all(&ipc::encode_message(command)?).await?;
+
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line).await?;
+        if line.is_empty() {
+            return Err(RembrandtError::Daemon(
+                "Daemon closed the connection".to_string(),
+            ));
+        }
+        ipc::decode_message(line.as_bytes())
     }
+}
 
-    // TODO: Add convenience methods for each command
-    // pub async fn spawn(...) -> Result<SessionId>
-    // pub async fn list() -> Result<Vec<SessionInfo>>
-    // pub async fn nudge(id: &str) -> Result<()>
-    // etc.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rembrandt_core::ipc::DaemonConnection;
+
+    fn connect(socket: &std::path::Path) -> DaemonConnection {
+        for _ in 0..100 {
+            if let Ok(conn) = DaemonConnection::connect(socket) {
+                return conn;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("daemon never came up");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn attach_streams_output_until_exit() {
+        // Woken by the readers, and on a timer
+        for pty_io in [PtyIo::Events, PtyIo::Poll] {
+            let dir = tempfile::tempdir().unwrap();
+            let socket = dir.path().join("daemon.sock");
+            let daemon = Arc::new(Daemon::new(socket.clone()).with_pty_io(pty_io));
+            let server = {
+                let daemon = daemon.clone();
+                tokio::spawn(async move { daemon.run().await })
+            };
+
+            let workdir = dir.path().to_path_buf();
+            tokio::task::spawn_blocking(move || {
+                let mut conn = connect(&socket);
+                conn.ping().unwrap();
+
+                let session_id = conn
+                    .spawn(
+                        "test-agent".to_string(),
+                        "sh".to_string(),
+                        vec!["-c".to_string(), "sleep 0.2; echo hello-$WHO".to_string()],
+                        vec![("WHO".to_string(), "daemon".to_string())],
+                        workdir,
+                        None,
+                        None,
+                        None,
+                        RestartPolicy::Never,
+                    )
+                    .unwrap();
+
+                let mut attached = DaemonConnection::connect(&socket).unwrap();
+                attached.set_framing(Framing::Binary).unwrap();
+                attached.ping().unwrap();
+                let (mut output, _) = attached.attach(&session_id).unwrap();
+                loop {
+                    match attached.next_event().unwrap() {
+                        Some(DaemonEvent::Output { data, .. }) => output.extend(data),
+                        Some(DaemonEvent::Exited { code, .. }) => {
+                            assert_eq!(code, 0);
+                            break;
+                        }
+                        other => panic!("unexpected event: {:?}", other),
+                    }
+                }
+                assert!(String::from_utf8_lossy(&output).contains("hello-daemon"));
+
+                let sessions = conn.list(None).unwrap();
+                assert_eq!(sessions.len(), 1);
+                assert_eq!(sessions[0].status, SessionStatus::Exited(0));
+
+                conn.request(&DaemonCommand::Shutdown).unwrap();
+            })
+            .await
+            .unwrap();
+
+            server.await.unwrap().unwrap();
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn read_only_attachment_refuses_input() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("daemon.sock");
+        let daemon = Arc::new(Daemon::new(socket.clone()));
+        let server = {
+            let daemon = daemon.clone();
+            tokio::spawn(async move { daemon.run().await })
+        };
+
+        let workdir = dir.path().to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = connect(&socket);
+            let session_id = conn
+                .spawn(
+                    "observed".to_string(),
+                    "sh".to_string(),
+                    vec!["-c".to_string(), "read line; echo got-$line".to_string()],
+                    Vec::new(),
+                    workdir,
+                    None,
+                    None,
+                    None,
+                    RestartPolicy::Never,
+                )
+                .unwrap();
+
+            let mut observer = DaemonConnection::connect(&socket).unwrap();
+            observer.attach_read_only(&session_id).unwrap();
+            let refused = observer.write(&session_id, b"sneaky\r".to_vec()).unwrap_err();
+            assert!(refused.to_string().contains("read-only"), "{}", refused);
+            assert!(observer.resize(&session_id, 10, 10).is_err());
+
+            // Other connections still drive the session
+            conn.write(&session_id, b"hi\r".to_vec()).unwrap();
+            let mut output = Vec::new();
+            loop {
+                match observer.next_event().unwrap() {
+                    Some(DaemonEvent::Output { data, .. }) => output.extend(data),
+                    Some(DaemonEvent::Exited { .. }) => break,
+                    Some(_) => {}
+                    None => panic!("daemon closed the attachment before the agent exited"),
+                }
+            }
+            let output = String::from_utf8_lossy(&output);
+            assert!(output.contains("got-hi") && !output.contains("got-sneaky"), "{}", output);
+
+            conn.request(&DaemonCommand::Shutdown).unwrap();
+        })
+        .await
+        .unwrap();
+
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn crashed_agent_is_restarted_with_its_log_tail() {
+        let dir = tempfile::tempdir().unwrap();
+        git2::Repository::init(dir.path()).unwrap();
+        let socket = dir.path().join("daemon.sock");
+        let daemon = Arc::new(Daemon::new(socket.clone()));
+        let server = {
+            let daemon = daemon.clone();
+            tokio::spawn(async move { daemon.run().await })
+        };
+
+        let workdir = dir.path().to_path_buf();
+        let repo = workdir.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = connect(&socket);
+            // Crashes the first time; the second run echoes its prompt
+            let script = "if [ -e crashed ]; then read line; echo \"got: $line\"; \
+                          else touch crashed; echo boom; exit 3; fi";
+            let first = conn
+                .spawn(
+                    "crashy".to_string(),
+                    "sh".to_string(),
+                    vec!["-c".to_string(), script.to_string()],
+                    Vec::new(),
+                    workdir,
+                    None,
+                    None,
+                    None,
+                    RestartPolicy::OnFailure { max_retries: 2 },
+                )
+                .unwrap();
+
+            let deadline = Instant::now() + Duration::from_secs(15);
+            let restarted = loop {
+                let sessions = conn.list(None).unwrap();
+                if let [session] = sessions.as_slice()
+                    && session.id != first
+                    && session.status == SessionStatus::Exited(0)
+                {
+                    break session.id.clone();
+                }
+                assert!(Instant::now() < deadline, "agent was not restarted: {:?}", sessions);
+                std::thread::sleep(Duration::from_millis(50));
+            };
+
+            let (history, _) = conn.history(&restarted).unwrap();
+            let history = String::from_utf8_lossy(&history);
+            assert!(history.contains("got: Your previous session"), "{}", history);
+            assert!(history.contains("exited with code 3"));
+            assert!(history.contains("Its last output was: boom"));
+
+            let store = StateStore::open(&repo).unwrap();
+            let restarts = store.restarts("crashy").unwrap();
+            assert_eq!(restarts.len(), 1);
+            assert_eq!(restarts[0].attempt, 1);
+            assert_eq!(restarts[0].reason, "exited with code 3");
+            assert_eq!(restarts[0].session_id.as_deref(), Some(restarted.as_str()));
+
+            conn.request(&DaemonCommand::Shutdown).unwrap();
+        })
+        .await
+        .unwrap();
+
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn usage_an_agent_prints_is_recorded_in_its_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        git2::Repository::init(dir.path()).unwrap();
+        let socket = dir.path().join("daemon.sock");
+        let daemon = Arc::new(Daemon::new(socket.clone()));
+        let server = {
+            let daemon = daemon.clone();
+            tokio::spawn(async move { daemon.run().await })
+        };
+
+        let repo = dir.path().to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = connect(&socket);
+            let store = StateStore::open(&repo).unwrap();
+            let event = r#"{"type":"result","total_cost_usd":0.5,"usage":{"input_tokens":100,"output_tokens":20}}"#;
+            conn.spawn(
+                "spender".to_string(),
+                "sh".to_string(),
+                vec!["-c".to_string(), format!("echo '{}'; sleep 5", event)],
+                Vec::new(),
+                repo.clone(),
+                None,
+                None,
+                None,
+                RestartPolicy::Never,
+            )
+            .unwrap();
+
+            let deadline = Instant::now() + Duration::from_secs(10);
+            let totals = loop {
+                let totals = store.usage_for("spender").unwrap();
+                if totals.samples > 0 {
+                    break totals;
+                }
+                assert!(Instant::now() < deadline, "usage was not recorded");
+                std::thread::sleep(Duration::from_millis(50));
+            };
+            assert_eq!((totals.input_tokens, totals.output_tokens), (100, 20));
+            assert_eq!(totals.cost_usd, 0.5);
+
+            conn.request(&DaemonCommand::Shutdown).unwrap();
+        })
+        .await
+        .unwrap();
+
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn stop_escalates_until_the_session_exits() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("daemon.sock");
+        let daemon = Arc::new(Daemon::new(socket.clone()));
+        let server = {
+            let daemon = daemon.clone();
+            tokio::spawn(async move { daemon.run().await })
+        };
+
+        let workdir = dir.path().to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = connect(&socket);
+            let mut spawn = |script: &str| {
+                conn.spawn(
+                    "stubborn".to_string(),
+                    "sh".to_string(),
+                    vec!["-c".to_string(), script.to_string()],
+                    Vec::new(),
+                    workdir.clone(),
+                    None,
+                    None,
+                    None,
+                    RestartPolicy::Never,
+                )
+                .unwrap()
+            };
+            // Ctrl+C ends a plain shell loop; one ignoring SIGINT needs SIGTERM,
+            // and one ignoring both needs SIGKILL
+            let polite = spawn("while true; do sleep 0.1; done");
+            let deaf = spawn("trap '' INT; trap 'exit 0' TERM; while true; do sleep 0.1; done");
+            let stubborn = spawn("trap '' INT TERM; while true; do sleep 0.1; done");
+            std::thread::sleep(Duration::from_millis(300));
+
+            assert_eq!(conn.stop(&polite, Some(2)).unwrap(), "stopped by quit sequence");
+            assert_eq!(conn.stop(&deaf, Some(1)).unwrap(), "stopped by SIGTERM");
+            assert_eq!(conn.stop(&stubborn, Some(1)).unwrap(), "stopped by SIGKILL");
+            assert_eq!(conn.stop(&polite, Some(1)).unwrap(), "already exited");
+            assert!(conn.stop("missing", Some(1)).is_err());
+
+            conn.request(&DaemonCommand::Shutdown).unwrap();
+        })
+        .await
+        .unwrap();
+
+        server.await.unwrap().unwrap();
+    }
+
+    // `rembrandt kill`, early-stopped and cancelled competitors, and the GUI
+    // when it runs through the daemon all end up in the Kill command
+    #[cfg(target_os = "linux")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn kill_takes_helpers_that_left_the_agents_session() {
+        fn alive(pid: u32) -> bool {
+            std::fs::read_to_string(format!("/proc/{pid}/stat"))
+                .is_ok_and(|stat| !matches!(stat.rsplit_once(") ").map(|(_, r)| &r[..1]), Some("Z")))
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("daemon.sock");
+        let daemon = Arc::new(Daemon::new(socket.clone()));
+        let server = {
+            let daemon = daemon.clone();
+            tokio::spawn(async move { daemon.run().await })
+        };
+
+        let workdir = dir.path().to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = connect(&socket);
+            let session = conn
+                .spawn(
+                    "spawner".to_string(),
+                    "sh".to_string(),
+                    vec![
+                        "-c".to_string(),
+                        "setsid sh -c 'echo $$ > helper.tmp && mv helper.tmp helper.pid; exec sleep 60' & wait"
+                            .to_string(),
+                    ],
+                    Vec::new(),
+                    workdir.clone(),
+                    None,
+                    None,
+                    None,
+                    RestartPolicy::Never,
+                )
+                .unwrap();
+
+            let deadline = Instant::now() + Duration::from_secs(10);
+            let helper: u32 = loop {
+                if let Some(pid) = std::fs::read_to_string(workdir.join("helper.pid"))
+                    .ok()
+                    .and_then(|pid| pid.trim().parse().ok())
+                {
+                    break pid;
+                }
+                assert!(Instant::now() < deadline, "helper did not start");
+                std::thread::sleep(Duration::from_millis(20));
+            };
+            assert!(alive(helper));
+
+            conn.kill(&session).unwrap();
+            let sessions = conn.list(None).unwrap();
+            assert_eq!(sessions[0].status, SessionStatus::Exited(-1));
+            let deadline = Instant::now() + Duration::from_secs(5);
+            while alive(helper) {
+                assert!(Instant::now() < deadline, "helper {helper} outlived the kill");
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            assert!(conn.kill("missing").is_err());
+
+            conn.request(&DaemonCommand::Shutdown).unwrap();
+        })
+        .await
+        .unwrap();
+
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn paused_sessions_stop_producing_output_until_resumed() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("daemon.sock");
+        let daemon = Arc::new(Daemon::new(socket.clone()));
+        let server = {
+            let daemon = daemon.clone();
+            tokio::spawn(async move { daemon.run().await })
+        };
+
+        let workdir = dir.path().to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = connect(&socket);
+            let id = conn
+                .spawn(
+                    "ticker".to_string(),
+                    "sh".to_string(),
+                    vec!["-c".to_string(), "while true; do echo tick; sleep 0.05; done".to_string()],
+                    Vec::new(),
+                    workdir,
+                    None,
+                    None,
+                    None,
+                    RestartPolicy::Never,
+                )
+                .unwrap();
+            let mut watcher = connect(&socket);
+            let mut info = || watcher.list_by_agent("ticker", None).unwrap().remove(0);
+            std::thread::sleep(Duration::from_millis(300));
+
+            conn.pause(&id).unwrap();
+            std::thread::sleep(Duration::from_millis(100));
+            let paused = info();
+            assert!(paused.paused);
+            std::thread::sleep(Duration::from_millis(400));
+            assert_eq!(info().output_bytes, paused.output_bytes, "no output while paused");
+
+            conn.resume(&id).unwrap();
+            std::thread::sleep(Duration::from_millis(400));
+            let resumed = info();
+            assert!(!resumed.paused);
+            assert!(resumed.output_bytes > paused.output_bytes);
+
+            // Stopping a paused session resumes it so it can quit
+            conn.pause(&id).unwrap();
+            assert_eq!(conn.stop(&id, Some(2)).unwrap(), "stopped by quit sequence");
+
+            conn.request(&DaemonCommand::Shutdown).unwrap();
+        })
+        .await
+        .unwrap();
+
+        server.await.unwrap().unwrap();
+    }
+
+    #[test]
+    fn pending_output_coalesces_and_drops_oldest() {
+        let mut pending = PendingOutput::new(8);
+        pending.push(0, b"abc".to_vec());
+        pending.push(3, b"de".to_vec());
+        assert_eq!(pending.pop(), Some((0, b"abcde".to_vec())));
+        assert!(pending.is_empty());
+
+        // Over the limit: the oldest bytes go, and the offset moves with them
+        pending.push(5, b"fghij".to_vec());
+        pending.push(10, b"klmno".to_vec());
+        assert_eq!(pending.dropped, 2);
+        assert_eq!(pending.pop(), Some((7, b"hijklmno".to_vec())));
+
+        // A gap from the broadcast channel counts as dropped and isn't merged
+        pending.push(15, b"p".to_vec());
+        pending.push(20, b"u".to_vec());
+        assert_eq!(pending.dropped, 6);
+        assert_eq!(pending.pop(), Some((15, b"p".to_vec())));
+        assert_eq!(pending.pop(), Some((20, b"u".to_vec())));
+        assert_eq!(pending.pop(), None);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn shutdown_terminates_agents_and_writes_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("daemon.sock");
+        let daemon = Arc::new(
+            Daemon::new(socket.clone())
+                .with_shutdown_policy(ShutdownPolicy::Terminate, Duration::from_secs(5)),
+        );
+        let server = {
+            let daemon = daemon.clone();
+            tokio::spawn(async move { daemon.run().await })
+        };
+
+        let workdir = dir.path().to_path_buf();
+        let client_socket = socket.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = connect(&client_socket);
+            // Exits cleanly on SIGTERM, so a 0 exit code proves it wasn't SIGKILLed
+            conn.spawn(
+                "graceful".to_string(),
+                "sh".to_string(),
+                vec![
+                    "-c".to_string(),
+                    "trap 'exit 0' TERM; while true; do sleep 0.1; done".to_string(),
+                ],
+                Vec::new(),
+                workdir,
+                None,
+                None,
+                None,
+                RestartPolicy::Never,
+            )
+            .unwrap();
+            // Let the shell install its trap
+            std::thread::sleep(Duration::from_millis(200));
+            conn.request(&DaemonCommand::Shutdown).unwrap();
+        })
+        .await
+        .unwrap();
+
+        server.await.unwrap().unwrap();
+
+        let manifest = ShutdownManifest::load(&ShutdownManifest::path_for(&socket))
+            .unwrap()
+            .unwrap();
+        assert_eq!(manifest.policy, ShutdownPolicy::Terminate);
+        assert_eq!(manifest.sessions.len(), 1);
+        assert_eq!(manifest.sessions[0].info.agent_id, "graceful");
+        assert_eq!(manifest.sessions[0].info.status, SessionStatus::Exited(0));
+        assert!(manifest.surviving().is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn serves_prometheus_metrics() {
+        use std::io::{Read, Write};
+
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("daemon.sock");
+        // Grab a free port, then hand it to the daemon
+        let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let daemon = Arc::new(Daemon::new(socket.clone()).with_metrics_addr(addr));
+        let server = {
+            let daemon = daemon.clone();
+            tokio::spawn(async move { daemon.run().await })
+        };
+
+        tokio::task::spawn_blocking(move || {
+            let mut conn = connect(&socket);
+            let scrape = |path: &str| {
+                let mut stream = std::net::TcpStream::connect(addr).unwrap();
+                write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path).unwrap();
+                let mut response = String::new();
+                stream.read_to_string(&mut response).unwrap();
+                response
+            };
+
+            let body = scrape("/metrics");
+            assert!(body.starts_with("HTTP/1.1 200 OK"));
+            assert!(body.contains("# TYPE rembrandt_sessions gauge"));
+            assert!(body.contains("rembrandt_sessions{status=\"running\"} 0"));
+            assert!(scrape("/").starts_with("HTTP/1.1 404"));
+
+            // Competitions run in the client, which reports them when they finish
+            conn.record_competition("cancelled", vec![2_000]).unwrap();
+            assert!(conn.record_competition("won", vec![]).is_err());
+            let body = scrape("/metrics");
+            assert!(body.contains("rembrandt_competitions_total{outcome=\"cancelled\"} 1"));
+            assert!(body.contains("rembrandt_validation_duration_seconds_count 1"));
+
+            conn.request(&DaemonCommand::Shutdown).unwrap();
+        })
+        .await
+        .unwrap();
+
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn tcp_clients_are_limited_to_their_token_role() {
+        use auth::Role;
+
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("daemon.sock");
+        let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let tokens = [("grafana", "read-1", Role::Read), ("ci", "op-1", Role::Operator), ("ops", "admin-1", Role::Admin)]
+            .into_iter()
+            .map(|(name, token, role)| (name.to_string(), ApiToken { token: token.to_string(), role }))
+            .collect();
+        let daemon = Arc::new(
+            Daemon::new(socket.clone())
+                .with_listen_addr(addr)
+                .with_api_tokens(tokens, dir.path().to_path_buf()),
+        );
+        let server = {
+            let daemon = daemon.clone();
+            tokio::spawn(async move { daemon.run().await })
+        };
+
+        let repo = dir.path().to_path_buf();
+        git2::Repository::init(&repo).unwrap();
+        let elsewhere = tempfile::tempdir().unwrap();
+        tokio::task::spawn_blocking(move || {
+            let mut local = connect(&socket);
+            let addr = addr.to_string();
+            let spawn_with = |conn: &mut DaemonConnection, command: &str, env: &[(&str, &str)], workdir: &Path| {
+                conn.spawn(
+                    "remote-agent".to_string(),
+                    command.to_string(),
+                    Vec::new(),
+                    env.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+                    workdir.to_path_buf(),
+                    None,
+                    None,
+                    None,
+                    RestartPolicy::Never,
+                )
+            };
+            let spawn = |conn: &mut DaemonConnection| spawn_with(conn, "claude", &[], &repo);
+
+            let mut anonymous = DaemonConnection::connect_tcp(&addr).unwrap();
+            anonymous.ping().unwrap();
+            let refused = anonymous.list(None).unwrap_err();
+            assert_eq!(refused.code(), crate::ErrorCode::Forbidden);
+            assert!(anonymous.authenticate("guess").is_err());
+
+            let mut reader = DaemonConnection::connect_tcp(&addr).unwrap();
+            assert_eq!(reader.authenticate("read-1").unwrap(), "read (grafana)");
+            assert!(reader.list(None).unwrap().is_empty());
+            assert!(spawn(&mut reader).is_err());
+
+            // Remote clients may only start known agents, as they are, in
+            // this daemon's repositories
+            let mut operator = DaemonConnection::connect_tcp(&addr).unwrap();
+            operator.authenticate("op-1").unwrap();
+            for refused in [
+                spawn_with(&mut operator, "sh", &[], &repo),
+                spawn_with(&mut operator, "claude", &[("PATH", "/tmp/bin")], &repo),
+                spawn_with(&mut operator, "claude", &[], elsewhere.path()),
+            ] {
+                assert_eq!(refused.unwrap_err().code(), crate::ErrorCode::Forbidden);
+            }
+            let session_id = local
+                .spawn(
+                    "local-agent".to_string(),
+                    "sleep".to_string(),
+                    vec!["5".to_string()],
+                    Vec::new(),
+                    repo.clone(),
+                    None,
+                    None,
+                    None,
+                    RestartPolicy::Never,
+                )
+                .unwrap();
+            operator.kill(&session_id).unwrap();
+            assert!(operator.request(&DaemonCommand::Shutdown).is_err());
+            let refused = operator.merge(repo.clone(), "local-agent", "main", true).unwrap_err();
+            assert_eq!(refused.code(), crate::ErrorCode::Forbidden);
+            assert!(operator.config(repo.clone()).is_err());
+
+            // Merges and config edits take an admin, in a served repository
+            let mut admin = DaemonConnection::connect_tcp(&addr).unwrap();
+            admin.authenticate("admin-1").unwrap();
+            assert_eq!(admin.config(repo.clone()).unwrap(), "");
+            let refused = admin.config(elsewhere.path().to_path_buf()).unwrap_err();
+            assert_eq!(refused.code(), crate::ErrorCode::Forbidden);
+            assert!(admin.set_config(repo.clone(), "csi_poll_interval_secs = \"soon\"".to_string()).is_err());
+            admin.set_config(repo.clone(), "csi_poll_interval_secs = 30\n".to_string()).unwrap();
+            assert_eq!(crate::config::AppConfig::load(&repo).unwrap().csi_poll_interval_secs, 30);
+            let missing = admin.merge(repo.clone(), "nobody", "main", false).unwrap_err();
+            assert_ne!(missing.code(), crate::ErrorCode::Forbidden);
+
+            let store = StateStore::open(&repo).unwrap();
+            let audit = store.audit_log(&Default::default()).unwrap();
+            let actions: Vec<(&str, &str)> =
+                audit.iter().map(|e| (e.actor.as_str(), e.action.as_str())).collect();
+            assert_eq!(actions, [("ci", "kill"), ("ops", "config"), ("ops", "config"), ("ops", "merge")]);
+            assert_eq!(audit[0].source, crate::audit::AuditSource::Api);
+            assert_eq!(audit[0].target.as_deref(), Some("local-agent"));
+
+            local.request(&DaemonCommand::Shutdown).unwrap();
+        })
+        .await
+        .unwrap();
+
+        server.await.unwrap().unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn tls_clients_complete_a_handshake() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("daemon.sock");
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let tls_dir = dir.path().join(".rembrandt/tls");
+        std::fs::create_dir_all(&tls_dir).unwrap();
+        std::fs::write(tls_dir.join("cert.pem"), certified.cert.pem()).unwrap();
+        std::fs::write(tls_dir.join("key.pem"), certified.key_pair.serialize_pem()).unwrap();
+        let config = crate::config::ListenTlsConfig {
+            cert: ".rembrandt/tls/cert.pem".into(),
+            key: ".rembrandt/tls/key.pem".into(),
+        };
+        let acceptor = tls::acceptor(&config, dir.path()).unwrap();
+
+        let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let tokens = [("ci".to_string(), ApiToken { token: "op-1".to_string(), role: auth::Role::Operator })]
+            .into_iter()
+            .collect();
+        let daemon = Arc::new(
+            Daemon::new(socket.clone())
+                .with_listen_addr(addr)
+                .with_tls(acceptor)
+                .with_api_tokens(tokens, dir.path().to_path_buf()),
+        );
+        let server = {
+            let daemon = daemon.clone();
+            tokio::spawn(async move { daemon.run().await })
+        };
+
+        let ca = tls_dir.join("cert.pem");
+        let stranger = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let other_ca = dir.path().join("other.pem");
+        std::fs::write(&other_ca, stranger.cert.pem()).unwrap();
+        tokio::task::spawn_blocking(move || {
+            let mut local = connect(&socket);
+            let host = format!("localhost:{}", addr.port());
+
+            let mut client = DaemonConnection::connect_tls(&host, &ca).unwrap();
+            client.ping().unwrap();
+            assert_eq!(client.authenticate("op-1").unwrap(), "operator (ci)");
+            assert!(client.list(None).unwrap().is_empty());
+
+            // Plain TCP gets nowhere, nor does a client trusting another certificate
+            let plain = DaemonConnection::connect_tcp(&host).and_then(|mut conn| conn.ping());
+            assert!(plain.is_err());
+            assert!(DaemonConnection::connect_tls(&host, &other_ca).is_err());
+            // The certificate is for localhost, not the address
+            assert!(DaemonConnection::connect_tls(&addr.to_string(), &ca).is_err());
+
+            local.request(&DaemonCommand::Shutdown).unwrap();
+        })
+        .await
+        .unwrap();
+
+        server.await.unwrap().unwrap();
+    }
 }
@@ -0,0 +1,193 @@
+//! `rembrandt daemon supervise`: restart the daemon when it crashes
+//!
+//! The supervisor runs `rembrandt daemon` as a child process and starts it
+//! again whenever it exits non-zero or is killed, so unattended runs
+//! survive a daemon failure. Every start reruns the daemon's startup
+//! reconciliation. Restarts back off like an agent's (from 1s, doubling up
+//! to a minute); a daemon that stayed up for [`STABLE_UPTIME`] starts over
+//! from the shortest wait. A daemon that dies within [`STARTUP_GRACE`] of
+//! its very first start isn't restarted at all, since it would most likely
+//! never come up. A clean exit (`Shutdown`) ends the supervisor,
+//! and Ctrl-C or SIGTERM is passed on to the daemon, which shuts down by
+//! its `shutdown_policy` before the supervisor exits.
+//!
+//! Each restart is recorded in the repository's audit log as
+//! `daemon_restart`.
+
+use crate::audit::{self, AuditSource};
+use crate::restart::RestartPolicy;
+use crate::{RembrandtError, Result};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
+use std::time::{Duration, Instant};
+use tokio::process::{Child, Command};
+
+/// Uptime after which a crash counts as the first in a row again
+pub const STABLE_UPTIME: Duration = Duration::from_secs(10 * 60);
+
+/// A daemon that dies this soon after the supervisor first starts it is
+/// taken to be unable to start at all (bad config, socket in use)
+pub const STARTUP_GRACE: Duration = Duration::from_secs(3);
+
+/// Keeps one daemon process running
+pub struct Supervisor {
+    program: PathBuf,
+    args: Vec<OsString>,
+    max_restarts: Option<u32>,
+    audit_repo: Option<PathBuf>,
+}
+
+impl Supervisor {
+    /// Supervise `program args`, e.g. this executable with `daemon ...`
+    pub fn new(program: impl Into<PathBuf>, args: impl IntoIterator<Item = impl Into<OsString>>) -> Self {
+        Self {
+            program: program.into(),
+            args: args.into_iter().map(Into::into).collect(),
+            max_restarts: None,
+            audit_repo: None,
+        }
+    }
+
+    /// Give up after this many crashes in a row (default: never)
+    pub fn with_max_restarts(mut self, max_restarts: Option<u32>) -> Self {
+        self.max_restarts = max_restarts;
+        self
+    }
+
+    /// Record restarts in this repository's audit log
+    pub fn with_audit_repo(mut self, repo: impl Into<PathBuf>) -> Self {
+        self.audit_repo = Some(repo.into());
+        self
+    }
+
+    /// Run the daemon until it exits cleanly or the supervisor is stopped
+    pub async fn run(self) -> Result<()> {
+        let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .map_err(|e| RembrandtError::Daemon(e.to_string()))?;
+        let mut crashes = 0u32;
+        let mut first_start = true;
+        loop {
+            let started = Instant::now();
+            let mut child = Command::new(&self.program)
+                .args(&self.args)
+                .spawn()
+                .map_err(|e| RembrandtError::Daemon(format!("starting {}: {}", self.program.display(), e)))?;
+            tracing::info!(pid = child.id(), "Supervised daemon started");
+
+            let status = tokio::select! {
+                status = child.wait() => status?,
+                _ = tokio::signal::ctrl_c() => return stop(&mut child).await,
+                _ = sigterm.recv() => return stop(&mut child).await,
+            };
+            if status.success() {
+                tracing::info!("Daemon shut down; supervisor exiting");
+                return Ok(());
+            }
+
+            let uptime = started.elapsed();
+            if std::mem::take(&mut first_start) && uptime < STARTUP_GRACE {
+                return Err(RembrandtError::Daemon(format!(
+                    "daemon {} right after starting; not restarting a daemon that can't start",
+                    describe(&status)
+                )));
+            }
+            crashes = if uptime >= STABLE_UPTIME { 1 } else { crashes + 1 };
+            if self.max_restarts.is_some_and(|max| crashes > max) {
+                return Err(RembrandtError::Daemon(format!(
+                    "daemon {} {} time(s) in a row; not restarting it again",
+                    describe(&status),
+                    crashes
+                )));
+            }
+            let delay = RestartPolicy::backoff(crashes);
+            tracing::warn!(
+                uptime_secs = uptime.as_secs(),
+                attempt = crashes,
+                "Daemon {}; restarting in {}s",
+                describe(&status),
+                delay.as_secs()
+            );
+            if let Some(repo) = &self.audit_repo {
+                record_restart(repo, &status, crashes, uptime);
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = tokio::signal::ctrl_c() => return Ok(()),
+                _ = sigterm.recv() => return Ok(()),
+            }
+        }
+    }
+}
+
+/// Ask the daemon to shut down and wait for it
+async fn stop(child: &mut Child) -> Result<()> {
+    if let Some(pid) = child.id() {
+        unsafe {
+            libc::kill(pid as i32, libc::SIGTERM);
+        }
+    }
+    child.wait().await?;
+    Ok(())
+}
+
+/// How the daemon ended, e.g. `exited with code 1`
+fn describe(status: &ExitStatus) -> String {
+    use std::os::unix::process::ExitStatusExt;
+    match (status.code(), status.signal()) {
+        (Some(code), _) => format!("exited with code {}", code),
+        (None, Some(signal)) => format!("was killed by signal {}", signal),
+        (None, None) => "exited".to_string(),
+    }
+}
+
+fn record_restart(repo: &Path, status: &ExitStatus, attempt: u32, uptime: Duration) {
+    audit::record(
+        repo,
+        AuditSource::Daemon,
+        "daemon_restart",
+        None,
+        serde_json::json!({
+            "reason": describe(status),
+            "attempt": attempt,
+            "uptime_secs": uptime.as_secs(),
+        }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn restarts_a_crashing_daemon_until_it_exits_cleanly() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("crashed");
+        // Crashes the first time, once past its startup, and exits cleanly
+        // the second
+        let script = format!(
+            "if [ -e '{0}' ]; then exit 0; fi; touch '{0}'; sleep {1}; exit 3",
+            marker.display(),
+            STARTUP_GRACE.as_secs() + 1
+        );
+        Supervisor::new("sh", ["-c", script.as_str()])
+            .with_max_restarts(Some(1))
+            .with_audit_repo(dir.path())
+            .run()
+            .await
+            .unwrap();
+        let store = crate::state::StateStore::open(dir.path()).unwrap();
+        let entries = store
+            .audit_log(&audit::AuditQuery {
+                action: Some("daemon_restart".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+
+        // Failing to start at all isn't retried, limit or not
+        let err = Supervisor::new("sh", ["-c", "exit 3"]).run().await.unwrap_err();
+        assert!(err.to_string().contains("exited with code 3 right after starting"));
+    }
+}
@@ -0,0 +1,78 @@
+//! TLS for the daemon's TCP listener
+//!
+//! Tokens cross the wire with every `Authenticate`, and an operator token
+//! can spawn agents, so `rembrandt daemon --listen` only serves plain TCP on
+//! loopback. Any other address needs `[listen_tls]` in config.toml, and
+//! clients then connect with TLS (`DaemonConnection::connect_tls`).
+
+use crate::config::ListenTlsConfig;
+use crate::{RembrandtError, Result};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::{self, ServerConfig};
+use tokio_rustls::TlsAcceptor;
+
+/// Acceptor serving `config`'s certificate chain and key, paths relative
+/// to `repo_path`
+pub fn acceptor(config: &ListenTlsConfig, repo_path: &Path) -> Result<TlsAcceptor> {
+    let read = |path: &Path| {
+        let path = repo_path.join(path);
+        std::fs::read(&path)
+            .map_err(|e| RembrandtError::Config(format!("[listen_tls] {}: {}", path.display(), e)))
+    };
+    let certs: Vec<CertificateDer<'static>> = rustls_pemfile::certs(&mut read(&config.cert)?.as_slice())
+        .collect::<std::result::Result<_, _>>()
+        .map_err(|e| RembrandtError::Config(format!("[listen_tls] cert: {}", e)))?;
+    if certs.is_empty() {
+        return Err(RembrandtError::Config(format!(
+            "[listen_tls] cert {} holds no certificate",
+            config.cert.display()
+        )));
+    }
+    let key: PrivateKeyDer<'static> = rustls_pemfile::private_key(&mut read(&config.key)?.as_slice())
+        .map_err(|e| RembrandtError::Config(format!("[listen_tls] key: {}", e)))?
+        .ok_or_else(|| {
+            RembrandtError::Config(format!("[listen_tls] key {} holds no private key", config.key.display()))
+        })?;
+
+    let server = ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .and_then(|builder| builder.with_no_client_auth().with_single_cert(certs, key))
+        .map_err(|e| RembrandtError::Config(format!("[listen_tls]: {}", e)))?;
+    Ok(TlsAcceptor::from(Arc::new(server)))
+}
+
+/// Refuse to serve plain TCP anywhere but loopback
+pub fn check_listen_addr(addr: SocketAddr, tls: bool) -> Result<()> {
+    if tls || addr.ip().is_loopback() {
+        return Ok(());
+    }
+    Err(RembrandtError::Config(format!(
+        "--listen {} would send API tokens in cleartext; listen on 127.0.0.1 or configure [listen_tls]",
+        addr
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_tcp_is_loopback_only() {
+        assert!(check_listen_addr("127.0.0.1:7878".parse().unwrap(), false).is_ok());
+        assert!(check_listen_addr("[::1]:7878".parse().unwrap(), false).is_ok());
+        let err = check_listen_addr("0.0.0.0:7878".parse().unwrap(), false).unwrap_err();
+        assert!(err.to_string().contains("[listen_tls]"));
+        assert!(check_listen_addr("0.0.0.0:7878".parse().unwrap(), true).is_ok());
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("cert.pem"), "").unwrap();
+        let config = ListenTlsConfig { cert: "cert.pem".into(), key: "key.pem".into() };
+        let Err(err) = acceptor(&config, dir.path()) else {
+            panic!("an empty cert file was accepted");
+        };
+        assert!(err.to_string().contains("holds no certificate"));
+    }
+}
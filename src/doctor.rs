@@ -0,0 +1,548 @@
+//! `rembrandt doctor`: end-to-end environment checks.
+//!
+//! Each check reports ok / warn / fail with a one-line fix. `--fix` applies
+//! the repairs that can't lose work: migrating state.db, removing a dead
+//! daemon socket, pruning worktrees whose directory is gone, and deleting
+//! `rembrandt/*` branches that are orphaned and already merged into the base
+//! branch. Orphaned worktrees that still exist are only reported, since they
+//! may hold uncommitted changes.
+
+use crate::agent::AgentType;
+use crate::daemon::ipc::DaemonConnection;
+use crate::daemon::SessionStatus as PtyStatus;
+use crate::state::{migrations, StateStore};
+use crate::worktree::main_repo_root;
+use crate::{RembrandtError, Result};
+use git2::{BranchType, Repository, WorktreePruneOptions};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Agent CLIs rembrandt knows how to spawn
+const AGENTS: [AgentType; 5] = [
+    AgentType::ClaudeCode,
+    AgentType::OpenCode,
+    AgentType::AmpCode,
+    AgentType::Codex,
+    AgentType::Aider,
+];
+
+/// Outcome of a single check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Ok,
+    Warn,
+    Fail,
+}
+
+impl CheckStatus {
+    pub fn symbol(self) -> &'static str {
+        match self {
+            CheckStatus::Ok => "✓",
+            CheckStatus::Warn => "!",
+            CheckStatus::Fail => "✗",
+        }
+    }
+}
+
+/// One line of the doctor report
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: String,
+    pub status: CheckStatus,
+    pub detail: String,
+    /// What to run or change to resolve a warning or failure
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn ok(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: CheckStatus::Ok,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn warn(name: impl Into<String>, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: CheckStatus::Warn,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn fail(name: impl Into<String>, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: CheckStatus::Fail,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// Worktrees and branches no session accounts for
+#[derive(Debug, Default)]
+pub struct Leftovers {
+    /// Registered worktrees whose directory no longer exists
+    pub missing_worktrees: Vec<String>,
+    /// Worktrees on disk with no active session
+    pub orphan_worktrees: Vec<(String, PathBuf)>,
+    /// `rembrandt/*` branches with no worktree or active session, and
+    /// whether they are already merged into the base branch
+    pub orphan_branches: Vec<(String, bool)>,
+}
+
+/// Run every check against `repo_path`
+pub fn diagnose(repo_path: &Path, base_branch: &str, socket: &Path) -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    let repo = match Repository::open(repo_path) {
+        Ok(repo) => {
+            checks.push(Check::ok("git repository", repo_path.display().to_string()));
+            Some(repo)
+        }
+        Err(e) => {
+            checks.push(Check::fail(
+                "git repository",
+                e.message().to_string(),
+                "run rembrandt from a git repository or pass --repo",
+            ));
+            None
+        }
+    };
+    if let Some(repo) = &repo {
+        checks.push(match repo.find_branch(base_branch, BranchType::Local) {
+            Ok(_) => Check::ok("base branch", base_branch),
+            Err(_) => Check::fail(
+                "base branch",
+                format!("'{}' not found", base_branch),
+                format!("create it (git branch {}) or pass --base <branch>", base_branch),
+            ),
+        });
+    }
+
+    checks.extend(check_agents());
+    checks.push(check_beads());
+    checks.push(check_tool("porque (pq)", "pq", "install porque for decision context"));
+
+    let (daemon_check, daemon_agents) = check_daemon(repo_path, socket);
+    checks.push(daemon_check);
+    checks.push(check_state(repo_path));
+
+    if repo.is_some() {
+        checks.extend(check_leftovers(repo_path, base_branch, &daemon_agents));
+    }
+    checks
+}
+
+fn check_agents() -> Vec<Check> {
+    let mut checks: Vec<Check> = AGENTS
+        .iter()
+        .map(|agent| match tool_version(agent.command()) {
+            Some(version) => Check::ok(agent.to_string(), version),
+            None => Check::warn(
+                agent.to_string(),
+                format!("`{}` not on PATH", agent.command()),
+                format!("install {} to spawn it", agent),
+            ),
+        })
+        .collect();
+    if checks.iter().all(|c| c.status != CheckStatus::Ok) {
+        checks.push(Check::fail(
+            "agent CLIs",
+            "no supported agent found",
+            "install at least one of claude, opencode, amp, codex, aider",
+        ));
+    }
+    checks
+}
+
+fn check_tool(name: &str, command: &str, fix: &str) -> Check {
+    match tool_version(command) {
+        Some(version) => Check::ok(name, version),
+        None => Check::warn(name, format!("`{}` not on PATH", command), fix),
+    }
+}
+
+/// Beads as `br`, else `bd`, matching `BeadsClient::detect`
+fn check_beads() -> Check {
+    match ["br", "bd"].into_iter().find_map(|command| Some((command, tool_version(command)?))) {
+        Some((command, version)) => Check::ok("beads", format!("{} ({})", version, command)),
+        None => Check::warn(
+            "beads",
+            "neither `br` nor `bd` on PATH",
+            "install beads_rust (br) or beads (bd) to assign tasks with --task",
+        ),
+    }
+}
+
+/// First line of `<command> --version`, if the command runs
+fn tool_version(command: &str) -> Option<String> {
+    let output = Command::new(command)
+        .arg("--version")
+        .stdin(Stdio::null())
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Some(stdout.lines().next().unwrap_or("").trim().to_string())
+}
+
+/// Daemon reachability, plus the agents it is running for this repository
+fn check_daemon(repo_path: &Path, socket: &Path) -> (Check, HashSet<String>) {
+    if !socket.exists() {
+        return (
+            Check::warn(
+                "daemon",
+                format!("not running ({})", socket.display()),
+                "start it with `rembrandt daemon` to keep agents alive without the TUI",
+            ),
+            HashSet::new(),
+        );
+    }
+    let sessions = DaemonConnection::connect(socket).and_then(|mut conn| {
+        conn.ping()?;
+        conn.list(main_repo_root(repo_path))
+    });
+    match sessions {
+        Ok(sessions) => {
+            let running: HashSet<String> = sessions
+                .into_iter()
+                .filter(|s| s.status == PtyStatus::Running)
+                .map(|s| s.agent_id)
+                .collect();
+            (
+                Check::ok("daemon", format!("{} ({} running here)", socket.display(), running.len())),
+                running,
+            )
+        }
+        Err(e) => (
+            Check::warn(
+                "daemon",
+                format!("stale socket {}: {}", socket.display(), e),
+                "rembrandt doctor --fix removes it",
+            ),
+            HashSet::new(),
+        ),
+    }
+}
+
+/// Schema version of state.db without migrating it
+fn state_version(repo_path: &Path) -> Result<Option<i64>> {
+    let db_path = repo_path.join(".rembrandt").join("state.db");
+    if !db_path.exists() {
+        return Ok(None);
+    }
+    let conn = rusqlite::Connection::open(&db_path)?;
+    migrations::current_version(&conn).map(Some)
+}
+
+fn check_state(repo_path: &Path) -> Check {
+    let latest = migrations::latest_version();
+    match state_version(repo_path) {
+        Ok(None) => Check::ok("state.db", "not created yet"),
+        Ok(Some(version)) if version == latest => {
+            Check::ok("state.db", format!("schema v{}", version))
+        }
+        Ok(Some(version)) if version < latest => Check::warn(
+            "state.db",
+            format!("schema v{}, v{} available", version, latest),
+            "rembrandt doctor --fix migrates it",
+        ),
+        Ok(Some(version)) => Check::fail(
+            "state.db",
+            format!("schema v{} is newer than this rembrandt (v{})", version, latest),
+            "upgrade rembrandt",
+        ),
+        Err(e) => Check::fail(
+            "state.db",
+            e.to_string(),
+            "move .rembrandt/state.db aside; it is recreated on next use",
+        ),
+    }
+}
+
+fn check_leftovers(repo_path: &Path, base_branch: &str, daemon_agents: &HashSet<String>) -> Vec<Check> {
+    let leftovers = match find_leftovers(repo_path, base_branch, daemon_agents) {
+        Ok(leftovers) => leftovers,
+        Err(e) => {
+            return vec![Check::fail("worktrees", e.to_string(), "check `git worktree list`")];
+        }
+    };
+
+    let mut checks = Vec::new();
+    if leftovers.missing_worktrees.is_empty() && leftovers.orphan_worktrees.is_empty() {
+        checks.push(Check::ok("worktrees", "no leftovers"));
+    }
+    if !leftovers.missing_worktrees.is_empty() {
+        checks.push(Check::warn(
+            "worktrees",
+            format!("directory gone: {}", leftovers.missing_worktrees.join(", ")),
+            "rembrandt doctor --fix prunes them",
+        ));
+    }
+    if !leftovers.orphan_worktrees.is_empty() {
+        let names: Vec<&str> = leftovers.orphan_worktrees.iter().map(|(n, _)| n.as_str()).collect();
+        checks.push(Check::warn(
+            "worktrees",
+            format!("no active session: {}", names.join(", ")),
+            "review them, then `rembrandt merge <agent>` or `rembrandt gc`",
+        ));
+    }
+
+    let (merged, unmerged): (Vec<_>, Vec<_>) =
+        leftovers.orphan_branches.iter().partition(|(_, merged)| *merged);
+    if merged.is_empty() && unmerged.is_empty() {
+        checks.push(Check::ok("branches", "no leftovers"));
+    }
+    if !merged.is_empty() {
+        let names: Vec<&str> = merged.iter().map(|(b, _)| b.as_str()).collect();
+        checks.push(Check::warn(
+            "branches",
+            format!("merged into {}: {}", base_branch, names.join(", ")),
+            "rembrandt doctor --fix deletes them",
+        ));
+    }
+    if !unmerged.is_empty() {
+        let names: Vec<&str> = unmerged.iter().map(|(b, _)| b.as_str()).collect();
+        checks.push(Check::warn(
+            "branches",
+            format!("unmerged, no session: {}", names.join(", ")),
+            "merge or delete them with git once reviewed",
+        ));
+    }
+    checks
+}
+
+/// Worktrees and `rembrandt/*` branches not backed by an active state.db
+/// session or a running daemon session
+pub fn find_leftovers(
+    repo_path: &Path,
+    base_branch: &str,
+    daemon_agents: &HashSet<String>,
+) -> Result<Leftovers> {
+    let repo = Repository::open(repo_path)?;
+    let mut active_agents = daemon_agents.clone();
+    let mut active_branches = HashSet::new();
+    if state_version(repo_path)?.is_some() {
+        for session in StateStore::open(repo_path)?.list_active()? {
+            active_branches.insert(session.branch_name);
+            active_agents.insert(session.agent_id);
+        }
+    }
+
+    let mut leftovers = Leftovers::default();
+    let mut worktree_names = HashSet::new();
+    for name in repo.worktrees()?.iter().flatten() {
+        let worktree = repo.find_worktree(name)?;
+        worktree_names.insert(name.to_string());
+        if !worktree.path().exists() {
+            leftovers.missing_worktrees.push(name.to_string());
+        } else if !active_agents.contains(name) {
+            leftovers
+                .orphan_worktrees
+                .push((name.to_string(), worktree.path().to_path_buf()));
+        }
+    }
+
+    let base = repo
+        .find_branch(base_branch, BranchType::Local)
+        .ok()
+        .and_then(|b| b.get().target());
+    for branch in repo.branches(Some(BranchType::Local))? {
+        let (branch, _) = branch?;
+        let Some(name) = branch.name()?.map(str::to_string) else {
+            continue;
+        };
+        let Some(agent_id) = name.strip_prefix("rembrandt/") else {
+            continue;
+        };
+        if worktree_names.contains(agent_id)
+            || active_agents.contains(agent_id)
+            || active_branches.contains(&name)
+        {
+            continue;
+        }
+        let merged = match (base, branch.get().target()) {
+            (Some(base), Some(tip)) => base == tip || repo.graph_descendant_of(base, tip)?,
+            _ => false,
+        };
+        leftovers.orphan_branches.push((name, merged));
+    }
+    Ok(leftovers)
+}
+
+/// Apply the safe repairs, returning a line per action taken
+pub fn repair(repo_path: &Path, base_branch: &str, socket: &Path) -> Result<Vec<String>> {
+    let mut actions = Vec::new();
+
+    if let Some(version) = state_version(repo_path)?
+        && version < migrations::latest_version()
+    {
+        let store = StateStore::open(repo_path)?;
+        actions.push(format!(
+            "migrated state.db v{} -> v{}",
+            version,
+            store.schema_version()?
+        ));
+    }
+
+    let mut daemon_agents = HashSet::new();
+    if socket.exists() {
+        match DaemonConnection::connect(socket).and_then(|mut conn| conn.list(main_repo_root(repo_path))) {
+            Ok(sessions) => {
+                daemon_agents = sessions
+                    .into_iter()
+                    .filter(|s| s.status == PtyStatus::Running)
+                    .map(|s| s.agent_id)
+                    .collect();
+            }
+            Err(_) => {
+                std::fs::remove_file(socket)
+                    .map_err(|e| RembrandtError::Daemon(format!("{}: {}", socket.display(), e)))?;
+                actions.push(format!("removed stale socket {}", socket.display()));
+            }
+        }
+    }
+
+    if Repository::open(repo_path).is_err() {
+        return Ok(actions);
+    }
+    let leftovers = find_leftovers(repo_path, base_branch, &daemon_agents)?;
+    let repo = Repository::open(repo_path)?;
+    for name in &leftovers.missing_worktrees {
+        // Without `valid`, prune refuses worktrees whose directory still exists
+        let worktree = repo.find_worktree(name)?;
+        let locked = crate::worktree::locked_by_rembrandt(&worktree);
+        worktree.prune(Some(WorktreePruneOptions::new().locked(locked)))?;
+        actions.push(format!("pruned worktree {}", name));
+    }
+    for (name, merged) in &leftovers.orphan_branches {
+        if *merged {
+            repo.find_branch(name, BranchType::Local)?.delete()?;
+            actions.push(format!("deleted merged branch {}", name));
+        }
+    }
+    Ok(actions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::init_repo;
+    use git2::Signature;
+
+    fn commit(repo: &Repository, refname: &str, parents: &[&git2::Commit]) -> git2::Oid {
+        let sig = Signature::now("test", "test@example.com").unwrap();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+        repo.commit(Some(refname), &sig, &sig, "commit", &tree, parents).unwrap()
+    }
+
+    #[test]
+    fn fix_prunes_missing_worktrees_and_merged_branches_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = init_repo(dir.path());
+        let base_commit = repo.head().unwrap().peel_to_commit().unwrap();
+
+        // Merged (points at main) vs. unmerged (one commit ahead)
+        repo.branch("rembrandt/done", &base_commit, false).unwrap();
+        commit(&repo, "refs/heads/rembrandt/wip", &[&base_commit]);
+
+        // A worktree whose directory was deleted by hand
+        let gone = repo.branch("rembrandt/gone", &base_commit, false).unwrap();
+        let gone_path = dir.path().join(".rembrandt/agents/gone");
+        std::fs::create_dir_all(gone_path.parent().unwrap()).unwrap();
+        repo.worktree(
+            "gone",
+            &gone_path,
+            Some(git2::WorktreeAddOptions::new().reference(Some(gone.get()))),
+        )
+        .unwrap();
+        std::fs::remove_dir_all(&gone_path).unwrap();
+
+        let leftovers = find_leftovers(dir.path(), "main", &HashSet::new()).unwrap();
+        assert_eq!(leftovers.missing_worktrees, vec!["gone".to_string()]);
+        assert_eq!(
+            leftovers.orphan_branches,
+            vec![("rembrandt/done".to_string(), true), ("rembrandt/wip".to_string(), false)]
+        );
+
+        let socket = dir.path().join("missing.sock");
+        let actions = repair(dir.path(), "main", &socket).unwrap();
+        assert_eq!(
+            actions,
+            vec!["pruned worktree gone".to_string(), "deleted merged branch rembrandt/done".to_string()]
+        );
+
+        // The unmerged branch survives; the pruned worktree's branch is now an orphan
+        let leftovers = find_leftovers(dir.path(), "main", &HashSet::new()).unwrap();
+        assert!(leftovers.missing_worktrees.is_empty());
+        assert_eq!(
+            leftovers.orphan_branches,
+            vec![("rembrandt/gone".to_string(), true), ("rembrandt/wip".to_string(), false)]
+        );
+    }
+
+    fn check<'a>(checks: &'a [Check], name: &str) -> Option<&'a Check> {
+        checks.iter().find(|c| c.name == name)
+    }
+
+    #[test]
+    fn reports_a_missing_repository_and_unusable_state_db() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("missing.sock");
+        let db_path = dir.path().join(".rembrandt/state.db");
+        std::fs::create_dir_all(db_path.parent().unwrap()).unwrap();
+
+        // Written by a newer rembrandt
+        let conn = rusqlite::Connection::open(&db_path).unwrap();
+        migrations::current_version(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO schema_migrations(version, applied_at) VALUES (?1, 'later')",
+            [migrations::latest_version() + 1],
+        )
+        .unwrap();
+        drop(conn);
+
+        let checks = diagnose(dir.path(), "main", &socket);
+        assert_eq!(check(&checks, "git repository").unwrap().status, CheckStatus::Fail);
+        assert!(check(&checks, "base branch").is_none());
+        assert!(check(&checks, "worktrees").is_none(), "leftovers need a repository");
+        let state = check(&checks, "state.db").unwrap();
+        assert_eq!(state.status, CheckStatus::Fail);
+        assert!(state.detail.contains("newer than this rembrandt"), "{}", state.detail);
+        // Nothing is safe to repair: no migrating down, no repository to prune
+        assert!(repair(dir.path(), "main", &socket).unwrap().is_empty());
+
+        std::fs::write(&db_path, "not a database").unwrap();
+        let checks = diagnose(dir.path(), "main", &socket);
+        let state = check(&checks, "state.db").unwrap();
+        assert_eq!(state.status, CheckStatus::Fail);
+        assert!(state.fix.as_deref().unwrap().contains("move .rembrandt/state.db aside"));
+    }
+
+    #[test]
+    fn stale_socket_is_reported_then_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        init_repo(dir.path());
+        // Left behind by a daemon that died: nothing listens on it
+        let socket = dir.path().join("daemon.sock");
+        std::fs::write(&socket, "").unwrap();
+
+        let checks = diagnose(dir.path(), "trunk", &socket);
+        let daemon = check(&checks, "daemon").unwrap();
+        assert_eq!(daemon.status, CheckStatus::Warn);
+        assert!(daemon.detail.starts_with("stale socket"), "{}", daemon.detail);
+        assert_eq!(check(&checks, "base branch").unwrap().status, CheckStatus::Fail);
+
+        let actions = repair(dir.path(), "trunk", &socket).unwrap();
+        assert_eq!(actions, vec![format!("removed stale socket {}", socket.display())]);
+        assert!(!socket.exists());
+    }
+}
@@ -0,0 +1,98 @@
+//! User-facing rendering of CLI errors.
+//!
+//! Errors print as `error[code]: message` with an optional `hint:` line. The
+//! code comes from the [`RembrandtError`] in the error's chain; hints are the
+//! error's own, except for missing branches, where the repository is checked
+//! for a remote branch of the same name.
+
+use crate::{ErrorCode, RembrandtError};
+use git2::{BranchType, Repository};
+use std::path::Path;
+
+/// Code and hint for an error, looking through `anyhow` context
+pub fn classify(err: &anyhow::Error, repo_path: &Path) -> (Option<ErrorCode>, Option<String>) {
+    for cause in err.chain() {
+        if let Some(e) = cause.downcast_ref::<RembrandtError>() {
+            let hint = match e {
+                RembrandtError::Git(git) => branch_hint(git, repo_path),
+                _ => None,
+            };
+            return (Some(e.code()), hint.or_else(|| e.hint()));
+        }
+        if let Some(git) = cause.downcast_ref::<git2::Error>() {
+            let hint = branch_hint(git, repo_path).or_else(|| {
+                (git.code() == git2::ErrorCode::NotFound)
+                    .then(|| "run `git branch -a` to see available branches".to_string())
+            });
+            return (Some(ErrorCode::Git), hint);
+        }
+    }
+    (None, None)
+}
+
+/// `error[code]: message`, plus a hint line when there is one
+pub fn render(err: &anyhow::Error, repo_path: &Path) -> String {
+    let (code, hint) = classify(err, repo_path);
+    let mut out = match code {
+        Some(code) => format!("error[{}]: {:#}\n", code, err),
+        None => format!("error: {:#}\n", err),
+    };
+    if let Some(hint) = hint {
+        out.push_str(&format!("  hint: {}\n", hint));
+    }
+    out
+}
+
+/// For a missing local branch, point at a remote branch of the same name
+fn branch_hint(err: &git2::Error, repo_path: &Path) -> Option<String> {
+    if err.code() != git2::ErrorCode::NotFound {
+        return None;
+    }
+    let name = missing_branch(err.message())?;
+    let repo = Repository::discover(repo_path).ok()?;
+    let mut remote = repo
+        .branches(Some(BranchType::Remote))
+        .ok()?
+        .flatten()
+        .filter_map(|(branch, _)| branch.name().ok().flatten().map(str::to_string))
+        .filter(|full| full.split_once('/').is_some_and(|(_, short)| short == name))
+        .collect::<Vec<_>>();
+    remote.sort();
+    let candidate = remote.first()?;
+    Some(format!("branch '{}' not found — did you mean {}?", name, candidate))
+}
+
+/// Branch name from libgit2's "cannot locate local branch 'X'"
+fn missing_branch(message: &str) -> Option<&str> {
+    let rest = message.strip_prefix("cannot locate local branch '")?;
+    rest.strip_suffix('\'')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::init_repo;
+
+    #[test]
+    fn missing_base_branch_suggests_the_remote_one() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = init_repo(dir.path());
+        let commit = repo.head().unwrap().target().unwrap();
+        repo.reference("refs/remotes/origin/trunk", commit, false, "test").unwrap();
+
+        let Err(missing) = repo.find_branch("trunk", BranchType::Local) else {
+            panic!("trunk has no local branch");
+        };
+        let err = anyhow::Error::from(RembrandtError::Git(missing));
+        let rendered = render(&err, dir.path());
+        assert!(rendered.starts_with("error[git]: Git operation failed: cannot locate local branch 'trunk'"));
+        assert!(rendered.contains("hint: branch 'trunk' not found — did you mean origin/trunk?"));
+
+        let err = anyhow::Error::from(RembrandtError::SessionNotFound("ses-1".to_string()))
+            .context("attaching");
+        assert_eq!(
+            render(&err, dir.path()),
+            "error[session_not_found]: attaching: Session not found: ses-1\n  hint: run `rembrandt list` to see current sessions\n"
+        );
+    }
+}
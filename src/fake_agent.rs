@@ -0,0 +1,141 @@
+//! Scripted stand-in for an agent CLI.
+//!
+//! `rembrandt fake-agent` plays a script instead of calling a model, so
+//! spawn, attach, merge and competition flows can be exercised in CI. Steps
+//! come from its arguments, or from `REMBRANDT_FAKE_SCRIPT` separated by `;`:
+//!
+//! - `say:TEXT` prints a line
+//! - `sleep:MS` waits
+//! - `read` reads a line from stdin and prints `got: LINE`
+//! - `commit:PATH=CONTENT` writes a file and commits it on the current branch
+//! - `exit:CODE` stops with that exit code (the default is 0)
+
+use crate::{RembrandtError, Result};
+use git2::{Repository, Signature};
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Environment variable holding a script when no steps are given as arguments
+pub const SCRIPT_ENV: &str = "REMBRANDT_FAKE_SCRIPT";
+
+/// One step of a fake agent's script
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FakeStep {
+    Say(String),
+    Sleep(Duration),
+    Read,
+    Commit { path: String, content: String },
+    Exit(i32),
+}
+
+impl FromStr for FakeStep {
+    type Err = RembrandtError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || RembrandtError::Validation(format!("invalid fake-agent step: {:?}", s));
+        let (verb, arg) = s.split_once(':').unwrap_or((s, ""));
+        match verb {
+            "say" => Ok(FakeStep::Say(arg.to_string())),
+            "sleep" => arg
+                .parse()
+                .map(|ms| FakeStep::Sleep(Duration::from_millis(ms)))
+                .map_err(|_| invalid()),
+            "read" => Ok(FakeStep::Read),
+            "commit" => {
+                let (path, content) = arg.split_once('=').unwrap_or((arg, ""));
+                if path.is_empty() {
+                    return Err(invalid());
+                }
+                Ok(FakeStep::Commit {
+                    path: path.to_string(),
+                    content: content.to_string(),
+                })
+            }
+            "exit" => arg.parse().map(FakeStep::Exit).map_err(|_| invalid()),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Parse a `;`-separated script, as found in `REMBRANDT_FAKE_SCRIPT`
+pub fn parse_script(script: &str) -> Result<Vec<FakeStep>> {
+    script
+        .split(';')
+        .map(str::trim)
+        .filter(|step| !step.is_empty())
+        .map(FakeStep::from_str)
+        .collect()
+}
+
+/// Play `steps` in `workdir`, returning the exit code to finish with
+pub fn run(steps: &[FakeStep], workdir: &Path) -> Result<i32> {
+    let stdin = std::io::stdin();
+    let mut stdout = std::io::stdout();
+    for step in steps {
+        match step {
+            FakeStep::Say(text) => writeln!(stdout, "{}", text)?,
+            FakeStep::Sleep(duration) => std::thread::sleep(*duration),
+            FakeStep::Read => {
+                let mut line = String::new();
+                stdin.lock().read_line(&mut line)?;
+                writeln!(stdout, "got: {}", line.trim_end())?;
+            }
+            FakeStep::Commit { path, content } => {
+                commit_file(workdir, path, content)?;
+                writeln!(stdout, "committed {}", path)?;
+            }
+            FakeStep::Exit(code) => {
+                stdout.flush()?;
+                return Ok(*code);
+            }
+        }
+        stdout.flush()?;
+    }
+    Ok(0)
+}
+
+/// Write `path` under the checkout containing `workdir` and commit it to HEAD
+fn commit_file(workdir: &Path, path: &str, content: &str) -> Result<()> {
+    let repo = Repository::discover(workdir)?;
+    let root = repo
+        .workdir()
+        .ok_or_else(|| RembrandtError::Agent("fake-agent needs a checkout, not a bare repo".to_string()))?;
+    let file = root.join(path);
+    if let Some(parent) = file.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&file, content)?;
+
+    let mut index = repo.index()?;
+    index.add_path(Path::new(path))?;
+    index.write()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let sig = Signature::now("rembrandt fake-agent", "fake-agent@rembrandt.invalid")?;
+    let parent = repo.head()?.peel_to_commit()?;
+    repo.commit(Some("HEAD"), &sig, &sig, &format!("fake-agent: {}", path), &tree, &[&parent])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripts_parse_into_steps() {
+        let steps = parse_script("say:hi there; sleep:50;read;commit:src/a.txt=x=1;exit:3").unwrap();
+        assert_eq!(
+            steps,
+            vec![
+                FakeStep::Say("hi there".to_string()),
+                FakeStep::Sleep(Duration::from_millis(50)),
+                FakeStep::Read,
+                FakeStep::Commit { path: "src/a.txt".to_string(), content: "x=1".to_string() },
+                FakeStep::Exit(3),
+            ]
+        );
+        assert!(parse_script("sleep:soon").is_err());
+        assert!(parse_script("dance").is_err());
+    }
+}
@@ -0,0 +1,155 @@
+//! Follow-up work agents defer, filed as Beads issues.
+//!
+//! Agents mark work they are leaving for later with `TODO(rembrandt): ...`
+//! on a line of output or in a commit message, or list it in a fenced block:
+//!
+//! ````text
+//! ```follow-up
+//! - handle Windows paths in the config loader
+//! - test the migration on an empty database
+//! ```
+//! ````
+//!
+//! `TimelineObserver` records these in state.db as it scans output and
+//! commits. `rembrandt csi run` then files the pending ones as Beads issues
+//! linked to the agent's task, so deferred work isn't lost when the session
+//! ends.
+
+use crate::integration::beads::BeadsClient;
+use crate::state::StateStore;
+use crate::timeline::{one_line, strip_ansi};
+use crate::Result;
+use chrono::{DateTime, Utc};
+
+/// Prefix marking a single deferred item
+pub const MARKER: &str = "TODO(rembrandt):";
+
+/// Info string of a fenced block of deferred items
+const FENCE_INFO: &str = "follow-up";
+
+/// Longest follow-up text kept; longer ones are cut
+const MAX_LEN: usize = 200;
+
+/// Deferred work reported by an agent.
+#[derive(Debug, Clone)]
+pub struct FollowUp {
+    pub id: i64,
+    pub agent_id: String,
+    pub text: String,
+    /// Where it was found: `output` or `commit <sha>`
+    pub source: String,
+    /// The task the agent was working on
+    pub task_id: Option<String>,
+    /// The Beads issue it was filed as
+    pub issue_id: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub filed_at: Option<DateTime<Utc>>,
+}
+
+/// Follow-ups marked in `text`, in order and without repeats
+pub fn detect(text: &str) -> Vec<String> {
+    let mut found: Vec<String> = Vec::new();
+    let mut in_fence = false;
+    for line in text.lines().map(strip_ansi) {
+        let line = line.trim();
+        if let Some(info) = line.strip_prefix("```") {
+            in_fence = !in_fence && info.trim().trim_end_matches(':') == FENCE_INFO;
+            continue;
+        }
+        let item = if in_fence {
+            let item = line.trim_start_matches(['-', '*']).trim();
+            item.strip_prefix(MARKER).unwrap_or(item).trim()
+        } else if let Some(at) = line.find(MARKER) {
+            line[at + MARKER.len()..].trim()
+        } else {
+            continue;
+        };
+        let item = one_line(item, MAX_LEN);
+        if !item.is_empty() && !found.contains(&item) {
+            found.push(item);
+        }
+    }
+    found
+}
+
+/// Record the follow-ups marked in `text`; returns how many were new.
+pub fn record(store: &StateStore, agent_id: &str, text: &str, source: &str) -> Result<usize> {
+    let mut new = 0;
+    for item in detect(text) {
+        if store.record_followup(agent_id, &item, source)? {
+            new += 1;
+        }
+    }
+    Ok(new)
+}
+
+/// File every pending follow-up as a Beads issue linked to its agent's task;
+/// returns the ones filed. Does nothing without Beads installed.
+pub fn file_pending(store: &StateStore, beads: &BeadsClient) -> Result<Vec<FollowUp>> {
+    if !beads.is_available() {
+        return Ok(Vec::new());
+    }
+    let mut filed = Vec::new();
+    for followup in store.followups(None, true)? {
+        let context = match &followup.task_id {
+            Some(task) => format!("while working on {}", task),
+            None => "with no task".to_string(),
+        };
+        let description = format!(
+            "Deferred by {} {} (found in {}).\n\n{}",
+            followup.agent_id, context, followup.source, followup.text
+        );
+        let title = one_line(&followup.text, 80);
+        match beads.create(&title, &description, followup.task_id.as_deref()) {
+            Ok(issue_id) => {
+                store.mark_followup_filed(followup.id, issue_id.as_deref())?;
+                filed.push(FollowUp { issue_id, filed_at: Some(Utc::now()), ..followup });
+            }
+            Err(e) => tracing::warn!(agent_id = %followup.agent_id, error = %e, "could not file follow-up"),
+        }
+    }
+    Ok(filed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markers_and_fenced_blocks_are_recorded_once() {
+        let output = "\u{1b}[2m// TODO(rembrandt): retry on 429\u{1b}[0m\r\n\
+            Done. Left for later:\n\
+            ```follow-up\n\
+            - handle Windows paths\n\
+            * TODO(rembrandt): retry on 429\n\
+            * test the migration on an empty database\n\
+            \n\
+            ```\n\
+            ```rust\n\
+            - not a follow-up\n\
+            ```\n\
+            TODO(rembrandt): retry on 429\n";
+        assert_eq!(
+            detect(output),
+            vec![
+                "retry on 429",
+                "handle Windows paths",
+                "test the migration on an empty database",
+            ]
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = StateStore::open(dir.path()).unwrap();
+        assert_eq!(record(&store, "a1", output, "output").unwrap(), 3);
+        assert_eq!(record(&store, "a1", "TODO(rembrandt): retry on 429", "commit abc1234").unwrap(), 0);
+        let pending = store.followups(Some("a1"), true).unwrap();
+        assert_eq!(pending.len(), 3);
+        assert_eq!(pending[0].source, "output");
+
+        store.mark_followup_filed(pending[0].id, Some("bd-7")).unwrap();
+        assert_eq!(store.followups(None, true).unwrap().len(), 2);
+        let all = store.followups(Some("a1"), false).unwrap();
+        assert_eq!(all[0].issue_id.as_deref(), Some("bd-7"));
+        assert!(store.followups(Some("a2"), false).unwrap().is_empty());
+    }
+}
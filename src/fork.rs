@@ -0,0 +1,174 @@
+//! Forking an agent into a new one that starts from its work.
+//!
+//! `rembrandt fork <agent> --as <new>` spawns `<new>` in a fresh worktree
+//! whose branch starts at the tip of `<agent>`'s branch, so a half-done
+//! approach can be taken in two directions, each with its own prompt. Only
+//! committed work carries over: uncommitted changes stay in the source's
+//! worktree. The new agent's timeline records where it was forked from.
+
+use crate::audit::AuditQuery;
+use crate::rescue;
+use crate::state::{SessionRecord, StateStore};
+use crate::{RembrandtError, Result};
+use git2::{Branch, BranchType, Repository};
+use std::path::Path;
+
+/// What a fork starts from
+#[derive(Debug, Clone)]
+pub struct ForkPlan {
+    pub source: SessionRecord,
+    /// Agent type for the new agent, e.g. `claude-code`
+    pub agent: String,
+    /// Abbreviated commit the new branch starts at
+    pub base_commit: String,
+    /// Source files with uncommitted changes, which the fork won't have
+    pub left_behind: Vec<String>,
+}
+
+impl ForkPlan {
+    /// Branch the new agent's branch is created from
+    pub fn base_branch(&self) -> &str {
+        &self.source.branch_name
+    }
+
+    /// One line for the new agent's timeline, e.g. `forked from claude-1a2b at 3f9c2d1`
+    pub fn lineage(&self) -> String {
+        format!("forked from {} at {}", self.source.agent_id, self.base_commit)
+    }
+}
+
+/// Check that `source` can be forked as `new_id` and work out what from
+///
+/// The agent type is `agent` if given, else the one `source` was spawned
+/// with according to the audit log, else the prefix of a generated ID such
+/// as `codex-4f2a`.
+pub fn plan(repo_path: &Path, store: &StateStore, source: &str, new_id: &str, agent: Option<&str>) -> Result<ForkPlan> {
+    let branch_name = format!("rembrandt/{}", new_id);
+    if new_id.is_empty() || new_id.contains('/') || !Branch::name_is_valid(&branch_name)? {
+        return Err(RembrandtError::Agent(format!("'{}' is not a valid agent ID", new_id)));
+    }
+    let repo = Repository::open(repo_path)?;
+    if store.get_session(new_id)?.is_some() || repo.find_branch(&branch_name, BranchType::Local).is_ok() {
+        return Err(RembrandtError::Agent(format!(
+            "agent '{}' already exists; pick another name for --as",
+            new_id
+        )));
+    }
+
+    let record = store
+        .get_session(source)?
+        .ok_or_else(|| RembrandtError::SessionNotFound(source.to_string()))?;
+    let tip = repo
+        .find_branch(&record.branch_name, BranchType::Local)
+        .map_err(|_| {
+            RembrandtError::Worktree(format!(
+                "branch {} of '{}' no longer exists",
+                record.branch_name, source
+            ))
+        })?
+        .get()
+        .peel_to_commit()?;
+    let base_commit = tip.as_object().short_id()?.as_str().unwrap_or_default().to_string();
+
+    let agent = match agent {
+        Some(agent) => agent.to_string(),
+        None => spawned_as(store, source)?
+            .or_else(|| agent_type_of(source).map(str::to_string))
+            .ok_or_else(|| RembrandtError::Agent(format!("can't tell which agent '{}' runs; pass --agent", source)))?,
+    };
+    let left_behind = if record.checkout_path.is_dir() {
+        rescue::check(&record.checkout_path)?.uncommitted
+    } else {
+        Vec::new()
+    };
+
+    Ok(ForkPlan {
+        source: record,
+        agent,
+        base_commit,
+        left_behind,
+    })
+}
+
+/// The agent type `agent_id`'s latest spawn was audited with
+fn spawned_as(store: &StateStore, agent_id: &str) -> Result<Option<String>> {
+    let spawns = store.audit_log(&AuditQuery {
+        action: Some("spawn".to_string()),
+        target: Some(agent_id.to_string()),
+        limit: Some(1),
+        ..Default::default()
+    })?;
+    Ok(spawns
+        .into_iter()
+        .next()
+        .and_then(|entry| entry.params?.get("agent")?.as_str().map(str::to_string)))
+}
+
+/// The agent type in an ID generated by `spawn`: `<agent>-<4 hex digits>`
+pub(crate) fn agent_type_of(agent_id: &str) -> Option<&str> {
+    let (agent, suffix) = agent_id.rsplit_once('-')?;
+    (!agent.is_empty() && suffix.len() == 4 && suffix.chars().all(|c| c.is_ascii_hexdigit())).then_some(agent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{git, session_record};
+    use crate::audit::{self, AuditSource};
+    use crate::isolation::IsolationMode;
+    use crate::worktree::WorktreeManager;
+
+    #[test]
+    fn forks_start_at_the_source_branch_tip() {
+        let dir = tempfile::tempdir().unwrap();
+        git(dir.path(), &["init", "-q", "-b", "main"]);
+        std::fs::write(dir.path().join("lib.rs"), "fn a() {}\n").unwrap();
+        git(dir.path(), &["add", "lib.rs"]);
+        git(dir.path(), &["commit", "-qm", "init"]);
+        let store = StateStore::open(dir.path()).unwrap();
+        let info = WorktreeManager::new(dir.path())
+            .unwrap()
+            .create_worktree("claude-code-1a2b", "main")
+            .unwrap();
+        std::fs::write(info.path.join("lib.rs"), "fn a() { b() }\n").unwrap();
+        git(&info.path, &["commit", "-qam", "half done"]);
+        std::fs::write(info.path.join("notes.md"), "wip\n").unwrap();
+        store
+            .upsert_session(&SessionRecord {
+                runtime_kind: "daemon".to_string(),
+                isolation_mode: IsolationMode::Worktree,
+                branch_name: info.branch.clone(),
+                checkout_path: info.path.clone(),
+                ..session_record("claude-code-1a2b")
+            })
+            .unwrap();
+
+        let fork = plan(dir.path(), &store, "claude-code-1a2b", "alt", None).unwrap();
+        assert_eq!(fork.agent, "claude-code");
+        assert_eq!(fork.base_branch(), "rembrandt/claude-code-1a2b");
+        assert_eq!(fork.left_behind, vec!["notes.md"]);
+        let worktree = Repository::open(&info.path).unwrap();
+        let tip = worktree.head().unwrap().peel_to_commit().unwrap().id();
+        assert!(tip.to_string().starts_with(&fork.base_commit));
+        assert!(fork.lineage().starts_with("forked from claude-code-1a2b at "));
+
+        // The audit log knows better than the ID
+        audit::record_in(
+            &store,
+            AuditSource::Cli,
+            "spawn",
+            Some("claude-code-1a2b"),
+            serde_json::json!({"agent": "aider"}),
+        )
+        .unwrap();
+        assert_eq!(
+            plan(dir.path(), &store, "claude-code-1a2b", "alt", None).unwrap().agent,
+            "aider"
+        );
+
+        assert!(plan(dir.path(), &store, "claude-code-1a2b", "claude-code-1a2b", None).is_err());
+        assert!(plan(dir.path(), &store, "claude-code-1a2b", "a b", None).is_err());
+        assert!(plan(dir.path(), &store, "missing", "alt", None).is_err());
+        assert_eq!(agent_type_of("my-agent"), None);
+    }
+}
@@ -0,0 +1,253 @@
+//! Health probes: a project smoke check run in each agent's checkout.
+//!
+//! `rembrandt csi run` runs `health_command` (e.g. `cargo check -q`) in every
+//! live session's checkout at most every `health_interval_mins`, keeping a
+//! pass/fail streak per agent in state.db. A check failing for
+//! `health_broken_after_mins` raises a `broken-build` attention item, which
+//! the dashboard shows next to the agent; it is resolved when the check
+//! passes again. With `health_steer`, an agent whose check starts failing is
+//! sent the failure.
+
+use crate::config::AppConfig;
+use crate::csi::SessionProbe;
+use crate::state::StateStore;
+use crate::timeline::{one_line, strip_ansi};
+use crate::Result;
+use chrono::{DateTime, Duration, Utc};
+use std::path::Path;
+
+/// Attention kind raised when an agent's build has been broken too long
+pub const BROKEN_BUILD: &str = "broken-build";
+
+/// Lines of check output kept in state.db
+const OUTPUT_LINES: usize = 40;
+
+/// Latest health check of one agent.
+#[derive(Debug, Clone)]
+pub struct HealthRecord {
+    pub agent_id: String,
+    pub checked_at: DateTime<Utc>,
+    pub passed: bool,
+    /// Consecutive checks with the same result, this one included
+    pub streak: u32,
+    /// When the current run of failures began
+    pub failing_since: Option<DateTime<Utc>>,
+    /// Tail of the check's output
+    pub output: Option<String>,
+}
+
+impl HealthRecord {
+    /// How long the check has been failing, `None` while it passes
+    pub fn failing_for(&self, now: DateTime<Utc>) -> Option<Duration> {
+        self.failing_since
+            .filter(|_| !self.passed)
+            .map(|since| now.signed_duration_since(since))
+    }
+
+    /// The most telling line of the output: the first error, else the last line
+    pub fn failure_line(&self) -> Option<String> {
+        let output = self.output.as_deref()?;
+        let lines: Vec<String> = output
+            .lines()
+            .map(|line| strip_ansi(line).trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect();
+        lines
+            .iter()
+            .find(|line| line.to_lowercase().starts_with("error"))
+            .or(lines.last())
+            .map(|line| one_line(line, 200))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HealthPolicy {
+    pub command: String,
+    pub interval: Duration,
+    pub broken_after: Duration,
+    /// How long one check may run before it is killed
+    pub timeout: std::time::Duration,
+    pub steer: bool,
+}
+
+impl HealthPolicy {
+    /// The configured policy, or `None` without a `health_command`
+    pub fn from_config(config: &AppConfig) -> Option<Self> {
+        Some(Self {
+            command: config.health_command.clone()?,
+            interval: Duration::minutes(config.health_interval_mins as i64),
+            broken_after: Duration::minutes(config.health_broken_after_mins as i64),
+            timeout: std::time::Duration::from_secs(config.health_timeout_secs),
+            steer: config.health_steer,
+        })
+    }
+
+    /// Whether `record` has failed for at least `broken_after`
+    pub fn is_broken(&self, record: &HealthRecord, now: DateTime<Utc>) -> bool {
+        record.failing_for(now).is_some_and(|age| age >= self.broken_after)
+    }
+
+    /// Check every live session that is due; returns the new results.
+    pub fn run_pass<P: SessionProbe + ?Sized>(
+        &self,
+        store: &StateStore,
+        mut probe: Option<&mut P>,
+    ) -> Result<Vec<HealthRecord>> {
+        let now = Utc::now();
+        let mut checked = Vec::new();
+        for session in store.list_active()? {
+            let agent_id = session.agent_id.as_str();
+            let due = store
+                .get_health(agent_id)?
+                .is_none_or(|last| now.signed_duration_since(last.checked_at) >= self.interval);
+            if !due || !session.checkout_path.is_dir() {
+                continue;
+            }
+
+            let (passed, output) = run_check(&self.command, &session.checkout_path, self.timeout);
+            let record = store.record_health(agent_id, passed, Some(&output))?;
+            if record.streak == 1 {
+                let detail = match (passed, record.failure_line()) {
+                    (true, _) => "health check passing".to_string(),
+                    (false, Some(line)) => format!("health check failing: {}", line),
+                    (false, None) => "health check failing".to_string(),
+                };
+                store.record_event(Some(agent_id), "health", &detail)?;
+                if !passed
+                    && self.steer
+                    && let Some(probe) = probe.as_deref_mut()
+                    && let Err(e) = probe.send_message(agent_id, &self.steer_message(&record))
+                {
+                    tracing::warn!(agent_id, error = %e, "could not steer agent about its health check");
+                }
+            }
+            self.update_attention(store, &record)?;
+            checked.push(record);
+        }
+        Ok(checked)
+    }
+
+    fn steer_message(&self, record: &HealthRecord) -> String {
+        let reason = record.failure_line().map(|line| format!(": {}", line)).unwrap_or_default();
+        format!(
+            "The health check `{}` is failing in your worktree{}. Please get it passing again before moving on.",
+            self.command, reason
+        )
+    }
+
+    /// Raise `broken-build` once the build has been broken long enough, and
+    /// resolve it when the check passes again
+    fn update_attention(&self, store: &StateStore, record: &HealthRecord) -> Result<()> {
+        let now = record.checked_at;
+        let open: Vec<i64> = store
+            .open_attention()?
+            .into_iter()
+            .filter(|item| item.kind == BROKEN_BUILD && item.agent_id.as_deref() == Some(&record.agent_id))
+            .map(|item| item.id)
+            .collect();
+        if record.passed {
+            for id in open {
+                store.resolve_attention(id)?;
+            }
+        } else if open.is_empty() && self.is_broken(record, now) {
+            let minutes = record.failing_for(now).map_or(0, |age| age.num_minutes());
+            let reason = record.failure_line().map(|line| format!(": {}", line)).unwrap_or_default();
+            store.raise_attention(
+                Some(&record.agent_id),
+                BROKEN_BUILD,
+                &format!("`{}` failing for {}m{}", self.command, minutes, reason),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Run `command` with `sh -c` in `checkout`; returns whether it passed and
+/// the tail of its combined output. A check still running after `timeout`
+/// is killed with everything it started, and fails.
+pub fn run_check(command: &str, checkout: &Path, timeout: std::time::Duration) -> (bool, String) {
+    match crate::hooks::run_command(command, checkout, &[], timeout) {
+        Ok(run) => {
+            let lines: Vec<&str> = run.output.lines().collect();
+            let mut tail = lines[lines.len().saturating_sub(OUTPUT_LINES)..].join("\n");
+            if run.timed_out {
+                tail.push_str(&format!("\nerror: health check timed out after {}s", timeout.as_secs()));
+            }
+            (run.succeeded(), tail.trim_start().to_string())
+        }
+        Err(e) => (false, format!("could not run health check: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::SessionRecord;
+    use crate::test_support::session_record;
+
+    #[derive(Default)]
+    struct SteerProbe {
+        sent: Vec<(String, String)>,
+    }
+
+    impl SessionProbe for SteerProbe {
+        fn output_bytes(&mut self, _agent_id: &str) -> Result<Option<usize>> {
+            Ok(None)
+        }
+
+        fn nudge(&mut self, _agent_id: &str) -> Result<String> {
+            Ok("enter".to_string())
+        }
+
+        fn send_message(&mut self, agent_id: &str, message: &str) -> Result<()> {
+            self.sent.push((agent_id.to_string(), message.to_string()));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn failing_checks_build_streaks_steer_and_raise_attention() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = StateStore::open(dir.path()).unwrap();
+        store
+            .upsert_session(&SessionRecord {
+                runtime_kind: "daemon".to_string(),
+                checkout_path: dir.path().to_path_buf(),
+                ..session_record("a1")
+            })
+            .unwrap();
+        let policy = HealthPolicy {
+            command: "test -f ok || { echo 'error[E0308]: mismatched types' >&2; exit 1; }".to_string(),
+            interval: Duration::zero(),
+            broken_after: Duration::zero(),
+            timeout: std::time::Duration::from_secs(30),
+            steer: true,
+        };
+        let mut probe = SteerProbe::default();
+
+        let first = policy.run_pass(&store, Some(&mut probe)).unwrap();
+        assert!(!first[0].passed);
+        assert_eq!(first[0].failure_line().as_deref(), Some("error[E0308]: mismatched types"));
+        assert_eq!(probe.sent.len(), 1);
+        assert!(probe.sent[0].1.contains("mismatched types"), "{}", probe.sent[0].1);
+        let attention = store.open_attention().unwrap();
+        assert_eq!(attention.len(), 1);
+        assert_eq!(attention[0].kind, BROKEN_BUILD);
+
+        // Still failing: the streak grows, no second steer or attention item
+        let second = policy.run_pass(&store, Some(&mut probe)).unwrap();
+        assert_eq!(second[0].streak, 2);
+        assert_eq!(second[0].failing_since, first[0].failing_since);
+        assert_eq!(probe.sent.len(), 1);
+        assert_eq!(store.open_attention().unwrap().len(), 1);
+
+        std::fs::write(dir.path().join("ok"), "").unwrap();
+        let fixed = policy.run_pass(&store, Some(&mut probe)).unwrap();
+        assert!(fixed[0].passed && fixed[0].streak == 1 && fixed[0].failing_since.is_none());
+        assert!(store.open_attention().unwrap().is_empty());
+
+        let (passed, output) = run_check("sleep 5", dir.path(), std::time::Duration::from_millis(100));
+        assert!(!passed);
+        assert_eq!(output, "error: health check timed out after 0s");
+    }
+}
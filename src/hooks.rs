@@ -0,0 +1,274 @@
+//! Lifecycle hooks: user scripts run at fixed points of an agent's life.
+//!
+//! Hooks are shell commands from `[hooks]` in `.rembrandt/config.toml`:
+//!
+//! ```toml
+//! [hooks]
+//! timeout_secs = 60
+//! post_worktree = ["npm ci"]
+//! on_failure = ["notify-send \"$REMBRANDT_AGENT_ID failed\""]
+//! ```
+//!
+//! Each runs with `sh -c`, with the agent's context in its environment
+//! (`REMBRANDT_AGENT_ID`, `REMBRANDT_BRANCH`, `REMBRANDT_TASK_ID` and the
+//! agent's configured env) plus `REMBRANDT_HOOK` and `REMBRANDT_REPO`. Every
+//! run is recorded in `csi_events` with the tail of its output. A failing
+//! `pre_spawn`, `post_worktree` or `pre_merge` hook stops what it guards;
+//! `post_merge` and `on_failure` hooks are only reported.
+
+use crate::config::HooksConfig;
+use crate::state::StateStore;
+use crate::{RembrandtError, Result};
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+pub use rembrandt_core::hooks::HookPoint;
+
+/// Bytes of a hook's output kept for its event
+const OUTPUT_TAIL_BYTES: usize = 4096;
+
+/// How often a running hook is checked for exit or timeout
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// How long output is still collected after a hook exits, from background
+/// processes that inherited its pipes
+const OUTPUT_DRAIN_WAIT: Duration = Duration::from_millis(100);
+
+/// `csi_events` kinds for hook runs
+pub const HOOK_EVENT: &str = "hook";
+pub const HOOK_FAILED_EVENT: &str = "hook_failed";
+
+/// How one hook command went
+#[derive(Debug, Clone)]
+pub struct HookRun {
+    pub command: String,
+    /// Exit code; `None` if it timed out or died from a signal
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    pub duration: Duration,
+    /// Tail of stdout and stderr, interleaved as written
+    pub output: String,
+}
+
+impl HookRun {
+    pub fn succeeded(&self) -> bool {
+        !self.timed_out && self.exit_code == Some(0)
+    }
+
+    /// One line on how it went, e.g. ``post_worktree `make setup` exited 2 in 1.2s``
+    pub fn describe(&self, point: HookPoint) -> String {
+        let outcome = match (self.timed_out, self.exit_code) {
+            (true, _) => "timed out".to_string(),
+            (false, Some(code)) => format!("exited {}", code),
+            (false, None) => "was killed".to_string(),
+        };
+        format!(
+            "{} `{}` {} in {:.1}s",
+            point,
+            self.command,
+            outcome,
+            self.duration.as_secs_f64()
+        )
+    }
+}
+
+/// Where and for whom hooks run
+pub struct HookContext<'a> {
+    pub repo_path: &'a Path,
+    /// Working directory of the hooks
+    pub cwd: &'a Path,
+    pub agent_id: &'a str,
+    /// The agent's environment (see `AppConfig::spawn_env`)
+    pub env: &'a [(String, String)],
+    /// Where to record runs, if anywhere
+    pub store: Option<&'a StateStore>,
+}
+
+/// Run the hooks configured for `point`, in order
+///
+/// Every run is recorded. For blocking points the first failure stops the
+/// rest and is returned as an error; otherwise all hooks run and failures
+/// are only recorded and logged.
+pub fn run(hooks: &HooksConfig, point: HookPoint, ctx: &HookContext<'_>) -> Result<Vec<HookRun>> {
+    let timeout = Duration::from_secs(hooks.timeout_secs);
+    let mut env = ctx.env.to_vec();
+    env.push(("REMBRANDT_HOOK".to_string(), point.as_str().to_string()));
+    env.push(("REMBRANDT_REPO".to_string(), ctx.repo_path.display().to_string()));
+
+    let mut runs = Vec::new();
+    for command in hooks.commands(point) {
+        let run = run_command(command, ctx.cwd, &env, timeout)?;
+        let summary = run.describe(point);
+        if let Some(store) = ctx.store {
+            let kind = if run.succeeded() { HOOK_EVENT } else { HOOK_FAILED_EVENT };
+            let message = if run.output.is_empty() {
+                summary.clone()
+            } else {
+                format!("{}\n{}", summary, run.output)
+            };
+            if let Err(e) = store.record_event(Some(ctx.agent_id), kind, &message) {
+                tracing::warn!("Failed to record {} hook for {}: {}", point, ctx.agent_id, e);
+            }
+        }
+
+        let failed = !run.succeeded();
+        runs.push(run);
+        if failed {
+            if point.is_blocking() {
+                return Err(RembrandtError::Runtime(format!("hook failed: {}", summary)));
+            }
+            tracing::warn!("Hook for {} failed: {}", ctx.agent_id, summary);
+        }
+    }
+    Ok(runs)
+}
+
+/// Run one hook command, killing it (and whatever it started) on timeout;
+/// health checks are run the same way
+pub(crate) fn run_command(command: &str, cwd: &Path, env: &[(String, String)], timeout: Duration) -> Result<HookRun> {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c")
+        .arg(command)
+        .current_dir(cwd)
+        .envs(env.iter().map(|(key, value)| (key, value)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+
+    let started = Instant::now();
+    let mut child = cmd.spawn()?;
+    let output = Arc::new(Mutex::new(Vec::new()));
+    let readers = [
+        child.stdout.take().map(|pipe| collect(pipe, output.clone())),
+        child.stderr.take().map(|pipe| collect(pipe, output.clone())),
+    ];
+
+    let mut timed_out = false;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break Some(status);
+        }
+        if started.elapsed() >= timeout {
+            timed_out = true;
+            #[cfg(unix)]
+            rembrandt_core::process::signal_tree(child.id(), libc::SIGKILL);
+            let _ = child.kill();
+            let _ = child.wait();
+            break None;
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    };
+    let duration = started.elapsed();
+
+    // Don't wait on background processes that kept the pipes open
+    let drain_deadline = Instant::now() + OUTPUT_DRAIN_WAIT;
+    while readers.iter().flatten().any(|r| !r.is_finished()) && Instant::now() < drain_deadline {
+        std::thread::sleep(Duration::from_millis(5));
+    }
+
+    let output = output.lock().map(|o| o.clone()).unwrap_or_default();
+    let start = output.len().saturating_sub(OUTPUT_TAIL_BYTES);
+    Ok(HookRun {
+        command: command.to_string(),
+        exit_code: status.and_then(|s| s.code()),
+        timed_out,
+        duration,
+        output: String::from_utf8_lossy(&output[start..]).trim_end().to_string(),
+    })
+}
+
+/// Append everything read from `pipe` to `output`
+fn collect(mut pipe: impl Read + Send + 'static, output: Arc<Mutex<Vec<u8>>>) -> std::thread::JoinHandle<()> {
+    std::thread::spawn(move || {
+        let mut buf = [0u8; 4096];
+        while let Ok(n) = pipe.read(&mut buf) {
+            if n == 0 {
+                break;
+            }
+            if let Ok(mut output) = output.lock() {
+                output.extend_from_slice(&buf[..n]);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hooks(toml: &str) -> HooksConfig {
+        crate::config::AppConfig::from_toml(toml).unwrap().hooks
+    }
+
+    #[test]
+    fn runs_with_context_and_records_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = StateStore::open(dir.path()).unwrap();
+        let hooks = hooks(
+            r#"
+            [hooks]
+            post_worktree = ["echo \"$REMBRANDT_HOOK $REMBRANDT_AGENT_ID\" > seen; echo copied", "exit 0"]
+            on_failure = ["echo oops >&2; exit 4", "touch still-ran"]
+            "#,
+        );
+        let env = vec![("REMBRANDT_AGENT_ID".to_string(), "claude-1".to_string())];
+        let ctx = HookContext {
+            repo_path: dir.path(),
+            cwd: dir.path(),
+            agent_id: "claude-1",
+            env: &env,
+            store: Some(&store),
+        };
+
+        let runs = run(&hooks, HookPoint::PostWorktree, &ctx).unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].output, "copied");
+        let seen = std::fs::read_to_string(dir.path().join("seen")).unwrap();
+        assert_eq!(seen.trim(), "post_worktree claude-1");
+
+        // Non-blocking: the failure is recorded and the next hook still runs
+        let runs = run(&hooks, HookPoint::OnFailure, &ctx).unwrap();
+        assert_eq!(runs[0].exit_code, Some(4));
+        assert!(dir.path().join("still-ran").exists());
+
+        let events = store.list_agent_events("claude-1").unwrap();
+        let kinds: Vec<&str> = events.iter().map(|e| e.kind.as_str()).collect();
+        assert_eq!(kinds, [HOOK_EVENT, HOOK_EVENT, HOOK_FAILED_EVENT, HOOK_EVENT]);
+        assert!(events[2].message.starts_with("on_failure `echo oops >&2; exit 4` exited 4"));
+        assert!(events[2].message.ends_with("\noops"));
+    }
+
+    #[test]
+    fn blocking_hooks_stop_on_failure_and_time_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let hooks = hooks(
+            r#"
+            [hooks]
+            timeout_secs = 1
+            pre_spawn = ["sleep 30", "touch never"]
+            "#,
+        );
+        let ctx = HookContext {
+            repo_path: dir.path(),
+            cwd: dir.path(),
+            agent_id: "claude-1",
+            env: &[],
+            store: None,
+        };
+
+        let started = Instant::now();
+        let err = run(&hooks, HookPoint::PreSpawn, &ctx).unwrap_err().to_string();
+        assert!(err.contains("pre_spawn `sleep 30` timed out"), "{}", err);
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(!dir.path().join("never").exists());
+        assert!(run(&hooks, HookPoint::PostMerge, &ctx).unwrap().is_empty());
+    }
+}
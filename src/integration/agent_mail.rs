@@ -28,6 +28,11 @@ impl AgentMailIntegration {
         }
     }
 
+    /// Configured Agent Mail server URL, if any
+    pub fn server_url(&self) -> Option<&str> {
+        self.server_url.as_deref()
+    }
+
     /// Reserve files for an agent
     pub fn reserve_files(&self, agent_id: &str, files: &[PathBuf]) -> Result<Reservation> {
         // TODO: Implement via MCP
@@ -40,25 +45,25 @@ impl AgentMailIntegration {
     }
 
     /// Release file reservations
-    pub fn release_reservation(&self, reservation_id: &str) -> Result<()> {
+    pub fn release_reservation(&self, _reservation_id: &str) -> Result<()> {
         // TODO: Implement via MCP
         Ok(())
     }
 
     /// Send a message to another agent
-    pub fn send_message(&self, from: &str, to: &str, content: &str) -> Result<()> {
+    pub fn send_message(&self, _from: &str, _to: &str, _content: &str) -> Result<()> {
         // TODO: Implement via MCP
         Ok(())
     }
 
     /// Broadcast a message to all agents
-    pub fn broadcast(&self, from: &str, content: &str) -> Result<()> {
+    pub fn broadcast(&self, _from: &str, _content: &str) -> Result<()> {
         // TODO: Implement via MCP
         Ok(())
     }
 
     /// Check for new messages
-    pub fn check_messages(&self, agent_id: &str) -> Result<Vec<Message>> {
+    pub fn check_messages(&self, _agent_id: &str) -> Result<Vec<Message>> {
         // TODO: Implement via MCP
         Ok(vec![])
     }
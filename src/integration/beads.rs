@@ -1,92 +1,27 @@
-//! Beads-rust integration - task tracking via `br` CLI
+//! Beads integration - task tracking via the `br` or `bd` CLI
+//!
+//! The client lives in `rembrandt-core` so the GUI uses the same one.
 
 use super::Integration;
-use crate::Result;
-use std::process::Command;
 
-/// Integration with Beads issue tracker
-pub struct BeadsIntegration {
-    available: bool,
-}
-
-impl BeadsIntegration {
-    pub fn new() -> Self {
-        let available = Command::new("br")
-            .arg("--version")
-            .output()
-            .map(|o| o.status.success())
-            .unwrap_or(false);
-
-        Self { available }
-    }
-
-    /// Get ready tasks (no blockers)
-    pub fn ready_tasks(&self) -> Result<Vec<BeadsTask>> {
-        if !self.available {
-            return Ok(vec![]);
-        }
-
-        let output = Command::new("br")
-            .args(["ready", "--json"])
-            .output()?;
-
-        if output.status.success() {
-            let tasks: Vec<BeadsTask> = serde_json::from_slice(&output.stdout)
-                .unwrap_or_default();
-            Ok(tasks)
-        } else {
-            Ok(vec![])
-        }
-    }
-
-    /// Update task status
-    pub fn update_status(&self, task_id: &str, status: &str) -> Result<()> {
-        if !self.available {
-            return Ok(());
-        }
-
-        Command::new("br")
-            .args(["update", task_id, "--status", status])
-            .output()?;
+pub use rembrandt_core::beads::{BeadsBinary, BeadsClient, BeadsTask};
 
-        Ok(())
-    }
-
-    /// Sync with remote
-    pub fn sync(&self) -> Result<()> {
-        if !self.available {
-            return Ok(());
-        }
-
-        Command::new("br")
-            .arg("sync")
-            .output()?;
-
-        Ok(())
+/// One-line summary for `rembrandt status`: the CLI in use and the database
+/// read directly, if any
+pub fn describe(beads: &BeadsClient) -> String {
+    let mut text = beads.binary().map_or("not found".to_string(), |b| format!("available ({})", b));
+    if let Some(db) = beads.database() {
+        text.push_str(&format!(", reading {}", db.display()));
     }
+    text
 }
 
-impl Default for BeadsIntegration {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-impl Integration for BeadsIntegration {
+impl Integration for BeadsClient {
     fn is_available(&self) -> bool {
-        self.available
+        BeadsClient::is_available(self)
     }
 
     fn name(&self) -> &'static str {
         "beads"
     }
 }
-
-/// A Beads task
-#[derive(Debug, Clone, serde::Deserialize)]
-pub struct BeadsTask {
-    pub id: String,
-    pub title: String,
-    pub status: String,
-    pub priority: Option<i32>,
-}
@@ -1,26 +1,19 @@
 //! Workspace isolation strategies for v2 orchestration.
 
+#[cfg(any(test, feature = "test-util"))]
+mod tempdir;
+
+#[cfg(any(test, feature = "test-util"))]
+pub use tempdir::TempdirIsolation;
+
+use crate::config::AppConfig;
 use crate::worktree::WorktreeManager;
 use crate::{RembrandtError, Result};
 use async_trait::async_trait;
 use git2::{BranchType, Repository};
 use std::path::{Path, PathBuf};
 
-/// Supported workspace isolation modes.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum IsolationMode {
-    Branch,
-    Worktree,
-}
-
-impl std::fmt::Display for IsolationMode {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            IsolationMode::Branch => write!(f, "branch"),
-            IsolationMode::Worktree => write!(f, "worktree"),
-        }
-    }
-}
+pub use rembrandt_core::isolation::IsolationMode;
 
 /// Provisioned workspace details returned by an isolation strategy.
 #[derive(Debug, Clone)]
@@ -30,6 +23,9 @@ pub struct IsolationContext {
     pub repo_path: PathBuf,
     pub checkout_path: PathBuf,
     pub branch_name: String,
+    /// Untracked files propagated into a new worktree (`[worktree]
+    /// copy_patterns`), relative to the repository root
+    pub propagated: Vec<PathBuf>,
 }
 
 #[async_trait]
@@ -63,20 +59,14 @@ impl IsolationStrategy for WorktreeIsolation {
         agent_id: &str,
         base_branch: &str,
     ) -> Result<IsolationContext> {
-        let manager = WorktreeManager::new(repo_path)?;
-        let info = manager.create_worktree(agent_id, base_branch)?;
-        Ok(IsolationContext {
-            agent_id: agent_id.to_string(),
-            mode: IsolationMode::Worktree,
-            repo_path: repo_path.to_path_buf(),
-            checkout_path: info.path,
-            branch_name: info.branch,
-        })
+        prepare_blocking(IsolationMode::Worktree, repo_path, agent_id, base_branch).await
     }
 
     async fn cleanup(&self, ctx: &IsolationContext) -> Result<()> {
-        let manager = WorktreeManager::new(&ctx.repo_path)?;
-        manager.remove_worktree(&ctx.agent_id)
+        let (repo_path, agent_id) = (ctx.repo_path.clone(), ctx.agent_id.clone());
+        tokio::task::spawn_blocking(move || WorktreeManager::new(&repo_path)?.remove_worktree(&agent_id))
+            .await
+            .map_err(|e| RembrandtError::Isolation(format!("worktree cleanup failed: {}", e)))?
     }
 }
 
@@ -95,24 +85,89 @@ impl IsolationStrategy for BranchIsolation {
         agent_id: &str,
         base_branch: &str,
     ) -> Result<IsolationContext> {
-        let repo = Repository::open(repo_path)?;
-        let branch_name = format!("rembrandt/{}", agent_id);
-
-        let base = repo
-            .find_branch(base_branch, BranchType::Local)
-            .map_err(RembrandtError::Git)?;
-        let base_commit = base.get().peel_to_commit()?;
-
-        if repo.find_branch(&branch_name, BranchType::Local).is_err() {
-            repo.branch(&branch_name, &base_commit, false)?;
-        }
-
-        Ok(IsolationContext {
-            agent_id: agent_id.to_string(),
-            mode: IsolationMode::Branch,
-            repo_path: repo_path.to_path_buf(),
-            checkout_path: repo_path.to_path_buf(),
-            branch_name,
-        })
+        prepare_blocking(IsolationMode::Branch, repo_path, agent_id, base_branch).await
+    }
+}
+
+/// Provision an agent's workspace in `mode`.
+///
+/// The strategies above do this for the orchestrator, on the blocking pool;
+/// the PTY spawn paths (CLI, TUI) call it directly since they run without an
+/// async runtime.
+pub fn prepare_workspace(
+    mode: IsolationMode,
+    repo_path: &Path,
+    agent_id: &str,
+    base_branch: &str,
+) -> Result<IsolationContext> {
+    match mode {
+        IsolationMode::Worktree => create_worktree(repo_path, agent_id, base_branch),
+        IsolationMode::Branch => create_branch(repo_path, agent_id, base_branch),
+    }
+}
+
+/// Remove a workspace whose setup failed, e.g. on a failing `post_worktree`
+/// hook: a worktree goes, while a branch checkout is the shared one and stays
+pub fn discard_workspace(ctx: &IsolationContext) {
+    if ctx.mode != IsolationMode::Worktree {
+        return;
     }
+    let removed = AppConfig::load(&ctx.repo_path)
+        .and_then(|config| config.worktree.manager(&ctx.repo_path))
+        .and_then(|manager| manager.remove_worktree(&ctx.agent_id));
+    if let Err(e) = removed {
+        tracing::warn!(agent_id = %ctx.agent_id, error = %e, "Could not remove worktree after failed setup");
+    }
+}
+
+/// `prepare_workspace` on the blocking pool, keeping git work off async tasks
+async fn prepare_blocking(
+    mode: IsolationMode,
+    repo_path: &Path,
+    agent_id: &str,
+    base_branch: &str,
+) -> Result<IsolationContext> {
+    let repo_path = repo_path.to_path_buf();
+    let (agent_id, base_branch) = (agent_id.to_string(), base_branch.to_string());
+    tokio::task::spawn_blocking(move || prepare_workspace(mode, &repo_path, &agent_id, &base_branch))
+        .await
+        .map_err(|e| RembrandtError::Isolation(format!("preparing {} workspace failed: {}", mode, e)))?
+}
+
+fn create_worktree(repo_path: &Path, agent_id: &str, base_branch: &str) -> Result<IsolationContext> {
+    let config = AppConfig::load(repo_path)?;
+    let manager = config.worktree.manager(repo_path)?;
+    let info = manager.create_worktree(agent_id, base_branch)?;
+    crate::blackboard::sync_into(repo_path, &info.path)?;
+    Ok(IsolationContext {
+        agent_id: agent_id.to_string(),
+        mode: IsolationMode::Worktree,
+        repo_path: repo_path.to_path_buf(),
+        checkout_path: info.path,
+        branch_name: info.branch,
+        propagated: info.propagated,
+    })
+}
+
+fn create_branch(repo_path: &Path, agent_id: &str, base_branch: &str) -> Result<IsolationContext> {
+    let repo = Repository::open(repo_path)?;
+    let branch_name = format!("rembrandt/{}", agent_id);
+
+    let base = repo
+        .find_branch(base_branch, BranchType::Local)
+        .map_err(RembrandtError::Git)?;
+    let base_commit = base.get().peel_to_commit()?;
+
+    if repo.find_branch(&branch_name, BranchType::Local).is_err() {
+        repo.branch(&branch_name, &base_commit, false)?;
+    }
+
+    Ok(IsolationContext {
+        agent_id: agent_id.to_string(),
+        mode: IsolationMode::Branch,
+        repo_path: repo_path.to_path_buf(),
+        checkout_path: repo_path.to_path_buf(),
+        branch_name,
+        propagated: Vec::new(),
+    })
 }
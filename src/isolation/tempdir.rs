@@ -0,0 +1,86 @@
+//! Git-free isolation for tests (`--features test-util`).
+
+use super::{IsolationContext, IsolationMode, IsolationStrategy};
+use crate::Result;
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tempfile::TempDir;
+
+/// Fabricates each agent's checkout as a plain directory under a temporary
+/// root, seeded with fixed files; nothing touches git.
+///
+/// Contexts report `Worktree` mode and a `rembrandt/<agent>` branch name, as
+/// a real worktree would. The root is deleted when the strategy is dropped.
+pub struct TempdirIsolation {
+    root: TempDir,
+    seed: Vec<(PathBuf, String)>,
+    prepared: Mutex<Vec<String>>,
+}
+
+impl TempdirIsolation {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            root: tempfile::tempdir()?,
+            seed: Vec::new(),
+            prepared: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Write `content` to `path` (relative) in every checkout
+    pub fn with_file(mut self, path: impl Into<PathBuf>, content: impl Into<String>) -> Self {
+        self.seed.push((path.into(), content.into()));
+        self
+    }
+
+    /// Directory the checkouts are created in
+    pub fn root(&self) -> &Path {
+        self.root.path()
+    }
+
+    /// Agents whose checkouts were prepared, oldest first
+    pub fn prepared(&self) -> Vec<String> {
+        self.prepared.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl IsolationStrategy for TempdirIsolation {
+    fn mode(&self) -> IsolationMode {
+        IsolationMode::Worktree
+    }
+
+    async fn prepare(
+        &self,
+        repo_path: &Path,
+        agent_id: &str,
+        _base_branch: &str,
+    ) -> Result<IsolationContext> {
+        let checkout_path = self.root.path().join(agent_id);
+        for (path, content) in &self.seed {
+            let file = checkout_path.join(path);
+            if let Some(parent) = file.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(file, content)?;
+        }
+        std::fs::create_dir_all(&checkout_path)?;
+        self.prepared.lock().unwrap().push(agent_id.to_string());
+
+        Ok(IsolationContext {
+            agent_id: agent_id.to_string(),
+            mode: IsolationMode::Worktree,
+            repo_path: repo_path.to_path_buf(),
+            checkout_path,
+            branch_name: format!("rembrandt/{}", agent_id),
+            propagated: Vec::new(),
+        })
+    }
+
+    async fn cleanup(&self, ctx: &IsolationContext) -> Result<()> {
+        if ctx.checkout_path.starts_with(self.root.path()) && ctx.checkout_path.exists() {
+            std::fs::remove_dir_all(&ctx.checkout_path)?;
+        }
+        Ok(())
+    }
+}
@@ -3,70 +3,55 @@
 //! Like Rembrandt's workshop - multiple apprentices working on different parts
 //! of the canvas, unified by the master into a cohesive masterpiece.
 
-pub mod agent;
+pub mod attribution;
+pub mod audit;
+pub mod blackboard;
+pub mod bundle;
+pub mod budget;
 pub mod cli;
-pub mod competition;
-pub mod config;
+pub mod completion;
+pub mod conflicts;
+pub mod csi;
 pub mod daemon;
+pub mod doctor;
+pub mod errors;
+pub mod fake_agent;
+pub mod followups;
+pub mod fork;
+pub mod health;
+pub mod hooks;
 pub mod isolation;
 pub mod integration;
+pub mod master;
+pub mod merge_queue;
 pub mod orchestrator;
+pub mod plan;
+pub mod reconcile;
+pub mod report;
+pub mod rescue;
+pub mod resolution;
+pub mod restore;
 pub mod runtime;
+pub mod schedule;
+pub mod search;
+pub mod snapshot;
 pub mod state;
+pub mod statusline;
+pub mod sync;
+pub mod telemetry;
+pub mod terminal;
+pub mod templates;
+pub mod timeline;
+pub mod trailers;
+pub mod transcript;
 pub mod tui;
-pub mod worktree;
+pub mod usage;
+pub mod warm_pool;
+pub mod watch;
 
-use thiserror::Error;
+#[cfg(test)]
+mod test_support;
 
-#[derive(Error, Debug)]
-pub enum RembrandtError {
-    #[error("Git operation failed: {0}")]
-    Git(#[from] git2::Error),
-
-    #[error("Database error: {0}")]
-    Database(#[from] rusqlite::Error),
-
-    #[error("IO error: {0}")]
-    Io(#[from] std::io::Error),
-
-    #[error("Agent error: {0}")]
-    Agent(String),
-
-    #[error("Worktree error: {0}")]
-    Worktree(String),
-
-    #[error("Competition error: {0}")]
-    Competition(String),
-
-    #[error("Evaluation error: {0}")]
-    Evaluation(String),
-
-    #[error("Validation error: {0}")]
-    Validation(String),
-
-    #[error("Daemon error: {0}")]
-    Daemon(String),
-
-    #[error("Runtime error: {0}")]
-    Runtime(String),
-
-    #[error("Orchestrator error: {0}")]
-    Orchestrator(String),
-
-    #[error("State error: {0}")]
-    State(String),
-
-    #[error("Isolation error: {0}")]
-    Isolation(String),
-
-    #[error("Configuration error: {0}")]
-    Config(String),
-
-    #[error("Session not found: {0}")]
-    SessionNotFound(String),
-
-    #[error("PTY error: {0}")]
-    Pty(String),
-}
-
-pub type Result<T> = std::result::Result<T, RembrandtError>;
+pub use rembrandt_core::{
+    agent, competition, config, ignore, process, restart, worktree, ErrorCode, ErrorPayload, RembrandtError, Result,
+};
@@ -1,38 +1,147 @@
 use anyhow::Result;
 use clap::Parser;
-use rembrandt::agent::AgentType;
-use rembrandt::cli::{Cli, Commands};
-use rembrandt::daemon::session::PtySession;
+use rembrandt::agent::{wait_for_first_screen, AgentType};
+use rembrandt::audit::AuditSource;
+use rembrandt::cli::{
+    BlackboardAction, Cli, Commands, CompeteAction, CsiAction, DaemonAction, MsgAction, PoolAction, QueueAction,
+    StateAction,
+};
+use rembrandt::daemon::manager::SessionInfo;
+use rembrandt::daemon::session::{PtySession, SessionStatus};
+use rembrandt::daemon::ipc::{default_socket_path, DaemonConnection};
+use rembrandt::hooks::{HookContext, HookPoint};
+use rembrandt::isolation::IsolationMode;
 use rembrandt::runtime::AgentRuntime;
-use rembrandt::worktree::WorktreeManager;
-use std::io::Read;
-use std::path::PathBuf;
-
-fn main() -> Result<()> {
-    // Initialize logging
-    tracing_subscriber::fmt()
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::from_default_env()
-                .add_directive("rembrandt=info".parse()?),
-        )
-        .init();
+use rembrandt::state::{SessionQuery, SessionRecord, StateStore};
+use rembrandt::worktree::{main_repo_root, WorktreeManager};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
+fn main() -> ExitCode {
+    rembrandt::completion::handle_request();
     let cli = Cli::parse();
+    let repo_path = cli.repo.clone().unwrap_or_else(|| PathBuf::from("."));
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprint!("{}", rembrandt::errors::render(&e, &repo_path));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
+    // Runs from the commit hook: a broken config or log setup mustn't
+    // surface there, so it goes before either is touched
+    if let Commands::CommitTrailers { message_file } = &cli.command {
+        commit_trailers(message_file);
+        return Ok(());
+    }
     let use_v2 = cli.v2;
     let repo_path = cli.repo.unwrap_or_else(|| PathBuf::from("."));
 
-    match cli.command {
-        Commands::Init => {
+    // Initialize logging (and trace export). A broken config file shouldn't
+    // stop commands that never read it
+    let mut telemetry_config = rembrandt::config::AppConfig::load(&repo_path).unwrap_or_default();
+    if let Some(format) = cli.log_format {
+        telemetry_config.log_format = format;
+    }
+    let _telemetry = rembrandt::telemetry::init(&telemetry_config)?;
+
+    // A fork is a spawn from another agent's branch
+    let command = match cli.command {
+        Commands::Fork { source, new_agent, agent, prompt, no_prompt, detach, model } => {
+            let store = StateStore::open(&repo_path)?;
+            let fork = rembrandt::fork::plan(&repo_path, &store, &source, &new_agent, agent.as_deref())?;
+            if !fork.left_behind.is_empty() {
+                eprintln!(
+                    "Warning: {} uncommitted file(s) in {} won't be in the fork; commit them first to carry them over",
+                    fork.left_behind.len(),
+                    source
+                );
+            }
+            println!("Forking '{}' at {} as '{}'...", source, fork.base_commit, new_agent);
+            Commands::Spawn {
+                agent: Some(fork.agent.clone()),
+                task: fork.source.task_id.clone(),
+                branch: fork.base_branch().to_string(),
+                r#continue: None,
+                prompt,
+                no_prompt,
+                detach,
+                remote: None,
+                buffer_capacity: None,
+                restart: None,
+                profile: None,
+                model: model.or_else(|| fork.source.model.clone()),
+                env: Vec::new(),
+                id: Some(new_agent),
+                forked_from: Some(fork.lineage()),
+            }
+        }
+        command => command,
+    };
+
+    match command {
+        Commands::Init { hooks } => {
             println!("Initializing Rembrandt...");
             let manager = WorktreeManager::new(&repo_path)?;
             println!("Created {}", manager.rembrandt_dir().display());
+            if hooks {
+                let hook = rembrandt::trailers::install_hook(&repo_path)?;
+                println!("Installed {}", hook.display());
+            }
         }
 
-        Commands::Spawn { agent, task, branch, r#continue: continue_id, prompt, no_prompt } => {
+        Commands::CommitTrailers { .. } => unreachable!("handled before config is loaded"),
+
+        Commands::Spawn { agent, task, branch, r#continue: continue_id, prompt, no_prompt, detach, remote, buffer_capacity, restart, profile, model, env, id, forked_from } => {
             let wt_manager = WorktreeManager::new(&repo_path)?;
+            let config = rembrandt::config::AppConfig::load(&repo_path)?;
+            let profile = profile.map(|name| config.profile(&name).cloned()).transpose()?;
+            let agent = match (agent, &profile) {
+                (Some(agent), _) => agent,
+                (None, Some(profile)) => profile.agent.clone(),
+                (None, None) => anyhow::bail!("spawn needs an agent type or --profile"),
+            };
+            let store = StateStore::open(&repo_path)?;
+            let agent_env =
+                |id: &str, branch: &str| config.spawn_env(profile.as_ref(), &env, id, branch, task.as_deref());
+
+            // A detached spawn asking for nothing a pooled agent lacks takes
+            // one from the warm pool instead of starting cold
+            let pool_eligible = detach
+                && continue_id.is_none()
+                && id.is_none()
+                && forked_from.is_none()
+                && remote.is_none()
+                && profile.is_none()
+                && model.is_none()
+                && env.is_empty()
+                && buffer_capacity.is_none()
+                && restart.is_none()
+                && config
+                    .pool
+                    .agents
+                    .keys()
+                    .any(|pooled| rembrandt::warm_pool::canonical_type(pooled) == rembrandt::warm_pool::canonical_type(&agent));
+            let pooled = if pool_eligible {
+                DaemonConnection::connect(&default_socket_path())
+                    .map_err(anyhow::Error::from)
+                    .and_then(|mut daemon| {
+                        Ok(rembrandt::warm_pool::claim(&repo_path, &config, &store, &mut daemon, &agent, &branch)?)
+                    })
+                    .unwrap_or_else(|e| {
+                        eprintln!("Warning: could not take an agent from the pool: {}", e);
+                        None
+                    })
+            } else {
+                None
+            };
 
-            // Determine worktree: continue existing or create new
-            let (agent_id, worktree_path) = if let Some(existing_id) = continue_id {
+            // Determine worktree: continue existing, take a pooled one, or create new
+            let (agent_id, worktree_path, branch_name, mode) = if let Some(existing_id) = continue_id {
                 // Find existing worktree
                 let worktrees = wt_manager.list_worktrees()?;
                 let existing = worktrees.iter().find(|wt| wt.agent_id == existing_id);
@@ -42,7 +151,9 @@ fn main() -> Result<()> {
                         println!("Continuing in existing worktree '{}'...", existing_id);
                         println!("  Worktree: {}", wt.path.display());
                         println!("  Branch:   {}", wt.branch);
-                        (existing_id, wt.path.clone())
+                        let hook_env = agent_env(&existing_id, &wt.branch);
+                        run_hooks(&config, HookPoint::PreSpawn, &repo_path, &repo_path, &existing_id, &hook_env, &store)?;
+                        (existing_id, wt.path.clone(), wt.branch.clone(), IsolationMode::Worktree)
                     }
                     None => {
                         eprintln!("Error: No worktree found for '{}'", existing_id);
@@ -53,29 +164,92 @@ fn main() -> Result<()> {
                         std::process::exit(1);
                     }
                 }
+            } else if let Some(pooled) = &pooled {
+                println!("Taking pooled {} agent '{}'...", agent, pooled.agent_id);
+                println!("  Worktree: {}", pooled.checkout_path.display());
+                println!("  Branch:   {} (at the tip of {})", pooled.branch_name, pooled.base_branch);
+                (
+                    pooled.agent_id.clone(),
+                    pooled.checkout_path.clone(),
+                    pooled.branch_name.clone(),
+                    IsolationMode::Worktree,
+                )
             } else {
                 // Generate a short agent ID: agent-type + short random suffix
-                let suffix: String = (0..4)
-                    .map(|_| format!("{:x}", rand::random::<u8>() % 16))
-                    .collect();
-                let agent_id = format!("{}-{}", agent, suffix);
+                let agent_id = id.unwrap_or_else(|| {
+                    let suffix: String = (0..4)
+                        .map(|_| format!("{:x}", rand::random::<u8>() % 16))
+                        .collect();
+                    format!("{}-{}", agent, suffix)
+                });
 
                 println!("Spawning {} agent as '{}'...", agent, agent_id);
 
-                // Create worktree
-                let worktree = wt_manager.create_worktree(&agent_id, &branch)?;
-                println!("  Worktree: {}", worktree.path.display());
-                println!("  Branch:   {}", worktree.branch);
+                // Create the workspace: a worktree unless the profile says otherwise
+                let mode = profile
+                    .as_ref()
+                    .map_or(IsolationMode::Worktree, |p| p.isolation_mode());
+                let hook_env = agent_env(&agent_id, &format!("rembrandt/{}", agent_id));
+                run_hooks(&config, HookPoint::PreSpawn, &repo_path, &repo_path, &agent_id, &hook_env, &store)?;
+                let workspace =
+                    rembrandt::isolation::prepare_workspace(mode, &repo_path, &agent_id, &branch)?;
+                if let Err(e) = run_hooks(
+                    &config,
+                    HookPoint::PostWorktree,
+                    &repo_path,
+                    &workspace.checkout_path,
+                    &agent_id,
+                    &hook_env,
+                    &store,
+                ) {
+                    rembrandt::isolation::discard_workspace(&workspace);
+                    return Err(e);
+                }
+                match mode {
+                    IsolationMode::Worktree => {
+                        println!("  Worktree: {}", workspace.checkout_path.display())
+                    }
+                    IsolationMode::Branch => {
+                        println!("  Checkout: {} (shared)", workspace.checkout_path.display())
+                    }
+                }
+                println!("  Branch:   {}", workspace.branch_name);
+                if !workspace.propagated.is_empty() {
+                    let paths: Vec<String> =
+                        workspace.propagated.iter().map(|p| p.display().to_string()).collect();
+                    let verb = match config.worktree.copy_mode {
+                        rembrandt::worktree::CopyMode::Copy => "Copied",
+                        rembrandt::worktree::CopyMode::Symlink => "Linked",
+                    };
+                    println!("  {}:   {}", verb, paths.join(", "));
+                }
 
-                (agent_id, worktree.path)
+                (agent_id, workspace.checkout_path, workspace.branch_name, mode)
             };
 
             if let Some(task_id) = &task {
                 println!("  Task:     {}", task_id);
+                let beads = config.beads.client(&repo_path);
+                if let Err(e) = beads.claim(task_id) {
+                    eprintln!("Warning: could not claim task {}: {}", task_id, e);
+                }
+            }
+            let model = model.or_else(|| profile.as_ref().and_then(|p| p.model.clone()));
+            if let Some(model) = &model {
+                println!("  Model:    {}", model);
             }
 
-            // Get initial prompt
-            let initial_prompt: Option<String> = if let Some(p) = prompt {
+            // Get initial prompt: the profile's template, or the prompt as given
+            let template = profile.as_ref().and_then(|p| p.template.as_deref());
+            let initial_prompt: Option<String> = if let Some(name) = template {
+                let vars = rembrandt::templates::TemplateVars {
+                    task: task.as_deref(),
+                    prompt: prompt.as_deref(),
+                    agent: &agent_id,
+                    branch: &branch_name,
+                };
+                Some(rembrandt::templates::render_file(&repo_path, name, &vars)?)
+            } else if let Some(p) = prompt {
                 Some(p)
             } else if no_prompt {
                 None
@@ -95,24 +269,150 @@ fn main() -> Result<()> {
 
             // Resolve agent type to command
             let agent_type = AgentType::from_str(&agent);
-            let command = agent_type.command();
-            let args = agent_type.default_args();
+            let mut command = agent_type.command().to_string();
+            let mut args: Vec<String> = agent_type.default_args().iter().map(|a| a.to_string()).collect();
+            if let Some(model) = &model {
+                args.extend(config.model_args(&agent_type, model)?);
+            }
+            let env = agent_env(&agent_id, &branch_name);
 
             println!("  Command:  {}", command);
+
+            let buffer_capacity = buffer_capacity.or(config.buffer_capacity);
+            if restart.is_some() && !detach {
+                anyhow::bail!("--restart needs --detach: only the daemon can respawn an agent");
+            }
+            let restart = restart.unwrap_or(config.restart);
+            if let Some(name) = &remote {
+                let host = config.remote(name)?;
+                let branch = format!("rembrandt/{}", agent_id);
+                let remote_path = rembrandt::runtime::ssh::prepare_remote_worktree(
+                    host, &repo_path, &branch, &agent_id,
+                )?;
+                println!("  Remote:   {}:{}", host.host, remote_path);
+                (command, args) =
+                    rembrandt::runtime::ssh::remote_agent_command(host, &agent_id, &command, &args, &env);
+            }
             println!();
 
+            // Tracked in state.db once the agent is running, so `list` shows
+            // its model and a restarted agent is reminded of its task
+            let now = chrono::Utc::now();
+            let record = SessionRecord {
+                agent_id: agent_id.clone(),
+                runtime_kind: if detach { "daemon" } else { "pty" }.to_string(),
+                runtime_session_id: None,
+                isolation_mode: mode,
+                branch_name: branch_name.clone(),
+                checkout_path: worktree_path.clone(),
+                task_id: task.clone(),
+                status: rembrandt::state::SessionStatus::Active,
+                model: model.clone(),
+                created_at: now,
+                updated_at: now,
+                prompt: initial_prompt.clone(),
+                agent_type: Some(agent_type.to_string()),
+                updated_seq: 0,
+            };
+            let audit_params = serde_json::json!({
+                "agent": agent,
+                "model": model,
+                "task": task,
+                "branch": branch_name,
+                "isolation": mode.to_string(),
+                "detach": detach,
+                "pooled": pooled.is_some(),
+                "remote": remote,
+                "forked_from": forked_from,
+            });
+            // Recorded with the session so `timeline` shows the lineage
+            let record_lineage = |store: &StateStore| -> Result<()> {
+                if let Some(lineage) = &forked_from {
+                    store.record_timeline(&agent_id, rembrandt::timeline::TimelineKind::Spawned, Some(lineage))?;
+                }
+                Ok(())
+            };
+
             // Spawn the agent in a PTY with current terminal size
             let (cols, rows) = crossterm::terminal::size().unwrap_or((80, 24));
+
+            if detach {
+                let mut daemon = DaemonConnection::connect(&default_socket_path()).map_err(|_| {
+                    anyhow::anyhow!("--detach needs a running daemon (start one with `rembrandt daemon`)")
+                })?;
+                let session_id = match &pooled {
+                    Some(pooled) => {
+                        daemon.resize(&pooled.session_id, rows, cols)?;
+                        pooled.session_id.clone()
+                    }
+                    None => daemon.spawn(
+                        agent_id.clone(),
+                        command,
+                        args,
+                        env,
+                        worktree_path.clone(),
+                        Some(rows),
+                        Some(cols),
+                        buffer_capacity,
+                        restart,
+                    )?,
+                };
+                store.upsert_session(&record)?;
+                record_lineage(&store)?;
+                audit(&repo_path, "spawn", Some(&agent_id), audit_params);
+                if let Some(prompt_text) = initial_prompt {
+                    // A pooled agent is already up and waiting
+                    if pooled.is_none() {
+                        wait_for_first_screen(|| daemon.output_end(&session_id))?;
+                    }
+                    daemon.write(&session_id, format!("{}\n", prompt_text).into_bytes())?;
+                }
+                println!("Agent running in daemon with session ID: {}", session_id);
+                if pool_eligible {
+                    match rembrandt::warm_pool::fill(&repo_path, &config, &store, &mut daemon) {
+                        Ok(started) if !started.is_empty() => {
+                            println!("Started {} idle agent(s) to refill the pool", started.len())
+                        }
+                        Ok(_) => {}
+                        Err(e) => eprintln!("Warning: could not refill the pool: {}", e),
+                    }
+                }
+                if let Some(backend) = rembrandt::terminal::backend(config.terminal_backend) {
+                    let repo = main_repo_root(&repo_path).unwrap_or_else(|| repo_path.clone());
+                    let pane = rembrandt::terminal::AgentPane::attach(
+                        &std::env::current_exe()?,
+                        &repo,
+                        &agent_id,
+                        false,
+                    );
+                    match backend.open(&pane) {
+                        Ok(()) => println!("Opened {} in {}", agent_id, backend.name()),
+                        Err(e) => eprintln!("Warning: could not open {} in {}: {}", agent_id, backend.name(), e),
+                    }
+                }
+                println!(
+                    "Stop it with: rembrandt --repo {} stop {}",
+                    repo_path.display(),
+                    agent_id
+                );
+                return Ok(());
+            }
+
+            let args: Vec<&str> = args.iter().map(String::as_str).collect();
             let mut session = PtySession::spawn(
                 agent_id.clone(),
-                command,
+                &command,
                 &args,
+                &env,
                 &worktree_path,
-                10 * 1024, // 10KB output buffer
+                buffer_capacity.unwrap_or(rembrandt::daemon::manager::DEFAULT_BUFFER_CAPACITY),
                 Some(rows),
                 Some(cols),
             )?;
 
+            store.upsert_session(&record)?;
+            record_lineage(&store)?;
+            audit(&repo_path, "spawn", Some(&agent_id), audit_params);
             println!("Agent spawned with session ID: {}", session.id);
             println!("Press Ctrl+D to detach (agent keeps running in worktree)");
             println!("{}", "─".repeat(60));
@@ -131,69 +431,54 @@ fn main() -> Result<()> {
             };
             use std::io::Write;
 
-            let mut reader = session.try_clone_reader()?;
-            let mut buf = [0u8; 1024];
-
             // Enable raw mode for keyboard input
             enable_raw_mode()?;
 
             let result: Result<()> = (|| {
                 loop {
                     // Poll for keyboard events (non-blocking)
-                    if event::poll(std::time::Duration::from_millis(10))? {
-                        if let Event::Key(key) = event::read()? {
-                            // Ctrl+D to detach
-                            if key.code == KeyCode::Char('d')
-                                && key.modifiers.contains(KeyModifiers::CONTROL)
-                            {
-                                break;
-                            }
+                    if event::poll(std::time::Duration::from_millis(10))?
+                        && let Event::Key(key) = event::read()?
+                    {
+                        // Ctrl+D to detach
+                        if key.code == KeyCode::Char('d')
+                            && key.modifiers.contains(KeyModifiers::CONTROL)
+                        {
+                            break;
+                        }
 
-                            // Forward key to PTY
-                            let bytes: Vec<u8> = match key.code {
-                                KeyCode::Char(c) => {
-                                    if key.modifiers.contains(KeyModifiers::CONTROL) {
-                                        // Convert to control character
-                                        vec![(c as u8) & 0x1f]
-                                    } else {
-                                        c.to_string().into_bytes()
-                                    }
+                        // Forward key to PTY
+                        let bytes: Vec<u8> = match key.code {
+                            KeyCode::Char(c) => {
+                                if key.modifiers.contains(KeyModifiers::CONTROL) {
+                                    // Convert to control character
+                                    vec![(c as u8) & 0x1f]
+                                } else {
+                                    c.to_string().into_bytes()
                                 }
-                                KeyCode::Enter => vec![b'\r'],
-                                KeyCode::Backspace => vec![127],
-                                KeyCode::Tab => vec![b'\t'],
-                                KeyCode::Esc => vec![27],
-                                KeyCode::Up => vec![27, b'[', b'A'],
-                                KeyCode::Down => vec![27, b'[', b'B'],
-                                KeyCode::Right => vec![27, b'[', b'C'],
-                                KeyCode::Left => vec![27, b'[', b'D'],
-                                _ => vec![],
-                            };
-
-                            if !bytes.is_empty() {
-                                session.write(&bytes)?;
                             }
+                            KeyCode::Enter => vec![b'\r'],
+                            KeyCode::Backspace => vec![127],
+                            KeyCode::Tab => vec![b'\t'],
+                            KeyCode::Esc => vec![27],
+                            KeyCode::Up => vec![27, b'[', b'A'],
+                            KeyCode::Down => vec![27, b'[', b'B'],
+                            KeyCode::Right => vec![27, b'[', b'C'],
+                            KeyCode::Left => vec![27, b'[', b'D'],
+                            _ => vec![],
+                        };
+
+                        if !bytes.is_empty() {
+                            session.write(&bytes)?;
                         }
                     }
 
-                    // Read PTY output (non-blocking via WouldBlock)
-                    match reader.read(&mut buf) {
-                        Ok(0) => {
-                            // EOF - process exited
-                            session.poll();
-                            break;
-                        }
-                        Ok(n) => {
-                            // Write to stdout
-                            std::io::stdout().write_all(&buf[..n])?;
-                            std::io::stdout().flush()?;
-                        }
-                        Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
-                            // No data available, continue
-                        }
-                        Err(e) => {
-                            return Err(e.into());
-                        }
+                    // Forward output the session's reader has captured
+                    session.poll();
+                    let output = session.drain_available();
+                    if !output.is_empty() {
+                        std::io::stdout().write_all(&output)?;
+                        std::io::stdout().flush()?;
                     }
 
                     // Check if process exited
@@ -216,19 +501,41 @@ fn main() -> Result<()> {
                 println!("Resume with: rembrandt spawn {} -C {}", agent, agent_id);
             } else {
                 println!("Agent exited: {:?}", session.status);
+                let status = match session.status {
+                    SessionStatus::Exited(0) => rembrandt::state::SessionStatus::Completed,
+                    _ => rembrandt::state::SessionStatus::Failed,
+                };
+                // Unless `rembrandt stop` got to it first
+                let updated =
+                    store.transition_status(&agent_id, |record| record.status.can_transition_to(status).then_some(status))?;
+                if updated.is_some() && status == rembrandt::state::SessionStatus::Failed {
+                    run_hooks(&config, HookPoint::OnFailure, &repo_path, &worktree_path, &agent_id, &env, &store)?;
+                }
             }
         }
 
         Commands::Compete {
+            action,
             prompt,
             agents,
             evaluator,
             model,
             timeout,
             branch,
+            early_stop_score,
+            early_stop_grace,
+            benchmark,
+            benchmark_weight,
+            benchmark_higher_is_better,
+            retries,
         } => {
-            use rembrandt::agent::AgentType;
-            use rembrandt::competition::{EvaluatorStrategy, MetricWeights};
+            use rembrandt::competition::{CompetitorSpec, EvaluatorStrategy};
+
+            if let Some(CompeteAction::History { limit }) = action {
+                return print_competition_history(&repo_path, limit);
+            }
+            // Required by clap unless a subcommand is given
+            let prompt = prompt.unwrap_or_default();
 
             println!("Starting competition mode...");
             println!("  Prompt: {}", prompt);
@@ -236,34 +543,237 @@ fn main() -> Result<()> {
             println!("  Evaluator: {}", evaluator);
             println!("  Timeout: {} minutes", timeout);
             println!("  Base branch: {}", branch);
+            if let Some(score) = early_stop_score {
+                println!(
+                    "  Early stop: score >= {:.2}, {} minute(s) grace",
+                    score, early_stop_grace
+                );
+            }
+            if let Some(command) = &benchmark {
+                println!(
+                    "  Benchmark: {} ({} is better, weight {:.2})",
+                    command,
+                    if benchmark_higher_is_better { "higher" } else { "lower" },
+                    benchmark_weight
+                );
+            }
+            if retries > 0 {
+                println!("  Retry rounds: {}", retries);
+            }
             println!();
 
-            // Parse agent types
-            let agent_types: Vec<AgentType> = agents
-                .iter()
-                .map(|s| match s.as_str() {
-                    "claude-code" => AgentType::ClaudeCode,
-                    "opencode" => AgentType::OpenCode,
-                    "ampcode" => AgentType::AmpCode,
-                    "codex" => AgentType::Codex,
-                    "aider" => AgentType::Aider,
-                    other => AgentType::Custom(other.to_string()),
-                })
-                .collect();
+            // Parse competitors
+            let competitors = CompetitorSpec::parse_all(&agents)?;
 
             // Parse evaluator strategy
-            let evaluator_strategy = match evaluator.as_str() {
-                "model" => EvaluatorStrategy::Model { model_name: model },
-                "human" => EvaluatorStrategy::Human,
-                _ => EvaluatorStrategy::Metrics(MetricWeights::default()),
+            let mut evaluator_strategy = EvaluatorStrategy::from_name(&evaluator, &model);
+            let benchmark = benchmark.map(|command| rembrandt::competition::Benchmark {
+                command,
+                lower_is_better: !benchmark_higher_is_better,
+                weight: benchmark_weight,
+            });
+            if let Some(benchmark) = &benchmark {
+                evaluator_strategy.set_benchmark_weight(benchmark.weight);
+            }
+
+            for competitor in competitors.iter().filter(|c| !c.variant.is_default()) {
+                println!("  {} ({})", competitor.agent_type, competitor.variant);
+            }
+
+            // Competitors run in the daemon, so they outlive a lost terminal
+            let mut daemon = DaemonConnection::connect(&default_socket_path()).map_err(|_| {
+                anyhow::anyhow!("compete needs a running daemon (start one with `rembrandt daemon`)")
+            })?;
+            let config = rembrandt::config::AppConfig::load(&repo_path)?;
+            let mut manager = config.competition_manager(&repo_path, &branch)?;
+            let mut registry = rembrandt::agent::AgentRegistry::new();
+            let runtime = tokio::runtime::Runtime::new()?;
+            let id = runtime.block_on(manager.start_competition(
+                prompt.clone(),
+                competitors,
+                evaluator_strategy,
+                timeout,
+                &mut registry,
+            ))?;
+            manager.set_early_stop(
+                &id,
+                early_stop_score.map(|min_score| rembrandt::competition::EarlyStopPolicy {
+                    min_score,
+                    grace_minutes: early_stop_grace,
+                }),
+            )?;
+            manager.set_benchmark(&id, benchmark)?;
+            manager.set_retry_rounds(&id, retries)?;
+
+            let competition = manager
+                .get_competition(&id)
+                .ok_or_else(|| anyhow::anyhow!("competition {} vanished", id))?
+                .clone();
+            println!("Competition {} started", id);
+            let mut sessions = BTreeMap::new();
+            for competitor in &competition.competitors {
+                let session_id = spawn_competitor(&config, &mut daemon, competitor, &prompt)?;
+                println!("  {} in {}", competitor.agent_id, competitor.worktree_path.display());
+                sessions.insert(competitor.agent_id.clone(), session_id);
+            }
+
+            let mut last_status = None;
+            let status = loop {
+                std::thread::sleep(COMPETE_POLL_INTERVAL);
+                registry.sync_competitors(&daemon.list(None)?);
+                let status = runtime.block_on(manager.update_competition(&id, &mut registry))?;
+                if last_status.as_ref() != Some(&status) {
+                    println!("{}", competition_status_line(&status));
+                    last_status = Some(status.clone());
+                }
+                let competition = manager
+                    .get_competition(&id)
+                    .ok_or_else(|| anyhow::anyhow!("competition {} vanished", id))?
+                    .clone();
+
+                // Competitors cut off by early stop
+                for agent_id in &competition.stopped_early {
+                    if registry.get_session(agent_id).is_some_and(|s| s.status == rembrandt::agent::AgentStatus::Active) {
+                        let _ = registry.update_status(agent_id, rembrandt::agent::AgentStatus::Stopped);
+                        if let Some(session_id) = sessions.get(agent_id) {
+                            let _ = daemon.kill(session_id);
+                        }
+                        println!("  {} stopped early", agent_id);
+                    }
+                }
+                // A retry round started: re-run each failed agent with its failures
+                for follow_up in manager.take_follow_ups(&id) {
+                    if let Some(competitor) = competition.competitors.iter().find(|c| c.agent_id == follow_up.agent_id) {
+                        let session_id = spawn_competitor(&config, &mut daemon, competitor, &follow_up.prompt)?;
+                        println!("  {} retrying", competitor.agent_id);
+                        sessions.insert(competitor.agent_id.clone(), session_id);
+                    }
+                }
+
+                if !matches!(
+                    status,
+                    rembrandt::competition::CompetitionStatus::Spawning
+                        | rembrandt::competition::CompetitionStatus::Running { .. }
+                        | rembrandt::competition::CompetitionStatus::Evaluating
+                ) {
+                    break status;
+                }
+            };
+
+            let competition = manager
+                .get_competition(&id)
+                .ok_or_else(|| anyhow::anyhow!("competition {} vanished", id))?;
+            report_competition(&mut daemon, competition);
+            if let Some(result) = &competition.evaluation_result {
+                println!();
+                for ranking in &result.rankings {
+                    println!("  {}. {} ({:.2}) {}", ranking.rank, ranking.agent_id, ranking.score, ranking.reasoning);
+                }
+            }
+            let rembrandt::competition::CompetitionStatus::Merging = status else {
+                return Ok(());
+            };
+            // A `human` stage only proposes: the person picks by number
+            let mut winner = competition.winner.clone().unwrap_or_default();
+            let human = competition.evaluator_strategy.involves_human();
+            let ranked: Vec<String> = competition
+                .evaluation_result
+                .iter()
+                .flat_map(|result| result.rankings.iter().map(|r| r.agent_id.clone()))
+                .collect();
+            if human {
+                print!("Winner to merge into {} (1-{}, empty to not merge): ", branch, ranked.len());
+            } else {
+                print!("Merge {} into {}? [y/N] ", winner, branch);
+            }
+            std::io::Write::flush(&mut std::io::stdout())?;
+            let mut input = String::new();
+            std::io::stdin().read_line(&mut input)?;
+            let input = input.trim();
+            let merge = if human {
+                match input.parse::<usize>().ok().and_then(|n| ranked.get(n.wrapping_sub(1))) {
+                    Some(picked) => {
+                        if *picked != winner {
+                            manager.select_winner(&id, picked, "picked at the `rembrandt compete` prompt")?;
+                            winner = picked.clone();
+                        }
+                        true
+                    }
+                    None if input.is_empty() => false,
+                    None => anyhow::bail!("'{}' is not one of 1-{}", input, ranked.len()),
+                }
+            } else {
+                matches!(input, "y" | "Y" | "yes")
             };
+            if merge {
+                let tip = manager.merge_winner(&id)?;
+                println!("Merged {} into {} at {}", winner, branch, &tip[..7]);
+                if let Some(competition) = manager.get_competition(&id) {
+                    report_competition(&mut daemon, competition);
+                }
+            } else {
+                println!("Not merging; the competitors' worktrees are left in place.");
+            }
+        }
+
+        Commands::Plan { description, tasks, manifest_only, spawn, branch, yes } => {
+            use rembrandt::plan::{self, Plan};
+
+            let config = rembrandt::config::AppConfig::load(&repo_path)?;
+            let command = plan::planner_command(&config)?;
+            println!("Planning with `{}`...", command);
+            let mut plan = Plan::new(&description, plan::propose(&repo_path, &command, &description, tasks)?);
+
+            if !manifest_only {
+                let beads = config.beads.client(&repo_path);
+                for task in &mut plan.tasks {
+                    task.beads_id = beads.create(&task.title, &task.description, None)?;
+                }
+            }
+            let manifest = plan.write_manifest(&repo_path)?;
 
-            println!("Competition would start with:");
-            println!("  {} agents", agent_types.len());
-            println!("  Strategy: {:?}", evaluator_strategy);
+            for (i, task) in plan.tasks.iter().enumerate() {
+                println!();
+                println!("{}. {} [{}]", i + 1, task.title, task.beads_id.as_deref().unwrap_or("-"));
+                if !task.description.is_empty() {
+                    println!("   {}", task.description);
+                }
+                if !task.files.is_empty() {
+                    println!("   files: {}", task.files.join(", "));
+                }
+            }
             println!();
-            println!("(Competition manager not yet wired to agent spawning)");
-            // TODO: Actually start competition via CompetitionManager
+            for (a, b, path) in plan.overlaps() {
+                println!("warning: tasks {} and {} both touch {}", a + 1, b + 1, path);
+            }
+            println!("Wrote {}", manifest.display());
+
+            if let Some(agent) = spawn {
+                if !yes {
+                    print!("Spawn {} {} agent(s) on '{}'? [y/N] ", plan.tasks.len(), agent, branch);
+                    std::io::Write::flush(&mut std::io::stdout())?;
+                    let mut input = String::new();
+                    std::io::stdin().read_line(&mut input)?;
+                    if !matches!(input.trim(), "y" | "Y" | "yes") {
+                        println!("Not spawning.");
+                        return Ok(());
+                    }
+                }
+                let exe = std::env::current_exe()?;
+                for task in &plan.tasks {
+                    let mut spawn = std::process::Command::new(&exe);
+                    spawn
+                        .arg("--repo")
+                        .arg(&repo_path)
+                        .args(["spawn", &agent, "--detach", "--branch", &branch, "--prompt", &task.prompt()]);
+                    if let Some(id) = &task.beads_id {
+                        spawn.args(["--task", id]);
+                    }
+                    if !spawn.status()?.success() {
+                        anyhow::bail!("spawning an agent for '{}' failed", task.title);
+                    }
+                }
+            }
         }
 
         Commands::CompeteStatus { id } => {
@@ -277,44 +787,51 @@ fn main() -> Result<()> {
             // TODO: Cancel via CompetitionManager
         }
 
-        Commands::List { verbose } => {
+        Commands::List { verbose, status, task, agent_type, since, active, limit, offset, json } => {
+            let config = rembrandt::config::AppConfig::load(&repo_path)?;
+            let mut query = if active { SessionQuery::active() } else { SessionQuery::default() };
+            query.statuses.extend(status);
+            query.task_id = task;
+            query.agent_type = agent_type.map(|agent| AgentType::from_str(&agent).to_string());
+            query.since = since.as_deref().map(rembrandt::audit::parse_since).transpose()?;
+            query.limit = limit;
+            query.offset = offset;
+            let filtered = query.task_id.is_some()
+                || !query.statuses.is_empty()
+                || query.agent_type.is_some()
+                || query.since.is_some();
+
+            if json {
+                let store = StateStore::open(&repo_path)?;
+                for session in store.query_sessions(&query)? {
+                    println!("{}", serde_json::to_string(&session_json(&session))?);
+                }
+                return Ok(());
+            }
+
             if use_v2 {
                 let orch = rembrandt::orchestrator::Orchestrator::new(
                     &repo_path,
                     rembrandt::runtime::PiRuntime::new(),
                 )?;
-                let sessions = orch.list_agents()?;
                 println!("V2 sessions (state.db):");
-                if sessions.is_empty() {
-                    println!("  (none)");
-                } else {
-                    for session in &sessions {
-                        println!(
-                            "  {} [{}] {} {}",
-                            session.agent_id,
-                            session.status,
-                            session.isolation_mode,
-                            session.branch_name
-                        );
-                    }
-                }
+                print_v2_sessions(&*orch.state().get()?, &repo_path, &query, verbose.then_some(&config))?;
                 if !verbose {
                     return Ok(());
                 }
                 println!();
-            } else if let Ok(store) = rembrandt::state::StateStore::open(&repo_path) {
-                let sessions = store.list_sessions()?;
+            } else if let Ok(store) = StateStore::open(&repo_path)
+                && (filtered || store.count_sessions(&query)? > 0)
+            {
+                println!("V2 tracked sessions (state.db):");
+                print_v2_sessions(&store, &repo_path, &query, verbose.then_some(&config))?;
+                println!();
+            }
+
+            if let Ok(mut daemon) = DaemonConnection::connect(&default_socket_path()) {
+                let sessions = daemon.list(None)?;
                 if !sessions.is_empty() {
-                    println!("V2 tracked sessions (state.db):");
-                    for session in &sessions {
-                        println!(
-                            "  {} [{}] {} {}",
-                            session.agent_id,
-                            session.status,
-                            session.isolation_mode,
-                            session.branch_name
-                        );
-                    }
+                    print_daemon_sessions(&sessions, &repo_path, verbose);
                     println!();
                 }
             }
@@ -333,11 +850,11 @@ fn main() -> Result<()> {
 
             if verbose {
                 println!("\nIntegrations:");
-                let beads = rembrandt::integration::beads::BeadsIntegration::new();
+                let beads = rembrandt::config::AppConfig::load(&repo_path)?.beads.client(&repo_path);
                 let agent_mail = rembrandt::integration::agent_mail::AgentMailIntegration::new();
                 println!(
-                    "  beads (br): {}",
-                    if beads.is_available() { "available" } else { "not found" }
+                    "  beads: {}",
+                    rembrandt::integration::beads::describe(&beads)
                 );
                 println!(
                     "  agent-mail: {}",
@@ -346,9 +863,64 @@ fn main() -> Result<()> {
             }
         }
 
-        Commands::Attach { agent } => {
-            println!("Attaching to agent {}...", agent);
-            // TODO: Attach to agent PTY
+        Commands::Attach { agent, read_only } => {
+            use rembrandt::tui::attach::{attach_via_daemon, AttachResult};
+
+            let socket = default_socket_path();
+            let repo = main_repo_root(&repo_path).unwrap_or_else(|| repo_path.clone());
+            let mut daemon = DaemonConnection::connect(&socket)?;
+            // Prefer the agent's running session, else its latest one
+            let mut sessions = daemon.list_by_agent(&agent, Some(repo))?;
+            sessions.sort_by_key(|s| (s.status == SessionStatus::Running, s.created_at));
+            let session = sessions
+                .pop()
+                .ok_or_else(|| rembrandt::RembrandtError::SessionNotFound(agent.clone()))?;
+
+            let mode = if read_only { " read-only" } else { "" };
+            println!("Attached to {}{} (Ctrl+D to detach)", agent, mode);
+            match attach_via_daemon(&socket, &session.id, read_only)? {
+                AttachResult::Detached => println!("\r\nDetached from {}", agent),
+                AttachResult::SessionEnded => println!("\r\nSession {} ended", agent),
+                AttachResult::Error(e) => anyhow::bail!(e),
+            }
+        }
+
+        Commands::Layout { session, read_only, replace } => {
+            use rembrandt::config::TerminalBackendKind;
+            use rembrandt::terminal::AgentPane;
+
+            let config = rembrandt::config::AppConfig::load(&repo_path)?;
+            let kind = match config.terminal_backend {
+                TerminalBackendKind::None => TerminalBackendKind::Tmux,
+                kind => kind,
+            };
+            let backend = rembrandt::terminal::backend(kind).ok_or_else(|| {
+                anyhow::anyhow!("terminal backend {:?} has no layout support", kind)
+            })?;
+            let repo = main_repo_root(&repo_path).unwrap_or_else(|| repo_path.clone());
+            let mut daemon = DaemonConnection::connect(&default_socket_path()).map_err(|_| {
+                anyhow::anyhow!("layout attaches through the daemon; start one with `rembrandt daemon`")
+            })?;
+            let mut agents: Vec<String> = daemon
+                .list(Some(repo.clone()))?
+                .into_iter()
+                .filter(|s| s.status == SessionStatus::Running)
+                .map(|s| s.agent_id)
+                .collect();
+            agents.sort();
+            agents.dedup();
+            if agents.is_empty() {
+                println!("No running agents");
+                return Ok(());
+            }
+
+            let exe = std::env::current_exe()?;
+            let panes: Vec<AgentPane> = agents
+                .iter()
+                .map(|agent| AgentPane::attach(&exe, &repo, agent, read_only))
+                .collect();
+            println!("Laying out {} agent(s) in {} session {}", panes.len(), backend.name(), session);
+            backend.layout(&session, &panes, replace)?;
         }
 
         Commands::Broadcast { message, to } => {
@@ -360,89 +932,1307 @@ fn main() -> Result<()> {
             // TODO: Send via Agent Mail
         }
 
-        Commands::Merge { agent, no_check } => {
-            println!("Merging work from agent {}...", agent);
+        Commands::Merge { agent, base, no_check, no_validate, spawn_resolver } => {
+            use rembrandt::integration::Integration;
+            use rembrandt::merge_queue::MergeStatus;
+
+            let config = rembrandt::config::AppConfig::load(&repo_path)?;
+            let store = StateStore::open(&repo_path)?;
+            let session = store
+                .get_session(&agent)?
+                .ok_or_else(|| rembrandt::RembrandtError::SessionNotFound(agent.clone()))?;
+            println!("Merging work from agent {} into {}...", agent, base);
+
             if !no_check {
-                println!("Running pre-merge checks...");
+                let porque = rembrandt::integration::porque::PorqueIntegration::new();
+                if porque.is_available() {
+                    println!("Running pre-merge checks...");
+                    let files: Vec<PathBuf> = WorktreeManager::new(&repo_path)?
+                        .branch_diff(&session.branch_name, &base)?
+                        .into_iter()
+                        .map(|file| session.checkout_path.join(file.path))
+                        .collect();
+                    let files: Vec<&Path> = files.iter().map(PathBuf::as_path).collect();
+                    let violations = porque.check(&files)?;
+                    for violation in &violations {
+                        println!("  ✗ {} {}: {}", violation.decision_id, violation.file, violation.reason);
+                    }
+                    if !violations.is_empty() {
+                        anyhow::bail!("{} decision violation(s); fix them or pass --no-check", violations.len());
+                    }
+                } else {
+                    println!("pq not found; skipping the decision check");
+                }
             }
-            // TODO: Merge worktree branch
-        }
 
-        Commands::Stop { agent } => {
-            println!("Stopping agent {}...", agent);
-            // TODO: Stop agent process
-        }
+            let runtime = tokio::runtime::Runtime::new()?;
+            let entry = runtime.block_on(rembrandt::merge_queue::merge_now(
+                &repo_path,
+                &base,
+                &store,
+                &agent,
+                !no_validate,
+                &config.solution_validator(&base),
+                &config.hooks,
+            ))?;
+            println!("  {} [{}] {}", entry.agent_id, entry.status, entry.detail.as_deref().unwrap_or(""));
+            audit(
+                &repo_path,
+                "merge",
+                Some(&agent),
+                serde_json::json!({ "base": base, "check": !no_check, "validate": !no_validate, "status": entry.status.to_string() }),
+            );
+            if entry.status == MergeStatus::Conflicted {
+                let worktrees = config.worktree.manager(&repo_path)?;
+                if let Some(resolution) = rembrandt::resolution::prepare(&store, &worktrees, &entry, &base)? {
+                    println!("Rebase left stopped in {}", resolution.checkout_path.display());
+                    println!("  Branch: {}", resolution.branch_name);
+                    println!("  Conflicted files:");
+                    for file in &resolution.conflicted {
+                        println!("    {}", file);
+                    }
+                    audit(
+                        &repo_path,
+                        "merge_resolution",
+                        Some(&agent),
+                        serde_json::json!({ "merge_id": entry.id, "resolver": resolution.resolver_id, "conflicted": resolution.conflicted }),
+                    );
+                    if spawn_resolver {
+                        let agent_type = session.agent_type.as_deref().unwrap_or("claude-code");
+                        let mut spawn = std::process::Command::new(std::env::current_exe()?);
+                        spawn.arg("--repo").arg(&repo_path).args([
+                            "spawn",
+                            agent_type,
+                            "--continue",
+                            &resolution.resolver_id,
+                            "--detach",
+                            "--prompt",
+                            &rembrandt::resolution::prompt(&resolution),
+                        ]);
+                        if let Some(task_id) = &session.task_id {
+                            spawn.args(["--task", task_id]);
+                        }
+                        if !spawn.status()?.success() {
+                            anyhow::bail!("spawning a resolver for {} failed", agent);
+                        }
+                    } else {
+                        println!(
+                            "Resolve them there and run `git rebase --continue` until it finishes, or rerun with --spawn-resolver"
+                        );
+                    }
+                    println!("Then land the result with `rembrandt merge {}`", resolution.resolver_id);
+                }
+            }
+            if entry.status != MergeStatus::Merged {
+                anyhow::bail!("{} was not merged into {} ({})", agent, base, entry.status);
+            }
+            if let Some(resolution) =
+                rembrandt::resolution::finish(&store, &agent, entry.detail.as_deref().unwrap_or("merged"))?
+            {
+                println!("  Resolves the conflicted merge of {}", resolution.agent_id);
+            }
 
-        Commands::Cleanup { all } => {
-            let manager = WorktreeManager::new(&repo_path)?;
-            let worktrees = manager.list_worktrees()?;
+            // A verified merge finishes the agent's task
+            let beads = rembrandt::config::AppConfig::load(&repo_path)?.beads.client(&repo_path);
+            if let Some(task_id) = &session.task_id
+                && beads.is_available()
+            {
+                let commit = git2::Repository::open(&repo_path)?.revparse_single(&base)?.id();
+                beads.complete(
+                    task_id,
+                    &format!("Merged {} into {} at {}", session.branch_name, base, commit),
+                )?;
+                println!("  Task {} closed (merge commit {})", task_id, commit);
+                audit(&repo_path, "task", Some(&agent), serde_json::json!({ "task": task_id, "status": "closed", "commit": commit.to_string() }));
+            }
+        }
 
-            if worktrees.is_empty() {
-                println!("No worktrees to clean up");
+        Commands::Conflicts { base } => {
+            let store = StateStore::open(&repo_path)?;
+            let branches = rembrandt::conflicts::active_agent_branches(&repo_path, &store)?;
+            let report = rembrandt::conflicts::check(&repo_path, &base, &branches)?;
+            if report.agents.is_empty() {
+                println!("No active agent branches");
                 return Ok(());
             }
 
-            if all {
-                println!("Cleaning up all {} worktrees...", worktrees.len());
-                for wt in &worktrees {
-                    print!("  Removing {}... ", wt.agent_id);
-                    match manager.remove_worktree(&wt.agent_id) {
-                        Ok(_) => println!("done"),
-                        Err(e) => println!("failed: {}", e),
+            println!("Checked {} agent branch(es) against {} and each other", report.agents.len(), base);
+            let collisions: Vec<_> = report.with_base.iter().chain(&report.between_agents).collect();
+            if collisions.is_empty() {
+                println!("No overlapping changes");
+            }
+            for collision in collisions {
+                let pair = format!("{} / {}", collision.left, collision.right);
+                if collision.conflicts.is_empty() {
+                    println!("  ~ {}: both change {}", pair, collision.overlapping.join(", "));
+                } else {
+                    println!("  ✗ {}: conflict in {}", pair, collision.conflicts.join(", "));
+                    if !collision.overlapping.is_empty() {
+                        println!("      also both change {}", collision.overlapping.join(", "));
                     }
                 }
-            } else {
-                // TODO: Only remove worktrees with Completed/Stopped status
-                // For now, list what would be cleaned (requires agent registry)
-                println!("Worktrees that would be cleaned (once registry tracks status):");
-                for wt in &worktrees {
-                    println!("  {} (status unknown - use --all to force)", wt.agent_id);
-                }
+            }
+            if report.has_conflicts() {
+                std::process::exit(1);
             }
         }
 
-        Commands::Gc { dry_run } => {
-            let manager = WorktreeManager::new(&repo_path)?;
-            let worktrees = manager.list_worktrees()?;
+        Commands::Sync { agent, base, merge, no_validate } => {
+            use rembrandt::sync::{SyncMode, SyncOutcome};
 
-            if worktrees.is_empty() {
-                println!("No worktrees found");
+            let config = rembrandt::config::AppConfig::load(&repo_path)?;
+            let store = StateStore::open(&repo_path)?;
+            let mode = if merge { SyncMode::Merge } else { SyncMode::Rebase };
+            let runtime = tokio::runtime::Runtime::new()?;
+            let report = runtime.block_on(rembrandt::sync::sync(
+                &repo_path,
+                &store,
+                &agent,
+                &base,
+                mode,
+                !no_validate,
+                &config.validation_commands,
+                &config.lint,
+            ))?;
+
+            audit(
+                &repo_path,
+                "sync",
+                Some(&agent),
+                serde_json::json!({ "base": base, "merge": merge, "validate": !no_validate, "ok": report.is_ok() }),
+            );
+            if report.fetched {
+                println!("Fetched {}", report.onto);
+            }
+            match &report.outcome {
+                SyncOutcome::UpToDate => println!("{} is up to date with {}", agent, report.onto),
+                SyncOutcome::Updated { commits } => {
+                    println!("{} picked up {} commit(s) from {}", agent, commits, report.onto)
+                }
+                SyncOutcome::Conflict(message) => {
+                    println!("{} conflicts with {}; left unchanged: {}", agent, report.onto, message)
+                }
+                SyncOutcome::Refused(message) => println!("Could not update {}: {}", agent, message),
+            }
+            for check in &report.checks {
+                let mark = match (check.passed, check.blocking) {
+                    (true, _) => "✓",
+                    (false, true) => "✗",
+                    (false, false) => "⚠",
+                };
+                println!("  {} {}", mark, check.name);
+                if !check.passed {
+                    for line in check.output.lines() {
+                        println!("      {}", line);
+                    }
+                }
+            }
+            if !report.is_ok() {
+                std::process::exit(1);
+            }
+        }
+
+        Commands::Snapshot { agent, label, list } => {
+            let store = StateStore::open(&repo_path)?;
+            if list {
+                let snapshots = store.snapshots(&agent)?;
+                if snapshots.is_empty() {
+                    println!("No snapshots of {}", agent);
+                }
+                for snap in &snapshots {
+                    println!(
+                        "#{} {} {} {}",
+                        snap.id,
+                        snap.created_at.format("%Y-%m-%d %H:%M"),
+                        &snap.head_commit[..7.min(snap.head_commit.len())],
+                        snap.label.as_deref().unwrap_or("")
+                    );
+                }
                 return Ok(());
             }
+            let snap = rembrandt::snapshot::snapshot(&repo_path, &store, &agent, label.as_deref())?;
+            audit(&repo_path, "snapshot", Some(&agent), serde_json::json!({ "snapshot": snap.id, "label": label }));
+            println!("Snapshot #{} of {} ({})", snap.id, agent, snap.ref_name());
+        }
 
-            println!("Found {} worktree(s):", worktrees.len());
-            let mut to_clean = Vec::new();
+        Commands::Restore { agent, snapshot } => {
+            let store = StateStore::open(&repo_path)?;
+            let previous = rembrandt::snapshot::restore(&repo_path, &store, &agent, snapshot)?;
+            audit(&repo_path, "restore", Some(&agent), serde_json::json!({ "snapshot": snapshot, "saved_as": previous.id }));
+            println!("Restored {} to snapshot #{}", agent, snapshot);
+            println!("Previous state saved as snapshot #{}", previous.id);
+        }
 
-            for wt in &worktrees {
-                // All worktrees in .rembrandt/agents/ are candidates
-                // In TUI mode, sessions are tracked in memory
-                // Without daemon, we can't know if they're truly orphaned
-                // So we list them all and let user decide
-                println!("  {} → {} ({})", wt.agent_id, wt.branch, wt.path.display());
-                to_clean.push(wt);
-            }
+        Commands::Report { agent, base, no_validate } => {
+            let config = rembrandt::config::AppConfig::load(&repo_path)?;
+            let state = rembrandt::state::StatePool::open(&repo_path)?;
+            let options = rembrandt::report::ReportOptions {
+                base_branch: base,
+                validate: !no_validate,
+                ..rembrandt::report::ReportOptions::from_config(&config)
+            };
+            let runtime = tokio::runtime::Runtime::new()?;
+            let path = runtime.block_on(rembrandt::report::generate(&repo_path, &state, &agent, &options))?;
+            println!("Wrote {}", path.display());
+        }
 
-            if dry_run {
-                println!("\nDry run - {} worktree(s) would be removed", to_clean.len());
-            } else {
-                println!("\nCleaning {} worktree(s)...", to_clean.len());
-                for wt in to_clean {
-                    print!("  Removing {}... ", wt.agent_id);
-                    match manager.remove_worktree(&wt.agent_id) {
-                        Ok(_) => println!("done"),
-                        Err(e) => println!("failed: {}", e),
+        Commands::Queue { action } => {
+            let store = StateStore::open(&repo_path)?;
+            match action {
+                Some(QueueAction::Add { agent }) => {
+                    let id = rembrandt::merge_queue::enqueue(&store, &agent)?;
+                    audit(&repo_path, "queue_add", Some(&agent), serde_json::json!({ "entry": id }));
+                    println!("Queued {} (#{})", agent, id);
+                }
+                Some(QueueAction::Remove { agent }) => {
+                    if store.dequeue_merge(&agent)? > 0 {
+                        audit(&repo_path, "queue_remove", Some(&agent), serde_json::Value::Null);
+                        println!("Removed {} from the merge queue", agent);
+                    } else {
+                        println!("{} is not waiting in the merge queue", agent);
+                    }
+                }
+                Some(QueueAction::Run { base, no_validate }) => {
+                    let config = rembrandt::config::AppConfig::load(&repo_path)?;
+                    let runtime = tokio::runtime::Runtime::new()?;
+                    let handled = runtime.block_on(rembrandt::merge_queue::run(
+                        &repo_path,
+                        &base,
+                        &store,
+                        !no_validate,
+                        &config.solution_validator(&base),
+                        &config.hooks,
+                    ))?;
+                    if handled.is_empty() {
+                        println!("Merge queue is empty");
+                    }
+                    for entry in &handled {
+                        audit(
+                            &repo_path,
+                            "merge",
+                            Some(&entry.agent_id),
+                            serde_json::json!({ "base": base, "validate": !no_validate, "queued": true, "status": entry.status.to_string() }),
+                        );
+                        println!(
+                            "  {} [{}] {}",
+                            entry.agent_id,
+                            entry.status,
+                            entry.detail.as_deref().unwrap_or("")
+                        );
+                    }
+                }
+                None => {
+                    let entries = store.merge_queue()?;
+                    if entries.is_empty() {
+                        println!("Merge queue is empty");
+                    }
+                    for entry in &entries {
+                        println!(
+                            "#{} {} {} [{}] {}",
+                            entry.id,
+                            entry.enqueued_at.format("%Y-%m-%d %H:%M"),
+                            entry.agent_id,
+                            entry.status,
+                            entry.detail.as_deref().unwrap_or("")
+                        );
                     }
                 }
             }
         }
 
-        Commands::Dashboard => {
-            rembrandt::tui::run(repo_path)?;
+        Commands::Stop { agent, all, status, grace, force } => {
+            let config = rembrandt::config::AppConfig::load(&repo_path)?;
+            let grace = grace.unwrap_or(config.stop_grace_secs);
+            let agents = match agent {
+                Some(agent) => vec![agent],
+                None => batch_targets(&repo_path, &status)?,
+            };
+            if all && agents.is_empty() {
+                println!("No matching sessions to stop");
+            }
+            let mut failed = 0;
+            for agent in &agents {
+                if let Err(e) = stop_agent(&repo_path, &config, agent, grace, force) {
+                    eprintln!("  Could not stop {}: {}", agent, e);
+                    failed += 1;
+                }
+            }
+            if failed > 0 {
+                anyhow::bail!("{} of {} agent(s) could not be stopped", failed, agents.len());
+            }
         }
 
-        Commands::Status => {
-            println!("Rembrandt Status");
-            println!("================");
+        Commands::Steer { agent, message, at, every, list, cancel } => {
+            let store = StateStore::open(&repo_path)?;
+            if let Some(id) = cancel {
+                if store.cancel_steer(id)? {
+                    audit(&repo_path, "steer_cancel", None, serde_json::json!({ "steer": id }));
+                    println!("Cancelled scheduled steer #{}", id);
+                } else {
+                    println!("No scheduled steer #{}", id);
+                }
+                return Ok(());
+            }
+            if list {
+                let steers = store.scheduled_steers(agent.as_deref())?;
+                if steers.is_empty() {
+                    println!("No steers scheduled");
+                }
+                for steer in &steers {
+                    let every = steer
+                        .every
+                        .map(|every| format!(" every {}m", every.num_minutes()))
+                        .unwrap_or_default();
+                    println!(
+                        "#{} {} {}{} (sent {}x): {}",
+                        steer.id,
+                        steer.agent_id,
+                        steer.due_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"),
+                        every,
+                        steer.sent_count,
+                        rembrandt::timeline::one_line(&steer.message, 60)
+                    );
+                }
+                return Ok(());
+            }
+
+            let (Some(agent), Some(message)) = (agent, message) else {
+                anyhow::bail!("steer needs an agent and a message");
+            };
+            let now = chrono::Utc::now();
+            let every = every.as_deref().map(rembrandt::schedule::parse_every).transpose()?;
+            let due_at = match (&at, every) {
+                (Some(at), _) => Some(rembrandt::schedule::parse_at(at, now)?),
+                (None, Some(every)) => Some(now + every),
+                (None, None) => None,
+            };
+
+            let Some(due_at) = due_at else {
+                let daemon = DaemonConnection::connect(&default_socket_path())
+                    .map_err(|_| anyhow::anyhow!("no daemon running; only detached agents can be steered from here"))?;
+                let repo = main_repo_root(&repo_path).unwrap_or_else(|| repo_path.clone());
+                let mut probe = rembrandt::csi::DaemonProbe::new(daemon, repo);
+                rembrandt::csi::SessionProbe::send_message(&mut probe, &agent, &message)?;
+                let detail = format!("manual: {}", rembrandt::timeline::one_line(&message, 80));
+                store.record_timeline(&agent, rembrandt::timeline::TimelineKind::Steer, Some(&detail))?;
+                audit(&repo_path, "steer", Some(&agent), serde_json::json!({ "message": message }));
+                println!("Steered {}", agent);
+                return Ok(());
+            };
+
+            if rembrandt::schedule::agent_ended(&store, &agent)? {
+                anyhow::bail!("no live agent '{}' in this repository to steer", agent);
+            }
+            let id = store.schedule_steer(&agent, &message, due_at, every)?;
+            audit(
+                &repo_path,
+                "steer_schedule",
+                Some(&agent),
+                serde_json::json!({ "steer": id, "at": due_at.to_rfc3339(), "every_secs": every.map(|e| e.num_seconds()) }),
+            );
+            let when = due_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M");
+            match every {
+                Some(every) => println!("Scheduled steer #{} for {} at {}, then every {}m", id, agent, when, every.num_minutes()),
+                None => println!("Scheduled steer #{} for {} at {}", id, agent, when),
+            }
+            let repo = main_repo_root(&repo_path).unwrap_or_else(|| repo_path.clone());
+            match DaemonConnection::connect(&default_socket_path()).and_then(|mut daemon| daemon.schedule_repo()) {
+                Err(_) => println!("No daemon is running; start `rembrandt daemon` here to have it sent"),
+                Ok(Some(scheduled)) if scheduled == repo => {}
+                Ok(Some(scheduled)) => eprintln!(
+                    "Warning: the running daemon sends the steers of {}, not this repository; start `rembrandt daemon` here to have it sent",
+                    scheduled.display()
+                ),
+                Ok(None) => eprintln!(
+                    "Warning: the running daemon doesn't send scheduled steers; restart `rembrandt daemon` here to have it sent"
+                ),
+            }
+        }
+
+        Commands::Nudge { agent, all, status } => {
+            let mut daemon = DaemonConnection::connect(&default_socket_path())
+                .map_err(|_| anyhow::anyhow!("no daemon running; only detached agents can be nudged from here"))?;
+            let repo = main_repo_root(&repo_path).unwrap_or_else(|| repo_path.clone());
+            let agents = match (agent, all && status.is_empty()) {
+                (Some(agent), _) => vec![agent],
+                // Every running agent, tracked in state.db or not
+                (None, true) => {
+                    let mut agents: Vec<String> = daemon
+                        .list(Some(repo.clone()))?
+                        .into_iter()
+                        .filter(|s| s.status == SessionStatus::Running)
+                        .map(|s| s.agent_id)
+                        .collect();
+                    agents.sort();
+                    agents.dedup();
+                    agents
+                }
+                (None, false) => batch_targets(&repo_path, &status)?,
+            };
+            let store = StateStore::open(&repo_path).ok();
+            let mut nudged = 0;
+            for agent in &agents {
+                for session in daemon.list_by_agent(agent, Some(repo.clone()))? {
+                    if session.status != SessionStatus::Running {
+                        continue;
+                    }
+                    let strategy = match daemon.nudge(&session.id) {
+                        Ok(strategy) => strategy,
+                        Err(e) => {
+                            eprintln!("Could not nudge {}: {}", agent, e);
+                            continue;
+                        }
+                    };
+                    println!("Nudged {} ({})", agent, strategy);
+                    if let Some(store) = &store {
+                        let detail = format!("manual: {}", strategy);
+                        store.record_timeline(agent, rembrandt::timeline::TimelineKind::Nudge, Some(&detail))?;
+                    }
+                    audit(&repo_path, "nudge", Some(agent), serde_json::json!({ "strategy": strategy }));
+                    nudged += 1;
+                }
+            }
+            if nudged == 0 {
+                println!("No running daemon sessions to nudge");
+            }
+        }
+
+        Commands::Fork { .. } => unreachable!("forks are turned into spawns above"),
+
+        Commands::Pause { agent } => pause_or_resume(&repo_path, &agent, true)?,
+
+        Commands::Resume { agent } => pause_or_resume(&repo_path, &agent, false)?,
+
+        Commands::Cleanup { all, force } => {
+            let manager = WorktreeManager::new(&repo_path)?;
+            let worktrees = manager.list_worktrees()?;
+
+            if worktrees.is_empty() {
+                println!("No worktrees to clean up");
+                return Ok(());
+            }
+
+            if all {
+                println!("Cleaning up all {} worktrees...", worktrees.len());
+                for wt in &worktrees {
+                    remove_guarded_worktree(&repo_path, &manager, wt, force, "cleanup");
+                }
+            } else {
+                // TODO: Only remove worktrees with Completed/Stopped status
+                // For now, list what would be cleaned (requires agent registry)
+                println!("Worktrees that would be cleaned (once registry tracks status):");
+                for wt in &worktrees {
+                    println!("  {} (status unknown - use --all to force)", wt.agent_id);
+                }
+            }
+        }
+
+        Commands::Gc { dry_run, force } => {
+            if !dry_run {
+                prune_session_logs(&repo_path, &rembrandt::config::AppConfig::load(&repo_path)?);
+            }
+            let manager = WorktreeManager::new(&repo_path)?;
+            let worktrees = manager.list_worktrees()?;
+
+            if worktrees.is_empty() {
+                println!("No worktrees found");
+                return Ok(());
+            }
+
+            println!("Found {} worktree(s):", worktrees.len());
+            let mut to_clean = Vec::new();
+
+            for wt in &worktrees {
+                // All worktrees in .rembrandt/agents/ are candidates
+                // In TUI mode, sessions are tracked in memory
+                // Without daemon, we can't know if they're truly orphaned
+                // So we list them all and let user decide
+                println!("  {} → {} ({})", wt.agent_id, wt.branch, wt.path.display());
+                to_clean.push(wt);
+            }
+
+            if dry_run {
+                println!("\nDry run - {} worktree(s) would be removed", to_clean.len());
+            } else {
+                println!("\nCleaning {} worktree(s)...", to_clean.len());
+                for wt in to_clean {
+                    remove_guarded_worktree(&repo_path, &manager, wt, force, "gc");
+                }
+            }
+        }
+
+        Commands::Dashboard => {
+            rembrandt::tui::run(repo_path)?;
+        }
+
+        Commands::Daemon {
+            action: Some(DaemonAction::Supervise { max_restarts }),
+            socket,
+            metrics_addr,
+            listen,
+        } => {
+            let mut args: Vec<std::ffi::OsString> = vec!["--repo".into(), repo_path.clone().into()];
+            args.push("--log-format".into());
+            args.push(
+                match telemetry_config.log_format {
+                    rembrandt::config::LogFormat::Text => "text",
+                    rembrandt::config::LogFormat::Json => "json",
+                }
+                .into(),
+            );
+            args.push("daemon".into());
+            if let Some(socket) = socket {
+                args.extend(["--socket".into(), socket.into()]);
+            }
+            if let Some(addr) = metrics_addr {
+                args.extend(["--metrics-addr".into(), addr.to_string().into()]);
+            }
+            if let Some(addr) = listen {
+                args.extend(["--listen".into(), addr.to_string().into()]);
+            }
+            let mut supervisor = rembrandt::daemon::supervise::Supervisor::new(std::env::current_exe()?, args)
+                .with_max_restarts(max_restarts);
+            if let Some(repo) = main_repo_root(&repo_path) {
+                supervisor = supervisor.with_audit_repo(repo);
+            }
+            println!("Supervising the daemon; it is restarted if it crashes");
+            tokio::runtime::Runtime::new()?.block_on(supervisor.run())?;
+        }
+
+        Commands::Daemon {
+            action: None,
+            socket,
+            metrics_addr,
+            listen,
+        } => {
+            let socket = socket.unwrap_or_else(rembrandt::daemon::ipc::default_socket_path);
+            println!("Rembrandt daemon listening on {}", socket.display());
+            let config = rembrandt::config::AppConfig::load(&repo_path)?;
+            if main_repo_root(&repo_path).is_some() {
+                let store = StateStore::open(&repo_path)?;
+                match rembrandt::reconcile::reconcile(&repo_path, &store) {
+                    Ok(report) => {
+                        for line in report.lines() {
+                            println!("Reconciled: {}", line);
+                        }
+                    }
+                    Err(e) => eprintln!("Skipped worktree reconciliation: {}", e),
+                }
+                prune_session_logs(&repo_path, &config);
+            }
+            let mut daemon = rembrandt::daemon::Daemon::new(socket).with_shutdown_policy(
+                config.shutdown_policy,
+                std::time::Duration::from_secs(config.shutdown_grace_secs),
+            )
+            .with_nudge_ladders(config.agent_nudge_ladders())
+            .with_quit_sequences(config.agent_quit_sequences())
+            .with_stop_grace(std::time::Duration::from_secs(config.stop_grace_secs))
+            .with_pty_io(config.pty_io);
+            if let Some(capacity) = config.buffer_capacity {
+                daemon = daemon.with_buffer_capacity(capacity);
+            }
+            if let Some(addr) = metrics_addr {
+                println!("Metrics at http://{}/metrics", addr);
+                daemon = daemon.with_metrics_addr(addr);
+            }
+            if !config.api_tokens.is_empty() {
+                let repo = main_repo_root(&repo_path).unwrap_or_else(|| repo_path.clone());
+                daemon = daemon.with_api_tokens(config.api_tokens.clone(), repo);
+            }
+            if let Some(repo) = main_repo_root(&repo_path) {
+                daemon = daemon.with_schedule(repo);
+            }
+            if let Some(addr) = listen {
+                if let Some(tls) = &config.listen_tls {
+                    let root = main_repo_root(&repo_path).unwrap_or_else(|| repo_path.clone());
+                    daemon = daemon.with_tls(rembrandt::daemon::tls::acceptor(tls, &root)?);
+                }
+                rembrandt::daemon::tls::check_listen_addr(addr, config.listen_tls.is_some())?;
+                let scheme = if config.listen_tls.is_some() { "TLS" } else { "plain TCP" };
+                println!("Accepting token-authenticated clients on {} ({})", addr, scheme);
+                daemon = daemon.with_listen_addr(addr);
+            }
+            tokio::runtime::Runtime::new()?.block_on(daemon.run())?;
+        }
+
+        Commands::Attention { resolve } => {
+            let store = StateStore::open(&repo_path)?;
+            if let Some(id) = resolve {
+                if store.resolve_attention(id)? {
+                    audit(&repo_path, "attention_resolve", None, serde_json::json!({ "item": id }));
+                    println!("Resolved #{}", id);
+                } else {
+                    println!("No open attention item #{}", id);
+                }
+                return Ok(());
+            }
+
+            let items = store.open_attention()?;
+            if items.is_empty() {
+                println!("Nothing needs attention");
+            }
+            for item in &items {
+                println!(
+                    "#{} {} [{}] {}: {}",
+                    item.id,
+                    item.created_at.format("%Y-%m-%d %H:%M"),
+                    item.kind,
+                    item.agent_id.as_deref().unwrap_or("-"),
+                    item.message
+                );
+            }
+        }
+
+        Commands::Timeline { agent } => {
+            let store = StateStore::open(&repo_path)?;
+            let entries = store.timeline(&agent)?;
+            if entries.is_empty() {
+                println!("No timeline recorded for {}", agent);
+            }
+            for entry in &entries {
+                println!(
+                    "{} {:<14} {}",
+                    entry.created_at.format("%Y-%m-%d %H:%M:%S"),
+                    entry.kind,
+                    entry.detail.as_deref().unwrap_or("")
+                );
+            }
+        }
+
+        Commands::Transcript { agent, tail, json } => {
+            let store = StateStore::open(&repo_path)?;
+            let mut reader = rembrandt::transcript::TranscriptReader::new();
+            let checkout = match store.get_session(&agent)? {
+                Some(record) => record.checkout_path,
+                None => WorktreeManager::new(&repo_path)?.worktree_path(&agent),
+            };
+            reader.read_session_files(&store, &agent, &checkout)?;
+            // Agents run with stream-json print their conversation instead
+            if let Ok(mut daemon) = DaemonConnection::connect(&default_socket_path()) {
+                let repo = main_repo_root(&repo_path).unwrap_or_else(|| repo_path.clone());
+                for session in daemon.list_by_agent(&agent, Some(repo))? {
+                    let (history, _) = daemon.history(&session.id)?;
+                    reader.read_output(&store, &agent, &history)?;
+                }
+            }
+
+            let entries = store.transcript(&agent, tail)?;
+            if entries.is_empty() && !json {
+                println!("No transcript found for {} (only Claude Code sessions have one)", agent);
+            }
+            for entry in &entries {
+                if json {
+                    println!("{}", serde_json::to_string(entry)?);
+                    continue;
+                }
+                let mut lines = entry.text.lines();
+                println!(
+                    "{} {:<11} {}",
+                    entry.created_at.format("%Y-%m-%d %H:%M:%S"),
+                    entry.role,
+                    lines.next().unwrap_or("")
+                );
+                for line in lines {
+                    println!("{:32}{}", "", line);
+                }
+            }
+        }
+
+        Commands::Search { pattern, ignore_case, agent, since, until, max_per_session, json } => {
+            let store = StateStore::open(&repo_path)?;
+            let mut query = rembrandt::search::SearchQuery::new(&pattern, ignore_case)?;
+            query.agent = agent;
+            query.since = since.as_deref().map(rembrandt::audit::parse_since).transpose()?;
+            query.until = until.as_deref().map(rembrandt::audit::parse_since).transpose()?;
+            query.max_excerpts = max_per_session;
+            // The daemon keeps logs in the main checkout
+            let repo = main_repo_root(&repo_path).unwrap_or_else(|| repo_path.clone());
+            let results = rembrandt::search::search(&repo, &store, &query)?;
+            if results.is_empty() && !json {
+                println!("No matches for {}", pattern);
+            }
+            for hits in &results {
+                if json {
+                    println!("{}", serde_json::to_string(hits)?);
+                    continue;
+                }
+                println!(
+                    "{} {:<10} {} ({} hit{}, last {})",
+                    hits.agent_id,
+                    hits.source.as_str(),
+                    hits.session_id.as_deref().unwrap_or("-"),
+                    hits.hits,
+                    if hits.hits == 1 { "" } else { "s" },
+                    hits.last_at.format("%Y-%m-%d %H:%M:%S"),
+                );
+                for excerpt in &hits.excerpts {
+                    let place = match (excerpt.line, excerpt.at) {
+                        (Some(line), _) => line.to_string(),
+                        (None, Some(at)) => at.format("%H:%M:%S").to_string(),
+                        (None, None) => String::new(),
+                    };
+                    println!("  {:>8}  {}", place, excerpt.text);
+                }
+                if hits.hits > hits.excerpts.len() {
+                    println!("  {:>8}  … {} more", "", hits.hits - hits.excerpts.len());
+                }
+            }
+        }
+
+        Commands::Audit { action, agent, since, limit, json } => {
+            let store = StateStore::open(&repo_path)?;
+            let query = rembrandt::audit::AuditQuery {
+                action,
+                target: agent,
+                since: since.as_deref().map(rembrandt::audit::parse_since).transpose()?,
+                limit: Some(limit),
+            };
+            let entries = store.audit_log(&query)?;
+            if entries.is_empty() && !json {
+                println!("No audit entries");
+            }
+            for entry in &entries {
+                if json {
+                    println!("{}", serde_json::to_string(entry)?);
+                    continue;
+                }
+                let params = entry.params.as_ref().map(|p| p.to_string()).unwrap_or_default();
+                println!(
+                    "#{} {} {:<6} {:<12} {:<18} {:<20} {}",
+                    entry.id,
+                    entry.created_at.format("%Y-%m-%d %H:%M:%S"),
+                    entry.source,
+                    entry.actor,
+                    entry.action,
+                    entry.target.as_deref().unwrap_or("-"),
+                    params
+                );
+            }
+        }
+
+        Commands::BlameReport { base, since, until, json } => {
+            let root = main_repo_root(&repo_path).unwrap_or_else(|| repo_path.clone());
+            let store = root
+                .join(".rembrandt")
+                .join("state.db")
+                .exists()
+                .then(|| StateStore::open(&root))
+                .transpose()?;
+            let since = since.as_deref().map(rembrandt::audit::parse_since).transpose()?;
+            let until = until
+                .as_deref()
+                .map(|value| {
+                    rembrandt::audit::parse_since(value)
+                        .map_err(|_| anyhow::anyhow!("invalid --until '{}': expected e.g. 30m, 24h, 7d or an RFC 3339 time", value))
+                })
+                .transpose()?;
+            let report = rembrandt::attribution::report(&root, store.as_ref(), base.as_deref(), since, until)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+                return Ok(());
+            }
+
+            let share = |insertions: usize| match report.total.insertions {
+                0 => 0.0,
+                total => insertions as f64 * 100.0 / total as f64,
+            };
+            println!(
+                "{}: {} commit(s), +{} -{}",
+                report.base, report.total.commits, report.total.insertions, report.total.deletions
+            );
+            if report.total.commits == 0 {
+                return Ok(());
+            }
+            println!("\n{:<12} {:>6} {:>8} {:>8} {:>8} {:>6}", "TYPE", "AGENTS", "COMMITS", "ADDED", "REMOVED", "SHARE");
+            for row in &report.by_type {
+                println!(
+                    "{:<12} {:>6} {:>8} {:>8} {:>8} {:>5.1}%",
+                    row.agent_type,
+                    row.agents,
+                    row.totals.commits,
+                    row.totals.insertions,
+                    row.totals.deletions,
+                    share(row.totals.insertions)
+                );
+            }
+            let unattributed = &report.unattributed;
+            println!(
+                "{:<12} {:>6} {:>8} {:>8} {:>8} {:>5.1}%",
+                "(none)",
+                "-",
+                unattributed.commits,
+                unattributed.insertions,
+                unattributed.deletions,
+                share(unattributed.insertions)
+            );
+            if !report.by_agent.is_empty() {
+                println!("\n{:<24} {:<12} {:>8} {:>8} {:>8}  TASKS", "AGENT", "TYPE", "COMMITS", "ADDED", "REMOVED");
+                for row in &report.by_agent {
+                    let tasks = row.tasks.iter().cloned().collect::<Vec<_>>().join(",");
+                    println!(
+                        "{:<24} {:<12} {:>8} {:>8} {:>8}  {}",
+                        row.agent_id,
+                        row.agent_type,
+                        row.totals.commits,
+                        row.totals.insertions,
+                        row.totals.deletions,
+                        if tasks.is_empty() { "-" } else { &tasks }
+                    );
+                }
+            }
+        }
+
+        Commands::Blackboard { action } => {
+            // Agents run this from their worktrees; the blackboard is the main checkout's
+            let root = main_repo_root(&repo_path).unwrap_or_else(|| repo_path.clone());
+            match action {
+                None => match rembrandt::blackboard::read(&root)? {
+                    Some(contents) => print!("{}", contents),
+                    None => println!("The blackboard is empty; add to it with `rembrandt blackboard add \"...\"`"),
+                },
+                Some(BlackboardAction::Add { text, author }) => {
+                    let author = author
+                        .or_else(|| std::env::var("REMBRANDT_AGENT_ID").ok())
+                        .unwrap_or_else(|| "human".to_string());
+                    rembrandt::blackboard::append(&root, &author, &text)?;
+                    audit(&repo_path, "blackboard_add", None, serde_json::json!({ "author": author, "text": text }));
+                    let synced = rembrandt::blackboard::sync_all(&root)?;
+                    println!("Added to {} (synced into {} worktree(s))", rembrandt::blackboard::path(&root).display(), synced);
+                }
+                Some(BlackboardAction::Sync) => {
+                    let synced = rembrandt::blackboard::sync_all(&root)?;
+                    println!("Synced the blackboard into {} worktree(s)", synced);
+                }
+            }
+        }
+
+        Commands::Msg { action } => {
+            let store = StateStore::open(&repo_path)?;
+            match action {
+                MsgAction::Send { from, to, text } => {
+                    let id = store.send_agent_message(&from, &to, &text)?;
+                    audit(&repo_path, "message", Some(&to), serde_json::json!({ "message": id, "from": from, "text": text }));
+                    println!("Queued message #{} for {}", id, to);
+                }
+                MsgAction::Inbox { agent, pending } => {
+                    let messages = store.inbox(&agent, pending)?;
+                    if messages.is_empty() {
+                        println!("No messages for {}", agent);
+                    }
+                    for message in messages {
+                        let state = match message.delivered_at {
+                            Some(at) => format!("delivered {}", at.format("%H:%M:%S")),
+                            None => "pending".to_string(),
+                        };
+                        println!(
+                            "#{} {} from {} [{}]: {}",
+                            message.id,
+                            message.created_at.format("%Y-%m-%d %H:%M:%S"),
+                            message.from_agent,
+                            state,
+                            message.body
+                        );
+                    }
+                }
+            }
+        }
+
+        Commands::Followups { agent, pending } => {
+            let store = StateStore::open(&repo_path)?;
+            let followups = store.followups(agent.as_deref(), pending)?;
+            if followups.is_empty() {
+                println!("No follow-ups");
+            }
+            for followup in followups {
+                let state = match (&followup.filed_at, &followup.issue_id) {
+                    (Some(_), Some(issue)) => format!("filed as {}", issue),
+                    (Some(_), None) => "filed".to_string(),
+                    (None, _) => "pending".to_string(),
+                };
+                let task = followup.task_id.as_deref().map(|t| format!(" ({})", t)).unwrap_or_default();
+                println!(
+                    "#{} {}{} [{}] {}: {}",
+                    followup.id, followup.agent_id, task, state, followup.source, followup.text
+                );
+            }
+        }
+
+        Commands::Master { master, apprentices, base, interval, once } => {
+            let config = rembrandt::config::AppConfig::load(&repo_path)?;
+            let store = StateStore::open(&repo_path)?;
+            let repo = main_repo_root(&repo_path).unwrap_or_else(|| repo_path.clone());
+            let mut master_loop = rembrandt::master::MasterLoop::new(&repo, &master, apprentices, &base);
+            let interval = interval.unwrap_or(config.csi_poll_interval_secs);
+
+            loop {
+                let conn = DaemonConnection::connect(&default_socket_path())?;
+                let mut probe = rembrandt::csi::DaemonProbe::new(conn, repo.clone());
+                let pass = master_loop.run_pass(&store, &mut probe)?;
+                for (agent_id, message) in &pass.steers {
+                    println!("{} -> {}: {}", master, agent_id, message);
+                }
+                if pass.digest.is_some() {
+                    println!("Sent digest to {}", master);
+                }
+
+                if once {
+                    break;
+                }
+                std::thread::sleep(std::time::Duration::from_secs(interval));
+            }
+        }
+
+        Commands::Pool { action } => {
+            let config = rembrandt::config::AppConfig::load(&repo_path)?;
+            let store = StateStore::open(&repo_path)?;
+            match action {
+                PoolAction::Status => {
+                    let pooled = store.pooled_agents()?;
+                    if pooled.is_empty() {
+                        println!("The pool is empty");
+                    }
+                    let now = chrono::Utc::now();
+                    for agent in &pooled {
+                        let idle = now.signed_duration_since(agent.created_at).num_minutes();
+                        println!(
+                            "  {}  {}  off {} ({}), idle {}m",
+                            agent.agent_id,
+                            agent.agent_type,
+                            agent.base_branch,
+                            &agent.base_commit[..agent.base_commit.len().min(8)],
+                            idle
+                        );
+                    }
+                    for (agent, size) in &config.pool.agents {
+                        let agent_type = rembrandt::warm_pool::canonical_type(agent);
+                        let waiting = pooled.iter().filter(|p| p.agent_type == agent_type).count();
+                        println!("{}: {} of {} waiting", agent, waiting, size);
+                    }
+                }
+                PoolAction::Fill | PoolAction::Drain => {
+                    let mut daemon = DaemonConnection::connect(&default_socket_path()).map_err(|_| {
+                        anyhow::anyhow!("the pool lives in the daemon; start one with `rembrandt daemon`")
+                    })?;
+                    if matches!(action, PoolAction::Drain) {
+                        let drained = rembrandt::warm_pool::drain(&repo_path, &config, &store, &mut daemon)?;
+                        println!("Stopped {} pooled agent(s)", drained);
+                        return Ok(());
+                    }
+                    if config.pool.agents.is_empty() {
+                        anyhow::bail!("no pool sizes configured; set e.g. `agents = {{ claude = 2 }}` under [pool]");
+                    }
+                    let started = rembrandt::warm_pool::fill(&repo_path, &config, &store, &mut daemon)?;
+                    for agent in &started {
+                        println!("Started {} in {}", agent.agent_id, agent.checkout_path.display());
+                    }
+                    println!("Pool full ({} started)", started.len());
+                }
+            }
+        }
+
+        Commands::State { action } => match action {
+            StateAction::Verify { repair } => {
+                use rembrandt::state::maintenance::{self, Repair};
+
+                let db_path = repo_path.join(".rembrandt").join("state.db");
+                let problems = maintenance::verify(&db_path)?;
+                if problems.is_empty() {
+                    println!("{}: ok", db_path.display());
+                    return Ok(());
+                }
+                println!("{}: {} problem(s)", db_path.display(), problems.len());
+                for problem in problems.iter().take(20) {
+                    println!("  {}", problem);
+                }
+                if problems.len() > 20 {
+                    println!("  … {} more", problems.len() - 20);
+                }
+                if !repair {
+                    anyhow::bail!("state.db is damaged; `rembrandt state verify --repair` can fix it");
+                }
+                match maintenance::repair(&db_path)? {
+                    Repair::Reindexed => println!("Repaired: rebuilt the damaged indexes"),
+                    Repair::Rebuilt { damaged } => println!(
+                        "Repaired: copied everything readable into a fresh state.db (damaged copy kept at {})",
+                        damaged.display()
+                    ),
+                    Repair::Restored { damaged } => println!(
+                        "Restored {}, taken before the last migration; later changes are lost (damaged copy kept at {})",
+                        maintenance::backup_path(&db_path).display(),
+                        damaged.display()
+                    ),
+                }
+            }
+        },
+
+        Commands::Csi { action } => match action {
+            CsiAction::Run { once } => {
+                let config = rembrandt::config::AppConfig::load(&repo_path)?;
+                let store = rembrandt::state::StateStore::open(&repo_path)?;
+                let repo = main_repo_root(&repo_path).unwrap_or_else(|| repo_path.clone());
+                let mut supervisor = rembrandt::csi::Supervisor::new(&config);
+                let budgets = rembrandt::budget::BudgetPolicy::from_config(&config);
+                let health = rembrandt::health::HealthPolicy::from_config(&config);
+                let beads = config.beads.client(&repo_path);
+                let mut orch = rembrandt::orchestrator::Orchestrator::new(
+                    &repo_path,
+                    rembrandt::runtime::PiRuntime::new(),
+                )?;
+                if config.generate_reports {
+                    orch = orch.with_reports(rembrandt::report::ReportOptions::from_config(&config));
+                }
+                orch = orch.with_hooks(config.hooks.clone());
+                let runtime = tokio::runtime::Runtime::new()?;
+                let checkpoint_every = std::time::Duration::from_secs(config.wal_checkpoint_interval_secs);
+                let mut last_checkpoint = std::time::Instant::now();
+
+                loop {
+                    // Reconnect each pass so a daemon started later is picked up
+                    let mut probe = DaemonConnection::connect(&default_socket_path())
+                        .ok()
+                        .map(|conn| rembrandt::csi::DaemonProbe::new(conn, repo.clone()));
+                    let report = supervisor.run_pass(&store, probe.as_mut())?;
+                    println!("CSI run #{}: {}", report.run_id, report.summary());
+                    let synced = rembrandt::blackboard::sync_all(&repo)?;
+                    if synced > 0 {
+                        println!("  synced the blackboard into {} worktree(s)", synced);
+                    }
+                    for finding in &report.findings {
+                        println!("  {} [{}] {}", finding.agent_id, finding.kind.as_str(), finding.message);
+                    }
+                    for followup in rembrandt::followups::file_pending(&store, &beads)? {
+                        let issue = followup.issue_id.as_deref().unwrap_or("an issue");
+                        println!("  {} [follow-up] filed as {}: {}", followup.agent_id, issue, followup.text);
+                    }
+                    if let Some(health) = &health {
+                        for record in health.run_pass(&store, probe.as_mut())? {
+                            let state = if record.passed { "passing" } else { "failing" };
+                            let line = record.failure_line().filter(|_| !record.passed).unwrap_or_default();
+                            println!("  {} [health] {} ({}x) {}", record.agent_id, state, record.streak, line);
+                        }
+                    }
+                    // Runtime agents have no daemon session for the pass to nudge
+                    if config.csi_remediations.contains(&rembrandt::config::CsiRemediation::Nudge) {
+                        for (agent_id, strategy) in
+                            runtime.block_on(orch.nudge_idle_agents(&config.agent_nudge_ladders()))?
+                        {
+                            let strategy = strategy.describe();
+                            rembrandt::audit::record(&repo_path, AuditSource::Csi, "nudge", Some(&agent_id), serde_json::json!({ "strategy": strategy }));
+                            println!("  {} [nudge] {}", agent_id, strategy);
+                        }
+                    }
+                    let delivered = runtime.block_on(orch.deliver_messages())?;
+                    if delivered > 0 {
+                        println!("  delivered {} message(s) to idle runtime agents", delivered);
+                    }
+                    for action in runtime.block_on(orch.enforce_budgets(&budgets))? {
+                        match action {
+                            rembrandt::budget::BudgetAction::WrapUp { agent_id, reason } => {
+                                rembrandt::audit::record(&repo_path, AuditSource::Csi, "steer", Some(&agent_id), serde_json::json!({ "budget": reason }));
+                                println!("  {} [budget] {}; asked to wrap up", agent_id, reason);
+                            }
+                            rembrandt::budget::BudgetAction::Stopped { agent_id, reason } => {
+                                rembrandt::audit::record(&repo_path, AuditSource::Csi, "kill", Some(&agent_id), serde_json::json!({ "budget": reason }));
+                                println!("  {} [budget] {}; stopped", agent_id, reason);
+                            }
+                        }
+                    }
+
+                    if !checkpoint_every.is_zero() && last_checkpoint.elapsed() >= checkpoint_every {
+                        last_checkpoint = std::time::Instant::now();
+                        match store.checkpoint() {
+                            Ok(checkpoint) if checkpoint.busy => {
+                                tracing::debug!("WAL checkpoint incomplete: another connection was writing")
+                            }
+                            Ok(_) => {}
+                            Err(e) => tracing::warn!("WAL checkpoint failed: {}", e),
+                        }
+                    }
+
+                    if once {
+                        runtime.block_on(orch.finish_reports());
+                        break;
+                    }
+                    std::thread::sleep(std::time::Duration::from_secs(config.csi_poll_interval_secs));
+                }
+            }
+            CsiAction::Report { limit } => {
+                let store = rembrandt::state::StateStore::open(&repo_path)?;
+                let runs = store.list_csi_runs(limit)?;
+                if runs.is_empty() {
+                    println!("No CSI runs yet (start one with `rembrandt csi run`)");
+                }
+                for run in &runs {
+                    println!(
+                        "#{} {} [{}] {}",
+                        run.id,
+                        run.started_at.format("%Y-%m-%d %H:%M:%S"),
+                        run.status,
+                        run.summary.as_deref().unwrap_or("")
+                    );
+                    for event in store.list_csi_events(run.id)? {
+                        println!(
+                            "  {} [{}] {}",
+                            event.agent_id.as_deref().unwrap_or("-"),
+                            event.kind,
+                            event.message
+                        );
+                    }
+                }
+
+                let hook_runs = store.list_loose_events(limit)?;
+                if !hook_runs.is_empty() {
+                    println!();
+                    println!("Hook runs:");
+                }
+                for event in &hook_runs {
+                    let summary = event.message.lines().next().unwrap_or("");
+                    println!(
+                        "  {} {} [{}] {}",
+                        event.created_at.format("%Y-%m-%d %H:%M:%S"),
+                        event.agent_id.as_deref().unwrap_or("-"),
+                        event.kind,
+                        summary
+                    );
+                }
+            }
+        },
+
+        Commands::Watch { stream, interval, once, until_idle } => {
+            use rembrandt::watch::{Snapshot, WatchFeed};
+            use std::io::IsTerminal;
+
+            let store = StateStore::open(&repo_path)?;
+            let repo_root = main_repo_root(&repo_path);
+            let pty_sessions = || -> Vec<SessionInfo> {
+                DaemonConnection::connect(&default_socket_path())
+                    .and_then(|mut daemon| daemon.list(repo_root.clone()))
+                    .unwrap_or_default()
+            };
+            let redraw = std::io::stdout().is_terminal();
+            let mut feed = if stream { Some(WatchFeed::starting_now(&store, &pty_sessions())?) } else { None };
+            let mut last: Option<Snapshot> = None;
+
+            loop {
+                let sessions = pty_sessions();
+                let snapshot = Snapshot::collect(&store, &sessions)?;
+                let now = chrono::Utc::now();
+                match &mut feed {
+                    Some(feed) => {
+                        for event in feed.poll(&store, &sessions)? {
+                            println!("{}", event.line(now));
+                        }
+                    }
+                    None => {
+                        // Terminals get a redraw; logs only see the table when it changes
+                        if redraw {
+                            print!("\x1b[2J\x1b[H{}", snapshot.table(now));
+                        } else if last.as_ref().is_none_or(|last| snapshot.changed_since(last)) {
+                            println!("{}", snapshot.table(now));
+                        }
+                    }
+                }
+                std::io::Write::flush(&mut std::io::stdout())?;
+
+                if once || (until_idle && !snapshot.has_active()) {
+                    std::process::exit(snapshot.exit_code().into());
+                }
+                last = Some(snapshot);
+                std::thread::sleep(std::time::Duration::from_secs(interval.max(1)));
+            }
+        }
+
+        Commands::Completions { shell } => {
+            rembrandt::completion::write_registration(&shell, &mut std::io::stdout())?;
+        }
+
+        Commands::FakeAgent { steps } => {
+            use rembrandt::fake_agent::{self, FakeStep};
+
+            let steps = if steps.is_empty() {
+                fake_agent::parse_script(&std::env::var(fake_agent::SCRIPT_ENV).unwrap_or_default())?
+            } else {
+                steps.iter().map(|s| s.parse::<FakeStep>()).collect::<rembrandt::Result<_>>()?
+            };
+            let code = fake_agent::run(&steps, &std::env::current_dir()?)?;
+            std::process::exit(code);
+        }
+
+        Commands::Export { output, no_sanitize } => {
+            let summary = rembrandt::bundle::export(&repo_path, &output, !no_sanitize)?;
+            println!("Wrote {} ({})", output.display(), summary.manifest.files.join(", "));
+            if summary.manifest.sanitized {
+                println!("Redacted {} secret value(s)", summary.redactions);
+            }
+        }
+
+        Commands::Import { bundle, force } => {
+            if let Ok(mut daemon) = DaemonConnection::connect(&default_socket_path()) {
+                rembrandt::bundle::check_not_served(&mut daemon, &repo_path)?;
+            }
+            let summary = rembrandt::bundle::import(&repo_path, &bundle, force)?;
+            audit(
+                &repo_path,
+                "import",
+                None,
+                serde_json::json!({ "bundle": bundle.display().to_string(), "files": summary.manifest.files, "force": force }),
+            );
+            println!(
+                "Imported {} from a bundle made {} ({})",
+                summary.manifest.files.join(", "),
+                summary.manifest.created_at,
+                bundle.display()
+            );
+            if let Some(backup) = &summary.backup_dir {
+                println!("Replaced files were moved to {}", backup.display());
+            }
+            if summary.manifest.sanitized && summary.manifest.files.iter().any(|f| f == "config.toml") {
+                println!("The bundle was sanitized: env values in config.toml read {}", rembrandt::bundle::REDACTED);
+            }
+            println!("Sessions refer to the original machine's checkouts; run `rembrandt doctor` to review them");
+        }
+
+        Commands::Doctor { fix, base, socket } => {
+            use rembrandt::doctor::{self, CheckStatus};
+
+            let socket = socket.unwrap_or_else(default_socket_path);
+            if fix {
+                let actions = doctor::repair(&repo_path, &base, &socket)?;
+                for action in &actions {
+                    println!("fixed: {}", action);
+                }
+                if !actions.is_empty() {
+                    println!();
+                }
+            }
+
+            println!("Rembrandt Doctor");
+            println!("================");
+            let checks = doctor::diagnose(&repo_path, &base, &socket);
+            let width = checks.iter().map(|c| c.name.len()).max().unwrap_or(0);
+            for check in &checks {
+                println!("  {} {:width$}  {}", check.status.symbol(), check.name, check.detail);
+                if let Some(fix) = &check.fix {
+                    println!("    {:width$}  → {}", "", fix);
+                }
+            }
+
+            let failed = checks.iter().filter(|c| c.status == CheckStatus::Fail).count();
+            let warned = checks.iter().filter(|c| c.status == CheckStatus::Warn).count();
+            println!();
+            println!("{} failed, {} warning(s)", failed, warned);
+            if failed > 0 {
+                std::process::exit(1);
+            }
+        }
+
+        Commands::Status { oneline: true } => {
+            println!("{}", rembrandt::statusline::StatusLine::for_repo(&repo_path)?);
+        }
+
+        Commands::Status { oneline: false } => {
+            println!("Rembrandt Status");
+            println!("================");
             println!();
 
             if use_v2 {
@@ -450,7 +2240,7 @@ fn main() -> Result<()> {
                     &repo_path,
                     rembrandt::runtime::PiRuntime::new(),
                 )?;
-                let sessions = orch.list_agents()?;
+                let sessions = tokio::runtime::Runtime::new()?.block_on(orch.list_agents())?;
                 println!("V2 Orchestration:");
                 println!("  runtime:     {}", rembrandt::runtime::PiRuntime::new().name());
                 println!("  state.db:    {}", orch.state().db_path().display());
@@ -458,13 +2248,36 @@ fn main() -> Result<()> {
                 println!();
             }
 
+            if let Ok(store) = StateStore::open(&repo_path) {
+                let total = store.usage_totals()?;
+                if total.samples > 0 {
+                    let today = store.usage_since(rembrandt::usage::start_of_today())?;
+                    println!("Usage:");
+                    println!("  total:       {}", total.short());
+                    println!("  today:       {}", today.short());
+                    let config = rembrandt::config::AppConfig::load(&repo_path)?;
+                    if let Some(limit) = config.usage_alert_usd {
+                        let over: Vec<String> = store
+                            .list_sessions()?
+                            .into_iter()
+                            .filter(|s| store.usage_for(&s.agent_id).is_ok_and(|u| u.cost_usd > limit))
+                            .map(|s| s.agent_id)
+                            .collect();
+                        if !over.is_empty() {
+                            println!("  ⚠ over ${:.2}: {}", limit, over.join(", "));
+                        }
+                    }
+                    println!();
+                }
+            }
+
             println!("Integrations:");
 
             // Check beads
-            let beads = rembrandt::integration::beads::BeadsIntegration::new();
+            let beads = rembrandt::config::AppConfig::load(&repo_path)?.beads.client(&repo_path);
             println!(
-                "  beads (br): {}",
-                if beads.is_available() { "available" } else { "not found" }
+                "  beads: {}",
+                rembrandt::integration::beads::describe(&beads)
             );
 
             // Check agent-mail
@@ -486,3 +2299,508 @@ fn main() -> Result<()> {
 }
 
 use rembrandt::integration::Integration;
+
+/// Stop one agent's running daemon sessions (see `Commands::Stop`), reopen
+/// its task and write its report
+fn stop_agent(
+    repo_path: &Path,
+    config: &rembrandt::config::AppConfig,
+    agent: &str,
+    grace: u64,
+    force: bool,
+) -> Result<()> {
+    println!("Stopping agent {}...", agent);
+    let mut stopped = false;
+    // SIGKILL leaves the agent no chance to commit: save what it has first
+    if force
+        && let Ok(store) = StateStore::open(repo_path)
+        && let Some(record) = store.get_session(agent)?
+        && record.isolation_mode == IsolationMode::Worktree
+        && record.checkout_path.is_dir()
+    {
+        match rembrandt::rescue::rescue(repo_path, &store, agent, &record.checkout_path, "kill") {
+            Ok(Some(snapshot)) => println!(
+                "  Saved uncommitted work as snapshot #{} (`rembrandt restore {} {}` brings it back)",
+                snapshot.id, agent, snapshot.id
+            ),
+            Ok(None) => {}
+            Err(e) => eprintln!("  Could not snapshot uncommitted work: {}", e),
+        }
+    }
+    match DaemonConnection::connect(&default_socket_path()) {
+        Ok(mut daemon) => {
+            let repo = main_repo_root(repo_path).unwrap_or_else(|| repo_path.to_path_buf());
+            let sessions = daemon.list_by_agent(agent, Some(repo))?;
+            let running: Vec<_> = sessions
+                .iter()
+                .filter(|s| s.status == SessionStatus::Running)
+                .collect();
+            if running.is_empty() {
+                println!("No running session for '{}' in this repository", agent);
+            }
+            for session in running {
+                if force {
+                    daemon.kill(&session.id)?;
+                    println!("  Killed session {}", session.id);
+                } else {
+                    let how = daemon.stop(&session.id, Some(grace))?;
+                    println!("  Session {} {}", session.id, how);
+                }
+                stopped = true;
+            }
+        }
+        Err(_) => {
+            println!("No daemon running; agents started without --detach stop when their terminal closes");
+        }
+    }
+
+    audit(repo_path, if force { "kill" } else { "stop" }, Some(agent), serde_json::json!({ "grace_secs": grace, "stopped": stopped }));
+
+    // A stopped agent leaves its task unfinished: hand it back
+    if let Ok(store) = StateStore::open(repo_path)
+        && let Some(record) = store.transition_status(agent, |record| {
+            let to = rembrandt::state::SessionStatus::Stopped;
+            ((stopped || record.status.is_active()) && record.status.can_transition_to(to)).then_some(to)
+        })?
+    {
+        store.touch_heartbeat(agent, Some("stopped"))?;
+        let beads = config.beads.client(repo_path);
+        if let Some(task_id) = &record.task_id
+            && beads.is_available()
+        {
+            beads.update_status(task_id, "open")?;
+            audit(repo_path, "task", Some(agent), serde_json::json!({ "task": task_id, "status": "open" }));
+            println!("  Task {} reopened", task_id);
+        }
+    }
+
+    if config.generate_reports
+        && let Ok(state) = rembrandt::state::StatePool::open(repo_path)
+        && state.get()?.get_session(agent)?.is_some()
+    {
+        let options = rembrandt::report::ReportOptions::from_config(config);
+        let runtime = tokio::runtime::Runtime::new()?;
+        match runtime.block_on(rembrandt::report::generate(repo_path, &state, agent, &options)) {
+            Ok(path) => println!("  Report: {}", path.display()),
+            Err(e) => eprintln!("  Could not write report: {}", e),
+        }
+    }
+    Ok(())
+}
+
+/// A v2 session as printed by `list --json`
+fn session_json(session: &SessionRecord) -> serde_json::Value {
+    serde_json::json!({
+        "agent_id": session.agent_id,
+        "agent_type": session.agent_type,
+        "status": session.status.to_string(),
+        "task_id": session.task_id,
+        "model": session.model,
+        "runtime": session.runtime_kind,
+        "isolation": session.isolation_mode.to_string(),
+        "branch": session.branch_name,
+        "checkout_path": session.checkout_path,
+        "created_at": session.created_at.to_rfc3339(),
+        "updated_at": session.updated_at.to_rfc3339(),
+    })
+}
+
+/// Agents that `--all` applies to: v2 sessions with one of `statuses`, or
+/// the active ones when none are given
+fn batch_targets(repo_path: &Path, statuses: &[rembrandt::state::SessionStatus]) -> Result<Vec<String>> {
+    let store = StateStore::open(repo_path)?;
+    let mut query = if statuses.is_empty() { SessionQuery::active() } else { SessionQuery::default() };
+    query.statuses.extend(statuses.iter().copied());
+    Ok(store.query_sessions(&query)?.into_iter().map(|s| s.agent_id).collect())
+}
+
+/// Run the configured hooks for `point`, recording each run in state.db
+fn run_hooks(
+    config: &rembrandt::config::AppConfig,
+    point: HookPoint,
+    repo_path: &Path,
+    cwd: &Path,
+    agent_id: &str,
+    env: &[(String, String)],
+    store: &StateStore,
+) -> Result<()> {
+    let ctx = HookContext {
+        repo_path,
+        cwd,
+        agent_id,
+        env,
+        store: Some(store),
+    };
+    rembrandt::hooks::run(&config.hooks, point, &ctx)?;
+    Ok(())
+}
+
+/// Remove an agent worktree unless it holds work that exists nowhere else;
+/// with `force`, remove it anyway after snapshotting its uncommitted changes
+fn remove_guarded_worktree(
+    repo_path: &Path,
+    manager: &WorktreeManager,
+    wt: &rembrandt::worktree::WorktreeInfo,
+    force: bool,
+    reason: &str,
+) {
+    print!("  Removing {}... ", wt.agent_id);
+    let at_risk = rembrandt::rescue::check(&wt.path).unwrap_or_default();
+    if !at_risk.is_empty() {
+        if !force {
+            println!("skipped: {} (--force removes it, saving a snapshot)", at_risk.summary());
+            return;
+        }
+        let rescued = StateStore::open(repo_path)
+            .and_then(|store| rembrandt::rescue::rescue(repo_path, &store, &wt.agent_id, &wt.path, reason));
+        match rescued {
+            Ok(Some(snapshot)) => print!(
+                "saved uncommitted work as snapshot #{} (recover: {})... ",
+                snapshot.id,
+                rembrandt::rescue::recovery_hint(&snapshot)
+            ),
+            Ok(None) => {}
+            Err(e) => {
+                println!("failed: could not save its {}: {}", at_risk.summary(), e);
+                return;
+            }
+        }
+    }
+    match manager.remove_worktree(&wt.agent_id) {
+        Ok(_) => {
+            audit(repo_path, "remove_worktree", Some(&wt.agent_id), serde_json::json!({ "reason": reason, "force": force }));
+            println!("done")
+        }
+        Err(e) => println!("failed: {}", e),
+    }
+}
+
+/// Add Rembrandt trailers to the commit message in `message_file`; a
+/// failure is reported but must not stop the commit
+fn commit_trailers(message_file: &Path) {
+    let annotated = std::env::current_dir().map_err(Into::into).and_then(|checkout| {
+        match rembrandt::trailers::provenance(&checkout, |key| std::env::var(key).ok())? {
+            Some(provenance) => rembrandt::trailers::annotate(&checkout, message_file, &provenance),
+            None => Ok(()),
+        }
+    });
+    if let Err(e) = annotated {
+        eprintln!("rembrandt: commit trailers not added: {}", e);
+    }
+}
+
+/// Remove session logs past `log_retention_days`/`log_retention_mb`
+fn prune_session_logs(repo_path: &Path, config: &rembrandt::config::AppConfig) {
+    let repo = main_repo_root(repo_path).unwrap_or_else(|| repo_path.to_path_buf());
+    match rembrandt::daemon::logs::prune(&repo, config) {
+        Ok(removed) if !removed.is_empty() => println!("Removed {} old session log(s)", removed.len()),
+        Ok(_) => {}
+        Err(e) => eprintln!("Skipped session log cleanup: {}", e),
+    }
+}
+
+/// Record a mutation made from the command line in the audit log
+fn audit(repo_path: &Path, action: &str, target: Option<&str>, params: serde_json::Value) {
+    rembrandt::audit::record(repo_path, AuditSource::Cli, action, target, params);
+}
+
+/// Pause (SIGSTOP) or resume (SIGCONT) an agent's running daemon sessions
+/// and record the change in state.db
+fn pause_or_resume(repo_path: &Path, agent: &str, pause: bool) -> Result<()> {
+    use rembrandt::state::SessionStatus as StoredStatus;
+
+    let action = if pause { "pause" } else { "resume" };
+    let mut daemon = DaemonConnection::connect(&default_socket_path()).map_err(|_| {
+        rembrandt::RembrandtError::Daemon(format!("no daemon running; only detached agents can {}", action))
+    })?;
+    let repo = main_repo_root(repo_path).unwrap_or_else(|| repo_path.to_path_buf());
+    let sessions = daemon.list_by_agent(agent, Some(repo))?;
+    let running: Vec<_> = sessions
+        .iter()
+        .filter(|s| s.status == SessionStatus::Running && s.paused != pause)
+        .collect();
+    if running.is_empty() {
+        println!(
+            "No {} session for '{}' in this repository",
+            if pause { "running" } else { "paused" },
+            agent
+        );
+        return Ok(());
+    }
+    for session in running {
+        if pause {
+            daemon.pause(&session.id)?;
+            println!("  Paused session {}", session.id);
+        } else {
+            daemon.resume(&session.id)?;
+            println!("  Resumed session {}", session.id);
+        }
+    }
+
+    if let Ok(store) = StateStore::open(repo_path)
+        && store
+            .transition_status(agent, |record| {
+                if pause {
+                    let to = StoredStatus::Paused;
+                    record.status.can_transition_to(to).then_some(to)
+                } else {
+                    (record.status == StoredStatus::Paused).then_some(StoredStatus::Active)
+                }
+            })?
+            .is_some()
+    {
+        store.touch_heartbeat(agent, Some(if pause { "paused" } else { "resumed" }))?;
+    }
+    audit(repo_path, action, Some(agent), serde_json::Value::Null);
+    Ok(())
+}
+
+/// Print daemon sessions grouped by repository, marking the current one;
+/// `verbose` adds each running agent's latest CPU and memory sample
+fn print_daemon_sessions(sessions: &[SessionInfo], repo_path: &Path, verbose: bool) {
+    let current = main_repo_root(repo_path);
+    let mut by_repo: BTreeMap<Option<&PathBuf>, Vec<&SessionInfo>> = BTreeMap::new();
+    for session in sessions {
+        by_repo.entry(session.repo.as_ref()).or_default().push(session);
+    }
+
+    println!("Daemon sessions:");
+    for (repo, sessions) in by_repo {
+        let label = repo.map_or("(no repo)".to_string(), |r| r.display().to_string());
+        let marker = if repo.is_some() && repo == current.as_ref() { " (current)" } else { "" };
+        println!("  {}{}", label, marker);
+        for session in sessions {
+            let status = match &session.status {
+                SessionStatus::Running => "active".to_string(),
+                SessionStatus::Exited(0) => "done".to_string(),
+                SessionStatus::Exited(code) => format!("failed ({})", code),
+                SessionStatus::Failed(_) => "error".to_string(),
+            };
+            println!("    {} [{}] {}", session.agent_id, status, session.command);
+            if verbose && let Some(sample) = &session.resources {
+                println!(
+                    "      {} (peak {})",
+                    sample,
+                    rembrandt::process::format_bytes(session.peak_rss_bytes)
+                );
+            }
+        }
+    }
+}
+
+/// How often `rembrandt compete` checks on its competitors
+const COMPETE_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Run a competitor's agent in the daemon with `prompt` as its first input;
+/// returns the session id
+fn spawn_competitor(
+    config: &rembrandt::config::AppConfig,
+    daemon: &mut DaemonConnection,
+    competitor: &rembrandt::competition::CompetitorSolution,
+    prompt: &str,
+) -> Result<String> {
+    let agent_type = &competitor.agent_type;
+    let mut args: Vec<String> = agent_type.default_args().iter().map(|a| a.to_string()).collect();
+    if let Some(model) = &competitor.variant.model {
+        args.extend(config.model_args(agent_type, model)?);
+    }
+    if let Some(temperature) = competitor.variant.temperature {
+        args.extend(config.temperature_args(agent_type, temperature)?);
+    }
+    let env = config.spawn_env(None, &[], &competitor.agent_id, &competitor.branch, None);
+    let (cols, rows) = crossterm::terminal::size().unwrap_or((80, 24));
+    let session_id = daemon.spawn(
+        competitor.agent_id.clone(),
+        agent_type.command().to_string(),
+        args,
+        env,
+        competitor.worktree_path.clone(),
+        Some(rows),
+        Some(cols),
+        config.buffer_capacity,
+        rembrandt::restart::RestartPolicy::Never,
+    )?;
+    wait_for_first_screen(|| daemon.output_end(&session_id))?;
+    daemon.write(&session_id, format!("{}\n", prompt).into_bytes())?;
+    Ok(session_id)
+}
+
+/// Count a finished competition in the daemon's metrics; competitions still
+/// waiting on a merge aren't counted yet
+fn report_competition(daemon: &mut DaemonConnection, competition: &rembrandt::competition::CompetitionGroup) {
+    let Some(outcome) = competition.status.outcome() else {
+        return;
+    };
+    let validation_ms = competition
+        .competitors
+        .iter()
+        .filter_map(|c| c.validation.as_ref().map(|v| v.validation_time_ms))
+        .collect();
+    if let Err(e) = daemon.record_competition(outcome, validation_ms) {
+        eprintln!("Warning: could not report the competition to the daemon's metrics: {}", e);
+    }
+}
+
+/// One line on where a competition has got to
+fn competition_status_line(status: &rembrandt::competition::CompetitionStatus) -> String {
+    use rembrandt::competition::CompetitionStatus;
+    match status {
+        CompetitionStatus::Spawning => "Spawning agents...".to_string(),
+        CompetitionStatus::Running { completed, total } => format!("Running: {}/{} agents done", completed, total),
+        CompetitionStatus::Evaluating => "Evaluating solutions...".to_string(),
+        CompetitionStatus::Merging => "Winner picked".to_string(),
+        CompetitionStatus::Completed { winner_id } => format!("Completed: {} won", winner_id),
+        CompetitionStatus::Failed(reason) => format!("Failed: {}", reason),
+        CompetitionStatus::Cancelled => "Cancelled".to_string(),
+    }
+}
+
+/// `rembrandt compete history`: finished competitions, newest first, then
+/// win rates per agent type overall and per kind of task
+fn print_competition_history(repo_path: &Path, limit: usize) -> Result<()> {
+    use rembrandt::competition::{history_stats, load_history, CompetitionStatus};
+
+    let history = load_history(&repo_path.join(".rembrandt"))?;
+    if history.is_empty() {
+        println!("No finished competitions yet");
+        return Ok(());
+    }
+
+    let store = StateStore::open(repo_path).ok();
+    for competition in history.iter().take(limit) {
+        let status = match &competition.status {
+            CompetitionStatus::Completed { .. } => "completed",
+            CompetitionStatus::Failed(_) => "failed",
+            CompetitionStatus::Cancelled => "cancelled",
+            _ => "unfinished",
+        };
+        let winner = match competition.winning_solution() {
+            Some(w) if w.variant.is_default() => w.agent_type.to_string(),
+            Some(w) => format!("{} ({})", w.agent_type, w.variant),
+            None => "-".to_string(),
+        };
+        let score = competition
+            .winner_score()
+            .map_or_else(|| "-".to_string(), |score| format!("{:.2}", score));
+        let duration = competition
+            .duration()
+            .map_or_else(|| "-".to_string(), rembrandt::tui::App::format_duration);
+        let mut cost = 0.0;
+        let mut tracked = false;
+        for competitor in &competition.competitors {
+            if let Some(usage) = store.as_ref().and_then(|s| s.usage_for(&competitor.agent_id).ok())
+                && usage.samples > 0
+            {
+                cost += usage.cost_usd;
+                tracked = true;
+            }
+        }
+        let cost = if tracked { format!("${:.2}", cost) } else { "-".to_string() };
+        let mut prompt: String = competition.prompt.lines().next().unwrap_or_default().to_string();
+        if prompt.chars().count() > 50 {
+            prompt = format!("{}…", prompt.chars().take(49).collect::<String>());
+        }
+        println!(
+            "  {} {} [{}] winner {} score {} {} {} \"{}\"",
+            competition.id,
+            competition.started_at.format("%Y-%m-%d %H:%M"),
+            status,
+            winner,
+            score,
+            duration,
+            cost,
+            prompt
+        );
+    }
+    if history.len() > limit {
+        println!("  ... showing {} of {}", limit, history.len());
+    }
+
+    let stats = history_stats(&history);
+    if stats.decided == 0 {
+        return Ok(());
+    }
+    println!();
+    println!("Wins ({} of {} competitions decided):", stats.decided, stats.total);
+    for record in &stats.agents {
+        println!(
+            "  {} {}/{} ({:.0}%)",
+            record.agent,
+            record.wins,
+            record.entered,
+            record.win_rate() * 100.0
+        );
+    }
+    println!();
+    println!("By task:");
+    for (kind, records) in &stats.by_kind {
+        for record in records.iter().filter(|r| r.wins > 0) {
+            println!(
+                "  {} wins {:.0}% of {} tasks ({}/{})",
+                record.agent,
+                record.win_rate() * 100.0,
+                kind,
+                record.wins,
+                record.entered
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Print v2 sessions matching `query`, with a per-status tally
+///
+/// With `usage_config` (the `--verbose` listing), each line also shows the
+/// session's cumulative cost and tokens, flagged past `usage_alert_usd`.
+fn print_v2_sessions(
+    store: &StateStore,
+    repo_path: &Path,
+    query: &SessionQuery,
+    usage_config: Option<&rembrandt::config::AppConfig>,
+) -> Result<()> {
+    let sessions = store.query_sessions(query)?;
+    if sessions.is_empty() {
+        println!("  (none)");
+    }
+    for session in &sessions {
+        let usage = match usage_config {
+            Some(config) => {
+                let usage = store.usage_for(&session.agent_id)?;
+                let alert = match config.usage_alert_usd {
+                    Some(limit) if usage.cost_usd > limit => format!(" ⚠ over ${:.2}", limit),
+                    _ => String::new(),
+                };
+                format!("  {}{}", usage.short(), alert)
+            }
+            None => String::new(),
+        };
+        let model = match (&session.model, usage_config) {
+            (Some(model), Some(_)) => format!("  model {}", model),
+            _ => String::new(),
+        };
+        println!(
+            "  {} [{}] {} {}{}{}",
+            session.agent_id, session.status, session.isolation_mode, session.branch_name, model, usage
+        );
+        if usage_config.is_some() {
+            let report = rembrandt::report::report_path(repo_path, &session.agent_id);
+            if report.exists() {
+                println!("    report: {}", report.display());
+            }
+        }
+    }
+
+    let total = store.count_sessions(query)?;
+    if total > sessions.len() {
+        println!("  ... showing {} of {}", sessions.len(), total);
+    }
+    let counts: Vec<String> = store
+        .count_by_status()?
+        .into_iter()
+        .map(|(status, n)| format!("{} {}", n, status))
+        .collect();
+    if !counts.is_empty() {
+        println!("  ({})", counts.join(", "));
+    }
+    Ok(())
+}
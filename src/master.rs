@@ -0,0 +1,294 @@
+//! Master/apprentice mode: one agent oversees the others.
+//!
+//! Each pass types a digest of the apprentices into the master's session:
+//! their status, the files changed on their branch, open attention items
+//! and questions from their recent output. The master answers with
+//! `STEER <apprentice>: <message>` lines, which are forwarded to that
+//! apprentice as steers. A digest is only sent when it changed, so an idle
+//! team doesn't flood the master.
+
+use crate::csi::SessionProbe;
+use crate::state::{SessionRecord, StateStore};
+use crate::timeline::{one_line, strip_ansi, TimelineKind};
+use crate::worktree::WorktreeManager;
+use crate::Result;
+use std::path::{Path, PathBuf};
+
+/// Prefix of the reply lines that become steers
+pub const STEER_PREFIX: &str = "STEER ";
+
+/// Most questions quoted per apprentice
+const MAX_QUESTIONS: usize = 2;
+
+/// Most changed files named per apprentice
+const MAX_FILES: usize = 5;
+
+/// What the master is told about one apprentice
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApprenticeDigest {
+    pub agent_id: String,
+    pub status: String,
+    pub task_id: Option<String>,
+    pub files: Vec<String>,
+    pub additions: usize,
+    pub deletions: usize,
+    pub attention: Vec<String>,
+    pub questions: Vec<String>,
+}
+
+impl ApprenticeDigest {
+    fn line(&self) -> String {
+        let mut line = format!("{} ({}", self.agent_id, self.status);
+        if let Some(task) = &self.task_id {
+            line.push_str(&format!(", task {}", task));
+        }
+        line.push(')');
+        if self.files.is_empty() {
+            line.push_str(": no changes yet");
+        } else {
+            let mut named: Vec<&str> = self.files.iter().take(MAX_FILES).map(String::as_str).collect();
+            let more = self.files.len().saturating_sub(MAX_FILES);
+            let more = format!("{} more", more);
+            if self.files.len() > MAX_FILES {
+                named.push(&more);
+            }
+            line.push_str(&format!(
+                ": {} file(s) +{}/-{} ({})",
+                self.files.len(),
+                self.additions,
+                self.deletions,
+                named.join(", ")
+            ));
+        }
+        for item in &self.attention {
+            line.push_str(&format!("; needs attention: {}", item));
+        }
+        for question in &self.questions {
+            line.push_str(&format!("; asks: \"{}\"", question));
+        }
+        line
+    }
+}
+
+/// Digest text typed into the master's session
+///
+/// One line, since a newline would submit a partial message to most agents.
+pub fn render_digest(apprentices: &[ApprenticeDigest]) -> String {
+    let body: Vec<String> = apprentices.iter().map(ApprenticeDigest::line).collect();
+    format!(
+        "[rembrandt digest] {}. To direct an apprentice, reply with one line per instruction: \
+         {}<agent>: <message>",
+        body.join(" | "),
+        STEER_PREFIX
+    )
+}
+
+/// `(apprentice, message)` pairs from the master's output
+pub fn parse_steers(output: &str, apprentices: &[String]) -> Vec<(String, String)> {
+    output
+        .lines()
+        .map(strip_ansi)
+        .filter_map(|line| {
+            let rest = line.trim().trim_start_matches(['⏺', '●', '*', '-', ' ']).strip_prefix(STEER_PREFIX)?;
+            let (agent, message) = rest.split_once(':')?;
+            let agent = agent.trim();
+            let message = message.trim();
+            (apprentices.iter().any(|a| a == agent) && !message.is_empty())
+                .then(|| (agent.to_string(), message.to_string()))
+        })
+        .collect()
+}
+
+/// Lines of recent output that read like questions to a human
+fn questions(output: &[u8]) -> Vec<String> {
+    let text = String::from_utf8_lossy(output);
+    let mut found: Vec<String> = text
+        .lines()
+        .map(|line| strip_ansi(line).trim().to_string())
+        .filter(|line| line.ends_with('?') && line.len() > 3 && !line.contains(STEER_PREFIX))
+        .map(|line| one_line(&line, 160))
+        .collect();
+    found.dedup();
+    let skip = found.len().saturating_sub(MAX_QUESTIONS);
+    found.split_off(skip)
+}
+
+/// Outcome of one pass
+#[derive(Debug, Clone, Default)]
+pub struct MasterPass {
+    /// Steers forwarded to apprentices
+    pub steers: Vec<(String, String)>,
+    /// The digest sent to the master, if one was
+    pub digest: Option<String>,
+}
+
+/// Runs master passes, remembering what has been sent and read
+pub struct MasterLoop {
+    repo_path: PathBuf,
+    master: String,
+    /// Apprentices named up front; empty to take every other active session
+    apprentices: Vec<String>,
+    base_branch: String,
+    /// Stream offset of the master's output read so far
+    cursor: Option<usize>,
+    last_digest: Option<String>,
+}
+
+impl MasterLoop {
+    pub fn new(repo_path: &Path, master: &str, apprentices: Vec<String>, base_branch: &str) -> Self {
+        Self {
+            repo_path: repo_path.to_path_buf(),
+            master: master.to_string(),
+            apprentices,
+            base_branch: base_branch.to_string(),
+            cursor: None,
+            last_digest: None,
+        }
+    }
+
+    /// Forward the master's new steers, then send a digest if anything changed
+    ///
+    /// Output the master wrote before the first pass is skipped.
+    pub fn run_pass(&mut self, store: &StateStore, probe: &mut dyn SessionProbe) -> Result<MasterPass> {
+        let mut pass = MasterPass::default();
+        let sessions: Vec<SessionRecord> = store
+            .list_sessions()?
+            .into_iter()
+            .filter(|s| s.agent_id != self.master)
+            .filter(|s| {
+                if self.apprentices.is_empty() {
+                    s.status.is_active()
+                } else {
+                    self.apprentices.contains(&s.agent_id)
+                }
+            })
+            .collect();
+        let names: Vec<String> = if self.apprentices.is_empty() {
+            sessions.iter().map(|s| s.agent_id.clone()).collect()
+        } else {
+            self.apprentices.clone()
+        };
+
+        if let Some((output, start)) = probe.recent_output(&self.master)? {
+            let end = start + output.len();
+            if let Some(cursor) = self.cursor {
+                let from = cursor.saturating_sub(start).min(output.len());
+                for (agent_id, message) in parse_steers(&String::from_utf8_lossy(&output[from..]), &names) {
+                    probe.send_message(&agent_id, &message)?;
+                    store.record_timeline(&agent_id, TimelineKind::Steer, Some(&one_line(&message, 80)))?;
+                    pass.steers.push((agent_id, message));
+                }
+            }
+            self.cursor = Some(end);
+        }
+
+        let manager = WorktreeManager::new(&self.repo_path)?;
+        let attention = store.open_attention()?;
+        let mut digests = Vec::new();
+        for name in &names {
+            let session = sessions.iter().find(|s| &s.agent_id == name);
+            let diff = session
+                .and_then(|s| manager.branch_diff(&s.branch_name, &self.base_branch).ok())
+                .unwrap_or_default();
+            let output = probe.recent_output(name)?.map(|(output, _)| output).unwrap_or_default();
+            digests.push(ApprenticeDigest {
+                agent_id: name.clone(),
+                status: session.map_or_else(|| "unknown".to_string(), |s| s.status.to_string()),
+                task_id: session.and_then(|s| s.task_id.clone()),
+                files: diff.iter().map(|f| f.path.clone()).collect(),
+                additions: diff.iter().map(|f| f.additions).sum(),
+                deletions: diff.iter().map(|f| f.deletions).sum(),
+                attention: attention
+                    .iter()
+                    .filter(|item| item.agent_id.as_deref() == Some(name.as_str()))
+                    .map(|item| format!("{}: {}", item.kind, item.message))
+                    .collect(),
+                questions: questions(&output),
+            });
+        }
+
+        if digests.is_empty() {
+            return Ok(pass);
+        }
+        let digest = render_digest(&digests);
+        if self.last_digest.as_ref() != Some(&digest) {
+            probe.send_message(&self.master, &digest)?;
+            store.record_timeline(
+                &self.master,
+                TimelineKind::Steer,
+                Some(&format!("digest of {} apprentice(s)", digests.len())),
+            )?;
+            self.last_digest = Some(digest.clone());
+            pass.digest = Some(digest);
+        }
+        Ok(pass)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::session_record;
+    use std::collections::HashMap;
+
+    #[derive(Default)]
+    struct FakeProbe {
+        output: HashMap<String, Vec<u8>>,
+        sent: Vec<(String, String)>,
+    }
+
+    impl SessionProbe for FakeProbe {
+        fn output_bytes(&mut self, agent_id: &str) -> Result<Option<usize>> {
+            Ok(self.output.get(agent_id).map(Vec::len))
+        }
+
+        fn recent_output(&mut self, agent_id: &str) -> Result<Option<(Vec<u8>, usize)>> {
+            Ok(self.output.get(agent_id).map(|output| (output.clone(), 0)))
+        }
+
+        fn nudge(&mut self, _agent_id: &str) -> Result<String> {
+            Ok("enter".to_string())
+        }
+
+        fn send_message(&mut self, agent_id: &str, message: &str) -> Result<()> {
+            self.sent.push((agent_id.to_string(), message.to_string()));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn digests_go_to_the_master_and_its_steers_to_apprentices() {
+        let dir = tempfile::tempdir().unwrap();
+        git2::Repository::init(dir.path()).unwrap();
+        let store = StateStore::open(dir.path()).unwrap();
+        for agent in ["lead", "a1", "a2"] {
+            store.upsert_session(&session_record(agent)).unwrap();
+        }
+        store.raise_attention(Some("a2"), "blocked", "needs a token").unwrap();
+
+        let mut probe = FakeProbe::default();
+        probe.output.insert("lead".to_string(), b"STEER a1: old advice\n".to_vec());
+        probe.output.insert("a1".to_string(), b"\x1b[1mShould I drop the v1 API?\x1b[0m\n".to_vec());
+        let mut master = MasterLoop::new(dir.path(), "lead", Vec::new(), "main");
+
+        let pass = master.run_pass(&store, &mut probe).unwrap();
+        assert!(pass.steers.is_empty(), "output before the first pass is skipped");
+        let digest = pass.digest.unwrap();
+        assert!(digest.contains("a1 (active): no changes yet; asks: \"Should I drop the v1 API?\""), "{}", digest);
+        assert!(digest.contains("a2 (active): no changes yet; needs attention: blocked: needs a token"), "{}", digest);
+        assert!(!digest.contains("lead ("));
+        assert_eq!(probe.sent.len(), 1);
+
+        probe
+            .output
+            .get_mut("lead")
+            .unwrap()
+            .extend_from_slice(b"\xe2\x8f\xba STEER a1: keep v1, deprecate it\nSTEER a9: nobody\n");
+        let pass = master.run_pass(&store, &mut probe).unwrap();
+        assert_eq!(pass.steers, vec![("a1".to_string(), "keep v1, deprecate it".to_string())]);
+        assert!(pass.digest.is_none(), "nothing changed");
+        assert_eq!(probe.sent.last().unwrap(), &("a1".to_string(), "keep v1, deprecate it".to_string()));
+        let timeline = store.timeline("a1").unwrap();
+        assert_eq!(timeline.last().unwrap().kind, TimelineKind::Steer);
+    }
+}
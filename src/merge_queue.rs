@@ -0,0 +1,407 @@
+//! Merge queue serializing merges from many agents into the base branch.
+//!
+//! Agents that finish at about the same time all branched from an older
+//! base; merging them in whatever order they land tends to conflict. Queued
+//! branches are instead taken one at a time: rebased onto the current base,
+//! revalidated, and merged, so each one is checked against everything merged
+//! before it. Branches whose rebase conflicts are flagged for a human
+//! (`merge-conflict` attention items); branches whose merge fails after a
+//! clean rebase (the base moved underneath) go to the back of the queue.
+//! `pre_merge` hooks run in the checkout right before its merge and can
+//! veto it; `post_merge` hooks run in the repository after it.
+
+use crate::agent::AgentType;
+use crate::competition::{CompetitorSolution, SolutionValidator};
+use crate::config::HooksConfig;
+use crate::hooks::{self, HookContext, HookPoint};
+use crate::state::StateStore;
+use crate::sync::{update_checkout, SyncMode, Update};
+use crate::worktree::WorktreeManager;
+use crate::{RembrandtError, Result};
+use chrono::{DateTime, Utc};
+use std::path::{Path, PathBuf};
+
+/// Attention kind raised for a branch that needs a manual rebase
+pub const MERGE_CONFLICT: &str = "merge-conflict";
+
+/// Merge attempts before a requeued branch is flagged instead
+pub const MAX_MERGE_ATTEMPTS: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeStatus {
+    Queued,
+    Rebasing,
+    Validating,
+    Merged,
+    /// Needs a human: the rebase conflicted or merging kept failing
+    Conflicted,
+    /// Validation failed after rebasing, or the checkout is unusable
+    Failed,
+}
+
+impl MergeStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MergeStatus::Queued => "queued",
+            MergeStatus::Rebasing => "rebasing",
+            MergeStatus::Validating => "validating",
+            MergeStatus::Merged => "merged",
+            MergeStatus::Conflicted => "conflicted",
+            MergeStatus::Failed => "failed",
+        }
+    }
+
+    /// Still being worked on by a queue run
+    pub fn is_in_progress(self) -> bool {
+        matches!(self, MergeStatus::Rebasing | MergeStatus::Validating)
+    }
+}
+
+impl std::str::FromStr for MergeStatus {
+    type Err = RembrandtError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "queued" => Ok(MergeStatus::Queued),
+            "rebasing" => Ok(MergeStatus::Rebasing),
+            "validating" => Ok(MergeStatus::Validating),
+            "merged" => Ok(MergeStatus::Merged),
+            "conflicted" => Ok(MergeStatus::Conflicted),
+            "failed" => Ok(MergeStatus::Failed),
+            other => Err(RembrandtError::State(format!(
+                "unknown merge status '{}'",
+                other
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for MergeStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A branch waiting in (or processed by) the merge queue
+#[derive(Debug, Clone)]
+pub struct MergeQueueEntry {
+    pub id: i64,
+    pub agent_id: String,
+    pub branch_name: String,
+    pub checkout_path: PathBuf,
+    pub status: MergeStatus,
+    /// Failed merges after a clean rebase so far
+    pub attempts: u32,
+    /// Why the entry is in its current status
+    pub detail: Option<String>,
+    pub enqueued_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Queue an agent's branch for merging; returns the entry id
+pub fn enqueue(store: &StateStore, agent_id: &str) -> Result<i64> {
+    let session = store
+        .get_session(agent_id)?
+        .ok_or_else(|| RembrandtError::SessionNotFound(agent_id.to_string()))?;
+    store.enqueue_merge(agent_id, &session.branch_name, &session.checkout_path)
+}
+
+/// Work through the queue until no branch is waiting
+///
+/// Returns every entry taken off the queue, in the order they were handled,
+/// with the status each ended up in. With `validate` off, branches are
+/// merged right after a clean rebase.
+#[tracing::instrument(skip(store, validator, hooks))]
+pub async fn run(
+    repo_path: &Path,
+    base_branch: &str,
+    store: &StateStore,
+    validate: bool,
+    validator: &SolutionValidator,
+    hooks: &HooksConfig,
+) -> Result<Vec<MergeQueueEntry>> {
+    // A run that died mid-merge leaves its entry in progress; retry it
+    for entry in store.merge_queue()? {
+        if entry.status.is_in_progress() {
+            store.requeue_merge(entry.id, "interrupted")?;
+        }
+    }
+
+    let worktrees = WorktreeManager::new(repo_path)?;
+    let mut handled = Vec::new();
+
+    while let Some(entry) = store.next_queued_merge()? {
+        handled.push(take(entry, base_branch, store, &worktrees, validator, validate, hooks).await?);
+    }
+
+    Ok(handled)
+}
+
+/// Queue an agent's branch and merge it right away, ahead of anything else
+/// waiting; returns the entry with the status it ended up in
+///
+/// Refused while a resolution worktree's rebase is unfinished.
+#[tracing::instrument(skip(store, validator, hooks))]
+pub async fn merge_now(
+    repo_path: &Path,
+    base_branch: &str,
+    store: &StateStore,
+    agent_id: &str,
+    validate: bool,
+    validator: &SolutionValidator,
+    hooks: &HooksConfig,
+) -> Result<MergeQueueEntry> {
+    // Merging would abort a resolution's unfinished rebase
+    if let Some(resolution) = store.open_resolution(agent_id)?
+        && crate::resolution::unfinished(&resolution)?
+    {
+        return Err(RembrandtError::Conflict(format!(
+            "the rebase in {} isn't finished; resolve the conflicts and run `git rebase --continue` there first",
+            resolution.checkout_path.display()
+        )));
+    }
+    let id = enqueue(store, agent_id)?;
+    let entry = store
+        .merge_queue()?
+        .into_iter()
+        .find(|entry| entry.id == id)
+        .ok_or_else(|| RembrandtError::State(format!("merge queue entry {} vanished", id)))?;
+    let worktrees = WorktreeManager::new(repo_path)?;
+    take(entry, base_branch, store, &worktrees, validator, validate, hooks).await
+}
+
+/// Process one entry and record the outcome
+async fn take(
+    entry: MergeQueueEntry,
+    base_branch: &str,
+    store: &StateStore,
+    worktrees: &WorktreeManager,
+    validator: &SolutionValidator,
+    validate: bool,
+    hooks: &HooksConfig,
+) -> Result<MergeQueueEntry> {
+    let (status, detail) = process(&entry, base_branch, store, worktrees, validator, validate, hooks).await?;
+    match status {
+        MergeStatus::Queued => store.requeue_merge(entry.id, &detail)?,
+        status => {
+            store.update_merge(entry.id, status, Some(&detail))?;
+            if status == MergeStatus::Conflicted {
+                store.raise_attention(Some(&entry.agent_id), MERGE_CONFLICT, &detail)?;
+            }
+        }
+    }
+    Ok(MergeQueueEntry {
+        status,
+        detail: Some(detail),
+        ..entry
+    })
+}
+
+/// Rebase, validate and merge one entry; returns its new status and why
+async fn process(
+    entry: &MergeQueueEntry,
+    base_branch: &str,
+    store: &StateStore,
+    worktrees: &WorktreeManager,
+    validator: &SolutionValidator,
+    validate: bool,
+    hooks: &HooksConfig,
+) -> Result<(MergeStatus, String)> {
+    if !entry.checkout_path.exists() {
+        return Ok((MergeStatus::Failed, "checkout no longer exists".to_string()));
+    }
+
+    store.update_merge(entry.id, MergeStatus::Rebasing, None)?;
+    match update_checkout(&entry.checkout_path, base_branch, SyncMode::Rebase)? {
+        Update::Clean => {}
+        Update::Conflict(message) => {
+            return Ok((
+                MergeStatus::Conflicted,
+                format!("rebase onto {} conflicts: {}", base_branch, message),
+            ));
+        }
+        Update::Refused(message) => {
+            return Ok((MergeStatus::Failed, format!("could not rebase: {}", message)));
+        }
+    }
+
+    let mut lint_warnings = Vec::new();
+    if validate {
+        store.update_merge(entry.id, MergeStatus::Validating, None)?;
+        let solution = CompetitorSolution {
+            agent_id: entry.agent_id.clone(),
+            agent_type: AgentType::Custom("merge-queue".to_string()),
+            variant: Default::default(),
+            branch: entry.branch_name.clone(),
+            worktree_path: entry.checkout_path.clone(),
+            completed_at: None,
+            validation: None,
+            diff_stats: None,
+        };
+        let validation = validator.validate(&solution).await?;
+        if !validation.is_valid() {
+            let summary = validation.failure_summary();
+            let first = summary.lines().next().unwrap_or("validation failed");
+            return Ok((MergeStatus::Failed, first.trim_end_matches(':').to_string()));
+        }
+        lint_warnings = validation.lint_warnings().map(|lint| lint.name.clone()).collect();
+    }
+
+    let task_id = store.get_session(&entry.agent_id)?.and_then(|s| s.task_id);
+    let mut env = crate::agent::context_env(&entry.agent_id, &entry.branch_name, task_id.as_deref());
+    env.push(("REMBRANDT_BASE_BRANCH".to_string(), base_branch.to_string()));
+    let repo_path = worktrees.repo_path();
+    let run = |point, cwd| run_hooks(hooks, point, repo_path, cwd, &entry.agent_id, &env);
+    if let Err(e) = run(HookPoint::PreMerge, &entry.checkout_path).await {
+        return Ok((MergeStatus::Failed, e.to_string()));
+    }
+
+    match worktrees.merge_branch(&entry.branch_name, base_branch) {
+        Ok(tip) => {
+            run(HookPoint::PostMerge, repo_path).await?;
+            let mut detail = format!("merged at {}", &tip.to_string()[..7]);
+            if !lint_warnings.is_empty() {
+                detail.push_str(&format!(" (lint warnings: {})", lint_warnings.join(", ")));
+            }
+            Ok((MergeStatus::Merged, detail))
+        }
+        Err(e) if entry.attempts + 1 < MAX_MERGE_ATTEMPTS => {
+            Ok((MergeStatus::Queued, format!("merge failed, requeued: {}", e)))
+        }
+        Err(e) => Ok((
+            MergeStatus::Conflicted,
+            format!("merge failed {} times: {}", MAX_MERGE_ATTEMPTS, e),
+        )),
+    }
+}
+
+/// Run the hooks for `point` on the blocking pool, recording them in the
+/// repository's state.db
+async fn run_hooks(
+    hooks: &HooksConfig,
+    point: HookPoint,
+    repo_path: &Path,
+    cwd: &Path,
+    agent_id: &str,
+    env: &[(String, String)],
+) -> Result<()> {
+    if hooks.commands(point).is_empty() {
+        return Ok(());
+    }
+    let hooks = hooks.clone();
+    let (repo_path, cwd) = (repo_path.to_path_buf(), cwd.to_path_buf());
+    let (agent_id, env) = (agent_id.to_string(), env.to_vec());
+    tokio::task::spawn_blocking(move || {
+        let store = StateStore::open(&repo_path)?;
+        let ctx = HookContext {
+            repo_path: &repo_path,
+            cwd: &cwd,
+            agent_id: &agent_id,
+            env: &env,
+            store: Some(&store),
+        };
+        hooks::run(&hooks, point, &ctx).map(|_| ())
+    })
+    .await
+    .map_err(|e| RembrandtError::Runtime(format!("{} hooks failed to run: {}", point, e)))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{git, init_repo, session_record};
+    use crate::competition::{LintCheck, LintSeverity};
+    use crate::isolation::IsolationMode;
+    use crate::state::{SessionRecord, SessionStatus};
+
+    fn agent_commits(store: &StateStore, worktrees: &WorktreeManager, agent_id: &str, file: &str, contents: &str) {
+        let worktree = worktrees.create_worktree(agent_id, "main").unwrap();
+        std::fs::write(worktree.path.join(file), contents).unwrap();
+        git(&worktree.path, &["add", "."]);
+        git(&worktree.path, &["commit", "-m", agent_id]);
+        store
+            .upsert_session(&SessionRecord {
+                isolation_mode: IsolationMode::Worktree,
+                branch_name: worktree.branch.clone(),
+                checkout_path: worktree.path.clone(),
+                status: SessionStatus::Completed,
+                ..session_record(agent_id)
+            })
+            .unwrap();
+        enqueue(store, agent_id).unwrap();
+    }
+
+    #[tokio::test]
+    async fn merges_in_order_and_flags_conflicts() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = init_repo(dir.path());
+
+        let store = StateStore::open(dir.path()).unwrap();
+        let worktrees = WorktreeManager::new(dir.path()).unwrap();
+        agent_commits(&store, &worktrees, "a", "shared.txt", "from a\n");
+        agent_commits(&store, &worktrees, "b", "b.txt", "from b\n");
+        agent_commits(&store, &worktrees, "c", "shared.txt", "from c\n");
+        // Queueing again keeps the original place
+        enqueue(&store, "a").unwrap();
+        assert_eq!(store.merge_queue().unwrap().len(), 3);
+
+        let log = tempfile::tempdir().unwrap();
+        let hooks = crate::config::AppConfig::from_toml(&format!(
+            "[hooks]\npost_merge = [\"echo $REMBRANDT_AGENT_ID >> {}/merged\"]",
+            log.path().display()
+        ))
+        .unwrap()
+        .hooks;
+        let validator = SolutionValidator::new("main".to_string())
+            .with_lint_checks(vec![LintCheck::new("style", LintSeverity::Warn).with_command("false")]);
+        let handled = run(dir.path(), "main", &store, true, &validator, &hooks).await.unwrap();
+        let outcome: Vec<(&str, MergeStatus)> =
+            handled.iter().map(|e| (e.agent_id.as_str(), e.status)).collect();
+        assert_eq!(
+            outcome,
+            [
+                ("a", MergeStatus::Merged),
+                ("b", MergeStatus::Merged),
+                ("c", MergeStatus::Conflicted),
+            ]
+        );
+        // Warning-only lint failures don't stop a merge
+        assert!(handled[0].detail.as_deref().unwrap().ends_with("(lint warnings: style)"));
+
+        // b was rebased onto a, so main has both without a merge commit
+        let main = repo.find_branch("main", git2::BranchType::Local).unwrap();
+        let tip = main.get().peel_to_commit().unwrap();
+        assert_eq!(tip.parent_count(), 1);
+        for name in ["shared.txt", "b.txt"] {
+            assert!(tip.tree().unwrap().get_name(name).is_some());
+        }
+        let merged = std::fs::read_to_string(log.path().join("merged")).unwrap();
+        assert_eq!(merged, "a\nb\n");
+
+        let attention = store.open_attention().unwrap();
+        assert_eq!(attention.len(), 1);
+        assert_eq!(attention[0].kind, MERGE_CONFLICT);
+        assert_eq!(attention[0].agent_id.as_deref(), Some("c"));
+        assert!(store.next_queued_merge().unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn merge_now_jumps_the_queue() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = init_repo(dir.path());
+
+        let store = StateStore::open(dir.path()).unwrap();
+        let worktrees = WorktreeManager::new(dir.path()).unwrap();
+        agent_commits(&store, &worktrees, "a", "a.txt", "from a\n");
+        agent_commits(&store, &worktrees, "b", "b.txt", "from b\n");
+
+        let validator = SolutionValidator::new("main".to_string());
+        let entry = merge_now(dir.path(), "main", &store, "b", false, &validator, &HooksConfig::default())
+            .await
+            .unwrap();
+        assert_eq!((entry.agent_id.as_str(), entry.status), ("b", MergeStatus::Merged));
+        let tip = repo.revparse_single("main").unwrap().peel_to_commit().unwrap();
+        assert!(tip.tree().unwrap().get_name("b.txt").is_some());
+        assert_eq!(store.next_queued_merge().unwrap().map(|e| e.agent_id).as_deref(), Some("a"));
+    }
+}
@@ -1,11 +1,21 @@
 //! V2 orchestration service layer.
 
+use crate::agent::{AgentType, NudgeStrategy};
+use crate::budget::{BudgetAction, BudgetPolicy, BUDGET_STOPPED, BUDGET_WARNING};
+use crate::config::HooksConfig;
+use crate::hooks::{self, HookContext, HookPoint};
 use crate::isolation::{BranchIsolation, IsolationContext, IsolationMode, IsolationStrategy, WorktreeIsolation};
+use crate::report::{self, ReportOptions};
 use crate::runtime::{AgentRuntime, RuntimeAgentStatus};
-use crate::state::{SessionRecord, SessionStatus, StateStore};
+use crate::state::{SessionRecord, SessionStatus, StatePool};
+use crate::timeline::{self, TimelineKind};
 use crate::Result;
 use chrono::Utc;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::task::JoinSet;
+use tracing::{info_span, Instrument};
 
 /// Parameters for spawning an agent session through the v2 orchestration path.
 #[derive(Debug, Clone)]
@@ -26,32 +36,95 @@ pub struct SpawnResult {
 }
 
 /// Orchestration service coordinating runtime, isolation, and persistent state.
+///
+/// State goes through a `StatePool` and hooks run on the blocking pool, so an
+/// `Arc<Orchestrator>` can be driven from several tasks at once.
 pub struct Orchestrator<R: AgentRuntime> {
     repo_path: PathBuf,
     runtime: R,
-    state: StateStore,
+    state: StatePool,
+    /// Write a review report when an agent stops or finishes
+    reports: Option<ReportOptions>,
+    /// Reports still being written, off the status change that asked for them
+    pending_reports: Mutex<JoinSet<()>>,
+    /// Lifecycle hooks run around spawns and failures
+    hooks: HooksConfig,
+    /// Provisions every workspace in place of the per-mode strategies
+    isolation: Option<Arc<dyn IsolationStrategy>>,
 }
 
 impl<R: AgentRuntime> Orchestrator<R> {
     pub fn new(repo_path: impl AsRef<Path>, runtime: R) -> Result<Self> {
         let repo_path = repo_path.as_ref().to_path_buf();
-        let state = StateStore::open(&repo_path)?;
+        let state = StatePool::open(&repo_path)?;
+        match crate::reconcile::reconcile(&repo_path, &*state.get()?) {
+            Ok(report) => {
+                for line in report.lines() {
+                    tracing::info!("reconciled: {}", line);
+                }
+            }
+            Err(e) => tracing::warn!("Skipped worktree reconciliation: {}", e),
+        }
         Ok(Self {
             repo_path,
             runtime,
             state,
+            reports: None,
+            pending_reports: Mutex::new(JoinSet::new()),
+            hooks: HooksConfig::default(),
+            isolation: None,
         })
     }
 
-    pub fn state(&self) -> &StateStore {
+    /// Generate review reports for agents that stop or finish.
+    pub fn with_reports(mut self, options: ReportOptions) -> Self {
+        self.reports = Some(options);
+        self
+    }
+
+    /// Run these lifecycle hooks around spawns and failures.
+    pub fn with_hooks(mut self, hooks: HooksConfig) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Provision workspaces with `strategy`, whatever mode is requested.
+    pub fn with_isolation(mut self, strategy: impl IsolationStrategy + 'static) -> Self {
+        self.isolation = Some(Arc::new(strategy));
+        self
+    }
+
+    pub fn runtime(&self) -> &R {
+        &self.runtime
+    }
+
+    pub fn state(&self) -> &StatePool {
         &self.state
     }
 
+    #[tracing::instrument(
+        skip_all,
+        fields(agent_id = %req.agent_id, runtime = self.runtime.name(), mode = ?req.isolation_mode)
+    )]
     pub async fn spawn_agent(&self, req: SpawnRequest) -> Result<SpawnResult> {
+        let branch = format!("rembrandt/{}", req.agent_id);
+        let env = crate::agent::context_env(&req.agent_id, &branch, req.task_id.as_deref());
+        self.run_hooks(HookPoint::PreSpawn, &req.agent_id, &self.repo_path, &env).await?;
+
         let strategy = self.strategy_for(req.isolation_mode);
         let workspace = strategy
             .prepare(&self.repo_path, &req.agent_id, &req.base_branch)
+            .instrument(info_span!("isolation.prepare", base_branch = %req.base_branch))
             .await?;
+        if let Err(e) = self
+            .run_hooks(HookPoint::PostWorktree, &req.agent_id, &workspace.checkout_path, &env)
+            .await
+        {
+            if let Err(cleanup) = strategy.cleanup(&workspace).await {
+                tracing::warn!(agent_id = %req.agent_id, error = %cleanup, "Could not remove workspace after failed setup");
+            }
+            return Err(e);
+        }
 
         let handle = self
             .runtime
@@ -61,6 +134,7 @@ impl<R: AgentRuntime> Orchestrator<R> {
                 req.prompt.as_deref(),
                 req.model.as_deref(),
             )
+            .instrument(info_span!("runtime.spawn"))
             .await?;
 
         let now = Utc::now();
@@ -76,24 +150,61 @@ impl<R: AgentRuntime> Orchestrator<R> {
             model: handle.model,
             created_at: now,
             updated_at: now,
+            prompt: req.prompt,
+            agent_type: Some(self.runtime.agent_type().to_string()),
+            updated_seq: 0,
         };
 
-        self.state.upsert_session(&session)?;
-        self.state.touch_heartbeat(&session.agent_id, Some("spawned"))?;
+        let record = session.clone();
+        self.state
+            .call(move |store| {
+                store.upsert_session(&record)?;
+                store.touch_heartbeat(&record.agent_id, Some("spawned"))?;
+                store.record_timeline(
+                    &record.agent_id,
+                    TimelineKind::Spawned,
+                    Some(&format!("{} on {}", record.runtime_kind, record.branch_name)),
+                )
+            })
+            .await?;
 
         Ok(SpawnResult { session, workspace })
     }
 
-    pub fn list_agents(&self) -> Result<Vec<SessionRecord>> {
-        self.state.list_sessions()
+    pub async fn list_agents(&self) -> Result<Vec<SessionRecord>> {
+        self.state.call(|store| store.list_sessions()).await
+    }
+
+    pub async fn get_status(&self, agent_id: &str) -> Result<Option<SessionRecord>> {
+        let agent_id = agent_id.to_string();
+        self.state.call(move |store| store.get_session(&agent_id)).await
     }
 
-    pub fn get_status(&self, agent_id: &str) -> Result<Option<SessionRecord>> {
-        self.state.get_session(agent_id)
+    /// Set an agent's status, if its current one allows, and heartbeat note
+    async fn set_status(&self, agent_id: &str, status: SessionStatus, note: &'static str) -> Result<()> {
+        let agent_id = agent_id.to_string();
+        self.state
+            .call(move |store| {
+                store.transition_status(&agent_id, |current| current.status.can_transition_to(status).then_some(status))?;
+                store.touch_heartbeat(&agent_id, Some(note))
+            })
+            .await
     }
 
+    /// Refresh an agent's heartbeat and add an entry to its timeline
+    async fn record_activity(&self, agent_id: &str, note: &'static str, kind: TimelineKind, detail: String) -> Result<()> {
+        let agent_id = agent_id.to_string();
+        self.state
+            .call(move |store| {
+                store.touch_heartbeat(&agent_id, Some(note))?;
+                store.record_timeline(&agent_id, kind, Some(&detail))
+            })
+            .await
+    }
+
+    #[tracing::instrument(skip(self), fields(runtime = self.runtime.name()))]
     pub async fn refresh_runtime_status(&self, agent_id: &str) -> Result<Option<SessionStatus>> {
-        let Some(record) = self.state.get_session(agent_id)? else {
+        let Some(record) = self.get_status(agent_id).await? else {
             return Ok(None);
         };
         let Some(runtime_session_id) = &record.runtime_session_id else {
@@ -103,47 +214,397 @@ impl<R: AgentRuntime> Orchestrator<R> {
         let runtime_status = self
             .runtime
             .status(&crate::runtime::RuntimeSessionId(runtime_session_id.clone()))
+            .instrument(info_span!("runtime.status"))
             .await?;
 
+        self.collect_usage(agent_id, runtime_session_id).await?;
+
         let mapped = map_runtime_status(runtime_status);
-        self.state.update_status(agent_id, mapped)?;
-        self.state.touch_heartbeat(agent_id, Some("status-refreshed"))?;
-        Ok(Some(mapped))
+        let agent = agent_id.to_string();
+        let (applied, status) = self
+            .state
+            .call(move |store| {
+                // A status another process settled on (stopped, completed)
+                // wins over what the runtime reports
+                let mut previous = None;
+                let updated = store.transition_status(&agent, |current| {
+                    previous = Some(current.status);
+                    // A paused agent stays paused until resumed, unless it ended
+                    if current.status == SessionStatus::Paused && mapped.is_active() {
+                        return None;
+                    }
+                    current.status.can_transition_to(mapped).then_some(mapped)
+                })?;
+                store.touch_heartbeat(&agent, Some("status-refreshed"))?;
+                let changed = updated.is_some() && previous != Some(mapped);
+                Ok((changed, previous.filter(|_| updated.is_none()).unwrap_or(mapped)))
+            })
+            .await?;
+        if applied && mapped == SessionStatus::Failed {
+            let env = crate::agent::context_env(agent_id, &record.branch_name, record.task_id.as_deref());
+            self.run_hooks(HookPoint::OnFailure, agent_id, &record.checkout_path, &env)
+                .await?;
+        }
+        if applied && !mapped.is_active() {
+            self.write_report(agent_id);
+        }
+        Ok(Some(status))
+    }
+
+    /// Persist any usage the runtime has observed since the last call.
+    async fn collect_usage(&self, agent_id: &str, runtime_session_id: &str) -> Result<()> {
+        let samples = self
+            .runtime
+            .drain_usage(&crate::runtime::RuntimeSessionId(runtime_session_id.to_string()))
+            .instrument(info_span!("runtime.drain_usage"))
+            .await?;
+        if samples.is_empty() {
+            return Ok(());
+        }
+        let agent_id = agent_id.to_string();
+        self.state
+            .call(move |store| {
+                for sample in &samples {
+                    store.record_usage(&agent_id, sample)?;
+                }
+                Ok(())
+            })
+            .await
     }
 
+    #[tracing::instrument(skip(self), fields(runtime = self.runtime.name()))]
     pub async fn kill_agent(&self, agent_id: &str) -> Result<()> {
-        if let Some(record) = self.state.get_session(agent_id)? {
+        if let Some(record) = self.get_status(agent_id).await? {
             if let Some(runtime_session_id) = record.runtime_session_id {
+                // Capture the final usage report before the output goes away
+                let _ = self.collect_usage(agent_id, &runtime_session_id).await;
                 let _ = self
                     .runtime
                     .stop(&crate::runtime::RuntimeSessionId(runtime_session_id))
+                    .instrument(info_span!("runtime.stop"))
                     .await;
             }
-            self.state.update_status(agent_id, SessionStatus::Stopped)?;
-            self.state.touch_heartbeat(agent_id, Some("stopped"))?;
+            self.set_status(agent_id, SessionStatus::Stopped, "stopped").await?;
+            self.write_report(agent_id);
         }
         Ok(())
     }
 
+    /// Suspend a live agent through its runtime and mark it paused
+    #[tracing::instrument(skip(self), fields(runtime = self.runtime.name()))]
+    pub async fn pause_agent(&self, agent_id: &str) -> Result<()> {
+        let record = self
+            .get_status(agent_id)
+            .await?
+            .ok_or_else(|| crate::RembrandtError::SessionNotFound(agent_id.to_string()))?;
+        if !record.status.can_transition_to(SessionStatus::Paused) {
+            return Err(crate::RembrandtError::State(format!(
+                "{} is {} and can't be paused",
+                agent_id, record.status
+            )));
+        }
+        if let Some(runtime_session_id) = record.runtime_session_id {
+            self.runtime
+                .pause(&crate::runtime::RuntimeSessionId(runtime_session_id))
+                .instrument(info_span!("runtime.pause"))
+                .await?;
+        }
+        self.set_status(agent_id, SessionStatus::Paused, "paused").await
+    }
+
+    /// Continue an agent suspended with `pause_agent`
+    #[tracing::instrument(skip(self), fields(runtime = self.runtime.name()))]
+    pub async fn resume_agent(&self, agent_id: &str) -> Result<()> {
+        let record = self
+            .get_status(agent_id)
+            .await?
+            .ok_or_else(|| crate::RembrandtError::SessionNotFound(agent_id.to_string()))?;
+        if record.status != SessionStatus::Paused {
+            return Ok(());
+        }
+        if let Some(runtime_session_id) = record.runtime_session_id {
+            self.runtime
+                .resume(&crate::runtime::RuntimeSessionId(runtime_session_id))
+                .instrument(info_span!("runtime.resume"))
+                .await?;
+        }
+        self.set_status(agent_id, SessionStatus::Active, "resumed").await
+    }
+
+    /// Run the hooks for `point` on the blocking pool, recording them in
+    /// state.db
+    async fn run_hooks(&self, point: HookPoint, agent_id: &str, cwd: &Path, env: &[(String, String)]) -> Result<()> {
+        if self.hooks.commands(point).is_empty() {
+            return Ok(());
+        }
+        let (hooks, state) = (self.hooks.clone(), self.state.clone());
+        let (repo_path, cwd) = (self.repo_path.clone(), cwd.to_path_buf());
+        let (agent_id, env) = (agent_id.to_string(), env.to_vec());
+        tokio::task::spawn_blocking(move || {
+            let store = state.get()?;
+            let ctx = HookContext {
+                repo_path: &repo_path,
+                cwd: &cwd,
+                agent_id: &agent_id,
+                env: &env,
+                store: Some(&store),
+            };
+            hooks::run(&hooks, point, &ctx).map(|_| ())
+        })
+        .await
+        .map_err(|e| crate::RembrandtError::Runtime(format!("{} hooks failed to run: {}", point, e)))?
+    }
+
+    /// Start writing the agent's review report if reports are on; failures
+    /// are logged. Validation can take minutes, so the report is written in
+    /// the background (see [`Self::finish_reports`]).
+    fn write_report(&self, agent_id: &str) {
+        let Some(options) = self.reports.clone() else {
+            return;
+        };
+        let (repo_path, state, agent_id) = (self.repo_path.clone(), self.state.clone(), agent_id.to_string());
+        let mut pending = self.pending_reports.lock().unwrap();
+        while pending.try_join_next().is_some() {}
+        pending.spawn(async move {
+            match report::generate(&repo_path, &state, &agent_id, &options).await {
+                Ok(path) => tracing::info!("wrote report {}", path.display()),
+                Err(e) => tracing::warn!("Could not write report for {}: {}", agent_id, e),
+            }
+        });
+    }
+
+    /// Wait for every report still being written
+    pub async fn finish_reports(&self) {
+        let mut pending = std::mem::take(&mut *self.pending_reports.lock().unwrap());
+        while pending.join_next().await.is_some() {}
+    }
+
+    #[tracing::instrument(skip(self, message), fields(runtime = self.runtime.name()))]
     pub async fn steer_agent(&self, agent_id: &str, message: &str) -> Result<()> {
-        if let Some(record) = self.state.get_session(agent_id)? {
-            if let Some(runtime_session_id) = record.runtime_session_id {
+        if let Some(record) = self.get_status(agent_id).await?
+            && let Some(runtime_session_id) = record.runtime_session_id
+        {
+            self.runtime
+                .send_message(
+                    &crate::runtime::RuntimeSessionId(runtime_session_id),
+                    message,
+                )
+                .instrument(info_span!("runtime.send_message"))
+                .await?;
+            self.record_activity(agent_id, "message-sent", TimelineKind::Steer, timeline::one_line(message, 80))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Send pending inbox messages to runtime-managed agents that are idle;
+    /// returns how many were delivered.
+    #[tracing::instrument(skip(self), fields(runtime = self.runtime.name()))]
+    pub async fn deliver_messages(&self) -> Result<usize> {
+        let pending = self
+            .state
+            .call(|store| {
+                let mut pending = Vec::new();
+                for session in store.list_active()? {
+                    let messages = store.inbox(&session.agent_id, true)?;
+                    if let Some(id) = session.runtime_session_id.filter(|_| !messages.is_empty()) {
+                        pending.push((id, messages));
+                    }
+                }
+                Ok(pending)
+            })
+            .await?;
+
+        let mut delivered = 0;
+        for (runtime_session_id, messages) in pending {
+            let id = crate::runtime::RuntimeSessionId(runtime_session_id);
+            if self.runtime.status(&id).await? != RuntimeAgentStatus::Idle {
+                continue;
+            }
+            for message in messages {
                 self.runtime
-                    .send_message(
-                        &crate::runtime::RuntimeSessionId(runtime_session_id),
-                        message,
-                    )
+                    .send_message(&id, &message.delivery_text())
+                    .instrument(info_span!("runtime.send_message"))
                     .await?;
-                self.state.touch_heartbeat(agent_id, Some("message-sent"))?;
+                self.state.call(move |store| store.mark_message_delivered(&message)).await?;
+                delivered += 1;
             }
         }
-        Ok(())
+        Ok(delivered)
     }
 
-    fn strategy_for(&self, mode: IsolationMode) -> Box<dyn IsolationStrategy> {
+    /// Nudge runtime-managed agents that sit idle with no mail waiting,
+    /// using each one's ladder from `ladders` (or its agent type's default);
+    /// returns the agents nudged and how.
+    ///
+    /// Agents with mail are left to `deliver_messages`, as CSI hands idle
+    /// daemon agents their mail instead of nudging them.
+    #[tracing::instrument(skip_all, fields(runtime = self.runtime.name()))]
+    pub async fn nudge_idle_agents(
+        &self,
+        ladders: &HashMap<AgentType, Vec<NudgeStrategy>>,
+    ) -> Result<Vec<(String, NudgeStrategy)>> {
+        let candidates = self
+            .state
+            .call(|store| {
+                let mut candidates = Vec::new();
+                for session in store.list_active()? {
+                    if session.runtime_session_id.is_some() && store.inbox(&session.agent_id, true)?.is_empty() {
+                        candidates.push(session);
+                    }
+                }
+                Ok(candidates)
+            })
+            .await?;
+
+        let mut nudged = Vec::new();
+        for session in candidates {
+            let Some(runtime_session_id) = &session.runtime_session_id else {
+                continue;
+            };
+            let id = crate::runtime::RuntimeSessionId(runtime_session_id.clone());
+            if self.runtime.status(&id).await? != RuntimeAgentStatus::Idle {
+                continue;
+            }
+            let agent_type = session
+                .agent_type
+                .as_deref()
+                .map_or_else(|| self.runtime.agent_type(), AgentType::from_str);
+            let ladder = ladders
+                .get(&agent_type)
+                .cloned()
+                .unwrap_or_else(|| agent_type.default_nudge_ladder());
+            if let Some(strategy) = self.nudge_agent(&session.agent_id, &ladder).await? {
+                nudged.push((session.agent_id, strategy));
+            }
+        }
+        Ok(nudged)
+    }
+
+    /// Nudge a runtime-managed agent with the message steps of its ladder.
+    ///
+    /// Runtimes take messages, not keystrokes, so only `Text` and
+    /// `SendMessage` strategies apply; the first one is sent. Returns the
+    /// strategy used, or `None` if the ladder has no message step.
+    #[tracing::instrument(skip(self, ladder), fields(runtime = self.runtime.name()))]
+    pub async fn nudge_agent(
+        &self,
+        agent_id: &str,
+        ladder: &[NudgeStrategy],
+    ) -> Result<Option<NudgeStrategy>> {
+        let Some((strategy, message)) = ladder.iter().find_map(|s| match s {
+            NudgeStrategy::Text(message) | NudgeStrategy::SendMessage(message) => Some((s, message)),
+            _ => None,
+        }) else {
+            return Ok(None);
+        };
+        if let Some(record) = self.get_status(agent_id).await?
+            && let Some(runtime_session_id) = record.runtime_session_id
+        {
+            self.runtime
+                .send_message(
+                    &crate::runtime::RuntimeSessionId(runtime_session_id),
+                    message,
+                )
+                .instrument(info_span!("runtime.send_message"))
+                .await?;
+            self.record_activity(agent_id, "nudged", TimelineKind::Nudge, strategy.describe())
+                .await?;
+            return Ok(Some(strategy.clone()));
+        }
+        Ok(None)
+    }
+
+    /// Steer, then stop, active sessions that are over `policy`'s budgets.
+    ///
+    /// An over-budget agent first gets the wrap-up message and a
+    /// `budget-warning` attention item; once that item is older than the grace
+    /// period (or straight away if there is no wrap-up message) it is stopped,
+    /// marked `stopped_for_budget`, and a `budget-stopped` item replaces the
+    /// warning.
+    #[tracing::instrument(skip_all)]
+    pub async fn enforce_budgets(&self, policy: &BudgetPolicy) -> Result<Vec<BudgetAction>> {
+        if !policy.is_enabled() {
+            return Ok(Vec::new());
+        }
+
+        let now = Utc::now();
+        let (today, attention, sessions) = self
+            .state
+            .call(|store| {
+                let sessions = store
+                    .list_active()?
+                    .into_iter()
+                    .map(|session| Ok((store.usage_for(&session.agent_id)?, session)))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok((store.usage_since(crate::usage::start_of_today())?, store.open_attention()?, sessions))
+            })
+            .await?;
+        let mut actions = Vec::new();
+
+        for (usage, session) in sessions {
+            let agent_id = session.agent_id.clone();
+            let Some(reason) = policy.exceeded(&session, &usage, &today, now) else {
+                continue;
+            };
+            let warning = attention
+                .iter()
+                .find(|a| a.kind == BUDGET_WARNING && a.agent_id.as_deref() == Some(agent_id.as_str()));
+
+            match (&policy.wrap_up_message, warning) {
+                (Some(message), None) => {
+                    if let Err(e) = self.steer_agent(&agent_id, message).await {
+                        tracing::warn!(agent_id, error = %e, "failed to send budget wrap-up");
+                    }
+                    let (id, detail) = (agent_id.clone(), format!("{}; asked to wrap up", reason));
+                    self.state
+                        .call(move |store| store.raise_attention(Some(&id), BUDGET_WARNING, &detail))
+                        .await?;
+                    actions.push(BudgetAction::WrapUp { agent_id, reason });
+                }
+                (Some(_), Some(warning)) if now.signed_duration_since(warning.created_at) < policy.grace => {}
+                (_, warning) => {
+                    if let Err(e) = self.kill_agent(&agent_id).await {
+                        // Someone still has to stop it; the other sessions get checked regardless
+                        tracing::warn!(agent_id, error = %e, "failed to stop agent over budget");
+                        let reported = attention
+                            .iter()
+                            .any(|a| a.kind == BUDGET_STOPPED && a.agent_id.as_deref() == Some(agent_id.as_str()));
+                        if !reported {
+                            let (id, detail) = (agent_id.clone(), format!("{}; could not stop it: {}", reason, e));
+                            self.state
+                                .call(move |store| store.raise_attention(Some(&id), BUDGET_STOPPED, &detail))
+                                .await?;
+                        }
+                        continue;
+                    }
+                    let (id, detail) = (agent_id.clone(), format!("{}; stopped", reason));
+                    let warning_id = warning.map(|w| w.id);
+                    self.state
+                        .call(move |store| {
+                            let to = SessionStatus::StoppedForBudget;
+                            store.transition_status(&id, |current| current.status.can_transition_to(to).then_some(to))?;
+                            if let Some(warning_id) = warning_id {
+                                store.resolve_attention(warning_id)?;
+                            }
+                            store.raise_attention(Some(&id), BUDGET_STOPPED, &detail)
+                        })
+                        .await?;
+                    actions.push(BudgetAction::Stopped { agent_id, reason });
+                }
+            }
+        }
+
+        Ok(actions)
+    }
+
+    fn strategy_for(&self, mode: IsolationMode) -> Arc<dyn IsolationStrategy> {
+        if let Some(strategy) = &self.isolation {
+            return strategy.clone();
+        }
         match mode {
-            IsolationMode::Branch => Box::new(BranchIsolation),
-            IsolationMode::Worktree => Box::new(WorktreeIsolation),
+            IsolationMode::Branch => Arc::new(BranchIsolation),
+            IsolationMode::Worktree => Arc::new(WorktreeIsolation),
         }
     }
 }
@@ -158,3 +619,145 @@ fn map_runtime_status(status: RuntimeAgentStatus) -> SessionStatus {
         RuntimeAgentStatus::Stopped => SessionStatus::Stopped,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::isolation::TempdirIsolation;
+    use crate::runtime::InMemoryRuntime;
+    use crate::test_support::init_repo;
+    use crate::usage::UsageSample;
+
+    #[tokio::test]
+    async fn drives_a_session_through_its_runtime() {
+        let dir = tempfile::tempdir().unwrap();
+        init_repo(dir.path());
+
+        let orch = Orchestrator::new(dir.path(), InMemoryRuntime::new()).unwrap();
+        let spawned = orch
+            .spawn_agent(SpawnRequest {
+                agent_id: "a1".to_string(),
+                base_branch: "main".to_string(),
+                isolation_mode: IsolationMode::Worktree,
+                prompt: Some("fix the bug".to_string()),
+                model: None,
+                task_id: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(spawned.session.status, SessionStatus::Starting);
+        assert_eq!(spawned.session.runtime_kind, "memory");
+        assert_eq!(spawned.session.agent_type.as_deref(), Some("memory"));
+        let session = orch.runtime.session("a1").unwrap();
+        assert_eq!(session.prompt.as_deref(), Some("fix the bug"));
+        assert_eq!(session.checkout_path, spawned.workspace.checkout_path);
+
+        orch.steer_agent("a1", "add a test too").await.unwrap();
+        assert_eq!(orch.runtime.session("a1").unwrap().messages, vec!["add a test too"]);
+
+        orch.state().get().unwrap().send_agent_message("a2", "a1", "the API takes a &str now").unwrap();
+        assert_eq!(orch.deliver_messages().await.unwrap(), 0, "waits until a1 is idle");
+        orch.runtime.set_status("a1", RuntimeAgentStatus::Idle).unwrap();
+        assert_eq!(orch.deliver_messages().await.unwrap(), 1);
+        assert_eq!(
+            orch.runtime.session("a1").unwrap().messages.last().unwrap(),
+            "[message from a2] the API takes a &str now"
+        );
+        assert!(orch.state().get().unwrap().inbox("a1", true).unwrap().is_empty());
+
+        // Idle with no mail left: a nudge, with the first message step
+        let ladders = HashMap::from([(
+            AgentType::from_str("memory"),
+            vec![NudgeStrategy::Enter, NudgeStrategy::Text("keep going".to_string())],
+        )]);
+        let nudged = orch.nudge_idle_agents(&ladders).await.unwrap();
+        assert_eq!(nudged, vec![("a1".to_string(), NudgeStrategy::Text("keep going".to_string()))]);
+        assert_eq!(orch.runtime.session("a1").unwrap().messages.last().unwrap(), "keep going");
+        orch.runtime.set_status("a1", RuntimeAgentStatus::Running).unwrap();
+        assert!(orch.nudge_idle_agents(&ladders).await.unwrap().is_empty());
+
+        orch.runtime
+            .record_usage("a1", UsageSample { cost_usd: Some(0.25), source: "claude", ..UsageSample::default() })
+            .unwrap();
+        assert_eq!(orch.refresh_runtime_status("a1").await.unwrap(), Some(SessionStatus::Active));
+        assert_eq!(orch.state().get().unwrap().usage_for("a1").unwrap().cost_usd, 0.25);
+
+        orch.pause_agent("a1").await.unwrap();
+        assert!(orch.runtime.session("a1").unwrap().paused);
+        assert_eq!(orch.refresh_runtime_status("a1").await.unwrap(), Some(SessionStatus::Paused));
+        orch.resume_agent("a1").await.unwrap();
+        assert!(!orch.runtime.session("a1").unwrap().paused);
+        assert_eq!(orch.get_status("a1").await.unwrap().unwrap().status, SessionStatus::Active);
+
+        orch.runtime.set_status("a1", RuntimeAgentStatus::Completed).unwrap();
+        assert_eq!(orch.refresh_runtime_status("a1").await.unwrap(), Some(SessionStatus::Completed));
+
+        // Finished sessions stay finished, whatever the runtime says next
+        orch.kill_agent("a1").await.unwrap();
+        let record = orch.get_status("a1").await.unwrap().unwrap();
+        assert_eq!(record.status, SessionStatus::Completed);
+        assert_eq!(orch.runtime.session("a1").unwrap().status, RuntimeAgentStatus::Stopped);
+    }
+
+    #[tokio::test]
+    async fn failed_post_worktree_hook_removes_the_worktree() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = init_repo(dir.path());
+
+        let hooks = HooksConfig {
+            post_worktree: vec!["exit 3".to_string()],
+            ..HooksConfig::default()
+        };
+        let orch = Orchestrator::new(dir.path(), InMemoryRuntime::new()).unwrap().with_hooks(hooks);
+        let spawned = orch
+            .spawn_agent(SpawnRequest {
+                agent_id: "h1".to_string(),
+                base_branch: "main".to_string(),
+                isolation_mode: IsolationMode::Worktree,
+                prompt: None,
+                model: None,
+                task_id: None,
+            })
+            .await;
+        assert!(spawned.is_err());
+        assert!(orch.runtime.session("h1").is_none());
+        assert!(repo.find_worktree("h1").is_err());
+        assert!(!dir.path().join(".rembrandt/agents/h1").exists());
+    }
+
+    #[tokio::test]
+    async fn scripted_runtime_runs_without_git() {
+        let dir = tempfile::tempdir().unwrap();
+        let runtime = InMemoryRuntime::new();
+        runtime.script_status(
+            "b1",
+            [RuntimeAgentStatus::Starting, RuntimeAgentStatus::Running, RuntimeAgentStatus::Failed("boom".to_string())],
+        );
+        let isolation = TempdirIsolation::new().unwrap().with_file("src/lib.rs", "// seeded\n");
+        let checkouts = isolation.root().to_path_buf();
+        let orch = Orchestrator::new(dir.path(), runtime).unwrap().with_isolation(isolation);
+
+        let spawned = orch
+            .spawn_agent(SpawnRequest {
+                agent_id: "b1".to_string(),
+                base_branch: "main".to_string(),
+                isolation_mode: IsolationMode::Branch,
+                prompt: None,
+                model: None,
+                task_id: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(spawned.workspace.checkout_path, checkouts.join("b1"));
+        assert!(checkouts.join("b1/src/lib.rs").exists());
+
+        let mut seen = Vec::new();
+        for _ in 0..4 {
+            seen.push(orch.refresh_runtime_status("b1").await.unwrap().unwrap());
+        }
+        assert_eq!(
+            seen,
+            vec![SessionStatus::Starting, SessionStatus::Active, SessionStatus::Failed, SessionStatus::Failed]
+        );
+    }
+}
@@ -0,0 +1,232 @@
+//! Plan mode: split a large feature into parallel agent assignments.
+//!
+//! `rembrandt plan` hands the feature description to the planner command
+//! (`plan_command`, falling back to `report_summary_command`) on stdin and
+//! asks for independent tasks, each with the files it may touch. The tasks
+//! become Beads issues when `br` is available, and are always written to a
+//! manifest under `.rembrandt/plans/` so the plan can be spawned later.
+
+use crate::config::AppConfig;
+use crate::{RembrandtError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// One assignment in a plan
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanTask {
+    pub title: String,
+    #[serde(default)]
+    pub description: String,
+    /// Files and directories the task is expected to touch
+    #[serde(default)]
+    pub files: Vec<String>,
+    /// Beads issue created for the task, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub beads_id: Option<String>,
+}
+
+impl PlanTask {
+    /// Prompt for the agent that picks this task up
+    pub fn prompt(&self) -> String {
+        let mut prompt = format!("{}\n\n{}", self.title, self.description).trim_end().to_string();
+        if !self.files.is_empty() {
+            prompt.push_str(&format!(
+                "\n\nOther agents are working in parallel; keep your changes to: {}",
+                self.files.join(", ")
+            ));
+        }
+        prompt
+    }
+}
+
+/// A decomposition of one feature description
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Plan {
+    pub id: String,
+    pub description: String,
+    pub created_at: DateTime<Utc>,
+    pub tasks: Vec<PlanTask>,
+}
+
+impl Plan {
+    pub fn new(description: &str, tasks: Vec<PlanTask>) -> Self {
+        let created_at = Utc::now();
+        Self {
+            id: format!("plan-{}", created_at.format("%Y%m%d-%H%M%S")),
+            description: description.to_string(),
+            created_at,
+            tasks,
+        }
+    }
+
+    /// Pairs of task indices whose file scopes share a path, and the path
+    ///
+    /// A directory scope covers everything under it.
+    pub fn overlaps(&self) -> Vec<(usize, usize, String)> {
+        let covers = |a: &str, b: &str| {
+            let a = a.trim_end_matches('/');
+            let b = b.trim_end_matches('/');
+            a == b || b.starts_with(&format!("{}/", a))
+        };
+        let mut overlaps = Vec::new();
+        for (i, first) in self.tasks.iter().enumerate() {
+            for (j, second) in self.tasks.iter().enumerate().skip(i + 1) {
+                let shared = first.files.iter().find_map(|a| {
+                    second.files.iter().find_map(|b| {
+                        if covers(a, b) {
+                            Some(b.clone())
+                        } else if covers(b, a) {
+                            Some(a.clone())
+                        } else {
+                            None
+                        }
+                    })
+                });
+                if let Some(path) = shared {
+                    overlaps.push((i, j, path));
+                }
+            }
+        }
+        overlaps
+    }
+
+    /// Write the plan to `.rembrandt/plans/<id>.json`, returning the path
+    pub fn write_manifest(&self, repo_path: &Path) -> Result<PathBuf> {
+        let path = manifest_path(repo_path, &self.id);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| RembrandtError::State(e.to_string()))?;
+        std::fs::write(&path, json)?;
+        Ok(path)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        serde_json::from_str(&std::fs::read_to_string(path)?)
+            .map_err(|e| RembrandtError::Validation(format!("{} is not a plan manifest: {}", path.display(), e)))
+    }
+}
+
+/// Where a plan's manifest is written
+pub fn manifest_path(repo_path: &Path, plan_id: &str) -> PathBuf {
+    repo_path
+        .join(".rembrandt")
+        .join("plans")
+        .join(format!("{}.json", plan_id))
+}
+
+/// The configured planner command
+pub fn planner_command(config: &AppConfig) -> Result<String> {
+    config
+        .plan_command
+        .clone()
+        .or_else(|| config.report_summary_command.clone())
+        .ok_or_else(|| {
+            RembrandtError::Config(
+                "no planner configured: set plan_command (e.g. `claude -p`) in .rembrandt/config.toml".to_string(),
+            )
+        })
+}
+
+/// What the planner is asked
+pub fn build_prompt(description: &str, max_tasks: usize) -> String {
+    format!(
+        "Split the feature below into at most {} tasks that separate engineers can work on \
+         in parallel without touching the same files. For each task give a short title, a \
+         description precise enough to start work from, and the files or directories it \
+         will change.\n\n\
+         Reply with JSON only, in this shape:\n\
+         {{\"tasks\": [{{\"title\": \"...\", \"description\": \"...\", \"files\": [\"src/...\"]}}]}}\n\n\
+         Feature:\n{}",
+        max_tasks, description
+    )
+}
+
+/// Tasks from the planner's reply
+///
+/// Accepts `{"tasks": [...]}` or a bare array, optionally wrapped in a code
+/// fence or surrounded by prose.
+pub fn parse_response(text: &str) -> Result<Vec<PlanTask>> {
+    #[derive(Deserialize)]
+    struct Reply {
+        tasks: Vec<PlanTask>,
+    }
+
+    let start = text.find(['{', '[']);
+    let end = text.rfind(['}', ']']);
+    let (Some(start), Some(end)) = (start, end) else {
+        return Err(RembrandtError::Validation("planner reply contains no JSON".to_string()));
+    };
+    let json = text.get(start..=end).unwrap_or_default();
+    let tasks = if json.starts_with('[') {
+        serde_json::from_str::<Vec<PlanTask>>(json)
+    } else {
+        serde_json::from_str::<Reply>(json).map(|reply| reply.tasks)
+    }
+    .map_err(|e| RembrandtError::Validation(format!("planner reply is not a plan: {}", e)))?;
+    let tasks: Vec<PlanTask> = tasks
+        .into_iter()
+        .filter(|task| !task.title.trim().is_empty())
+        .collect();
+    if tasks.is_empty() {
+        return Err(RembrandtError::Validation("planner proposed no tasks".to_string()));
+    }
+    Ok(tasks)
+}
+
+/// Ask `command` to decompose `description` into at most `max_tasks` tasks
+pub fn propose(repo_path: &Path, command: &str, description: &str, max_tasks: usize) -> Result<Vec<PlanTask>> {
+    let mut child = Command::new("sh")
+        .args(["-c", command])
+        .current_dir(repo_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(build_prompt(description, max_tasks).as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(RembrandtError::Runtime(format!(
+            "planner command exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    let mut tasks = parse_response(&String::from_utf8_lossy(&output.stdout))?;
+    tasks.truncate(max_tasks);
+    Ok(tasks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn planner_reply_becomes_a_manifest_with_overlaps_flagged() {
+        let reply = "Here is the plan:\n```json\n{\"tasks\": [\
+            {\"title\": \"API\", \"description\": \"Add endpoints\", \"files\": [\"src/api/\"]},\
+            {\"title\": \"Client\", \"files\": [\"src/api/client.rs\", \"web/\"]},\
+            {\"title\": \"Docs\", \"files\": [\"README.md\"]}\
+            ]}\n```";
+        let tasks = parse_response(reply).unwrap();
+        assert_eq!(tasks.len(), 3);
+        assert!(tasks[0].prompt().ends_with("keep your changes to: src/api/"));
+        assert!(parse_response("no idea").is_err());
+        assert!(parse_response("{\"tasks\": []}").is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let command = format!("grep -q 'at most 2 tasks' && printf '%s' '{}'", reply.replace('\n', " "));
+        let tasks = propose(dir.path(), &command, "Add an API", 2).unwrap();
+        assert_eq!(tasks.len(), 2);
+
+        let plan = Plan::new("Add an API", tasks);
+        assert_eq!(plan.overlaps(), vec![(0, 1, "src/api/client.rs".to_string())]);
+        let path = plan.write_manifest(dir.path()).unwrap();
+        assert_eq!(Plan::load(&path).unwrap().tasks, plan.tasks);
+    }
+}
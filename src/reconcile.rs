@@ -0,0 +1,336 @@
+//! Startup reconciliation of `state.db`, git worktrees and `.rembrandt/agents`.
+//!
+//! The three drift apart when a worktree directory is deleted by hand, a
+//! process dies between creating a worktree and recording its session, or
+//! state.db is reset. On startup the orchestrator (and `rembrandt daemon`
+//! for its repository) brings them back in line:
+//!
+//! - git worktrees whose directory is gone are pruned
+//! - active sessions whose checkout vanished are marked `failed`
+//! - worktrees with no session are re-registered as `stopped` so they show
+//!   up in `rembrandt list` and can be merged or cleaned up, except those
+//!   of agents waiting in the warm pool or running in the daemon
+//! - directories under `.rembrandt/agents` (or the configured worktree
+//!   directory, when it is per-repository) that git doesn't know about are
+//!   reported, never deleted
+
+use crate::daemon::ipc::{default_socket_path, DaemonConnection};
+use crate::daemon::session::SessionStatus as PtyStatus;
+use crate::isolation::IsolationMode;
+use crate::state::{SessionRecord, SessionStatus, StateStore};
+use crate::Result;
+use chrono::Utc;
+use git2::{Repository, WorktreePruneOptions};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// `runtime_kind` of sessions re-registered from an untracked worktree
+pub const ADOPTED_RUNTIME: &str = "adopted";
+
+/// What a reconciliation pass changed or found
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ReconcileReport {
+    /// Worktrees pruned because their directory no longer exists
+    pub pruned_worktrees: Vec<String>,
+    /// Active sessions marked failed because their checkout vanished
+    pub failed_sessions: Vec<String>,
+    /// Worktrees with no session, now recorded in state.db
+    pub registered_worktrees: Vec<String>,
+    /// Directories under `.rembrandt/agents` that aren't git worktrees
+    pub stray_dirs: Vec<PathBuf>,
+}
+
+impl ReconcileReport {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// One human-readable line per change or finding
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for name in &self.pruned_worktrees {
+            lines.push(format!("pruned worktree {} (directory gone)", name));
+        }
+        for agent_id in &self.failed_sessions {
+            lines.push(format!("marked {} failed (checkout vanished)", agent_id));
+        }
+        for name in &self.registered_worktrees {
+            lines.push(format!("registered untracked worktree {}", name));
+        }
+        for dir in &self.stray_dirs {
+            lines.push(format!("{} is not a git worktree; left in place", dir.display()));
+        }
+        lines
+    }
+}
+
+/// Reconcile `repo_path`'s state.db, git worktrees and agent directories
+#[tracing::instrument(skip(store))]
+pub fn reconcile(repo_path: &Path, store: &StateStore) -> Result<ReconcileReport> {
+    reconcile_with_daemon(repo_path, store, &default_socket_path())
+}
+
+/// `reconcile`, asking the daemon listening on `socket` what it runs
+fn reconcile_with_daemon(repo_path: &Path, store: &StateStore, socket: &Path) -> Result<ReconcileReport> {
+    let repo = Repository::open(repo_path)?;
+    let mut report = ReconcileReport::default();
+
+    // Worktrees git knows about, minus those whose directory is gone
+    let mut worktrees = Vec::new();
+    for name in repo.worktrees()?.iter().flatten() {
+        let worktree = repo.find_worktree(name)?;
+        if worktree.path().exists() {
+            worktrees.push((name.to_string(), worktree.path().to_path_buf()));
+        } else {
+            // Without `valid`, prune only removes worktrees that are really gone
+            let locked = crate::worktree::locked_by_rembrandt(&worktree);
+            worktree.prune(Some(WorktreePruneOptions::new().locked(locked)))?;
+            report.pruned_worktrees.push(name.to_string());
+        }
+    }
+
+    let sessions = store.list_sessions()?;
+    for session in sessions.iter().filter(|s| s.status.is_active()) {
+        if !session.checkout_path.exists()
+            && store
+                .transition_status(&session.agent_id, |current| {
+                    current.status.is_active().then_some(SessionStatus::Failed)
+                })?
+                .is_some()
+        {
+            report.failed_sessions.push(session.agent_id.clone());
+        }
+    }
+
+    // Pooled agents' worktrees have no session until they are handed out
+    let pooled = store.pooled_agents()?;
+    let known: HashSet<&str> = sessions
+        .iter()
+        .map(|s| s.agent_id.as_str())
+        .chain(pooled.iter().map(|p| p.agent_id.as_str()))
+        .collect();
+    let config = crate::config::AppConfig::load(repo_path).unwrap_or_default();
+    let mut agents_dirs = vec![repo_path.join(".rembrandt").join("agents")];
+    agents_dirs.extend(config.worktree.worktrees_dir(repo_path));
+    // git reports absolute paths, while `repo_path` may be `.`
+    let canonical = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let managed_dirs: Vec<PathBuf> = agents_dirs.iter().map(|dir| canonical(dir)).collect();
+    let in_daemon = live_daemon_workdirs(socket);
+    for (name, path) in &worktrees {
+        let path_canonical = canonical(path);
+        if known.contains(name.as_str())
+            || !managed_dirs.iter().any(|dir| path_canonical.starts_with(dir))
+            || in_daemon.contains(&path_canonical)
+        {
+            continue;
+        }
+        let branch_name = Repository::open(path)
+            .ok()
+            .and_then(|wt| wt.head().ok()?.shorthand().map(str::to_string))
+            .unwrap_or_else(|| format!("rembrandt/{}", name));
+        let now = Utc::now();
+        store.upsert_session(&SessionRecord {
+            agent_id: name.clone(),
+            runtime_kind: ADOPTED_RUNTIME.to_string(),
+            runtime_session_id: None,
+            isolation_mode: IsolationMode::Worktree,
+            branch_name,
+            checkout_path: path.clone(),
+            task_id: None,
+            status: SessionStatus::Stopped,
+            model: None,
+            created_at: now,
+            updated_at: now,
+            prompt: None,
+            agent_type: None,
+            updated_seq: 0,
+        })?;
+        report.registered_worktrees.push(name.clone());
+    }
+
+    // A worktree directory shared between repositories holds other
+    // repositories' worktrees too, so only per-repository ones are scanned
+    if !config.worktree.dir.as_deref().is_some_and(|dir| dir.contains("{repo")) {
+        agents_dirs.truncate(1);
+    }
+    let registered: HashSet<PathBuf> = worktrees.iter().map(|(_, path)| canonical(path)).collect();
+    for agents_dir in &agents_dirs {
+        if let Ok(entries) = std::fs::read_dir(agents_dir) {
+            report.stray_dirs.extend(
+                entries
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .filter(|path| path.is_dir())
+                    .filter(|path| !registered.contains(&canonical(path))),
+            );
+        }
+    }
+    report.stray_dirs.sort();
+
+    Ok(report)
+}
+
+/// Checkouts of agents running in the daemon, which may not have reached
+/// state.db yet; empty when no daemon is running
+fn live_daemon_workdirs(socket: &Path) -> HashSet<PathBuf> {
+    let Ok(mut daemon) = DaemonConnection::connect(socket) else {
+        return HashSet::new();
+    };
+    daemon
+        .list(None)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|session| session.status == PtyStatus::Running)
+        .map(|session| {
+            let workdir = PathBuf::from(session.workdir);
+            workdir.canonicalize().unwrap_or(workdir)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::daemon::Daemon;
+    use crate::restart::RestartPolicy;
+    use crate::test_support::{init_repo, session_record};
+    use crate::worktree::WorktreeManager;
+    use std::sync::Arc;
+
+    #[test]
+    fn repairs_drift_between_state_and_worktrees() {
+        let dir = tempfile::tempdir().unwrap();
+        init_repo(dir.path());
+
+        let worktrees = WorktreeManager::new(dir.path()).unwrap();
+        let untracked = worktrees.create_worktree("untracked", "main").unwrap();
+        let vanished = worktrees.create_worktree("vanished", "main").unwrap();
+        std::fs::remove_dir_all(&vanished.path).unwrap();
+        std::fs::create_dir_all(dir.path().join(".rembrandt/agents/stray")).unwrap();
+        let pooled = worktrees.create_worktree("pooled", "main").unwrap();
+
+        let store = StateStore::open(dir.path()).unwrap();
+        let now = Utc::now();
+        store
+            .add_pooled(&crate::warm_pool::PooledAgent {
+                agent_id: "pooled".to_string(),
+                agent_type: "claude-code".to_string(),
+                session_id: "s1".to_string(),
+                base_branch: "main".to_string(),
+                base_commit: String::new(),
+                branch_name: pooled.branch.clone(),
+                checkout_path: pooled.path.clone(),
+                created_at: now,
+            })
+            .unwrap();
+        store
+            .upsert_session(&SessionRecord {
+                isolation_mode: IsolationMode::Worktree,
+                branch_name: vanished.branch.clone(),
+                checkout_path: vanished.path.clone(),
+                ..session_record("vanished")
+            })
+            .unwrap();
+
+        let report = reconcile(dir.path(), &store).unwrap();
+        assert_eq!(report.pruned_worktrees, vec!["vanished".to_string()]);
+        assert_eq!(report.failed_sessions, vec!["vanished".to_string()]);
+        assert_eq!(report.registered_worktrees, vec!["untracked".to_string()]);
+        assert_eq!(report.stray_dirs, vec![dir.path().join(".rembrandt/agents/stray")]);
+
+        let adopted = store.get_session("untracked").unwrap().unwrap();
+        assert_eq!(adopted.status, SessionStatus::Stopped);
+        assert_eq!(adopted.branch_name, untracked.branch);
+        assert_eq!(store.get_session("vanished").unwrap().unwrap().status, SessionStatus::Failed);
+        // A pooled agent's worktree is the pool's, not cleanup's
+        assert!(store.get_session("pooled").unwrap().is_none());
+
+        // A second pass finds nothing new to repair
+        let again = reconcile(dir.path(), &store).unwrap();
+        assert!(again.pruned_worktrees.is_empty());
+        assert!(again.failed_sessions.is_empty());
+        assert!(again.registered_worktrees.is_empty());
+
+        // Also when the repository is named by a path unlike git's, e.g. `.`
+        worktrees.create_worktree("later", "main").unwrap();
+        let roundabout = dir.path().join(".rembrandt").join("..");
+        let report = reconcile(&roundabout, &store).unwrap();
+        assert_eq!(report.registered_worktrees, vec!["later".to_string()]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn live_daemon_agents_are_left_alone_but_vanished_checkouts_fail() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo_path = dir.path().join("repo");
+        std::fs::create_dir(&repo_path).unwrap();
+        init_repo(&repo_path);
+        let socket = dir.path().join("daemon.sock");
+        let daemon = Arc::new(Daemon::new(socket.clone()));
+        let server = {
+            let daemon = daemon.clone();
+            tokio::spawn(async move { daemon.run().await })
+        };
+
+        tokio::task::spawn_blocking(move || {
+            let mut conn = (0..100)
+                .find_map(|_| {
+                    DaemonConnection::connect(&socket)
+                        .inspect_err(|_| std::thread::sleep(std::time::Duration::from_millis(10)))
+                        .ok()
+                })
+                .expect("daemon never came up");
+            let worktrees = WorktreeManager::new(&repo_path).unwrap();
+            let store = StateStore::open(&repo_path).unwrap();
+            let spawn = |conn: &mut DaemonConnection, agent_id: &str, workdir: PathBuf| {
+                conn.spawn(
+                    agent_id.to_string(),
+                    "sleep".to_string(),
+                    vec!["30".to_string()],
+                    Vec::new(),
+                    workdir,
+                    None,
+                    None,
+                    None,
+                    RestartPolicy::Never,
+                )
+                .unwrap()
+            };
+
+            // Spawned by the daemon, not yet recorded in state.db
+            let starting = worktrees.create_worktree("starting", "main").unwrap();
+            spawn(&mut conn, "starting", starting.path.clone());
+            // Running in the daemon, but its checkout was deleted underneath it
+            let deleted = worktrees.create_worktree("deleted", "main").unwrap();
+            spawn(&mut conn, "deleted", deleted.path.clone());
+            store
+                .upsert_session(&SessionRecord {
+                    runtime_kind: "daemon".to_string(),
+                    isolation_mode: IsolationMode::Worktree,
+                    branch_name: deleted.branch.clone(),
+                    checkout_path: deleted.path.clone(),
+                    ..session_record("deleted")
+                })
+                .unwrap();
+            std::fs::remove_dir_all(&deleted.path).unwrap();
+
+            let report = reconcile_with_daemon(&repo_path, &store, &socket).unwrap();
+            assert!(report.registered_worktrees.is_empty(), "{:?}", report.registered_worktrees);
+            assert!(store.get_session("starting").unwrap().is_none());
+            assert_eq!(report.pruned_worktrees, vec!["deleted".to_string()]);
+            assert_eq!(report.failed_sessions, vec!["deleted".to_string()]);
+            assert_eq!(store.get_session("deleted").unwrap().unwrap().status, SessionStatus::Failed);
+
+            // Once the daemon is gone, the untracked worktree is adopted
+            conn.request(&crate::daemon::ipc::DaemonCommand::Shutdown).unwrap();
+            while DaemonConnection::connect(&socket).is_ok() {
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+            let report = reconcile_with_daemon(&repo_path, &store, &socket).unwrap();
+            assert_eq!(report.registered_worktrees, vec!["starting".to_string()]);
+        })
+        .await
+        .unwrap();
+
+        server.await.unwrap().unwrap();
+    }
+}
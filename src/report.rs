@@ -0,0 +1,387 @@
+//! Review reports for finished agents (`.rembrandt/reports/<agent>.md`).
+//!
+//! A report gathers what a reviewer needs in one place: the task and prompt,
+//! per-file diff stats against the base, validation results, timeline
+//! highlights and cost. With `report_summary_command` configured, the
+//! command is also handed the prompt and diff on stdin and its output is
+//! included as a change summary. Reports are written on stop/completion when
+//! `generate_reports` is on, or on demand with `rembrandt report <agent>`.
+
+use crate::competition::LintCheck;
+use crate::config::AppConfig;
+use crate::state::{SessionRecord, StatePool};
+use crate::sync::{run_checks, CheckRun};
+use crate::timeline::{TimelineEntry, TimelineKind};
+use crate::usage::{format_tokens, UsageTotals};
+use crate::worktree::WorktreeManager;
+use crate::{RembrandtError, Result};
+use rembrandt_core::FileDiff;
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Longest diff (in bytes) handed to the summary command
+const MAX_SUMMARY_DIFF_BYTES: usize = 40_000;
+
+/// Most recent timeline entries shown in a report
+const TIMELINE_HIGHLIGHTS: usize = 20;
+
+/// How reports are generated
+#[derive(Debug, Clone)]
+pub struct ReportOptions {
+    /// Branch the diff stats are taken against
+    pub base_branch: String,
+    /// Rerun validation in the agent's checkout
+    pub validate: bool,
+    pub validation_commands: Vec<String>,
+    pub lint_checks: Vec<LintCheck>,
+    /// Shell command that reads a prompt on stdin and prints a summary
+    pub summary_command: Option<String>,
+}
+
+impl ReportOptions {
+    pub fn from_config(config: &AppConfig) -> Self {
+        Self {
+            base_branch: "main".to_string(),
+            validate: true,
+            validation_commands: config.validation_commands.clone(),
+            lint_checks: config.lint.clone(),
+            summary_command: config.report_summary_command.clone(),
+        }
+    }
+}
+
+/// Where an agent's report is written
+pub fn report_path(repo_path: &Path, agent_id: &str) -> PathBuf {
+    repo_path
+        .join(".rembrandt")
+        .join("reports")
+        .join(format!("{}.md", agent_id))
+}
+
+/// Write `agent_id`'s report, returning its path
+#[tracing::instrument(skip(state, options))]
+pub async fn generate(
+    repo_path: &Path,
+    state: &StatePool,
+    agent_id: &str,
+    options: &ReportOptions,
+) -> Result<PathBuf> {
+    let id = agent_id.to_string();
+    let (session, timeline, usage) = state
+        .call(move |store| {
+            let session = store
+                .get_session(&id)?
+                .ok_or_else(|| RembrandtError::SessionNotFound(id.clone()))?;
+            Ok((session, store.timeline(&id)?, store.usage_for(&id)?))
+        })
+        .await?;
+
+    // A branch that's gone (merged and deleted) just has no diff to show
+    let files = WorktreeManager::new(repo_path)?
+        .branch_diff(&session.branch_name, &options.base_branch)
+        .unwrap_or_default();
+    let checks = if options.validate && session.checkout_path.exists() {
+        run_checks(
+            agent_id,
+            &session.checkout_path,
+            &options.base_branch,
+            &options.validation_commands,
+            &options.lint_checks,
+        )
+        .await?
+    } else {
+        Vec::new()
+    };
+    let summary = options.summary_command.as_ref().map(|command| {
+        summarize(repo_path, command, &session, &options.base_branch)
+            .unwrap_or_else(|e| format!("_Summary unavailable: {}_", e))
+    });
+
+    let report = render(
+        &session,
+        &options.base_branch,
+        &files,
+        &checks,
+        &timeline,
+        &usage,
+        summary.as_deref(),
+    );
+    let path = report_path(repo_path, agent_id);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(&path, report)?;
+    Ok(path)
+}
+
+fn render(
+    session: &SessionRecord,
+    base_branch: &str,
+    files: &[FileDiff],
+    checks: &[CheckRun],
+    timeline: &[TimelineEntry],
+    usage: &UsageTotals,
+    summary: Option<&str>,
+) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# {}\n", session.agent_id);
+    let _ = writeln!(out, "- **Status:** {}", session.status);
+    let _ = writeln!(out, "- **Task:** {}", session.task_id.as_deref().unwrap_or("-"));
+    let _ = writeln!(out, "- **Runtime:** {}", session.runtime_kind);
+    let _ = writeln!(out, "- **Model:** {}", session.model.as_deref().unwrap_or("-"));
+    let _ = writeln!(out, "- **Branch:** `{}` (against `{}`)", session.branch_name, base_branch);
+    let _ = writeln!(
+        out,
+        "- **Cost:** ${:.2} ({} input / {} output tokens)",
+        usage.cost_usd,
+        format_tokens(usage.input_tokens),
+        format_tokens(usage.output_tokens)
+    );
+    let _ = writeln!(
+        out,
+        "- **Ran:** {} to {}",
+        session.created_at.format("%Y-%m-%d %H:%M"),
+        session.updated_at.format("%Y-%m-%d %H:%M")
+    );
+
+    if let Some(prompt) = &session.prompt {
+        let _ = writeln!(out, "\n## Prompt\n");
+        for line in prompt.lines() {
+            let _ = writeln!(out, "> {}", line);
+        }
+    }
+
+    if let Some(summary) = summary {
+        let _ = writeln!(out, "\n## Summary\n\n{}", summary.trim());
+    }
+
+    let _ = writeln!(out, "\n## Changes\n");
+    if files.is_empty() {
+        let _ = writeln!(out, "No changes against `{}`.", base_branch);
+    } else {
+        let (additions, deletions) = files
+            .iter()
+            .fold((0, 0), |(a, d), f| (a + f.additions, d + f.deletions));
+        let _ = writeln!(out, "{} files changed, +{} -{}\n", files.len(), additions, deletions);
+        let _ = writeln!(out, "| File | Change | + | - |");
+        let _ = writeln!(out, "|------|--------|---|---|");
+        for file in files {
+            let _ = writeln!(
+                out,
+                "| `{}` | {:?} | {} | {} |",
+                file.path, file.change, file.additions, file.deletions
+            );
+        }
+    }
+
+    if !checks.is_empty() {
+        let _ = writeln!(out, "\n## Validation\n");
+        for check in checks {
+            let mark = match (check.passed, check.blocking) {
+                (true, _) => "✓",
+                (false, true) => "✗",
+                (false, false) => "⚠",
+            };
+            let _ = writeln!(out, "- {} {}", mark, check.name);
+            if !check.passed && !check.output.is_empty() {
+                let _ = writeln!(out, "\n```\n{}\n```\n", check.output);
+            }
+        }
+    }
+
+    // Tool runs are too chatty for a summary
+    let highlights: Vec<&TimelineEntry> = timeline
+        .iter()
+        .filter(|e| e.kind != TimelineKind::ToolRun)
+        .collect();
+    if !highlights.is_empty() {
+        let _ = writeln!(out, "\n## Timeline\n");
+        for entry in &highlights[highlights.len().saturating_sub(TIMELINE_HIGHLIGHTS)..] {
+            let _ = writeln!(
+                out,
+                "- {} {}{}",
+                entry.created_at.format("%H:%M:%S"),
+                entry.kind.as_str(),
+                entry.detail.as_deref().map(|d| format!(": {}", d)).unwrap_or_default()
+            );
+        }
+    }
+
+    out
+}
+
+/// Ask `command` for a summary of the branch's changes
+fn summarize(
+    repo_path: &Path,
+    command: &str,
+    session: &SessionRecord,
+    base_branch: &str,
+) -> Result<String> {
+    let output = Command::new("git")
+        .args(["diff", &format!("{}...{}", base_branch, session.branch_name)])
+        .current_dir(repo_path)
+        .output()?;
+    let mut diff = String::from_utf8_lossy(&output.stdout).into_owned();
+    if diff.len() > MAX_SUMMARY_DIFF_BYTES {
+        let mut cut = MAX_SUMMARY_DIFF_BYTES;
+        while !diff.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        diff.truncate(cut);
+        diff.push_str("\n[... diff truncated]");
+    }
+
+    let prompt = format!(
+        "Summarize this change for a code reviewer in a short paragraph: what it does, \
+         anything risky, and anything left unfinished.\n\n\
+         Task: {}\n\n{}",
+        session.prompt.as_deref().unwrap_or("(no prompt recorded)"),
+        diff
+    );
+    let mut child = Command::new("sh")
+        .args(["-c", command])
+        .current_dir(repo_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    // Written from another thread while the output is read, so a command
+    // that prints before it has read everything can't deadlock with us
+    let stdin = child.stdin.take();
+    let writer = std::thread::spawn(move || stdin.map(|mut stdin| stdin.write_all(prompt.as_bytes())));
+    let output = child.wait_with_output()?;
+    // A command that stops reading early just doesn't see the whole prompt
+    let _ = writer.join();
+    if !output.status.success() {
+        return Err(RembrandtError::Runtime(format!(
+            "summary command exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::isolation::IsolationMode;
+    use crate::state::SessionStatus;
+    use crate::test_support::{init_repo, session_record};
+    use crate::usage::UsageSample;
+
+    #[tokio::test]
+    async fn writes_a_report_with_every_section() {
+        let dir = tempfile::tempdir().unwrap();
+        init_repo(dir.path());
+
+        let worktree = WorktreeManager::new(dir.path())
+            .unwrap()
+            .create_worktree("agent", "main")
+            .unwrap();
+        std::fs::write(worktree.path.join("hello.txt"), "hello\nworld\n").unwrap();
+        for args in [&["add", "hello.txt"][..], &["commit", "-qm", "hello"]] {
+            let status = Command::new("git")
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .current_dir(&worktree.path)
+                .status()
+                .unwrap();
+            assert!(status.success(), "git {:?} failed", args);
+        }
+
+        let state = StatePool::open(dir.path()).unwrap();
+        let store = state.get().unwrap();
+        store
+            .upsert_session(&SessionRecord {
+                isolation_mode: IsolationMode::Worktree,
+                branch_name: worktree.branch.clone(),
+                checkout_path: worktree.path.clone(),
+                task_id: Some("bd-7".to_string()),
+                status: SessionStatus::Completed,
+                prompt: Some("Say hello".to_string()),
+                ..session_record("agent")
+            })
+            .unwrap();
+        store.record_timeline("agent", TimelineKind::Commit, Some("hello")).unwrap();
+        store.record_timeline("agent", TimelineKind::ToolRun, Some("Bash(ls)")).unwrap();
+        store
+            .record_usage(
+                "agent",
+                &UsageSample {
+                    input_tokens: 1200,
+                    output_tokens: 300,
+                    cost_usd: Some(0.42),
+                    model: None,
+                    source: "claude",
+                },
+            )
+            .unwrap();
+
+        let options = ReportOptions {
+            base_branch: "main".to_string(),
+            validate: true,
+            validation_commands: vec!["test -f hello.txt".to_string()],
+            lint_checks: vec![LintCheck::new("fmt", crate::competition::LintSeverity::Warn).with_command("false")],
+            summary_command: Some("grep -q 'Say hello' && echo Adds a greeting file.".to_string()),
+        };
+        let path = generate(dir.path(), &state, "agent", &options).await.unwrap();
+        assert_eq!(path, report_path(dir.path(), "agent"));
+
+        let report = std::fs::read_to_string(path).unwrap();
+        assert!(report.starts_with("# agent\n"));
+        assert!(report.contains("- **Task:** bd-7"));
+        assert!(report.contains("- **Cost:** $0.42 (1.2k input / 300 output tokens)"));
+        assert!(report.contains("> Say hello"));
+        assert!(report.contains("## Summary\n\nAdds a greeting file."));
+        assert!(report.contains("| `hello.txt` | Added | 2 | 0 |"));
+        assert!(report.contains("- ✓ test -f hello.txt"));
+        assert!(report.contains("- ⚠ lint: fmt (warning)"));
+        assert!(report.contains("commit: hello"));
+        assert!(!report.contains("Bash(ls)"));
+    }
+
+    #[tokio::test]
+    async fn reports_what_it_can_when_parts_are_missing_or_failing() {
+        let dir = tempfile::tempdir().unwrap();
+        init_repo(dir.path());
+        let state = StatePool::open(dir.path()).unwrap();
+        let options = ReportOptions {
+            base_branch: "main".to_string(),
+            validate: true,
+            validation_commands: vec!["echo 2 tests failed; exit 1".to_string()],
+            lint_checks: Vec::new(),
+            summary_command: Some("cat >/dev/null; echo model overloaded >&2; exit 2".to_string()),
+        };
+
+        let err = generate(dir.path(), &state, "nobody", &options).await.unwrap_err();
+        assert!(matches!(err, RembrandtError::SessionNotFound(_)), "{}", err);
+        assert!(!report_path(dir.path(), "nobody").exists());
+
+        // Merged and cleaned up: no branch, no checkout
+        let store = state.get().unwrap();
+        store
+            .upsert_session(&SessionRecord {
+                checkout_path: dir.path().join("gone"),
+                ..session_record("merged")
+            })
+            .unwrap();
+        let report = std::fs::read_to_string(generate(dir.path(), &state, "merged", &options).await.unwrap()).unwrap();
+        assert!(report.contains("No changes against `main`."));
+        assert!(!report.contains("## Validation"), "nothing to validate without a checkout");
+        assert!(report.contains("_Summary unavailable: "), "{}", report);
+        assert!(report.contains("model overloaded"));
+
+        // A failing check shows its output
+        store
+            .upsert_session(&SessionRecord {
+                checkout_path: dir.path().to_path_buf(),
+                ..session_record("failing")
+            })
+            .unwrap();
+        let report = std::fs::read_to_string(generate(dir.path(), &state, "failing", &options).await.unwrap()).unwrap();
+        assert!(report.contains("- ✗ echo 2 tests failed; exit 1\n\n```\n2 tests failed\n```"), "{}", report);
+    }
+}
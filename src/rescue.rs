@@ -0,0 +1,144 @@
+//! Guarding uncommitted work before an agent's worktree goes away.
+//!
+//! `rembrandt cleanup` and `rembrandt gc` skip worktrees holding uncommitted
+//! changes or commits their upstream doesn't have, unless given `--force`;
+//! even then the changes are snapshotted first (see `snapshot`) under a
+//! `rescue` label, so they survive the worktree. `rembrandt stop` and the
+//! dashboard's kill rescue a dirty worktree the same way. The snapshot is
+//! recorded in state.db and kept alive by its `refs/rembrandt/snapshots/` ref.
+
+use crate::snapshot::{self, SnapshotRecord};
+use crate::state::StateStore;
+use crate::worktree::GUARD_FILE;
+use crate::Result;
+use git2::{BranchType, Repository, StatusOptions};
+use std::path::Path;
+
+/// Label prefix of snapshots taken to rescue work
+pub const RESCUE_LABEL: &str = "rescue";
+
+/// Work in a checkout that only exists there
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorkAtRisk {
+    /// Changed and untracked paths, ignored files excluded
+    pub uncommitted: Vec<String>,
+    /// Commits on the checked-out branch its upstream doesn't have
+    pub unpushed: usize,
+}
+
+impl WorkAtRisk {
+    pub fn is_empty(&self) -> bool {
+        self.uncommitted.is_empty() && self.unpushed == 0
+    }
+
+    /// e.g. `3 uncommitted files, 1 unpushed commit`
+    pub fn summary(&self) -> String {
+        let plural = |n: usize| if n == 1 { "" } else { "s" };
+        let mut parts = Vec::new();
+        if !self.uncommitted.is_empty() {
+            let n = self.uncommitted.len();
+            parts.push(format!("{} uncommitted file{}", n, plural(n)));
+        }
+        if self.unpushed > 0 {
+            parts.push(format!("{} unpushed commit{}", self.unpushed, plural(self.unpushed)));
+        }
+        parts.join(", ")
+    }
+}
+
+/// What would be lost if `checkout` were deleted now
+pub fn check(checkout: &Path) -> Result<WorkAtRisk> {
+    let repo = Repository::open(checkout)?;
+    let mut options = StatusOptions::new();
+    options.include_untracked(true).recurse_untracked_dirs(true).include_ignored(false);
+    let uncommitted = repo
+        .statuses(Some(&mut options))?
+        .iter()
+        .filter_map(|entry| entry.path().map(str::to_string))
+        .filter(|path| !path.starts_with(".rembrandt/") && path != GUARD_FILE)
+        .collect();
+
+    let unpushed = match repo.head() {
+        Ok(head) if head.is_branch() => {
+            let name = head.shorthand().unwrap_or_default();
+            let upstream = repo.find_branch(name, BranchType::Local).and_then(|b| b.upstream());
+            match (head.target(), upstream.ok().and_then(|u| u.get().target())) {
+                (Some(local), Some(upstream)) => repo.graph_ahead_behind(local, upstream)?.0,
+                _ => 0,
+            }
+        }
+        _ => 0,
+    };
+
+    Ok(WorkAtRisk { uncommitted, unpushed })
+}
+
+/// Snapshot `agent_id`'s uncommitted changes before `reason` (e.g. `cleanup`);
+/// `None` when there is nothing uncommitted to save.
+pub fn rescue(
+    repo_path: &Path,
+    store: &StateStore,
+    agent_id: &str,
+    checkout: &Path,
+    reason: &str,
+) -> Result<Option<SnapshotRecord>> {
+    if check(checkout)?.uncommitted.is_empty() {
+        return Ok(None);
+    }
+    let label = format!("{} before {}", RESCUE_LABEL, reason);
+    let record = snapshot::snapshot(repo_path, store, agent_id, Some(&label))?;
+    store.record_event(
+        Some(agent_id),
+        "rescue",
+        &format!("uncommitted work saved as snapshot #{} before {}", record.id, reason),
+    )?;
+    Ok(Some(record))
+}
+
+/// How to get rescued work back once the worktree is gone
+pub fn recovery_hint(record: &SnapshotRecord) -> String {
+    format!(
+        "git switch -c rescue/{}-{} {}",
+        record.agent_id,
+        record.id,
+        record.ref_name()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::git;
+    use crate::worktree::WorktreeManager;
+
+    #[test]
+    fn dirty_worktrees_are_rescued_into_snapshots() {
+        let dir = tempfile::tempdir().unwrap();
+        git(dir.path(), &["init", "-q", "-b", "main"]);
+        std::fs::write(dir.path().join("lib.rs"), "fn a() {}\n").unwrap();
+        git(dir.path(), &["add", "lib.rs"]);
+        git(dir.path(), &["commit", "-qm", "init"]);
+        let store = StateStore::open(dir.path()).unwrap();
+        let manager = WorktreeManager::new(dir.path()).unwrap().with_locking(true);
+        let info = manager.create_worktree("a1", "main").unwrap();
+
+        // Fresh worktree: the guard and state are not work
+        assert!(check(&info.path).unwrap().is_empty());
+        assert!(rescue(dir.path(), &store, "a1", &info.path, "cleanup").unwrap().is_none());
+
+        std::fs::write(info.path.join("lib.rs"), "fn a() { todo!() }\n").unwrap();
+        std::fs::write(info.path.join("notes.md"), "wip\n").unwrap();
+        let at_risk = check(&info.path).unwrap();
+        assert_eq!(at_risk.uncommitted, vec!["lib.rs", "notes.md"]);
+        assert_eq!(at_risk.summary(), "2 uncommitted files");
+
+        let record = rescue(dir.path(), &store, "a1", &info.path, "cleanup").unwrap().unwrap();
+        assert_eq!(record.label.as_deref(), Some("rescue before cleanup"));
+        manager.remove_worktree("a1").unwrap();
+
+        // The work outlives the worktree
+        let hint = recovery_hint(&record);
+        assert!(hint.starts_with("git switch -c rescue/a1-"), "{}", hint);
+        git(dir.path(), &["show", &format!("{}:notes.md", record.ref_name())]);
+    }
+}
@@ -0,0 +1,315 @@
+//! Resolving a merge whose rebase conflicted.
+//!
+//! `rembrandt merge` rebases an agent's branch onto the base before merging
+//! it. When that rebase conflicts, it is replayed in a worktree of its own
+//! on `rembrandt/resolve-<agent>`, started at the agent's tip, and left
+//! stopped at the conflict; the agent's worktree, where it may still be
+//! running, is untouched. A human, or a resolver agent started with
+//! `--spawn-resolver`, fixes the conflicted files and finishes the rebase,
+//! and `rembrandt merge resolve-<agent>` lands the result. The resolution
+//! is recorded in state.db against the merge-queue entry that conflicted,
+//! which is marked merged (and its `merge-conflict` attention resolved) when
+//! the resolver's branch is.
+
+use crate::isolation::IsolationMode;
+use crate::merge_queue::{MergeQueueEntry, MergeStatus, MERGE_CONFLICT};
+use crate::state::{SessionRecord, SessionStatus, StateStore};
+use crate::worktree::WorktreeManager;
+use crate::{RembrandtError, Result};
+use chrono::{DateTime, Utc};
+use git2::{Repository, RepositoryState};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// `runtime_kind` of a resolution's session until an agent is started in it
+pub const RESOLUTION_RUNTIME: &str = "resolution";
+
+/// A conflicted merge being resolved in its own worktree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolution {
+    pub id: i64,
+    /// The merge-queue entry that conflicted
+    pub merge_id: i64,
+    /// The agent whose branch conflicted
+    pub agent_id: String,
+    /// Id the resolution's worktree and session go by
+    pub resolver_id: String,
+    pub base_branch: String,
+    pub branch_name: String,
+    pub checkout_path: PathBuf,
+    /// Files with conflicts where the rebase stopped
+    pub conflicted: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+/// Id of the resolution for `agent_id`'s merges
+pub fn resolver_id(agent_id: &str) -> String {
+    format!("resolve-{}", agent_id)
+}
+
+/// Replay the conflicting rebase of `entry` onto `base` in a resolution
+/// worktree, replacing an earlier resolution of the same agent unless that
+/// one is still underway (mid-rebase or with uncommitted changes)
+///
+/// Returns None, leaving nothing behind, when the rebase now applies
+/// cleanly.
+pub fn prepare(
+    store: &StateStore,
+    worktrees: &WorktreeManager,
+    entry: &MergeQueueEntry,
+    base: &str,
+) -> Result<Option<Resolution>> {
+    let resolver_id = resolver_id(&entry.agent_id);
+    if let Some(session) = store.get_session(&resolver_id)?
+        && session.status.is_active()
+    {
+        return Err(RembrandtError::Worktree(format!(
+            "{} is still working on the last conflict; stop it before preparing another",
+            resolver_id
+        )));
+    }
+    // Someone may be partway through resolving the last conflict by hand
+    if let Some(previous) = store.open_resolution(&resolver_id)?
+        && previous.checkout_path.exists()
+        && (unfinished(&previous)? || !crate::rescue::check(&previous.checkout_path)?.uncommitted.is_empty())
+    {
+        return Err(RembrandtError::Worktree(format!(
+            "{} is still being resolved in {}; finish it and merge {}, or remove that worktree, \
+             before preparing another",
+            resolver_id,
+            previous.checkout_path.display(),
+            resolver_id
+        )));
+    }
+    discard(store, worktrees, &resolver_id)?;
+
+    let worktree = worktrees.create_worktree(&resolver_id, &entry.branch_name)?;
+    let output = Command::new("git")
+        .args(["rebase", base])
+        .current_dir(&worktree.path)
+        .output()?;
+    if output.status.success() {
+        discard(store, worktrees, &resolver_id)?;
+        return Ok(None);
+    }
+    let conflicted = conflicted_files(&worktree.path)?;
+    if conflicted.is_empty() {
+        let _ = Command::new("git").args(["rebase", "--abort"]).current_dir(&worktree.path).output();
+        discard(store, worktrees, &resolver_id)?;
+        return Err(RembrandtError::Worktree(format!(
+            "git rebase {} failed: {}",
+            base,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    // A session makes the worktree `rembrandt spawn -C` and `rembrandt
+    // merge` targets, carrying the original agent's task
+    let original = store.get_session(&entry.agent_id)?;
+    let now = Utc::now();
+    store.upsert_session(&SessionRecord {
+        agent_id: resolver_id.clone(),
+        runtime_kind: RESOLUTION_RUNTIME.to_string(),
+        runtime_session_id: None,
+        isolation_mode: IsolationMode::Worktree,
+        branch_name: worktree.branch.clone(),
+        checkout_path: worktree.path.clone(),
+        task_id: original.as_ref().and_then(|s| s.task_id.clone()),
+        status: SessionStatus::Stopped,
+        model: None,
+        created_at: now,
+        updated_at: now,
+        prompt: None,
+        agent_type: original.and_then(|s| s.agent_type),
+        updated_seq: 0,
+    })?;
+    let mut resolution = Resolution {
+        id: 0,
+        merge_id: entry.id,
+        agent_id: entry.agent_id.clone(),
+        resolver_id,
+        base_branch: base.to_string(),
+        branch_name: worktree.branch,
+        checkout_path: worktree.path,
+        conflicted,
+        created_at: now,
+        resolved_at: None,
+    };
+    resolution.id = store.record_resolution(&resolution)?;
+    Ok(Some(resolution))
+}
+
+/// What a resolver agent is asked to do
+pub fn prompt(resolution: &Resolution) -> String {
+    format!(
+        "Rebasing {agent}'s work onto {base} stopped on conflicts in:\n{files}\n\n\
+         Resolve each conflict so both sides' intent survives, `git add` the files and run \
+         `git rebase --continue`. Later commits may conflict too; repeat until the rebase \
+         finishes. Don't abort or restart the rebase. Then run the project's tests and fix \
+         what the resolution broke.",
+        agent = resolution.agent_id,
+        base = resolution.base_branch,
+        files = resolution
+            .conflicted
+            .iter()
+            .map(|file| format!("- {}", file))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    )
+}
+
+/// Whether the rebase in `resolution`'s worktree is still underway
+pub fn unfinished(resolution: &Resolution) -> Result<bool> {
+    let state = Repository::open(&resolution.checkout_path)?.state();
+    Ok(state != RepositoryState::Clean)
+}
+
+/// Close `resolver_id`'s resolution now that its branch has merged,
+/// marking the merge it resolved merged too; None if it has none
+pub fn finish(store: &StateStore, resolver_id: &str, detail: &str) -> Result<Option<Resolution>> {
+    let Some(resolution) = store.open_resolution(resolver_id)? else {
+        return Ok(None);
+    };
+    store.close_resolution(resolution.id)?;
+    store.update_merge(
+        resolution.merge_id,
+        MergeStatus::Merged,
+        Some(&format!("resolved by {}, {}", resolver_id, detail)),
+    )?;
+    for item in store.open_attention()? {
+        if item.kind == MERGE_CONFLICT && item.agent_id.as_deref() == Some(resolution.agent_id.as_str()) {
+            store.resolve_attention(item.id)?;
+        }
+    }
+    Ok(Some(resolution))
+}
+
+/// Paths with unmerged changes in `checkout`
+fn conflicted_files(checkout: &Path) -> Result<Vec<String>> {
+    let output = Command::new("git")
+        .args(["diff", "--name-only", "--diff-filter=U"])
+        .current_dir(checkout)
+        .output()?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Remove an earlier resolution's worktree and branch, closing it
+fn discard(store: &StateStore, worktrees: &WorktreeManager, resolver_id: &str) -> Result<()> {
+    if let Some(previous) = store.open_resolution(resolver_id)? {
+        store.close_resolution(previous.id)?;
+    }
+    worktrees.remove_worktree(resolver_id)?;
+    let branch = format!("rembrandt/{}", resolver_id);
+    if Repository::open(worktrees.repo_path())?
+        .find_branch(&branch, git2::BranchType::Local)
+        .is_ok()
+    {
+        worktrees.delete_branch(&branch)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::git;
+
+    /// `claude-1a2b`'s branch queued for merging, conflicting with main on
+    /// a.txt
+    fn conflicted_merge(repo_path: &Path) -> (StateStore, WorktreeManager, MergeQueueEntry, PathBuf) {
+        git(repo_path, &["init", "-q", "-b", "main"]);
+        git(repo_path, &["config", "user.name", "t"]);
+        git(repo_path, &["config", "user.email", "t@example.com"]);
+        std::fs::write(repo_path.join("a.txt"), "base\n").unwrap();
+        git(repo_path, &["add", "."]);
+        git(repo_path, &["commit", "-q", "-m", "init"]);
+
+        let store = StateStore::open(repo_path).unwrap();
+        let worktrees = WorktreeManager::new(repo_path).unwrap();
+        let agent = worktrees.create_worktree("claude-1a2b", "main").unwrap();
+        std::fs::write(agent.path.join("a.txt"), "agent\n").unwrap();
+        git(&agent.path, &["commit", "-q", "-am", "agent"]);
+        std::fs::write(repo_path.join("a.txt"), "human\n").unwrap();
+        git(repo_path, &["commit", "-q", "-am", "human"]);
+
+        let merge_id = store.enqueue_merge("claude-1a2b", &agent.branch, &agent.path).unwrap();
+        store.update_merge(merge_id, MergeStatus::Conflicted, Some("rebase conflicts")).unwrap();
+        store.raise_attention(Some("claude-1a2b"), MERGE_CONFLICT, "rebase conflicts").unwrap();
+        let entry = store.merge_queue().unwrap().remove(0);
+        (store, worktrees, entry, agent.path)
+    }
+
+    #[test]
+    fn conflicting_rebase_is_left_in_a_resolution_worktree() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo_path = dir.path();
+        let (store, worktrees, entry, agent_path) = conflicted_merge(repo_path);
+
+        let resolution = prepare(&store, &worktrees, &entry, "main").unwrap().unwrap();
+        assert_eq!(resolution.resolver_id, "resolve-claude-1a2b");
+        assert_eq!(resolution.conflicted, vec!["a.txt".to_string()]);
+        assert!(unfinished(&resolution).unwrap());
+        assert!(prompt(&resolution).contains("- a.txt"));
+        assert_eq!(store.open_resolution("resolve-claude-1a2b").unwrap(), Some(resolution.clone()));
+        // The agent's own worktree is left as it was
+        assert_eq!(std::fs::read_to_string(agent_path.join("a.txt")).unwrap(), "agent\n");
+        // A resolution in progress isn't thrown away by the next attempt
+        let err = prepare(&store, &worktrees, &entry, "main").unwrap_err();
+        assert!(err.to_string().contains("still being resolved"), "{}", err);
+        assert!(unfinished(&resolution).unwrap());
+
+        std::fs::write(resolution.checkout_path.join("a.txt"), "agent and human\n").unwrap();
+        git(&resolution.checkout_path, &["add", "a.txt"]);
+        git(&resolution.checkout_path, &["-c", "core.editor=true", "rebase", "--continue"]);
+        assert!(!unfinished(&resolution).unwrap());
+
+        finish(&store, "resolve-claude-1a2b", "merged at abc1234").unwrap().unwrap();
+        assert_eq!(store.merge_queue().unwrap()[0].status, MergeStatus::Merged);
+        assert!(store.open_attention().unwrap().is_empty());
+        assert_eq!(store.open_resolution("resolve-claude-1a2b").unwrap(), None);
+    }
+
+    #[test]
+    fn an_in_progress_resolution_is_not_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let (store, worktrees, entry, _) = conflicted_merge(dir.path());
+        let first = prepare(&store, &worktrees, &entry, "main").unwrap().unwrap();
+
+        // Rebased, but the result isn't committed yet
+        std::fs::write(first.checkout_path.join("a.txt"), "agent and human\n").unwrap();
+        git(&first.checkout_path, &["add", "a.txt"]);
+        git(&first.checkout_path, &["-c", "core.editor=true", "rebase", "--continue"]);
+        std::fs::write(first.checkout_path.join("a.txt"), "still tidying\n").unwrap();
+        let err = prepare(&store, &worktrees, &entry, "main").unwrap_err();
+        assert!(err.to_string().contains("still being resolved"), "{}", err);
+        assert_eq!(std::fs::read_to_string(first.checkout_path.join("a.txt")).unwrap(), "still tidying\n");
+
+        // An agent working on it
+        git(&first.checkout_path, &["checkout", "a.txt"]);
+        let mut resolver = store.get_session(&first.resolver_id).unwrap().unwrap();
+        resolver.status = SessionStatus::Active;
+        store.upsert_session(&resolver).unwrap();
+        let err = prepare(&store, &worktrees, &entry, "main").unwrap_err();
+        assert!(err.to_string().contains("still working on the last conflict"), "{}", err);
+        assert_eq!(store.open_resolution(&first.resolver_id).unwrap(), Some(first.clone()));
+
+        // Once it's idle and clean, a fresh attempt replaces it
+        resolver.status = SessionStatus::Stopped;
+        store.upsert_session(&resolver).unwrap();
+        let second = prepare(&store, &worktrees, &entry, "main").unwrap().unwrap();
+        assert_ne!(second.id, first.id);
+        assert!(unfinished(&second).unwrap());
+
+        // A base that doesn't exist fails without leaving a worktree behind
+        git(&second.checkout_path, &["rebase", "--abort"]);
+        let err = prepare(&store, &worktrees, &entry, "no-such-base").unwrap_err();
+        assert!(err.to_string().contains("git rebase no-such-base failed"), "{}", err);
+        assert!(!second.checkout_path.exists());
+        assert_eq!(store.open_resolution(&second.resolver_id).unwrap(), None);
+    }
+}
@@ -0,0 +1,329 @@
+//! Restoring the GUI's agents from a previous launch
+//!
+//! Every agent the GUI starts is recorded in its repository's state.db, the
+//! store the CLI uses, with its worktree, branch, task, model and the exact
+//! command line it was started with. When its
+//! process exits the session is marked completed or failed, and killing it
+//! from the GUI marks it stopped. A session still recorded as active whose
+//! process is gone — the GUI was closed with agents running in-process, or
+//! the daemon they ran in died — is offered for restore on the next launch:
+//! the agent starts again in its existing worktree, with the same command
+//! line. Agents still running in `rembrandt daemon` are followed again
+//! instead.
+//!
+//! Competitors aren't offered; a competition is started over as a whole.
+
+use crate::agent::AgentType;
+use crate::config::AppConfig;
+use crate::daemon::session::SessionStatus as PtyStatus;
+use crate::isolation::IsolationMode;
+use crate::state::{SessionQuery, SessionRecord, SessionStatus, StateStore};
+use crate::worktree::main_repo_root;
+use crate::{RembrandtError, Result};
+use chrono::{DateTime, Utc};
+use git2::Repository;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// `runtime_kind` of agents run in the GUI's own process
+pub const GUI_RUNTIME: &str = "gui";
+
+/// `runtime_kind` of agents run in `rembrandt daemon`, as the CLI records
+/// detached spawns
+pub const DAEMON_RUNTIME: &str = "daemon";
+
+/// An agent started in the GUI (or detached from the CLI) that is recorded
+/// as active but no longer runs
+#[derive(Debug, Clone, Serialize)]
+pub struct RestorableSession {
+    pub agent_id: String,
+    pub agent_type: Option<String>,
+    pub branch: String,
+    pub worktree: PathBuf,
+    pub task_id: Option<String>,
+    pub model: Option<String>,
+    /// When the session was last recorded as running
+    pub updated_at: DateTime<Utc>,
+}
+
+/// An agent the GUI has just started
+#[derive(Debug, Clone, Copy)]
+pub struct Launch<'a> {
+    pub agent_id: &'a str,
+    pub command: &'a str,
+    pub args: &'a [String],
+    pub workdir: &'a Path,
+    pub session_id: &'a str,
+    /// Whether it runs in `rembrandt daemon` rather than the GUI
+    pub daemon: bool,
+    pub task_id: Option<&'a str>,
+    pub model: Option<&'a str>,
+    /// First input typed into it
+    pub prompt: Option<&'a str>,
+}
+
+/// How to start a restored agent again
+#[derive(Debug, Clone)]
+pub struct Relaunch {
+    pub agent_id: String,
+    pub command: String,
+    pub args: Vec<String>,
+    pub workdir: PathBuf,
+    pub task_id: Option<String>,
+    pub model: Option<String>,
+    /// The agent's original first input, typed in again so it picks its
+    /// work back up; what it already did is in the worktree
+    pub prompt: Option<String>,
+}
+
+/// Record a started agent as active in its repository's state.db
+///
+/// Agents started outside a git repository have nowhere to be restored
+/// into and aren't recorded.
+pub fn record_launch(launch: &Launch<'_>) -> Result<()> {
+    let Some(repo_path) = main_repo_root(launch.workdir) else {
+        return Ok(());
+    };
+    let checkout = Repository::discover(launch.workdir)?;
+    let branch_name = checkout.head()?.shorthand().unwrap_or("HEAD").to_string();
+    let isolation_mode = if checkout.is_worktree() {
+        IsolationMode::Worktree
+    } else {
+        IsolationMode::Branch
+    };
+    let now = Utc::now();
+    let store = StateStore::open(&repo_path)?;
+    store.record_launch_command(launch.agent_id, launch.command, launch.args)?;
+    store.upsert_session(&SessionRecord {
+        agent_id: launch.agent_id.to_string(),
+        runtime_kind: if launch.daemon { DAEMON_RUNTIME } else { GUI_RUNTIME }.to_string(),
+        runtime_session_id: Some(launch.session_id.to_string()),
+        isolation_mode,
+        branch_name,
+        checkout_path: launch.workdir.canonicalize()?,
+        task_id: launch.task_id.map(str::to_string),
+        status: SessionStatus::Active,
+        model: launch.model.map(str::to_string),
+        created_at: now,
+        updated_at: now,
+        prompt: launch.prompt.map(str::to_string),
+        agent_type: Some(AgentType::from_command(launch.command).to_string()),
+        updated_seq: 0,
+    })
+}
+
+/// Mark an agent whose process exited completed (exit code 0) or failed,
+/// unless it was already stopped
+pub fn record_exit(repo_path: &Path, agent_id: &str, status: &PtyStatus) -> Result<()> {
+    let ended = match status {
+        PtyStatus::Running => return Ok(()),
+        PtyStatus::Exited(0) => SessionStatus::Completed,
+        PtyStatus::Exited(_) | PtyStatus::Failed(_) => SessionStatus::Failed,
+    };
+    end(repo_path, agent_id, ended)
+}
+
+/// Mark an agent stopped, so it isn't offered for restore
+pub fn record_stopped(repo_path: &Path, agent_id: &str) -> Result<()> {
+    end(repo_path, agent_id, SessionStatus::Stopped)
+}
+
+fn end(repo_path: &Path, agent_id: &str, status: SessionStatus) -> Result<()> {
+    StateStore::open(repo_path)?
+        .transition_status(agent_id, |current| current.status.is_active().then_some(status))?;
+    Ok(())
+}
+
+/// Active sessions in the repository around `path` that aren't among
+/// `running` agent ids and whose worktree is still there, most recent first
+pub fn restorable(path: &Path, running: &HashSet<String>) -> Result<Vec<RestorableSession>> {
+    let Some(repo_path) = main_repo_root(path) else {
+        return Ok(Vec::new());
+    };
+    let mut sessions: Vec<RestorableSession> = StateStore::open(&repo_path)?
+        .query_sessions(&SessionQuery::active())?
+        .into_iter()
+        .filter(|s| s.runtime_kind == GUI_RUNTIME || s.runtime_kind == DAEMON_RUNTIME)
+        .filter(|s| !running.contains(&s.agent_id) && s.checkout_path.exists())
+        .map(|s| RestorableSession {
+            agent_id: s.agent_id,
+            agent_type: s.agent_type,
+            branch: s.branch_name,
+            worktree: s.checkout_path,
+            task_id: s.task_id,
+            model: s.model,
+            updated_at: s.updated_at,
+        })
+        .collect();
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.updated_at));
+    Ok(sessions)
+}
+
+/// How to start `agent_id` again in its existing worktree
+pub fn relaunch(path: &Path, agent_id: &str) -> Result<Relaunch> {
+    let repo_path = main_repo_root(path)
+        .ok_or_else(|| RembrandtError::Worktree(format!("{} is not in a git repository", path.display())))?;
+    let store = StateStore::open(&repo_path)?;
+    let record = store
+        .get_session(agent_id)?
+        .ok_or_else(|| RembrandtError::Runtime(format!("No session recorded for {}", agent_id)))?;
+    if !record.status.is_active() {
+        return Err(RembrandtError::Runtime(format!(
+            "{} is {}; only active sessions are restored",
+            agent_id, record.status
+        )));
+    }
+    if !record.checkout_path.exists() {
+        return Err(RembrandtError::Worktree(format!(
+            "Worktree for {} is gone: {}",
+            agent_id,
+            record.checkout_path.display()
+        )));
+    }
+
+    // Sessions recorded before launch commands were have only their type
+    let (command, args) = match store.launch_command(agent_id)? {
+        Some(launched) => launched,
+        None => {
+            let agent = AgentType::from_str(record.agent_type.as_deref().unwrap_or("claude"));
+            let mut args: Vec<String> = agent.default_args().iter().map(|a| a.to_string()).collect();
+            if let Some(model) = &record.model {
+                args.extend(AppConfig::load(&repo_path).unwrap_or_default().model_args(&agent, model)?);
+            }
+            (agent.command().to_string(), args)
+        }
+    };
+    Ok(Relaunch {
+        agent_id: record.agent_id,
+        command,
+        args,
+        workdir: record.checkout_path,
+        task_id: record.task_id,
+        model: record.model,
+        prompt: record.prompt,
+    })
+}
+
+/// Stop offering `agent_id` for restore
+pub fn dismiss(path: &Path, agent_id: &str) -> Result<()> {
+    let repo_path = main_repo_root(path)
+        .ok_or_else(|| RembrandtError::Worktree(format!("{} is not in a git repository", path.display())))?;
+    record_stopped(&repo_path, agent_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{init_repo, session_record};
+    use crate::worktree::WorktreeManager;
+
+    #[test]
+    fn launches_are_restored_as_they_were_started() {
+        let dir = tempfile::tempdir().unwrap();
+        init_repo(dir.path());
+        let worktrees = WorktreeManager::new(dir.path()).unwrap();
+        let first = worktrees.create_worktree("claude-1", "main").unwrap();
+        let second = worktrees.create_worktree("claude-2", "main").unwrap();
+
+        let args = vec!["--model".to_string(), "opus".to_string()];
+        let launch = |agent_id, workdir| Launch {
+            agent_id,
+            command: "/opt/agents/bin/claude",
+            args: &args,
+            workdir,
+            session_id: "s1",
+            daemon: false,
+            task_id: Some("bd-1"),
+            model: Some("opus"),
+            prompt: Some("fix the parser"),
+        };
+        record_launch(&launch("claude-1", &first.path)).unwrap();
+        record_launch(&launch("claude-2", &second.path)).unwrap();
+
+        let offered: Vec<String> =
+            restorable(dir.path(), &HashSet::new()).unwrap().into_iter().map(|s| s.agent_id).collect();
+        assert_eq!(offered.len(), 2);
+        let running = HashSet::from(["claude-2".to_string()]);
+        assert_eq!(restorable(dir.path(), &running).unwrap().len(), 1);
+
+        // The same binary and arguments, not whatever `claude` is on PATH
+        let again = relaunch(dir.path(), "claude-1").unwrap();
+        assert_eq!(again.command, "/opt/agents/bin/claude");
+        assert_eq!(again.args, args);
+        assert_eq!(again.workdir, first.path.canonicalize().unwrap());
+        assert_eq!(again.prompt.as_deref(), Some("fix the parser"));
+
+        // Exited and dismissed agents aren't offered again
+        record_exit(dir.path(), "claude-1", &PtyStatus::Exited(0)).unwrap();
+        dismiss(dir.path(), "claude-2").unwrap();
+        assert!(restorable(dir.path(), &HashSet::new()).unwrap().is_empty());
+        let store = StateStore::open(dir.path()).unwrap();
+        assert_eq!(store.get_session("claude-1").unwrap().unwrap().status, SessionStatus::Completed);
+        assert_eq!(store.get_session("claude-2").unwrap().unwrap().status, SessionStatus::Stopped);
+        assert!(relaunch(dir.path(), "claude-1").is_err());
+
+        // A session recorded without its command line is started from its
+        // type, with the model flag from `[model_flags]`
+        let third = worktrees.create_worktree("amp-3", "main").unwrap();
+        let mut record = store.get_session("claude-2").unwrap().unwrap();
+        record.agent_id = "amp-3".to_string();
+        record.checkout_path = third.path.clone();
+        record.status = SessionStatus::Active;
+        record.agent_type = Some("ampcode".to_string());
+        record.model = Some("fast".to_string());
+        store.upsert_session(&record).unwrap();
+        std::fs::write(dir.path().join(".rembrandt/config.toml"), "[model_flags]\namp = \"--mode {model}\"\n")
+            .unwrap();
+        let again = relaunch(dir.path(), "amp-3").unwrap();
+        assert_eq!(again.command, "amp");
+        assert_eq!(again.args, vec!["--mode", "fast"]);
+    }
+
+    #[test]
+    fn refuses_what_it_cannot_restore() {
+        let outside = tempfile::tempdir().unwrap();
+        assert!(restorable(outside.path(), &HashSet::new()).unwrap().is_empty());
+        for err in [relaunch(outside.path(), "claude-1").unwrap_err(), dismiss(outside.path(), "claude-1").unwrap_err()] {
+            assert!(err.to_string().contains("is not in a git repository"), "{}", err);
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        init_repo(dir.path());
+        let err = relaunch(dir.path(), "nobody").unwrap_err();
+        assert!(err.to_string().contains("No session recorded for nobody"), "{}", err);
+
+        let store = StateStore::open(dir.path()).unwrap();
+        let worktree = WorktreeManager::new(dir.path()).unwrap().create_worktree("claude-1", "main").unwrap();
+        store
+            .upsert_session(&SessionRecord {
+                runtime_kind: GUI_RUNTIME.to_string(),
+                checkout_path: worktree.path.clone(),
+                ..session_record("claude-1")
+            })
+            .unwrap();
+        // Started from the CLI: not the GUI's to restore
+        store
+            .upsert_session(&SessionRecord {
+                checkout_path: worktree.path.clone(),
+                ..session_record("pi-2")
+            })
+            .unwrap();
+        assert_eq!(restorable(dir.path(), &HashSet::new()).unwrap().len(), 1);
+
+        // A worktree removed by hand is neither offered nor relaunched
+        std::fs::remove_dir_all(&worktree.path).unwrap();
+        assert!(restorable(dir.path(), &HashSet::new()).unwrap().is_empty());
+        let err = relaunch(dir.path(), "claude-1").unwrap_err();
+        assert!(err.to_string().contains("Worktree for claude-1 is gone"), "{}", err);
+
+        // An exit reported after a stop doesn't overwrite it
+        record_exit(dir.path(), "claude-1", &PtyStatus::Running).unwrap();
+        assert_eq!(store.get_session("claude-1").unwrap().unwrap().status, SessionStatus::Active);
+        record_stopped(dir.path(), "claude-1").unwrap();
+        record_exit(dir.path(), "claude-1", &PtyStatus::Exited(1)).unwrap();
+        assert_eq!(store.get_session("claude-1").unwrap().unwrap().status, SessionStatus::Stopped);
+        // Unknown agents are nothing to mark
+        record_stopped(dir.path(), "nobody").unwrap();
+    }
+}
@@ -0,0 +1,160 @@
+//! In-memory runtime for orchestrator tests (`--features test-util`).
+//!
+//! Sessions are plain records: spawning never starts a process, statuses
+//! change only when a test sets or scripts them, and messages and usage are
+//! kept for assertions.
+
+use super::{AgentHandle, AgentRuntime, RuntimeAgentStatus, RuntimeSessionId};
+use crate::isolation::IsolationContext;
+use crate::usage::UsageSample;
+use crate::{RembrandtError, Result};
+use async_trait::async_trait;
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// A session spawned on an [`InMemoryRuntime`]
+#[derive(Debug, Clone)]
+pub struct MemorySession {
+    pub agent_id: String,
+    pub checkout_path: PathBuf,
+    pub prompt: Option<String>,
+    pub model: Option<String>,
+    pub status: RuntimeAgentStatus,
+    /// Messages sent with `send_message`, oldest first
+    pub messages: Vec<String>,
+    /// Usage `drain_usage` hands over next
+    pub pending_usage: Vec<UsageSample>,
+    /// Statuses the next `status` calls report, one per call
+    pub script: VecDeque<RuntimeAgentStatus>,
+    /// Set by `pause`, cleared by `resume`
+    pub paused: bool,
+}
+
+#[derive(Default)]
+pub struct InMemoryRuntime {
+    sessions: Mutex<HashMap<String, MemorySession>>,
+    /// Scripts for agents that haven't been spawned yet
+    scripts: Mutex<HashMap<String, VecDeque<RuntimeAgentStatus>>>,
+}
+
+impl InMemoryRuntime {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The session spawned for `agent_id`, if any
+    pub fn session(&self, agent_id: &str) -> Option<MemorySession> {
+        self.sessions.lock().unwrap().get(&runtime_id(agent_id)).cloned()
+    }
+
+    /// Report `status` for `agent_id` from now on
+    pub fn set_status(&self, agent_id: &str, status: RuntimeAgentStatus) -> Result<()> {
+        self.with_session(&runtime_id(agent_id), |session| {
+            session.script.clear();
+            session.status = status;
+        })
+    }
+
+    /// Have `agent_id`'s next `status` calls report `statuses` in order
+    ///
+    /// The last status sticks once the script runs out. Scripts can be set
+    /// before the agent is spawned; `set_status` and `stop` discard them.
+    pub fn script_status(&self, agent_id: &str, statuses: impl IntoIterator<Item = RuntimeAgentStatus>) {
+        let statuses: VecDeque<_> = statuses.into_iter().collect();
+        match self.sessions.lock().unwrap().get_mut(&runtime_id(agent_id)) {
+            Some(session) => session.script = statuses,
+            None => {
+                self.scripts.lock().unwrap().insert(agent_id.to_string(), statuses);
+            }
+        }
+    }
+
+    /// Queue usage for the next `drain_usage`
+    pub fn record_usage(&self, agent_id: &str, sample: UsageSample) -> Result<()> {
+        self.with_session(&runtime_id(agent_id), |session| session.pending_usage.push(sample))
+    }
+
+    fn with_session<T>(&self, id: &str, f: impl FnOnce(&mut MemorySession) -> T) -> Result<T> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let session = sessions
+            .get_mut(id)
+            .ok_or_else(|| RembrandtError::SessionNotFound(id.to_string()))?;
+        Ok(f(session))
+    }
+}
+
+/// Runtime session ids are derived from the agent id, so tests can name them
+fn runtime_id(agent_id: &str) -> String {
+    format!("mem-{}", agent_id)
+}
+
+#[async_trait]
+impl AgentRuntime for InMemoryRuntime {
+    fn name(&self) -> &'static str {
+        "memory"
+    }
+
+    async fn spawn(
+        &self,
+        agent_id: &str,
+        workspace: &IsolationContext,
+        prompt: Option<&str>,
+        model: Option<&str>,
+    ) -> Result<AgentHandle> {
+        let id = runtime_id(agent_id);
+        let script = self.scripts.lock().unwrap().remove(agent_id).unwrap_or_default();
+        self.sessions.lock().unwrap().insert(
+            id.clone(),
+            MemorySession {
+                agent_id: agent_id.to_string(),
+                checkout_path: workspace.checkout_path.clone(),
+                prompt: prompt.map(str::to_string),
+                model: model.map(str::to_string),
+                status: RuntimeAgentStatus::Running,
+                messages: Vec::new(),
+                pending_usage: Vec::new(),
+                script,
+                paused: false,
+            },
+        );
+        Ok(AgentHandle {
+            runtime_session_id: RuntimeSessionId(id),
+            agent_id: agent_id.to_string(),
+            model: model.map(str::to_string),
+            metadata: HashMap::new(),
+        })
+    }
+
+    async fn send_message(&self, runtime_session_id: &RuntimeSessionId, message: &str) -> Result<()> {
+        self.with_session(&runtime_session_id.0, |session| session.messages.push(message.to_string()))
+    }
+
+    async fn status(&self, runtime_session_id: &RuntimeSessionId) -> Result<RuntimeAgentStatus> {
+        self.with_session(&runtime_session_id.0, |session| {
+            if let Some(next) = session.script.pop_front() {
+                session.status = next;
+            }
+            session.status.clone()
+        })
+    }
+
+    async fn stop(&self, runtime_session_id: &RuntimeSessionId) -> Result<()> {
+        self.with_session(&runtime_session_id.0, |session| {
+            session.script.clear();
+            session.status = RuntimeAgentStatus::Stopped;
+        })
+    }
+
+    async fn pause(&self, runtime_session_id: &RuntimeSessionId) -> Result<()> {
+        self.with_session(&runtime_session_id.0, |session| session.paused = true)
+    }
+
+    async fn resume(&self, runtime_session_id: &RuntimeSessionId) -> Result<()> {
+        self.with_session(&runtime_session_id.0, |session| session.paused = false)
+    }
+
+    async fn drain_usage(&self, runtime_session_id: &RuntimeSessionId) -> Result<Vec<UsageSample>> {
+        self.with_session(&runtime_session_id.0, |session| std::mem::take(&mut session.pending_usage))
+    }
+}
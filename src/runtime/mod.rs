@@ -1,11 +1,19 @@
 //! Agent runtime abstraction for v2 orchestration.
 
+#[cfg(any(test, feature = "test-util"))]
+pub mod memory;
 mod pi;
+pub mod ssh;
 
+#[cfg(any(test, feature = "test-util"))]
+pub use memory::InMemoryRuntime;
 pub use pi::PiRuntime;
+pub use ssh::SshRuntime;
 
+use crate::agent::AgentType;
 use crate::isolation::IsolationContext;
-use crate::Result;
+use crate::usage::UsageSample;
+use crate::{RembrandtError, Result};
 use async_trait::async_trait;
 use std::collections::HashMap;
 
@@ -37,6 +45,12 @@ pub enum RuntimeAgentStatus {
 pub trait AgentRuntime: Send + Sync {
     fn name(&self) -> &'static str;
 
+    /// The kind of agent this runtime launches, recorded on its sessions;
+    /// runtimes that launch their own agent are named after it
+    fn agent_type(&self) -> AgentType {
+        AgentType::from_str(self.name())
+    }
+
     async fn spawn(
         &self,
         agent_id: &str,
@@ -50,4 +64,20 @@ pub trait AgentRuntime: Send + Sync {
     async fn status(&self, runtime_session_id: &RuntimeSessionId) -> Result<RuntimeAgentStatus>;
 
     async fn stop(&self, runtime_session_id: &RuntimeSessionId) -> Result<()>;
+
+    /// Suspend the agent without ending it, keeping its context
+    async fn pause(&self, _runtime_session_id: &RuntimeSessionId) -> Result<()> {
+        Err(RembrandtError::Runtime(format!("{} can't pause agents", self.name())))
+    }
+
+    /// Continue an agent suspended with `pause`
+    async fn resume(&self, _runtime_session_id: &RuntimeSessionId) -> Result<()> {
+        Err(RembrandtError::Runtime(format!("{} can't pause agents", self.name())))
+    }
+
+    /// Usage reported since the last call; runtimes that can't observe
+    /// token counts return nothing.
+    async fn drain_usage(&self, _runtime_session_id: &RuntimeSessionId) -> Result<Vec<UsageSample>> {
+        Ok(Vec::new())
+    }
 }
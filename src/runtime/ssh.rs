@@ -0,0 +1,450 @@
+//! SSH runtime: agents run on a remote machine, driven through a local PTY.
+//!
+//! The local side runs `ssh -tt` inside a PTY, so output streams back and
+//! nudges/kills are forwarded exactly as for local sessions. Before spawning,
+//! the agent's branch is pushed to the remote clone and checked out there as a
+//! worktree under `.rembrandt/worktrees/<agent-id>`. The push never discards
+//! remote commits the local repository doesn't have.
+
+use super::{AgentHandle, AgentRuntime, RuntimeAgentStatus, RuntimeSessionId};
+use crate::config::RemoteHostConfig;
+use crate::agent::{AgentType, NudgeStrategy};
+use crate::daemon::{SessionManager, SessionStatus};
+use crate::isolation::IsolationContext;
+use crate::usage::{UsageSample, UsageScanner};
+use crate::{RembrandtError, Result};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Command;
+use std::sync::{Mutex, MutexGuard};
+
+/// Runs one agent command on a configured SSH host.
+pub struct SshRuntime {
+    remote: RemoteHostConfig,
+    command: String,
+    args: Vec<String>,
+    sessions: Mutex<SessionManager>,
+    /// Per-session usage scanner and the output offset it has consumed
+    usage: Mutex<HashMap<String, (usize, UsageScanner)>>,
+}
+
+impl SshRuntime {
+    pub fn new(remote: RemoteHostConfig, command: impl Into<String>, args: Vec<String>) -> Self {
+        Self {
+            remote,
+            command: command.into(),
+            args,
+            sessions: Mutex::new(SessionManager::new()),
+            usage: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The session's buffered output.
+    pub fn read_output(&self, runtime_session_id: &RuntimeSessionId) -> Result<Vec<u8>> {
+        let sessions = self.lock()?;
+        let session = sessions
+            .get(&runtime_session_id.0)
+            .ok_or_else(|| RembrandtError::SessionNotFound(runtime_session_id.0.clone()))?;
+        Ok(session.read_output_raw())
+    }
+
+    /// Forward a nudge to the remote agent; returns the strategy used.
+    pub fn nudge(&self, runtime_session_id: &RuntimeSessionId) -> Result<NudgeStrategy> {
+        self.lock()?.nudge(&runtime_session_id.0)
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, SessionManager>> {
+        self.sessions
+            .lock()
+            .map_err(|_| RembrandtError::Runtime("SSH session lock poisoned".to_string()))
+    }
+}
+
+#[async_trait]
+impl AgentRuntime for SshRuntime {
+    fn name(&self) -> &'static str {
+        "ssh"
+    }
+
+    fn agent_type(&self) -> AgentType {
+        AgentType::from_command(&self.command)
+    }
+
+    async fn spawn(
+        &self,
+        agent_id: &str,
+        workspace: &IsolationContext,
+        prompt: Option<&str>,
+        model: Option<&str>,
+    ) -> Result<AgentHandle> {
+        let remote_path = prepare_remote_worktree(
+            &self.remote,
+            &workspace.repo_path,
+            &workspace.branch_name,
+            agent_id,
+        )?;
+        let env = crate::agent::context_env(agent_id, &workspace.branch_name, None);
+        let (command, args) =
+            remote_agent_command(&self.remote, agent_id, &self.command, &self.args, &env);
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+        let session_id = {
+            let mut sessions = self.lock()?;
+            let id = sessions.spawn(agent_id.to_string(), &command, &args, &workspace.checkout_path)?;
+            if let Some(prompt) = prompt {
+                sessions.write(&id, prompt.as_bytes())?;
+                sessions.write(&id, b"\n")?;
+            }
+            id
+        };
+
+        let mut metadata = HashMap::new();
+        metadata.insert("host".to_string(), self.remote.host.clone());
+        metadata.insert("remote_path".to_string(), remote_path);
+
+        Ok(AgentHandle {
+            runtime_session_id: RuntimeSessionId(session_id),
+            agent_id: agent_id.to_string(),
+            model: model.map(str::to_string),
+            metadata,
+        })
+    }
+
+    async fn send_message(&self, runtime_session_id: &RuntimeSessionId, message: &str) -> Result<()> {
+        let mut sessions = self.lock()?;
+        sessions.write(&runtime_session_id.0, message.as_bytes())?;
+        sessions.write(&runtime_session_id.0, b"\n")
+    }
+
+    async fn status(&self, runtime_session_id: &RuntimeSessionId) -> Result<RuntimeAgentStatus> {
+        let mut sessions = self.lock()?;
+        let Some(session) = sessions.get_mut(&runtime_session_id.0) else {
+            return Ok(RuntimeAgentStatus::Stopped);
+        };
+        Ok(match session.poll() {
+            SessionStatus::Running => RuntimeAgentStatus::Running,
+            SessionStatus::Exited(0) => RuntimeAgentStatus::Completed,
+            // ssh itself exits 255 when the connection fails or drops
+            SessionStatus::Exited(255) => {
+                RuntimeAgentStatus::Failed(format!("lost connection to {}", self.remote.host))
+            }
+            SessionStatus::Exited(code) => RuntimeAgentStatus::Failed(format!("exit code {}", code)),
+            SessionStatus::Failed(reason) => RuntimeAgentStatus::Failed(reason),
+        })
+    }
+
+    async fn stop(&self, runtime_session_id: &RuntimeSessionId) -> Result<()> {
+        // Closing the ssh PTY hangs up the remote TTY, which ends the agent
+        self.lock()?.kill(&runtime_session_id.0)
+    }
+
+    async fn drain_usage(&self, runtime_session_id: &RuntimeSessionId) -> Result<Vec<UsageSample>> {
+        let (output, total) = {
+            let sessions = self.lock()?;
+            let Some(session) = sessions.get(&runtime_session_id.0) else {
+                return Ok(Vec::new());
+            };
+            let (output, start) = session.output_snapshot();
+            let total = start + output.len();
+            (output, total)
+        };
+
+        let mut usage = self
+            .usage
+            .lock()
+            .map_err(|_| RembrandtError::Runtime("SSH usage lock poisoned".to_string()))?;
+        let (consumed, scanner) = usage.entry(runtime_session_id.0.clone()).or_default();
+        // Anything older than the ring buffer's window is gone; scan what's left
+        let unseen = (total - *consumed).min(output.len());
+        *consumed = total;
+        Ok(scanner.feed(&output[output.len() - unseen..]))
+    }
+}
+
+/// `[user@]host` for ssh
+pub fn ssh_destination(remote: &RemoteHostConfig) -> String {
+    match &remote.user {
+        Some(user) => format!("{}@{}", user, remote.host),
+        None => remote.host.clone(),
+    }
+}
+
+/// Where an agent's worktree lives on the remote machine
+pub fn remote_worktree_path(remote: &RemoteHostConfig, agent_id: &str) -> String {
+    format!(
+        "{}/.rembrandt/worktrees/{}",
+        remote.repo_path.trim_end_matches('/'),
+        agent_id
+    )
+}
+
+/// Arguments for `ssh` running `remote_command` on the host
+///
+/// `tty` forces a remote TTY so interactive agents behave as they do locally.
+pub fn ssh_args(remote: &RemoteHostConfig, remote_command: &str, tty: bool) -> Vec<String> {
+    let mut args = Vec::new();
+    if tty {
+        args.push("-tt".to_string());
+    }
+    if let Some(port) = remote.port {
+        args.push("-p".to_string());
+        args.push(port.to_string());
+    }
+    if let Some(identity) = &remote.identity_file {
+        args.push("-i".to_string());
+        args.push(identity.display().to_string());
+    }
+    for option in &remote.ssh_options {
+        args.push("-o".to_string());
+        args.push(option.clone());
+    }
+    args.push(ssh_destination(remote));
+    args.push(remote_command.to_string());
+    args
+}
+
+/// The local command that runs an agent in its remote worktree
+///
+/// `env` is set on the remote side; the local environment doesn't cross
+/// the SSH connection.
+pub fn remote_agent_command(
+    remote: &RemoteHostConfig,
+    agent_id: &str,
+    command: &str,
+    args: &[impl AsRef<str>],
+    env: &[(String, String)],
+) -> (String, Vec<String>) {
+    let mut remote_command = format!(
+        "cd {} && exec ",
+        shell_quote(&remote_worktree_path(remote, agent_id))
+    );
+    if !env.is_empty() {
+        remote_command.push_str("env ");
+        for (key, value) in env {
+            remote_command.push_str(&shell_quote(&format!("{}={}", key, value)));
+            remote_command.push(' ');
+        }
+    }
+    remote_command.push_str(&shell_quote(command));
+    for arg in args {
+        remote_command.push(' ');
+        remote_command.push_str(&shell_quote(arg.as_ref()));
+    }
+    ("ssh".to_string(), ssh_args(remote, &remote_command, true))
+}
+
+/// Make sure the remote clone has a worktree for `branch`
+///
+/// The branch is pushed from the local repository on first use, so the agent
+/// starts from the same commit it would locally. An existing remote worktree
+/// (e.g. when continuing a session) is reused as-is. Returns its path.
+pub fn prepare_remote_worktree(
+    remote: &RemoteHostConfig,
+    local_repo: &Path,
+    branch: &str,
+    agent_id: &str,
+) -> Result<String> {
+    let worktree = remote_worktree_path(remote, agent_id);
+    if run_remote(remote, &format!("test -d {}", shell_quote(&worktree))).is_ok() {
+        return Ok(worktree);
+    }
+
+    let url = remote_git_url(remote);
+    let listed = Command::new("git")
+        .arg("-C")
+        .arg(local_repo)
+        .args(["ls-remote", &url, &format!("refs/heads/{}", branch)])
+        .env("GIT_SSH_COMMAND", git_ssh_command(remote))
+        .output()?;
+    if !listed.status.success() {
+        return Err(RembrandtError::Runtime(format!(
+            "Failed to read {} on {}: {}",
+            branch,
+            remote.host,
+            String::from_utf8_lossy(&listed.stderr).trim()
+        )));
+    }
+    let stdout = String::from_utf8_lossy(&listed.stdout);
+    let remote_head = stdout.split_whitespace().next();
+    let lease = push_lease(local_repo, branch, remote_head)?;
+
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(local_repo)
+        .args(["push", "--quiet", &lease, &url])
+        .arg(format!("refs/heads/{0}:refs/heads/{0}", branch))
+        .env("GIT_SSH_COMMAND", git_ssh_command(remote))
+        .output()?;
+    if !output.status.success() {
+        return Err(RembrandtError::Runtime(format!(
+            "Failed to push {} to {}: {}",
+            branch,
+            remote.host,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    run_remote(
+        remote,
+        &format!(
+            "git -C {} worktree add --quiet {} {}",
+            shell_quote(&remote.repo_path),
+            shell_quote(&worktree),
+            shell_quote(branch)
+        ),
+    )?;
+    Ok(worktree)
+}
+
+/// `--force-with-lease` for pushing `branch` over `remote_head`, the
+/// commit it points to on the host (None when it isn't there)
+///
+/// The push may rewrite the remote branch only when it points to a commit
+/// this repository has, so work an earlier remote session left on it isn't
+/// thrown away; the lease also refuses if it moves before the push lands.
+fn push_lease(local_repo: &Path, branch: &str, remote_head: Option<&str>) -> Result<String> {
+    if let Some(head) = remote_head {
+        let known = Command::new("git")
+            .arg("-C")
+            .arg(local_repo)
+            .args(["cat-file", "-e", &format!("{}^{{commit}}", head)])
+            .status()?;
+        if !known.success() {
+            return Err(RembrandtError::Runtime(format!(
+                "Won't push {}: the remote branch is at {}, which isn't in this repository; fetch it first",
+                branch, head
+            )));
+        }
+    }
+    Ok(format!("--force-with-lease=refs/heads/{}:{}", branch, remote_head.unwrap_or_default()))
+}
+
+/// Run a non-interactive command on the host
+fn run_remote(remote: &RemoteHostConfig, remote_command: &str) -> Result<()> {
+    let output = Command::new("ssh")
+        .args(ssh_args(remote, remote_command, false))
+        .output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(RembrandtError::Runtime(format!(
+            "`{}` failed on {}: {}",
+            remote_command,
+            remote.host,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )))
+    }
+}
+
+/// `ssh://` URL of the remote clone, for git push
+fn remote_git_url(remote: &RemoteHostConfig) -> String {
+    let port = remote.port.map(|p| format!(":{}", p)).unwrap_or_default();
+    let path = if remote.repo_path.starts_with('/') {
+        remote.repo_path.clone()
+    } else {
+        // Relative paths are relative to the login directory
+        format!("/~/{}", remote.repo_path)
+    };
+    format!("ssh://{}{}{}", ssh_destination(remote), port, path)
+}
+
+/// `GIT_SSH_COMMAND` carrying the identity file and options (not the port,
+/// which is in the URL)
+fn git_ssh_command(remote: &RemoteHostConfig) -> String {
+    let mut command = "ssh".to_string();
+    if let Some(identity) = &remote.identity_file {
+        command.push_str(" -i ");
+        command.push_str(&shell_quote(&identity.display().to_string()));
+    }
+    for option in &remote.ssh_options {
+        command.push_str(" -o ");
+        command.push_str(&shell_quote(option));
+    }
+    command
+}
+
+/// Quote a word for a POSIX shell
+pub(crate) fn shell_quote(word: &str) -> String {
+    if !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:@,+".contains(c))
+    {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buildbox() -> RemoteHostConfig {
+        RemoteHostConfig {
+            host: "build.example.com".to_string(),
+            user: Some("dev".to_string()),
+            port: Some(2222),
+            identity_file: None,
+            repo_path: "/srv/src/app/".to_string(),
+            ssh_options: vec!["ServerAliveInterval=30".to_string()],
+        }
+    }
+
+    #[test]
+    fn agent_command_runs_in_remote_worktree() {
+        let (command, args) =
+            remote_agent_command(&buildbox(), "claude-1a2b", "claude", &["--print", "it's done"], &[]);
+
+        assert_eq!(command, "ssh");
+        assert_eq!(
+            args,
+            vec![
+                "-tt",
+                "-p",
+                "2222",
+                "-o",
+                "ServerAliveInterval=30",
+                "dev@build.example.com",
+                r"cd /srv/src/app/.rembrandt/worktrees/claude-1a2b && exec claude --print 'it'\''s done'",
+            ]
+        );
+
+        let env = vec![("REMBRANDT_TASK_ID".to_string(), "br 7".to_string())];
+        let (_, args) = remote_agent_command(&buildbox(), "claude-1a2b", "claude", &[] as &[&str], &env);
+        assert!(args.last().unwrap().ends_with("&& exec env 'REMBRANDT_TASK_ID=br 7' claude"));
+
+        let runtime = SshRuntime::new(buildbox(), "/usr/local/bin/claude", Vec::new());
+        assert_eq!(runtime.agent_type(), AgentType::ClaudeCode);
+    }
+
+    #[test]
+    fn push_lease_keeps_remote_commits_this_repository_lacks() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = git2::Repository::init(dir.path()).unwrap();
+        let sig = git2::Signature::now("test", "test@example.com").unwrap();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+        let head = repo.commit(Some("refs/heads/agent"), &sig, &sig, "init", &tree, &[]).unwrap().to_string();
+
+        assert_eq!(
+            push_lease(dir.path(), "agent", None).unwrap(),
+            "--force-with-lease=refs/heads/agent:"
+        );
+        assert_eq!(
+            push_lease(dir.path(), "agent", Some(&head)).unwrap(),
+            format!("--force-with-lease=refs/heads/agent:{}", head)
+        );
+        assert!(push_lease(dir.path(), "agent", Some(&"1".repeat(40))).is_err());
+    }
+
+    #[test]
+    fn git_url_includes_port_and_home_relative_paths() {
+        let mut remote = buildbox();
+        assert_eq!(remote_git_url(&remote), "ssh://dev@build.example.com:2222/srv/src/app/");
+
+        remote.port = None;
+        remote.repo_path = "src/app".to_string();
+        assert_eq!(remote_git_url(&remote), "ssh://dev@build.example.com/~/src/app");
+    }
+}
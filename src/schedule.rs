@@ -0,0 +1,148 @@
+//! Scheduled steers: messages typed into an agent's session later.
+//!
+//! `rembrandt steer <agent> --at +30m "..."` stores a steer in state.db to
+//! be sent once; `--every 30m` sends it again at that interval for as long
+//! as the agent lives. `rembrandt daemon` checks for due steers in its
+//! repository, types each into the agent's running session like a message
+//! and records it on the agent's timeline. A recurring steer that fell due
+//! several times while nothing sent it goes out once, then keeps its cycle.
+//! Steers for agents that have ended are dropped, leaving a note on the
+//! timeline; `rembrandt steer` refuses to schedule one for an agent that
+//! isn't live.
+
+use crate::state::StateStore;
+use crate::timeline::{self, TimelineKind};
+use crate::{RembrandtError, Result};
+use chrono::{DateTime, Duration, Utc};
+
+/// A steer waiting to be sent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduledSteer {
+    pub id: i64,
+    pub agent_id: String,
+    pub message: String,
+    pub due_at: DateTime<Utc>,
+    /// Interval of a recurring steer; None for one sent once
+    pub every: Option<Duration>,
+    /// Times it has been sent
+    pub sent_count: u32,
+    pub last_sent_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ScheduledSteer {
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.due_at <= now
+    }
+
+    /// When it goes out again after being sent at `now`; None for a one-off
+    pub fn next_due(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let every = self.every.filter(|every| *every > Duration::zero())?;
+        let mut next = self.due_at + every;
+        if next <= now {
+            // Skip the cycles missed while no daemon was running
+            let missed = (now - next).num_seconds() / every.num_seconds().max(1) + 1;
+            next += every * missed as i32;
+        }
+        Some(next)
+    }
+}
+
+/// When `--at` means: `+30m` from `now`, or an RFC 3339 time
+pub fn parse_at(value: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(at) = DateTime::parse_from_rfc3339(value) {
+        return Ok(at.with_timezone(&Utc));
+    }
+    value
+        .strip_prefix('+')
+        .and_then(crate::audit::parse_duration)
+        .map(|delay| now + delay)
+        .ok_or_else(|| {
+            RembrandtError::Config(format!(
+                "invalid --at '{}': expected e.g. +30m, +2h or an RFC 3339 time",
+                value
+            ))
+        })
+}
+
+/// A `--every` interval such as `30m`; at least a minute
+pub fn parse_every(value: &str) -> Result<Duration> {
+    match crate::audit::parse_duration(value.trim()) {
+        Some(every) if every >= Duration::minutes(1) => Ok(every),
+        _ => Err(RembrandtError::Config(format!(
+            "invalid --every '{}': expected an interval of at least a minute, e.g. 30m or 2h",
+            value
+        ))),
+    }
+}
+
+/// Record that `steer` was typed into its agent's session at `now`: on the
+/// timeline, and by moving a recurring steer to its next time or removing
+/// a one-off
+pub fn mark_sent(store: &StateStore, steer: &ScheduledSteer, now: DateTime<Utc>) -> Result<()> {
+    let detail = format!("scheduled: {}", timeline::one_line(&steer.message, 80));
+    store.record_timeline(&steer.agent_id, TimelineKind::Steer, Some(&detail))?;
+    store.record_steer_sent(steer.id, now, steer.next_due(now))
+}
+
+/// Remove `steer` because its agent has ended, noting on the agent's
+/// timeline that it was never sent
+pub fn drop_steer(store: &StateStore, steer: &ScheduledSteer) -> Result<()> {
+    let detail = format!("dropped, agent ended: {}", timeline::one_line(&steer.message, 80));
+    store.record_timeline(&steer.agent_id, TimelineKind::Steer, Some(&detail))?;
+    store.cancel_steer(steer.id).map(drop)
+}
+
+/// Whether `agent_id` has ended, so its steers will never be sent
+pub fn agent_ended(store: &StateStore, agent_id: &str) -> Result<bool> {
+    use crate::state::SessionStatus::*;
+    Ok(store
+        .get_session(agent_id)?
+        .is_none_or(|record| matches!(record.status, Completed | Failed | Stopped | StoppedForBudget)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recurring_steers_keep_their_cycle() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = StateStore::open(dir.path()).unwrap();
+        let now = Utc::now();
+
+        assert_eq!(parse_at("+30m", now).unwrap(), now + Duration::minutes(30));
+        assert!(parse_at("later", now).is_err());
+        assert!(parse_every("10s").is_err());
+
+        let once = store.schedule_steer("claude-1", "check in", now - Duration::seconds(1), None).unwrap();
+        let every = store
+            .schedule_steer("claude-1", "summarize progress", now - Duration::minutes(95), Some(Duration::minutes(30)))
+            .unwrap();
+        store.schedule_steer("claude-1", "later", now + Duration::hours(1), None).unwrap();
+        let due: Vec<ScheduledSteer> = store.scheduled_steers(None).unwrap().into_iter().filter(|s| s.is_due(now)).collect();
+        assert_eq!(due.iter().map(|s| s.id).collect::<Vec<_>>(), vec![every, once]);
+
+        for steer in &due {
+            mark_sent(&store, steer, now).unwrap();
+        }
+        let left = store.scheduled_steers(Some("claude-1")).unwrap();
+        assert_eq!(left.len(), 2);
+        // Three cycles were missed; it goes out once and stays on its cycle
+        let recurring = left.iter().find(|s| s.id == every).unwrap();
+        assert_eq!(recurring.due_at, due[0].due_at + Duration::minutes(120));
+        assert_eq!(recurring.sent_count, 1);
+        assert_eq!(store.timeline("claude-1").unwrap().len(), 2);
+
+        assert!(store.cancel_steer(every).unwrap());
+        assert!(!store.cancel_steer(every).unwrap());
+        assert!(agent_ended(&store, "claude-1").unwrap());
+
+        let later = store.scheduled_steers(Some("claude-1")).unwrap().remove(0);
+        drop_steer(&store, &later).unwrap();
+        assert!(store.scheduled_steers(None).unwrap().is_empty());
+        let timeline = store.timeline("claude-1").unwrap();
+        assert!(timeline.iter().any(|entry| entry.detail.as_deref() == Some("dropped, agent ended: later")));
+    }
+}
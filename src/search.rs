@@ -0,0 +1,354 @@
+//! Searching what agents said and printed.
+//!
+//! `rembrandt search <pattern>` runs a regular expression over the session
+//! output logs the daemon keeps under `.rembrandt/logs` and over recorded
+//! transcripts, and reports the matches per session with a few excerpts.
+//! Logs carry no per-line times, so `--since`/`--until` select log files by
+//! when they were last written; transcript turns are filtered one by one.
+
+use crate::daemon::logs;
+use crate::state::StateStore;
+use crate::timeline::strip_ansi;
+use crate::{RembrandtError, Result};
+use chrono::{DateTime, Utc};
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+use std::path::Path;
+
+/// Characters of context kept before a match in an excerpt
+const CONTEXT_BEFORE: usize = 60;
+/// Longest excerpt, in characters
+const EXCERPT_MAX: usize = 200;
+
+/// What to look for, and where
+#[derive(Debug, Clone)]
+pub struct SearchQuery {
+    pub pattern: Regex,
+    /// Only this agent's sessions
+    pub agent: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// Excerpts kept per session; hits are counted regardless
+    pub max_excerpts: usize,
+}
+
+impl SearchQuery {
+    pub fn new(pattern: &str, ignore_case: bool) -> Result<Self> {
+        let pattern = RegexBuilder::new(pattern)
+            .case_insensitive(ignore_case)
+            .build()
+            .map_err(|e| RembrandtError::Config(format!("invalid pattern: {}", e)))?;
+        Ok(Self {
+            pattern,
+            agent: None,
+            since: None,
+            until: None,
+            max_excerpts: 5,
+        })
+    }
+
+    fn in_window(&self, at: DateTime<Utc>) -> bool {
+        self.since.is_none_or(|since| at >= since) && self.until.is_none_or(|until| at <= until)
+    }
+}
+
+/// Where a match was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HitSource {
+    /// A daemon session's output log
+    Log,
+    /// An agent's recorded transcript
+    Transcript,
+}
+
+impl HitSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HitSource::Log => "log",
+            HitSource::Transcript => "transcript",
+        }
+    }
+}
+
+/// One matching line
+#[derive(Debug, Clone, Serialize)]
+pub struct Excerpt {
+    /// Line number in the log, for log hits
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    /// When the turn was recorded, for transcript hits
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub at: Option<DateTime<Utc>>,
+    pub text: String,
+}
+
+/// The matches in one session
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionHits {
+    pub agent_id: String,
+    /// Daemon session the log belongs to; transcripts have none
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    pub source: HitSource,
+    pub hits: usize,
+    /// Last write to the log, or the latest matching turn
+    pub last_at: DateTime<Utc>,
+    pub excerpts: Vec<Excerpt>,
+}
+
+/// Search the logs and transcripts of `repo_path`, most recent sessions first
+pub fn search(repo_path: &Path, store: &StateStore, query: &SearchQuery) -> Result<Vec<SessionHits>> {
+    if let (Some(since), Some(until)) = (query.since, query.until)
+        && until < since
+    {
+        return Err(RembrandtError::Config(format!(
+            "--until {} is before --since {}",
+            until.to_rfc3339(),
+            since.to_rfc3339()
+        )));
+    }
+    let mut results = search_logs(repo_path, query)?;
+    results.extend(search_transcripts(store, query)?);
+    results.sort_by_key(|hits| std::cmp::Reverse(hits.last_at));
+    Ok(results)
+}
+
+fn search_logs(repo_path: &Path, query: &SearchQuery) -> Result<Vec<SessionHits>> {
+    let dir = logs::logs_dir(repo_path);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut results = Vec::new();
+    for agent_dir in std::fs::read_dir(&dir)? {
+        let agent_dir = agent_dir?;
+        let agent_id = agent_dir.file_name().to_string_lossy().into_owned();
+        if !agent_dir.file_type()?.is_dir() || query.agent.as_ref().is_some_and(|agent| *agent != agent_id) {
+            continue;
+        }
+        for file in std::fs::read_dir(agent_dir.path())? {
+            let path = file?.path();
+            let Some(session_id) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_suffix(".log"))
+            else {
+                continue;
+            };
+            let last_at: DateTime<Utc> = std::fs::metadata(&path)?.modified()?.into();
+            if !query.in_window(last_at) {
+                continue;
+            }
+
+            let data = std::fs::read(&path)?;
+            let mut hits = SessionHits {
+                agent_id: agent_id.clone(),
+                session_id: Some(session_id.to_string()),
+                source: HitSource::Log,
+                hits: 0,
+                last_at,
+                excerpts: Vec::new(),
+            };
+            for (index, line) in String::from_utf8_lossy(&data).lines().enumerate() {
+                // A carriage return redraws the line; what's left is what was shown
+                let shown = line.trim_end_matches('\r').rsplit('\r').next().unwrap_or_default();
+                let shown = strip_ansi(shown);
+                if let Some(text) = excerpt(&query.pattern, &shown) {
+                    hits.hits += 1;
+                    if hits.excerpts.len() < query.max_excerpts {
+                        hits.excerpts.push(Excerpt {
+                            line: Some(index + 1),
+                            at: None,
+                            text,
+                        });
+                    }
+                }
+            }
+            if hits.hits > 0 {
+                results.push(hits);
+            }
+        }
+    }
+    Ok(results)
+}
+
+fn search_transcripts(store: &StateStore, query: &SearchQuery) -> Result<Vec<SessionHits>> {
+    let mut results: Vec<SessionHits> = Vec::new();
+    for entry in store.transcripts(query.agent.as_deref())? {
+        if !query.in_window(entry.created_at) {
+            continue;
+        }
+        let matches: Vec<String> = entry
+            .text
+            .lines()
+            .filter_map(|line| excerpt(&query.pattern, line))
+            .collect();
+        if matches.is_empty() {
+            continue;
+        }
+
+        let index = match results.iter().position(|hits| hits.agent_id == entry.agent_id) {
+            Some(index) => index,
+            None => {
+                results.push(SessionHits {
+                    agent_id: entry.agent_id.clone(),
+                    session_id: None,
+                    source: HitSource::Transcript,
+                    hits: 0,
+                    last_at: entry.created_at,
+                    excerpts: Vec::new(),
+                });
+                results.len() - 1
+            }
+        };
+        let hits = &mut results[index];
+        hits.hits += matches.len();
+        hits.last_at = hits.last_at.max(entry.created_at);
+        for text in matches {
+            if hits.excerpts.len() < query.max_excerpts {
+                hits.excerpts.push(Excerpt {
+                    line: None,
+                    at: Some(entry.created_at),
+                    text: format!("{}: {}", entry.role, text),
+                });
+            }
+        }
+    }
+    Ok(results)
+}
+
+/// `line` around its first match of `pattern`, if it has one
+fn excerpt(pattern: &Regex, line: &str) -> Option<String> {
+    let found = pattern.find(line)?;
+    let before = line[..found.start()].chars().count();
+    let skip = before.saturating_sub(CONTEXT_BEFORE);
+    let rest: Vec<char> = line.chars().skip(skip).collect();
+    let mut text: String = rest.iter().take(EXCERPT_MAX).collect();
+    if rest.len() > EXCERPT_MAX {
+        text.push('…');
+    }
+    let text = text.trim();
+    Some(if skip > 0 { format!("…{}", text) } else { text.to_string() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::{TranscriptRole, TranscriptTurn};
+
+    #[test]
+    fn finds_matches_in_logs_and_transcripts() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = StateStore::open(dir.path()).unwrap();
+        let log = logs::log_path(dir.path(), "claude-1a2b", "s-1");
+        std::fs::create_dir_all(log.parent().unwrap()).unwrap();
+        std::fs::write(
+            &log,
+            "building\r\n\x1b[31merror[E0308]\x1b[0m: mismatched types\r\nwarming up\rERROR again\nok\n",
+        )
+        .unwrap();
+        let now = Utc::now();
+        store
+            .record_transcript(
+                "codex-9f9f",
+                &[
+                    TranscriptTurn {
+                        key: "a".to_string(),
+                        role: TranscriptRole::Assistant,
+                        text: "Fixed the error in parse()\nand another error".to_string(),
+                        created_at: now,
+                    },
+                    TranscriptTurn {
+                        key: "b".to_string(),
+                        role: TranscriptRole::User,
+                        text: "thanks".to_string(),
+                        created_at: now,
+                    },
+                ],
+            )
+            .unwrap();
+
+        let mut query = SearchQuery::new("error", true).unwrap();
+        let results = search(dir.path(), &store, &query).unwrap();
+        assert_eq!(results.len(), 2);
+        let log_hits = results.iter().find(|hits| hits.source == HitSource::Log).unwrap();
+        assert_eq!(log_hits.agent_id, "claude-1a2b");
+        assert_eq!(log_hits.session_id.as_deref(), Some("s-1"));
+        assert_eq!(log_hits.hits, 2);
+        assert_eq!(log_hits.excerpts[0].text, "error[E0308]: mismatched types");
+        assert_eq!(log_hits.excerpts[1].line, Some(3));
+        let transcript_hits = results.iter().find(|hits| hits.source == HitSource::Transcript).unwrap();
+        assert_eq!(transcript_hits.hits, 2);
+        assert_eq!(transcript_hits.excerpts[0].text, "assistant: Fixed the error in parse()");
+
+        query.max_excerpts = 1;
+        query.agent = Some("codex-9f9f".to_string());
+        let results = search(dir.path(), &store, &query).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!((results[0].hits, results[0].excerpts.len()), (2, 1));
+
+        query.agent = None;
+        query.since = Some(now + chrono::Duration::hours(1));
+        assert!(search(dir.path(), &store, &query).unwrap().is_empty());
+        assert!(SearchQuery::new("(", false).is_err());
+
+        let long = format!("{}needle{}", "x".repeat(100), "y".repeat(300));
+        let text = excerpt(&Regex::new("needle").unwrap(), &long).unwrap();
+        assert!(text.starts_with('…') && text.ends_with('…') && text.contains("needle"));
+    }
+
+    #[test]
+    fn time_filters_select_turns_and_log_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = StateStore::open(dir.path()).unwrap();
+        let now = Utc::now();
+        let hours_ago = |hours| now - chrono::Duration::hours(hours);
+        let turns: Vec<TranscriptTurn> = [3, 2, 1]
+            .into_iter()
+            .map(|hours| TranscriptTurn {
+                key: format!("t{}", hours),
+                role: TranscriptRole::Assistant,
+                text: format!("error {}h ago", hours),
+                created_at: hours_ago(hours),
+            })
+            .collect();
+        store.record_transcript("codex-9f9f", &turns).unwrap();
+        // A log last written two hours ago
+        let log = logs::log_path(dir.path(), "claude-1a2b", "s-1");
+        std::fs::create_dir_all(log.parent().unwrap()).unwrap();
+        std::fs::write(&log, "error in the log\n").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&log)
+            .unwrap()
+            .set_modified(hours_ago(2).into())
+            .unwrap();
+
+        let hits = |since: Option<i64>, until: Option<i64>| -> Vec<(HitSource, usize)> {
+            let mut query = SearchQuery::new("error", false).unwrap();
+            query.since = since.map(hours_ago);
+            query.until = until.map(hours_ago);
+            let mut found: Vec<_> = search(dir.path(), &store, &query)
+                .unwrap()
+                .into_iter()
+                .map(|hits| (hits.source, hits.hits))
+                .collect();
+            found.sort_by_key(|(source, _)| source.as_str());
+            found
+        };
+        assert_eq!(hits(None, None), [(HitSource::Log, 1), (HitSource::Transcript, 3)]);
+        // Both ends of the window are inclusive
+        assert_eq!(hits(Some(2), None), [(HitSource::Log, 1), (HitSource::Transcript, 2)]);
+        assert_eq!(hits(None, Some(2)), [(HitSource::Log, 1), (HitSource::Transcript, 2)]);
+        assert_eq!(hits(Some(2), Some(2)), [(HitSource::Log, 1), (HitSource::Transcript, 1)]);
+        assert_eq!(hits(Some(1), None), [(HitSource::Transcript, 1)]);
+        assert!(hits(Some(5), Some(4)).is_empty());
+
+        let mut inverted = SearchQuery::new("error", false).unwrap();
+        inverted.since = Some(hours_ago(1));
+        inverted.until = Some(hours_ago(3));
+        let err = search(dir.path(), &store, &inverted).unwrap_err();
+        assert!(matches!(err, RembrandtError::Config(_)), "{}", err);
+    }
+}
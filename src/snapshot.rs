@@ -0,0 +1,228 @@
+//! Snapshots of an agent's worktree for rolling back destructive moments.
+//!
+//! A snapshot captures the checked-out commit plus every uncommitted change,
+//! untracked files included (ignored files are not). The working tree is
+//! written to a scratch index and committed on top of HEAD without touching
+//! the agent's index, branch or files; the commit is kept alive by
+//! `refs/rembrandt/snapshots/<agent>/<id>` and recorded in state.db.
+//!
+//! Restoring resets the branch to the recorded commit and the files to the
+//! snapshot, leaving the dirty changes uncommitted as they were. The state
+//! being replaced is snapshotted first, so a restore can itself be undone.
+
+use crate::state::StateStore;
+use crate::sync::find_checkout;
+use crate::{RembrandtError, Result};
+use chrono::{DateTime, Utc};
+use git2::Repository;
+use std::path::Path;
+use std::process::Command;
+
+/// Label of the snapshot taken automatically before a restore
+pub const PRE_RESTORE_LABEL: &str = "before restore";
+
+#[derive(Debug, Clone)]
+pub struct SnapshotRecord {
+    pub id: i64,
+    pub agent_id: String,
+    /// Commit the worktree had checked out
+    pub head_commit: String,
+    /// Commit whose tree is the full working tree, parented on `head_commit`
+    pub snapshot_commit: String,
+    pub label: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl SnapshotRecord {
+    /// Ref that keeps the snapshot commit from being garbage collected
+    pub fn ref_name(&self) -> String {
+        format!("refs/rembrandt/snapshots/{}/{}", self.agent_id, self.id)
+    }
+}
+
+/// Snapshot `agent_id`'s worktree as it is right now
+pub fn snapshot(
+    repo_path: &Path,
+    store: &StateStore,
+    agent_id: &str,
+    label: Option<&str>,
+) -> Result<SnapshotRecord> {
+    let checkout = find_checkout(repo_path, store, agent_id)?;
+    if !checkout.exists() {
+        return Err(RembrandtError::Worktree(format!(
+            "{}'s checkout {} no longer exists",
+            agent_id,
+            checkout.display()
+        )));
+    }
+
+    let head = git(&checkout, &["rev-parse", "HEAD"], None)?;
+    let scratch_index = std::env::temp_dir().join(format!(
+        "rembrandt-snapshot-{}-{}.index",
+        std::process::id(),
+        Utc::now().timestamp_nanos_opt().unwrap_or_default()
+    ));
+    let tree = (|| {
+        git(&checkout, &["read-tree", "HEAD"], Some(&scratch_index))?;
+        // Branch-isolated agents work in the main checkout, next to state.db
+        git(&checkout, &["add", "--all", "--", ".", ":!.rembrandt"], Some(&scratch_index))?;
+        git(&checkout, &["write-tree"], Some(&scratch_index))
+    })();
+    let _ = std::fs::remove_file(&scratch_index);
+    let tree = tree?;
+
+    let message = format!("rembrandt snapshot of {}: {}", agent_id, label.unwrap_or("manual"));
+    let commit = git(
+        &checkout,
+        &[
+            "-c",
+            "user.name=rembrandt",
+            "-c",
+            "user.email=rembrandt@localhost",
+            "commit-tree",
+            &tree,
+            "-p",
+            &head,
+            "-m",
+            &message,
+        ],
+        None,
+    )?;
+
+    let id = store.record_snapshot(agent_id, &head, &commit, label)?;
+    let record = store
+        .get_snapshot(id)?
+        .ok_or_else(|| RembrandtError::State(format!("snapshot {} vanished", id)))?;
+    git(&checkout, &["update-ref", &record.ref_name(), &commit], None)?;
+    Ok(record)
+}
+
+/// Roll `agent_id`'s worktree back to snapshot `snapshot_id`
+///
+/// Returns the snapshot of the state that was replaced.
+pub fn restore(
+    repo_path: &Path,
+    store: &StateStore,
+    agent_id: &str,
+    snapshot_id: i64,
+) -> Result<SnapshotRecord> {
+    let target = store
+        .get_snapshot(snapshot_id)?
+        .filter(|s| s.agent_id == agent_id)
+        .ok_or_else(|| {
+            RembrandtError::State(format!("{} has no snapshot #{}", agent_id, snapshot_id))
+        })?;
+
+    // A branch-isolated agent works in the main checkout, which a restore
+    // would reset and clean along with everything else in it
+    let checkout = find_checkout(repo_path, store, agent_id)?;
+    if !Repository::open(&checkout).is_ok_and(|repo| repo.is_worktree()) {
+        return Err(RembrandtError::Worktree(format!(
+            "{} has no worktree of its own ({} is shared); only agent worktrees can be restored",
+            agent_id,
+            checkout.display()
+        )));
+    }
+    let previous = snapshot(repo_path, store, agent_id, Some(PRE_RESTORE_LABEL))?;
+
+    git(&checkout, &["reset", "--hard", &target.head_commit], None)?;
+    git(&checkout, &["clean", "-fd", "--exclude", ".rembrandt"], None)?;
+    // Files as in the snapshot, then the index back to HEAD so the
+    // snapshot's dirty changes are uncommitted again
+    let tree = format!("{}^{{tree}}", target.snapshot_commit);
+    git(&checkout, &["read-tree", "-u", "--reset", &tree], None)?;
+    git(&checkout, &["reset", "--quiet"], None)?;
+
+    Ok(previous)
+}
+
+/// Run git in `dir`, optionally against another index; returns trimmed stdout
+fn git(dir: &Path, args: &[&str], index: Option<&Path>) -> Result<String> {
+    let mut command = Command::new("git");
+    command.args(args).current_dir(dir);
+    if let Some(index) = index {
+        command.env("GIT_INDEX_FILE", index);
+    }
+    let output = command.output()?;
+    if !output.status.success() {
+        return Err(RembrandtError::Worktree(format!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::SessionRecord;
+    use crate::test_support::{init_repo, session_record};
+    use crate::worktree::WorktreeManager;
+
+    fn commit_all(dir: &Path, message: &str) {
+        git(dir, &["add", "--all"], None).unwrap();
+        git(
+            dir,
+            &["-c", "user.name=test", "-c", "user.email=test@example.com", "commit", "-m", message],
+            None,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn restores_commits_and_dirty_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = init_repo(dir.path());
+
+        let worktree = WorktreeManager::new(dir.path())
+            .unwrap()
+            .create_worktree("agent", "main")
+            .unwrap();
+        let path = &worktree.path;
+        std::fs::write(path.join("kept.txt"), "committed\n").unwrap();
+        commit_all(path, "work");
+        std::fs::write(path.join("kept.txt"), "dirty edit\n").unwrap();
+        std::fs::write(path.join("untracked.txt"), "new\n").unwrap();
+
+        let store = StateStore::open(dir.path()).unwrap();
+        let snap = snapshot(dir.path(), &store, "agent", Some("before refactor")).unwrap();
+        // Taking a snapshot leaves the worktree alone
+        assert_eq!(git(path, &["status", "--porcelain"], None).unwrap(), "M kept.txt\n?? untracked.txt");
+
+        // The agent wrecks its workspace and commits the damage
+        std::fs::remove_file(path.join("kept.txt")).unwrap();
+        std::fs::write(path.join("junk.txt"), "junk\n").unwrap();
+        commit_all(path, "oops");
+        std::fs::write(path.join("scratch.txt"), "scratch\n").unwrap();
+
+        let previous = restore(dir.path(), &store, "agent", snap.id).unwrap();
+        assert_eq!(previous.label.as_deref(), Some(PRE_RESTORE_LABEL));
+        assert_eq!(git(path, &["rev-parse", "HEAD"], None).unwrap(), snap.head_commit);
+        assert_eq!(std::fs::read_to_string(path.join("kept.txt")).unwrap(), "dirty edit\n");
+        assert!(path.join("untracked.txt").exists());
+        assert!(!path.join("junk.txt").exists() && !path.join("scratch.txt").exists());
+        assert_eq!(git(path, &["status", "--porcelain"], None).unwrap(), "M kept.txt\n?? untracked.txt");
+
+        // The wrecked state is itself restorable
+        assert_eq!(store.snapshots("agent").unwrap().len(), 2);
+        restore(dir.path(), &store, "agent", previous.id).unwrap();
+        assert!(path.join("junk.txt").exists() && path.join("scratch.txt").exists());
+        assert!(restore(dir.path(), &store, "other", snap.id).is_err());
+
+        // A branch-isolated agent shares the main checkout: never reset it
+        repo.set_head("refs/heads/main").unwrap();
+        store
+            .upsert_session(&SessionRecord {
+                runtime_kind: "daemon".to_string(),
+                checkout_path: dir.path().to_path_buf(),
+                ..session_record("shared")
+            })
+            .unwrap();
+        let shared = snapshot(dir.path(), &store, "shared", None).unwrap();
+        let err = restore(dir.path(), &store, "shared", shared.id).unwrap_err().to_string();
+        assert!(err.contains("only agent worktrees can be restored"), "{}", err);
+        assert_eq!(store.snapshots("shared").unwrap().len(), 1);
+    }
+}
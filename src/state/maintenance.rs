@@ -0,0 +1,205 @@
+//! Keeping state.db intact on hosts that run for weeks.
+//!
+//! `checkpoint` folds the write-ahead log back into the database, which the
+//! CSI loop does every `wal_checkpoint_interval_secs` so the WAL can't grow
+//! without bound between SQLite's own checkpoints. Before migrations touch an
+//! existing database, `StateStore::open` copies it to `state.db.bak`.
+//! `rembrandt state verify` runs `PRAGMA integrity_check` and, with
+//! `--repair`, rebuilds a damaged database from what can still be read, or
+//! from the backup.
+
+use super::StateStore;
+use crate::{RembrandtError, Result};
+use rusqlite::{Connection, OpenFlags};
+use std::path::{Path, PathBuf};
+
+/// Where the pre-migration snapshot of `db_path` goes
+pub fn backup_path(db_path: &Path) -> PathBuf {
+    sibling(db_path, "bak")
+}
+
+/// `state.db` → `state.db.<suffix>`
+fn sibling(db_path: &Path, suffix: &str) -> PathBuf {
+    let mut name = db_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    db_path.with_file_name(name)
+}
+
+/// What a WAL checkpoint did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    /// Another connection was mid-transaction, so not every frame was copied
+    pub busy: bool,
+    /// Frames in the log before it was truncated
+    pub log_frames: i64,
+    /// Frames copied into the database
+    pub checkpointed: i64,
+}
+
+impl StateStore {
+    /// Copy every committed WAL frame into the database and truncate the log
+    pub fn checkpoint(&self) -> Result<Checkpoint> {
+        let (busy, log_frames, checkpointed) = self
+            .conn
+            .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get(1)?, row.get(2)?))
+            })?;
+        Ok(Checkpoint {
+            busy: busy != 0,
+            log_frames,
+            checkpointed,
+        })
+    }
+
+    /// Write a consistent copy of the database, WAL included, to `dest`
+    pub fn backup_to(&self, dest: &Path) -> Result<()> {
+        backup(&self.conn, dest)
+    }
+}
+
+fn backup(conn: &Connection, dest: &Path) -> Result<()> {
+    // VACUUM INTO won't overwrite
+    if dest.exists() {
+        std::fs::remove_file(dest)?;
+    }
+    conn.execute("VACUUM INTO ?1", [dest.to_string_lossy()])?;
+    Ok(())
+}
+
+/// Problems `PRAGMA integrity_check` finds in the database at `db_path`;
+/// empty when it's sound
+pub fn verify(db_path: &Path) -> Result<Vec<String>> {
+    if !db_path.exists() {
+        return Err(RembrandtError::State(format!("{} does not exist", db_path.display())));
+    }
+    let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    match integrity_problems(&conn) {
+        // Damaged past the point SQLite can check it, e.g. a wrong header
+        Err(RembrandtError::Database(e)) => Ok(vec![e.to_string()]),
+        result => result,
+    }
+}
+
+fn integrity_problems(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+    let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+    let mut problems = Vec::new();
+    for row in rows {
+        let line = row?;
+        if line != "ok" {
+            problems.push(line);
+        }
+    }
+    Ok(problems)
+}
+
+/// How `repair` fixed the database
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Repair {
+    /// Only indexes were damaged; rebuilding them was enough
+    Reindexed,
+    /// The readable contents were copied into a fresh database; the damaged
+    /// one was kept at the path given
+    Rebuilt { damaged: PathBuf },
+    /// Nothing could be copied out, so the pre-migration backup was put in
+    /// place; the damaged database was kept at the path given
+    Restored { damaged: PathBuf },
+}
+
+/// Fix the database at `db_path`, trying the least lossy way first
+///
+/// Nothing else may have state.db open: stop the TUI, `csi run` and the
+/// orchestrator first.
+pub fn repair(db_path: &Path) -> Result<Repair> {
+    {
+        let conn = Connection::open(db_path)?;
+        if conn.execute_batch("REINDEX;").is_ok() && integrity_problems(&conn).is_ok_and(|p| p.is_empty()) {
+            return Ok(Repair::Reindexed);
+        }
+    }
+
+    let damaged = sibling(db_path, &format!("damaged-{}", chrono::Utc::now().format("%Y%m%d%H%M%S")));
+    let rebuilt = sibling(db_path, "rebuilt");
+    let copied = Connection::open(db_path)
+        .map_err(RembrandtError::from)
+        .and_then(|conn| backup(&conn, &rebuilt))
+        .and_then(|_| integrity_problems(&Connection::open(&rebuilt)?));
+    if matches!(copied, Ok(ref problems) if problems.is_empty()) {
+        replace(db_path, &rebuilt, &damaged)?;
+        return Ok(Repair::Rebuilt { damaged });
+    }
+    let _ = std::fs::remove_file(&rebuilt);
+
+    let bak = backup_path(db_path);
+    if bak.exists() && integrity_problems(&Connection::open(&bak)?)?.is_empty() {
+        std::fs::copy(&bak, &rebuilt)?;
+        replace(db_path, &rebuilt, &damaged)?;
+        return Ok(Repair::Restored { damaged });
+    }
+    Err(RembrandtError::State(format!(
+        "can't repair {}: its contents can't be read and there is no sound {}",
+        db_path.display(),
+        bak.display()
+    )))
+}
+
+/// Move `db_path` (and its WAL files) to `damaged`, then `fresh` into its place
+fn replace(db_path: &Path, fresh: &Path, damaged: &Path) -> Result<()> {
+    std::fs::rename(db_path, damaged)?;
+    for suffix in ["wal", "shm"] {
+        let _ = std::fs::remove_file(sibling(db_path, suffix));
+    }
+    std::fs::rename(fresh, db_path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::migrations::{self, MIGRATIONS};
+    use super::*;
+
+    #[test]
+    fn backs_up_checkpoints_and_repairs() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join(".rembrandt").join("state.db");
+        std::fs::create_dir_all(db_path.parent().unwrap()).unwrap();
+        {
+            // A database left at the first schema version
+            let conn = Connection::open(&db_path).unwrap();
+            conn.execute_batch(MIGRATIONS[0].sql).unwrap();
+            migrations::current_version(&conn).unwrap();
+            conn.execute(
+                "INSERT INTO schema_migrations(version, applied_at) VALUES (1, '2026-01-01T00:00:00Z')",
+                [],
+            )
+            .unwrap();
+        }
+
+        // Opening it snapshots it before migrating
+        let store = StateStore::open(dir.path()).unwrap();
+        let bak = Connection::open(backup_path(&db_path)).unwrap();
+        assert_eq!(migrations::current_version(&bak).unwrap(), 1);
+        drop(bak);
+
+        store.raise_attention(Some("a"), "budget", "over budget").unwrap();
+        let checkpoint = store.checkpoint().unwrap();
+        assert!(!checkpoint.busy);
+        assert_eq!(checkpoint.log_frames, checkpoint.checkpointed);
+        assert!(verify(&db_path).unwrap().is_empty());
+        store.backup_to(&backup_path(&db_path)).unwrap();
+        drop(store);
+
+        // Garbage in place of the database: nothing to copy out, so the
+        // backup goes back in place
+        std::fs::write(&db_path, vec![0x5a; 8192]).unwrap();
+        assert!(!verify(&db_path).unwrap().is_empty());
+        let Repair::Restored { damaged } = repair(&db_path).unwrap() else {
+            panic!("expected the backup to be restored");
+        };
+        assert!(damaged.exists());
+        assert!(verify(&db_path).unwrap().is_empty());
+        let store = StateStore::open(dir.path()).unwrap();
+        assert_eq!(store.open_attention().unwrap().len(), 1);
+    }
+}
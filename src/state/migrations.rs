@@ -0,0 +1,435 @@
+//! Ordered, forward-only schema migrations for `state.db`.
+//!
+//! Each migration runs in its own transaction together with the
+//! `schema_migrations` row that records it, so a failure leaves the database
+//! at the previous version. Migrations are never edited or removed once
+//! released; schema changes always get a new, higher version.
+
+use crate::{RembrandtError, Result};
+use chrono::Utc;
+use rusqlite::{params, Connection};
+
+/// A single schema change.
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub sql: &'static str,
+}
+
+/// All migrations, in the order they are applied.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "initial schema",
+        sql: r#"
+            CREATE TABLE IF NOT EXISTS sessions (
+              agent_id TEXT PRIMARY KEY,
+              runtime_kind TEXT NOT NULL,
+              runtime_session_id TEXT,
+              isolation_mode TEXT NOT NULL,
+              branch_name TEXT NOT NULL,
+              checkout_path TEXT NOT NULL,
+              task_id TEXT,
+              status TEXT NOT NULL,
+              model TEXT,
+              created_at TEXT NOT NULL,
+              updated_at TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS file_claims (
+              id INTEGER PRIMARY KEY AUTOINCREMENT,
+              agent_id TEXT NOT NULL,
+              path TEXT NOT NULL,
+              created_at TEXT NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS heartbeats (
+              agent_id TEXT PRIMARY KEY,
+              last_seen_at TEXT NOT NULL,
+              detail TEXT
+            );
+
+            CREATE TABLE IF NOT EXISTS csi_runs (
+              id INTEGER PRIMARY KEY AUTOINCREMENT,
+              started_at TEXT NOT NULL,
+              completed_at TEXT,
+              status TEXT NOT NULL,
+              summary TEXT
+            );
+
+            CREATE TABLE IF NOT EXISTS csi_events (
+              id INTEGER PRIMARY KEY AUTOINCREMENT,
+              csi_run_id INTEGER,
+              agent_id TEXT,
+              kind TEXT NOT NULL,
+              message TEXT NOT NULL,
+              created_at TEXT NOT NULL
+            );
+        "#,
+    },
+    Migration {
+        version: 2,
+        description: "index sessions by status and task, csi events by run",
+        sql: r#"
+            CREATE INDEX IF NOT EXISTS idx_sessions_status ON sessions(status);
+            CREATE INDEX IF NOT EXISTS idx_sessions_task_id ON sessions(task_id);
+            CREATE INDEX IF NOT EXISTS idx_csi_events_run ON csi_events(csi_run_id);
+        "#,
+    },
+    Migration {
+        version: 3,
+        description: "per-session token and cost usage",
+        sql: r#"
+            CREATE TABLE usage (
+              id INTEGER PRIMARY KEY AUTOINCREMENT,
+              agent_id TEXT NOT NULL,
+              recorded_at TEXT NOT NULL,
+              input_tokens INTEGER NOT NULL,
+              output_tokens INTEGER NOT NULL,
+              cost_usd REAL,
+              model TEXT,
+              source TEXT NOT NULL
+            );
+            CREATE INDEX idx_usage_agent ON usage(agent_id);
+            CREATE INDEX idx_usage_recorded_at ON usage(recorded_at);
+        "#,
+    },
+    Migration {
+        version: 4,
+        description: "attention queue",
+        sql: r#"
+            CREATE TABLE attention (
+              id INTEGER PRIMARY KEY AUTOINCREMENT,
+              agent_id TEXT,
+              kind TEXT NOT NULL,
+              message TEXT NOT NULL,
+              created_at TEXT NOT NULL,
+              resolved_at TEXT
+            );
+            CREATE INDEX idx_attention_open ON attention(resolved_at);
+        "#,
+    },
+    Migration {
+        version: 5,
+        description: "per-agent activity timeline",
+        sql: r#"
+            CREATE TABLE timeline (
+              id INTEGER PRIMARY KEY AUTOINCREMENT,
+              agent_id TEXT NOT NULL,
+              kind TEXT NOT NULL,
+              detail TEXT,
+              created_at TEXT NOT NULL
+            );
+            CREATE INDEX idx_timeline_agent ON timeline(agent_id);
+        "#,
+    },
+    Migration {
+        version: 6,
+        description: "merge queue",
+        sql: r#"
+            CREATE TABLE merge_queue (
+              id INTEGER PRIMARY KEY AUTOINCREMENT,
+              agent_id TEXT NOT NULL,
+              branch_name TEXT NOT NULL,
+              checkout_path TEXT NOT NULL,
+              status TEXT NOT NULL,
+              position INTEGER NOT NULL,
+              attempts INTEGER NOT NULL DEFAULT 0,
+              detail TEXT,
+              enqueued_at TEXT NOT NULL,
+              updated_at TEXT NOT NULL
+            );
+            CREATE INDEX idx_merge_queue_status ON merge_queue(status, position);
+        "#,
+    },
+    Migration {
+        version: 7,
+        description: "worktree snapshots",
+        sql: r#"
+            CREATE TABLE snapshots (
+              id INTEGER PRIMARY KEY AUTOINCREMENT,
+              agent_id TEXT NOT NULL,
+              head_commit TEXT NOT NULL,
+              snapshot_commit TEXT NOT NULL,
+              label TEXT,
+              created_at TEXT NOT NULL
+            );
+            CREATE INDEX idx_snapshots_agent ON snapshots(agent_id);
+        "#,
+    },
+    Migration {
+        version: 8,
+        description: "initial prompt per session",
+        sql: r#"
+            ALTER TABLE sessions ADD COLUMN prompt TEXT;
+        "#,
+    },
+    Migration {
+        version: 9,
+        description: "restarts of crashed agents",
+        sql: r#"
+            CREATE TABLE restarts (
+              id INTEGER PRIMARY KEY AUTOINCREMENT,
+              agent_id TEXT NOT NULL,
+              attempt INTEGER NOT NULL,
+              reason TEXT NOT NULL,
+              session_id TEXT,
+              error TEXT,
+              created_at TEXT NOT NULL
+            );
+            CREATE INDEX idx_restarts_agent ON restarts(agent_id);
+        "#,
+    },
+    Migration {
+        version: 10,
+        description: "generation counter for optimistic session updates",
+        sql: r#"
+            ALTER TABLE sessions ADD COLUMN updated_seq INTEGER NOT NULL DEFAULT 0;
+        "#,
+    },
+    Migration {
+        version: 11,
+        description: "agent inboxes",
+        sql: r#"
+            CREATE TABLE messages (
+              id INTEGER PRIMARY KEY AUTOINCREMENT,
+              from_agent TEXT NOT NULL,
+              to_agent TEXT NOT NULL,
+              body TEXT NOT NULL,
+              created_at TEXT NOT NULL,
+              delivered_at TEXT
+            );
+            CREATE INDEX idx_messages_inbox ON messages(to_agent, delivered_at);
+        "#,
+    },
+    Migration {
+        version: 12,
+        description: "health probe streaks",
+        sql: r#"
+            CREATE TABLE health (
+              agent_id TEXT PRIMARY KEY,
+              checked_at TEXT NOT NULL,
+              passed INTEGER NOT NULL,
+              streak INTEGER NOT NULL,
+              failing_since TEXT,
+              output TEXT
+            );
+        "#,
+    },
+    Migration {
+        version: 13,
+        description: "follow-up work found in agent output",
+        sql: r#"
+            CREATE TABLE followups (
+              id INTEGER PRIMARY KEY AUTOINCREMENT,
+              agent_id TEXT NOT NULL,
+              text TEXT NOT NULL,
+              source TEXT NOT NULL,
+              task_id TEXT,
+              issue_id TEXT,
+              created_at TEXT NOT NULL,
+              filed_at TEXT,
+              UNIQUE(agent_id, text)
+            );
+        "#,
+    },
+    Migration {
+        version: 14,
+        description: "append-only audit log",
+        sql: r#"
+            CREATE TABLE audit_log (
+              id INTEGER PRIMARY KEY AUTOINCREMENT,
+              created_at TEXT NOT NULL,
+              source TEXT NOT NULL,
+              actor TEXT NOT NULL,
+              action TEXT NOT NULL,
+              target TEXT,
+              params TEXT
+            );
+            CREATE INDEX idx_audit_log_action ON audit_log(action, created_at);
+            CREATE TRIGGER audit_log_no_update BEFORE UPDATE ON audit_log
+            BEGIN
+              SELECT RAISE(ABORT, 'audit_log is append-only');
+            END;
+            CREATE TRIGGER audit_log_no_delete BEFORE DELETE ON audit_log
+            BEGIN
+              SELECT RAISE(ABORT, 'audit_log is append-only');
+            END;
+        "#,
+    },
+    Migration {
+        version: 15,
+        description: "conversation transcripts",
+        sql: r#"
+            CREATE TABLE transcripts (
+              id INTEGER PRIMARY KEY AUTOINCREMENT,
+              agent_id TEXT NOT NULL,
+              entry_key TEXT NOT NULL,
+              role TEXT NOT NULL,
+              text TEXT NOT NULL,
+              created_at TEXT NOT NULL,
+              UNIQUE(agent_id, entry_key)
+            );
+        "#,
+    },
+    Migration {
+        version: 16,
+        description: "warm pool of idle agents",
+        sql: r#"
+            CREATE TABLE warm_pool (
+              agent_id TEXT PRIMARY KEY,
+              agent_type TEXT NOT NULL,
+              session_id TEXT NOT NULL,
+              base_branch TEXT NOT NULL,
+              base_commit TEXT NOT NULL,
+              branch_name TEXT NOT NULL,
+              checkout_path TEXT NOT NULL,
+              created_at TEXT NOT NULL
+            );
+        "#,
+    },
+    Migration {
+        version: 17,
+        description: "agent type per session",
+        sql: r#"
+            ALTER TABLE sessions ADD COLUMN agent_type TEXT;
+            -- Earlier sessions were named after the type they were spawned as
+            UPDATE sessions SET agent_type = CASE
+              WHEN agent_id LIKE 'claude-%' THEN 'claude-code'
+              WHEN agent_id LIKE 'opencode-%' THEN 'opencode'
+              WHEN agent_id LIKE 'amp-%' OR agent_id LIKE 'ampcode-%' THEN 'ampcode'
+              WHEN agent_id LIKE 'codex-%' THEN 'codex'
+              WHEN agent_id LIKE 'aider-%' THEN 'aider'
+            END;
+        "#,
+    },
+    Migration {
+        version: 18,
+        description: "scheduled steers",
+        sql: r#"
+            CREATE TABLE scheduled_steers (
+              id INTEGER PRIMARY KEY AUTOINCREMENT,
+              agent_id TEXT NOT NULL,
+              message TEXT NOT NULL,
+              due_at TEXT NOT NULL,
+              every_secs INTEGER,
+              sent_count INTEGER NOT NULL DEFAULT 0,
+              last_sent_at TEXT,
+              created_at TEXT NOT NULL
+            );
+            CREATE INDEX idx_scheduled_steers_agent ON scheduled_steers(agent_id);
+        "#,
+    },
+    Migration {
+        version: 19,
+        description: "merge conflict resolutions",
+        sql: r#"
+            CREATE TABLE merge_resolutions (
+              id INTEGER PRIMARY KEY AUTOINCREMENT,
+              merge_id INTEGER NOT NULL,
+              agent_id TEXT NOT NULL,
+              resolver_id TEXT NOT NULL,
+              base_branch TEXT NOT NULL,
+              branch_name TEXT NOT NULL,
+              checkout_path TEXT NOT NULL,
+              -- Conflicted paths, one per line
+              conflicted TEXT NOT NULL,
+              created_at TEXT NOT NULL,
+              resolved_at TEXT
+            );
+            CREATE INDEX idx_merge_resolutions_resolver ON merge_resolutions(resolver_id);
+        "#,
+    },
+    Migration {
+        version: 20,
+        description: "launch commands of restorable agents",
+        sql: r#"
+            CREATE TABLE launch_commands (
+              agent_id TEXT PRIMARY KEY,
+              command TEXT NOT NULL,
+              -- JSON array of arguments
+              args TEXT NOT NULL,
+              created_at TEXT NOT NULL
+            );
+        "#,
+    },
+];
+
+/// Version a fully migrated database is at.
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map_or(0, |m| m.version)
+}
+
+/// Highest applied version, or 0 for a new database.
+pub fn current_version(conn: &Connection) -> Result<i64> {
+    ensure_migrations_table(conn)?;
+    let version: Option<i64> =
+        conn.query_row("SELECT MAX(version) FROM schema_migrations", [], |row| row.get(0))?;
+    Ok(version.unwrap_or(0))
+}
+
+/// Apply every pending migration; returns the versions applied.
+///
+/// Refuses to touch a database written by a newer Rembrandt, since there is
+/// no way to migrate down.
+pub fn migrate(conn: &mut Connection) -> Result<Vec<i64>> {
+    let current = current_version(conn)?;
+    let latest = latest_version();
+    if current > latest {
+        return Err(RembrandtError::State(format!(
+            "state.db is at schema version {}, but this rembrandt only knows up to {}; upgrade rembrandt",
+            current, latest
+        )));
+    }
+
+    let mut applied = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration.sql).map_err(|e| {
+            RembrandtError::State(format!(
+                "migration {} ({}) failed: {}",
+                migration.version, migration.description, e
+            ))
+        })?;
+        tx.execute(
+            "INSERT INTO schema_migrations(version, applied_at) VALUES (?1, ?2)",
+            params![migration.version, Utc::now().to_rfc3339()],
+        )?;
+        tx.commit()?;
+        tracing::debug!(version = migration.version, "applied state.db migration");
+        applied.push(migration.version);
+    }
+    Ok(applied)
+}
+
+fn ensure_migrations_table(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS schema_migrations (
+          version INTEGER PRIMARY KEY,
+          applied_at TEXT NOT NULL
+        );
+        "#,
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_are_strictly_increasing() {
+        let versions: Vec<i64> = MIGRATIONS.iter().map(|m| m.version).collect();
+        assert!(versions.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(versions.first(), Some(&1));
+    }
+
+    #[test]
+    fn migrate_is_idempotent() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        assert_eq!(migrate(&mut conn).unwrap().len(), MIGRATIONS.len());
+        assert!(migrate(&mut conn).unwrap().is_empty());
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+    }
+}
@@ -1,10 +1,35 @@
 //! Persistent orchestration state for v2 (`.rembrandt/state.db`).
 
+pub mod maintenance;
+pub mod migrations;
+pub mod pool;
+
+use crate::audit::{AuditEntry, AuditQuery, AuditSource};
+use crate::followups::FollowUp;
+use crate::health::HealthRecord;
 use crate::isolation::IsolationMode;
+use crate::merge_queue::{MergeQueueEntry, MergeStatus};
+use crate::resolution::Resolution;
+use crate::schedule::ScheduledSteer;
+use crate::snapshot::SnapshotRecord;
+use crate::timeline::{TimelineEntry, TimelineKind};
+use crate::transcript::{TranscriptEntry, TranscriptTurn};
+use crate::usage::{UsageSample, UsageTotals};
+use crate::warm_pool::PooledAgent;
 use crate::{RembrandtError, Result};
 use chrono::{DateTime, Utc};
-use rusqlite::{params, Connection, OptionalExtension};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
 use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub use pool::{PooledStore, StatePool};
+
+/// How long a write waits for another connection's lock before failing
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Reads and compare-and-set attempts before a contended update gives up
+const MAX_CAS_ATTEMPTS: usize = 5;
 
 /// Persisted session status for v2 orchestration.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -15,9 +40,53 @@ pub enum SessionStatus {
     Completed,
     Failed,
     Stopped,
+    /// Flagged by CSI as stuck; needs a human or a steer
+    Blocked,
+    /// Stopped by the orchestrator for exceeding a cost or time budget
+    StoppedForBudget,
+    /// Suspended with `rembrandt pause` until `rembrandt resume`
+    Paused,
 }
 
 impl SessionStatus {
+    /// Statuses of sessions that still have a live agent.
+    pub const ACTIVE: [SessionStatus; 3] = [
+        SessionStatus::Starting,
+        SessionStatus::Active,
+        SessionStatus::Idle,
+    ];
+
+    pub fn is_active(self) -> bool {
+        Self::ACTIVE.contains(&self)
+    }
+
+    /// Statuses a session in this status may move to
+    ///
+    /// A live session (starting, active, idle or blocked) can move between
+    /// those, be paused, and end in any final status; a paused one resumes
+    /// into a live status or ends. A stopped one can still be marked
+    /// stopped for budget. Completed, failed and budget-stopped sessions
+    /// stay put: a continued agent is stored afresh with `upsert_session`,
+    /// which replaces an ended session whole.
+    pub fn next_statuses(self) -> &'static [SessionStatus] {
+        use SessionStatus::*;
+        match self {
+            Starting => &[Active, Idle, Blocked, Paused, Completed, Failed, Stopped, StoppedForBudget],
+            Active => &[Idle, Blocked, Paused, Completed, Failed, Stopped, StoppedForBudget],
+            Idle => &[Active, Blocked, Paused, Completed, Failed, Stopped, StoppedForBudget],
+            Blocked => &[Active, Idle, Paused, Completed, Failed, Stopped, StoppedForBudget],
+            Paused => &[Active, Idle, Blocked, Completed, Failed, Stopped, StoppedForBudget],
+            Stopped => &[StoppedForBudget],
+            Completed | Failed | StoppedForBudget => &[],
+        }
+    }
+
+    /// Whether a session may go from this status to `next`; staying in the
+    /// same status is always allowed
+    pub fn can_transition_to(self, next: SessionStatus) -> bool {
+        self == next || self.next_statuses().contains(&next)
+    }
+
     fn as_str(self) -> &'static str {
         match self {
             SessionStatus::Starting => "starting",
@@ -26,6 +95,9 @@ impl SessionStatus {
             SessionStatus::Completed => "completed",
             SessionStatus::Failed => "failed",
             SessionStatus::Stopped => "stopped",
+            SessionStatus::Blocked => "blocked",
+            SessionStatus::StoppedForBudget => "stopped_for_budget",
+            SessionStatus::Paused => "paused",
         }
     }
 
@@ -37,6 +109,9 @@ impl SessionStatus {
             "completed" => Ok(SessionStatus::Completed),
             "failed" => Ok(SessionStatus::Failed),
             "stopped" => Ok(SessionStatus::Stopped),
+            "blocked" => Ok(SessionStatus::Blocked),
+            "stopped_for_budget" => Ok(SessionStatus::StoppedForBudget),
+            "paused" => Ok(SessionStatus::Paused),
             other => Err(RembrandtError::State(format!(
                 "unknown session status '{}'",
                 other
@@ -45,6 +120,14 @@ impl SessionStatus {
     }
 }
 
+impl std::str::FromStr for SessionStatus {
+    type Err = RembrandtError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        SessionStatus::from_str(value)
+    }
+}
+
 impl std::fmt::Display for SessionStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.as_str())
@@ -65,6 +148,173 @@ pub struct SessionRecord {
     pub model: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Task the agent was started with
+    pub prompt: Option<String>,
+    /// Agent CLI it runs, canonically named (`claude-code`, `aider`, ...);
+    /// None when it wasn't started from one
+    pub agent_type: Option<String>,
+    /// Generation of the stored row, bumped by every write; compare-and-set
+    /// updates fail if it moved since the record was read (0 before the
+    /// record is first stored)
+    pub updated_seq: i64,
+}
+
+/// Filter and page over sessions; empty filters match everything.
+#[derive(Debug, Clone, Default)]
+pub struct SessionQuery {
+    /// Match any of these statuses
+    pub statuses: Vec<SessionStatus>,
+    pub task_id: Option<String>,
+    pub agent_type: Option<String>,
+    /// Only sessions created at or after this time
+    pub since: Option<DateTime<Utc>>,
+    pub limit: Option<usize>,
+    pub offset: usize,
+}
+
+impl SessionQuery {
+    /// Sessions that are starting, active, or idle.
+    pub fn active() -> Self {
+        Self {
+            statuses: SessionStatus::ACTIVE.to_vec(),
+            ..Self::default()
+        }
+    }
+
+    pub fn status(mut self, status: SessionStatus) -> Self {
+        self.statuses.push(status);
+        self
+    }
+
+    pub fn task(mut self, task_id: impl Into<String>) -> Self {
+        self.task_id = Some(task_id.into());
+        self
+    }
+
+    pub fn agent_type(mut self, agent_type: impl Into<String>) -> Self {
+        self.agent_type = Some(agent_type.into());
+        self
+    }
+
+    pub fn since(mut self, since: DateTime<Utc>) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    pub fn page(mut self, limit: usize, offset: usize) -> Self {
+        self.limit = Some(limit);
+        self.offset = offset;
+        self
+    }
+
+    fn where_clause(&self) -> (String, Vec<Value>) {
+        let mut conditions = Vec::new();
+        let mut values = Vec::new();
+        if !self.statuses.is_empty() {
+            conditions.push(format!(
+                "status IN ({})",
+                vec!["?"; self.statuses.len()].join(", ")
+            ));
+            values.extend(
+                self.statuses
+                    .iter()
+                    .map(|s| Value::Text(s.as_str().to_string())),
+            );
+        }
+        if let Some(task_id) = &self.task_id {
+            conditions.push("task_id = ?".to_string());
+            values.push(Value::Text(task_id.clone()));
+        }
+        if let Some(agent_type) = &self.agent_type {
+            conditions.push("agent_type = ?".to_string());
+            values.push(Value::Text(agent_type.clone()));
+        }
+        if let Some(since) = self.since {
+            conditions.push("created_at >= ?".to_string());
+            values.push(Value::Text(since.to_rfc3339()));
+        }
+
+        if conditions.is_empty() {
+            (String::new(), values)
+        } else {
+            (format!(" WHERE {}", conditions.join(" AND ")), values)
+        }
+    }
+}
+
+/// Last heartbeat written for an agent.
+#[derive(Debug, Clone)]
+pub struct HeartbeatRecord {
+    pub agent_id: String,
+    pub last_seen_at: DateTime<Utc>,
+    pub detail: Option<String>,
+}
+
+/// One CSI supervision pass.
+#[derive(Debug, Clone)]
+pub struct CsiRunRecord {
+    pub id: i64,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub status: String,
+    pub summary: Option<String>,
+}
+
+/// A finding or remediation recorded during a CSI pass.
+#[derive(Debug, Clone)]
+pub struct CsiEventRecord {
+    pub id: i64,
+    pub csi_run_id: Option<i64>,
+    pub agent_id: Option<String>,
+    pub kind: String,
+    pub message: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Something that needs a human to look at it.
+#[derive(Debug, Clone)]
+pub struct AttentionItem {
+    pub id: i64,
+    pub agent_id: Option<String>,
+    pub kind: String,
+    pub message: String,
+    pub created_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+/// A message from one agent (or a human) to another, held until delivered.
+#[derive(Debug, Clone)]
+pub struct AgentMessage {
+    pub id: i64,
+    pub from_agent: String,
+    pub to_agent: String,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    /// When it was typed into the recipient's session or sent to its runtime
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+impl AgentMessage {
+    /// What the recipient is sent
+    pub fn delivery_text(&self) -> String {
+        format!("[message from {}] {}", self.from_agent, self.body)
+    }
+}
+
+/// One attempt at restarting a crashed agent.
+#[derive(Debug, Clone)]
+pub struct RestartRecord {
+    pub id: i64,
+    pub agent_id: String,
+    /// 1 for the first restart
+    pub attempt: u32,
+    /// How the previous session ended, e.g. `exited with code 1`
+    pub reason: String,
+    /// Daemon session of the restarted agent, if it came up
+    pub session_id: Option<String>,
+    /// Why the respawn failed, if it did
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
 }
 
 /// SQLite-backed state store.
@@ -77,89 +327,60 @@ impl StateStore {
     pub fn open(repo_path: impl AsRef<Path>) -> Result<Self> {
         let rembrandt_dir = repo_path.as_ref().join(".rembrandt");
         std::fs::create_dir_all(&rembrandt_dir)?;
-        let db_path = rembrandt_dir.join("state.db");
-        let conn = Connection::open(&db_path)?;
-
-        let mut store = Self { db_path, conn };
+        let mut store = Self::connect(rembrandt_dir.join("state.db"))?;
         store.init_schema()?;
         Ok(store)
     }
 
+    /// Another connection to a database `open` has already set up
+    pub(crate) fn connect(db_path: PathBuf) -> Result<Self> {
+        let conn = Connection::open(&db_path)?;
+        // The CLI, daemon, TUI and orchestrator tasks all write state.db
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        // Sync the WAL on every commit, so a power loss can't undo one
+        conn.pragma_update(None, "synchronous", "FULL")?;
+        Ok(Self { db_path, conn })
+    }
+
     pub fn db_path(&self) -> &Path {
         &self.db_path
     }
 
-    fn init_schema(&mut self) -> Result<()> {
-        self.conn.execute_batch(
-            r#"
-            PRAGMA journal_mode = WAL;
-
-            CREATE TABLE IF NOT EXISTS schema_migrations (
-              version INTEGER PRIMARY KEY,
-              applied_at TEXT NOT NULL
-            );
-
-            CREATE TABLE IF NOT EXISTS sessions (
-              agent_id TEXT PRIMARY KEY,
-              runtime_kind TEXT NOT NULL,
-              runtime_session_id TEXT,
-              isolation_mode TEXT NOT NULL,
-              branch_name TEXT NOT NULL,
-              checkout_path TEXT NOT NULL,
-              task_id TEXT,
-              status TEXT NOT NULL,
-              model TEXT,
-              created_at TEXT NOT NULL,
-              updated_at TEXT NOT NULL
-            );
-
-            CREATE TABLE IF NOT EXISTS file_claims (
-              id INTEGER PRIMARY KEY AUTOINCREMENT,
-              agent_id TEXT NOT NULL,
-              path TEXT NOT NULL,
-              created_at TEXT NOT NULL
-            );
-
-            CREATE TABLE IF NOT EXISTS heartbeats (
-              agent_id TEXT PRIMARY KEY,
-              last_seen_at TEXT NOT NULL,
-              detail TEXT
-            );
-
-            CREATE TABLE IF NOT EXISTS csi_runs (
-              id INTEGER PRIMARY KEY AUTOINCREMENT,
-              started_at TEXT NOT NULL,
-              completed_at TEXT,
-              status TEXT NOT NULL,
-              summary TEXT
-            );
-
-            CREATE TABLE IF NOT EXISTS csi_events (
-              id INTEGER PRIMARY KEY AUTOINCREMENT,
-              csi_run_id INTEGER,
-              agent_id TEXT,
-              kind TEXT NOT NULL,
-              message TEXT NOT NULL,
-              created_at TEXT NOT NULL
-            );
-            "#,
-        )?;
-
-        self.conn.execute(
-            "INSERT OR IGNORE INTO schema_migrations(version, applied_at) VALUES(1, ?1)",
-            [Utc::now().to_rfc3339()],
-        )?;
+    /// Schema version the database is at after migrations ran.
+    pub fn schema_version(&self) -> Result<i64> {
+        migrations::current_version(&self.conn)
+    }
 
+    fn init_schema(&mut self) -> Result<()> {
+        self.conn.execute_batch("PRAGMA journal_mode = WAL;")?;
+        // Keep a way back in case a migration goes wrong halfway
+        let current = migrations::current_version(&self.conn)?;
+        if current > 0 && current < migrations::latest_version() {
+            self.backup_to(&maintenance::backup_path(&self.db_path))?;
+        }
+        migrations::migrate(&mut self.conn)?;
         Ok(())
     }
 
+    /// Store a newly spawned session, or refresh the details of a stored one
+    ///
+    /// A session that ended is replaced whole, since a continued agent is a
+    /// fresh run. A live or paused one only has its details refreshed; its
+    /// status goes through `transition_status`, so an upsert made from a
+    /// stale read can't undo a concurrent stop, and it stays where it is
+    /// when the status machine doesn't allow the move.
     pub fn upsert_session(&self, record: &SessionRecord) -> Result<()> {
-        self.conn.execute(
+        let (isolation_mode, checkout_path) = (
+            isolation_mode_to_str(record.isolation_mode),
+            record.checkout_path.to_string_lossy().to_string(),
+        );
+        let replaced = self.conn.execute(
             r#"
             INSERT INTO sessions (
               agent_id, runtime_kind, runtime_session_id, isolation_mode, branch_name,
-              checkout_path, task_id, status, model, created_at, updated_at
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+              checkout_path, task_id, status, model, created_at, updated_at, prompt,
+              agent_type
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
             ON CONFLICT(agent_id) DO UPDATE SET
               runtime_kind = excluded.runtime_kind,
               runtime_session_id = excluded.runtime_session_id,
@@ -169,23 +390,66 @@ impl StateStore {
               task_id = excluded.task_id,
               status = excluded.status,
               model = excluded.model,
-              updated_at = excluded.updated_at
+              updated_at = excluded.updated_at,
+              prompt = excluded.prompt,
+              agent_type = excluded.agent_type,
+              updated_seq = sessions.updated_seq + 1
+            WHERE sessions.status IN ('completed', 'failed', 'stopped', 'stopped_for_budget')
             "#,
             params![
                 record.agent_id,
                 record.runtime_kind,
                 record.runtime_session_id,
-                isolation_mode_to_str(record.isolation_mode),
+                isolation_mode,
                 record.branch_name,
-                record.checkout_path.to_string_lossy().to_string(),
+                checkout_path,
                 record.task_id,
                 record.status.as_str(),
                 record.model,
                 record.created_at.to_rfc3339(),
                 record.updated_at.to_rfc3339(),
+                record.prompt,
+                record.agent_type,
             ],
         )?;
+        if replaced > 0 {
+            return Ok(());
+        }
 
+        self.conn.execute(
+            r#"
+            UPDATE sessions SET
+              runtime_kind = ?2,
+              runtime_session_id = ?3,
+              isolation_mode = ?4,
+              branch_name = ?5,
+              checkout_path = ?6,
+              task_id = ?7,
+              model = ?8,
+              updated_at = ?9,
+              prompt = ?10,
+              agent_type = ?11,
+              updated_seq = updated_seq + 1
+            WHERE agent_id = ?1
+            "#,
+            params![
+                record.agent_id,
+                record.runtime_kind,
+                record.runtime_session_id,
+                isolation_mode,
+                record.branch_name,
+                checkout_path,
+                record.task_id,
+                record.model,
+                record.updated_at.to_rfc3339(),
+                record.prompt,
+                record.agent_type,
+            ],
+        )?;
+        self.transition_status(&record.agent_id, |current| {
+            (current.status != record.status && current.status.can_transition_to(record.status))
+                .then_some(record.status)
+        })?;
         Ok(())
     }
 
@@ -193,62 +457,85 @@ impl StateStore {
         let mut stmt = self.conn.prepare(
             r#"
             SELECT agent_id, runtime_kind, runtime_session_id, isolation_mode, branch_name,
-                   checkout_path, task_id, status, model, created_at, updated_at
+                   checkout_path, task_id, status, model, created_at, updated_at, prompt,
+                   updated_seq, agent_type
             FROM sessions WHERE agent_id = ?1
             "#,
         )?;
 
         let row = stmt
-            .query_row([agent_id], |row| {
-                let created_at: String = row.get(9)?;
-                let updated_at: String = row.get(10)?;
-                Ok(SessionRecord {
-                    agent_id: row.get(0)?,
-                    runtime_kind: row.get(1)?,
-                    runtime_session_id: row.get(2)?,
-                    isolation_mode: isolation_mode_from_str(&row.get::<_, String>(3)?)
-                        .map_err(to_sql_err)?,
-                    branch_name: row.get(4)?,
-                    checkout_path: PathBuf::from(row.get::<_, String>(5)?),
-                    task_id: row.get(6)?,
-                    status: SessionStatus::from_str(&row.get::<_, String>(7)?).map_err(to_sql_err)?,
-                    model: row.get(8)?,
-                    created_at: parse_rfc3339(&created_at).map_err(to_sql_err)?,
-                    updated_at: parse_rfc3339(&updated_at).map_err(to_sql_err)?,
-                })
-            })
+            .query_row([agent_id], session_from_row)
             .optional()?;
 
         Ok(row)
     }
 
     pub fn list_sessions(&self) -> Result<Vec<SessionRecord>> {
-        let mut stmt = self.conn.prepare(
+        self.query_sessions(&SessionQuery::default())
+    }
+
+    /// Sessions matching `query`, most recently updated first.
+    pub fn query_sessions(&self, query: &SessionQuery) -> Result<Vec<SessionRecord>> {
+        let (where_clause, mut values) = query.where_clause();
+        let mut sql = format!(
             r#"
             SELECT agent_id, runtime_kind, runtime_session_id, isolation_mode, branch_name,
-                   checkout_path, task_id, status, model, created_at, updated_at
-            FROM sessions
+                   checkout_path, task_id, status, model, created_at, updated_at, prompt,
+                   updated_seq, agent_type
+            FROM sessions{}
             ORDER BY updated_at DESC
             "#,
+            where_clause
+        );
+        if query.limit.is_some() || query.offset > 0 {
+            // SQLite needs a LIMIT to use OFFSET; -1 means unbounded
+            sql.push_str(" LIMIT ? OFFSET ?");
+            values.push(Value::Integer(query.limit.map_or(-1, |l| l as i64)));
+            values.push(Value::Integer(query.offset as i64));
+        }
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(params_from_iter(values), session_from_row)?;
+
+        let mut out = Vec::new();
+        for row in rows {
+            out.push(row?);
+        }
+        Ok(out)
+    }
+
+    pub fn list_by_status(&self, status: SessionStatus) -> Result<Vec<SessionRecord>> {
+        self.query_sessions(&SessionQuery::default().status(status))
+    }
+
+    pub fn list_by_task(&self, task_id: &str) -> Result<Vec<SessionRecord>> {
+        self.query_sessions(&SessionQuery::default().task(task_id))
+    }
+
+    /// Sessions that are starting, active, or idle.
+    pub fn list_active(&self) -> Result<Vec<SessionRecord>> {
+        self.query_sessions(&SessionQuery::active())
+    }
+
+    /// Number of sessions matching `query`, ignoring its limit and offset.
+    pub fn count_sessions(&self, query: &SessionQuery) -> Result<usize> {
+        let (where_clause, values) = query.where_clause();
+        let count: i64 = self.conn.query_row(
+            &format!("SELECT COUNT(*) FROM sessions{}", where_clause),
+            params_from_iter(values),
+            |row| row.get(0),
         )?;
+        Ok(count as usize)
+    }
 
+    /// Session count per status, for statuses that have any sessions.
+    pub fn count_by_status(&self) -> Result<Vec<(SessionStatus, usize)>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT status, COUNT(*) FROM sessions GROUP BY status ORDER BY status")?;
         let rows = stmt.query_map([], |row| {
-            let created_at: String = row.get(9)?;
-            let updated_at: String = row.get(10)?;
-            Ok(SessionRecord {
-                agent_id: row.get(0)?,
-                runtime_kind: row.get(1)?,
-                runtime_session_id: row.get(2)?,
-                isolation_mode: isolation_mode_from_str(&row.get::<_, String>(3)?)
-                    .map_err(to_sql_err)?,
-                branch_name: row.get(4)?,
-                checkout_path: PathBuf::from(row.get::<_, String>(5)?),
-                task_id: row.get(6)?,
-                status: SessionStatus::from_str(&row.get::<_, String>(7)?).map_err(to_sql_err)?,
-                model: row.get(8)?,
-                created_at: parse_rfc3339(&created_at).map_err(to_sql_err)?,
-                updated_at: parse_rfc3339(&updated_at).map_err(to_sql_err)?,
-            })
+            let status = SessionStatus::from_str(&row.get::<_, String>(0)?).map_err(to_sql_err)?;
+            Ok((status, row.get::<_, i64>(1)? as usize))
         })?;
 
         let mut out = Vec::new();
@@ -258,14 +545,145 @@ impl StateStore {
         Ok(out)
     }
 
+    /// Set a session's status; an actual change is also put on its timeline.
+    ///
+    /// Fails if the session can't go there from its current status (see
+    /// `SessionStatus::next_statuses`); use `transition_status` when the new
+    /// status depends on the current one.
     pub fn update_status(&self, agent_id: &str, status: SessionStatus) -> Result<()> {
+        self.transition_status(agent_id, |_| Some(status)).map(|_| ())
+    }
+
+    /// Move a session to the status `decide` picks from its current record
+    ///
+    /// The write is a compare-and-set on the record's `updated_seq`: if
+    /// another process changed the row in between, it is re-read and
+    /// `decide` asked again, so a decision is never made on stale state
+    /// (e.g. marking a session `completed` just after `rembrandt stop` made
+    /// it `stopped`). `decide` returning `None` leaves the session alone.
+    /// Returns the updated record, or `None` if nothing was written.
+    pub fn transition_status(
+        &self,
+        agent_id: &str,
+        mut decide: impl FnMut(&SessionRecord) -> Option<SessionStatus>,
+    ) -> Result<Option<SessionRecord>> {
+        for _ in 0..MAX_CAS_ATTEMPTS {
+            let Some(record) = self.get_session(agent_id)? else {
+                return Ok(None);
+            };
+            let Some(status) = decide(&record) else {
+                return Ok(None);
+            };
+            match self.compare_and_set_status(&record, status) {
+                Ok(updated) => return Ok(Some(updated)),
+                Err(RembrandtError::Conflict(_)) => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        Err(RembrandtError::Conflict(format!(
+            "session {} kept changing; gave up after {} attempts",
+            agent_id, MAX_CAS_ATTEMPTS
+        )))
+    }
+
+    /// Set `record`'s status if the stored row is still at its
+    /// `updated_seq`; fails with `RembrandtError::Conflict` otherwise, and
+    /// with `RembrandtError::State` for a transition the status machine
+    /// doesn't allow.
+    pub fn compare_and_set_status(&self, record: &SessionRecord, status: SessionStatus) -> Result<SessionRecord> {
+        if !record.status.can_transition_to(status) {
+            return Err(RembrandtError::State(format!(
+                "session {} can't go from {} to {}",
+                record.agent_id, record.status, status
+            )));
+        }
+        let now = Utc::now();
+        let changed = self.conn.execute(
+            r#"
+            UPDATE sessions SET status = ?1, updated_at = ?2, updated_seq = updated_seq + 1
+            WHERE agent_id = ?3 AND updated_seq = ?4
+            "#,
+            params![status.as_str(), now.to_rfc3339(), record.agent_id, record.updated_seq],
+        )?;
+        if changed == 0 {
+            return Err(match self.get_session(&record.agent_id)? {
+                Some(current) => RembrandtError::Conflict(format!(
+                    "session {} changed since it was read (seq {} -> {})",
+                    record.agent_id, record.updated_seq, current.updated_seq
+                )),
+                None => RembrandtError::SessionNotFound(record.agent_id.clone()),
+            });
+        }
+        if record.status != status {
+            self.record_timeline(
+                &record.agent_id,
+                TimelineKind::StatusChanged,
+                Some(&format!("{} -> {}", record.status, status)),
+            )?;
+        }
+        Ok(SessionRecord {
+            status,
+            updated_at: now,
+            updated_seq: record.updated_seq + 1,
+            ..record.clone()
+        })
+    }
+
+    pub fn record_timeline(
+        &self,
+        agent_id: &str,
+        kind: TimelineKind,
+        detail: Option<&str>,
+    ) -> Result<()> {
         self.conn.execute(
-            "UPDATE sessions SET status = ?1, updated_at = ?2 WHERE agent_id = ?3",
-            params![status.as_str(), Utc::now().to_rfc3339(), agent_id],
+            "INSERT INTO timeline(agent_id, kind, detail, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![agent_id, kind.as_str(), detail, Utc::now().to_rfc3339()],
         )?;
         Ok(())
     }
 
+    /// An agent's timeline, oldest first.
+    pub fn timeline(&self, agent_id: &str) -> Result<Vec<TimelineEntry>> {
+        self.query_timeline("WHERE agent_id = ?1 ORDER BY id", params![agent_id])
+    }
+
+    /// Timeline entries of every agent recorded after entry `after_id`,
+    /// oldest first
+    pub fn timeline_after(&self, after_id: i64, limit: usize) -> Result<Vec<TimelineEntry>> {
+        self.query_timeline("WHERE id > ?1 ORDER BY id LIMIT ?2", params![after_id, limit as i64])
+    }
+
+    /// Id of the newest timeline entry, or 0 if there are none
+    pub fn last_timeline_id(&self) -> Result<i64> {
+        Ok(self
+            .conn
+            .query_row("SELECT COALESCE(MAX(id), 0) FROM timeline", [], |row| row.get(0))?)
+    }
+
+    fn query_timeline(&self, filter: &str, params: impl rusqlite::Params) -> Result<Vec<TimelineEntry>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, agent_id, kind, detail, created_at FROM timeline {}",
+            filter
+        ))?;
+
+        let rows = stmt.query_map(params, |row| {
+            let created_at: String = row.get(4)?;
+            Ok(TimelineEntry {
+                id: row.get(0)?,
+                agent_id: row.get(1)?,
+                kind: row.get::<_, String>(2)?.parse().map_err(to_sql_err)?,
+                detail: row.get(3)?,
+                created_at: parse_rfc3339(&created_at).map_err(to_sql_err)?,
+            })
+        })?;
+
+        let mut out = Vec::new();
+        for row in rows {
+            out.push(row?);
+        }
+        Ok(out)
+    }
+
     pub fn touch_heartbeat(&self, agent_id: &str, detail: Option<&str>) -> Result<()> {
         self.conn.execute(
             r#"
@@ -278,36 +696,1380 @@ impl StateStore {
         )?;
         Ok(())
     }
-}
 
-fn isolation_mode_to_str(mode: IsolationMode) -> &'static str {
-    match mode {
-        IsolationMode::Branch => "branch",
-        IsolationMode::Worktree => "worktree",
+    pub fn record_usage(&self, agent_id: &str, sample: &UsageSample) -> Result<()> {
+        self.conn.execute(
+            r#"
+            INSERT INTO usage(agent_id, recorded_at, input_tokens, output_tokens, cost_usd, model, source)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            "#,
+            params![
+                agent_id,
+                Utc::now().to_rfc3339(),
+                sample.input_tokens as i64,
+                sample.output_tokens as i64,
+                sample.cost_usd,
+                sample.model,
+                sample.source,
+            ],
+        )?;
+        Ok(())
     }
-}
 
-fn isolation_mode_from_str(value: &str) -> Result<IsolationMode> {
-    match value {
-        "branch" => Ok(IsolationMode::Branch),
-        "worktree" => Ok(IsolationMode::Worktree),
-        other => Err(RembrandtError::State(format!(
-            "unknown isolation mode '{}'",
-            other
-        ))),
+    /// Cumulative usage for one session.
+    pub fn usage_for(&self, agent_id: &str) -> Result<UsageTotals> {
+        self.usage_where("WHERE agent_id = ?1", [agent_id])
     }
-}
 
-fn parse_rfc3339(value: &str) -> Result<DateTime<Utc>> {
-    chrono::DateTime::parse_from_rfc3339(value)
-        .map(|dt| dt.with_timezone(&Utc))
-        .map_err(|e| RembrandtError::State(format!("invalid timestamp '{}': {}", value, e)))
-}
+    /// Cumulative usage across all sessions.
+    pub fn usage_totals(&self) -> Result<UsageTotals> {
+        self.usage_where("", [])
+    }
 
-fn to_sql_err(err: RembrandtError) -> rusqlite::Error {
-    rusqlite::Error::FromSqlConversionFailure(
-        0,
-        rusqlite::types::Type::Text,
-        Box::new(err),
-    )
+    /// Usage recorded at or after `since`, across all sessions.
+    pub fn usage_since(&self, since: DateTime<Utc>) -> Result<UsageTotals> {
+        self.usage_where("WHERE recorded_at >= ?1", [since.to_rfc3339()])
+    }
+
+    fn usage_where<P: rusqlite::Params>(&self, where_clause: &str, params: P) -> Result<UsageTotals> {
+        let totals = self.conn.query_row(
+            &format!(
+                r#"
+                SELECT COALESCE(SUM(input_tokens), 0), COALESCE(SUM(output_tokens), 0),
+                       COALESCE(SUM(cost_usd), 0.0), COUNT(*)
+                FROM usage {}
+                "#,
+                where_clause
+            ),
+            params,
+            |row| {
+                Ok(UsageTotals {
+                    input_tokens: row.get::<_, i64>(0)? as u64,
+                    output_tokens: row.get::<_, i64>(1)? as u64,
+                    cost_usd: row.get(2)?,
+                    samples: row.get::<_, i64>(3)? as u64,
+                })
+            },
+        )?;
+        Ok(totals)
+    }
+
+    /// Add an item to the attention queue; returns its id.
+    pub fn raise_attention(&self, agent_id: Option<&str>, kind: &str, message: &str) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO attention(agent_id, kind, message, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![agent_id, kind, message, Utc::now().to_rfc3339()],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Unresolved attention items, oldest first.
+    pub fn open_attention(&self) -> Result<Vec<AttentionItem>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT id, agent_id, kind, message, created_at, resolved_at
+            FROM attention
+            WHERE resolved_at IS NULL
+            ORDER BY id
+            "#,
+        )?;
+
+        let rows = stmt.query_map([], |row| {
+            let created_at: String = row.get(4)?;
+            let resolved_at: Option<String> = row.get(5)?;
+            Ok(AttentionItem {
+                id: row.get(0)?,
+                agent_id: row.get(1)?,
+                kind: row.get(2)?,
+                message: row.get(3)?,
+                created_at: parse_rfc3339(&created_at).map_err(to_sql_err)?,
+                resolved_at: resolved_at
+                    .map(|v| parse_rfc3339(&v))
+                    .transpose()
+                    .map_err(to_sql_err)?,
+            })
+        })?;
+
+        let mut out = Vec::new();
+        for row in rows {
+            out.push(row?);
+        }
+        Ok(out)
+    }
+
+    /// Mark an attention item handled; returns false if it doesn't exist or was already resolved.
+    pub fn resolve_attention(&self, id: i64) -> Result<bool> {
+        let changed = self.conn.execute(
+            "UPDATE attention SET resolved_at = ?1 WHERE id = ?2 AND resolved_at IS NULL",
+            params![Utc::now().to_rfc3339(), id],
+        )?;
+        Ok(changed > 0)
+    }
+
+    /// Put a message in `to`'s inbox; returns its id.
+    pub fn send_agent_message(&self, from: &str, to: &str, body: &str) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO messages(from_agent, to_agent, body, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![from, to, body, Utc::now().to_rfc3339()],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// `agent_id`'s messages, oldest first; only undelivered ones with `pending_only`.
+    pub fn inbox(&self, agent_id: &str, pending_only: bool) -> Result<Vec<AgentMessage>> {
+        let filter = if pending_only { "AND delivered_at IS NULL" } else { "" };
+        let mut stmt = self.conn.prepare(&format!(
+            r#"
+            SELECT id, from_agent, to_agent, body, created_at, delivered_at
+            FROM messages
+            WHERE to_agent = ?1 {}
+            ORDER BY id
+            "#,
+            filter
+        ))?;
+
+        let rows = stmt.query_map([agent_id], |row| {
+            let created_at: String = row.get(4)?;
+            let delivered_at: Option<String> = row.get(5)?;
+            Ok(AgentMessage {
+                id: row.get(0)?,
+                from_agent: row.get(1)?,
+                to_agent: row.get(2)?,
+                body: row.get(3)?,
+                created_at: parse_rfc3339(&created_at).map_err(to_sql_err)?,
+                delivered_at: delivered_at
+                    .map(|v| parse_rfc3339(&v))
+                    .transpose()
+                    .map_err(to_sql_err)?,
+            })
+        })?;
+
+        let mut out = Vec::new();
+        for row in rows {
+            out.push(row?);
+        }
+        Ok(out)
+    }
+
+    /// Record a message as delivered and add it to the recipient's timeline.
+    pub fn mark_message_delivered(&self, message: &AgentMessage) -> Result<()> {
+        self.conn.execute(
+            "UPDATE messages SET delivered_at = ?1 WHERE id = ?2 AND delivered_at IS NULL",
+            params![Utc::now().to_rfc3339(), message.id],
+        )?;
+        self.record_timeline(
+            &message.to_agent,
+            TimelineKind::Message,
+            Some(&format!("from {}: {}", message.from_agent, crate::timeline::one_line(&message.body, 80))),
+        )
+    }
+
+    pub fn get_heartbeat(&self, agent_id: &str) -> Result<Option<HeartbeatRecord>> {
+        let row = self
+            .conn
+            .query_row(
+                "SELECT agent_id, last_seen_at, detail FROM heartbeats WHERE agent_id = ?1",
+                [agent_id],
+                |row| {
+                    let last_seen_at: String = row.get(1)?;
+                    Ok(HeartbeatRecord {
+                        agent_id: row.get(0)?,
+                        last_seen_at: parse_rfc3339(&last_seen_at).map_err(to_sql_err)?,
+                        detail: row.get(2)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(row)
+    }
+
+    /// Open a CSI run; returns its id for `record_csi_event`/`finish_csi_run`.
+    pub fn begin_csi_run(&self) -> Result<i64> {
+        self.conn.execute(
+            "INSERT INTO csi_runs(started_at, status) VALUES (?1, 'running')",
+            [Utc::now().to_rfc3339()],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    pub fn record_csi_event(
+        &self,
+        csi_run_id: i64,
+        agent_id: Option<&str>,
+        kind: &str,
+        message: &str,
+    ) -> Result<()> {
+        self.conn.execute(
+            r#"
+            INSERT INTO csi_events(csi_run_id, agent_id, kind, message, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            "#,
+            params![csi_run_id, agent_id, kind, message, Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// Record an event outside any CSI run, e.g. a hook run
+    pub fn record_event(&self, agent_id: Option<&str>, kind: &str, message: &str) -> Result<()> {
+        self.conn.execute(
+            r#"
+            INSERT INTO csi_events(csi_run_id, agent_id, kind, message, created_at)
+            VALUES (NULL, ?1, ?2, ?3, ?4)
+            "#,
+            params![agent_id, kind, message, Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    pub fn finish_csi_run(&self, csi_run_id: i64, status: &str, summary: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE csi_runs SET completed_at = ?1, status = ?2, summary = ?3 WHERE id = ?4",
+            params![Utc::now().to_rfc3339(), status, summary, csi_run_id],
+        )?;
+        Ok(())
+    }
+
+    /// Most recent CSI runs first.
+    pub fn list_csi_runs(&self, limit: usize) -> Result<Vec<CsiRunRecord>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT id, started_at, completed_at, status, summary
+            FROM csi_runs
+            ORDER BY id DESC
+            LIMIT ?1
+            "#,
+        )?;
+
+        let rows = stmt.query_map([limit as i64], |row| {
+            let started_at: String = row.get(1)?;
+            let completed_at: Option<String> = row.get(2)?;
+            Ok(CsiRunRecord {
+                id: row.get(0)?,
+                started_at: parse_rfc3339(&started_at).map_err(to_sql_err)?,
+                completed_at: completed_at
+                    .map(|v| parse_rfc3339(&v))
+                    .transpose()
+                    .map_err(to_sql_err)?,
+                status: row.get(3)?,
+                summary: row.get(4)?,
+            })
+        })?;
+
+        let mut out = Vec::new();
+        for row in rows {
+            out.push(row?);
+        }
+        Ok(out)
+    }
+
+    pub fn list_csi_events(&self, csi_run_id: i64) -> Result<Vec<CsiEventRecord>> {
+        self.query_csi_events("WHERE csi_run_id = ?1 ORDER BY id", params![csi_run_id])
+    }
+
+    /// An agent's events, from CSI runs and outside them, oldest first
+    pub fn list_agent_events(&self, agent_id: &str) -> Result<Vec<CsiEventRecord>> {
+        self.query_csi_events("WHERE agent_id = ?1 ORDER BY id", params![agent_id])
+    }
+
+    /// The latest events recorded outside CSI runs (hooks), newest first
+    pub fn list_loose_events(&self, limit: usize) -> Result<Vec<CsiEventRecord>> {
+        self.query_csi_events(
+            "WHERE csi_run_id IS NULL ORDER BY id DESC LIMIT ?1",
+            params![limit as i64],
+        )
+    }
+
+    fn query_csi_events(&self, filter: &str, params: impl rusqlite::Params) -> Result<Vec<CsiEventRecord>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, csi_run_id, agent_id, kind, message, created_at FROM csi_events {}",
+            filter
+        ))?;
+
+        let rows = stmt.query_map(params, |row| {
+            let created_at: String = row.get(5)?;
+            Ok(CsiEventRecord {
+                id: row.get(0)?,
+                csi_run_id: row.get(1)?,
+                agent_id: row.get(2)?,
+                kind: row.get(3)?,
+                message: row.get(4)?,
+                created_at: parse_rfc3339(&created_at).map_err(to_sql_err)?,
+            })
+        })?;
+
+        let mut out = Vec::new();
+        for row in rows {
+            out.push(row?);
+        }
+        Ok(out)
+    }
+
+    /// Add an agent's branch to the back of the merge queue; returns the entry id.
+    ///
+    /// An agent already waiting in the queue keeps its place.
+    pub fn enqueue_merge(
+        &self,
+        agent_id: &str,
+        branch_name: &str,
+        checkout_path: &Path,
+    ) -> Result<i64> {
+        let waiting: Option<i64> = self
+            .conn
+            .query_row(
+                "SELECT id FROM merge_queue WHERE agent_id = ?1 AND status = ?2",
+                params![agent_id, MergeStatus::Queued.as_str()],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if let Some(id) = waiting {
+            return Ok(id);
+        }
+
+        let now = Utc::now().to_rfc3339();
+        self.conn.execute(
+            r#"
+            INSERT INTO merge_queue(agent_id, branch_name, checkout_path, status, position, enqueued_at, updated_at)
+            VALUES (?1, ?2, ?3, ?4, (SELECT COALESCE(MAX(position), 0) + 1 FROM merge_queue), ?5, ?5)
+            "#,
+            params![
+                agent_id,
+                branch_name,
+                checkout_path.to_string_lossy().to_string(),
+                MergeStatus::Queued.as_str(),
+                now,
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Every merge queue entry in queue order, finished ones included.
+    pub fn merge_queue(&self) -> Result<Vec<MergeQueueEntry>> {
+        self.list_merges(None)
+    }
+
+    /// The entry at the front of the queue still waiting to merge.
+    pub fn next_queued_merge(&self) -> Result<Option<MergeQueueEntry>> {
+        Ok(self.list_merges(Some(MergeStatus::Queued))?.into_iter().next())
+    }
+
+    pub fn update_merge(&self, id: i64, status: MergeStatus, detail: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE merge_queue SET status = ?1, detail = ?2, updated_at = ?3 WHERE id = ?4",
+            params![status.as_str(), detail, Utc::now().to_rfc3339(), id],
+        )?;
+        Ok(())
+    }
+
+    /// Send an entry to the back of the queue, counting the failed attempt.
+    pub fn requeue_merge(&self, id: i64, detail: &str) -> Result<()> {
+        self.conn.execute(
+            r#"
+            UPDATE merge_queue
+            SET status = ?1, detail = ?2, updated_at = ?3, attempts = attempts + 1,
+                position = (SELECT MAX(position) + 1 FROM merge_queue)
+            WHERE id = ?4
+            "#,
+            params![MergeStatus::Queued.as_str(), detail, Utc::now().to_rfc3339(), id],
+        )?;
+        Ok(())
+    }
+
+    /// Drop an agent's waiting entries; returns how many were removed.
+    pub fn dequeue_merge(&self, agent_id: &str) -> Result<usize> {
+        Ok(self.conn.execute(
+            "DELETE FROM merge_queue WHERE agent_id = ?1 AND status = ?2",
+            params![agent_id, MergeStatus::Queued.as_str()],
+        )?)
+    }
+
+    /// Record a restart attempt, with the session it started or the error
+    /// it hit; returns its id.
+    pub fn record_restart(
+        &self,
+        agent_id: &str,
+        attempt: u32,
+        reason: &str,
+        outcome: std::result::Result<&str, &str>,
+    ) -> Result<i64> {
+        let (session_id, error) = match outcome {
+            Ok(session_id) => (Some(session_id), None),
+            Err(error) => (None, Some(error)),
+        };
+        self.conn.execute(
+            r#"
+            INSERT INTO restarts(agent_id, attempt, reason, session_id, error, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            "#,
+            params![agent_id, attempt, reason, session_id, error, Utc::now().to_rfc3339()],
+        )?;
+        let id = self.conn.last_insert_rowid();
+        let detail = match error {
+            None => format!("attempt {} after it {}", attempt, reason),
+            Some(error) => format!("attempt {} after it {} failed: {}", attempt, reason, error),
+        };
+        self.record_timeline(agent_id, TimelineKind::Restarted, Some(&detail))?;
+        Ok(id)
+    }
+
+    /// An agent's restart attempts, oldest first.
+    pub fn restarts(&self, agent_id: &str) -> Result<Vec<RestartRecord>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT id, agent_id, attempt, reason, session_id, error, created_at
+            FROM restarts
+            WHERE agent_id = ?1
+            ORDER BY id
+            "#,
+        )?;
+
+        let rows = stmt.query_map([agent_id], |row| {
+            let created_at: String = row.get(6)?;
+            Ok(RestartRecord {
+                id: row.get(0)?,
+                agent_id: row.get(1)?,
+                attempt: row.get::<_, i64>(2)? as u32,
+                reason: row.get(3)?,
+                session_id: row.get(4)?,
+                error: row.get(5)?,
+                created_at: parse_rfc3339(&created_at).map_err(to_sql_err)?,
+            })
+        })?;
+
+        let mut out = Vec::new();
+        for row in rows {
+            out.push(row?);
+        }
+        Ok(out)
+    }
+
+    /// Record a worktree snapshot; returns its id.
+    pub fn record_snapshot(
+        &self,
+        agent_id: &str,
+        head_commit: &str,
+        snapshot_commit: &str,
+        label: Option<&str>,
+    ) -> Result<i64> {
+        self.conn.execute(
+            r#"
+            INSERT INTO snapshots(agent_id, head_commit, snapshot_commit, label, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            "#,
+            params![agent_id, head_commit, snapshot_commit, label, Utc::now().to_rfc3339()],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// An agent's snapshots, oldest first.
+    pub fn snapshots(&self, agent_id: &str) -> Result<Vec<SnapshotRecord>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT id, agent_id, head_commit, snapshot_commit, label, created_at
+            FROM snapshots
+            WHERE agent_id = ?1
+            ORDER BY id
+            "#,
+        )?;
+
+        let rows = stmt.query_map([agent_id], snapshot_from_row)?;
+        let mut out = Vec::new();
+        for row in rows {
+            out.push(row?);
+        }
+        Ok(out)
+    }
+
+    pub fn get_snapshot(&self, id: i64) -> Result<Option<SnapshotRecord>> {
+        Ok(self
+            .conn
+            .query_row(
+                r#"
+                SELECT id, agent_id, head_commit, snapshot_commit, label, created_at
+                FROM snapshots
+                WHERE id = ?1
+                "#,
+                [id],
+                snapshot_from_row,
+            )
+            .optional()?)
+    }
+
+    /// Record a health check result, extending or restarting the agent's
+    /// streak; returns the updated record.
+    pub fn record_health(&self, agent_id: &str, passed: bool, output: Option<&str>) -> Result<HealthRecord> {
+        let now = Utc::now();
+        let previous = self.get_health(agent_id)?;
+        let (streak, failing_since) = match previous {
+            Some(prev) if prev.passed == passed => (prev.streak + 1, prev.failing_since),
+            _ => (1, (!passed).then_some(now)),
+        };
+        self.conn.execute(
+            r#"
+            INSERT INTO health(agent_id, checked_at, passed, streak, failing_since, output)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            ON CONFLICT(agent_id) DO UPDATE SET
+              checked_at = excluded.checked_at,
+              passed = excluded.passed,
+              streak = excluded.streak,
+              failing_since = excluded.failing_since,
+              output = excluded.output
+            "#,
+            params![
+                agent_id,
+                now.to_rfc3339(),
+                passed,
+                streak as i64,
+                failing_since.map(|t| t.to_rfc3339()),
+                output
+            ],
+        )?;
+        Ok(HealthRecord {
+            agent_id: agent_id.to_string(),
+            checked_at: now,
+            passed,
+            streak,
+            failing_since,
+            output: output.map(str::to_string),
+        })
+    }
+
+    pub fn get_health(&self, agent_id: &str) -> Result<Option<HealthRecord>> {
+        Ok(self
+            .conn
+            .query_row(
+                r#"
+                SELECT agent_id, checked_at, passed, streak, failing_since, output
+                FROM health
+                WHERE agent_id = ?1
+                "#,
+                [agent_id],
+                health_from_row,
+            )
+            .optional()?)
+    }
+
+    /// The latest health check of every agent that has had one.
+    pub fn list_health(&self) -> Result<Vec<HealthRecord>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT agent_id, checked_at, passed, streak, failing_since, output
+            FROM health
+            ORDER BY agent_id
+            "#,
+        )?;
+
+        let rows = stmt.query_map([], health_from_row)?;
+        let mut out = Vec::new();
+        for row in rows {
+            out.push(row?);
+        }
+        Ok(out)
+    }
+
+    /// Note follow-up work an agent deferred, linked to its session's task;
+    /// returns false if the agent already reported the same text.
+    pub fn record_followup(&self, agent_id: &str, text: &str, source: &str) -> Result<bool> {
+        let inserted = self.conn.execute(
+            r#"
+            INSERT OR IGNORE INTO followups(agent_id, text, source, task_id, created_at)
+            VALUES (?1, ?2, ?3, (SELECT task_id FROM sessions WHERE agent_id = ?1), ?4)
+            "#,
+            params![agent_id, text, source, Utc::now().to_rfc3339()],
+        )?;
+        Ok(inserted > 0)
+    }
+
+    /// Follow-ups, oldest first: one agent's or everyone's, optionally only
+    /// those not filed as issues yet.
+    pub fn followups(&self, agent_id: Option<&str>, pending_only: bool) -> Result<Vec<FollowUp>> {
+        let mut conditions = Vec::new();
+        if agent_id.is_some() {
+            conditions.push("agent_id = ?1");
+        }
+        if pending_only {
+            conditions.push("filed_at IS NULL");
+        }
+        let filter = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+        let mut stmt = self.conn.prepare(&format!(
+            r#"
+            SELECT id, agent_id, text, source, task_id, issue_id, created_at, filed_at
+            FROM followups
+            {}
+            ORDER BY id
+            "#,
+            filter
+        ))?;
+
+        let params: Vec<&str> = agent_id.into_iter().collect();
+        let rows = stmt.query_map(params_from_iter(params), |row| {
+            let created_at: String = row.get(6)?;
+            let filed_at: Option<String> = row.get(7)?;
+            Ok(FollowUp {
+                id: row.get(0)?,
+                agent_id: row.get(1)?,
+                text: row.get(2)?,
+                source: row.get(3)?,
+                task_id: row.get(4)?,
+                issue_id: row.get(5)?,
+                created_at: parse_rfc3339(&created_at).map_err(to_sql_err)?,
+                filed_at: filed_at
+                    .map(|v| parse_rfc3339(&v))
+                    .transpose()
+                    .map_err(to_sql_err)?,
+            })
+        })?;
+
+        let mut out = Vec::new();
+        for row in rows {
+            out.push(row?);
+        }
+        Ok(out)
+    }
+
+    /// Record that a follow-up was filed, as `issue_id` if the tracker returned one.
+    pub fn mark_followup_filed(&self, id: i64, issue_id: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "UPDATE followups SET filed_at = ?1, issue_id = ?2 WHERE id = ?3",
+            params![Utc::now().to_rfc3339(), issue_id, id],
+        )?;
+        Ok(())
+    }
+
+    /// Append an entry to the audit log.
+    pub fn record_audit(
+        &self,
+        source: AuditSource,
+        actor: &str,
+        action: &str,
+        target: Option<&str>,
+        params: Option<&serde_json::Value>,
+    ) -> Result<()> {
+        self.conn.execute(
+            r#"
+            INSERT INTO audit_log(created_at, source, actor, action, target, params)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            "#,
+            params![
+                Utc::now().to_rfc3339(),
+                source.as_str(),
+                actor,
+                action,
+                target,
+                params.map(serde_json::Value::to_string),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Audit log entries matching `query`, oldest first; with a limit, the
+    /// most recent ones.
+    pub fn audit_log(&self, query: &AuditQuery) -> Result<Vec<AuditEntry>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT id, created_at, source, actor, action, target, params
+            FROM audit_log
+            WHERE (?1 IS NULL OR action = ?1)
+              AND (?2 IS NULL OR target = ?2)
+              AND (?3 IS NULL OR created_at >= ?3)
+            ORDER BY id DESC
+            LIMIT ?4
+            "#,
+        )?;
+
+        let limit = query.limit.map_or(-1, |limit| limit as i64);
+        let since = query.since.map(|since| since.to_rfc3339());
+        let rows = stmt.query_map(
+            params![query.action, query.target, since, limit],
+            |row| {
+                let created_at: String = row.get(1)?;
+                let params: Option<String> = row.get(6)?;
+                Ok(AuditEntry {
+                    id: row.get(0)?,
+                    created_at: parse_rfc3339(&created_at).map_err(to_sql_err)?,
+                    source: row.get::<_, String>(2)?.parse().map_err(to_sql_err)?,
+                    actor: row.get(3)?,
+                    action: row.get(4)?,
+                    target: row.get(5)?,
+                    params: params.and_then(|p| serde_json::from_str(&p).ok()),
+                })
+            },
+        )?;
+
+        let mut out = Vec::new();
+        for row in rows {
+            out.push(row?);
+        }
+        out.reverse();
+        Ok(out)
+    }
+
+    /// Store conversation turns not stored yet; returns how many were new
+    pub fn record_transcript(&self, agent_id: &str, turns: &[TranscriptTurn]) -> Result<usize> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            INSERT OR IGNORE INTO transcripts(agent_id, entry_key, role, text, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            "#,
+        )?;
+        let mut added = 0;
+        for turn in turns {
+            added += stmt.execute(params![
+                agent_id,
+                turn.key,
+                turn.role.as_str(),
+                turn.text,
+                turn.created_at.to_rfc3339(),
+            ])?;
+        }
+        Ok(added)
+    }
+
+    /// Every transcript turn, oldest first, optionally of one agent only
+    pub fn transcripts(&self, agent_id: Option<&str>) -> Result<Vec<TranscriptEntry>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT id, agent_id, role, text, created_at
+            FROM transcripts
+            WHERE ?1 IS NULL OR agent_id = ?1
+            ORDER BY id
+            "#,
+        )?;
+
+        let rows = stmt.query_map(params![agent_id], |row| {
+            let created_at: String = row.get(4)?;
+            Ok(TranscriptEntry {
+                id: row.get(0)?,
+                agent_id: row.get(1)?,
+                role: row.get::<_, String>(2)?.parse().map_err(to_sql_err)?,
+                text: row.get(3)?,
+                created_at: parse_rfc3339(&created_at).map_err(to_sql_err)?,
+            })
+        })?;
+
+        let mut out = Vec::new();
+        for row in rows {
+            out.push(row?);
+        }
+        Ok(out)
+    }
+
+    /// Add an idle agent to the warm pool.
+    pub fn add_pooled(&self, agent: &PooledAgent) -> Result<()> {
+        self.conn.execute(
+            r#"
+            INSERT INTO warm_pool(agent_id, agent_type, session_id, base_branch, base_commit,
+                                  branch_name, checkout_path, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            "#,
+            params![
+                agent.agent_id,
+                agent.agent_type,
+                agent.session_id,
+                agent.base_branch,
+                agent.base_commit,
+                agent.branch_name,
+                agent.checkout_path.to_string_lossy(),
+                agent.created_at.to_rfc3339(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Pooled agents, oldest first.
+    pub fn pooled_agents(&self) -> Result<Vec<PooledAgent>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT agent_id, agent_type, session_id, base_branch, base_commit,
+                   branch_name, checkout_path, created_at
+            FROM warm_pool
+            ORDER BY created_at, agent_id
+            "#,
+        )?;
+
+        let rows = stmt.query_map([], |row| {
+            let checkout_path: String = row.get(6)?;
+            let created_at: String = row.get(7)?;
+            Ok(PooledAgent {
+                agent_id: row.get(0)?,
+                agent_type: row.get(1)?,
+                session_id: row.get(2)?,
+                base_branch: row.get(3)?,
+                base_commit: row.get(4)?,
+                branch_name: row.get(5)?,
+                checkout_path: PathBuf::from(checkout_path),
+                created_at: parse_rfc3339(&created_at).map_err(to_sql_err)?,
+            })
+        })?;
+
+        let mut out = Vec::new();
+        for row in rows {
+            out.push(row?);
+        }
+        Ok(out)
+    }
+
+    /// Take an agent out of the warm pool; false if it was no longer there,
+    /// e.g. because another spawn took it first.
+    pub fn remove_pooled(&self, agent_id: &str) -> Result<bool> {
+        let removed = self.conn.execute("DELETE FROM warm_pool WHERE agent_id = ?1", [agent_id])?;
+        Ok(removed > 0)
+    }
+
+    /// Schedule `message` to be typed into `agent_id`'s session at `due_at`,
+    /// and again every `every` if given; returns the steer's id.
+    pub fn schedule_steer(
+        &self,
+        agent_id: &str,
+        message: &str,
+        due_at: DateTime<Utc>,
+        every: Option<chrono::Duration>,
+    ) -> Result<i64> {
+        self.conn.execute(
+            r#"
+            INSERT INTO scheduled_steers(agent_id, message, due_at, every_secs, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            "#,
+            params![
+                agent_id,
+                message,
+                due_at.to_rfc3339(),
+                every.map(|every| every.num_seconds()),
+                Utc::now().to_rfc3339(),
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Scheduled steers, all or one agent's, soonest first.
+    pub fn scheduled_steers(&self, agent_id: Option<&str>) -> Result<Vec<ScheduledSteer>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT id, agent_id, message, due_at, every_secs, sent_count, last_sent_at, created_at
+            FROM scheduled_steers
+            WHERE ?1 IS NULL OR agent_id = ?1
+            ORDER BY id
+            "#,
+        )?;
+
+        let rows = stmt.query_map([agent_id], |row| {
+            let due_at: String = row.get(3)?;
+            let every_secs: Option<i64> = row.get(4)?;
+            let last_sent_at: Option<String> = row.get(6)?;
+            let created_at: String = row.get(7)?;
+            Ok(ScheduledSteer {
+                id: row.get(0)?,
+                agent_id: row.get(1)?,
+                message: row.get(2)?,
+                due_at: parse_rfc3339(&due_at).map_err(to_sql_err)?,
+                every: every_secs.map(chrono::Duration::seconds),
+                sent_count: row.get(5)?,
+                last_sent_at: last_sent_at
+                    .as_deref()
+                    .map(parse_rfc3339)
+                    .transpose()
+                    .map_err(to_sql_err)?,
+                created_at: parse_rfc3339(&created_at).map_err(to_sql_err)?,
+            })
+        })?;
+
+        let mut out = Vec::new();
+        for row in rows {
+            out.push(row?);
+        }
+        // Times are compared parsed: RFC 3339 strings with and without
+        // fractional seconds don't sort by time
+        out.sort_by_key(|steer| steer.due_at);
+        Ok(out)
+    }
+
+    /// Count a steer as sent at `sent_at` and move it to `next_due`, or
+    /// remove it when it isn't sent again.
+    pub fn record_steer_sent(
+        &self,
+        id: i64,
+        sent_at: DateTime<Utc>,
+        next_due: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        match next_due {
+            Some(next_due) => self.conn.execute(
+                r#"
+                UPDATE scheduled_steers
+                SET due_at = ?2, last_sent_at = ?3, sent_count = sent_count + 1
+                WHERE id = ?1
+                "#,
+                params![id, next_due.to_rfc3339(), sent_at.to_rfc3339()],
+            )?,
+            None => self.conn.execute("DELETE FROM scheduled_steers WHERE id = ?1", [id])?,
+        };
+        Ok(())
+    }
+
+    /// Drop a scheduled steer; false if there was none with that id.
+    pub fn cancel_steer(&self, id: i64) -> Result<bool> {
+        let removed = self.conn.execute("DELETE FROM scheduled_steers WHERE id = ?1", [id])?;
+        Ok(removed > 0)
+    }
+
+    /// Record a conflicted merge being resolved in `resolution`'s worktree;
+    /// returns its id.
+    pub fn record_resolution(&self, resolution: &Resolution) -> Result<i64> {
+        self.conn.execute(
+            r#"
+            INSERT INTO merge_resolutions(
+              merge_id, agent_id, resolver_id, base_branch, branch_name, checkout_path, conflicted, created_at
+            )
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            "#,
+            params![
+                resolution.merge_id,
+                resolution.agent_id,
+                resolution.resolver_id,
+                resolution.base_branch,
+                resolution.branch_name,
+                resolution.checkout_path.to_string_lossy(),
+                resolution.conflicted.join("\n"),
+                resolution.created_at.to_rfc3339(),
+            ],
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// The unresolved resolution worked on as `resolver_id`, if any.
+    pub fn open_resolution(&self, resolver_id: &str) -> Result<Option<Resolution>> {
+        let resolution = self
+            .conn
+            .query_row(
+                r#"
+                SELECT id, merge_id, agent_id, resolver_id, base_branch, branch_name, checkout_path,
+                       conflicted, created_at, resolved_at
+                FROM merge_resolutions
+                WHERE resolver_id = ?1 AND resolved_at IS NULL
+                ORDER BY id DESC
+                LIMIT 1
+                "#,
+                [resolver_id],
+                |row| {
+                    let conflicted: String = row.get(7)?;
+                    let created_at: String = row.get(8)?;
+                    let resolved_at: Option<String> = row.get(9)?;
+                    Ok(Resolution {
+                        id: row.get(0)?,
+                        merge_id: row.get(1)?,
+                        agent_id: row.get(2)?,
+                        resolver_id: row.get(3)?,
+                        base_branch: row.get(4)?,
+                        branch_name: row.get(5)?,
+                        checkout_path: PathBuf::from(row.get::<_, String>(6)?),
+                        conflicted: conflicted.lines().map(str::to_string).collect(),
+                        created_at: parse_rfc3339(&created_at).map_err(to_sql_err)?,
+                        resolved_at: resolved_at
+                            .as_deref()
+                            .map(parse_rfc3339)
+                            .transpose()
+                            .map_err(to_sql_err)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(resolution)
+    }
+
+    /// Mark a resolution done, or dropped in favor of a newer one.
+    pub fn close_resolution(&self, id: i64) -> Result<()> {
+        self.conn.execute(
+            "UPDATE merge_resolutions SET resolved_at = ?1 WHERE id = ?2",
+            params![Utc::now().to_rfc3339(), id],
+        )?;
+        Ok(())
+    }
+
+    /// Remember the command line `agent_id` was started with, so it can be
+    /// started the same way again.
+    pub fn record_launch_command(&self, agent_id: &str, command: &str, args: &[String]) -> Result<()> {
+        self.conn.execute(
+            r#"
+            INSERT INTO launch_commands(agent_id, command, args, created_at)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT(agent_id) DO UPDATE SET
+              command = excluded.command,
+              args = excluded.args,
+              created_at = excluded.created_at
+            "#,
+            params![agent_id, command, serde_json::json!(args).to_string(), Utc::now().to_rfc3339()],
+        )?;
+        Ok(())
+    }
+
+    /// The command and arguments `agent_id` was last started with, if recorded.
+    pub fn launch_command(&self, agent_id: &str) -> Result<Option<(String, Vec<String>)>> {
+        let row: Option<(String, String)> = self
+            .conn
+            .query_row(
+                "SELECT command, args FROM launch_commands WHERE agent_id = ?1",
+                [agent_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        row.map(|(command, args)| {
+            let args = serde_json::from_str(&args)
+                .map_err(|e| RembrandtError::State(format!("invalid launch args for {}: {}", agent_id, e)))?;
+            Ok((command, args))
+        })
+        .transpose()
+    }
+
+    /// An agent's conversation in the order it was recorded; with a limit,
+    /// the most recent turns.
+    pub fn transcript(&self, agent_id: &str, limit: Option<usize>) -> Result<Vec<TranscriptEntry>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT id, agent_id, role, text, created_at
+            FROM transcripts
+            WHERE agent_id = ?1
+            ORDER BY id DESC
+            LIMIT ?2
+            "#,
+        )?;
+
+        let limit = limit.map_or(-1, |limit| limit as i64);
+        let rows = stmt.query_map(params![agent_id, limit], |row| {
+            let created_at: String = row.get(4)?;
+            Ok(TranscriptEntry {
+                id: row.get(0)?,
+                agent_id: row.get(1)?,
+                role: row.get::<_, String>(2)?.parse().map_err(to_sql_err)?,
+                text: row.get(3)?,
+                created_at: parse_rfc3339(&created_at).map_err(to_sql_err)?,
+            })
+        })?;
+
+        let mut out = Vec::new();
+        for row in rows {
+            out.push(row?);
+        }
+        out.reverse();
+        Ok(out)
+    }
+
+    fn list_merges(&self, status: Option<MergeStatus>) -> Result<Vec<MergeQueueEntry>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT id, agent_id, branch_name, checkout_path, status, attempts, detail, enqueued_at, updated_at
+            FROM merge_queue
+            WHERE ?1 IS NULL OR status = ?1
+            ORDER BY position
+            "#,
+        )?;
+
+        let rows = stmt.query_map([status.map(MergeStatus::as_str)], |row| {
+            let enqueued_at: String = row.get(7)?;
+            let updated_at: String = row.get(8)?;
+            Ok(MergeQueueEntry {
+                id: row.get(0)?,
+                agent_id: row.get(1)?,
+                branch_name: row.get(2)?,
+                checkout_path: PathBuf::from(row.get::<_, String>(3)?),
+                status: row.get::<_, String>(4)?.parse().map_err(to_sql_err)?,
+                attempts: row.get::<_, i64>(5)? as u32,
+                detail: row.get(6)?,
+                enqueued_at: parse_rfc3339(&enqueued_at).map_err(to_sql_err)?,
+                updated_at: parse_rfc3339(&updated_at).map_err(to_sql_err)?,
+            })
+        })?;
+
+        let mut out = Vec::new();
+        for row in rows {
+            out.push(row?);
+        }
+        Ok(out)
+    }
+}
+
+fn session_from_row(row: &Row<'_>) -> rusqlite::Result<SessionRecord> {
+    let created_at: String = row.get(9)?;
+    let updated_at: String = row.get(10)?;
+    Ok(SessionRecord {
+        agent_id: row.get(0)?,
+        runtime_kind: row.get(1)?,
+        runtime_session_id: row.get(2)?,
+        isolation_mode: isolation_mode_from_str(&row.get::<_, String>(3)?).map_err(to_sql_err)?,
+        branch_name: row.get(4)?,
+        checkout_path: PathBuf::from(row.get::<_, String>(5)?),
+        task_id: row.get(6)?,
+        status: SessionStatus::from_str(&row.get::<_, String>(7)?).map_err(to_sql_err)?,
+        model: row.get(8)?,
+        created_at: parse_rfc3339(&created_at).map_err(to_sql_err)?,
+        updated_at: parse_rfc3339(&updated_at).map_err(to_sql_err)?,
+        prompt: row.get(11)?,
+        updated_seq: row.get(12)?,
+        agent_type: row.get(13)?,
+    })
+}
+
+fn snapshot_from_row(row: &Row<'_>) -> rusqlite::Result<SnapshotRecord> {
+    let created_at: String = row.get(5)?;
+    Ok(SnapshotRecord {
+        id: row.get(0)?,
+        agent_id: row.get(1)?,
+        head_commit: row.get(2)?,
+        snapshot_commit: row.get(3)?,
+        label: row.get(4)?,
+        created_at: parse_rfc3339(&created_at).map_err(to_sql_err)?,
+    })
+}
+
+fn health_from_row(row: &Row<'_>) -> rusqlite::Result<HealthRecord> {
+    let checked_at: String = row.get(1)?;
+    let failing_since: Option<String> = row.get(4)?;
+    Ok(HealthRecord {
+        agent_id: row.get(0)?,
+        checked_at: parse_rfc3339(&checked_at).map_err(to_sql_err)?,
+        passed: row.get(2)?,
+        streak: row.get::<_, i64>(3)? as u32,
+        failing_since: failing_since
+            .map(|v| parse_rfc3339(&v))
+            .transpose()
+            .map_err(to_sql_err)?,
+        output: row.get(5)?,
+    })
+}
+
+fn isolation_mode_to_str(mode: IsolationMode) -> &'static str {
+    match mode {
+        IsolationMode::Branch => "branch",
+        IsolationMode::Worktree => "worktree",
+    }
+}
+
+fn isolation_mode_from_str(value: &str) -> Result<IsolationMode> {
+    match value {
+        "branch" => Ok(IsolationMode::Branch),
+        "worktree" => Ok(IsolationMode::Worktree),
+        other => Err(RembrandtError::State(format!(
+            "unknown isolation mode '{}'",
+            other
+        ))),
+    }
+}
+
+fn parse_rfc3339(value: &str) -> Result<DateTime<Utc>> {
+    chrono::DateTime::parse_from_rfc3339(value)
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|e| RembrandtError::State(format!("invalid timestamp '{}': {}", value, e)))
+}
+
+fn to_sql_err(err: RembrandtError) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(
+        0,
+        rusqlite::types::Type::Text,
+        Box::new(err),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::session_record;
+
+    fn record(agent_id: &str, status: SessionStatus, task_id: Option<&str>) -> SessionRecord {
+        SessionRecord {
+            task_id: task_id.map(str::to_string),
+            status,
+            ..session_record(agent_id)
+        }
+    }
+
+    #[test]
+    fn filters_pages_and_counts_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = StateStore::open(dir.path()).unwrap();
+        store.upsert_session(&record("a", SessionStatus::Active, Some("bd-1"))).unwrap();
+        store.upsert_session(&record("b", SessionStatus::Idle, Some("bd-1"))).unwrap();
+        store.upsert_session(&record("c", SessionStatus::Completed, Some("bd-2"))).unwrap();
+        store.upsert_session(&record("d", SessionStatus::Completed, None)).unwrap();
+
+        assert_eq!(store.list_active().unwrap().len(), 2);
+        assert_eq!(store.list_by_status(SessionStatus::Completed).unwrap().len(), 2);
+        assert_eq!(store.list_by_task("bd-1").unwrap().len(), 2);
+
+        let query = SessionQuery::default().task("bd-1").status(SessionStatus::Idle);
+        let found = store.query_sessions(&query).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].agent_id, "b");
+
+        let first = store.query_sessions(&SessionQuery::default().page(3, 0)).unwrap();
+        let rest = store.query_sessions(&SessionQuery::default().page(3, 3)).unwrap();
+        assert_eq!((first.len(), rest.len()), (3, 1));
+        assert_eq!(store.count_sessions(&SessionQuery::default().page(1, 0)).unwrap(), 4);
+
+        assert_eq!(
+            store.count_by_status().unwrap(),
+            vec![
+                (SessionStatus::Active, 1),
+                (SessionStatus::Completed, 2),
+                (SessionStatus::Idle, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn filters_sessions_by_agent_type_and_age() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = StateStore::open(dir.path()).unwrap();
+        let mut old = record("claude-code-1a2b", SessionStatus::Failed, None);
+        old.agent_type = Some("claude-code".to_string());
+        old.created_at = Utc::now() - chrono::Duration::days(3);
+        let mut recent = record("claude-code-3c4d", SessionStatus::Failed, None);
+        recent.agent_type = Some("claude-code".to_string());
+        let mut aider = record("aider-5e6f", SessionStatus::Failed, None);
+        aider.agent_type = Some("aider".to_string());
+        for session in [&old, &recent, &aider] {
+            store.upsert_session(session).unwrap();
+        }
+
+        let query = SessionQuery::default()
+            .status(SessionStatus::Failed)
+            .agent_type("claude-code")
+            .since(Utc::now() - chrono::Duration::days(1));
+        let found = store.query_sessions(&query).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].agent_id, "claude-code-3c4d");
+        assert_eq!(found[0].agent_type.as_deref(), Some("claude-code"));
+        assert_eq!(store.count_sessions(&SessionQuery::default().agent_type("claude-code")).unwrap(), 2);
+    }
+
+    #[test]
+    fn usage_accumulates_per_session() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = StateStore::open(dir.path()).unwrap();
+        let sample = |input, output, cost| UsageSample {
+            input_tokens: input,
+            output_tokens: output,
+            cost_usd: cost,
+            model: None,
+            source: "claude",
+        };
+        store.record_usage("a", &sample(100, 10, Some(0.25))).unwrap();
+        store.record_usage("a", &sample(50, 5, None)).unwrap();
+        store.record_usage("b", &sample(1, 1, Some(1.0))).unwrap();
+
+        let a = store.usage_for("a").unwrap();
+        assert_eq!((a.input_tokens, a.output_tokens, a.samples), (150, 15, 2));
+        assert_eq!(a.cost_usd, 0.25);
+        assert_eq!(store.usage_totals().unwrap().cost_usd, 1.25);
+        assert_eq!(store.usage_since(Utc::now() + chrono::Duration::hours(1)).unwrap().samples, 0);
+        assert_eq!(store.usage_for("nobody").unwrap(), UsageTotals::default());
+    }
+
+    #[test]
+    fn status_changes_land_on_the_timeline() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = StateStore::open(dir.path()).unwrap();
+        store.upsert_session(&record("a", SessionStatus::Active, None)).unwrap();
+        store.update_status("a", SessionStatus::Active).unwrap();
+        store.update_status("a", SessionStatus::Completed).unwrap();
+
+        let timeline = store.timeline("a").unwrap();
+        assert_eq!(timeline.len(), 1);
+        assert_eq!(timeline[0].kind, TimelineKind::StatusChanged);
+        assert_eq!(timeline[0].detail.as_deref(), Some("active -> completed"));
+    }
+
+    #[test]
+    fn finished_sessions_cannot_come_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = StateStore::open(dir.path()).unwrap();
+        store.upsert_session(&record("a", SessionStatus::Starting, None)).unwrap();
+        for status in [SessionStatus::Active, SessionStatus::Idle, SessionStatus::Blocked, SessionStatus::Completed] {
+            store.update_status("a", status).unwrap();
+        }
+
+        let err = store.update_status("a", SessionStatus::Active).unwrap_err().to_string();
+        assert!(err.contains("can't go from completed to active"), "{}", err);
+        store.update_status("a", SessionStatus::Completed).unwrap();
+        let current = store.get_session("a").unwrap().unwrap();
+        assert_eq!(current.status, SessionStatus::Completed);
+        let changes: Vec<_> = store.timeline("a").unwrap().into_iter().filter_map(|e| e.detail).collect();
+        assert_eq!(changes.last().map(String::as_str), Some("blocked -> completed"));
+
+        assert!(SessionStatus::Stopped.can_transition_to(SessionStatus::StoppedForBudget));
+        assert!(!SessionStatus::Stopped.can_transition_to(SessionStatus::Completed));
+        assert!(!SessionStatus::Failed.can_transition_to(SessionStatus::Starting));
+    }
+
+    #[test]
+    fn stale_status_updates_are_rejected_and_retried() {
+        let dir = tempfile::tempdir().unwrap();
+        let cli = StateStore::open(dir.path()).unwrap();
+        let daemon = StateStore::open(dir.path()).unwrap();
+        cli.upsert_session(&record("a", SessionStatus::Active, None)).unwrap();
+
+        // The CLI reads the session, then the daemon stops it
+        let seen = cli.get_session("a").unwrap().unwrap();
+        daemon.update_status("a", SessionStatus::Stopped).unwrap();
+        let err = cli.compare_and_set_status(&seen, SessionStatus::Completed).unwrap_err();
+        assert!(matches!(err, RembrandtError::Conflict(_)), "{}", err);
+
+        // Re-reading sees the stop, so the exit isn't recorded over it
+        let mut decided_on = Vec::new();
+        let updated = cli
+            .transition_status("a", |current| {
+                decided_on.push(current.status);
+                current.status.is_active().then_some(SessionStatus::Completed)
+            })
+            .unwrap();
+        assert!(updated.is_none());
+        assert_eq!(decided_on, [SessionStatus::Stopped]);
+        let current = cli.get_session("a").unwrap().unwrap();
+        assert_eq!(current.status, SessionStatus::Stopped);
+        assert_eq!(current.updated_seq, seen.updated_seq + 1);
+
+        let updated = cli
+            .transition_status("a", |_| Some(SessionStatus::StoppedForBudget))
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.updated_seq, current.updated_seq + 1);
+        assert!(cli.transition_status("missing", |_| Some(SessionStatus::Failed)).unwrap().is_none());
+
+        // Upserting a live session refreshes its details, but its status only
+        // moves where the status machine allows
+        cli.upsert_session(&record("b", SessionStatus::Active, None)).unwrap();
+        cli.upsert_session(&record("b", SessionStatus::Starting, Some("bd-9"))).unwrap();
+        let current = cli.get_session("b").unwrap().unwrap();
+        assert_eq!((current.status, current.task_id.as_deref()), (SessionStatus::Active, Some("bd-9")));
+        cli.upsert_session(&record("b", SessionStatus::Idle, None)).unwrap();
+        let changes: Vec<_> = cli.timeline("b").unwrap().into_iter().filter_map(|e| e.detail).collect();
+        assert_eq!(changes, ["active -> idle"]);
+        // An ended one is replaced by the fresh run
+        cli.update_status("b", SessionStatus::Completed).unwrap();
+        cli.upsert_session(&record("b", SessionStatus::Starting, None)).unwrap();
+        assert_eq!(cli.get_session("b").unwrap().unwrap().status, SessionStatus::Starting);
+    }
+
+    #[test]
+    fn restart_attempts_are_recorded_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = StateStore::open(dir.path()).unwrap();
+        store.record_restart("a", 1, "exited with code 1", Ok("ses-2")).unwrap();
+        store.record_restart("a", 2, "exited with code 1", Err("no such command")).unwrap();
+
+        let restarts = store.restarts("a").unwrap();
+        assert_eq!(restarts.len(), 2);
+        assert_eq!(restarts[0].attempt, 1);
+        assert_eq!(restarts[0].session_id.as_deref(), Some("ses-2"));
+        assert_eq!(restarts[1].error.as_deref(), Some("no such command"));
+        assert!(store.restarts("b").unwrap().is_empty());
+
+        let timeline = store.timeline("a").unwrap();
+        assert_eq!(timeline.len(), 2);
+        assert_eq!(timeline[0].kind, TimelineKind::Restarted);
+        assert_eq!(
+            timeline[1].detail.as_deref(),
+            Some("attempt 2 after it exited with code 1 failed: no such command")
+        );
+    }
 }
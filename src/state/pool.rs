@@ -0,0 +1,127 @@
+//! Shared access to state.db from async code.
+//!
+//! A `StateStore` owns one rusqlite connection, which can't be shared between
+//! threads and blocks while SQLite works. `StatePool` hands out connections
+//! to the same database instead: async callers use `call`, which runs on
+//! tokio's blocking pool, and synchronous ones check a connection out with
+//! `get`. Connections go back to the pool when dropped, and concurrent
+//! writers wait on each other through SQLite's busy timeout.
+
+use super::StateStore;
+use crate::{RembrandtError, Result};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Idle connections kept open for reuse
+const MAX_IDLE: usize = 4;
+
+/// Cloneable handle to state.db for concurrent callers
+#[derive(Clone)]
+pub struct StatePool {
+    db_path: PathBuf,
+    idle: Arc<Mutex<Vec<StateStore>>>,
+}
+
+impl StatePool {
+    /// Open (and migrate) `.rembrandt/state.db` in `repo_path`
+    pub fn open(repo_path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::from_store(StateStore::open(repo_path)?))
+    }
+
+    /// Pool more connections alongside an open store
+    pub fn from_store(store: StateStore) -> Self {
+        Self {
+            db_path: store.db_path().to_path_buf(),
+            idle: Arc::new(Mutex::new(vec![store])),
+        }
+    }
+
+    pub fn db_path(&self) -> &Path {
+        &self.db_path
+    }
+
+    /// Check out a connection, opening one if none is idle
+    ///
+    /// Blocks while SQLite works; from async code use `call`.
+    pub fn get(&self) -> Result<PooledStore> {
+        let idle = self.idle.lock().unwrap_or_else(PoisonError::into_inner).pop();
+        let store = match idle {
+            Some(store) => store,
+            None => StateStore::connect(self.db_path.clone())?,
+        };
+        Ok(PooledStore {
+            store: Some(store),
+            idle: self.idle.clone(),
+        })
+    }
+
+    /// Run `f` with a connection on the blocking thread pool
+    pub async fn call<T, F>(&self, f: F) -> Result<T>
+    where
+        F: FnOnce(&StateStore) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let pool = self.clone();
+        tokio::task::spawn_blocking(move || f(&*pool.get()?))
+            .await
+            .map_err(|e| RembrandtError::State(format!("state.db task failed: {}", e)))?
+    }
+}
+
+/// A connection checked out of a `StatePool`
+pub struct PooledStore {
+    store: Option<StateStore>,
+    idle: Arc<Mutex<Vec<StateStore>>>,
+}
+
+impl Deref for PooledStore {
+    type Target = StateStore;
+
+    fn deref(&self) -> &StateStore {
+        self.store.as_ref().expect("store is only taken on drop")
+    }
+}
+
+impl Drop for PooledStore {
+    fn drop(&mut self) {
+        let mut idle = self.idle.lock().unwrap_or_else(PoisonError::into_inner);
+        if idle.len() < MAX_IDLE
+            && let Some(store) = self.store.take()
+        {
+            idle.push(store);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::session_record;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_tasks_share_the_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let pool = StatePool::open(dir.path()).unwrap();
+
+        let tasks: Vec<_> = (0..16)
+            .map(|i| {
+                let pool = pool.clone();
+                tokio::spawn(async move {
+                    let agent_id = format!("agent-{}", i);
+                    pool.call(move |store| {
+                        store.upsert_session(&session_record(&agent_id))?;
+                        store.touch_heartbeat(&agent_id, Some("spawned"))
+                    })
+                    .await
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+
+        assert_eq!(pool.get().unwrap().list_sessions().unwrap().len(), 16);
+        assert!(pool.idle.lock().unwrap().len() <= MAX_IDLE);
+    }
+}
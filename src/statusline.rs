@@ -0,0 +1,115 @@
+//! One-line summary for shell prompts and tmux status lines.
+//!
+//! `rembrandt status --oneline` prints e.g. `3▶ 1⚠ 2✔`: agents running,
+//! agents needing a human, and agents that completed. It reads state.db
+//! only — no daemon, git or integration calls — so it's quick enough to run
+//! on every prompt.
+
+use crate::state::{SessionStatus, StateStore};
+use crate::Result;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::Path;
+
+/// Agent counts shown in the status line
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StatusLine {
+    pub active: usize,
+    /// Blocked, failed or over budget, or with an open attention item
+    pub attention: usize,
+    pub completed: usize,
+}
+
+impl StatusLine {
+    pub fn from_store(store: &StateStore) -> Result<Self> {
+        let flagged: HashSet<String> = store
+            .open_attention()?
+            .into_iter()
+            .filter_map(|item| item.agent_id)
+            .collect();
+        let mut line = Self::default();
+        for session in store.list_sessions()? {
+            let needs_human = matches!(
+                session.status,
+                SessionStatus::Blocked | SessionStatus::Failed | SessionStatus::StoppedForBudget
+            );
+            if needs_human || flagged.contains(&session.agent_id) {
+                line.attention += 1;
+            } else if session.status.is_active() {
+                line.active += 1;
+            } else if session.status == SessionStatus::Completed {
+                line.completed += 1;
+            }
+        }
+        Ok(line)
+    }
+
+    /// Counts for `repo_path`; all zero when rembrandt never ran there
+    pub fn for_repo(repo_path: &Path) -> Result<Self> {
+        if !repo_path.join(".rembrandt").join("state.db").exists() {
+            return Ok(Self::default());
+        }
+        Self::from_store(&StateStore::open(repo_path)?)
+    }
+}
+
+/// Nonzero counts only, so an idle repo adds nothing to a prompt
+impl fmt::Display for StatusLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts: Vec<String> = [(self.active, '▶'), (self.attention, '⚠'), (self.completed, '✔')]
+            .into_iter()
+            .filter(|(count, _)| *count > 0)
+            .map(|(count, symbol)| format!("{}{}", count, symbol))
+            .collect();
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::isolation::IsolationMode;
+    use crate::state::SessionRecord;
+    use crate::test_support::session_record;
+    use std::path::PathBuf;
+
+    #[test]
+    fn counts_active_attention_and_completed_agents() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(StatusLine::for_repo(dir.path()).unwrap().to_string(), "");
+        assert!(!dir.path().join(".rembrandt").exists());
+
+        let store = StateStore::open(dir.path()).unwrap();
+        let statuses = [
+            ("a", SessionStatus::Active),
+            ("b", SessionStatus::Idle),
+            ("c", SessionStatus::Active),
+            ("d", SessionStatus::Blocked),
+            ("e", SessionStatus::Completed),
+            ("f", SessionStatus::Stopped),
+        ];
+        for (agent_id, status) in statuses {
+            store
+                .upsert_session(&SessionRecord {
+                    runtime_kind: "daemon".to_string(),
+                    isolation_mode: IsolationMode::Worktree,
+                    checkout_path: PathBuf::from(agent_id),
+                    status,
+                    ..session_record(agent_id)
+                })
+                .unwrap();
+        }
+        store.raise_attention(Some("c"), "budget", "over budget").unwrap();
+
+        let line = StatusLine::for_repo(dir.path()).unwrap();
+        assert_eq!(
+            line,
+            StatusLine {
+                active: 2,
+                attention: 2,
+                completed: 1
+            }
+        );
+        assert_eq!(line.to_string(), "2▶ 2⚠ 1✔");
+    }
+}
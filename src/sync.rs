@@ -0,0 +1,419 @@
+//! Bring an agent's branch up to date with its base and retest it.
+//!
+//! Long-running agents drift from the base branch while others merge. `sync`
+//! fetches the base's upstream (when it has one), rebases the agent's branch
+//! onto it, or merges it in, inside the agent's worktree, then reruns the
+//! validation commands from `.rembrandt/config.toml` so the agent finds out
+//! early if the new base broke its work. The merge queue updates checkouts
+//! the same way.
+
+use crate::agent::AgentType;
+use crate::competition::{run_lint_checks, CompetitorSolution, LintCheck, LintSeverity, SolutionValidator};
+use crate::state::StateStore;
+use crate::worktree::WorktreeManager;
+use crate::{RembrandtError, Result};
+use git2::Repository;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Output lines kept per check in a sync report
+const CHECK_OUTPUT_LINES: usize = 20;
+
+/// How base changes get into the agent's branch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncMode {
+    /// Replay the agent's commits on top of the base
+    Rebase,
+    /// Merge the base into the agent's branch
+    Merge,
+}
+
+/// Outcome of updating a checkout with `update_checkout`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Update {
+    Clean,
+    /// Stopped on conflicts and was aborted, leaving the checkout as it was
+    Conflict(String),
+    /// Git wouldn't start, e.g. uncommitted changes in the checkout
+    Refused(String),
+}
+
+/// What happened to the agent's branch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncOutcome {
+    UpToDate,
+    /// Picked up this many new base commits
+    Updated { commits: usize },
+    /// The base conflicts with the agent's work; nothing was changed
+    Conflict(String),
+    /// Git refused to update the checkout; nothing was changed
+    Refused(String),
+}
+
+/// One validation command and how it went
+#[derive(Debug, Clone)]
+pub struct CheckRun {
+    pub name: String,
+    pub passed: bool,
+    /// Whether a failure counts against the branch (warning-only lint
+    /// checks don't)
+    pub blocking: bool,
+    /// Last lines of the command's output
+    pub output: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct SyncReport {
+    pub agent_id: String,
+    /// What the branch was synced with, e.g. `origin/main` after a fetch
+    pub onto: String,
+    pub fetched: bool,
+    pub outcome: SyncOutcome,
+    /// Empty if validation was skipped or the update failed
+    pub checks: Vec<CheckRun>,
+}
+
+impl SyncReport {
+    /// The branch is current and every check passed
+    pub fn is_ok(&self) -> bool {
+        matches!(self.outcome, SyncOutcome::UpToDate | SyncOutcome::Updated { .. })
+            && self.checks.iter().all(|c| c.passed || !c.blocking)
+    }
+}
+
+/// Worktree an agent works in, from state.db or the worktree list
+pub fn find_checkout(repo_path: &Path, store: &StateStore, agent_id: &str) -> Result<PathBuf> {
+    if let Some(session) = store.get_session(agent_id)? {
+        return Ok(session.checkout_path);
+    }
+    WorktreeManager::new(repo_path)?
+        .list_worktrees()?
+        .into_iter()
+        .find(|w| w.agent_id == agent_id)
+        .map(|w| w.path)
+        .ok_or_else(|| RembrandtError::SessionNotFound(agent_id.to_string()))
+}
+
+/// Sync `agent_id`'s checkout with `base` and rerun validation
+///
+/// `commands` are run with `sh -c` in the checkout; when empty, the cargo or
+/// npm checks competitions use are detected instead. Validation is skipped
+/// when `validate` is off or the update didn't go through.
+#[tracing::instrument(skip(store, commands))]
+#[allow(clippy::too_many_arguments)]
+pub async fn sync(
+    repo_path: &Path,
+    store: &StateStore,
+    agent_id: &str,
+    base: &str,
+    mode: SyncMode,
+    validate: bool,
+    commands: &[String],
+    lint: &[LintCheck],
+) -> Result<SyncReport> {
+    let checkout = find_checkout(repo_path, store, agent_id)?;
+    if !checkout.exists() {
+        return Err(RembrandtError::Worktree(format!(
+            "{}'s checkout {} no longer exists",
+            agent_id,
+            checkout.display()
+        )));
+    }
+
+    let upstream = fetch_upstream(repo_path, base)?;
+    let fetched = upstream.is_some();
+    let onto = upstream.unwrap_or_else(|| base.to_string());
+
+    let behind = commits_behind(&checkout, &onto)?;
+    let outcome = if behind == 0 {
+        SyncOutcome::UpToDate
+    } else {
+        match update_checkout(&checkout, &onto, mode)? {
+            Update::Clean => SyncOutcome::Updated { commits: behind },
+            Update::Conflict(message) => SyncOutcome::Conflict(message),
+            Update::Refused(message) => SyncOutcome::Refused(message),
+        }
+    };
+
+    let updated = matches!(outcome, SyncOutcome::UpToDate | SyncOutcome::Updated { .. });
+    let checks = if validate && updated {
+        run_checks(agent_id, &checkout, base, commands, lint).await?
+    } else {
+        Vec::new()
+    };
+
+    Ok(SyncReport {
+        agent_id: agent_id.to_string(),
+        onto,
+        fetched,
+        outcome,
+        checks,
+    })
+}
+
+/// Rebase the branch checked out at `checkout` onto `onto`, or merge it in
+pub(crate) fn update_checkout(checkout: &Path, onto: &str, mode: SyncMode) -> Result<Update> {
+    let (command, args): (&str, &[&str]) = match mode {
+        SyncMode::Rebase => ("rebase", &[]),
+        SyncMode::Merge => ("merge", &["--no-edit"]),
+    };
+    let output = Command::new("git")
+        .arg(command)
+        .args(args)
+        .arg(onto)
+        .current_dir(checkout)
+        .output()?;
+    if output.status.success() {
+        return Ok(Update::Clean);
+    }
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let message = stderr
+        .lines()
+        .chain(stdout.lines())
+        .find(|line| line.contains("CONFLICT") || line.starts_with("error"))
+        .or_else(|| stderr.lines().rfind(|line| !line.trim().is_empty()))
+        .unwrap_or("git failed")
+        .trim()
+        .to_string();
+
+    // Only a rebase or merge that got underway can be aborted
+    let aborted = Command::new("git")
+        .args([command, "--abort"])
+        .current_dir(checkout)
+        .output()?
+        .status
+        .success();
+    Ok(if aborted {
+        Update::Conflict(message)
+    } else {
+        Update::Refused(message)
+    })
+}
+
+/// Fetch `base`'s upstream, returning its ref (`origin/main`) if it has one
+fn fetch_upstream(repo_path: &Path, base: &str) -> Result<Option<String>> {
+    let repo = Repository::open(repo_path)?;
+    let Ok(upstream) = repo.find_branch(base, git2::BranchType::Local)?.upstream() else {
+        return Ok(None);
+    };
+    let Some(upstream_name) = upstream.name()?.map(str::to_string) else {
+        return Ok(None);
+    };
+    let remote = repo.branch_upstream_remote(&format!("refs/heads/{}", base))?;
+    let remote = remote.as_str().unwrap_or("origin");
+
+    let output = Command::new("git")
+        .args(["fetch", remote])
+        .current_dir(repo_path)
+        .output()?;
+    if !output.status.success() {
+        return Err(RembrandtError::Worktree(format!(
+            "git fetch {} failed: {}",
+            remote,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(Some(upstream_name))
+}
+
+/// Commits on `onto` that the checkout's branch doesn't have yet
+fn commits_behind(checkout: &Path, onto: &str) -> Result<usize> {
+    let output = Command::new("git")
+        .args(["rev-list", "--count", &format!("HEAD..{}", onto)])
+        .current_dir(checkout)
+        .output()?;
+    if !output.status.success() {
+        return Err(RembrandtError::Worktree(format!(
+            "Unknown base '{}': {}",
+            onto,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .map_err(|e| RembrandtError::Worktree(format!("Unexpected git rev-list output: {}", e)))
+}
+
+/// Run `commands` in `checkout`, or the detected cargo/npm checks if empty,
+/// then the `lint` checks
+pub(crate) async fn run_checks(
+    agent_id: &str,
+    checkout: &Path,
+    base: &str,
+    commands: &[String],
+    lint: &[LintCheck],
+) -> Result<Vec<CheckRun>> {
+    let lint_runs = run_lint_checks(lint, checkout).into_iter().map(|lint| CheckRun {
+        name: match lint.severity {
+            LintSeverity::Block => format!("lint: {}", lint.name),
+            LintSeverity::Warn => format!("lint: {} (warning)", lint.name),
+        },
+        passed: lint.passed,
+        blocking: lint.severity == LintSeverity::Block,
+        output: lint.output.unwrap_or_default(),
+    });
+    if commands.is_empty() {
+        let solution = CompetitorSolution {
+            agent_id: agent_id.to_string(),
+            agent_type: AgentType::Custom("sync".to_string()),
+            variant: Default::default(),
+            branch: String::new(),
+            worktree_path: checkout.to_path_buf(),
+            completed_at: None,
+            validation: None,
+            diff_stats: None,
+        };
+        let validation = SolutionValidator::new(base.to_string()).validate(&solution).await?;
+        return Ok(vec![
+            CheckRun {
+                name: "type check".to_string(),
+                passed: validation.type_check_passed,
+                blocking: true,
+                output: tail(validation.type_check_output.as_deref().unwrap_or("")),
+            },
+            CheckRun {
+                name: "tests".to_string(),
+                passed: validation.tests_passed,
+                blocking: true,
+                output: tail(validation.tests_output.as_deref().unwrap_or("")),
+            },
+        ]
+        .into_iter()
+        .chain(lint_runs)
+        .collect());
+    }
+
+    let mut checks = Vec::new();
+    for command in commands {
+        let output = Command::new("sh")
+            .args(["-c", command])
+            .current_dir(checkout)
+            .output()?;
+        let combined = format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        checks.push(CheckRun {
+            name: command.clone(),
+            passed: output.status.success(),
+            blocking: true,
+            output: tail(&combined),
+        });
+    }
+    checks.extend(lint_runs);
+    Ok(checks)
+}
+
+fn tail(output: &str) -> String {
+    let lines: Vec<&str> = output.trim_end().lines().collect();
+    lines[lines.len().saturating_sub(CHECK_OUTPUT_LINES)..].join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{git, init_repo};
+
+    #[tokio::test]
+    async fn rebases_onto_base_and_reruns_checks() {
+        let dir = tempfile::tempdir().unwrap();
+        init_repo(dir.path());
+
+        let worktree = WorktreeManager::new(dir.path())
+            .unwrap()
+            .create_worktree("agent", "main")
+            .unwrap();
+        std::fs::write(worktree.path.join("agent.txt"), "agent\n").unwrap();
+        git(&worktree.path, &["add", "agent.txt"]);
+        git(&worktree.path, &["commit", "-m", "agent work"]);
+        std::fs::write(dir.path().join("base.txt"), "base\n").unwrap();
+        git(dir.path(), &["add", "base.txt"]);
+        git(dir.path(), &["commit", "-m", "base moved"]);
+
+        let store = StateStore::open(dir.path()).unwrap();
+        let commands = vec!["test -f base.txt".to_string(), "exit 3".to_string()];
+        let lint = [LintCheck::new("todo", LintSeverity::Warn).with_command("! grep -r TODO .")];
+        let report = sync(dir.path(), &store, "agent", "main", SyncMode::Rebase, true, &commands, &lint)
+            .await
+            .unwrap();
+        assert_eq!(report.outcome, SyncOutcome::Updated { commits: 1 });
+        assert!(!report.fetched);
+        assert_eq!(report.onto, "main");
+        assert!(report.checks[0].passed);
+        assert!(!report.checks[1].passed);
+        assert_eq!(report.checks[2].name, "lint: todo (warning)");
+        assert!(report.checks[2].passed);
+        assert!(!report.is_ok());
+        assert!(worktree.path.join("agent.txt").exists());
+
+        let again = sync(dir.path(), &store, "agent", "main", SyncMode::Merge, false, &[], &[])
+            .await
+            .unwrap();
+        assert_eq!(again.outcome, SyncOutcome::UpToDate);
+        assert!(again.checks.is_empty() && again.is_ok());
+    }
+
+    #[tokio::test]
+    async fn conflicts_and_refusals_leave_the_checkout_as_it_was() {
+        let dir = tempfile::tempdir().unwrap();
+        init_repo(dir.path());
+        let store = StateStore::open(dir.path()).unwrap();
+        let sync_agent = |mode| sync(dir.path(), &store, "agent", "main", mode, true, &[], &[]);
+
+        let err = sync(dir.path(), &store, "nobody", "main", SyncMode::Rebase, false, &[], &[])
+            .await
+            .unwrap_err();
+        assert!(matches!(err, RembrandtError::SessionNotFound(_)), "{}", err);
+
+        let worktree = WorktreeManager::new(dir.path())
+            .unwrap()
+            .create_worktree("agent", "main")
+            .unwrap();
+        std::fs::write(worktree.path.join("shared.txt"), "agent\n").unwrap();
+        git(&worktree.path, &["add", "shared.txt"]);
+        git(&worktree.path, &["commit", "-m", "agent work"]);
+        let agent_head = git(&worktree.path, &["rev-parse", "HEAD"]);
+        std::fs::write(dir.path().join("shared.txt"), "base\n").unwrap();
+        git(dir.path(), &["add", "shared.txt"]);
+        git(dir.path(), &["commit", "-m", "base moved"]);
+
+        for mode in [SyncMode::Rebase, SyncMode::Merge] {
+            let report = sync_agent(mode).await.unwrap();
+            assert!(
+                matches!(&report.outcome, SyncOutcome::Conflict(message) if message.contains("CONFLICT")),
+                "{:?}",
+                report.outcome
+            );
+            assert!(report.checks.is_empty() && !report.is_ok());
+            assert_eq!(git(&worktree.path, &["rev-parse", "HEAD"]), agent_head);
+            assert_eq!(std::fs::read_to_string(worktree.path.join("shared.txt")).unwrap(), "agent\n");
+        }
+
+        // Uncommitted work stops git before it starts
+        std::fs::write(worktree.path.join("shared.txt"), "editing\n").unwrap();
+        let report = sync_agent(SyncMode::Rebase).await.unwrap();
+        assert!(matches!(report.outcome, SyncOutcome::Refused(_)), "{:?}", report.outcome);
+        assert_eq!(std::fs::read_to_string(worktree.path.join("shared.txt")).unwrap(), "editing\n");
+
+        let err = sync(dir.path(), &store, "agent", "no-such-base", SyncMode::Rebase, false, &[], &[])
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("no-such-base"), "{}", err);
+
+        // A base tracking a remote that can't be reached
+        git(dir.path(), &["remote", "add", "origin", &dir.path().join("gone.git").to_string_lossy()]);
+        git(dir.path(), &["config", "branch.main.remote", "origin"]);
+        git(dir.path(), &["config", "branch.main.merge", "refs/heads/main"]);
+        git(dir.path(), &["update-ref", "refs/remotes/origin/main", "HEAD"]);
+        let err = sync_agent(SyncMode::Rebase).await.unwrap_err();
+        assert!(err.to_string().contains("git fetch origin failed"), "{}", err);
+
+        std::fs::remove_dir_all(&worktree.path).unwrap();
+        let err = sync_agent(SyncMode::Rebase).await.unwrap_err();
+        assert!(err.to_string().contains("no longer exists"), "{}", err);
+    }
+}
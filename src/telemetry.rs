@@ -0,0 +1,275 @@
+//! Logging and trace export setup.
+//!
+//! Spans from the orchestrator (spawn, isolation prepare, runtime calls) and
+//! the core (worktree creation, validation, evaluation, merge) always go to
+//! the log. When `otlp_endpoint` is set in `.rembrandt/config.toml` and the
+//! binary is built with `--features otel`, they are also exported over
+//! OTLP/HTTP, e.g. to a local Jaeger or an OpenTelemetry collector.
+//!
+//! With `log_format = "json"` (or `--log-format json`) each event is one
+//! JSON object per line, carrying the fields of the spans it happened in
+//! next to its own, so a daemon event about a session has its `agent_id`
+//! and `session_id` at the top level for a log shipper to index.
+
+use crate::config::{AppConfig, LogFormat};
+use crate::Result;
+use serde_json::{Map, Value};
+use std::fmt;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::fmt::format::{JsonFields, Writer};
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields, FormattedFields};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+/// Flushes exported spans when dropped; keep it alive for the whole run.
+#[must_use]
+pub struct TelemetryGuard {
+    #[cfg(feature = "otel")]
+    provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otel")]
+        if let Some(provider) = self.provider.take()
+            && let Err(e) = provider.shutdown()
+        {
+            eprintln!("Failed to flush traces: {}", e);
+        }
+    }
+}
+
+/// Install the global subscriber: env-filtered log output plus, if
+/// configured, the OTLP exporter.
+pub fn init(config: &AppConfig) -> Result<TelemetryGuard> {
+    let json = config.log_format == LogFormat::Json;
+    let (filter, rejected) = log_filter(config);
+    let registry = tracing_subscriber::registry()
+        .with(filter)
+        .with((!json).then(tracing_subscriber::fmt::layer))
+        .with(json.then(|| {
+            tracing_subscriber::fmt::layer()
+                .fmt_fields(JsonFields::new())
+                .event_format(FlatJson)
+        }));
+
+    #[cfg(feature = "otel")]
+    {
+        use opentelemetry::trace::TracerProvider as _;
+
+        let provider = config
+            .otlp_endpoint
+            .as_deref()
+            .map(|endpoint| tracer_provider(endpoint, &config.otlp_service_name))
+            .transpose()?;
+        let layer = provider.as_ref().map(|provider| {
+            tracing_opentelemetry::layer().with_tracer(provider.tracer("rembrandt"))
+        });
+        registry.with(layer).init();
+        warn_rejected(&rejected);
+        Ok(TelemetryGuard { provider })
+    }
+
+    #[cfg(not(feature = "otel"))]
+    {
+        registry.init();
+        warn_rejected(&rejected);
+        if config.otlp_endpoint.is_some() {
+            tracing::warn!("otlp_endpoint is set, but this rembrandt was built without the `otel` feature");
+        }
+        Ok(TelemetryGuard {})
+    }
+}
+
+/// `RUST_LOG`, then `rembrandt=info`, then the `log_levels` overrides
+///
+/// An override that doesn't parse is left out rather than failing startup;
+/// it's returned with the reason, to be logged once logging is up.
+fn log_filter(config: &AppConfig) -> (EnvFilter, Vec<String>) {
+    let mut filter = EnvFilter::from_default_env();
+    let mut rejected = Vec::new();
+    let overrides = config
+        .log_levels
+        .iter()
+        .map(|(module, level)| format!("{}={}", module, level));
+    for directive in std::iter::once("rembrandt=info".to_string()).chain(overrides) {
+        match directive.parse() {
+            Ok(parsed) => filter = filter.add_directive(parsed),
+            Err(e) => rejected.push(format!("'{}': {}", directive, e)),
+        }
+    }
+    (filter, rejected)
+}
+
+/// Log the `log_levels` overrides `log_filter` left out
+fn warn_rejected(rejected: &[String]) {
+    for directive in rejected {
+        tracing::warn!("Ignoring [log_levels] entry {}", directive);
+    }
+}
+
+/// Writes an event as one line of JSON: time, level, target, the fields of
+/// its spans from the outermost in, then its own fields
+struct FlatJson;
+
+impl<S, N> FormatEvent<S, N> for FlatJson
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        let meta = event.metadata();
+        let mut object = Map::new();
+        object.insert(
+            "timestamp".to_string(),
+            chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true).into(),
+        );
+        object.insert("level".to_string(), meta.level().as_str().into());
+        object.insert("target".to_string(), meta.target().into());
+        if let Some(scope) = ctx.event_scope() {
+            let mut spans = Vec::new();
+            for span in scope.from_root() {
+                spans.push(Value::from(span.name()));
+                let extensions = span.extensions();
+                if let Some(fields) = extensions.get::<FormattedFields<N>>()
+                    && let Ok(Value::Object(fields)) = serde_json::from_str::<Value>(fields)
+                {
+                    object.extend(fields);
+                }
+            }
+            object.insert("spans".to_string(), Value::Array(spans));
+        }
+        event.record(&mut JsonVisitor(&mut object));
+        writeln!(writer, "{}", Value::Object(object))
+    }
+}
+
+/// Collects event fields into a JSON object
+struct JsonVisitor<'a>(&'a mut Map<String, Value>);
+
+impl Visit for JsonVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name().to_string(), format!("{:?}", value).into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+}
+
+#[cfg(feature = "otel")]
+fn tracer_provider(
+    endpoint: &str,
+    service_name: &str,
+) -> Result<opentelemetry_sdk::trace::SdkTracerProvider> {
+    use opentelemetry_otlp::WithExportConfig;
+
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(traces_url(endpoint))
+        .build()
+        .map_err(|e| crate::RembrandtError::Config(format!("otlp_endpoint {}: {}", endpoint, e)))?;
+    Ok(opentelemetry_sdk::trace::SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            opentelemetry_sdk::Resource::builder()
+                .with_service_name(service_name.to_string())
+                .build(),
+        )
+        .build())
+}
+
+/// The collector's trace URL: `http://host:4318` gets the standard
+/// `/v1/traces` path, a full URL is kept as is.
+pub fn traces_url(endpoint: &str) -> String {
+    let endpoint = endpoint.trim_end_matches('/');
+    if endpoint.ends_with("/v1/traces") {
+        endpoint.to_string()
+    } else {
+        format!("{}/v1/traces", endpoint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn appends_the_traces_path_once() {
+        assert_eq!(traces_url("http://localhost:4318"), "http://localhost:4318/v1/traces");
+        assert_eq!(traces_url("http://localhost:4318/"), "http://localhost:4318/v1/traces");
+        assert_eq!(
+            traces_url("https://otel.example.com/v1/traces"),
+            "https://otel.example.com/v1/traces"
+        );
+    }
+
+    #[test]
+    fn json_events_carry_their_span_fields() {
+        use std::sync::{Arc, Mutex};
+
+        let buffer = Arc::new(Mutex::new(Vec::new()));
+        let writer = buffer.clone();
+        let layer = tracing_subscriber::fmt::layer()
+            .fmt_fields(JsonFields::new())
+            .event_format(FlatJson)
+            .with_writer(move || BufferWriter(writer.clone()));
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            let _span = tracing::info_span!("stop", session_id = "s-1", agent_id = "claude-1a2b").entered();
+            tracing::warn!(step = "sigterm", attempt = 2, "Stop step failed: {}", "gone");
+        });
+
+        let line = String::from_utf8(buffer.lock().unwrap().clone()).unwrap();
+        let event: Value = serde_json::from_str(line.trim()).unwrap();
+        assert_eq!(event["level"], "WARN");
+        assert_eq!(event["message"], "Stop step failed: gone");
+        assert_eq!(event["session_id"], "s-1");
+        assert_eq!(event["agent_id"], "claude-1a2b");
+        assert_eq!(event["step"], "sigterm");
+        assert_eq!(event["attempt"], 2);
+        assert_eq!(event["spans"], serde_json::json!(["stop"]));
+
+        let mut config = AppConfig::default();
+        config.log_levels.insert("rembrandt::daemon".to_string(), "debug".to_string());
+        assert!(log_filter(&config).1.is_empty());
+        // A bad level is skipped; the rest still apply
+        config.log_levels.insert("rembrandt::state".to_string(), "loud".to_string());
+        let (filter, rejected) = log_filter(&config);
+        assert_eq!(rejected.len(), 1);
+        assert!(rejected[0].starts_with("'rembrandt::state=loud'"), "{:?}", rejected);
+        assert!(filter.to_string().contains("rembrandt::daemon=debug"), "{}", filter);
+    }
+
+    struct BufferWriter(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for BufferWriter {
+        fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(data);
+            Ok(data.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,106 @@
+//! Prompt templates (`.rembrandt/templates/<name>.md`).
+//!
+//! A spawn profile can name a template; it becomes the agent's first prompt
+//! with `{{task}}`, `{{prompt}}`, `{{agent}}` and `{{branch}}` filled in.
+//! Agents read one line of input as the whole prompt, so the rendered
+//! template is sent as a single line.
+
+use crate::{RembrandtError, Result};
+use std::path::{Path, PathBuf};
+
+/// Values substituted into a template
+#[derive(Debug, Clone, Default)]
+pub struct TemplateVars<'a> {
+    /// Beads task ID (`--task`)
+    pub task: Option<&'a str>,
+    /// Prompt given on the command line (`--prompt`)
+    pub prompt: Option<&'a str>,
+    pub agent: &'a str,
+    pub branch: &'a str,
+}
+
+/// Where template `name` lives in a repository
+pub fn path(repo_path: impl AsRef<Path>, name: &str) -> PathBuf {
+    repo_path
+        .as_ref()
+        .join(".rembrandt")
+        .join("templates")
+        .join(format!("{}.md", name))
+}
+
+/// Read template `name`
+pub fn load(repo_path: impl AsRef<Path>, name: &str) -> Result<String> {
+    let path = path(repo_path, name);
+    std::fs::read_to_string(&path)
+        .map_err(|e| RembrandtError::Config(format!("template {}: {}", path.display(), e)))
+}
+
+/// Fill in a template and flatten it to one line
+///
+/// Unknown placeholders are left as they are. A prompt the template has no
+/// `{{prompt}}` for is appended, so it isn't silently dropped.
+pub fn render(template: &str, vars: &TemplateVars<'_>) -> String {
+    let mut text = template.to_string();
+    for (key, value) in [
+        ("task", vars.task.unwrap_or("")),
+        ("prompt", vars.prompt.unwrap_or("")),
+        ("agent", vars.agent),
+        ("branch", vars.branch),
+    ] {
+        text = text.replace(&format!("{{{{{}}}}}", key), value);
+    }
+    if let Some(prompt) = vars.prompt
+        && !template.contains("{{prompt}}")
+    {
+        text.push('\n');
+        text.push_str(prompt);
+    }
+
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Load and render template `name`
+pub fn render_file(repo_path: impl AsRef<Path>, name: &str, vars: &TemplateVars<'_>) -> Result<String> {
+    Ok(render(&load(repo_path, name)?, vars))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_placeholders_and_flattens() {
+        let template = "# Bugfix\n\nFix {{task}} on {{branch}}.\n\n- Add a regression test\n{{unknown}}\n";
+        let vars = TemplateVars {
+            task: Some("br-7"),
+            prompt: Some("It crashes on empty input."),
+            agent: "claude-1234",
+            branch: "rembrandt/claude-1234",
+        };
+        assert_eq!(
+            render(template, &vars),
+            "# Bugfix Fix br-7 on rembrandt/claude-1234. - Add a regression test {{unknown}} \
+             It crashes on empty input."
+        );
+
+        let inline = render("Context: {{prompt}} ({{task}})", &TemplateVars { agent: "a", branch: "b", ..vars });
+        assert_eq!(inline, "Context: It crashes on empty input. (br-7)");
+    }
+
+    #[test]
+    fn loads_from_the_templates_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(load(dir.path(), "bugfix").is_err());
+        std::fs::create_dir_all(dir.path().join(".rembrandt/templates")).unwrap();
+        std::fs::write(path(dir.path(), "bugfix"), "Fix {{task}}").unwrap();
+        let vars = TemplateVars {
+            task: Some("br-7"),
+            ..Default::default()
+        };
+        assert_eq!(render_file(dir.path(), "bugfix", &vars).unwrap(), "Fix br-7");
+    }
+}
@@ -0,0 +1,99 @@
+//! Terminal backends: open agents' attach clients in multiplexer panes.
+//!
+//! A pane runs `rembrandt attach <agent>` against the daemon, so it shows the
+//! agent's PTY without owning it; closing a pane only detaches.
+
+pub mod tmux;
+pub mod zellij;
+
+pub use tmux::TmuxBackend;
+pub use zellij::ZellijBackend;
+
+use crate::config::TerminalBackendKind;
+use crate::runtime::ssh::shell_quote;
+use crate::{RembrandtError, Result};
+use std::path::Path;
+use std::process::Command;
+
+/// An agent and the command that attaches to it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AgentPane {
+    pub agent_id: String,
+    pub command: Vec<String>,
+}
+
+impl AgentPane {
+    /// A pane running `exe --repo <repo> attach <agent>`
+    pub fn attach(exe: &Path, repo: &Path, agent_id: &str, read_only: bool) -> Self {
+        let mut command = vec![
+            exe.display().to_string(),
+            "--repo".to_string(),
+            repo.display().to_string(),
+            "attach".to_string(),
+            agent_id.to_string(),
+        ];
+        if read_only {
+            command.push("--read-only".to_string());
+        }
+        Self { agent_id: agent_id.to_string(), command }
+    }
+
+    /// The command as one shell word list, as multiplexers expect it
+    pub fn shell_command(&self) -> String {
+        self.command.iter().map(|word| shell_quote(word)).collect::<Vec<_>>().join(" ")
+    }
+}
+
+pub trait TerminalBackend {
+    fn name(&self) -> &'static str;
+
+    /// Open `pane` in a new window named after its agent
+    fn open(&self, pane: &AgentPane) -> Result<()>;
+
+    /// Tile `panes` in a fresh session called `session` and switch to it;
+    /// an existing session of that name is only replaced when `replace` is
+    /// set, and is an error otherwise
+    fn layout(&self, session: &str, panes: &[AgentPane], replace: bool) -> Result<()>;
+}
+
+/// The backend configured as `terminal_backend`, if it is implemented
+pub fn backend(kind: TerminalBackendKind) -> Option<Box<dyn TerminalBackend>> {
+    match kind {
+        TerminalBackendKind::Tmux => Some(Box::new(TmuxBackend::new())),
+        TerminalBackendKind::Zellij => Some(Box::new(ZellijBackend::new())),
+        TerminalBackendKind::None | TerminalBackendKind::Cmux => None,
+    }
+}
+
+/// Run a multiplexer command, returning its trimmed stdout
+fn run(program: &str, args: &[String]) -> Result<String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| RembrandtError::Runtime(format!("could not run {}: {}", program, e)))?;
+    if !output.status.success() {
+        return Err(RembrandtError::Runtime(format!(
+            "{} {} failed: {}",
+            program,
+            args.first().map(String::as_str).unwrap_or_default(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Run a multiplexer command on this terminal, e.g. to attach to a session
+fn run_interactive(program: &str, args: &[String]) -> Result<()> {
+    let status = Command::new(program)
+        .args(args)
+        .status()
+        .map_err(|e| RembrandtError::Runtime(format!("could not run {}: {}", program, e)))?;
+    if !status.success() {
+        return Err(RembrandtError::Runtime(format!(
+            "{} {} failed",
+            program,
+            args.first().map(String::as_str).unwrap_or_default()
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,185 @@
+//! tmux backend: one window per agent, or one tiled session for all of them.
+
+use super::{run, run_interactive, AgentPane, TerminalBackend};
+use crate::{RembrandtError, Result};
+use std::process::{Command, Stdio};
+
+/// Session `open` uses when run outside tmux
+pub const DEFAULT_SESSION: &str = "rembrandt";
+
+/// Window holding the panes of `rembrandt layout`
+const LAYOUT_WINDOW: &str = "agents";
+
+/// Prints the new pane's ID from `new-window`/`new-session`/`split-window`
+const PRINT_PANE: [&str; 3] = ["-P", "-F", "#{pane_id}"];
+
+pub struct TmuxBackend {
+    program: String,
+}
+
+impl Default for TmuxBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TmuxBackend {
+    pub fn new() -> Self {
+        Self { program: "tmux".to_string() }
+    }
+
+    /// Whether this process runs inside a tmux client
+    pub fn inside_tmux() -> bool {
+        std::env::var_os("TMUX").is_some_and(|v| !v.is_empty())
+    }
+
+    fn has_session(&self, session: &str) -> bool {
+        Command::new(&self.program)
+            .args(["has-session", "-t", &exact(session)])
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    }
+
+    /// Run a command that creates a pane, then title the pane after its agent
+    fn create_pane(&self, args: Vec<String>, agent_id: &str) -> Result<String> {
+        let pane_id = run(&self.program, &args)?;
+        run(&self.program, &title_args(&pane_id, agent_id))?;
+        Ok(pane_id)
+    }
+}
+
+impl TerminalBackend for TmuxBackend {
+    fn name(&self) -> &'static str {
+        "tmux"
+    }
+
+    fn open(&self, pane: &AgentPane) -> Result<()> {
+        let args = if Self::inside_tmux() {
+            open_args(pane, None, true)
+        } else {
+            open_args(pane, Some(DEFAULT_SESSION), self.has_session(DEFAULT_SESSION))
+        };
+        self.create_pane(args, &pane.agent_id)?;
+        Ok(())
+    }
+
+    fn layout(&self, session: &str, panes: &[AgentPane], replace: bool) -> Result<()> {
+        if panes.is_empty() {
+            return Err(RembrandtError::Validation("no agents to lay out".to_string()));
+        }
+        // The name may be taken by a session that isn't ours to close
+        if self.has_session(session) {
+            if !replace {
+                return Err(RembrandtError::Validation(format!(
+                    "tmux session {} already exists; attach to it with `tmux attach -t {}`, \
+                     or pass --replace to rebuild it",
+                    session, session
+                )));
+            }
+            run(&self.program, &to_args(&["kill-session", "-t", &exact(session)]))?;
+        }
+        let mut agents = panes.iter().map(|pane| pane.agent_id.as_str());
+        for args in layout_args(session, panes) {
+            if creates_pane(&args) {
+                self.create_pane(args, agents.next().unwrap_or_default())?;
+            } else {
+                run(&self.program, &args)?;
+            }
+        }
+
+        let switch = if Self::inside_tmux() { "switch-client" } else { "attach-session" };
+        run_interactive(&self.program, &to_args(&[switch, "-t", &exact(session)]))
+    }
+}
+
+/// Arguments that open `pane` in a new window: in the current session when
+/// `session` is `None`, else in `session`, creating it if it doesn't exist
+fn open_args(pane: &AgentPane, session: Option<&str>, session_exists: bool) -> Vec<String> {
+    let command = pane.shell_command();
+    let mut args = match session {
+        Some(session) if !session_exists => {
+            to_args(&["new-session", "-d", "-s", session, "-n", &pane.agent_id])
+        }
+        Some(session) => to_args(&["new-window", "-d", "-t", &format!("{}:", exact(session)), "-n", &pane.agent_id]),
+        None => to_args(&["new-window", "-n", &pane.agent_id]),
+    };
+    args.extend(to_args(&PRINT_PANE));
+    args.push(command);
+    args
+}
+
+/// Arguments that tile `panes` in a new detached `session`
+///
+/// Pane-creating commands come in the same order as `panes`; the tiled
+/// layout is reapplied after every split so panes never run out of room.
+fn layout_args(session: &str, panes: &[AgentPane]) -> Vec<Vec<String>> {
+    let window = format!("{}:{}", exact(session), LAYOUT_WINDOW);
+    let mut commands = Vec::new();
+    for (index, pane) in panes.iter().enumerate() {
+        let mut args = if index == 0 {
+            to_args(&["new-session", "-d", "-s", session, "-n", LAYOUT_WINDOW])
+        } else {
+            to_args(&["split-window", "-t", &window])
+        };
+        args.extend(to_args(&PRINT_PANE));
+        args.push(pane.shell_command());
+        commands.push(args);
+        commands.push(to_args(&["select-layout", "-t", &window, "tiled"]));
+    }
+    // `set-option` reads a bare `=name` as a window, so target `=name:`
+    let session = format!("{}:", exact(session));
+    commands.push(to_args(&["set-option", "-t", &session, "pane-border-status", "top"]));
+    commands.push(to_args(&["set-option", "-t", &session, "pane-border-format", " #{pane_title} "]));
+    commands
+}
+
+fn creates_pane(args: &[String]) -> bool {
+    matches!(args.first().map(String::as_str), Some("new-session" | "new-window" | "split-window"))
+}
+
+fn title_args(pane_id: &str, agent_id: &str) -> Vec<String> {
+    to_args(&["select-pane", "-t", pane_id, "-T", agent_id])
+}
+
+/// A target matching the session name exactly rather than as a prefix
+fn exact(session: &str) -> String {
+    format!("={}", session)
+}
+
+fn to_args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|a| a.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn builds_named_windows_and_tiled_layouts() {
+        let exe = Path::new("/usr/bin/rembrandt");
+        let repo = Path::new("/src/my repo");
+        let a1 = AgentPane::attach(exe, repo, "a1", false);
+        let a2 = AgentPane::attach(exe, repo, "a2", true);
+        assert_eq!(a1.shell_command(), "/usr/bin/rembrandt --repo '/src/my repo' attach a1");
+        assert!(a2.shell_command().ends_with("attach a2 --read-only"));
+
+        let inside = open_args(&a1, None, true);
+        assert_eq!(inside[..3], to_args(&["new-window", "-n", "a1"]));
+        assert_eq!(inside.last(), Some(&a1.shell_command()));
+        let fresh = open_args(&a1, Some("rembrandt"), false);
+        assert_eq!(fresh[..4], to_args(&["new-session", "-d", "-s", "rembrandt"]));
+        let existing = open_args(&a1, Some("rembrandt"), true);
+        assert_eq!(existing[..4], to_args(&["new-window", "-d", "-t", "=rembrandt:"]));
+
+        let layout = layout_args("crew", &[a1.clone(), a2.clone()]);
+        assert_eq!(layout[0][0], "new-session");
+        assert_eq!(layout[0].last(), Some(&a1.shell_command()));
+        assert_eq!(layout[1], to_args(&["select-layout", "-t", "=crew:agents", "tiled"]));
+        assert_eq!(layout[2][..3], to_args(&["split-window", "-t", "=crew:agents"]));
+        assert_eq!(layout[2].last(), Some(&a2.shell_command()));
+        assert_eq!(layout.iter().filter(|args| creates_pane(args)).count(), 2);
+        assert_eq!(title_args("%3", "a2"), to_args(&["select-pane", "-t", "%3", "-T", "a2"]));
+    }
+}
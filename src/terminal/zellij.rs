@@ -0,0 +1,149 @@
+//! zellij backend: panes opened through `zellij action`, layouts as KDL files.
+//!
+//! Needs no zellij plugin; named panes run the attach command directly.
+
+use super::{run, run_interactive, AgentPane, TerminalBackend};
+use crate::{RembrandtError, Result};
+
+/// Session `open` uses when run outside zellij
+pub const DEFAULT_SESSION: &str = "rembrandt";
+
+pub struct ZellijBackend {
+    program: String,
+}
+
+impl Default for ZellijBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ZellijBackend {
+    pub fn new() -> Self {
+        Self { program: "zellij".to_string() }
+    }
+
+    /// Whether this process runs inside a zellij session
+    pub fn inside_zellij() -> bool {
+        std::env::var_os("ZELLIJ").is_some()
+    }
+
+    /// Whether a session called `session` exists, running or resurrectable;
+    /// `list-sessions` fails when there are none
+    fn has_session(&self, session: &str) -> bool {
+        run(&self.program, &to_args(&["list-sessions", "--short", "--no-formatting"]))
+            .is_ok_and(|listed| listed.lines().any(|name| name.trim() == session))
+    }
+}
+
+impl TerminalBackend for ZellijBackend {
+    fn name(&self) -> &'static str {
+        "zellij"
+    }
+
+    fn open(&self, pane: &AgentPane) -> Result<()> {
+        let session = if Self::inside_zellij() {
+            None
+        } else {
+            run(&self.program, &to_args(&["attach", "--create-background", DEFAULT_SESSION]))?;
+            Some(DEFAULT_SESSION)
+        };
+        run(&self.program, &new_pane_args(pane, session))?;
+        Ok(())
+    }
+
+    fn layout(&self, session: &str, panes: &[AgentPane], replace: bool) -> Result<()> {
+        if panes.is_empty() {
+            return Err(RembrandtError::Validation("no agents to lay out".to_string()));
+        }
+        let file = std::env::temp_dir().join(format!("rembrandt-{}.kdl", session));
+        std::fs::write(&file, layout_kdl(panes))?;
+        let file = file.display().to_string();
+
+        if Self::inside_zellij() {
+            // A client can't switch sessions from the CLI, so open a tab
+            return run(&self.program, &to_args(&["action", "new-tab", "--layout", &file, "--name", session]))
+                .map(|_| ());
+        }
+        // The name may be taken by a session that isn't ours to close
+        if self.has_session(session) {
+            if !replace {
+                return Err(RembrandtError::Validation(format!(
+                    "zellij session {} already exists; attach to it with `zellij attach {}`, \
+                     or pass --replace to rebuild it",
+                    session, session
+                )));
+            }
+            run(&self.program, &to_args(&["delete-session", "--force", session]))?;
+        }
+        run_interactive(&self.program, &to_args(&["--session", session, "--layout", &file]))
+    }
+}
+
+/// Arguments that open a pane named after its agent, in the current session
+/// when `session` is `None`
+fn new_pane_args(pane: &AgentPane, session: Option<&str>) -> Vec<String> {
+    let mut args = match session {
+        Some(session) => to_args(&["--session", session]),
+        None => Vec::new(),
+    };
+    args.extend(to_args(&["action", "new-pane", "--name", &pane.agent_id, "--close-on-exit", "--"]));
+    args.extend(pane.command.iter().cloned());
+    args
+}
+
+/// A layout tiling `panes` in a near-square grid: rows of panes side by side
+fn layout_kdl(panes: &[AgentPane]) -> String {
+    let columns = (1..).find(|c| c * c >= panes.len()).unwrap_or(1);
+    let mut kdl = String::from("layout {\n");
+    for row in panes.chunks(columns) {
+        kdl.push_str("    pane split_direction=\"vertical\" {\n");
+        for pane in row {
+            let (program, args) = pane.command.split_first().expect("attach command is never empty");
+            kdl.push_str(&format!(
+                "        pane name={} command={} close_on_exit=true {{\n            args {}\n        }}\n",
+                kdl_string(&pane.agent_id),
+                kdl_string(program),
+                args.iter().map(|a| kdl_string(a)).collect::<Vec<_>>().join(" ")
+            ));
+        }
+        kdl.push_str("    }\n");
+    }
+    kdl.push_str("}\n");
+    kdl
+}
+
+fn kdl_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn to_args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|a| a.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn builds_named_panes_and_grid_layouts() {
+        let exe = Path::new("/usr/bin/rembrandt");
+        let repo = Path::new("/src/my \"repo\"");
+        let panes: Vec<AgentPane> =
+            ["a1", "a2", "a3"].iter().map(|id| AgentPane::attach(exe, repo, id, false)).collect();
+
+        let inside = new_pane_args(&panes[0], None);
+        assert_eq!(inside[..5], to_args(&["action", "new-pane", "--name", "a1", "--close-on-exit"]));
+        assert_eq!(inside[6..], panes[0].command[..]);
+        let outside = new_pane_args(&panes[0], Some("rembrandt"));
+        assert_eq!(outside[..3], to_args(&["--session", "rembrandt", "action"]));
+
+        let kdl = layout_kdl(&panes);
+        // Three panes make a 2x2 grid with one empty cell: two rows
+        assert_eq!(kdl.matches("split_direction=\"vertical\"").count(), 2);
+        assert_eq!(kdl.matches("close_on_exit=true").count(), 3);
+        assert!(kdl.contains("pane name=\"a3\" command=\"/usr/bin/rembrandt\""), "{}", kdl);
+        assert!(kdl.contains(r#"args "--repo" "/src/my \"repo\"" "attach" "a3""#), "{}", kdl);
+    }
+}
@@ -0,0 +1,58 @@
+//! Helpers shared by the unit tests
+
+use crate::isolation::IsolationMode;
+use crate::state::{SessionRecord, SessionStatus};
+use chrono::Utc;
+use git2::{Repository, Signature};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Run `git` in `dir` as a throwaway committer, failing the test with its
+/// stderr if it does not succeed; returns stdout
+pub fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(["-c", "user.name=t", "-c", "user.email=t@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "git {:?} failed: {}", args, String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+/// A repository in `dir` with one empty commit on `main`, checked out, and
+/// a committer configured for tests that commit through `git`
+pub fn init_repo(dir: &Path) -> Repository {
+    let repo = Repository::init(dir).unwrap();
+    {
+        let sig = Signature::now("test", "test@example.com").unwrap();
+        let tree = repo.find_tree(repo.index().unwrap().write_tree().unwrap()).unwrap();
+        repo.commit(Some("refs/heads/main"), &sig, &sig, "init", &tree, &[]).unwrap();
+    }
+    repo.set_head("refs/heads/main").unwrap();
+    let mut config = repo.config().unwrap();
+    config.set_str("user.name", "test").unwrap();
+    config.set_str("user.email", "test@example.com").unwrap();
+    repo
+}
+
+/// An active branch-mode session for `agent_id`, created now
+pub fn session_record(agent_id: &str) -> SessionRecord {
+    let now = Utc::now();
+    SessionRecord {
+        agent_id: agent_id.to_string(),
+        runtime_kind: "pi".to_string(),
+        runtime_session_id: None,
+        isolation_mode: IsolationMode::Branch,
+        branch_name: format!("rembrandt/{}", agent_id),
+        checkout_path: PathBuf::from("."),
+        task_id: None,
+        status: SessionStatus::Active,
+        model: None,
+        created_at: now,
+        updated_at: now,
+        prompt: None,
+        agent_type: None,
+        updated_seq: 0,
+    }
+}
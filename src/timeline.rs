@@ -0,0 +1,315 @@
+//! Structured per-agent activity timeline.
+//!
+//! Alongside the raw PTY log, notable moments in a session (spawn, first
+//! output, tool runs, commits, nudges, steers, status changes) are stored in
+//! the `timeline` table. Status changes are recorded by
+//! `StateStore::update_status`; everything observed from output or git is
+//! recorded by `TimelineObserver`, which the CSI pass and the TUI feed. The
+//! observer also picks up follow-up markers (see `followups`).
+
+use crate::state::StateStore;
+use crate::{RembrandtError, Result};
+use chrono::{DateTime, Utc};
+use git2::Repository;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimelineKind {
+    Spawned,
+    FirstOutput,
+    ToolRun,
+    Commit,
+    Nudge,
+    Steer,
+    StatusChanged,
+    Restarted,
+    Message,
+}
+
+impl TimelineKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TimelineKind::Spawned => "spawned",
+            TimelineKind::FirstOutput => "first_output",
+            TimelineKind::ToolRun => "tool_run",
+            TimelineKind::Commit => "commit",
+            TimelineKind::Nudge => "nudge",
+            TimelineKind::Steer => "steer",
+            TimelineKind::StatusChanged => "status_changed",
+            TimelineKind::Restarted => "restarted",
+            TimelineKind::Message => "message",
+        }
+    }
+}
+
+impl std::str::FromStr for TimelineKind {
+    type Err = RembrandtError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "spawned" => Ok(TimelineKind::Spawned),
+            "first_output" => Ok(TimelineKind::FirstOutput),
+            "tool_run" => Ok(TimelineKind::ToolRun),
+            "commit" => Ok(TimelineKind::Commit),
+            "nudge" => Ok(TimelineKind::Nudge),
+            "steer" => Ok(TimelineKind::Steer),
+            "status_changed" => Ok(TimelineKind::StatusChanged),
+            "restarted" => Ok(TimelineKind::Restarted),
+            "message" => Ok(TimelineKind::Message),
+            other => Err(RembrandtError::State(format!(
+                "unknown timeline kind '{}'",
+                other
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for TimelineKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TimelineEntry {
+    pub id: i64,
+    pub agent_id: String,
+    pub kind: TimelineKind,
+    pub detail: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Tool invocations announced in agent output.
+///
+/// Claude Code prints each tool call as `⏺ Tool(args)` (older builds use
+/// `●`); the returned strings are the `Tool(args)` part.
+pub fn detect_tool_runs(output: &[u8]) -> Vec<String> {
+    let text = String::from_utf8_lossy(output);
+    text.lines()
+        .map(strip_ansi)
+        .filter_map(|line| {
+            let rest = line
+                .trim_start()
+                .strip_prefix('⏺')
+                .or_else(|| line.trim_start().strip_prefix('●'))?
+                .trim();
+            let open = rest.find('(')?;
+            let name = &rest[..open];
+            let looks_like_tool = !name.is_empty()
+                && name.chars().next().is_some_and(|c| c.is_ascii_uppercase())
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            looks_like_tool.then(|| rest.to_string())
+        })
+        .collect()
+}
+
+/// First line of `text`, cut to `max` characters, for timeline details.
+pub fn one_line(text: &str, max: usize) -> String {
+    let line = text.lines().next().unwrap_or("").trim();
+    if line.chars().count() > max {
+        let cut: String = line.chars().take(max.saturating_sub(1)).collect();
+        format!("{}…", cut)
+    } else {
+        line.to_string()
+    }
+}
+
+/// Drop CSI escape sequences (colors, cursor movement) from a line.
+pub(crate) fn strip_ansi(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            if chars.next() == Some('[') {
+                // Parameters and intermediates, up to the final byte
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+        } else if c != '\r' {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Tracks what has already been recorded for each agent, so repeated
+/// observations only add new entries.
+#[derive(Debug, Default)]
+pub struct TimelineObserver {
+    seen_output: HashSet<String>,
+    /// Output stream offset up to which tool runs have been scanned
+    scanned: HashMap<String, usize>,
+    commits: HashMap<String, HashSet<String>>,
+}
+
+impl TimelineObserver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Output stream offset the next `observe_output` for the agent needs
+    /// data from, if it has been observed before
+    pub fn output_cursor(&self, agent_id: &str) -> Option<usize> {
+        self.scanned.get(agent_id).copied()
+    }
+
+    /// Note the agent's total output size; records `first_output` once.
+    pub fn observe_output_size(&mut self, store: &StateStore, agent_id: &str, total: usize) -> Result<()> {
+        if total > 0 && self.seen_output.insert(agent_id.to_string()) {
+            let already = store
+                .timeline(agent_id)?
+                .iter()
+                .any(|e| e.kind == TimelineKind::FirstOutput);
+            if !already {
+                store.record_timeline(agent_id, TimelineKind::FirstOutput, None)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Scan buffered output starting at stream offset `start` for tool runs
+    /// not seen before.
+    pub fn observe_output(
+        &mut self,
+        store: &StateStore,
+        agent_id: &str,
+        output: &[u8],
+        start: usize,
+    ) -> Result<()> {
+        let end = start + output.len();
+        let scanned = self.scanned.entry(agent_id.to_string()).or_insert(start);
+        if end <= *scanned {
+            return Ok(());
+        }
+        // Only scan complete lines; a partial tail is picked up next time
+        let from = scanned.saturating_sub(start);
+        let unseen = &output[from..];
+        let Some(last_newline) = unseen.iter().rposition(|&b| b == b'\n') else {
+            return Ok(());
+        };
+        *scanned = start + from + last_newline + 1;
+
+        let complete = &unseen[..=last_newline];
+        for tool in detect_tool_runs(complete) {
+            store.record_timeline(agent_id, TimelineKind::ToolRun, Some(&tool))?;
+        }
+        crate::followups::record(store, agent_id, &String::from_utf8_lossy(complete), "output")?;
+        Ok(())
+    }
+
+    /// Record commits on `branch` made since `since` that aren't on the
+    /// timeline yet.
+    pub fn observe_commits(
+        &mut self,
+        store: &StateStore,
+        agent_id: &str,
+        repo_path: &Path,
+        branch: &str,
+        since: DateTime<Utc>,
+    ) -> Result<()> {
+        let known = match self.commits.entry(agent_id.to_string()) {
+            std::collections::hash_map::Entry::Occupied(e) => e.into_mut(),
+            std::collections::hash_map::Entry::Vacant(e) => {
+                let recorded = store
+                    .timeline(agent_id)?
+                    .into_iter()
+                    .filter(|e| e.kind == TimelineKind::Commit)
+                    .filter_map(|e| e.detail?.split_whitespace().next().map(str::to_string))
+                    .collect();
+                e.insert(recorded)
+            }
+        };
+
+        let repo = Repository::open(repo_path)?;
+        let Ok(reference) = repo.find_branch(branch, git2::BranchType::Local) else {
+            return Ok(());
+        };
+        let Some(tip) = reference.get().target() else {
+            return Ok(());
+        };
+
+        let mut walk = repo.revwalk()?;
+        walk.push(tip)?;
+        walk.set_sorting(git2::Sort::TIME)?;
+        let mut new_commits = Vec::new();
+        for oid in walk {
+            let commit = repo.find_commit(oid?)?;
+            if commit.time().seconds() < since.timestamp() {
+                break;
+            }
+            let short = oid_short(&commit.id());
+            if known.contains(&short) {
+                break;
+            }
+            new_commits.push((
+                short,
+                commit.summary().unwrap_or("").to_string(),
+                commit.message().unwrap_or("").to_string(),
+            ));
+        }
+
+        // Oldest first, so the timeline reads in order
+        for (short, summary, message) in new_commits.into_iter().rev() {
+            store.record_timeline(
+                agent_id,
+                TimelineKind::Commit,
+                Some(&format!("{} {}", short, summary)),
+            )?;
+            crate::followups::record(store, agent_id, &message, &format!("commit {}", short))?;
+            known.insert(short);
+        }
+        Ok(())
+    }
+}
+
+fn oid_short(oid: &git2::Oid) -> String {
+    oid.to_string()[..7].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_claude_tool_calls() {
+        let output = "Let me check.\r\n\u{1b}[32m⏺\u{1b}[0m Bash(cargo test --workspace)\r\n  ⎿  ok\r\n⏺ I fixed it (mostly).\r\n● Read(src/main.rs)\n";
+        assert_eq!(
+            detect_tool_runs(output.as_bytes()),
+            vec!["Bash(cargo test --workspace)", "Read(src/main.rs)"]
+        );
+    }
+
+    #[test]
+    fn observer_records_each_event_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = StateStore::open(dir.path()).unwrap();
+        let mut observer = TimelineObserver::new();
+
+        observer.observe_output_size(&store, "a", 10).unwrap();
+        observer.observe_output_size(&store, "a", 20).unwrap();
+        observer.observe_output(&store, "a", b"\xe2\x8f\xba Bash(ls)\npart", 0).unwrap();
+        // Same buffer again plus the rest of the partial line
+        observer
+            .observe_output(&store, "a", b"\xe2\x8f\xba Bash(ls)\npart\n\xe2\x8f\xba Edit(x)\n", 0)
+            .unwrap();
+
+        let kinds: Vec<(TimelineKind, Option<String>)> = store
+            .timeline("a")
+            .unwrap()
+            .into_iter()
+            .map(|e| (e.kind, e.detail))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (TimelineKind::FirstOutput, None),
+                (TimelineKind::ToolRun, Some("Bash(ls)".to_string())),
+                (TimelineKind::ToolRun, Some("Edit(x)".to_string())),
+            ]
+        );
+    }
+}
@@ -0,0 +1,218 @@
+//! Commit trailers recording which agent made a commit.
+//!
+//! `rembrandt init --hooks` installs a `prepare-commit-msg` hook in the
+//! repository. Hooks are shared by all of a repository's worktrees, so it
+//! covers every agent, including ones spawned later. The hook runs
+//! `rembrandt commit-trailers`, which adds
+//!
+//! ```text
+//! Rembrandt-Agent: claude-1a2b
+//! Rembrandt-Task: bd-12
+//! Rembrandt-Session: 7f3c...
+//! ```
+//!
+//! to commits made by an agent: one whose environment has
+//! `REMBRANDT_AGENT_ID`, or any commit on a `rembrandt/<agent_id>` branch.
+//! The task and session come from the environment or the agent's session in
+//! state.db; either is left out when unknown. Other commits are untouched.
+//!
+//! A `prepare-commit-msg` hook that was already there is kept as
+//! `prepare-commit-msg.pre-rembrandt` and still runs first.
+
+use crate::state::StateStore;
+use crate::{RembrandtError, Result};
+use git2::Repository;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub const AGENT_TRAILER: &str = "Rembrandt-Agent";
+pub const TASK_TRAILER: &str = "Rembrandt-Task";
+pub const SESSION_TRAILER: &str = "Rembrandt-Session";
+
+/// Marks a hook as ours, so reinstalling replaces it instead of chaining it
+const HOOK_MARKER: &str = "# Installed by `rembrandt init --hooks`";
+
+/// Name a replaced hook is kept under
+const CHAINED_HOOK: &str = "prepare-commit-msg.pre-rembrandt";
+
+/// Provenance of a commit
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Provenance {
+    pub agent_id: String,
+    pub task_id: Option<String>,
+    pub session_id: Option<String>,
+}
+
+impl Provenance {
+    /// As `(key, value)` trailers, the agent first
+    pub fn trailers(&self) -> Vec<(&'static str, &str)> {
+        let mut trailers = vec![(AGENT_TRAILER, self.agent_id.as_str())];
+        if let Some(task_id) = &self.task_id {
+            trailers.push((TASK_TRAILER, task_id));
+        }
+        if let Some(session_id) = &self.session_id {
+            trailers.push((SESSION_TRAILER, session_id));
+        }
+        trailers
+    }
+
+    /// Read back from a commit message's trailers; None without an agent
+    pub fn from_message(message: &str) -> Option<Self> {
+        let trailers = git2::message_trailers_strs(message).ok()?;
+        let mut provenance = Provenance::default();
+        for (key, value) in trailers.iter() {
+            match key {
+                AGENT_TRAILER => provenance.agent_id = value.to_string(),
+                TASK_TRAILER => provenance.task_id = Some(value.to_string()),
+                SESSION_TRAILER => provenance.session_id = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        (!provenance.agent_id.is_empty()).then_some(provenance)
+    }
+}
+
+/// Install the `prepare-commit-msg` hook for `repo_path` and every worktree
+/// of it; returns the hook's path
+pub fn install_hook(repo_path: &Path) -> Result<PathBuf> {
+    let root = crate::worktree::main_repo_root(repo_path)
+        .ok_or_else(|| RembrandtError::Worktree(format!("{} is not in a git repository", repo_path.display())))?;
+    let repo = Repository::open(&root)?;
+    let hooks_dir = match repo.config()?.get_path("core.hooksPath") {
+        Ok(dir) => root.join(dir),
+        Err(_) => repo.path().join("hooks"),
+    };
+    std::fs::create_dir_all(&hooks_dir)?;
+
+    let hook = hooks_dir.join("prepare-commit-msg");
+    if let Ok(existing) = std::fs::read_to_string(&hook)
+        && !existing.contains(HOOK_MARKER)
+    {
+        std::fs::rename(&hook, hooks_dir.join(CHAINED_HOOK))?;
+    }
+    std::fs::write(&hook, hook_script())?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(hook)
+}
+
+/// The hook: run a chained hook, then `rembrandt commit-trailers`
+///
+/// It calls the rembrandt that installed it if that is still there, else
+/// whichever is on PATH, and does nothing when there is none. Whatever
+/// rembrandt does, the commit goes ahead.
+fn hook_script() -> String {
+    let installed = std::env::current_exe()
+        .map(|exe| exe.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    format!(
+        r#"#!/bin/sh
+{marker}
+# Adds Rembrandt-Agent, Rembrandt-Task and Rembrandt-Session trailers to
+# commits made by agents.
+chained="$(dirname "$0")/{chained}"
+if [ -x "$chained" ]; then
+  "$chained" "$@" || exit $?
+fi
+rembrandt='{installed}'
+[ -x "$rembrandt" ] || rembrandt=$(command -v rembrandt) || exit 0
+"$rembrandt" commit-trailers "$1" || true
+"#,
+        marker = HOOK_MARKER,
+        chained = CHAINED_HOOK,
+        installed = installed.replace('\'', r"'\''"),
+    )
+}
+
+/// Who is committing in `checkout`: the agent from `REMBRANDT_AGENT_ID` or
+/// the checked-out `rembrandt/<agent_id>` branch, with its task and session
+/// from the environment or state.db
+pub fn provenance(checkout: &Path, env: impl Fn(&str) -> Option<String>) -> Result<Option<Provenance>> {
+    let repo = Repository::discover(checkout)?;
+    let branch_agent = repo
+        .head()
+        .ok()
+        .and_then(|head| head.shorthand().and_then(|b| b.strip_prefix("rembrandt/")).map(str::to_string));
+    let Some(agent_id) = env("REMBRANDT_AGENT_ID").filter(|id| !id.is_empty()).or(branch_agent) else {
+        return Ok(None);
+    };
+
+    let record = crate::worktree::main_repo_root(checkout)
+        .filter(|root| root.join(".rembrandt").join("state.db").exists())
+        .map(StateStore::open)
+        .transpose()?
+        .map(|store| store.get_session(&agent_id))
+        .transpose()?
+        .flatten();
+    let task_id = env("REMBRANDT_TASK_ID")
+        .filter(|id| !id.is_empty())
+        .or_else(|| record.as_ref().and_then(|r| r.task_id.clone()));
+    let session_id = record.and_then(|r| r.runtime_session_id);
+    Ok(Some(Provenance {
+        agent_id,
+        task_id,
+        session_id,
+    }))
+}
+
+/// Add `provenance`'s trailers to the commit message being written at
+/// `message_file`, once
+pub fn annotate(checkout: &Path, message_file: &Path, provenance: &Provenance) -> Result<()> {
+    let mut command = Command::new("git");
+    command
+        .current_dir(checkout)
+        .args(["interpret-trailers", "--in-place", "--if-exists", "addIfDifferent"]);
+    for (key, value) in provenance.trailers() {
+        command.arg("--trailer").arg(format!("{}: {}", key, value));
+    }
+    let output = command.arg(message_file).output()?;
+    if !output.status.success() {
+        return Err(RembrandtError::Worktree(format!(
+            "git interpret-trailers failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::git;
+
+    #[test]
+    fn agent_commits_get_trailers() {
+        let dir = tempfile::tempdir().unwrap();
+        git(dir.path(), &["init", "-q", "-b", "main"]);
+        git(dir.path(), &["commit", "-q", "--allow-empty", "-m", "init"]);
+        let no_env = |_: &str| None;
+
+        // A human on main isn't an agent
+        assert_eq!(provenance(dir.path(), no_env).unwrap(), None);
+
+        git(dir.path(), &["checkout", "-q", "-b", "rembrandt/claude-1a2b"]);
+        let env = |key: &str| (key == "REMBRANDT_TASK_ID").then(|| "bd-7".to_string());
+        let found = provenance(dir.path(), env).unwrap().unwrap();
+        assert_eq!(found.agent_id, "claude-1a2b");
+        assert_eq!(found.task_id.as_deref(), Some("bd-7"));
+
+        let message = dir.path().join("MSG");
+        std::fs::write(&message, "Fix parser\n\nLonger body.\n").unwrap();
+        annotate(dir.path(), &message, &found).unwrap();
+        annotate(dir.path(), &message, &found).unwrap();
+        let written = std::fs::read_to_string(&message).unwrap();
+        assert_eq!(written.matches("Rembrandt-Agent: claude-1a2b").count(), 1);
+        assert!(written.contains("Rembrandt-Task: bd-7"));
+        assert_eq!(Provenance::from_message(&written), Some(found));
+
+        let hook = install_hook(dir.path()).unwrap();
+        // A failing rembrandt must never fail the commit
+        assert!(std::fs::read_to_string(&hook).unwrap().contains("commit-trailers \"$1\" || true"));
+        // Reinstalling replaces our own hook rather than chaining it
+        install_hook(dir.path()).unwrap();
+        assert!(!hook.with_file_name(CHAINED_HOOK).exists());
+    }
+}
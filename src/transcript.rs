@@ -0,0 +1,414 @@
+//! Conversation transcripts of Claude Code sessions.
+//!
+//! Claude Code writes each conversation to a JSONL file under
+//! `~/.claude/projects/<checkout path with non-alphanumerics as '-'>/`
+//! (`$CLAUDE_CONFIG_DIR` moves `~/.claude`), and prints the same events on
+//! stdout when run with `--output-format stream-json`. Either way, every
+//! line is one `user` or `assistant` message. `TranscriptReader` picks the
+//! prompts, replies, tool calls and tool results out of them and stores
+//! them in the `transcripts` table of state.db, so `rembrandt transcript`
+//! and the dashboard can show a readable conversation instead of raw PTY
+//! bytes. Entries are keyed by message UUID, so reading a file or stream
+//! again never duplicates them.
+
+use crate::state::StateStore;
+use crate::timeline::{one_line, strip_ansi};
+use crate::{RembrandtError, Result};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Longest tool result kept; the rest is in the agent's own log
+const MAX_TOOL_RESULT: usize = 2000;
+
+/// Longest summary of a tool call's input
+const MAX_TOOL_INPUT: usize = 200;
+
+/// Who said something in a conversation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TranscriptRole {
+    User,
+    Assistant,
+    /// A tool the assistant called, with a summary of its input
+    ToolUse,
+    /// What a tool call returned
+    ToolResult,
+}
+
+impl TranscriptRole {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TranscriptRole::User => "user",
+            TranscriptRole::Assistant => "assistant",
+            TranscriptRole::ToolUse => "tool_use",
+            TranscriptRole::ToolResult => "tool_result",
+        }
+    }
+}
+
+impl std::str::FromStr for TranscriptRole {
+    type Err = RembrandtError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "user" => Ok(TranscriptRole::User),
+            "assistant" => Ok(TranscriptRole::Assistant),
+            "tool_use" => Ok(TranscriptRole::ToolUse),
+            "tool_result" => Ok(TranscriptRole::ToolResult),
+            other => Err(RembrandtError::State(format!("unknown transcript role '{}'", other))),
+        }
+    }
+}
+
+impl std::fmt::Display for TranscriptRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+/// One turn of a conversation, as parsed
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranscriptTurn {
+    /// Identifies the turn across re-reads: message UUID and block index
+    pub key: String,
+    pub role: TranscriptRole,
+    pub text: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// One stored turn
+#[derive(Debug, Clone, Serialize)]
+pub struct TranscriptEntry {
+    pub id: i64,
+    pub agent_id: String,
+    pub role: TranscriptRole,
+    pub text: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Turns in one line of a session file or stream-json output
+///
+/// Lines that aren't user or assistant messages (system events, results,
+/// summaries, anything that isn't JSON) yield nothing, as do meta messages
+/// Claude Code adds itself and the assistant's thinking.
+pub fn parse_line(line: &str) -> Vec<TranscriptTurn> {
+    let line = line.trim();
+    if !line.starts_with('{') {
+        return Vec::new();
+    }
+    let Ok(event) = serde_json::from_str::<Value>(line) else {
+        return Vec::new();
+    };
+    let is_user = match event.get("type").and_then(Value::as_str) {
+        Some("user") => true,
+        Some("assistant") => false,
+        _ => return Vec::new(),
+    };
+    if event.get("isMeta").and_then(Value::as_bool) == Some(true) {
+        return Vec::new();
+    }
+    let Some(content) = event.get("message").and_then(|m| m.get("content")) else {
+        return Vec::new();
+    };
+    let id = event
+        .get("uuid")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("{:016x}", fnv1a(line.as_bytes())));
+    let created_at = event
+        .get("timestamp")
+        .and_then(Value::as_str)
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .map_or_else(Utc::now, |t| t.with_timezone(&Utc));
+
+    let text_role = if is_user {
+        TranscriptRole::User
+    } else {
+        TranscriptRole::Assistant
+    };
+    let blocks: Vec<(TranscriptRole, String)> = match content {
+        Value::String(text) => vec![(text_role, text.clone())],
+        Value::Array(blocks) => blocks
+            .iter()
+            .filter_map(|block| match block.get("type")?.as_str()? {
+                "text" => Some((text_role, block.get("text")?.as_str()?.to_string())),
+                "tool_use" => Some((TranscriptRole::ToolUse, tool_call(block))),
+                "tool_result" => Some((TranscriptRole::ToolResult, tool_result(block))),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    blocks
+        .into_iter()
+        .filter(|(_, text)| !text.trim().is_empty())
+        .enumerate()
+        .map(|(i, (role, text))| TranscriptTurn {
+            key: format!("{}:{}", id, i),
+            role,
+            text: text.trim().to_string(),
+            created_at,
+        })
+        .collect()
+}
+
+/// `Bash: cargo test`, or the tool's name and its input as one line
+fn tool_call(block: &Value) -> String {
+    let name = block.get("name").and_then(Value::as_str).unwrap_or("tool");
+    let input = block.get("input").cloned().unwrap_or(Value::Null);
+    let summary = ["command", "file_path", "pattern", "url", "description"]
+        .iter()
+        .find_map(|key| input.get(key).and_then(Value::as_str).map(str::to_string))
+        .unwrap_or_else(|| input.to_string());
+    format!("{}: {}", name, one_line(&summary, MAX_TOOL_INPUT))
+}
+
+fn tool_result(block: &Value) -> String {
+    let text = match block.get("content") {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|part| part.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    };
+    let mut text = if text.chars().count() > MAX_TOOL_RESULT {
+        let cut: String = text.chars().take(MAX_TOOL_RESULT).collect();
+        format!("{}…", cut)
+    } else {
+        text
+    };
+    if block.get("is_error").and_then(Value::as_bool) == Some(true) {
+        text.insert_str(0, "error: ");
+    }
+    text
+}
+
+/// 64-bit FNV-1a, to key messages that carry no UUID
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Claude Code's own directory: `$CLAUDE_CONFIG_DIR`, else `~/.claude`
+pub fn claude_dir() -> Option<PathBuf> {
+    match std::env::var_os("CLAUDE_CONFIG_DIR") {
+        Some(dir) => Some(PathBuf::from(dir)),
+        None => Some(PathBuf::from(std::env::var_os("HOME")?).join(".claude")),
+    }
+}
+
+/// Where Claude Code under `claude_dir` keeps the session files of a checkout
+pub fn project_dir(claude_dir: &Path, checkout: &Path) -> PathBuf {
+    let checkout = checkout.canonicalize().unwrap_or_else(|_| checkout.to_path_buf());
+    let encoded: String = checkout
+        .to_string_lossy()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    claude_dir.join("projects").join(encoded)
+}
+
+/// Reads transcripts into state.db, remembering how far it got so repeated
+/// calls only parse what was appended since
+#[derive(Debug)]
+pub struct TranscriptReader {
+    claude_dir: Option<PathBuf>,
+    /// Bytes of each session file already read
+    offsets: HashMap<PathBuf, u64>,
+    /// Incomplete last line of each agent's stream-json output
+    partial: HashMap<String, Vec<u8>>,
+}
+
+impl Default for TranscriptReader {
+    fn default() -> Self {
+        Self {
+            claude_dir: claude_dir(),
+            offsets: HashMap::new(),
+            partial: HashMap::new(),
+        }
+    }
+}
+
+impl TranscriptReader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Look for session files under `dir` instead of Claude Code's default
+    pub fn with_claude_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.claude_dir = Some(dir.into());
+        self
+    }
+
+    /// Store new turns from the Claude Code session files of `checkout`;
+    /// returns how many were added
+    pub fn read_session_files(&mut self, store: &StateStore, agent_id: &str, checkout: &Path) -> Result<usize> {
+        let Some(claude_dir) = &self.claude_dir else {
+            return Ok(0);
+        };
+        let Ok(entries) = std::fs::read_dir(project_dir(claude_dir, checkout)) else {
+            return Ok(0);
+        };
+        let mut files: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+            .collect();
+        files.sort();
+
+        let mut added = 0;
+        for path in files {
+            let offset = self.offsets.get(&path).copied().unwrap_or(0);
+            let mut file = std::fs::File::open(&path)?;
+            if file.metadata()?.len() <= offset {
+                continue;
+            }
+            file.seek(SeekFrom::Start(offset))?;
+            let mut bytes = Vec::new();
+            file.read_to_end(&mut bytes)?;
+            // Leave a line that is still being written for next time
+            let Some(end) = bytes.iter().rposition(|&b| b == b'\n') else {
+                continue;
+            };
+            let turns: Vec<TranscriptTurn> = String::from_utf8_lossy(&bytes[..=end])
+                .lines()
+                .flat_map(parse_line)
+                .collect();
+            added += store.record_transcript(agent_id, &turns)?;
+            self.offsets.insert(path, offset + end as u64 + 1);
+        }
+        Ok(added)
+    }
+
+    /// Store new turns from a chunk of an agent's stream-json output;
+    /// returns how many were added
+    pub fn read_output(&mut self, store: &StateStore, agent_id: &str, chunk: &[u8]) -> Result<usize> {
+        let partial = self.partial.entry(agent_id.to_string()).or_default();
+        partial.extend_from_slice(chunk);
+        let Some(end) = partial.iter().rposition(|&b| b == b'\n') else {
+            return Ok(0);
+        };
+        let complete: Vec<u8> = partial.drain(..=end).collect();
+        let turns: Vec<TranscriptTurn> = String::from_utf8_lossy(&complete)
+            .lines()
+            .flat_map(|line| parse_line(&strip_ansi(line)))
+            .collect();
+        store.record_transcript(agent_id, &turns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SESSION: &str = concat!(
+        r#"{"type":"summary","summary":"Fix the parser"}"#,
+        "\n",
+        r#"{"type":"user","uuid":"u1","timestamp":"2026-03-01T10:00:00Z","message":{"role":"user","content":"fix the parser"}}"#,
+        "\n",
+        r#"{"type":"user","uuid":"m1","isMeta":true,"message":{"role":"user","content":"<command-name>/clear</command-name>"}}"#,
+        "\n",
+        r#"{"type":"assistant","uuid":"a1","timestamp":"2026-03-01T10:00:05Z","message":{"content":[{"type":"thinking","thinking":"hmm"},{"type":"text","text":"Running the tests first."},{"type":"tool_use","id":"t1","name":"Bash","input":{"command":"cargo test parser"}}]}}"#,
+        "\n",
+        r#"{"type":"user","uuid":"r1","timestamp":"2026-03-01T10:00:09Z","message":{"content":[{"type":"tool_result","tool_use_id":"t1","is_error":true,"content":[{"type":"text","text":"1 failed"}]}]}}"#,
+        "\n",
+    );
+
+    #[test]
+    fn session_files_are_read_incrementally_without_duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = StateStore::open(dir.path()).unwrap();
+        let turns: Vec<_> = SESSION.lines().flat_map(parse_line).collect();
+        let roles: Vec<_> = turns.iter().map(|t| t.role).collect();
+        assert_eq!(
+            roles,
+            vec![
+                TranscriptRole::User,
+                TranscriptRole::Assistant,
+                TranscriptRole::ToolUse,
+                TranscriptRole::ToolResult
+            ]
+        );
+        assert_eq!(turns[2].text, "Bash: cargo test parser");
+        assert_eq!(turns[3].text, "error: 1 failed");
+        assert_eq!(turns[1].key, "a1:0");
+
+        // A session file as Claude Code lays it out for the checkout
+        let checkout = dir.path().join("agents/claude-1");
+        std::fs::create_dir_all(&checkout).unwrap();
+        let claude_home = dir.path().join("claude-home");
+        let project = project_dir(&claude_home, &checkout);
+        assert!(project.to_string_lossy().ends_with("-agents-claude-1"));
+        std::fs::create_dir_all(&project).unwrap();
+        let (head, tail) = SESSION.split_at(SESSION.find(r#"{"type":"user","uuid":"r1""#).unwrap());
+        std::fs::write(project.join("s1.jsonl"), head).unwrap();
+
+        let mut reader = TranscriptReader::new().with_claude_dir(&claude_home);
+        assert_eq!(reader.read_session_files(&store, "claude-1", &checkout).unwrap(), 3);
+        std::fs::write(project.join("s1.jsonl"), SESSION).unwrap();
+        assert_eq!(reader.read_session_files(&store, "claude-1", &checkout).unwrap(), 1);
+        let mut again = TranscriptReader::new().with_claude_dir(&claude_home);
+        assert_eq!(again.read_session_files(&store, "claude-1", &checkout).unwrap(), 0);
+
+        // The same messages arriving as stream-json aren't stored twice
+        let mut output = TranscriptReader::new();
+        let (first, rest) = tail.as_bytes().split_at(20);
+        assert_eq!(output.read_output(&store, "claude-1", first).unwrap(), 0);
+        assert_eq!(output.read_output(&store, "claude-1", rest).unwrap(), 0);
+
+        let transcript = store.transcript("claude-1", None).unwrap();
+        assert_eq!(transcript.len(), 4);
+        assert_eq!(transcript[0].text, "fix the parser");
+        assert_eq!(
+            store.transcript("claude-1", Some(1)).unwrap()[0].role,
+            TranscriptRole::ToolResult
+        );
+    }
+
+    #[test]
+    fn malformed_lines_and_missing_files_yield_nothing() {
+        for line in [
+            "",
+            "Running tests...",
+            r#"{"type":"user","message":"#,
+            r#"{"type":"result","message":{"content":"done"}}"#,
+            r#"{"type":"user","uuid":"u2"}"#,
+            r#"{"type":"assistant","uuid":"a2","message":{"content":42}}"#,
+            r#"{"type":"assistant","uuid":"a3","message":{"content":[{"type":"image"},{"text":"untyped"},{"type":"text","text":"   "}]}}"#,
+        ] {
+            assert!(parse_line(line).is_empty(), "{}", line);
+        }
+        assert!("narrator".parse::<TranscriptRole>().is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let store = StateStore::open(dir.path()).unwrap();
+        let checkout = dir.path().join("agents/claude-1");
+        std::fs::create_dir_all(&checkout).unwrap();
+        let claude_home = dir.path().join("claude-home");
+        let mut reader = TranscriptReader::new().with_claude_dir(&claude_home);
+        // Claude Code hasn't written anything for this checkout yet
+        assert_eq!(reader.read_session_files(&store, "claude-1", &checkout).unwrap(), 0);
+
+        // A line still being written waits for its newline
+        let project = project_dir(&claude_home, &checkout);
+        std::fs::create_dir_all(&project).unwrap();
+        let line = SESSION.lines().nth(1).unwrap();
+        std::fs::write(project.join("s1.jsonl"), line).unwrap();
+        std::fs::write(project.join("notes.txt"), format!("{}\n", line)).unwrap();
+        assert_eq!(reader.read_session_files(&store, "claude-1", &checkout).unwrap(), 0);
+        std::fs::write(project.join("s1.jsonl"), format!("{}\n", line)).unwrap();
+        assert_eq!(reader.read_session_files(&store, "claude-1", &checkout).unwrap(), 1);
+
+        // Terminal noise around stream-json output is stripped, not stored
+        let mut output = TranscriptReader::new();
+        let chunk = format!("\x1b[2K\x1b[1G{}\r\nnot json\n", SESSION.lines().nth(3).unwrap());
+        assert_eq!(output.read_output(&store, "claude-2", chunk.as_bytes()).unwrap(), 2);
+        assert_eq!(store.transcript("claude-2", None).unwrap().len(), 2);
+    }
+}
@@ -1,8 +1,27 @@
 //! Main TUI application state and event handling
 
+use crate::audit::AuditSource;
+use crate::config::{AppConfig, SpawnProfile};
 use crate::daemon::{SessionInfo, SessionManager, SessionStatus};
+use crate::health::HealthPolicy;
+use crate::hooks::{HookContext, HookPoint};
+use crate::isolation::IsolationMode;
+use crate::state::StateStore;
+use crate::timeline::{TimelineEntry, TimelineKind, TimelineObserver};
+use crate::transcript::{TranscriptEntry, TranscriptReader};
 use crate::worktree::WorktreeManager;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// How often the timeline is updated from session output and git
+const TIMELINE_REFRESH: Duration = Duration::from_secs(2);
+
+/// How often each agent's CPU and memory use is sampled
+pub const RESOURCE_SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Most recent conversation turns loaded for the transcript panel
+const TRANSCRIPT_TAIL: usize = 200;
 
 /// Available agent types for spawning
 pub const AGENT_TYPES: &[(&str, &str)] = &[
@@ -16,32 +35,59 @@ pub const AGENT_TYPES: &[(&str, &str)] = &[
 /// Pending confirmation action
 #[derive(Debug, Clone)]
 pub enum PendingConfirm {
-    /// Confirm kill of session (agent_id, session_id)
-    Kill { agent_id: String, session_id: String },
+    /// Confirm kill of sessions, as (agent_id, session_id) pairs
+    Kill { targets: Vec<(String, String)> },
+}
+
+/// What the spawn picker is pointing at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpawnChoice {
+    /// A bare agent type from `AGENT_TYPES`
+    Agent(&'static str),
+    /// A profile from `[profiles]` in .rembrandt/config.toml
+    Profile(String),
 }
 
 /// Spawn picker state
 #[derive(Debug, Clone)]
 pub struct SpawnPicker {
-    /// Currently selected agent type index
+    /// Currently selected entry: agent types first, then profiles
     pub selected: usize,
+    /// Configured profile names, listed after the agent types
+    pub profiles: Vec<String>,
+    /// Model to run the agent with; empty for its default (or the profile's)
+    pub model: String,
+    /// Whether keystrokes go to the model field rather than the list
+    pub editing_model: bool,
 }
 
 impl SpawnPicker {
-    pub fn new() -> Self {
-        Self { selected: 0 }
+    pub fn new(profiles: Vec<String>) -> Self {
+        Self {
+            selected: 0,
+            profiles,
+            model: String::new(),
+            editing_model: false,
+        }
+    }
+
+    fn len(&self) -> usize {
+        AGENT_TYPES.len() + self.profiles.len()
     }
 
     pub fn next(&mut self) {
-        self.selected = (self.selected + 1) % AGENT_TYPES.len();
+        self.selected = (self.selected + 1) % self.len();
     }
 
     pub fn prev(&mut self) {
-        self.selected = self.selected.checked_sub(1).unwrap_or(AGENT_TYPES.len() - 1);
+        self.selected = self.selected.checked_sub(1).unwrap_or(self.len() - 1);
     }
 
-    pub fn selected_type(&self) -> &'static str {
-        AGENT_TYPES[self.selected].0
+    pub fn selection(&self) -> SpawnChoice {
+        match AGENT_TYPES.get(self.selected) {
+            Some((agent, _)) => SpawnChoice::Agent(agent),
+            None => SpawnChoice::Profile(self.profiles[self.selected - AGENT_TYPES.len()].clone()),
+        }
     }
 }
 
@@ -55,6 +101,9 @@ pub struct App {
     pub should_quit: bool,
     /// Currently selected session index
     pub selected_index: usize,
+    /// Sessions marked with space; kill, nudge and cleanup apply to these
+    /// instead of the selected session when any are marked
+    pub marked: HashSet<String>,
     /// Status message to display
     pub status_message: Option<String>,
     /// Repository path
@@ -65,13 +114,51 @@ pub struct App {
     pub show_help: bool,
     /// Spawn picker dialog (if active)
     pub spawn_picker: Option<SpawnPicker>,
+    /// .rembrandt/config.toml, for spawn profiles and model flags
+    pub config: AppConfig,
     /// Flag to request terminal clear (after attach/detach)
     pub needs_clear: bool,
+    /// Whether the timeline panel for the selected agent is showing
+    pub show_timeline: bool,
+    /// Whether the selected agent's screen preview is showing
+    pub show_preview: bool,
+    /// Timeline of the selected agent, as of the last refresh
+    pub timeline: Vec<TimelineEntry>,
+    /// Whether the conversation panel for the selected agent is showing
+    /// (in place of the timeline)
+    pub show_transcript: bool,
+    /// Conversation of the selected agent, as of the last refresh
+    pub transcript: Vec<TranscriptEntry>,
+    /// state.db, for the activity timeline (None if it couldn't be opened)
+    pub(super) state: Option<StateStore>,
+    observer: TimelineObserver,
+    transcripts: TranscriptReader,
+    /// Last status seen per session, to record status changes
+    last_status: HashMap<String, SessionStatus>,
+    last_timeline_refresh: Option<Instant>,
+    last_resource_sample: Option<Instant>,
+    /// Agents whose health check has failed past `health_broken_after_mins`,
+    /// with how long it has been failing
+    pub broken_builds: HashMap<String, chrono::Duration>,
 }
 
 impl App {
     pub fn new(repo_path: PathBuf) -> crate::Result<Self> {
-        let worktrees = WorktreeManager::new(&repo_path).map_err(|e| {
+        let mut sessions = SessionManager::new();
+        let config = match AppConfig::load(&repo_path) {
+            Ok(config) => {
+                sessions.set_nudge_ladders(config.agent_nudge_ladders());
+                if let Some(capacity) = config.buffer_capacity {
+                    sessions.set_buffer_capacity(capacity);
+                }
+                config
+            }
+            Err(e) => {
+                tracing::warn!("Using default nudge ladders: {}", e);
+                AppConfig::default()
+            }
+        };
+        let worktrees = config.worktree.manager(&repo_path).map_err(|e| {
             crate::RembrandtError::Worktree(format!(
                 "Failed to open repo at {:?}: {}",
                 repo_path, e
@@ -79,16 +166,30 @@ impl App {
         })?;
 
         Ok(Self {
-            sessions: SessionManager::new(),
+            sessions,
             worktrees,
             should_quit: false,
             selected_index: 0,
+            marked: HashSet::new(),
             status_message: None,
-            repo_path,
             pending_confirm: None,
             show_help: false,
             spawn_picker: None,
+            config,
             needs_clear: false,
+            show_timeline: false,
+            show_preview: false,
+            timeline: Vec::new(),
+            show_transcript: false,
+            transcript: Vec::new(),
+            state: StateStore::open(&repo_path).ok(),
+            observer: TimelineObserver::new(),
+            transcripts: TranscriptReader::new(),
+            last_status: HashMap::new(),
+            last_timeline_refresh: None,
+            last_resource_sample: None,
+            broken_builds: HashMap::new(),
+            repo_path,
         })
     }
 
@@ -109,6 +210,7 @@ impl App {
         if count > 0 {
             self.selected_index = (self.selected_index + 1) % count;
         }
+        let _ = self.reload_timeline();
     }
 
     /// Select previous session
@@ -117,6 +219,37 @@ impl App {
         if count > 0 {
             self.selected_index = self.selected_index.checked_sub(1).unwrap_or(count - 1);
         }
+        let _ = self.reload_timeline();
+    }
+
+    /// Mark or unmark the selected session
+    pub fn toggle_mark(&mut self) {
+        if let Some(session) = self.selected_session()
+            && !self.marked.remove(&session.id)
+        {
+            self.marked.insert(session.id);
+        }
+        // The status bar shows the mark count and batch keys instead
+        self.status_message = None;
+    }
+
+    /// Unmark every session
+    pub fn clear_marks(&mut self) {
+        if !self.marked.is_empty() {
+            self.marked.clear();
+            self.status_message = Some("Marks cleared".to_string());
+        }
+    }
+
+    /// Sessions an action applies to: the marked ones, or else the selected one
+    pub fn target_sessions(&self) -> Vec<SessionInfo> {
+        if self.marked.is_empty() {
+            return self.selected_session().into_iter().collect();
+        }
+        self.session_list()
+            .into_iter()
+            .filter(|s| self.marked.contains(&s.id))
+            .collect()
     }
 
     /// Get session ID for the selected session (for attach)
@@ -134,44 +267,252 @@ impl App {
     pub fn poll_sessions(&mut self) {
         self.sessions.read_all_available();
         self.sessions.poll_all();
+        if !self.marked.is_empty() {
+            let live: HashSet<String> = self.session_list().into_iter().map(|s| s.id).collect();
+            self.marked.retain(|id| live.contains(id));
+        }
+        if self
+            .last_resource_sample
+            .is_none_or(|at| at.elapsed() >= RESOURCE_SAMPLE_INTERVAL)
+        {
+            self.last_resource_sample = Some(Instant::now());
+            self.sessions.sample_resources(&crate::process::ProcessTable::scan());
+        }
+
+        let due = self
+            .last_timeline_refresh
+            .is_none_or(|at| at.elapsed() >= TIMELINE_REFRESH);
+        if due {
+            self.last_timeline_refresh = Some(Instant::now());
+            if let Err(e) = self.refresh_timeline() {
+                tracing::debug!(error = %e, "timeline refresh failed");
+            }
+        }
+    }
+
+    /// Record new timeline events for every session and reload the
+    /// selected agent's timeline.
+    fn refresh_timeline(&mut self) -> crate::Result<()> {
+        let Some(store) = &self.state else {
+            return Ok(());
+        };
+
+        for session in self.sessions.list() {
+            let agent_id = session.agent_id.as_str();
+            self.observer.observe_output_size(store, agent_id, session.output_bytes)?;
+            // Only fetch output the observer hasn't scanned yet
+            let cursor = self.observer.output_cursor(agent_id).unwrap_or(0);
+            if let Ok((start, output)) = self.sessions.output_since(&session.id, cursor) {
+                self.observer.observe_output(store, agent_id, &output, start)?;
+                self.transcripts.read_output(store, agent_id, &output)?;
+            }
+            self.transcripts.read_session_files(store, agent_id, Path::new(&session.workdir))?;
+            let _ = self.observer.observe_commits(
+                store,
+                agent_id,
+                &self.repo_path,
+                &format!("rembrandt/{}", agent_id),
+                session.created_at,
+            );
+
+            let previous = self.last_status.insert(session.id.clone(), session.status.clone());
+            if let Some(previous) = previous
+                && previous != session.status
+            {
+                store.record_timeline(
+                    agent_id,
+                    TimelineKind::StatusChanged,
+                    Some(&format!(
+                        "{} -> {}",
+                        Self::status_display(&previous).1,
+                        Self::status_display(&session.status).1
+                    )),
+                )?;
+            }
+        }
+
+        self.refresh_health()?;
+        self.reload_timeline()
+    }
+
+    /// Reload which agents' builds are broken, per their last health check
+    fn refresh_health(&mut self) -> crate::Result<()> {
+        let (Some(store), Some(policy)) = (&self.state, HealthPolicy::from_config(&self.config)) else {
+            return Ok(());
+        };
+        let now = chrono::Utc::now();
+        self.broken_builds = store
+            .list_health()?
+            .into_iter()
+            .filter(|record| policy.is_broken(record, now))
+            .filter_map(|record| Some((record.agent_id.clone(), record.failing_for(now)?)))
+            .collect();
+        Ok(())
+    }
+
+    /// Reload the selected agent's timeline and transcript from state.db
+    fn reload_timeline(&mut self) -> crate::Result<()> {
+        (self.timeline, self.transcript) = match (&self.state, self.selected_session()) {
+            (Some(store), Some(session)) => (
+                store.timeline(&session.agent_id)?,
+                store.transcript(&session.agent_id, Some(TRANSCRIPT_TAIL))?,
+            ),
+            _ => (Vec::new(), Vec::new()),
+        };
+        Ok(())
+    }
+
+    /// Toggle the timeline panel
+    pub fn toggle_timeline(&mut self) {
+        self.show_timeline = !self.show_timeline;
+        self.show_transcript = false;
+        if let Err(e) = self.reload_timeline() {
+            self.status_message = Some(format!("Timeline unavailable: {}", e));
+        }
+    }
+
+    /// Toggle the conversation panel
+    pub fn toggle_transcript(&mut self) {
+        self.show_transcript = !self.show_transcript;
+        self.show_timeline = false;
+        if let Err(e) = self.reload_timeline() {
+            self.status_message = Some(format!("Transcript unavailable: {}", e));
+        }
+    }
+
+    /// Toggle the screen preview panel
+    pub fn toggle_preview(&mut self) {
+        self.show_preview = !self.show_preview;
     }
 
     /// Spawn a new agent session
     pub fn spawn_agent(&mut self, agent_type: &str, task: Option<&str>) -> crate::Result<String> {
+        self.spawn_with(agent_type, None, None, task)
+    }
+
+    /// Spawn a new agent session with a profile from `[profiles]`; `model`
+    /// overrides the profile's
+    pub fn spawn_profile(&mut self, name: &str, model: Option<&str>) -> crate::Result<String> {
+        let profile = self.config.profile(name)?.clone();
+        let model = model.or(profile.model.as_deref());
+        self.spawn_with(&profile.agent, Some(&profile), model, None)
+    }
+
+    /// Run the configured hooks for `point`, recording them in state.db
+    fn run_hooks(&self, point: HookPoint, cwd: &Path, agent_id: &str, env: &[(String, String)]) -> crate::Result<()> {
+        let ctx = HookContext {
+            repo_path: &self.repo_path,
+            cwd,
+            agent_id,
+            env,
+            store: self.state.as_ref(),
+        };
+        crate::hooks::run(&self.config.hooks, point, &ctx).map(|_| ())
+    }
+
+    /// Spawn an agent in a fresh workspace, isolated, prompted and with the
+    /// environment `profile` asks for, or else in a worktree with `task` as
+    /// its first prompt
+    fn spawn_with(
+        &mut self,
+        agent_type: &str,
+        profile: Option<&SpawnProfile>,
+        model: Option<&str>,
+        task: Option<&str>,
+    ) -> crate::Result<String> {
         use crate::agent::AgentType;
 
+        let mode = profile.map_or(IsolationMode::Worktree, SpawnProfile::isolation_mode);
+        let template = profile.and_then(|p| p.template.as_deref());
+
         // Generate agent ID
         let suffix: String = (0..4)
             .map(|_| format!("{:x}", rand::random::<u8>() % 16))
             .collect();
         let agent_id = format!("{}-{}", agent_type, suffix);
 
-        // Create worktree from current branch (HEAD)
-        // The worktree manager will create a new branch rembrandt/{agent_id}
+        // Create workspace from current branch (HEAD) on a new branch
+        // rembrandt/{agent_id}
         let base_branch = self.get_current_branch().unwrap_or_else(|| "main".to_string());
-        let worktree = self.worktrees.create_worktree(&agent_id, &base_branch)?;
+        let env = self.config.spawn_env(profile, &[], &agent_id, &format!("rembrandt/{}", agent_id), None);
+        self.run_hooks(HookPoint::PreSpawn, &self.repo_path, &agent_id, &env)?;
+        let workspace = crate::isolation::prepare_workspace(mode, &self.repo_path, &agent_id, &base_branch)?;
+        if let Err(e) = self.run_hooks(HookPoint::PostWorktree, &workspace.checkout_path, &agent_id, &env) {
+            crate::isolation::discard_workspace(&workspace);
+            return Err(e);
+        }
+        let prompt = match template {
+            Some(name) => Some(crate::templates::render_file(
+                &self.repo_path,
+                name,
+                &crate::templates::TemplateVars {
+                    task: None,
+                    prompt: task,
+                    agent: &agent_id,
+                    branch: &workspace.branch_name,
+                },
+            )?),
+            None => task.map(str::to_string),
+        };
 
         // Resolve command
         let agent = AgentType::from_str(agent_type);
         let command = agent.command();
-        let args = agent.default_args();
+        let mut args: Vec<String> = agent.default_args().iter().map(|a| a.to_string()).collect();
+        if let Some(model) = model {
+            args.extend(self.config.model_args(&agent, model)?);
+        }
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
 
         // Get actual terminal size
         let (cols, rows) = crossterm::terminal::size().unwrap_or((80, 24));
 
         // Spawn PTY session with actual terminal size
-        let session_id = self.sessions.spawn_with_size(
+        let session_id = self.sessions.spawn_with_env(
             agent_id.clone(),
             command,
             &args,
-            &worktree.path,
+            &env,
+            &workspace.checkout_path,
             Some(rows),
             Some(cols),
         )?;
+        if let Some(store) = &self.state {
+            let now = chrono::Utc::now();
+            store.upsert_session(&crate::state::SessionRecord {
+                agent_id: agent_id.clone(),
+                runtime_kind: "pty".to_string(),
+                runtime_session_id: None,
+                isolation_mode: mode,
+                branch_name: workspace.branch_name.clone(),
+                checkout_path: workspace.checkout_path.clone(),
+                task_id: None,
+                status: crate::state::SessionStatus::Active,
+                model: model.map(str::to_string),
+                created_at: now,
+                updated_at: now,
+                prompt: prompt.clone(),
+                agent_type: Some(agent.to_string()),
+                updated_seq: 0,
+            })?;
+            let detail = format!("{} on rembrandt/{}", command, agent_id);
+            store.record_timeline(&agent_id, TimelineKind::Spawned, Some(&detail))?;
+        }
+        self.audit(
+            "spawn",
+            &agent_id,
+            serde_json::json!({
+                "agent": agent_type,
+                "model": model,
+                "template": template,
+                "branch": workspace.branch_name,
+                "isolation": mode.to_string(),
+            }),
+        );
 
         // If we have an initial task/prompt, send it after a brief delay
         // to let the agent start up
-        if let Some(prompt) = task {
+        if let Some(prompt) = prompt {
             // Send the prompt to the agent's stdin
             // Add newline to submit the prompt
             let prompt_with_newline = format!("{}\n", prompt);
@@ -183,22 +524,45 @@ impl App {
             }
         }
 
-        self.status_message = Some(format!("Spawned {} ({})", agent_id, session_id));
+        let propagated = match workspace.propagated.len() {
+            0 => String::new(),
+            1 => ", 1 untracked file propagated".to_string(),
+            n => format!(", {} untracked files propagated", n),
+        };
+        self.status_message = Some(format!("Spawned {} ({}){}", agent_id, session_id, propagated));
         Ok(session_id)
     }
 
-    /// Request kill confirmation for the selected session
+    /// Request kill confirmation for the marked sessions, or the selected one
     pub fn request_kill(&mut self) {
-        if let Some(session) = self.selected_session() {
-            self.pending_confirm = Some(PendingConfirm::Kill {
-                agent_id: session.agent_id.clone(),
-                session_id: session.id.clone(),
-            });
-            self.status_message = Some(format!(
-                "Kill {} and DELETE worktree? (y/n)",
-                session.agent_id
-            ));
-        }
+        let targets = self.target_sessions();
+        let at_risk: Vec<String> = targets
+            .iter()
+            .filter_map(|session| {
+                let checkout = self.worktrees.worktree_path(&session.agent_id);
+                crate::rescue::check(&checkout)
+                    .ok()
+                    .filter(|at_risk| !at_risk.is_empty())
+                    .map(|at_risk| at_risk.summary())
+            })
+            .collect();
+        self.status_message = match (targets.as_slice(), at_risk.as_slice()) {
+            ([], _) => return,
+            ([session], []) => Some(format!("Kill {} and DELETE worktree? (y/n)", session.agent_id)),
+            ([session], [summary]) => Some(format!(
+                "Kill {} and DELETE worktree? It has {}; changes are snapshotted first. (y/n)",
+                session.agent_id, summary
+            )),
+            (_, []) => Some(format!("Kill {} agents and DELETE their worktrees? (y/n)", targets.len())),
+            (_, _) => Some(format!(
+                "Kill {} agents and DELETE their worktrees? {} have uncommitted work; it is snapshotted first. (y/n)",
+                targets.len(),
+                at_risk.len()
+            )),
+        };
+        self.pending_confirm = Some(PendingConfirm::Kill {
+            targets: targets.into_iter().map(|s| (s.agent_id, s.id)).collect(),
+        });
     }
 
     /// Cancel pending confirmation
@@ -211,46 +575,151 @@ impl App {
     pub fn confirm_action(&mut self) -> crate::Result<()> {
         if let Some(confirm) = self.pending_confirm.take() {
             match confirm {
-                PendingConfirm::Kill { agent_id, session_id } => {
-                    // Kill the PTY session (ignore errors - session may already be dead)
-                    let _ = self.sessions.kill(&session_id);
-
-                    // Remove from session manager
-                    self.sessions.remove(&session_id);
-
-                    // Cleanup the worktree
-                    if let Err(e) = self.worktrees.remove_worktree(&agent_id) {
-                        self.status_message = Some(format!(
-                            "Removed {} (worktree cleanup failed: {})",
-                            agent_id, e
-                        ));
-                    } else {
-                        self.status_message = Some(format!("Removed {} + cleaned worktree", agent_id));
-                    }
+                PendingConfirm::Kill { targets } => {
+                    let outcomes: Vec<String> = targets
+                        .iter()
+                        .map(|(agent_id, session_id)| self.kill_session(agent_id, session_id))
+                        .collect();
+                    self.marked.clear();
 
                     // Adjust selected index if needed
                     let count = self.sessions.total_count();
                     if self.selected_index >= count && count > 0 {
                         self.selected_index = count - 1;
                     }
+
+                    self.status_message = Some(match outcomes.as_slice() {
+                        [outcome] => outcome.clone(),
+                        _ => format!("Killed {} agents: {}", outcomes.len(), outcomes.join("; ")),
+                    });
                 }
             }
         }
         Ok(())
     }
 
+    /// Kill a session and remove its agent's worktree, snapshotting
+    /// uncommitted work first; returns what happened, for the status line
+    fn kill_session(&mut self, agent_id: &str, session_id: &str) -> String {
+        // Kill the PTY session (ignore errors - session may already be dead)
+        let _ = self.sessions.kill(session_id);
+
+        // Remove from session manager
+        self.sessions.remove(session_id);
+
+        // Save uncommitted work before the worktree goes
+        let checkout = self.worktrees.worktree_path(agent_id);
+        let rescued = match crate::state::StateStore::open(&self.repo_path) {
+            Ok(store) if checkout.is_dir() => {
+                crate::rescue::rescue(&self.repo_path, &store, agent_id, &checkout, "kill")
+            }
+            _ => Ok(None),
+        };
+        let note = match rescued {
+            Ok(Some(snapshot)) => format!(
+                "; uncommitted work kept, recover with `{}`",
+                crate::rescue::recovery_hint(&snapshot)
+            ),
+            Ok(None) => String::new(),
+            Err(e) => return format!("Removed {}; kept its worktree, snapshot failed: {}", agent_id, e),
+        };
+        self.audit("kill", agent_id, serde_json::json!({ "session": session_id }));
+        match self.worktrees.remove_worktree(agent_id) {
+            Ok(_) => format!("Removed {} + cleaned worktree{}", agent_id, note),
+            Err(e) => format!("Removed {} (worktree cleanup failed: {})", agent_id, e),
+        }
+    }
+
+    /// Remove finished sessions from the list: the marked ones that are no
+    /// longer running if any are marked, otherwise every successful one
+    pub fn cleanup_sessions(&mut self) {
+        let cleaned = if self.marked.is_empty() {
+            self.sessions.cleanup().len()
+        } else {
+            let done: Vec<String> = self
+                .target_sessions()
+                .into_iter()
+                .filter(|s| s.status != SessionStatus::Running)
+                .map(|s| s.id)
+                .collect();
+            for id in &done {
+                self.sessions.remove(id);
+                self.marked.remove(id);
+            }
+            done.len()
+        };
+        let count = self.sessions.total_count();
+        if self.selected_index >= count && count > 0 {
+            self.selected_index = count - 1;
+        }
+        self.status_message = Some(if cleaned == 0 {
+            "No completed sessions to clean".to_string()
+        } else {
+            format!("Cleaned {} session(s)", cleaned)
+        });
+    }
+
+    /// Record a mutation made from the dashboard in the audit log
+    fn audit(&self, action: &str, agent_id: &str, params: serde_json::Value) {
+        if let Some(store) = &self.state
+            && let Err(e) = crate::audit::record_in(store, AuditSource::Tui, action, Some(agent_id), params)
+        {
+            tracing::warn!(action, "failed to record audit entry: {}", e);
+        }
+    }
+
     /// Check if there's a pending confirmation
     pub fn has_pending_confirm(&self) -> bool {
         self.pending_confirm.is_some()
     }
 
-    /// Nudge the selected session
-    pub fn nudge_selected(&mut self) -> crate::Result<()> {
-        if let Some(session) = self.selected_session() {
-            self.sessions.nudge(&session.id)?;
-            self.status_message = Some(format!("Nudged {}", session.agent_id));
+    /// Nudge the marked sessions, or the selected one
+    ///
+    /// An agent that can't be nudged doesn't stop the others; failures are
+    /// listed in the status line after the ones that were nudged.
+    pub fn nudge_selected(&mut self) {
+        let targets = self.target_sessions();
+        if targets.is_empty() {
+            return;
         }
-        Ok(())
+        let mut nudged = Vec::new();
+        let mut failed = Vec::new();
+        for session in &targets {
+            if session.status != SessionStatus::Running && targets.len() > 1 {
+                continue;
+            }
+            match self.nudge_session(session) {
+                Ok(strategy) => nudged.push((session.agent_id.clone(), strategy)),
+                Err(e) => failed.push(format!("{}: {}", session.agent_id, e)),
+            }
+        }
+
+        let mut status = Vec::new();
+        match nudged.as_slice() {
+            [] => {}
+            [(agent_id, strategy)] => status.push(format!("Nudged {} ({})", agent_id, strategy)),
+            _ => status.push(format!("Nudged {} agents", nudged.len())),
+        }
+        if !failed.is_empty() {
+            status.push(format!("Nudge failed: {}", failed.join("; ")));
+        }
+        if status.is_empty() {
+            status.push("No running sessions to nudge".to_string());
+        }
+        self.status_message = Some(status.join("; "));
+    }
+
+    /// Nudge one session and record it; returns the strategy used
+    fn nudge_session(&mut self, session: &SessionInfo) -> crate::Result<String> {
+        let strategy = self.sessions.nudge(&session.id)?.describe();
+        if let Some(store) = &self.state {
+            let detail = format!("manual: {}", strategy);
+            if let Err(e) = store.record_timeline(&session.agent_id, TimelineKind::Nudge, Some(&detail)) {
+                tracing::warn!(agent_id = %session.agent_id, "failed to record nudge: {}", e);
+            }
+        }
+        self.audit("nudge", &session.agent_id, serde_json::json!({ "strategy": strategy }));
+        Ok(strategy)
     }
 
     /// Get count of sessions needing attention (failed/exited non-zero)
@@ -283,7 +752,7 @@ impl App {
 
     /// Open spawn picker dialog
     pub fn open_spawn_picker(&mut self) {
-        self.spawn_picker = Some(SpawnPicker::new());
+        self.spawn_picker = Some(SpawnPicker::new(self.config.profiles.keys().cloned().collect()));
     }
 
     /// Close spawn picker without spawning
@@ -294,8 +763,13 @@ impl App {
     /// Confirm spawn from picker
     pub fn confirm_spawn(&mut self) -> crate::Result<()> {
         if let Some(picker) = self.spawn_picker.take() {
-            let agent_type = picker.selected_type();
-            self.spawn_agent(agent_type, None)?;
+            let model = Some(picker.model.trim()).filter(|m| !m.is_empty());
+            match picker.selection() {
+                SpawnChoice::Agent(agent_type) => {
+                    self.spawn_with(agent_type, None, model, None)?
+                }
+                SpawnChoice::Profile(name) => self.spawn_profile(&name, model)?,
+            };
         }
         Ok(())
     }
@@ -321,3 +795,50 @@ impl App {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn marked_sessions_are_batch_targets_and_nudges_continue_past_failures() {
+        let dir = tempfile::tempdir().unwrap();
+        git2::Repository::init(dir.path()).unwrap();
+        let mut app = App::new(dir.path().to_path_buf()).unwrap();
+        let mut ids = Vec::new();
+        for agent in ["a", "b", "c"] {
+            ids.push(app.sessions.spawn(agent.to_string(), "sleep", &["30"], dir.path()).unwrap());
+        }
+        let agents = |targets: Vec<SessionInfo>| {
+            let mut agents: Vec<String> = targets.into_iter().map(|s| s.agent_id).collect();
+            agents.sort();
+            agents
+        };
+        let selected = app.selected_session().unwrap().agent_id;
+        assert_eq!(agents(app.target_sessions()), vec![selected]);
+
+        // Mark two, unmark and re-mark one
+        app.toggle_mark();
+        app.next_session();
+        app.toggle_mark();
+        app.toggle_mark();
+        app.toggle_mark();
+        assert_eq!(app.marked.len(), 2);
+        let marked = agents(app.target_sessions());
+        assert_eq!(marked.len(), 2);
+
+        // The first marked agent has nothing to nudge with; the other is still nudged
+        let broken = app.target_sessions().into_iter().find(|s| s.agent_id == marked[0]).unwrap();
+        app.sessions.set_nudge_ladder(&broken.id, Vec::new()).unwrap();
+        app.nudge_selected();
+        let status = app.status_message.clone().unwrap();
+        assert!(status.starts_with(&format!("Nudged {} (", marked[1])), "{}", status);
+        assert!(status.contains(&format!("Nudge failed: {}: ", marked[0])), "{}", status);
+
+        app.clear_marks();
+        assert!(app.marked.is_empty());
+        for id in &ids {
+            let _ = app.sessions.kill(id);
+        }
+    }
+}
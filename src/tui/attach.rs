@@ -2,6 +2,12 @@
 //!
 //! When attached, the PTY has direct control of the terminal.
 //! This allows full TUI applications like Claude Code to render correctly.
+//! The agent owns every row, so the Solo view's header is the terminal's
+//! title: the agent and its latest CPU and memory sample. With the activity
+//! timeline toggled on, the bottom rows are kept for it instead: the agent's
+//! terminal is that much shorter and a scroll region stops its output
+//! running into the panel. The loop sleeps in mio until a key is pressed or
+//! the agent prints something.
 
 use crossterm::{
     execute,
@@ -11,7 +17,17 @@ use std::fs::File;
 use std::io::{self, Read, Write};
 use std::os::unix::io::{AsRawFd, FromRawFd};
 
-use crate::daemon::SessionManager;
+use crate::daemon::ipc::DaemonConnection;
+use crate::daemon::session::PtySession;
+use crate::daemon::{DaemonEvent, Framing, SessionManager};
+use crate::state::StateStore;
+use crate::timeline::TimelineEntry;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+use mio::unix::SourceFd;
+use mio::{Events, Interest, Poll, Token, Waker};
+use std::path::Path;
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
 
 /// Result of an attach session
 pub enum AttachResult {
@@ -23,31 +39,50 @@ pub enum AttachResult {
     Error(String),
 }
 
+/// Rows of the Solo view's timeline panel, its title line included
+const TIMELINE_ROWS: u16 = 8;
+
 /// Attach directly to a PTY session
 ///
 /// This exits the alternate screen and gives the PTY direct terminal control.
+/// With `timeline`, the agent's activity timeline from that store is shown
+/// below it, when the terminal is tall enough.
 /// Detach methods:
 /// - Ctrl+] or Ctrl+\ (if not intercepted by the agent)
 /// - Double-Escape (press Escape twice quickly)
 pub fn attach_to_session(
     sessions: &mut SessionManager,
     session_id: &str,
+    timeline: Option<&StateStore>,
 ) -> crate::Result<AttachResult> {
-    // Get the session and take exclusive reader access
     let session = sessions
         .get_mut(session_id)
         .ok_or_else(|| crate::RembrandtError::SessionNotFound(session_id.to_string()))?;
 
-    // Get current terminal size
+    // Get current terminal size; the panel needs room left for the agent
     let (cols, rows) = crossterm::terminal::size().unwrap_or((80, 24));
+    let panel = timeline
+        .filter(|_| rows > TIMELINE_ROWS * 2)
+        .map(|store| TimelinePanel {
+            store,
+            agent_id: session.agent_id.clone(),
+            top: rows - TIMELINE_ROWS + 1,
+            cols,
+            entries: Vec::new(),
+        });
+    let rows = if panel.is_some() { rows - TIMELINE_ROWS } else { rows };
 
-    // Take EXCLUSIVE reader access - no competing readers
-    let pty_reader = session.take_reader().ok_or_else(|| {
-        crate::RembrandtError::Pty("Reader not available".to_string())
-    })?;
+    // Output captured while detached is history; only stream what's new
+    session.drain_available();
 
-    // Leave alternate screen for direct PTY access
+    // Leave alternate screen for direct PTY access, saving the terminal
+    // title the header replaces
     execute!(io::stdout(), LeaveAlternateScreen).ok();
+    io::stdout().write_all(b"\x1b[22;0t").ok();
+    if panel.is_some() {
+        // Keep the agent's scrolling above the panel
+        write!(io::stdout(), "\x1b[2J\x1b[1;{}r\x1b[H", rows).ok();
+    }
     io::stdout().flush().ok();
 
     // Resize and signal the app to redraw
@@ -58,7 +93,8 @@ pub fn attach_to_session(
     std::thread::sleep(std::time::Duration::from_millis(100));
 
     // Run the attach loop
-    let result = run_attach_loop(sessions, session_id, pty_reader);
+    let result = run_attach_loop(sessions, session_id, panel);
+    io::stdout().write_all(b"\x1b[r\x1b[23;0t").ok();
 
     // Disable mouse capture, re-enter alternate screen for TUI
     execute!(
@@ -71,15 +107,98 @@ pub fn attach_to_session(
     .ok();
     io::stdout().flush().ok();
 
-    // Return reader to session and extract result
     match result {
-        Ok((reader, attach_result)) => {
-            if let Some(session) = sessions.get_mut(session_id) {
-                session.return_reader(reader);
+        Ok(attach_result) => Ok(attach_result),
+        Err(e) => Ok(AttachResult::Error(e)),
+    }
+}
+
+/// Poll token for keyboard input
+const STDIN: Token = Token(0);
+/// Poll token the session's reader wakes on output
+const OUTPUT: Token = Token(1);
+/// Longest wait before checking the agent is still running; an agent whose
+/// children keep its terminal open exits without waking the loop
+const EXIT_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The Solo view's activity timeline, drawn in the rows below the agent's
+struct TimelinePanel<'a> {
+    store: &'a StateStore,
+    agent_id: String,
+    /// First terminal row of the panel (1-based)
+    top: u16,
+    cols: u16,
+    entries: Vec<TimelineEntry>,
+}
+
+impl TimelinePanel<'_> {
+    /// Reload the agent's entries from state.db
+    fn reload(&mut self) {
+        match self.store.timeline(&self.agent_id) {
+            Ok(entries) => self.entries = entries,
+            Err(e) => tracing::debug!(error = %e, "timeline reload failed"),
+        }
+    }
+
+    /// Draw the panel, newest entry at the bottom, leaving the cursor where
+    /// the agent had it
+    fn draw(&self, out: &mut impl Write) {
+        let width = self.cols as usize;
+        let title = format!("── Timeline: {} ", self.agent_id);
+        let fill = width.saturating_sub(title.chars().count());
+        let mut panel = format!(
+            "\x1b7\x1b[{};1H\x1b[2K\x1b[2m{}{}\x1b[0m",
+            self.top,
+            title.chars().take(width).collect::<String>(),
+            "─".repeat(fill)
+        );
+        let visible = (TIMELINE_ROWS - 1) as usize;
+        let skip = self.entries.len().saturating_sub(visible);
+        for row in 0..visible {
+            panel.push_str(&format!("\x1b[{};1H\x1b[2K", self.top + 1 + row as u16));
+            if let Some(entry) = self.entries.get(skip + row) {
+                let time = entry.created_at.with_timezone(&chrono::Local).format("%H:%M:%S");
+                let text = format!("{} {}", entry.kind.as_str(), entry.detail.as_deref().unwrap_or(""));
+                let text: String = text
+                    .trim_end()
+                    .chars()
+                    .map(|c| if c.is_control() { ' ' } else { c })
+                    .take(width.saturating_sub(9))
+                    .collect();
+                panel.push_str(&format!("\x1b[2m{}\x1b[0m {}", time, text));
+            } else if self.entries.is_empty() && row == 0 {
+                panel.push_str("\x1b[2mNo activity recorded yet\x1b[0m");
             }
-            Ok(attach_result)
         }
-        Err(e) => Ok(AttachResult::Error(e)),
+        panel.push_str("\x1b8");
+        out.write_all(panel.as_bytes()).ok();
+        out.flush().ok();
+    }
+}
+
+/// Whether `output` ends between escape sequences and characters, so the
+/// panel can be drawn after it without splitting one
+fn at_boundary(output: &[u8]) -> bool {
+    // A multi-byte character cut off at the end
+    if let Some(back) = output.iter().rev().take(4).position(|&b| b & 0xc0 != 0x80) {
+        let len = match output[output.len() - 1 - back] {
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            _ => 1,
+        };
+        if back + 1 < len {
+            return false;
+        }
+    }
+    let Some(escape) = output.iter().rposition(|&b| b == 0x1b) else {
+        return true;
+    };
+    match &output[escape + 1..] {
+        [] => false,
+        [b'[', rest @ ..] => rest.iter().any(|b| (0x40..=0x7e).contains(b)),
+        [b']', rest @ ..] => rest.contains(&0x07),
+        _ => true,
     }
 }
 
@@ -87,14 +206,27 @@ pub fn attach_to_session(
 fn run_attach_loop(
     sessions: &mut SessionManager,
     session_id: &str,
-    mut pty_reader: Box<dyn Read + Send>,
-) -> Result<(Box<dyn Read + Send>, AttachResult), String> {
+    mut panel: Option<TimelinePanel>,
+) -> Result<AttachResult, String> {
     let mut stdout = io::stdout();
 
     // Set up stdin for raw reading
     let stdin_fd = io::stdin().as_raw_fd();
     let mut stdin_reader = unsafe { File::from_raw_fd(libc::dup(stdin_fd)) };
 
+    // Wait on stdin and the session's output instead of polling them
+    let mut poll = Poll::new().map_err(|e| e.to_string())?;
+    poll.registry()
+        .register(&mut SourceFd(&stdin_fd), STDIN, Interest::READABLE)
+        .map_err(|e| e.to_string())?;
+    let waker = Arc::new(Waker::new(poll.registry(), OUTPUT).map_err(|e| e.to_string())?);
+    if let Some(session) = sessions.get(session_id) {
+        session.set_output_waker(Arc::new(move || {
+            waker.wake().ok();
+        }));
+    }
+    let mut events = Events::with_capacity(8);
+
     // Save original stdin flags and set non-blocking
     let original_flags = unsafe { libc::fcntl(stdin_fd, libc::F_GETFL) };
     unsafe {
@@ -119,112 +251,257 @@ fn run_attach_loop(
         }
     }
 
-    let mut read_buf = [0u8; 4096];
     let mut stdin_buf = [0u8; 256];
 
     // Track last escape time for double-escape detection
     let mut last_escape: Option<std::time::Instant> = None;
     const DOUBLE_ESCAPE_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(300);
+    let mut last_sample: Option<std::time::Instant> = None;
+    let (mut panel_stale, mut between_sequences) = (true, true);
 
     loop {
-        // Try to read from PTY (non-blocking since we set it up that way)
-        match pty_reader.read(&mut read_buf) {
-            Ok(0) => {
-                // EOF - PTY closed
-                drain_stdin(&mut stdin_reader);
-                restore_stdin();
-                return Ok((pty_reader, AttachResult::SessionEnded));
+        if last_sample.is_none_or(|at| at.elapsed() >= super::app::RESOURCE_SAMPLE_INTERVAL)
+            && let Some(session) = sessions.get_mut(session_id)
+        {
+            last_sample = Some(std::time::Instant::now());
+            session.sample_resources(&crate::process::ProcessTable::scan());
+            stdout.write_all(solo_header(session).as_bytes()).ok();
+            stdout.flush().ok();
+            if let Some(panel) = panel.as_mut() {
+                panel.reload();
+                panel_stale = true;
             }
-            Ok(n) => {
-                // Forward to stdout
-                stdout.write_all(&read_buf[..n]).ok();
+        }
+
+        // Forward output the session's reader has captured; the agent may
+        // have cleared the screen, panel included
+        if let Some(session) = sessions.get_mut(session_id) {
+            let output = session.drain_available();
+            if !output.is_empty() {
+                stdout.write_all(&output).ok();
                 stdout.flush().ok();
-            }
-            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
-                // No data available - that's fine
-            }
-            Err(_) => {
-                drain_stdin(&mut stdin_reader);
-                restore_stdin();
-                return Ok((pty_reader, AttachResult::SessionEnded));
+                panel_stale = true;
+                between_sequences = at_boundary(&output);
             }
         }
+        if let Some(panel) = &panel
+            && panel_stale
+            && between_sequences
+        {
+            panel.draw(&mut stdout);
+            panel_stale = false;
+        }
 
-        // Try to read from stdin (non-blocking)
-        match stdin_reader.read(&mut stdin_buf) {
-            Ok(0) => {
-                drain_stdin(&mut stdin_reader);
-                restore_stdin();
-                return Ok((pty_reader, AttachResult::Detached));
-            }
-            Ok(n) => {
-                // Check for detach sequences: Ctrl+] (0x1d) or Ctrl+\ (0x1c)
-                if stdin_buf[..n].contains(&0x1d) || stdin_buf[..n].contains(&0x1c) {
+        // Read all pending input: mio readiness is edge-triggered
+        loop {
+            match stdin_reader.read(&mut stdin_buf) {
+                Ok(0) => {
                     drain_stdin(&mut stdin_reader);
                     restore_stdin();
-                    return Ok((pty_reader, AttachResult::Detached));
+                    return Ok(AttachResult::Detached);
                 }
+                Ok(n) => {
+                    // Check for detach sequences: Ctrl+] (0x1d) or Ctrl+\ (0x1c)
+                    if stdin_buf[..n].contains(&0x1d) || stdin_buf[..n].contains(&0x1c) {
+                        drain_stdin(&mut stdin_reader);
+                        restore_stdin();
+                        return Ok(AttachResult::Detached);
+                    }
 
-                // Check for double-escape (Escape = 0x1b)
-                // Only count STANDALONE escapes, not escape sequences like arrow keys (\x1b[A)
-                let has_standalone_escape = if n == 1 && stdin_buf[0] == 0x1b {
-                    true // Single escape byte = standalone
-                } else {
-                    // Check for escape not followed by '[' (which would be an escape sequence)
-                    let mut found = false;
-                    for i in 0..n {
-                        if stdin_buf[i] == 0x1b {
-                            // Check if NOT followed by '['
-                            if i + 1 >= n || stdin_buf[i + 1] != b'[' {
-                                found = true;
-                                break;
+                    // Check for double-escape (Escape = 0x1b)
+                    // Only count STANDALONE escapes, not escape sequences like arrow keys (\x1b[A)
+                    let has_standalone_escape = if n == 1 && stdin_buf[0] == 0x1b {
+                        true // Single escape byte = standalone
+                    } else {
+                        // Check for escape not followed by '[' (which would be an escape sequence)
+                        let mut found = false;
+                        for i in 0..n {
+                            if stdin_buf[i] == 0x1b {
+                                // Check if NOT followed by '['
+                                if i + 1 >= n || stdin_buf[i + 1] != b'[' {
+                                    found = true;
+                                    break;
+                                }
                             }
                         }
-                    }
-                    found
-                };
+                        found
+                    };
 
-                if has_standalone_escape {
-                    if let Some(last) = last_escape {
-                        if last.elapsed() < DOUBLE_ESCAPE_TIMEOUT {
+                    if has_standalone_escape {
+                        if let Some(last) = last_escape
+                            && last.elapsed() < DOUBLE_ESCAPE_TIMEOUT
+                        {
                             // Double escape detected - detach!
                             drain_stdin(&mut stdin_reader);
                             restore_stdin();
-                            return Ok((pty_reader, AttachResult::Detached));
+                            return Ok(AttachResult::Detached);
                         }
+                        last_escape = Some(std::time::Instant::now());
                     }
-                    last_escape = Some(std::time::Instant::now());
-                }
 
-                // Forward to PTY
-                if let Some(session) = sessions.get_mut(session_id) {
-                    session.write(&stdin_buf[..n]).ok();
+                    // Forward to PTY
+                    if let Some(session) = sessions.get_mut(session_id) {
+                        session.write(&stdin_buf[..n]).ok();
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(_) => {
+                    drain_stdin(&mut stdin_reader);
+                    restore_stdin();
+                    return Ok(AttachResult::Error("stdin error".to_string()));
                 }
-            }
-            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
-                // No input available - that's fine
-            }
-            Err(_) => {
-                drain_stdin(&mut stdin_reader);
-                restore_stdin();
-                return Ok((pty_reader, AttachResult::Error("stdin error".to_string())));
             }
         }
 
         // Check if session is still running
         if let Some(session) = sessions.get_mut(session_id) {
+            session.poll();
             if !session.is_running() {
+                // Show the output it printed on the way out
+                stdout.write_all(&session.drain_available()).ok();
+                stdout.flush().ok();
                 drain_stdin(&mut stdin_reader);
                 restore_stdin();
-                return Ok((pty_reader, AttachResult::SessionEnded));
+                return Ok(AttachResult::SessionEnded);
             }
         } else {
             drain_stdin(&mut stdin_reader);
             restore_stdin();
-            return Ok((pty_reader, AttachResult::SessionEnded));
+            return Ok(AttachResult::SessionEnded);
+        }
+
+        // Sleep until there's input or output, or the next sample is due
+        let until_sample = last_sample.map_or(Duration::ZERO, |at| {
+            super::app::RESOURCE_SAMPLE_INTERVAL.saturating_sub(at.elapsed())
+        });
+        if let Err(e) = poll.poll(&mut events, Some(until_sample.min(EXIT_CHECK_INTERVAL)))
+            && e.kind() != io::ErrorKind::Interrupted
+        {
+            drain_stdin(&mut stdin_reader);
+            restore_stdin();
+            return Err(e.to_string());
+        }
+    }
+}
+
+/// Terminal title escape naming the agent, with its latest resource sample
+fn solo_header(session: &PtySession) -> String {
+    let title = match session.latest_resources() {
+        Some(sample) => format!("{} · {}", session.agent_id, sample),
+        None => session.agent_id.clone(),
+    };
+    format!("\x1b]2;{}\x07", title)
+}
+
+/// Attach the terminal to a daemon session until Ctrl+D or the session ends
+///
+/// Output streams from one connection; keystrokes go to the PTY over a
+/// second one. With `read_only`, the session is attached as an observer:
+/// keys other than Ctrl+D are dropped here, the PTY isn't resized to this
+/// terminal, and the daemon refuses input on the attached connection too.
+pub fn attach_via_daemon(socket: &Path, session_id: &str, read_only: bool) -> crate::Result<AttachResult> {
+    let mut events = DaemonConnection::connect(socket)?;
+    events.set_framing(Framing::Binary)?;
+    let (history, offset) = if read_only {
+        events.attach_read_only(session_id)?
+    } else {
+        events.attach(session_id)?
+    };
+    let mut input = if read_only {
+        None
+    } else {
+        let mut conn = DaemonConnection::connect(socket)?;
+        if let Ok((cols, rows)) = crossterm::terminal::size() {
+            conn.resize(session_id, rows, cols).ok();
+        }
+        Some(conn)
+    };
+
+    let mut stdout = io::stdout();
+    stdout.write_all(&history)?;
+    stdout.flush()?;
+
+    // `next_event` blocks, so events are read on their own thread
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        while let Ok(Some(event)) = events.next_event() {
+            if tx.send(event).is_err() {
+                break;
+            }
         }
+    });
+
+    crossterm::terminal::enable_raw_mode()?;
+    let result = (|| -> crate::Result<AttachResult> {
+        // Events can repeat output the history already covered
+        let mut seen = offset + history.len();
+        loop {
+            if event::poll(std::time::Duration::from_millis(10))?
+                && let Event::Key(key) = event::read()?
+            {
+                if key.code == KeyCode::Char('d') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                    return Ok(AttachResult::Detached);
+                }
+                if let Some(conn) = input.as_mut() {
+                    let bytes = key_bytes(&key);
+                    if !bytes.is_empty() {
+                        conn.write(session_id, bytes)?;
+                    }
+                }
+            }
+
+            loop {
+                match rx.try_recv() {
+                    Ok(DaemonEvent::Output { offset, data, .. }) => {
+                        let skip = seen.saturating_sub(offset).min(data.len());
+                        stdout.write_all(&data[skip..])?;
+                        stdout.flush()?;
+                        seen = seen.max(offset + data.len());
+                    }
+                    Ok(DaemonEvent::Exited { .. }) | Err(mpsc::TryRecvError::Disconnected) => {
+                        return Ok(AttachResult::SessionEnded);
+                    }
+                    Ok(DaemonEvent::StatusChanged { .. }) => {}
+                    Err(mpsc::TryRecvError::Empty) => break,
+                }
+            }
+        }
+    })();
+    crossterm::terminal::disable_raw_mode()?;
+    result
+}
+
+/// Bytes a key press sends to a terminal application
+fn key_bytes(key: &KeyEvent) -> Vec<u8> {
+    match key.code {
+        KeyCode::Char(c) if key.modifiers.contains(KeyModifiers::CONTROL) => vec![(c as u8) & 0x1f],
+        KeyCode::Char(c) => c.to_string().into_bytes(),
+        KeyCode::Enter => vec![b'\r'],
+        KeyCode::Backspace => vec![127],
+        KeyCode::Tab => vec![b'\t'],
+        KeyCode::Esc => vec![27],
+        KeyCode::Up => vec![27, b'[', b'A'],
+        KeyCode::Down => vec![27, b'[', b'B'],
+        KeyCode::Right => vec![27, b'[', b'C'],
+        KeyCode::Left => vec![27, b'[', b'D'],
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        // Small sleep to avoid busy-waiting
-        std::thread::sleep(std::time::Duration::from_millis(5));
+    #[test]
+    fn panel_waits_for_split_sequences_and_characters() {
+        assert!(at_boundary(b"plain text"));
+        assert!(at_boundary(b"\x1b[2J\x1b[1;1Hdone"));
+        assert!(at_boundary(b"\x1b]2;title\x07"));
+        assert!(at_boundary("caf\u{e9}".as_bytes()));
+        assert!(!at_boundary(b"text\x1b"));
+        assert!(!at_boundary(b"text\x1b[1;3"));
+        assert!(!at_boundary(b"\x1b]2;tit"));
+        assert!(!at_boundary(&"caf\u{e9}".as_bytes()[..4]));
+        assert!(!at_boundary(&"\u{2500}".as_bytes()[..2]));
     }
 }
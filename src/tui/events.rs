@@ -9,18 +9,18 @@ use super::App;
 /// Returns true if the app should continue running
 pub fn handle_events(app: &mut App) -> crate::Result<bool> {
     // Poll for events with a timeout (allows periodic status updates)
-    if event::poll(Duration::from_millis(100))? {
-        if let Event::Key(key) = event::read()? {
-            // Priority order: help overlay > spawn picker > confirmation > normal
-            if app.show_help {
-                handle_help_key(app, key)?;
-            } else if app.spawn_picker.is_some() {
-                handle_spawn_picker_key(app, key)?;
-            } else if app.has_pending_confirm() {
-                handle_confirm_key(app, key)?;
-            } else {
-                handle_symphony_key(app, key)?;
-            }
+    if event::poll(Duration::from_millis(100))?
+        && let Event::Key(key) = event::read()?
+    {
+        // Priority order: help overlay > spawn picker > confirmation > normal
+        if app.show_help {
+            handle_help_key(app, key)?;
+        } else if app.spawn_picker.is_some() {
+            handle_spawn_picker_key(app, key)?;
+        } else if app.has_pending_confirm() {
+            handle_confirm_key(app, key)?;
+        } else {
+            handle_symphony_key(app, key)?;
         }
     }
 
@@ -46,7 +46,29 @@ fn handle_help_key(app: &mut App, key: KeyEvent) -> crate::Result<()> {
 
 /// Handle keys when spawn picker is showing
 fn handle_spawn_picker_key(app: &mut App, key: KeyEvent) -> crate::Result<()> {
+    // Typing into the model field
+    if let Some(picker) = &mut app.spawn_picker
+        && picker.editing_model
+    {
+        match key.code {
+            KeyCode::Char(c) => {
+                picker.model.push(c);
+                return Ok(());
+            }
+            KeyCode::Backspace => {
+                picker.model.pop();
+                return Ok(());
+            }
+            _ => {}
+        }
+    }
+
     match key.code {
+        KeyCode::Tab => {
+            if let Some(picker) = &mut app.spawn_picker {
+                picker.editing_model = !picker.editing_model;
+            }
+        }
         KeyCode::Esc => {
             app.close_spawn_picker();
         }
@@ -114,7 +136,8 @@ fn handle_symphony_key(app: &mut App, key: KeyEvent) -> crate::Result<()> {
         KeyCode::Enter => {
             if let Some(session) = app.selected_session() {
                 if session.status == crate::daemon::SessionStatus::Running {
-                    match super::attach::attach_to_session(&mut app.sessions, &session.id) {
+                    let timeline = app.state.as_ref().filter(|_| app.show_timeline);
+                    match super::attach::attach_to_session(&mut app.sessions, &session.id, timeline) {
                         Ok(super::attach::AttachResult::Detached) => {
                             app.status_message = Some("Detached from session".to_string());
                         }
@@ -141,26 +164,44 @@ fn handle_symphony_key(app: &mut App, key: KeyEvent) -> crate::Result<()> {
             app.open_spawn_picker();
         }
 
-        // Kill selected (with confirmation)
+        // Kill selected or marked (with confirmation)
         KeyCode::Char('K') | KeyCode::Delete => {
             app.request_kill();
         }
 
-        // Nudge selected
+        // Nudge selected or marked
         KeyCode::Char('n') => {
-            if let Err(e) = app.nudge_selected() {
-                app.status_message = Some(format!("Nudge failed: {}", e));
-            }
+            app.nudge_selected();
+        }
+
+        // Toggle the selected agent's activity timeline
+        KeyCode::Char('t') => {
+            app.toggle_timeline();
+        }
+
+        // Toggle the selected agent's conversation transcript
+        KeyCode::Char('T') => {
+            app.toggle_transcript();
         }
 
-        // Cleanup exited sessions
+        // Toggle the selected agent's screen preview
+        KeyCode::Char('p') => {
+            app.toggle_preview();
+        }
+
+        // Cleanup exited sessions (the marked ones, if any)
         KeyCode::Char('c') => {
-            let cleaned = app.sessions.cleanup();
-            if cleaned.is_empty() {
-                app.status_message = Some("No completed sessions to clean".to_string());
-            } else {
-                app.status_message = Some(format!("Cleaned {} session(s)", cleaned.len()));
-            }
+            app.cleanup_sessions();
+        }
+
+        // Mark selected for a batch kill, nudge or cleanup
+        KeyCode::Char(' ') => {
+            app.toggle_mark();
+        }
+
+        // Clear marks
+        KeyCode::Esc => {
+            app.clear_marks();
         }
 
         _ => {}
@@ -5,7 +5,7 @@
 //! - Attach: (WIP) direct PTY control of an agent
 
 mod app;
-mod attach;  // WIP - needs PTY refactor
+pub mod attach;
 mod events;
 mod render;
 
@@ -11,6 +11,14 @@ use ratatui::{
 use super::app::AGENT_TYPES;
 use super::App;
 use crate::daemon::SessionStatus;
+use crate::process::format_bytes;
+use crate::timeline::TimelineKind;
+use crate::transcript::TranscriptRole;
+use rembrandt_core::screen::{CellStyle, ScreenColor};
+
+/// CPU use past which a session's usage is highlighted: more than one core
+/// busy usually means a build or test run rather than the agent itself
+const BUSY_CPU_PERCENT: f64 = 100.0;
 
 /// Render the entire application
 pub fn render(frame: &mut Frame, app: &App) {
@@ -52,7 +60,33 @@ fn render_symphony(frame: &mut Frame, app: &App) {
         .block(Block::default().borders(Borders::NONE));
     frame.render_widget(header, chunks[0]);
 
-    // Session list
+    // Session list, with the screen preview below and the timeline panel
+    // beside it when toggled on
+    let list_area = if app.show_preview {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .split(chunks[1]);
+        render_preview(frame, app, rows[1]);
+        rows[0]
+    } else {
+        chunks[1]
+    };
+    let list_area = if app.show_timeline || app.show_transcript {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+            .split(list_area);
+        if app.show_transcript {
+            render_transcript(frame, app, columns[1]);
+        } else {
+            render_timeline(frame, app, columns[1]);
+        }
+        columns[0]
+    } else {
+        list_area
+    };
+
     let sessions = app.session_list();
     let total = sessions.len();
 
@@ -62,7 +96,7 @@ fn render_symphony(frame: &mut Frame, app: &App) {
             .block(Block::default()
                 .title(" Sessions ")
                 .borders(Borders::ALL));
-        frame.render_widget(empty, chunks[1]);
+        frame.render_widget(empty, list_area);
     } else {
         let now = chrono::Utc::now();
         let items: Vec<ListItem> = sessions
@@ -79,21 +113,58 @@ fn render_symphony(frame: &mut Frame, app: &App) {
                 };
 
                 let selected = if i == app.selected_index { "▶ " } else { "  " };
+                let mark = if app.marked.contains(&session.id) {
+                    Span::styled("◆ ", Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD))
+                } else {
+                    Span::raw("  ")
+                };
 
                 // Calculate age
                 let age = now.signed_duration_since(session.created_at);
                 let age_str = App::format_duration(age);
 
+                // Last path component of the repository, to tell projects apart
+                let repo_name = session
+                    .repo
+                    .as_ref()
+                    .and_then(|r| r.file_name())
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+
+                let broken = app.broken_builds.get(&session.agent_id).map(|age| {
+                    Span::styled(
+                        format!("build broken {}  ", App::format_duration(*age)),
+                        Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                    )
+                });
+
+                let usage = session.resources.map(|sample| {
+                    let style = if sample.cpu_percent > BUSY_CPU_PERCENT {
+                        Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+                    } else {
+                        Style::default().fg(Color::DarkGray)
+                    };
+                    Span::styled(
+                        format!("{:.0}% {}  ", sample.cpu_percent, format_bytes(sample.rss_bytes)),
+                        style,
+                    )
+                });
+
                 let line = Line::from(vec![
                     Span::raw(selected),
+                    mark,
                     Span::styled(icon, style),
                     Span::raw(" "),
                     Span::styled(&session.agent_id, Style::default().add_modifier(Modifier::BOLD)),
                     Span::raw("  "),
                     Span::styled(status_text, style),
                     Span::raw("  "),
+                    broken.unwrap_or_default(),
+                    usage.unwrap_or_default(),
                     Span::styled(&session.command, Style::default().fg(Color::DarkGray)),
                     Span::raw("  "),
+                    Span::styled(repo_name, Style::default().add_modifier(Modifier::DIM)),
+                    Span::raw("  "),
                     Span::styled(age_str, Style::default().fg(Color::Cyan)),
                 ]);
 
@@ -112,16 +183,163 @@ fn render_symphony(frame: &mut Frame, app: &App) {
 
         let mut state = ListState::default();
         state.select(Some(app.selected_index));
-        frame.render_stateful_widget(list, chunks[1], &mut state);
+        frame.render_stateful_widget(list, list_area, &mut state);
     }
 
     // Status bar
-    let status_text = app.status_message.as_deref().unwrap_or("Enter: attach │ s: spawn │ ?: help");
+    let hint = if app.marked.is_empty() {
+        "Enter: attach │ s: spawn │ ?: help".to_string()
+    } else {
+        format!("{} marked │ K: kill │ n: nudge │ c: clean │ Esc: clear", app.marked.len())
+    };
+    let status_text = app.status_message.as_deref().unwrap_or(&hint);
     let status = Paragraph::new(format!(" {} ", status_text))
         .style(Style::default().fg(Color::White).bg(Color::Blue));
     frame.render_widget(status, chunks[2]);
 }
 
+/// Render the selected agent's activity timeline, newest at the bottom
+fn render_timeline(frame: &mut Frame, app: &App, area: Rect) {
+    let title = match app.selected_session() {
+        Some(session) => format!(" Timeline: {} ", session.agent_id),
+        None => " Timeline ".to_string(),
+    };
+
+    // Only the most recent entries that fit inside the borders
+    let visible = area.height.saturating_sub(2) as usize;
+    let skip = app.timeline.len().saturating_sub(visible);
+    let items: Vec<ListItem> = app
+        .timeline
+        .iter()
+        .skip(skip)
+        .map(|entry| {
+            let kind_style = match entry.kind {
+                TimelineKind::Commit => Style::default().fg(Color::Green),
+                TimelineKind::Nudge | TimelineKind::Steer | TimelineKind::Message => {
+                    Style::default().fg(Color::Yellow)
+                }
+                TimelineKind::StatusChanged => Style::default().fg(Color::Magenta),
+                TimelineKind::Restarted => Style::default().fg(Color::Red),
+                _ => Style::default().fg(Color::Cyan),
+            };
+            let time = entry.created_at.with_timezone(&chrono::Local).format("%H:%M:%S");
+            ListItem::new(Line::from(vec![
+                Span::styled(time.to_string(), Style::default().fg(Color::DarkGray)),
+                Span::raw(" "),
+                Span::styled(entry.kind.as_str(), kind_style),
+                Span::raw(" "),
+                Span::raw(entry.detail.as_deref().unwrap_or("")),
+            ]))
+        })
+        .collect();
+
+    let block = Block::default().title(title).borders(Borders::ALL);
+    if items.is_empty() {
+        let empty = Paragraph::new("No activity recorded yet")
+            .style(Style::default().fg(Color::Gray))
+            .block(block);
+        frame.render_widget(empty, area);
+    } else {
+        frame.render_widget(List::new(items).block(block), area);
+    }
+}
+
+/// Render the selected agent's conversation, newest at the bottom
+fn render_transcript(frame: &mut Frame, app: &App, area: Rect) {
+    let title = match app.selected_session() {
+        Some(session) => format!(" Transcript: {} ", session.agent_id),
+        None => " Transcript ".to_string(),
+    };
+    let block = Block::default().title(title).borders(Borders::ALL);
+    if app.transcript.is_empty() {
+        let empty = Paragraph::new("No conversation found (Claude Code sessions only)")
+            .style(Style::default().fg(Color::Gray))
+            .block(block);
+        frame.render_widget(empty, area);
+        return;
+    }
+
+    let mut lines: Vec<Line> = Vec::new();
+    for entry in &app.transcript {
+        let (label, style) = match entry.role {
+            TranscriptRole::User => ("you", Style::default().fg(Color::Yellow)),
+            TranscriptRole::Assistant => ("claude", Style::default().fg(Color::Cyan)),
+            TranscriptRole::ToolUse => ("tool", Style::default().fg(Color::Magenta)),
+            TranscriptRole::ToolResult => ("result", Style::default().fg(Color::DarkGray)),
+        };
+        for (i, text) in entry.text.lines().enumerate() {
+            let label = if i == 0 { label } else { "" };
+            lines.push(Line::from(vec![
+                Span::styled(format!("{:>6} ", label), style),
+                Span::raw(text.to_string()),
+            ]));
+        }
+    }
+    // Only the most recent lines that fit inside the borders
+    let visible = area.height.saturating_sub(2) as usize;
+    let lines = lines.split_off(lines.len().saturating_sub(visible));
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+/// Render what the selected agent's terminal currently shows
+fn render_preview(frame: &mut Frame, app: &App, area: Rect) {
+    let selected = app.selected_session();
+    let title = match &selected {
+        Some(session) => format!(" Preview: {} ", session.agent_id),
+        None => " Preview ".to_string(),
+    };
+    let block = Block::default().title(title).borders(Borders::ALL);
+    let Some(screen) = selected.and_then(|s| app.sessions.get_screen(&s.id).ok()) else {
+        let empty = Paragraph::new("No agent selected")
+            .style(Style::default().fg(Color::Gray))
+            .block(block);
+        frame.render_widget(empty, area);
+        return;
+    };
+
+    // The bottom of the screen is where agents prompt and report progress
+    let content = screen.content_lines();
+    let visible = area.height.saturating_sub(2) as usize;
+    let lines: Vec<Line> = content[content.len().saturating_sub(visible)..]
+        .iter()
+        .map(|line| {
+            Line::from(
+                line.spans
+                    .iter()
+                    .map(|span| Span::styled(span.text.as_str(), screen_style(&span.style)))
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+}
+
+fn screen_style(style: &CellStyle) -> Style {
+    let color = |color: ScreenColor| match color {
+        ScreenColor::Default => None,
+        ScreenColor::Idx(i) => Some(Color::Indexed(i)),
+        ScreenColor::Rgb(r, g, b) => Some(Color::Rgb(r, g, b)),
+    };
+    let mut out = Style::default();
+    if let Some(fg) = color(style.fg) {
+        out = out.fg(fg);
+    }
+    if let Some(bg) = color(style.bg) {
+        out = out.bg(bg);
+    }
+    for (on, modifier) in [
+        (style.bold, Modifier::BOLD),
+        (style.italic, Modifier::ITALIC),
+        (style.underline, Modifier::UNDERLINED),
+        (style.inverse, Modifier::REVERSED),
+    ] {
+        if on {
+            out = out.add_modifier(modifier);
+        }
+    }
+    out
+}
+
 /// Render centered popup area
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
@@ -166,9 +384,14 @@ fn render_help_overlay(frame: &mut Frame, _app: &App) {
             Span::styled("Actions", Style::default().fg(Color::Yellow)),
         ]),
         Line::from("  s       Spawn new agent"),
-        Line::from("  n       Nudge selected agent"),
-        Line::from("  K/Del   Kill selected agent"),
-        Line::from("  c       Cleanup completed sessions"),
+        Line::from("  Space   Mark/unmark agent for a batch action"),
+        Line::from("  Esc     Clear marks"),
+        Line::from("  n       Nudge selected (or marked) agents"),
+        Line::from("  K/Del   Kill selected (or marked) agents"),
+        Line::from("  t       Toggle activity timeline (also when attached)"),
+        Line::from("  T       Toggle conversation transcript"),
+        Line::from("  p       Toggle screen preview"),
+        Line::from("  c       Cleanup completed (or marked) sessions"),
         Line::from(""),
         Line::from(vec![
             Span::styled("When Attached", Style::default().fg(Color::Cyan)),
@@ -211,10 +434,17 @@ fn render_spawn_picker(frame: &mut Frame, app: &App) {
     // Clear the area first
     frame.render_widget(Clear, area);
 
-    let items: Vec<ListItem> = AGENT_TYPES
+    let entries = AGENT_TYPES
         .iter()
+        .map(|(short, name)| (name.to_string(), short.to_string()))
+        .chain(picker.profiles.iter().map(|name| {
+            let agent = app.config.profiles.get(name).map(|p| p.agent.clone()).unwrap_or_default();
+            (format!("Profile: {}", name), agent)
+        }));
+
+    let items: Vec<ListItem> = entries
         .enumerate()
-        .map(|(i, (short, name))| {
+        .map(|(i, (name, detail))| {
             let selected = if i == picker.selected { "▶ " } else { "  " };
             let style = if i == picker.selected {
                 Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)
@@ -224,8 +454,8 @@ fn render_spawn_picker(frame: &mut Frame, app: &App) {
 
             let line = Line::from(vec![
                 Span::raw(selected),
-                Span::styled(*name, style),
-                Span::styled(format!(" ({})", short), Style::default().fg(Color::DarkGray)),
+                Span::styled(name, style),
+                Span::styled(format!(" ({})", detail), Style::default().fg(Color::DarkGray)),
             ]);
 
             ListItem::new(line)
@@ -239,5 +469,31 @@ fn render_spawn_picker(frame: &mut Frame, app: &App) {
             .style(Style::default().bg(Color::Black)))
         .style(Style::default().fg(Color::White).bg(Color::Black));
 
-    frame.render_widget(list, area);
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(3)])
+        .split(area);
+    frame.render_widget(list, rows[0]);
+
+    // Model field, below the list; Tab moves the cursor into it
+    let (text, text_style) = if picker.model.is_empty() && !picker.editing_model {
+        ("default (Tab to set)".to_string(), Style::default().fg(Color::DarkGray))
+    } else if picker.editing_model {
+        (format!("{}▏", picker.model), Style::default().fg(Color::White))
+    } else {
+        (picker.model.clone(), Style::default().fg(Color::White))
+    };
+    let border_style = if picker.editing_model {
+        Style::default().fg(Color::Green)
+    } else {
+        Style::default()
+    };
+    let model = Paragraph::new(Span::styled(text, text_style)).block(
+        Block::default()
+            .title(" Model ")
+            .borders(Borders::ALL)
+            .border_style(border_style)
+            .style(Style::default().bg(Color::Black)),
+    );
+    frame.render_widget(model, rows[1]);
 }
@@ -0,0 +1,178 @@
+//! Token and cost usage reported by agents.
+//!
+//! Agents that emit machine-readable usage (Claude Code with
+//! `--output-format stream-json`, `codex exec --json`) print one JSON object
+//! per line. `UsageScanner` reassembles lines from raw PTY chunks and picks out
+//! the usage events, which end up in the `usage` table: the daemon scans the
+//! output of the sessions it runs, and runtimes hand theirs to the
+//! orchestrator.
+
+use chrono::{DateTime, Local, Utc};
+use serde_json::Value;
+
+/// Usage reported by one agent event.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UsageSample {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Cost in USD, when the agent reports it
+    pub cost_usd: Option<f64>,
+    pub model: Option<String>,
+    /// Which parser produced the sample (`claude`, `codex`)
+    pub source: &'static str,
+}
+
+/// Cumulative usage over some set of samples.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct UsageTotals {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: f64,
+    pub samples: u64,
+}
+
+impl UsageTotals {
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens
+    }
+
+    /// Compact form for tables, e.g. `$0.42 12.3k tok`
+    pub fn short(&self) -> String {
+        format!("${:.2} {} tok", self.cost_usd, format_tokens(self.total_tokens()))
+    }
+}
+
+/// `1234` → `1.2k`, `2500000` → `2.5M`
+pub fn format_tokens(tokens: u64) -> String {
+    match tokens {
+        t if t >= 1_000_000 => format!("{:.1}M", t as f64 / 1_000_000.0),
+        t if t >= 1_000 => format!("{:.1}k", t as f64 / 1_000.0),
+        t => t.to_string(),
+    }
+}
+
+/// Local midnight, for "today" totals and per-day budgets
+pub fn start_of_today() -> DateTime<Utc> {
+    Local::now()
+        .date_naive()
+        .and_hms_opt(0, 0, 0)
+        .and_then(|t| t.and_local_timezone(Local).earliest())
+        .map(|t| t.with_timezone(&Utc))
+        .unwrap_or_else(Utc::now)
+}
+
+/// Parse one line of agent output into a usage sample, if it carries one.
+pub fn parse_usage_line(line: &str) -> Option<UsageSample> {
+    let line = line.trim();
+    if !line.starts_with('{') {
+        return None;
+    }
+    let value: Value = serde_json::from_str(line).ok()?;
+    parse_claude_event(&value).or_else(|| parse_codex_event(&value))
+}
+
+/// Claude Code stream-json: the final `result` event has the session totals
+/// (`total_cost_usd` plus `usage`). Per-message `assistant` usage is ignored
+/// so the result isn't counted twice.
+fn parse_claude_event(value: &Value) -> Option<UsageSample> {
+    if value.get("type")?.as_str()? != "result" {
+        return None;
+    }
+    let usage = value.get("usage")?;
+    Some(UsageSample {
+        input_tokens: token_field(usage, "input_tokens")
+            + token_field(usage, "cache_creation_input_tokens")
+            + token_field(usage, "cache_read_input_tokens"),
+        output_tokens: token_field(usage, "output_tokens"),
+        cost_usd: value
+            .get("total_cost_usd")
+            .or_else(|| value.get("cost_usd"))
+            .and_then(Value::as_f64),
+        model: value.get("model").and_then(Value::as_str).map(str::to_string),
+        source: "claude",
+    })
+}
+
+/// `codex exec --json`: each `turn.completed` event reports that turn's usage.
+fn parse_codex_event(value: &Value) -> Option<UsageSample> {
+    if value.get("type")?.as_str()? != "turn.completed" {
+        return None;
+    }
+    let usage = value.get("usage")?;
+    Some(UsageSample {
+        input_tokens: token_field(usage, "input_tokens"),
+        output_tokens: token_field(usage, "output_tokens"),
+        cost_usd: None,
+        model: None,
+        source: "codex",
+    })
+}
+
+fn token_field(usage: &Value, key: &str) -> u64 {
+    usage.get(key).and_then(Value::as_u64).unwrap_or(0)
+}
+
+/// Reassembles lines from PTY output chunks and extracts usage samples.
+#[derive(Debug, Default)]
+pub struct UsageScanner {
+    partial: Vec<u8>,
+}
+
+impl UsageScanner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feed a chunk of output; returns samples from every completed line.
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<UsageSample> {
+        self.partial.extend_from_slice(chunk);
+        let Some(last_newline) = self.partial.iter().rposition(|&b| b == b'\n') else {
+            return Vec::new();
+        };
+        let complete: Vec<u8> = self.partial.drain(..=last_newline).collect();
+        String::from_utf8_lossy(&complete)
+            .lines()
+            .filter_map(parse_usage_line)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_claude_result_and_codex_turns() {
+        let claude = r#"{"type":"result","subtype":"success","total_cost_usd":0.0421,"usage":{"input_tokens":10,"cache_read_input_tokens":1200,"output_tokens":350}}"#;
+        let sample = parse_usage_line(claude).unwrap();
+        assert_eq!(sample.input_tokens, 1210);
+        assert_eq!(sample.output_tokens, 350);
+        assert_eq!(sample.cost_usd, Some(0.0421));
+        assert_eq!(sample.source, "claude");
+
+        let codex = r#"{"type":"turn.completed","usage":{"input_tokens":900,"cached_input_tokens":0,"output_tokens":80}}"#;
+        let sample = parse_usage_line(codex).unwrap();
+        assert_eq!((sample.input_tokens, sample.output_tokens), (900, 80));
+        assert_eq!(sample.cost_usd, None);
+
+        assert!(parse_usage_line(r#"{"type":"assistant","message":{}}"#).is_none());
+        assert!(parse_usage_line("Compiling rembrandt").is_none());
+    }
+
+    #[test]
+    fn scanner_joins_lines_split_across_chunks() {
+        let mut scanner = UsageScanner::new();
+        assert!(scanner.feed(br#"{"type":"turn.completed","usa"#).is_empty());
+        let samples = scanner.feed(b"ge\":{\"input_tokens\":5,\"output_tokens\":7}}\r\nnext");
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].output_tokens, 7);
+        assert_eq!(scanner.partial, b"next");
+    }
+
+    #[test]
+    fn formats_token_counts() {
+        assert_eq!(format_tokens(999), "999");
+        assert_eq!(format_tokens(12_345), "12.3k");
+        assert_eq!(format_tokens(2_500_000), "2.5M");
+    }
+}
@@ -0,0 +1,411 @@
+//! Warm pool of idle agents.
+//!
+//! Starting an agent such as Claude Code takes seconds. With sizes set under
+//! `[pool]`, `rembrandt pool fill` starts that many agents of each type in
+//! the daemon with no task, each in a worktree of its own off the pool's
+//! base branch. A detached `spawn` that needs nothing a pooled agent lacks —
+//! the same agent type and base branch, no profile, model or extra
+//! environment — takes one instead of starting a new agent: the pooled
+//! worktree's branch is moved up to the base's current tip, the agent is
+//! recorded as a session, and the prompt is typed into it. The spawn then
+//! tops the pool up again. With no pooled agent to take, it starts one cold
+//! as before. Only detached spawns consult the pool; a foreground spawn, and
+//! spawns from the TUI, GUI or orchestrator, always start cold.
+//!
+//! A pooled agent keeps the id it was started under. It was started before
+//! its task was known, so `REMBRANDT_TASK_ID` is not in its environment.
+
+use crate::agent::AgentType;
+use crate::config::AppConfig;
+use crate::daemon::ipc::DaemonConnection;
+use crate::daemon::session::SessionStatus as PtyStatus;
+use crate::hooks::{HookContext, HookPoint};
+use crate::isolation::IsolationMode;
+use crate::state::StateStore;
+use crate::{RembrandtError, Result};
+use chrono::{DateTime, Utc};
+use git2::{Repository, StatusOptions};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// An idle agent waiting in the pool
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PooledAgent {
+    pub agent_id: String,
+    /// Canonical agent type, e.g. `claude-code`
+    pub agent_type: String,
+    /// Daemon session the agent runs in
+    pub session_id: String,
+    pub base_branch: String,
+    /// Commit of the base branch the worktree was created at
+    pub base_commit: String,
+    pub branch_name: String,
+    pub checkout_path: PathBuf,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Canonical name of an agent type as given on the command line or in
+/// `[pool]`, so `claude` and `claude-code` share a pool
+pub fn canonical_type(agent: &str) -> String {
+    AgentType::from_str(agent).to_string()
+}
+
+/// Start agents until every type in `[pool]` has its configured number
+/// waiting, after dropping pooled agents that are no longer running;
+/// returns the agents started
+pub fn fill(
+    repo_path: &Path,
+    config: &AppConfig,
+    store: &StateStore,
+    daemon: &mut DaemonConnection,
+) -> Result<Vec<PooledAgent>> {
+    prune(repo_path, config, store, daemon)?;
+    let pooled = store.pooled_agents()?;
+    let mut started = Vec::new();
+    for (agent, size) in &config.pool.agents {
+        let agent_type = canonical_type(agent);
+        let waiting = pooled
+            .iter()
+            .filter(|p| p.agent_type == agent_type && p.base_branch == config.pool.base_branch)
+            .count();
+        for _ in waiting..*size {
+            started.push(start(repo_path, config, store, daemon, agent)?);
+        }
+    }
+    Ok(started)
+}
+
+/// Take a running pooled agent of `agent` type whose worktree is off
+/// `base_branch`, moving its branch up to the base's current tip
+///
+/// None when the pool has no such agent; spawn then starts one cold.
+pub fn claim(
+    repo_path: &Path,
+    config: &AppConfig,
+    store: &StateStore,
+    daemon: &mut DaemonConnection,
+    agent: &str,
+    base_branch: &str,
+) -> Result<Option<PooledAgent>> {
+    let agent_type = canonical_type(agent);
+    let running = running_sessions(daemon)?;
+    for pooled in store.pooled_agents()? {
+        if pooled.agent_type != agent_type || pooled.base_branch != base_branch {
+            continue;
+        }
+        // Another spawn may have taken it between the read and here
+        if !store.remove_pooled(&pooled.agent_id)? {
+            continue;
+        }
+        if !running.contains(&pooled.session_id) {
+            discard(repo_path, config, daemon, &pooled);
+            continue;
+        }
+        match advance_checkout(repo_path, &pooled) {
+            Ok(()) => return Ok(Some(pooled)),
+            Err(e) => {
+                tracing::warn!(agent_id = %pooled.agent_id, error = %e, "Discarding pooled agent");
+                discard(repo_path, config, daemon, &pooled);
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Stop every pooled agent and remove its worktree; returns how many
+pub fn drain(
+    repo_path: &Path,
+    config: &AppConfig,
+    store: &StateStore,
+    daemon: &mut DaemonConnection,
+) -> Result<usize> {
+    let mut drained = 0;
+    for pooled in store.pooled_agents()? {
+        if store.remove_pooled(&pooled.agent_id)? {
+            discard(repo_path, config, daemon, &pooled);
+            drained += 1;
+        }
+    }
+    Ok(drained)
+}
+
+/// Drop pooled agents whose session has ended; returns how many
+fn prune(
+    repo_path: &Path,
+    config: &AppConfig,
+    store: &StateStore,
+    daemon: &mut DaemonConnection,
+) -> Result<usize> {
+    let running = running_sessions(daemon)?;
+    let mut pruned = 0;
+    for pooled in store.pooled_agents()? {
+        if !running.contains(&pooled.session_id) && store.remove_pooled(&pooled.agent_id)? {
+            discard(repo_path, config, daemon, &pooled);
+            pruned += 1;
+        }
+    }
+    Ok(pruned)
+}
+
+fn running_sessions(daemon: &mut DaemonConnection) -> Result<HashSet<String>> {
+    Ok(daemon
+        .list(None)?
+        .into_iter()
+        .filter(|s| s.status == PtyStatus::Running)
+        .map(|s| s.id)
+        .collect())
+}
+
+/// Prepare a worktree and start an idle agent in it
+fn start(
+    repo_path: &Path,
+    config: &AppConfig,
+    store: &StateStore,
+    daemon: &mut DaemonConnection,
+    agent: &str,
+) -> Result<PooledAgent> {
+    let suffix: String = (0..4).map(|_| format!("{:x}", rand::random::<u8>() % 16)).collect();
+    let agent_id = format!("{}-{}", agent, suffix);
+    let base_branch = &config.pool.base_branch;
+
+    let env = config.spawn_env(None, &[], &agent_id, &format!("rembrandt/{}", agent_id), None);
+    let hooks = |point: HookPoint, cwd: &Path| -> Result<()> {
+        let ctx = HookContext {
+            repo_path,
+            cwd,
+            agent_id: &agent_id,
+            env: &env,
+            store: Some(store),
+        };
+        crate::hooks::run(&config.hooks, point, &ctx)?;
+        Ok(())
+    };
+    hooks(HookPoint::PreSpawn, repo_path)?;
+    let workspace = crate::isolation::prepare_workspace(IsolationMode::Worktree, repo_path, &agent_id, base_branch)?;
+    if let Err(e) = hooks(HookPoint::PostWorktree, &workspace.checkout_path) {
+        crate::isolation::discard_workspace(&workspace);
+        return Err(e);
+    }
+    let base_commit = Repository::open(&workspace.checkout_path)?
+        .head()?
+        .peel_to_commit()?
+        .id()
+        .to_string();
+
+    let agent_type = AgentType::from_str(agent);
+    let args = agent_type.default_args().iter().map(|a| a.to_string()).collect();
+    let session_id = daemon.spawn(
+        agent_id.clone(),
+        agent_type.command().to_string(),
+        args,
+        env.clone(),
+        workspace.checkout_path.clone(),
+        None,
+        None,
+        config.buffer_capacity,
+        config.restart,
+    )?;
+    let pooled = PooledAgent {
+        agent_id: agent_id.clone(),
+        agent_type: agent_type.to_string(),
+        session_id,
+        base_branch: base_branch.clone(),
+        base_commit,
+        branch_name: workspace.branch_name,
+        checkout_path: workspace.checkout_path,
+        created_at: Utc::now(),
+    };
+    store.add_pooled(&pooled)?;
+    tracing::debug!(agent_id = %agent_id, session_id = %pooled.session_id, "Started pooled agent");
+    Ok(pooled)
+}
+
+/// Move a pooled worktree's branch to the current tip of its base
+///
+/// Fails if the idle agent committed or changed tracked files, since it
+/// would then not start from a clean base.
+fn advance_checkout(repo_path: &Path, pooled: &PooledAgent) -> Result<()> {
+    let checkout = Repository::open(&pooled.checkout_path)?;
+    let head = checkout.head()?.peel_to_commit()?.id().to_string();
+    if head != pooled.base_commit {
+        return Err(RembrandtError::Worktree(format!(
+            "{} moved from {} while pooled",
+            pooled.branch_name, pooled.base_commit
+        )));
+    }
+    let changed = checkout.statuses(Some(StatusOptions::new().include_untracked(false)))?;
+    if !changed.is_empty() {
+        return Err(RembrandtError::Worktree(format!(
+            "{} has {} changed file(s) while pooled",
+            pooled.checkout_path.display(),
+            changed.len()
+        )));
+    }
+    drop(changed);
+
+    let repo = Repository::open(repo_path)?;
+    let tip = repo
+        .find_branch(&pooled.base_branch, git2::BranchType::Local)?
+        .get()
+        .peel_to_commit()?;
+    let tip = checkout.find_commit(tip.id())?;
+    checkout.reset(tip.as_object(), git2::ResetType::Hard, None)?;
+    Ok(())
+}
+
+/// Kill a pooled agent and remove its worktree and branch, logging failures
+fn discard(repo_path: &Path, config: &AppConfig, daemon: &mut DaemonConnection, pooled: &PooledAgent) {
+    // Already gone if its session ended
+    let _ = daemon.kill(&pooled.session_id);
+    let removed = config.worktree.manager(repo_path).and_then(|manager| {
+        manager.remove_worktree(&pooled.agent_id)?;
+        manager.delete_branch(&pooled.branch_name)
+    });
+    if let Err(e) = removed {
+        tracing::warn!(agent_id = %pooled.agent_id, error = %e, "Could not remove pooled agent's worktree");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::restart::RestartPolicy;
+    use crate::daemon::Daemon;
+    use crate::test_support::{git, init_repo};
+    use std::sync::Arc;
+
+    fn commit(repo: &Repository, message: &str) -> git2::Oid {
+        let sig = git2::Signature::now("test", "test@example.com").unwrap();
+        let tree_id = repo.index().unwrap().write_tree().unwrap();
+        let tree = repo.find_tree(tree_id).unwrap();
+        let parents: Vec<git2::Commit> = repo.head().ok().and_then(|h| h.peel_to_commit().ok()).into_iter().collect();
+        let parents: Vec<&git2::Commit> = parents.iter().collect();
+        repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &parents).unwrap()
+    }
+
+    #[test]
+    fn claimed_checkouts_move_to_the_base_tip() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = init_repo(dir.path());
+        let first = repo.head().unwrap().target().unwrap();
+        let store = StateStore::open(dir.path()).unwrap();
+
+        let workspace =
+            crate::isolation::prepare_workspace(IsolationMode::Worktree, dir.path(), "claude-0a0a", "main").unwrap();
+        let pooled = PooledAgent {
+            agent_id: "claude-0a0a".to_string(),
+            agent_type: canonical_type("claude"),
+            session_id: "s-1".to_string(),
+            base_branch: "main".to_string(),
+            base_commit: first.to_string(),
+            branch_name: workspace.branch_name.clone(),
+            checkout_path: workspace.checkout_path.clone(),
+            created_at: Utc::now(),
+        };
+        store.add_pooled(&pooled).unwrap();
+        assert_eq!(store.pooled_agents().unwrap(), vec![pooled.clone()]);
+        assert_eq!(pooled.agent_type, canonical_type("claude-code"));
+
+        // main moves on while the agent waits
+        let second = commit(&repo, "second");
+        advance_checkout(dir.path(), &pooled).unwrap();
+        let checkout = Repository::open(&workspace.checkout_path).unwrap();
+        assert_eq!(checkout.head().unwrap().peel_to_commit().unwrap().id(), second);
+        assert_eq!(checkout.head().unwrap().shorthand(), Some("rembrandt/claude-0a0a"));
+
+        // An agent that already did work isn't handed out
+        assert!(advance_checkout(dir.path(), &pooled).is_err());
+
+        assert!(store.remove_pooled("claude-0a0a").unwrap());
+        assert!(!store.remove_pooled("claude-0a0a").unwrap());
+        assert!(store.pooled_agents().unwrap().is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn dead_or_dirty_agents_are_discarded_instead_of_claimed() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo_path = dir.path().join("repo");
+        std::fs::create_dir(&repo_path).unwrap();
+        init_repo(&repo_path);
+        std::fs::write(repo_path.join("README.md"), "hello\n").unwrap();
+        git(&repo_path, &["add", "README.md"]);
+        git(&repo_path, &["commit", "-qm", "readme"]);
+        let socket = dir.path().join("daemon.sock");
+        let daemon = Arc::new(Daemon::new(socket.clone()));
+        let server = {
+            let daemon = daemon.clone();
+            tokio::spawn(async move { daemon.run().await })
+        };
+
+        tokio::task::spawn_blocking(move || {
+            let mut conn = (0..100)
+                .find_map(|_| {
+                    DaemonConnection::connect(&socket)
+                        .inspect_err(|_| std::thread::sleep(std::time::Duration::from_millis(10)))
+                        .ok()
+                })
+                .expect("daemon never came up");
+            let config = AppConfig::default();
+            let store = StateStore::open(&repo_path).unwrap();
+            let mut pool = Vec::new();
+            for agent_id in ["claude-dead", "claude-busy"] {
+                let workspace =
+                    crate::isolation::prepare_workspace(IsolationMode::Worktree, &repo_path, agent_id, "main").unwrap();
+                let base_commit = git(&workspace.checkout_path, &["rev-parse", "HEAD"]).trim().to_string();
+                let session_id = if agent_id == "claude-dead" {
+                    "s-ended".to_string()
+                } else {
+                    conn.spawn(
+                        agent_id.to_string(),
+                        "sleep".to_string(),
+                        vec!["30".to_string()],
+                        Vec::new(),
+                        workspace.checkout_path.clone(),
+                        None,
+                        None,
+                        None,
+                        RestartPolicy::Never,
+                    )
+                    .unwrap()
+                };
+                let pooled = PooledAgent {
+                    agent_id: agent_id.to_string(),
+                    agent_type: canonical_type("claude"),
+                    session_id,
+                    base_branch: "main".to_string(),
+                    base_commit,
+                    branch_name: workspace.branch_name,
+                    checkout_path: workspace.checkout_path,
+                    created_at: Utc::now(),
+                };
+                store.add_pooled(&pooled).unwrap();
+                pool.push(pooled);
+            }
+            // The idle agent touched a tracked file
+            std::fs::write(pool[1].checkout_path.join("README.md"), "edited\n").unwrap();
+
+            assert!(claim(&repo_path, &config, &store, &mut conn, "claude", "main").unwrap().is_none());
+            assert!(store.pooled_agents().unwrap().is_empty());
+            let repo = Repository::open(&repo_path).unwrap();
+            for pooled in &pool {
+                assert!(!pooled.checkout_path.exists(), "{}", pooled.checkout_path.display());
+                assert!(repo.find_branch(&pooled.branch_name, git2::BranchType::Local).is_err());
+            }
+            assert!(running_sessions(&mut conn).unwrap().is_empty(), "the busy agent was killed");
+
+            // A pooled agent whose worktree hook fails leaves nothing behind
+            let mut config = config;
+            config.pool.agents.insert("claude".to_string(), 1);
+            config.hooks.post_worktree = vec!["exit 3".to_string()];
+            assert!(fill(&repo_path, &config, &store, &mut conn).is_err());
+            assert!(store.pooled_agents().unwrap().is_empty());
+            assert_eq!(repo.worktrees().unwrap().len(), 0);
+
+            conn.request(&crate::daemon::ipc::DaemonCommand::Shutdown).unwrap();
+        })
+        .await
+        .unwrap();
+
+        server.await.unwrap().unwrap();
+    }
+}
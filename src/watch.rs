@@ -0,0 +1,319 @@
+//! Headless monitoring for `rembrandt watch`.
+//!
+//! Sessions report to two places: state.db (the v2 session table, its
+//! timeline and attention items) and the daemon (PTY sessions). A
+//! `WatchFeed` polls both and turns what changed since the last poll into
+//! `WatchEvent`s for `--stream`; a `Snapshot` is the compact table the
+//! default mode redraws. Either way the exit code says whether any agent
+//! failed or needs attention, so CI can gate on it.
+
+use crate::daemon::manager::SessionInfo;
+use crate::daemon::session::SessionStatus as PtyStatus;
+use crate::state::{AttentionItem, SessionStatus, StateStore};
+use crate::timeline::TimelineEntry;
+use crate::Result;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+/// Exit code when no agent failed or needs attention
+pub const EXIT_OK: u8 = 0;
+/// Exit code when an attention item is open but no agent failed
+pub const EXIT_ATTENTION: u8 = 2;
+/// Exit code when an agent failed
+pub const EXIT_FAILED: u8 = 3;
+
+/// Most timeline entries handed over by one poll
+const TIMELINE_BATCH: usize = 500;
+
+/// One agent in the status table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchRow {
+    pub agent_id: String,
+    /// `state` for state.db sessions, `pty` for daemon sessions
+    pub source: &'static str,
+    pub status: String,
+    pub active: bool,
+    pub failed: bool,
+    pub task_id: Option<String>,
+    pub since: DateTime<Utc>,
+}
+
+impl WatchRow {
+    fn from_pty(info: &SessionInfo) -> Self {
+        Self {
+            agent_id: info.agent_id.clone(),
+            source: "pty",
+            status: pty_status(&info.status),
+            active: info.status == PtyStatus::Running,
+            failed: matches!(info.status, PtyStatus::Exited(code) if code != 0)
+                || matches!(info.status, PtyStatus::Failed(_)),
+            task_id: None,
+            since: info.created_at,
+        }
+    }
+}
+
+/// Daemon session status in the words `rembrandt list` uses
+fn pty_status(status: &PtyStatus) -> String {
+    match status {
+        PtyStatus::Running => "active".to_string(),
+        PtyStatus::Exited(0) => "done".to_string(),
+        PtyStatus::Exited(code) => format!("failed ({})", code),
+        PtyStatus::Failed(_) => "error".to_string(),
+    }
+}
+
+/// Something that changed since the previous poll
+#[derive(Debug, Clone)]
+pub enum WatchEvent {
+    Timeline(TimelineEntry),
+    Attention(AttentionItem),
+    Pty { agent_id: String, status: PtyStatus },
+}
+
+impl WatchEvent {
+    /// `HH:MM:SS agent kind detail`, one line per event
+    pub fn line(&self, now: DateTime<Utc>) -> String {
+        match self {
+            WatchEvent::Timeline(entry) => format!(
+                "{} {} {} {}",
+                entry.created_at.format("%H:%M:%S"),
+                entry.agent_id,
+                entry.kind,
+                entry.detail.as_deref().unwrap_or("")
+            )
+            .trim_end()
+            .to_string(),
+            WatchEvent::Attention(item) => format!(
+                "{} {} attention {}: {}",
+                item.created_at.format("%H:%M:%S"),
+                item.agent_id.as_deref().unwrap_or("-"),
+                item.kind,
+                item.message
+            ),
+            WatchEvent::Pty { agent_id, status } => {
+                format!("{} {} pty {}", now.format("%H:%M:%S"), agent_id, pty_status(status))
+            }
+        }
+    }
+}
+
+/// Polls state.db and the daemon's session list for changes
+#[derive(Debug, Default)]
+pub struct WatchFeed {
+    last_timeline: i64,
+    last_attention: i64,
+    pty: HashMap<String, PtyStatus>,
+}
+
+impl WatchFeed {
+    /// A feed that reports only what happens from now on
+    pub fn starting_now(store: &StateStore, pty_sessions: &[SessionInfo]) -> Result<Self> {
+        let mut feed = Self {
+            last_timeline: store.last_timeline_id()?,
+            ..Self::default()
+        };
+        feed.last_attention = store.open_attention()?.iter().map(|a| a.id).max().unwrap_or(0);
+        feed.pty = pty_sessions
+            .iter()
+            .map(|s| (s.id.clone(), s.status.clone()))
+            .collect();
+        Ok(feed)
+    }
+
+    /// Events since the previous poll, oldest first within each source
+    pub fn poll(&mut self, store: &StateStore, pty_sessions: &[SessionInfo]) -> Result<Vec<WatchEvent>> {
+        let mut events = Vec::new();
+
+        for entry in store.timeline_after(self.last_timeline, TIMELINE_BATCH)? {
+            self.last_timeline = entry.id;
+            events.push(WatchEvent::Timeline(entry));
+        }
+
+        for item in store.open_attention()? {
+            if item.id > self.last_attention {
+                self.last_attention = item.id;
+                events.push(WatchEvent::Attention(item));
+            }
+        }
+
+        for session in pty_sessions {
+            if self.pty.get(&session.id) != Some(&session.status) {
+                self.pty.insert(session.id.clone(), session.status.clone());
+                events.push(WatchEvent::Pty {
+                    agent_id: session.agent_id.clone(),
+                    status: session.status.clone(),
+                });
+            }
+        }
+
+        Ok(events)
+    }
+}
+
+/// Every agent's current status, plus open attention items
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    pub rows: Vec<WatchRow>,
+    pub attention: Vec<AttentionItem>,
+}
+
+impl Snapshot {
+    pub fn collect(store: &StateStore, pty_sessions: &[SessionInfo]) -> Result<Self> {
+        let mut rows: Vec<WatchRow> = store
+            .list_sessions()?
+            .into_iter()
+            .map(|session| WatchRow {
+                active: session.status.is_active(),
+                failed: session.status == SessionStatus::Failed,
+                status: session.status.to_string(),
+                agent_id: session.agent_id,
+                source: "state",
+                task_id: session.task_id,
+                since: session.updated_at,
+            })
+            .collect();
+        rows.extend(pty_sessions.iter().map(WatchRow::from_pty));
+        rows.sort_by(|a, b| a.agent_id.cmp(&b.agent_id).then(a.source.cmp(b.source)));
+        Ok(Self {
+            rows,
+            attention: store.open_attention()?,
+        })
+    }
+
+    pub fn has_active(&self) -> bool {
+        self.rows.iter().any(|row| row.active)
+    }
+
+    /// `EXIT_FAILED`, `EXIT_ATTENTION` or `EXIT_OK`, in that order of precedence
+    pub fn exit_code(&self) -> u8 {
+        if self.rows.iter().any(|row| row.failed) {
+            EXIT_FAILED
+        } else if !self.attention.is_empty() {
+            EXIT_ATTENTION
+        } else {
+            EXIT_OK
+        }
+    }
+
+    /// Whether any status, task or attention item differs from `previous`;
+    /// ages don't count
+    pub fn changed_since(&self, previous: &Snapshot) -> bool {
+        let key = |s: &Snapshot| {
+            let rows: Vec<_> = s
+                .rows
+                .iter()
+                .map(|row| (row.agent_id.clone(), row.source, row.status.clone(), row.task_id.clone()))
+                .collect();
+            let attention: Vec<i64> = s.attention.iter().map(|item| item.id).collect();
+            (rows, attention)
+        };
+        key(self) != key(previous)
+    }
+
+    /// Fixed-width table with a tally line, sized for a narrow pane
+    pub fn table(&self, now: DateTime<Utc>) -> String {
+        let width = self
+            .rows
+            .iter()
+            .map(|row| row.agent_id.len())
+            .max()
+            .unwrap_or(0)
+            .max("AGENT".len());
+        let mut out = format!("{:<width$}  {:<5}  {:<18}  {:>6}  TASK\n", "AGENT", "SRC", "STATUS", "AGE");
+        for row in &self.rows {
+            out.push_str(&format!(
+                "{:<width$}  {:<5}  {:<18}  {:>6}  {}\n",
+                row.agent_id,
+                row.source,
+                row.status,
+                age(now, row.since),
+                row.task_id.as_deref().unwrap_or("-"),
+            ));
+        }
+        let active = self.rows.iter().filter(|row| row.active).count();
+        let failed = self.rows.iter().filter(|row| row.failed).count();
+        out.push_str(&format!(
+            "{} agents: {} active, {} failed, {} need attention\n",
+            self.rows.len(),
+            active,
+            failed,
+            self.attention.len()
+        ));
+        for item in &self.attention {
+            out.push_str(&format!(
+                "  ! {} {}: {}\n",
+                item.agent_id.as_deref().unwrap_or("-"),
+                item.kind,
+                item.message
+            ));
+        }
+        out
+    }
+}
+
+/// Compact elapsed time: `45s`, `12m`, `3h`, `2d`
+fn age(now: DateTime<Utc>, since: DateTime<Utc>) -> String {
+    let secs = now.signed_duration_since(since).num_seconds().max(0);
+    match secs {
+        0..60 => format!("{}s", secs),
+        60..3600 => format!("{}m", secs / 60),
+        3600..86400 => format!("{}h", secs / 3600),
+        _ => format!("{}d", secs / 86400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::session_record;
+    use crate::isolation::IsolationMode;
+    use crate::state::SessionRecord;
+    use crate::timeline::TimelineKind;
+
+    fn record(agent_id: &str, status: SessionStatus) -> SessionRecord {
+        SessionRecord {
+            isolation_mode: IsolationMode::Worktree,
+            task_id: Some("bd-7".to_string()),
+            status,
+            ..session_record(agent_id)
+        }
+    }
+
+    #[test]
+    fn feed_reports_new_events_and_exit_code_tracks_failures() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = StateStore::open(dir.path()).unwrap();
+        store.upsert_session(&record("a1", SessionStatus::Active)).unwrap();
+        store.record_timeline("a1", TimelineKind::Spawned, Some("pi")).unwrap();
+
+        let mut feed = WatchFeed::starting_now(&store, &[]).unwrap();
+        assert!(feed.poll(&store, &[]).unwrap().is_empty());
+        let snapshot = Snapshot::collect(&store, &[]).unwrap();
+        assert!(snapshot.has_active());
+        assert_eq!(snapshot.exit_code(), EXIT_OK);
+
+        store.raise_attention(Some("a1"), "blocked", "waiting on input").unwrap();
+        store.update_status("a1", SessionStatus::Failed).unwrap();
+        let lines: Vec<String> = feed
+            .poll(&store, &[])
+            .unwrap()
+            .iter()
+            .map(|event| event.line(Utc::now()))
+            .collect();
+        assert_eq!(lines.len(), 2, "{:?}", lines);
+        assert!(lines[0].ends_with("a1 status_changed active -> failed"), "{:?}", lines);
+        assert!(lines[1].ends_with("a1 attention blocked: waiting on input"), "{:?}", lines);
+        assert!(feed.poll(&store, &[]).unwrap().is_empty());
+
+        let after = Snapshot::collect(&store, &[]).unwrap();
+        assert!(after.changed_since(&snapshot));
+        assert!(!after.changed_since(&after.clone()));
+        let snapshot = after;
+        assert!(!snapshot.has_active());
+        assert_eq!(snapshot.exit_code(), EXIT_FAILED);
+        let table = snapshot.table(Utc::now());
+        assert!(table.contains("a1     state  failed"), "{}", table);
+        assert!(table.contains("1 agents: 0 active, 1 failed, 1 need attention"), "{}", table);
+    }
+}
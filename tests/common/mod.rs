@@ -0,0 +1,67 @@
+//! Shared setup for flows driven by `rembrandt fake-agent`.
+
+#![allow(dead_code)]
+
+use git2::{Repository, Signature};
+use rembrandt::daemon::manager::SessionManager;
+use rembrandt::daemon::session::SessionStatus;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+/// The `rembrandt` binary under test; run it with `fake-agent` first
+pub const REMBRANDT: &str = env!("CARGO_BIN_EXE_rembrandt");
+
+/// Arguments that run the fake agent with `steps`
+pub fn fake_agent_args<'a>(steps: &[&'a str]) -> Vec<&'a str> {
+    let mut args = vec!["fake-agent"];
+    args.extend_from_slice(steps);
+    args
+}
+
+/// A repository with one commit on `main`
+pub fn init_repo() -> TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    let repo = Repository::init(dir.path()).unwrap();
+    std::fs::write(dir.path().join("README.md"), "fixture\n").unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new("README.md")).unwrap();
+    index.write().unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let sig = Signature::now("test", "test@example.com").unwrap();
+    repo.commit(Some("refs/heads/main"), &sig, &sig, "init", &tree, &[]).unwrap();
+    repo.set_head("refs/heads/main").unwrap();
+    dir
+}
+
+/// Contents of `path` at the tip of `branch`, if it exists there
+pub fn file_on_branch(repo: &Path, branch: &str, path: &str) -> Option<String> {
+    let repo = Repository::open(repo).unwrap();
+    let tree = repo
+        .find_branch(branch, git2::BranchType::Local)
+        .unwrap()
+        .get()
+        .peel_to_tree()
+        .unwrap();
+    let entry = tree.get_path(Path::new(path)).ok()?;
+    let blob = repo.find_blob(entry.id()).unwrap();
+    Some(String::from_utf8_lossy(blob.content()).into_owned())
+}
+
+/// Poll `id` until it stops running
+pub fn wait_for_exit(manager: &mut SessionManager, id: &str) -> SessionStatus {
+    let deadline = Instant::now() + Duration::from_secs(30);
+    loop {
+        let status = manager.get_mut(id).unwrap().poll();
+        if status != SessionStatus::Running {
+            return status;
+        }
+        assert!(Instant::now() < deadline, "fake agent {} did not exit in time", id);
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+/// Everything `id` has printed so far
+pub fn output(manager: &SessionManager, id: &str) -> String {
+    String::from_utf8_lossy(&manager.get(id).unwrap().read_output_raw()).into_owned()
+}
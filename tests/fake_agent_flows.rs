@@ -0,0 +1,301 @@
+//! Spawn, attach, merge and competition flows with `rembrandt fake-agent`
+//! standing in for a real agent CLI.
+
+mod common;
+
+use common::{fake_agent_args, file_on_branch, init_repo, output, wait_for_exit, REMBRANDT};
+use rembrandt::agent::{AgentRegistry, AgentStatus, AgentType};
+use rembrandt::competition::{CompetitionManager, CompetitionStatus, EvaluatorStrategy};
+use rembrandt::daemon::ipc::{DaemonCommand, DaemonConnection, DaemonEvent};
+use rembrandt::daemon::manager::SessionManager;
+use rembrandt::daemon::session::SessionStatus;
+use rembrandt::daemon::{Daemon, ShutdownManifest};
+use rembrandt::restart::RestartPolicy;
+use rembrandt::state::{SessionRecord, StateStore};
+use rembrandt::worktree::WorktreeManager;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+#[test]
+fn spawned_agent_commits_and_its_branch_merges() {
+    let repo = init_repo();
+    let worktrees = WorktreeManager::new(repo.path()).unwrap();
+    let worktree = worktrees.create_worktree("fake-1", "main").unwrap();
+
+    let mut manager = SessionManager::new();
+    let id = manager
+        .spawn(
+            "fake-1".to_string(),
+            REMBRANDT,
+            &fake_agent_args(&["say:working on it", "commit:result.txt=done", "exit:0"]),
+            &worktree.path,
+        )
+        .unwrap();
+    assert_eq!(wait_for_exit(&mut manager, &id), SessionStatus::Exited(0));
+    let printed = output(&manager, &id);
+    assert!(printed.contains("working on it") && printed.contains("committed result.txt"), "{}", printed);
+
+    assert_eq!(file_on_branch(repo.path(), "main", "result.txt"), None);
+    worktrees.merge_branch(&worktree.branch, "main").unwrap();
+    assert_eq!(file_on_branch(repo.path(), "main", "result.txt").as_deref(), Some("done"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn attached_client_sees_output_input_and_exit_code() {
+    let repo = init_repo();
+    let socket = repo.path().join("daemon.sock");
+    let daemon = Arc::new(Daemon::new(socket.clone()));
+    let server = {
+        let daemon = daemon.clone();
+        tokio::spawn(async move { daemon.run().await })
+    };
+
+    let workdir = repo.path().to_path_buf();
+    let (printed, code) = tokio::task::spawn_blocking(move || {
+        let connect = || {
+            for _ in 0..100 {
+                if let Ok(conn) = DaemonConnection::connect(&socket) {
+                    return conn;
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            panic!("daemon never came up");
+        };
+        let mut control = connect();
+        let session_id = control
+            .spawn(
+                "fake-attach".to_string(),
+                REMBRANDT.to_string(),
+                vec!["fake-agent".to_string()],
+                vec![("REMBRANDT_FAKE_SCRIPT".to_string(), "say:ready;read;exit:3".to_string())],
+                workdir,
+                None,
+                None,
+                None,
+                RestartPolicy::Never,
+            )
+            .unwrap();
+
+        let mut attached = connect();
+        let (history, _) = attached.attach(&session_id).unwrap();
+        let mut printed = String::from_utf8_lossy(&history).into_owned();
+        let mut sent = false;
+        let code = loop {
+            if !sent && printed.contains("ready") {
+                control.write(&session_id, b"hello\r".to_vec()).unwrap();
+                sent = true;
+            }
+            match attached.next_event().unwrap() {
+                Some(DaemonEvent::Output { data, .. }) => printed.push_str(&String::from_utf8_lossy(&data)),
+                Some(DaemonEvent::Exited { code, .. }) => break code,
+                Some(DaemonEvent::StatusChanged { .. }) => {}
+                None => panic!("daemon closed the attachment before the agent exited"),
+            }
+        };
+        control.request(&DaemonCommand::Shutdown).unwrap();
+        (printed, code)
+    })
+    .await
+    .unwrap();
+    server.await.unwrap().unwrap();
+
+    assert!(printed.contains("got: hello"), "{}", printed);
+    assert_eq!(code, 3);
+}
+
+#[tokio::test]
+async fn competition_merges_the_agent_that_succeeded() {
+    let repo = init_repo();
+    let mut competitions = CompetitionManager::new(repo.path().to_path_buf(), "main".to_string()).unwrap();
+    let mut registry = AgentRegistry::new();
+    let id = competitions
+        .start_competition(
+            "add a result".to_string(),
+            vec![AgentType::Custom("good".to_string()), AgentType::Custom("bad".to_string())],
+            EvaluatorStrategy::default(),
+            60,
+            &mut registry,
+        )
+        .await
+        .unwrap();
+
+    let mut sessions = SessionManager::new();
+    let competitors = competitions.get_competition(&id).unwrap().competitors.clone();
+    for competitor in &competitors {
+        let steps: &[&str] = if competitor.agent_id.ends_with("-good") {
+            &["commit:result.txt=from good", "exit:0"]
+        } else {
+            &["say:giving up", "exit:1"]
+        };
+        let session = sessions
+            .spawn(competitor.agent_id.clone(), REMBRANDT, &fake_agent_args(steps), &competitor.worktree_path)
+            .unwrap();
+        let status = match wait_for_exit(&mut sessions, &session) {
+            SessionStatus::Exited(0) => AgentStatus::Completed,
+            other => AgentStatus::Failed(format!("{:?}", other)),
+        };
+        registry.update_status(&competitor.agent_id, status).unwrap();
+    }
+
+    let mut status = competitions.update_competition(&id, &mut registry).await.unwrap();
+    while status != CompetitionStatus::Merging {
+        assert!(
+            matches!(status, CompetitionStatus::Running { .. } | CompetitionStatus::Evaluating),
+            "{:?}",
+            status
+        );
+        status = competitions.update_competition(&id, &mut registry).await.unwrap();
+    }
+    let winner = competitions.get_competition(&id).unwrap().winner.clone().unwrap();
+    assert!(winner.ends_with("-good"), "{}", winner);
+
+    competitions.merge_winner(&id).unwrap();
+    assert_eq!(file_on_branch(repo.path(), "main", "result.txt").as_deref(), Some("from good"));
+}
+
+#[test]
+fn detached_agents_outlive_the_daemon() {
+    let repo = init_repo();
+    std::fs::create_dir_all(repo.path().join(".rembrandt")).unwrap();
+    std::fs::write(repo.path().join(".rembrandt/config.toml"), "shutdown_policy = \"detach\"\n").unwrap();
+    let socket = repo.path().join("daemon.sock");
+    // A daemon process of its own, since its exit is what hangs terminals up
+    let mut daemon = std::process::Command::new(REMBRANDT)
+        .arg("--repo")
+        .arg(repo.path())
+        .args(["daemon", "--socket"])
+        .arg(&socket)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let mut conn = (0..500)
+        .find_map(|_| {
+            std::thread::sleep(Duration::from_millis(10));
+            DaemonConnection::connect(&socket).ok()
+        })
+        .expect("daemon never came up");
+    conn.spawn(
+        "fake-detach".to_string(),
+        REMBRANDT.to_string(),
+        vec!["fake-agent".to_string()],
+        vec![(
+            "REMBRANDT_FAKE_SCRIPT".to_string(),
+            "sleep:500;say:after the daemon;sleep:30000".to_string(),
+        )],
+        repo.path().to_path_buf(),
+        None,
+        None,
+        None,
+        RestartPolicy::Never,
+    )
+    .unwrap();
+    conn.request(&DaemonCommand::Shutdown).unwrap();
+    assert!(daemon.wait().unwrap().success());
+
+    let manifest = ShutdownManifest::load(&ShutdownManifest::path_for(&socket)).unwrap().unwrap();
+    let surviving = manifest.surviving();
+    assert_eq!(surviving.len(), 1);
+    let (agent, pid) = (&surviving[0].info, surviving[0].pid.unwrap() as i32);
+    // The agent keeps running, and what it prints now still reaches its log
+    let log = rembrandt::daemon::logs::log_path(repo.path(), "fake-detach", &agent.id);
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !std::fs::read_to_string(&log).unwrap_or_default().contains("after the daemon") {
+        assert!(Instant::now() < deadline, "detached agent stopped printing");
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    assert_eq!(unsafe { libc::kill(pid, 0) }, 0, "detached agent was hung up");
+    unsafe {
+        libc::kill(pid, libc::SIGKILL);
+    }
+}
+
+#[test]
+fn batch_nudge_stop_and_steer_reach_every_agent() {
+    let repo = init_repo();
+    // The CLI finds the daemon at the default socket, under XDG_RUNTIME_DIR
+    let runtime_dir = tempfile::tempdir().unwrap();
+    let socket = runtime_dir.path().join("rembrandt.sock");
+    let rembrandt = || {
+        let mut command = std::process::Command::new(REMBRANDT);
+        command.env("XDG_RUNTIME_DIR", runtime_dir.path()).arg("--repo").arg(repo.path());
+        command
+    };
+    let mut daemon = rembrandt()
+        .args(["daemon", "--socket"])
+        .arg(&socket)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut conn = (0..500)
+        .find_map(|_| {
+            std::thread::sleep(Duration::from_millis(10));
+            DaemonConnection::connect(&socket).ok()
+        })
+        .expect("daemon never came up");
+
+    let store = StateStore::open(repo.path()).unwrap();
+    let agents = ["fake-batch-a", "fake-batch-b"];
+    for agent in agents {
+        conn.spawn(
+            agent.to_string(),
+            REMBRANDT.to_string(),
+            vec!["fake-agent".to_string()],
+            vec![("REMBRANDT_FAKE_SCRIPT".to_string(), "read;say:nudged;sleep:30000".to_string())],
+            repo.path().to_path_buf(),
+            None,
+            None,
+            None,
+            RestartPolicy::Never,
+        )
+        .unwrap();
+        let now = chrono::Utc::now();
+        store
+            .upsert_session(&SessionRecord {
+                agent_id: agent.to_string(),
+                runtime_kind: "pty".to_string(),
+                runtime_session_id: None,
+                isolation_mode: rembrandt::isolation::IsolationMode::Branch,
+                branch_name: format!("rembrandt/{}", agent),
+                checkout_path: repo.path().to_path_buf(),
+                task_id: None,
+                status: rembrandt::state::SessionStatus::Active,
+                model: None,
+                created_at: now,
+                updated_at: now,
+                prompt: None,
+                agent_type: None,
+                updated_seq: 0,
+            })
+            .unwrap();
+    }
+
+    let nudge = rembrandt().args(["nudge", "--all"]).output().unwrap();
+    let stdout = String::from_utf8_lossy(&nudge.stdout);
+    assert!(nudge.status.success(), "{}", String::from_utf8_lossy(&nudge.stderr));
+    for agent in agents {
+        assert!(stdout.contains(&format!("Nudged {}", agent)), "{}", stdout);
+    }
+
+    // The daemon sends this repository's steers, so scheduling one is quiet
+    let steer = rembrandt().args(["steer", agents[0], "--at", "+30m", "check in"]).output().unwrap();
+    assert!(steer.status.success(), "{}", String::from_utf8_lossy(&steer.stderr));
+    assert!(!String::from_utf8_lossy(&steer.stderr).contains("Warning"));
+    let ghost = rembrandt().args(["steer", "ghost", "--at", "+30m", "check in"]).output().unwrap();
+    assert!(!ghost.status.success());
+    assert_eq!(store.scheduled_steers(None).unwrap().len(), 1);
+
+    let stop = rembrandt().args(["stop", "--all", "--grace", "1"]).output().unwrap();
+    assert!(stop.status.success(), "{}", String::from_utf8_lossy(&stop.stderr));
+    for agent in agents {
+        let sessions = conn.list_by_agent(agent, None).unwrap();
+        assert!(sessions.iter().all(|s| s.status != SessionStatus::Running), "{} still runs", agent);
+        let record = store.get_session(agent).unwrap().unwrap();
+        assert_eq!(record.status, rembrandt::state::SessionStatus::Stopped);
+    }
+
+    conn.request(&DaemonCommand::Shutdown).unwrap();
+    assert!(daemon.wait().unwrap().success());
+}
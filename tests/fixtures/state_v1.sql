@@ -0,0 +1,59 @@
+-- state.db as written by the first v2 release (schema version 1).
+CREATE TABLE schema_migrations (
+  version INTEGER PRIMARY KEY,
+  applied_at TEXT NOT NULL
+);
+
+CREATE TABLE sessions (
+  agent_id TEXT PRIMARY KEY,
+  runtime_kind TEXT NOT NULL,
+  runtime_session_id TEXT,
+  isolation_mode TEXT NOT NULL,
+  branch_name TEXT NOT NULL,
+  checkout_path TEXT NOT NULL,
+  task_id TEXT,
+  status TEXT NOT NULL,
+  model TEXT,
+  created_at TEXT NOT NULL,
+  updated_at TEXT NOT NULL
+);
+
+CREATE TABLE file_claims (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  agent_id TEXT NOT NULL,
+  path TEXT NOT NULL,
+  created_at TEXT NOT NULL
+);
+
+CREATE TABLE heartbeats (
+  agent_id TEXT PRIMARY KEY,
+  last_seen_at TEXT NOT NULL,
+  detail TEXT
+);
+
+CREATE TABLE csi_runs (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  started_at TEXT NOT NULL,
+  completed_at TEXT,
+  status TEXT NOT NULL,
+  summary TEXT
+);
+
+CREATE TABLE csi_events (
+  id INTEGER PRIMARY KEY AUTOINCREMENT,
+  csi_run_id INTEGER,
+  agent_id TEXT,
+  kind TEXT NOT NULL,
+  message TEXT NOT NULL,
+  created_at TEXT NOT NULL
+);
+
+INSERT INTO schema_migrations VALUES (1, '2026-01-05T10:00:00+00:00');
+
+INSERT INTO sessions VALUES (
+  'claude-3f2a', 'pi', 'stub-claude-3f2a', 'branch', 'rembrandt/claude-3f2a',
+  '/work/app', 'bd-42', 'active', 'sonnet',
+  '2026-01-05T10:00:00+00:00', '2026-01-05T10:05:00+00:00'
+);
+
+INSERT INTO heartbeats VALUES ('claude-3f2a', '2026-01-05T10:05:00+00:00', 'spawned');
@@ -0,0 +1,61 @@
+//! Upgrading existing `state.db` files through the migration framework.
+
+use rembrandt::state::migrations::latest_version;
+use rembrandt::state::{SessionStatus, StateStore};
+use rusqlite::Connection;
+use std::path::Path;
+
+/// Write the v1 fixture to `<repo>/.rembrandt/state.db`.
+fn install_v1_fixture(repo: &Path) {
+    let dir = repo.join(".rembrandt");
+    std::fs::create_dir_all(&dir).unwrap();
+    let conn = Connection::open(dir.join("state.db")).unwrap();
+    conn.execute_batch(include_str!("fixtures/state_v1.sql")).unwrap();
+}
+
+#[test]
+fn v1_database_is_migrated_and_keeps_its_data() {
+    let repo = tempfile::tempdir().unwrap();
+    install_v1_fixture(repo.path());
+
+    let store = StateStore::open(repo.path()).unwrap();
+    assert_eq!(store.schema_version().unwrap(), latest_version());
+
+    let session = store.get_session("claude-3f2a").unwrap().unwrap();
+    assert_eq!(session.status, SessionStatus::Active);
+    assert_eq!(session.task_id.as_deref(), Some("bd-42"));
+    let heartbeat = store.get_heartbeat("claude-3f2a").unwrap().unwrap();
+    assert_eq!(heartbeat.detail.as_deref(), Some("spawned"));
+
+    // Reopening applies nothing further
+    drop(store);
+    let conn = Connection::open(repo.path().join(".rembrandt/state.db")).unwrap();
+    let applied: i64 = conn
+        .query_row("SELECT COUNT(*) FROM schema_migrations", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(applied, latest_version());
+    let index: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'index' AND name = 'idx_sessions_status'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(index, 1);
+}
+
+#[test]
+fn database_from_a_newer_release_is_refused() {
+    let repo = tempfile::tempdir().unwrap();
+    install_v1_fixture(repo.path());
+    let conn = Connection::open(repo.path().join(".rembrandt/state.db")).unwrap();
+    conn.execute(
+        "INSERT INTO schema_migrations VALUES (?1, '2030-01-01T00:00:00+00:00')",
+        [latest_version() + 1],
+    )
+    .unwrap();
+    drop(conn);
+
+    let err = StateStore::open(repo.path()).err().expect("newer schema must be refused");
+    assert!(err.to_string().contains("upgrade rembrandt"));
+}